@@ -1,3 +1,8 @@
 fn main() {
+    // ScreenCaptureKit is missing before macOS 12.3; link it weakly so the
+    // app still launches there and falls back to a virtual audio device
+    if std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("macos") {
+        println!("cargo:rustc-link-arg=-Wl,-weak_framework,ScreenCaptureKit");
+    }
     tauri_build::build()
 }
@@ -0,0 +1,154 @@
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::library::RecordingInfo;
+
+/// A library location kept as a read-only archive, e.g. a finished season
+/// offloaded to an external disk.
+///
+/// Its recordings are indexed when it is added, so they stay searchable while
+/// the disk is unplugged. DiscRec never deletes or rewrites files inside it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Archive {
+    pub path: String,
+    pub label: String,
+    pub indexed_at: String,
+    pub recordings: Vec<RecordingInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveSummary {
+    pub path: String,
+    pub label: String,
+    pub indexed_at: String,
+    pub recordings: usize,
+    pub size: u64,
+    /// Recordings not in DiscRec's own 48 kHz layout, which are normalized
+    /// when imported into the library.
+    pub nonstandard: usize,
+    /// Whether the archive's folder is currently reachable.
+    pub online: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchivedRecording {
+    pub archive: String,
+    pub online: bool,
+    #[serde(flatten)]
+    pub recording: RecordingInfo,
+}
+
+impl Archive {
+    /// Index every recording in `path` and the folders below it.
+    pub fn index(path: &Path, label: &str) -> Result<Self> {
+        let mut recordings =
+            crate::library::scan(path, true).context("Failed to read archive folder")?;
+        recordings.sort_by(|a, b| b.modified.cmp(&a.modified));
+        Ok(Self {
+            path: path.to_string_lossy().to_string(),
+            label: label.to_string(),
+            indexed_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            recordings,
+        })
+    }
+
+    pub fn summary(&self) -> ArchiveSummary {
+        ArchiveSummary {
+            path: self.path.clone(),
+            label: self.label.clone(),
+            indexed_at: self.indexed_at.clone(),
+            recordings: self.recordings.len(),
+            size: self.recordings.iter().map(|r| r.size).sum(),
+            nonstandard: self
+                .recordings
+                .iter()
+                .filter(|r| r.audio.is_some_and(|a| !a.is_standard()))
+                .count(),
+            online: Path::new(&self.path).is_dir(),
+        }
+    }
+
+    /// Whether `path` lies inside this archive. Works while it is offline.
+    pub fn contains(&self, path: &Path) -> bool {
+        let root = Path::new(&self.path);
+        path.starts_with(root)
+            || path
+                .canonicalize()
+                .is_ok_and(|canonical| canonical.starts_with(root))
+    }
+}
+
+pub struct ArchiveState(pub Mutex<Vec<Archive>>);
+
+impl ArchiveState {
+    pub fn load() -> Self {
+        let archives = std::fs::read_to_string(Self::index_path())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self(Mutex::new(archives))
+    }
+
+    fn index_path() -> PathBuf {
+        crate::data_dir::config_dir().join("archives.json")
+    }
+
+    pub fn save(&self) {
+        let path = Self::index_path();
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let archives = self.0.lock();
+        let _ = std::fs::write(
+            path,
+            serde_json::to_string_pretty(&*archives).unwrap_or_default(),
+        );
+    }
+
+    /// Refuse to modify anything stored in an archive.
+    pub fn ensure_writable(&self, path: &Path) -> Result<()> {
+        if let Some(archive) = self.0.lock().iter().find(|a| a.contains(path)) {
+            anyhow::bail!(
+                "{} is in the read-only archive '{}'",
+                path.display(),
+                archive.label
+            );
+        }
+        Ok(())
+    }
+
+    /// Label of an archive `dir` lies in or contains, if any.
+    pub fn overlaps(&self, dir: &Path) -> Option<String> {
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        self.0
+            .lock()
+            .iter()
+            .find(|a| a.contains(&dir) || Path::new(&a.path).starts_with(&dir))
+            .map(|a| a.label.clone())
+    }
+
+    /// Archived recordings whose file name contains `query` (case-insensitive), newest first.
+    pub fn search(&self, query: &str) -> Vec<ArchivedRecording> {
+        let query = query.to_lowercase();
+        let archives = self.0.lock();
+        let mut found: Vec<ArchivedRecording> = archives
+            .iter()
+            .flat_map(|archive| {
+                let online = Path::new(&archive.path).is_dir();
+                archive
+                    .recordings
+                    .iter()
+                    .filter(|r| r.filename.to_lowercase().contains(&query))
+                    .map(move |r| ArchivedRecording {
+                        archive: archive.label.clone(),
+                        online,
+                        recording: r.clone(),
+                    })
+            })
+            .collect();
+        found.sort_by(|a, b| b.recording.modified.cmp(&a.recording.modified));
+        found
+    }
+}
@@ -1,20 +1,110 @@
 use anyhow::Result;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::mpsc;
 use std::sync::Arc;
 use std::thread;
-
-use super::encoder::{create_encoder, AudioFormat};
+use std::time::Duration;
+
+use super::devices::{
+    find_capture_device, resolve as resolve_device, DeviceFallback, DevicePreference,
+};
+use super::encoder::{
+    create_encoder, create_segmented_encoder, part_name, AudioEncoder, AudioFormat,
+};
+use super::levels::{hold_peak, LevelMeter, LevelReport, LevelStats};
+use super::mixer::{Mixer, MIX_CHANNELS, MIX_SAMPLE_RATE};
+use super::pcm;
+use super::source::CaptureSource;
+use crate::events::{Recorder, RecordingEvent, StatusHook, StopReason, LEVEL_INTERVAL};
+use crate::session::RecordingSession;
 
 enum StreamMsg {
     Stop,
 }
 
+/// Opens the capture's output once the device's channels and sample rate are known.
+type SinkFactory = Box<dyn FnOnce(u16, u32) -> Result<Box<dyn AudioEncoder>> + Send>;
+
+/// One capture thread writing a single source to its own file.
+struct CaptureTrack {
+    source: CaptureSource,
+    stop_tx: mpsc::Sender<StreamMsg>,
+    thread_handle: thread::JoinHandle<Result<Option<String>>>,
+}
+
+/// One output file of a local recording, opened once the source's format is known.
+struct TrackFile {
+    session: Arc<RecordingSession>,
+    filename: String,
+    format: AudioFormat,
+    silence_trim: bool,
+    segment_minutes: Option<u32>,
+}
+
+impl TrackFile {
+    /// Unsegmented files are registered with the session right away, so they
+    /// are listed in source order.
+    fn new(
+        session: &Arc<RecordingSession>,
+        filename: String,
+        format: AudioFormat,
+        silence_trim: bool,
+        segment_minutes: Option<u32>,
+    ) -> Result<Self> {
+        // Parts are registered as they're opened
+        if segment_minutes.is_none() {
+            session.work_path(&filename)?;
+        }
+        Ok(Self {
+            session: Arc::clone(session),
+            filename,
+            format,
+            silence_trim,
+            segment_minutes,
+        })
+    }
+
+    fn open(self, channels: u16, sample_rate: u32) -> Result<Box<dyn AudioEncoder>> {
+        let Some(minutes) = self.segment_minutes else {
+            let path = self.session.work_dir().join(&self.filename);
+            return create_encoder(
+                &path.to_string_lossy(),
+                channels,
+                sample_rate,
+                self.format,
+                self.silence_trim,
+            );
+        };
+        let (session, filename) = (self.session, self.filename);
+        create_segmented_encoder(
+            channels,
+            sample_rate,
+            self.format,
+            self.silence_trim,
+            minutes,
+            move |part| {
+                let path = session.work_path(&part_name(&filename, part))?;
+                Ok(path.to_string_lossy().to_string())
+            },
+        )
+    }
+}
+
 pub struct AudioCapture {
-    stop_tx: Option<mpsc::Sender<StreamMsg>>,
-    thread_handle: Option<thread::JoinHandle<Result<Option<String>>>>,
+    tracks: Vec<CaptureTrack>,
+    /// Set when the tracks are mixed into one file instead of one file each.
+    mixer: Option<Mixer>,
+    session: Option<Arc<RecordingSession>>,
     is_recording: Arc<AtomicBool>,
     peak_level_bits: Arc<AtomicU32>,
+    /// How Discord-only capture was set up, once it has been.
+    routing: Arc<Mutex<Option<RoutingReport>>>,
+    status_hook: Option<StatusHook>,
+    /// Reports levels while recording and notices when the tracks stop on
+    /// their own; sending on the channel ends it.
+    monitor: Option<(mpsc::Sender<()>, thread::JoinHandle<()>)>,
 }
 
 // SAFETY: The cpal::Stream lives entirely on the dedicated thread
@@ -25,10 +115,25 @@ unsafe impl Sync for AudioCapture {}
 impl AudioCapture {
     pub fn new() -> Self {
         Self {
-            stop_tx: None,
-            thread_handle: None,
+            tracks: Vec::new(),
+            mixer: None,
+            session: None,
             is_recording: Arc::new(AtomicBool::new(false)),
             peak_level_bits: Arc::new(AtomicU32::new(0)),
+            routing: Arc::new(Mutex::new(None)),
+            status_hook: None,
+            monitor: None,
+        }
+    }
+
+    /// Report level, start, stop and failures to `hook` from now on.
+    pub fn set_status_hook(&mut self, hook: StatusHook) {
+        self.status_hook = Some(hook);
+    }
+
+    fn emit(&self, event: RecordingEvent) {
+        if let Some(hook) = &self.status_hook {
+            hook(Recorder::Local, event);
         }
     }
 
@@ -40,79 +145,385 @@ impl AudioCapture {
         f32::from_bits(self.peak_level_bits.load(Ordering::Relaxed))
     }
 
+    /// How routing Discord's audio went for the current recording, when it
+    /// captures Discord through PulseAudio/PipeWire.
+    pub fn routing_report(&self) -> Option<RoutingReport> {
+        self.routing.lock().clone()
+    }
+
+    /// Start capturing every local source in `sources`.
+    ///
+    /// The first source is written to `output_path`'s file name; any further
+    /// sources get their tag appended (e.g. `discord-<ts>-mic.wav`). With
+    /// `mix`, all sources are mixed into the single `output_path` file instead.
+    /// `capture_device` is the device for the system source, with
+    /// `device_fallback` deciding what happens when it isn't connected;
+    /// without it one is picked automatically. The device actually used is
+    /// kept in the session manifest. With `segment_minutes`, every file is
+    /// split into `-partNN` files of that length. `routed_apps` are recorded
+    /// along with Discord where per-app routing is used.
+    #[allow(clippy::too_many_arguments)]
     pub fn start(
         &mut self,
         output_path: &str,
+        sources: &[CaptureSource],
         format: AudioFormat,
         silence_trim: bool,
         max_duration_secs: Option<u32>,
+        segment_minutes: Option<u32>,
+        mix: bool,
+        capture_device: Option<&DevicePreference>,
+        device_fallback: DeviceFallback,
+        routed_apps: &[RoutedApp],
     ) -> Result<()> {
         if self.is_recording() {
             anyhow::bail!("Already recording");
         }
+        if sources.is_empty() {
+            anyhow::bail!("No audio source selected");
+        }
+        if let Some(source) = sources.iter().find(|s| !s.is_local()) {
+            anyhow::bail!("{} is not a local capture source", source);
+        }
 
-        let (stop_tx, stop_rx) = mpsc::channel();
-        let is_recording = Arc::clone(&self.is_recording);
-        let peak_level_bits = Arc::clone(&self.peak_level_bits);
-        let path = output_path.to_string();
-
-        #[cfg(target_os = "windows")]
-        let handle = {
-            thread::spawn(move || -> Result<Option<String>> {
-                capture_windows(
-                    &path,
-                    format,
-                    silence_trim,
-                    max_duration_secs,
-                    &is_recording,
-                    &peak_level_bits,
-                    &stop_rx,
-                )
-            })
+        let device = match capture_device {
+            Some(preference) if sources.iter().any(|s| s.uses_capture_device()) => {
+                Some(resolve_device(preference, device_fallback)?)
+            }
+            _ => None,
+        };
+        let capture_device = device.as_ref().and_then(|d| d.used.clone());
+        *self.routing.lock() = None;
+
+        // Write into a private session directory; moved to output_path on stop
+        let output = std::path::Path::new(output_path);
+        let library_dir = output.parent().unwrap_or(std::path::Path::new("."));
+        let stem = output
+            .file_stem()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let session = Arc::new(RecordingSession::create(library_dir, "local")?);
+        crate::latency::reset_capture();
+        if let Some(device) = &device {
+            session.set_capture_device(device.clone())?;
+        }
+        let segment_minutes = segment_minutes.filter(|&m| m > 0);
+
+        let mixer = if mix && sources.len() > 1 {
+            let filename = format!("{}.{}", stem, format.extension());
+            let track = TrackFile::new(&session, filename, format, silence_trim, segment_minutes)?;
+            Some(Mixer::new(
+                track.open(MIX_CHANNELS, MIX_SAMPLE_RATE)?,
+                sources.len(),
+            ))
+        } else {
+            None
         };
 
-        #[cfg(not(target_os = "windows"))]
-        let handle = {
-            thread::spawn(move || -> Result<Option<String>> {
-                capture_cpal(
-                    &path,
-                    format,
-                    silence_trim,
+        self.is_recording.store(true, Ordering::Relaxed);
+        for (i, &source) in sources.iter().enumerate() {
+            let (stop_tx, stop_rx) = mpsc::channel();
+            let is_recording = Arc::clone(&self.is_recording);
+            let peak_level_bits = Arc::clone(&self.peak_level_bits);
+            let capture_device = capture_device.clone();
+            let routed_apps = routed_apps.to_vec();
+            let status_hook = self.status_hook.clone();
+            let routing = Arc::clone(&self.routing);
+            let track_session = Arc::clone(&session);
+
+            let open_sink: SinkFactory = match &mixer {
+                Some(mixer) => {
+                    let mixer = mixer.clone();
+                    Box::new(move |channels, sample_rate| {
+                        Ok(Box::new(mixer.input(i, channels, sample_rate))
+                            as Box<dyn AudioEncoder>)
+                    })
+                }
+                None => {
+                    let filename = if i == 0 {
+                        format!("{}.{}", stem, format.extension())
+                    } else {
+                        format!("{}-{}.{}", stem, source.file_tag(), format.extension())
+                    };
+                    let track =
+                        TrackFile::new(&session, filename, format, silence_trim, segment_minutes)?;
+                    Box::new(move |channels, sample_rate| track.open(channels, sample_rate))
+                }
+            };
+            let thread_handle = thread::spawn(move || -> Result<Option<String>> {
+                let report_routing = |report: RoutingReport| {
+                    if let Err(e) = track_session.set_routing(report.clone()) {
+                        log::warn!("Failed to save the routing report: {}", e);
+                    }
+                    *routing.lock() = Some(report);
+                };
+                let result = capture_source(
+                    source,
+                    open_sink,
                     max_duration_secs,
+                    capture_device.as_deref(),
+                    &routed_apps,
+                    &report_routing,
                     &is_recording,
                     &peak_level_bits,
                     &stop_rx,
-                )
-            })
-        };
+                );
+                // Report right away rather than when the recording is stopped
+                if let (Err(e), Some(hook)) = (&result, &status_hook) {
+                    let message = format!("Capture of {} failed: {}", source, e);
+                    hook(Recorder::Local, RecordingEvent::Error { message });
+                }
+                result
+            });
+            self.tracks.push(CaptureTrack {
+                source,
+                stop_tx,
+                thread_handle,
+            });
+        }
+        self.mixer = mixer;
+        self.session = Some(session);
 
-        self.is_recording.store(true, Ordering::Relaxed);
-        self.stop_tx = Some(stop_tx);
-        self.thread_handle = Some(handle);
+        if let Some(hook) = self.status_hook.clone() {
+            self.monitor = Some(self.spawn_monitor(hook));
+        }
+        self.emit(RecordingEvent::Started {
+            path: Some(output_path.to_string()),
+            device,
+        });
 
         Ok(())
     }
 
-    pub fn stop(&mut self) -> Result<Option<String>> {
-        self.is_recording.store(false, Ordering::Relaxed);
+    fn spawn_monitor(&self, hook: StatusHook) -> (mpsc::Sender<()>, thread::JoinHandle<()>) {
+        let (tx, rx) = mpsc::channel();
+        let is_recording = Arc::clone(&self.is_recording);
+        let peak_level_bits = Arc::clone(&self.peak_level_bits);
+        let handle = thread::spawn(move || loop {
+            match rx.recv_timeout(LEVEL_INTERVAL) {
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                _ => return,
+            }
+            // Only the max duration limit clears the flag without stop()
+            if !is_recording.load(Ordering::Relaxed) {
+                let event = RecordingEvent::Stopped {
+                    reason: StopReason::MaxDuration,
+                    paths: Vec::new(),
+                };
+                hook(Recorder::Local, event);
+                return;
+            }
+            let peak = f32::from_bits(peak_level_bits.load(Ordering::Relaxed));
+            hook(Recorder::Local, RecordingEvent::Level { peak });
+        });
+        (tx, handle)
+    }
+
+    /// Stop all tracks and move their files into the library.
+    ///
+    /// Returns the saved paths, the first source's file first.
+    pub fn stop(&mut self) -> Result<Vec<String>> {
+        let (was_recording, failure) = self.end_tracks();
+        crate::latency::log_report();
+
+        // Move the finalized files out of the session directory
+        let Some(session) = self.session.take() else {
+            return failure.map_or(Ok(Vec::new()), Err);
+        };
+        let result = match failure {
+            None => session.commit(),
+            Some(e) => {
+                session.mark_failed(&e.to_string());
+                Err(e)
+            }
+        };
+        match &result {
+            // After a max duration stop the UI already knows
+            Ok(paths) if was_recording => self.emit(RecordingEvent::Stopped {
+                reason: StopReason::Requested,
+                paths: paths.clone(),
+            }),
+            Ok(_) => {}
+            Err(e) => self.emit(RecordingEvent::Error {
+                message: e.to_string(),
+            }),
+        }
+        result
+    }
+
+    /// Stop all tracks and delete what they recorded, for false starts.
+    ///
+    /// Returns whether there was a recording to abort.
+    pub fn abort(&mut self) -> bool {
+        let (_, failure) = self.end_tracks();
+        if let Some(e) = failure {
+            log::warn!("Aborted recording didn't finish cleanly: {}", e);
+        }
+        let Some(session) = self.session.take() else {
+            return false;
+        };
+        session.discard();
+        self.emit(RecordingEvent::Stopped {
+            reason: StopReason::Aborted,
+            paths: Vec::new(),
+        });
+        true
+    }
+
+    /// Signal and join every track and finalize the mix. Returns whether
+    /// the recording was still running and the first failure.
+    fn end_tracks(&mut self) -> (bool, Option<anyhow::Error>) {
+        if let Some((tx, handle)) = self.monitor.take() {
+            let _ = tx.send(());
+            let _ = handle.join();
+        }
+        let was_recording = self.is_recording.swap(false, Ordering::Relaxed);
         self.peak_level_bits
             .store(0f32.to_bits(), Ordering::Relaxed);
 
-        // Signal the recording thread to stop
-        if let Some(tx) = self.stop_tx.take() {
-            let _ = tx.send(StreamMsg::Stop);
+        // Signal every recording thread before joining any of them
+        for track in &self.tracks {
+            let _ = track.stop_tx.send(StreamMsg::Stop);
         }
 
-        // Wait for thread to finish and get the file path
-        if let Some(handle) = self.thread_handle.take() {
-            match handle.join() {
-                Ok(result) => return result,
-                Err(_) => anyhow::bail!("Recording thread panicked"),
+        // Wait for the threads to finish; the first failure fails the session
+        let mut failure = None;
+        for track in self.tracks.drain(..) {
+            let result = match track.thread_handle.join() {
+                Ok(result) => result,
+                Err(_) => Err(anyhow::anyhow!("Recording thread panicked")),
+            };
+            if let Err(e) = result {
+                log::error!("Capture of {} failed: {}", track.source, e);
+                failure.get_or_insert(e);
             }
         }
+        if let Some(mixer) = self.mixer.take() {
+            if let Err(e) = mixer.finalize() {
+                log::error!("Mixing failed: {}", e);
+                failure.get_or_insert(e);
+            }
+        }
+        (was_recording, failure)
+    }
+}
+
+/// Open each local source for `duration` and measure its levels.
+///
+/// Nothing is written to disk; the capture paths feed a [`LevelMeter`] instead
+/// of an encoder.
+pub fn preview_levels(
+    sources: &[CaptureSource],
+    capture_device: Option<&str>,
+    routed_apps: &[RoutedApp],
+    duration: Duration,
+) -> Result<Vec<LevelReport>> {
+    if let Some(source) = sources.iter().find(|s| !s.is_local()) {
+        anyhow::bail!("Level preview is not available for {}", source);
+    }
+
+    let is_recording = Arc::new(AtomicBool::new(true));
+    let peak_level_bits = Arc::new(AtomicU32::new(0));
+    let mut previews = Vec::new();
+    for &source in sources {
+        let stats = Arc::new(Mutex::new(LevelStats::default()));
+        let meter_stats = Arc::clone(&stats);
+        let open_sink: SinkFactory = Box::new(move |_, _| {
+            Ok(Box::new(LevelMeter::new(meter_stats)) as Box<dyn AudioEncoder>)
+        });
+
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let is_recording = Arc::clone(&is_recording);
+        let peak_level_bits = Arc::clone(&peak_level_bits);
+        let capture_device = capture_device.map(str::to_string);
+        let routed_apps = routed_apps.to_vec();
+        let handle = thread::spawn(move || -> Result<Option<String>> {
+            capture_source(
+                source,
+                open_sink,
+                None,
+                capture_device.as_deref(),
+                &routed_apps,
+                &|_| {},
+                &is_recording,
+                &peak_level_bits,
+                &stop_rx,
+            )
+        });
+        previews.push((source, stop_tx, handle, stats));
+    }
 
-        Ok(None)
+    thread::sleep(duration);
+    for (_, stop_tx, _, _) in &previews {
+        let _ = stop_tx.send(StreamMsg::Stop);
     }
+
+    let mut reports = Vec::new();
+    for (source, _, handle, stats) in previews {
+        match handle.join() {
+            Ok(Ok(_)) => reports.push(LevelReport::from_stats(source, &stats.lock())),
+            Ok(Err(e)) => anyhow::bail!("{}: {}", source, e),
+            Err(_) => anyhow::bail!("{}: preview thread panicked", source),
+        }
+    }
+    Ok(reports)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn capture_source(
+    source: CaptureSource,
+    open_sink: SinkFactory,
+    max_duration_secs: Option<u32>,
+    capture_device: Option<&str>,
+    routed_apps: &[RoutedApp],
+    report_routing: &dyn Fn(RoutingReport),
+    is_recording: &Arc<AtomicBool>,
+    peak_level_bits: &Arc<AtomicU32>,
+    stop_rx: &mpsc::Receiver<StreamMsg>,
+) -> Result<Option<String>> {
+    let input = match source {
+        #[cfg(target_os = "windows")]
+        CaptureSource::DiscordApp => {
+            return capture_windows(
+                open_sink,
+                max_duration_secs,
+                is_recording,
+                peak_level_bits,
+                stop_rx,
+            );
+        }
+        #[cfg(target_os = "macos")]
+        CaptureSource::DiscordApp if screen_capture::supported() => {
+            return capture_macos(
+                open_sink,
+                max_duration_secs,
+                is_recording,
+                peak_level_bits,
+                stop_rx,
+            );
+        }
+        #[cfg(target_os = "macos")]
+        CaptureSource::DiscordApp => {
+            log::warn!("Per-app capture needs macOS 13 or later; recording system audio instead");
+            CpalInput::System
+        }
+        #[cfg(target_os = "linux")]
+        CaptureSource::DiscordApp => CpalInput::DiscordApp,
+        CaptureSource::System => CpalInput::System,
+        CaptureSource::Mic => CpalInput::Microphone,
+        other => anyhow::bail!("Audio source '{}' is not supported on this platform", other),
+    };
+    capture_cpal(
+        open_sink,
+        input,
+        max_duration_secs,
+        capture_device,
+        routed_apps,
+        report_routing,
+        is_recording,
+        peak_level_bits,
+        stop_rx,
+    )
 }
 
 // ---------------------------------------------------------------------------
@@ -157,9 +568,7 @@ fn find_discord_pid() -> Result<u32> {
 
 #[cfg(target_os = "windows")]
 fn capture_windows(
-    path: &str,
-    format: AudioFormat,
-    silence_trim: bool,
+    open_sink: SinkFactory,
     max_duration_secs: Option<u32>,
     is_recording: &Arc<AtomicBool>,
     peak_level_bits: &Arc<AtomicU32>,
@@ -182,6 +591,7 @@ fn capture_windows(
     }
 
     let sample_rate = 48000u32;
+    // Output layout written to disk; the capture format may carry more channels
     let channels = 2u16;
     let bits_per_sample = 32u32;
 
@@ -193,18 +603,40 @@ fn capture_windows(
         channels as usize,
         None,
     );
-    let blockalign = desired_format.get_blockalign();
 
     let mut audio_client = AudioClient::new_application_loopback_client(discord_pid, true)
         .map_err(|e| anyhow::anyhow!("Failed to create loopback client for Discord: {:?}", e))?;
 
+    // Process loopback clients usually don't report a mix format; when one is
+    // available (e.g. 5.1 spatial sound) capture in it and downmix ourselves.
+    let capture_format = audio_client
+        .get_mixformat()
+        .ok()
+        .filter(|f| f.get_samplespersec() == sample_rate)
+        .unwrap_or(desired_format);
+    let frame_layout = FrameLayout::from_format(&capture_format)?;
+    let mixer = ChannelMixer::new(
+        capture_format.get_dwchannelmask(),
+        frame_layout.channels,
+        channels as usize,
+    );
+    log::info!(
+        "WASAPI capture format: {} ch, {} bits ({:?}), block align {}",
+        frame_layout.channels,
+        frame_layout.bytes_per_sample * 8,
+        frame_layout.sample_type,
+        frame_layout.block_align
+    );
+
     let mode = StreamMode::EventsShared {
         autoconvert: true,
         buffer_duration_hns: 0,
     };
     audio_client
-        .initialize_client(&desired_format, &Direction::Capture, &mode)
-        .map_err(|e| anyhow::anyhow!("Failed to init WASAPI client: {:?}", e))?;
+        .initialize_client(&capture_format, &Direction::Capture, &mode)
+        .map_err(|e| {
+            explain_exclusive_mode(anyhow::anyhow!("Failed to init WASAPI client: {:?}", e))
+        })?;
 
     let h_event = audio_client
         .set_get_eventhandle()
@@ -214,19 +646,26 @@ fn capture_windows(
         .get_audiocaptureclient()
         .map_err(|e| anyhow::anyhow!("Failed to get capture client: {:?}", e))?;
 
-    let mut encoder = create_encoder(path, channels, sample_rate, format, silence_trim)?;
+    let mut encoder = open_sink(channels, sample_rate)?;
 
     audio_client
         .start_stream()
-        .map_err(|e| anyhow::anyhow!("Failed to start stream: {:?}", e))?;
+        .map_err(|e| explain_exclusive_mode(anyhow::anyhow!("Failed to start stream: {:?}", e)))?;
 
-    log::info!("WASAPI per-process capture started: {}", path);
+    log::info!("WASAPI per-process capture started: {}", encoder.path());
 
     let mut sample_queue: VecDeque<u8> = VecDeque::new();
-    let bytes_per_frame = blockalign as usize;
+    let bytes_per_frame = frame_layout.block_align;
+    let mut frame_bytes = vec![0u8; bytes_per_frame];
+    let mut in_frame = vec![0f32; frame_layout.channels];
+    let mut out_frame = vec![0f32; channels as usize];
+    let mut out = Vec::new();
     let start_time = Instant::now();
+    // Loopback delivers nothing while Discord is quiet, so a gap alone is no error
+    let mut last_data = Instant::now();
+    let mut gap_checked = false;
 
-    loop {
+    'capture: loop {
         // Check for stop signal (non-blocking)
         if stop_rx.try_recv().is_ok() || !is_recording.load(Ordering::Relaxed) {
             break;
@@ -262,35 +701,41 @@ fn capture_windows(
             {
                 break;
             }
+            last_data = Instant::now();
+            gap_checked = false;
         }
 
-        // Process buffered samples as f32
-        while sample_queue.len() >= 4 {
-            let b = [
-                sample_queue.pop_front().unwrap(),
-                sample_queue.pop_front().unwrap(),
-                sample_queue.pop_front().unwrap(),
-                sample_queue.pop_front().unwrap(),
-            ];
-            let sample = f32::from_le_bytes(b);
-
-            // Update peak level (per-sample for responsiveness)
-            let current_peak = f32::from_bits(peak_level_bits.load(Ordering::Relaxed));
-            let abs_sample = sample.abs();
-            if abs_sample > current_peak {
-                peak_level_bits.store(abs_sample.to_bits(), Ordering::Relaxed);
+        // A long gap may mean another app took the output device exclusively
+        if !gap_checked && last_data.elapsed() >= NO_DATA_CHECK_AFTER {
+            gap_checked = true;
+            let held = exclusive_mode_endpoints();
+            if !held.is_empty() {
+                let _ = audio_client.stop_stream();
+                return Err(exclusive_mode_error(&held));
             }
+            log::info!("No audio from Discord for {:?}", NO_DATA_CHECK_AFTER);
+        }
 
-            if let Err(e) = encoder.write_sample(sample) {
-                log::error!("Failed to write sample: {}", e);
-                break;
+        // Process whole frames only; a partial frame waits for the next packet
+        while sample_queue.len() >= bytes_per_frame {
+            for b in frame_bytes.iter_mut() {
+                *b = sample_queue.pop_front().unwrap();
             }
+            frame_layout.decode(&frame_bytes, &mut in_frame);
+            mixer.mix(&in_frame, &mut out_frame);
+            out.extend_from_slice(&out_frame);
         }
 
-        // Decay peak level slightly each loop iteration
-        let current = f32::from_bits(peak_level_bits.load(Ordering::Relaxed));
-        if current > 0.001 {
-            peak_level_bits.store((current * 0.95).to_bits(), Ordering::Relaxed);
+        hold_peak(
+            peak_level_bits,
+            out.iter().fold(0.0f32, |max, s| max.max(s.abs())),
+        );
+        if !out.is_empty() {
+            if let Err(e) = encoder.write_samples(&out) {
+                log::error!("Failed to write samples: {}", e);
+                break 'capture;
+            }
+            out.clear();
         }
     }
 
@@ -302,43 +747,735 @@ fn capture_windows(
     Ok(Some(p))
 }
 
+/// Silence after which the Windows capture checks for an exclusive-mode conflict.
+#[cfg(target_os = "windows")]
+const NO_DATA_CHECK_AFTER: Duration = Duration::from_secs(5);
+
+/// `AUDCLNT_E_DEVICE_IN_USE`: another application opened the endpoint in exclusive mode.
+#[cfg(target_os = "windows")]
+const AUDCLNT_E_DEVICE_IN_USE: i32 = 0x8889000Au32 as i32;
+
+/// Names of the active output devices another application holds exclusively.
+///
+/// Opening a shared-mode client on such a device fails with
+/// `AUDCLNT_E_DEVICE_IN_USE`; the client is never started.
+#[cfg(target_os = "windows")]
+fn exclusive_mode_endpoints() -> Vec<String> {
+    use wasapi::*;
+
+    // Already initialized on capture threads; harmless otherwise
+    let _ = initialize_mta();
+    let Ok(collection) =
+        DeviceEnumerator::new().and_then(|e| e.get_device_collection(&Direction::Render))
+    else {
+        return Vec::new();
+    };
+
+    let mut held = Vec::new();
+    for device in collection.into_iter().flatten() {
+        let Ok(mut client) = device.get_iaudioclient() else {
+            continue;
+        };
+        let in_use = match client.get_mixformat() {
+            Ok(format) => client.initialize_client(
+                &format,
+                &Direction::Render,
+                &StreamMode::PollingShared {
+                    autoconvert: false,
+                    buffer_duration_hns: 0,
+                },
+            ),
+            Err(e) => Err(e),
+        };
+        if let Err(WasapiError::Windows(e)) = in_use {
+            if e.code().0 == AUDCLNT_E_DEVICE_IN_USE {
+                held.push(device.get_friendlyname().unwrap_or_default());
+            }
+        }
+    }
+    if !held.is_empty() {
+        log::warn!("Output devices held in exclusive mode: {:?}", held);
+    }
+    held
+}
+
+#[cfg(target_os = "windows")]
+fn exclusive_mode_error(endpoints: &[String]) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Another application is using {} in exclusive mode, so no audio can be captured. \
+         Close that application (some games and DAWs do this), or open Sound settings → \
+         the device's Properties → Advanced and turn off \"Allow applications to take \
+         exclusive control of this device\", then record again.",
+        endpoints.join(", ")
+    )
+}
+
+/// Replace a stream setup error with an actionable one when an output
+/// device is held in exclusive mode.
+#[cfg(target_os = "windows")]
+fn explain_exclusive_mode(error: anyhow::Error) -> anyhow::Error {
+    let held = exclusive_mode_endpoints();
+    if held.is_empty() {
+        error
+    } else {
+        exclusive_mode_error(&held)
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn explain_exclusive_mode(error: anyhow::Error) -> anyhow::Error {
+    error
+}
+
+/// Byte layout of one interleaved WASAPI frame in the negotiated capture format.
+#[cfg(target_os = "windows")]
+struct FrameLayout {
+    channels: usize,
+    block_align: usize,
+    bytes_per_sample: usize,
+    sample_type: wasapi::SampleType,
+}
+
+#[cfg(target_os = "windows")]
+impl FrameLayout {
+    fn from_format(format: &wasapi::WaveFormat) -> Result<Self> {
+        let channels = format.get_nchannels() as usize;
+        let block_align = format.get_blockalign() as usize;
+        let bytes_per_sample = format.get_bitspersample() as usize / 8;
+        // Plain WAVEFORMATEX has no subformat; 32-bit shared-mode audio is float
+        let sample_type = format.get_subformat().unwrap_or(if bytes_per_sample == 4 {
+            wasapi::SampleType::Float
+        } else {
+            wasapi::SampleType::Int
+        });
+
+        let supported = match sample_type {
+            wasapi::SampleType::Float => matches!(bytes_per_sample, 4 | 8),
+            wasapi::SampleType::Int => matches!(bytes_per_sample, 2..=4),
+        };
+        if channels == 0 || !supported || block_align < channels * bytes_per_sample {
+            anyhow::bail!(
+                "Unsupported capture format: {} channels, {} bits, block align {}",
+                channels,
+                bytes_per_sample * 8,
+                block_align
+            );
+        }
+
+        Ok(Self {
+            channels,
+            block_align,
+            bytes_per_sample,
+            sample_type,
+        })
+    }
+
+    /// Decode one frame of raw bytes into per-channel f32 samples.
+    fn decode(&self, frame: &[u8], out: &mut [f32]) {
+        for (ch, sample) in out.iter_mut().enumerate() {
+            let b = &frame[ch * self.bytes_per_sample..(ch + 1) * self.bytes_per_sample];
+            *sample = match (&self.sample_type, self.bytes_per_sample) {
+                (wasapi::SampleType::Float, 4) => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+                (wasapi::SampleType::Float, _) => {
+                    f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32
+                }
+                (wasapi::SampleType::Int, 2) => pcm::i16_to_float(i16::from_le_bytes([b[0], b[1]])),
+                (wasapi::SampleType::Int, 3) => {
+                    pcm::to_float(i32::from_le_bytes([0, b[0], b[1], b[2]]), 32)
+                }
+                (wasapi::SampleType::Int, _) => {
+                    pcm::to_float(i32::from_le_bytes([b[0], b[1], b[2], b[3]]), 32)
+                }
+            };
+        }
+    }
+}
+
+/// Mixing matrix from the capture layout to the output layout (mono or stereo).
+#[cfg(target_os = "windows")]
+struct ChannelMixer {
+    /// `gains[out][in]`
+    gains: Vec<Vec<f32>>,
+}
+
+#[cfg(target_os = "windows")]
+impl ChannelMixer {
+    fn new(channel_mask: u32, in_channels: usize, out_channels: usize) -> Self {
+        // Speaker positions in WAVE order; a zero mask means "first N speakers"
+        let mask = if channel_mask == 0 {
+            wasapi::make_simple_channelmask(in_channels)
+        } else {
+            channel_mask
+        };
+        let speakers: Vec<u32> = (0..32)
+            .map(|bit| 1u32 << bit)
+            .filter(|s| mask & s != 0)
+            .chain(std::iter::repeat(0))
+            .take(in_channels)
+            .collect();
+
+        let gains = if in_channels == out_channels {
+            (0..out_channels)
+                .map(|o| (0..in_channels).map(|i| (i == o) as u8 as f32).collect())
+                .collect()
+        } else {
+            let stereo: Vec<(f32, f32)> = speakers.iter().map(|&s| stereo_gains(s)).collect();
+            // Normalize so full-scale input on every channel cannot clip
+            let left_sum: f32 = stereo.iter().map(|g| g.0).sum::<f32>().max(1.0);
+            let right_sum: f32 = stereo.iter().map(|g| g.1).sum::<f32>().max(1.0);
+            let left: Vec<f32> = stereo.iter().map(|g| g.0 / left_sum).collect();
+            let right: Vec<f32> = stereo.iter().map(|g| g.1 / right_sum).collect();
+            if out_channels == 1 {
+                vec![left
+                    .iter()
+                    .zip(&right)
+                    .map(|(l, r)| (l + r) / 2.0)
+                    .collect()]
+            } else {
+                vec![left, right]
+            }
+        };
+
+        Self { gains }
+    }
+
+    fn mix(&self, input: &[f32], output: &mut [f32]) {
+        for (out, row) in output.iter_mut().zip(&self.gains) {
+            *out = row.iter().zip(input).map(|(g, s)| g * s).sum();
+        }
+    }
+}
+
+/// Stereo downmix gains for a WAVE speaker position (ITU-R BS.775 style).
+#[cfg(target_os = "windows")]
+fn stereo_gains(speaker: u32) -> (f32, f32) {
+    const HALF_POWER: f32 = std::f32::consts::FRAC_1_SQRT_2;
+    match speaker {
+        // Front left / right, front left-of-center / right-of-center
+        0x1 | 0x40 => (1.0, 0.0),
+        0x2 | 0x80 => (0.0, 1.0),
+        // Front center
+        0x4 => (HALF_POWER, HALF_POWER),
+        // LFE is dropped, as in the standard downmix
+        0x8 => (0.0, 0.0),
+        // Back left / side left
+        0x10 | 0x200 => (HALF_POWER, 0.0),
+        // Back right / side right
+        0x20 | 0x400 => (0.0, HALF_POWER),
+        // Back center, top and unknown speakers
+        _ => (0.5, 0.5),
+    }
+}
+
 // ---------------------------------------------------------------------------
-// Linux / macOS: cpal-based loopback capture (system audio)
+// macOS: per-app audio capture via ScreenCaptureKit (captures only Discord audio)
 // ---------------------------------------------------------------------------
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(target_os = "macos")]
+fn capture_macos(
+    open_sink: SinkFactory,
+    max_duration_secs: Option<u32>,
+    is_recording: &Arc<AtomicBool>,
+    peak_level_bits: &Arc<AtomicU32>,
+    stop_rx: &mpsc::Receiver<StreamMsg>,
+) -> Result<Option<String>> {
+    use std::time::Instant;
+
+    let (tx, rx) = mpsc::channel::<Vec<f32>>();
+    let stream = screen_capture::DiscordStream::start(tx)?;
+    let mut encoder = match open_sink(screen_capture::CHANNELS, screen_capture::SAMPLE_RATE) {
+        Ok(encoder) => encoder,
+        Err(e) => {
+            stream.stop();
+            return Err(e);
+        }
+    };
+    log::info!("ScreenCaptureKit capture started: {}", encoder.path());
+
+    let start_time = Instant::now();
+
+    'capture: loop {
+        if stop_rx.try_recv().is_ok() || !is_recording.load(Ordering::Relaxed) {
+            break;
+        }
+
+        if let Some(max_secs) = max_duration_secs {
+            if start_time.elapsed().as_secs() >= max_secs as u64 {
+                log::info!("Max recording duration ({max_secs}s) reached, auto-stopping");
+                is_recording.store(false, Ordering::Relaxed);
+                break;
+            }
+        }
+
+        // Buffers arrive roughly every 10ms while Discord is playing anything
+        let buffer = match rx.recv_timeout(Duration::from_millis(200)) {
+            Ok(buffer) => buffer,
+            Err(mpsc::RecvTimeoutError::Timeout) => Vec::new(),
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        };
+
+        hold_peak(
+            peak_level_bits,
+            buffer.iter().fold(0.0f32, |max, s| max.max(s.abs())),
+        );
+        if !buffer.is_empty() {
+            if let Err(e) = encoder.write_samples(&buffer) {
+                log::error!("Failed to write samples: {}", e);
+                break 'capture;
+            }
+        }
+    }
+
+    stream.stop();
+    let p = encoder.path().to_string();
+    encoder.finalize()?;
+    log::info!("Recording saved: {}", p);
+    Ok(Some(p))
+}
+
+#[cfg(target_os = "macos")]
+mod screen_capture {
+    use anyhow::{Context, Result};
+    use block2::RcBlock;
+    use dispatch2::DispatchQueue;
+    use objc2::rc::Retained;
+    use objc2::runtime::ProtocolObject;
+    use objc2::{define_class, msg_send, AllocAnyThread, DefinedClass};
+    use objc2_core_audio_types::{AudioBuffer, AudioBufferList};
+    use objc2_core_foundation::CFRetained;
+    use objc2_core_media::{
+        kCMSampleBufferFlag_AudioBufferList_Assure16ByteAlignment, CMBlockBuffer, CMSampleBuffer,
+    };
+    use objc2_foundation::{NSArray, NSError, NSObject, NSObjectProtocol, NSProcessInfo};
+    use objc2_screen_capture_kit::{
+        SCContentFilter, SCRunningApplication, SCShareableContent, SCStream, SCStreamConfiguration,
+        SCStreamOutput, SCStreamOutputType,
+    };
+    use std::ptr::{self, NonNull};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    pub const SAMPLE_RATE: u32 = 48000;
+    pub const CHANNELS: u16 = 2;
+
+    const DISCORD_BUNDLE_IDS: [&str; 3] = [
+        "com.hnc.Discord",
+        "com.hnc.DiscordPTB",
+        "com.hnc.DiscordCanary",
+    ];
+
+    /// How long to wait for ScreenCaptureKit to answer a request.
+    const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// ScreenCaptureKit can capture audio from macOS 13 on.
+    pub fn supported() -> bool {
+        NSProcessInfo::processInfo()
+            .operatingSystemVersion()
+            .majorVersion
+            >= 13
+    }
+
+    /// Carries a completion handler's result back to the capture thread.
+    struct Reply<T>(T);
+
+    // SAFETY: The Objective-C objects inside are only touched by the thread
+    // that receives them; the handler drops its reference after sending.
+    unsafe impl<T> Send for Reply<T> {}
+
+    fn describe(error: *mut NSError) -> String {
+        // SAFETY: ScreenCaptureKit passes either null or a valid NSError
+        unsafe { Retained::retain(error) }
+            .map(|e| e.localizedDescription().to_string())
+            .unwrap_or_else(|| "unknown error".to_string())
+    }
+
+    define_class!(
+        // SAFETY: NSObject has no subclassing requirements and AudioOutput
+        // does not implement Drop.
+        #[unsafe(super(NSObject))]
+        #[name = "DiscRecAudioOutput"]
+        #[ivars = mpsc::Sender<Vec<f32>>]
+        struct AudioOutput;
+
+        unsafe impl NSObjectProtocol for AudioOutput {}
+
+        unsafe impl SCStreamOutput for AudioOutput {
+            #[unsafe(method(stream:didOutputSampleBuffer:ofType:))]
+            fn did_output(
+                &self,
+                _stream: &SCStream,
+                sample_buffer: &CMSampleBuffer,
+                kind: SCStreamOutputType,
+            ) {
+                if kind != SCStreamOutputType::Audio {
+                    return;
+                }
+                if let Some(samples) = interleaved_samples(sample_buffer) {
+                    let _ = self.ivars().send(samples);
+                }
+            }
+        }
+    );
+
+    impl AudioOutput {
+        fn new(tx: mpsc::Sender<Vec<f32>>) -> Retained<Self> {
+            let this = Self::alloc().set_ivars(tx);
+            // SAFETY: NSObject's init is always safe to call
+            unsafe { msg_send![super(this), init] }
+        }
+    }
+
+    /// `AudioBufferList` with room for one buffer per channel.
+    #[repr(C)]
+    struct StereoBufferList {
+        number_buffers: u32,
+        buffers: [AudioBuffer; CHANNELS as usize],
+    }
+
+    /// Interleaved stereo f32 samples from one ScreenCaptureKit audio buffer.
+    ///
+    /// ScreenCaptureKit delivers 32-bit float PCM, normally one buffer per
+    /// channel (non-interleaved).
+    fn interleaved_samples(sample_buffer: &CMSampleBuffer) -> Option<Vec<f32>> {
+        let mut list = StereoBufferList {
+            number_buffers: 0,
+            buffers: [AudioBuffer {
+                mNumberChannels: 0,
+                mDataByteSize: 0,
+                mData: ptr::null_mut(),
+            }; CHANNELS as usize],
+        };
+        let mut block_buffer: *mut CMBlockBuffer = ptr::null_mut();
+        // SAFETY: `list` is a valid AudioBufferList with room for CHANNELS
+        // buffers, and its size is passed along.
+        let status = unsafe {
+            sample_buffer.audio_buffer_list_with_retained_block_buffer(
+                ptr::null_mut(),
+                (&mut list as *mut StereoBufferList).cast::<AudioBufferList>(),
+                std::mem::size_of::<StereoBufferList>(),
+                None,
+                None,
+                kCMSampleBufferFlag_AudioBufferList_Assure16ByteAlignment,
+                &mut block_buffer,
+            )
+        };
+        if status != 0 {
+            log::warn!("Failed to read ScreenCaptureKit audio buffer: OSStatus {status}");
+            return None;
+        }
+        // The buffers point into the block buffer; release it once copied
+        // SAFETY: The call above returned it retained
+        let _block_buffer = NonNull::new(block_buffer).map(|b| unsafe { CFRetained::from_raw(b) });
+
+        let buffers = &list.buffers[..(list.number_buffers as usize).min(CHANNELS as usize)];
+        let channels: Vec<&[f32]> = buffers
+            .iter()
+            .filter(|b| !b.mData.is_null())
+            .map(|b| {
+                // SAFETY: mData holds mDataByteSize bytes of f32 samples and
+                // stays valid while the block buffer is retained
+                unsafe {
+                    std::slice::from_raw_parts(
+                        b.mData as *const f32,
+                        b.mDataByteSize as usize / std::mem::size_of::<f32>(),
+                    )
+                }
+            })
+            .collect();
+
+        let samples = match channels.as_slice() {
+            [left, right] => left
+                .iter()
+                .zip(*right)
+                .flat_map(|(&l, &r)| [l, r])
+                .collect(),
+            [interleaved] if buffers[0].mNumberChannels == CHANNELS as u32 => interleaved.to_vec(),
+            [mono] => mono.iter().flat_map(|&s| [s, s]).collect(),
+            _ => return None,
+        };
+        Some(samples)
+    }
+
+    /// A running ScreenCaptureKit stream that sends Discord's audio to a channel.
+    pub struct DiscordStream {
+        stream: Retained<SCStream>,
+        // Kept alive for as long as the stream delivers to it
+        _output: Retained<AudioOutput>,
+    }
+
+    impl DiscordStream {
+        pub fn start(tx: mpsc::Sender<Vec<f32>>) -> Result<Self> {
+            let content = shareable_content()?;
+            // SAFETY: Plain property reads on a valid SCShareableContent
+            let (applications, display) =
+                unsafe { (content.applications(), content.displays().firstObject()) };
+            let display = display.context("No display available for ScreenCaptureKit")?;
+            let discord: Retained<SCRunningApplication> = applications
+                .iter()
+                .find(|app| {
+                    // SAFETY: Plain property read
+                    let bundle_id = unsafe { app.bundleIdentifier() }.to_string();
+                    DISCORD_BUNDLE_IDS.contains(&bundle_id.as_str())
+                })
+                .context("Discord is not running. Please start Discord before recording.")?;
+            // SAFETY: Plain property reads
+            log::info!(
+                "Found {} with PID {}",
+                unsafe { discord.applicationName() },
+                unsafe { discord.processID() }
+            );
+
+            // SAFETY: All arguments are valid objects; the filter keeps its own references
+            let filter = unsafe {
+                SCContentFilter::initWithDisplay_includingApplications_exceptingWindows(
+                    SCContentFilter::alloc(),
+                    &display,
+                    &NSArray::from_retained_slice(&[discord]),
+                    &NSArray::new(),
+                )
+            };
+
+            // SAFETY: Setters on a freshly created configuration
+            let config = unsafe {
+                let config = SCStreamConfiguration::new();
+                config.setCapturesAudio(true);
+                config.setSampleRate(SAMPLE_RATE as isize);
+                config.setChannelCount(CHANNELS as isize);
+                config.setExcludesCurrentProcessAudio(true);
+                // Video can't be turned off; keep it as small and slow as possible
+                config.setWidth(2);
+                config.setHeight(2);
+                config.setMinimumFrameInterval(objc2_core_media::CMTime {
+                    value: 1,
+                    timescale: 1,
+                    flags: objc2_core_media::CMTimeFlags::Valid,
+                    epoch: 0,
+                });
+                config
+            };
+
+            let output = AudioOutput::new(tx);
+            let queue = DispatchQueue::new("com.discrec.audio", None);
+            // SAFETY: The filter and configuration are valid, and `output`
+            // outlives the stream (both are held by DiscordStream)
+            let stream = unsafe {
+                let stream = SCStream::initWithFilter_configuration_delegate(
+                    SCStream::alloc(),
+                    &filter,
+                    &config,
+                    None,
+                );
+                stream
+                    .addStreamOutput_type_sampleHandlerQueue_error(
+                        ProtocolObject::from_ref(&*output),
+                        SCStreamOutputType::Audio,
+                        Some(&queue),
+                    )
+                    .map_err(|e| {
+                        anyhow::anyhow!("Failed to add audio output: {}", e.localizedDescription())
+                    })?;
+                stream
+            };
+
+            let (done_tx, done_rx) = mpsc::channel();
+            let handler = RcBlock::new(move |error: *mut NSError| {
+                let result = if error.is_null() {
+                    Ok(())
+                } else {
+                    Err(describe(error))
+                };
+                let _ = done_tx.send(result);
+            });
+            // SAFETY: The handler matches the expected signature
+            unsafe { stream.startCaptureWithCompletionHandler(Some(&handler)) };
+            match done_rx.recv_timeout(REPLY_TIMEOUT) {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => anyhow::bail!("Failed to start ScreenCaptureKit capture: {}", e),
+                Err(_) => anyhow::bail!("ScreenCaptureKit did not start capturing"),
+            }
+
+            Ok(Self {
+                stream,
+                _output: output,
+            })
+        }
+
+        /// Stop capturing, waiting briefly so the last buffers are delivered.
+        pub fn stop(&self) {
+            let (done_tx, done_rx) = mpsc::channel();
+            let handler = RcBlock::new(move |_error: *mut NSError| {
+                let _ = done_tx.send(());
+            });
+            // SAFETY: The handler matches the expected signature
+            unsafe { self.stream.stopCaptureWithCompletionHandler(Some(&handler)) };
+            let _ = done_rx.recv_timeout(REPLY_TIMEOUT);
+        }
+    }
+
+    /// Apps and displays ScreenCaptureKit may capture. Fails without the
+    /// Screen Recording permission.
+    fn shareable_content() -> Result<Retained<SCShareableContent>> {
+        let (tx, rx) = mpsc::channel();
+        let handler = RcBlock::new(
+            move |content: *mut SCShareableContent, error: *mut NSError| {
+                // SAFETY: ScreenCaptureKit passes either null or a valid object
+                let result = unsafe { Retained::retain(content) }.ok_or_else(|| describe(error));
+                let _ = tx.send(Reply(result));
+            },
+        );
+        // SAFETY: The handler matches the expected signature
+        unsafe { SCShareableContent::getShareableContentWithCompletionHandler(&handler) };
+
+        match rx.recv_timeout(REPLY_TIMEOUT) {
+            Ok(Reply(Ok(content))) => Ok(content),
+            Ok(Reply(Err(e))) => anyhow::bail!(
+                "DiscRec needs the Screen Recording permission to capture Discord. Allow it in System Settings → Privacy & Security → Screen & System Audio Recording, then try again. ({})",
+                e
+            ),
+            Err(_) => anyhow::bail!("ScreenCaptureKit did not respond"),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// cpal-based capture: system loopback, microphone, and routed Discord on Linux
+// ---------------------------------------------------------------------------
+
+/// Which device a cpal capture thread opens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CpalInput {
+    /// Discord moved onto a private sink via PulseAudio/PipeWire routing.
+    #[cfg(target_os = "linux")]
+    DiscordApp,
+    /// Monitor source (Linux), virtual device (macOS) or output loopback (Windows).
+    System,
+    Microphone,
+}
+
+/// `routed_apps` are routed along with Discord and `report_routing` receives
+/// how routing went when Discord is captured through PulseAudio/PipeWire.
+#[allow(clippy::too_many_arguments)]
 fn capture_cpal(
-    path: &str,
-    format: AudioFormat,
-    silence_trim: bool,
+    open_sink: SinkFactory,
+    input: CpalInput,
     max_duration_secs: Option<u32>,
+    capture_device: Option<&str>,
+    routed_apps: &[RoutedApp],
+    report_routing: &dyn Fn(RoutingReport),
     is_recording: &Arc<AtomicBool>,
     peak_level_bits: &Arc<AtomicU32>,
     stop_rx: &mpsc::Receiver<StreamMsg>,
 ) -> Result<Option<String>> {
-    use super::encoder::AudioEncoder;
     use anyhow::Context;
-    use cpal::traits::{DeviceTrait, StreamTrait};
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
     use cpal::{SampleFormat, StreamConfig};
-    use parking_lot::Mutex;
-    use std::time::{Duration, Instant};
+    use std::time::Instant;
 
     let host = cpal::default_host();
 
-    // On Linux, try per-app Discord routing via PulseAudio/PipeWire
+    // On Linux, route Discord onto its own sink via PulseAudio/PipeWire.
+    // Sandboxes only see their own streams (and there is no audio capture
+    // portal), so there the monitor of the default output is recorded
     #[cfg(target_os = "linux")]
-    let _routing = pulse_routing::DiscordRouting::setup();
+    let mut report = RoutingReport::new();
+    #[cfg(target_os = "linux")]
+    let _routing = if input != CpalInput::DiscordApp {
+        None
+    } else if crate::sandbox::is_sandboxed() {
+        report.check::<()>(
+            RoutingStep::Sandbox,
+            Err(anyhow::anyhow!(
+                "Per-app routing isn't possible in a sandbox"
+            )),
+        );
+        None
+    } else {
+        let routing = pulse_routing::DiscordRouting::setup(&mut report, routed_apps);
+        if routing.is_none() {
+            log::warn!("Per-app routing unavailable, capturing system audio instead");
+        }
+        routing
+    };
 
     #[cfg(target_os = "linux")]
     let preferred_source = _routing.as_ref().map(|r| r.monitor_source());
 
     #[cfg(not(target_os = "linux"))]
     let preferred_source: Option<&str> = None;
+    #[cfg(not(target_os = "linux"))]
+    let _ = (routed_apps, report_routing);
+
+    // Per-app routing has its own sink; the chosen device is for system audio
+    let selected = match capture_device {
+        Some(name) if input != CpalInput::Microphone && preferred_source.is_none() => {
+            let device = find_capture_device(&host, name);
+            if device.is_none() {
+                log::warn!("Capture device '{name}' not found, picking one automatically");
+            }
+            device
+        }
+        _ => None,
+    };
 
-    let device = get_loopback_device(&host, preferred_source)?;
-    let config = device
-        .default_output_config()
-        .context("Failed to get default output config")?;
+    let (device, config) = if input == CpalInput::Microphone {
+        let device = host
+            .default_input_device()
+            .context("No microphone available")?;
+        let config = device
+            .default_input_config()
+            .context("Failed to get default input config")?;
+        (device, config)
+    } else if let Some(device) = selected {
+        // Inputs record directly; Windows output devices record in loopback
+        let config = device
+            .default_input_config()
+            .or_else(|_| device.default_output_config())
+            .context("Failed to get capture device config")?;
+        log::info!(
+            "Using selected capture device: {}",
+            device.name().unwrap_or_default()
+        );
+        (device, config)
+    } else {
+        let device = get_loopback_device(&host, preferred_source)?;
+        let config = device
+            .default_output_config()
+            .context("Failed to get default output config")?;
+        (device, config)
+    };
+
+    // Apps routed onto their own channels need all of the sink's channels
+    #[cfg(target_os = "linux")]
+    let config = match _routing.as_ref().filter(|r| r.channels() > 2) {
+        Some(routing) => config_with_channels(&device, routing.channels()).unwrap_or_else(|| {
+            log::warn!(
+                "Can't open {} channels, apps on their own channels are mixed down",
+                routing.channels()
+            );
+            config
+        }),
+        None => config,
+    };
+
+    #[cfg(target_os = "linux")]
+    if input == CpalInput::DiscordApp {
+        let name = device.name().unwrap_or_default();
+        if let Some(source) = preferred_source {
+            let opened = if name.contains(source) {
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!(
+                    "{} not found, recording {} instead",
+                    source,
+                    name
+                ))
+            };
+            if report.check(RoutingStep::OpenMonitor, opened).is_some() {
+                report.mode = CaptureMode::DiscordOnly;
+            }
+        }
+        report_routing(report);
+    }
 
     log::info!(
         "Recording from: {} (format: {:?}, rate: {}, channels: {})",
@@ -348,13 +1485,8 @@ fn capture_cpal(
         config.channels()
     );
 
-    let encoder = create_encoder(
-        path,
-        config.channels(),
-        config.sample_rate().0,
-        format,
-        silence_trim,
-    )?;
+    let encoder = open_sink(config.channels(), config.sample_rate().0)?;
+    let path = encoder.path().to_string();
     let encoder: Arc<Mutex<Option<Box<dyn AudioEncoder>>>> = Arc::new(Mutex::new(Some(encoder)));
 
     let writer_ref = Arc::clone(&encoder);
@@ -375,14 +1507,11 @@ fn capture_cpal(
                     return;
                 }
                 let peak = data.iter().fold(0.0f32, |max, &s| max.max(s.abs()));
-                peak_bits.store(peak.to_bits(), Ordering::Relaxed);
+                hold_peak(&peak_bits, peak);
 
                 if let Some(ref mut w) = *writer_ref.lock() {
-                    for &sample in data {
-                        if let Err(e) = w.write_sample(sample) {
-                            log::error!("Failed to write sample: {}", e);
-                            return;
-                        }
+                    if let Err(e) = w.write_samples(data) {
+                        log::error!("Failed to write samples: {}", e);
                     }
                 }
             },
@@ -395,18 +1524,15 @@ fn capture_cpal(
                 if !rec_flag.load(Ordering::Relaxed) {
                     return;
                 }
-                let peak = data.iter().fold(0.0f32, |max, &s| {
-                    max.max((s as f32 / i16::MAX as f32).abs())
-                });
-                peak_bits.store(peak.to_bits(), Ordering::Relaxed);
+                let peak = data
+                    .iter()
+                    .fold(0.0f32, |max, &s| max.max(pcm::i16_to_float(s).abs()));
+                hold_peak(&peak_bits, peak);
 
                 if let Some(ref mut w) = *writer_ref.lock() {
-                    for &sample in data {
-                        let float_sample = sample as f32 / i16::MAX as f32;
-                        if let Err(e) = w.write_sample(float_sample) {
-                            log::error!("Failed to write sample: {}", e);
-                            return;
-                        }
+                    let samples: Vec<f32> = data.iter().map(|&s| pcm::i16_to_float(s)).collect();
+                    if let Err(e) = w.write_samples(&samples) {
+                        log::error!("Failed to write samples: {}", e);
                     }
                 }
             },
@@ -415,9 +1541,21 @@ fn capture_cpal(
         ),
         fmt => anyhow::bail!("Unsupported sample format: {:?}", fmt),
     }
-    .context("Failed to build input stream")?;
-
-    stream.play().context("Failed to start audio stream")?;
+    .context("Failed to build input stream");
+
+    // Loopback fails when another app holds the output device exclusively
+    let explain = |e: anyhow::Error| {
+        if input == CpalInput::Microphone {
+            e
+        } else {
+            explain_exclusive_mode(e)
+        }
+    };
+    let stream = stream.map_err(explain)?;
+    stream
+        .play()
+        .context("Failed to start audio stream")
+        .map_err(explain)?;
     log::info!("Recording started: {}", path);
 
     // Block until stop signal or max duration
@@ -459,83 +1597,310 @@ fn capture_cpal(
 // Linux: PulseAudio/PipeWire per-app routing for Discord-only capture
 // ---------------------------------------------------------------------------
 
+/// Another app recorded along with Discord through per-app routing on Linux.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutedApp {
+    /// Matched case-insensitively against the stream's application name or
+    /// binary, e.g. `spotify` or `firefox`.
+    pub name: String,
+    /// Record it on a channel pair of its own after Discord's, instead of
+    /// mixed in with Discord.
+    #[serde(default)]
+    pub own_channels: bool,
+}
+
+/// Apps playing audio that could be routed along with Discord.
+#[cfg(target_os = "linux")]
+pub fn playing_apps() -> Result<Vec<String>> {
+    if crate::sandbox::is_sandboxed() {
+        anyhow::bail!("Other apps can't be routed from a sandbox");
+    }
+    pulse_routing::playing_apps()
+}
+
+/// Hand Discord back to its own sink if an earlier run was killed while it
+/// was routed for recording, which would otherwise leave it muted.
+#[cfg(target_os = "linux")]
+pub fn clean_up_stale_routing() {
+    if !crate::sandbox::is_sandboxed() {
+        pulse_routing::clean_up_stale();
+    }
+}
+
+/// A step in routing Discord onto its own sink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutingStep {
+    /// Flatpak and Snap sandboxes can't see other apps' streams.
+    Sandbox,
+    FindDiscord,
+    CreateSink,
+    /// Playing the sink on the speakers too; capture works without it.
+    Loopback,
+    MoveStream,
+    /// Opening the sink's monitor as the capture device.
+    OpenMonitor,
+    /// Routing another app picked in settings; the error names the app.
+    RouteApp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingStepResult {
+    pub step: RoutingStep,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// What Discord capture ended up recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureMode {
+    /// Only Discord, routed onto DiscRec's sink.
+    DiscordOnly,
+    /// Everything playing, because routing didn't work out.
+    SystemAudio,
+}
+
+/// How setting up Discord-only capture went, so a fallback to recording
+/// all system audio is visible in the status and the session manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingReport {
+    pub steps: Vec<RoutingStepResult>,
+    pub mode: CaptureMode,
+    /// What each channel pair of the recording holds, in order, when other
+    /// apps were routed too.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub channel_pairs: Vec<String>,
+}
+
+#[cfg(target_os = "linux")]
+impl RoutingReport {
+    fn new() -> Self {
+        Self {
+            steps: Vec::new(),
+            mode: CaptureMode::SystemAudio,
+            channel_pairs: Vec::new(),
+        }
+    }
+
+    /// Note how `step` went and pass on its value if it succeeded.
+    fn check<T>(&mut self, step: RoutingStep, result: Result<T>) -> Option<T> {
+        let (value, error) = match result {
+            Ok(value) => (Some(value), None),
+            Err(e) => {
+                log::warn!("Per-app routing step {:?} failed: {:#}", step, e);
+                (None, Some(format!("{:#}", e)))
+            }
+        };
+        self.steps.push(RoutingStepResult {
+            step,
+            ok: error.is_none(),
+            error,
+        });
+        value
+    }
+}
+
 #[cfg(target_os = "linux")]
 mod pulse_routing {
-    use std::process::Command;
+    use anyhow::{Context, Result};
+    use parking_lot::Mutex;
+    use serde::{Deserialize, Serialize};
+    use std::io::{BufRead, BufReader};
+    use std::path::PathBuf;
+    use std::process::{Child, Command, Stdio};
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::{RoutedApp, RoutingReport, RoutingStep};
+
+    const SINK_NAME: &str = "discrec_capture";
 
     pub struct DiscordRouting {
+        routed: Arc<Mutex<Routed>>,
+        /// Channels of the capture sink: Discord's pair, then one per app
+        /// recorded on its own channels.
+        channels: u16,
+        /// `pactl subscribe`, for moving the streams of a restarted Discord
+        /// or app onto their sink too.
+        watcher: Option<(Child, thread::JoinHandle<()>)>,
+    }
+
+    /// What routing changed; kept on disk while Discord is routed, so the
+    /// next start can undo it when DiscRec was killed.
+    #[derive(Serialize, Deserialize)]
+    struct Routed {
         null_sink_module: u32,
         loopback_module: u32,
         sink_input_idx: u32,
         original_sink: u32,
+        /// Channel pair sinks for other apps and their loopbacks.
+        #[serde(default)]
+        app_modules: Vec<u32>,
+    }
+
+    /// The sink an app's streams are moved to, matched by name.
+    struct Target {
+        app: String,
+        sink: String,
     }
 
     impl DiscordRouting {
-        /// Try to set up per-app routing. Returns None if pactl or Discord not found.
-        pub fn setup() -> Option<Self> {
+        /// Try to set up per-app routing, with `apps` recorded along with
+        /// Discord, noting every step in `report`. Returns None if pactl or
+        /// Discord not found.
+        pub fn setup(report: &mut RoutingReport, apps: &[RoutedApp]) -> Option<Self> {
             // Find Discord's sink input
-            let (sink_input_idx, original_sink) = find_discord_sink_input()?;
+            let (sink_input_idx, original_sink) =
+                report.check(RoutingStep::FindDiscord, find_discord_sink_input())?;
             log::info!("Found Discord sink input #{sink_input_idx} on sink #{original_sink}");
 
+            // Discord's pair first, then a pair per app kept apart
+            let pairs = 1 + apps.iter().filter(|app| app.own_channels).count();
+            let channels = pairs * 2;
+            let channel_map = (0..channels)
+                .map(|c| match c {
+                    0 => "front-left".to_string(),
+                    1 => "front-right".to_string(),
+                    c => format!("aux{}", c - 2),
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+
             // Create null sink for capture
-            let null_sink_module = run_pactl(&[
-                "load-module",
-                "module-null-sink",
-                "sink_name=discrec_capture",
-                "sink_properties=device.description=DiscRec",
-                "rate=48000",
-                "channels=2",
-            ])?;
+            let null_sink_module = report.check(
+                RoutingStep::CreateSink,
+                run_pactl(&[
+                    "load-module",
+                    "module-null-sink",
+                    &format!("sink_name={}", SINK_NAME),
+                    "sink_properties=device.description=DiscRec",
+                    "rate=48000",
+                    &format!("channels={}", channels),
+                    &format!("channel_map={}", channel_map),
+                ]),
+            )?;
             log::info!("Created null sink (module #{null_sink_module})");
 
-            // Create loopback so user still hears Discord
-            let loopback_module = run_pactl(&[
-                "load-module",
-                "module-loopback",
-                "source=discrec_capture.monitor",
-                "latency_msec=1",
-            ]);
-            if loopback_module.is_none() {
-                log::warn!("Failed to create loopback — user won't hear Discord during recording");
+            // Create loopback so user still hears Discord; other apps' pairs
+            // get their own below
+            let mut loopback = vec![
+                "load-module".to_string(),
+                "module-loopback".to_string(),
+                format!("source={}.monitor", SINK_NAME),
+                "latency_msec=1".to_string(),
+            ];
+            if pairs > 1 {
+                loopback.extend([
+                    "channels=2".to_string(),
+                    "channel_map=front-left,front-right".to_string(),
+                    "remix=false".to_string(),
+                ]);
             }
+            let loopback: Vec<&str> = loopback.iter().map(String::as_str).collect();
+            let loopback_module = report.check(
+                RoutingStep::Loopback,
+                run_pactl(&loopback).context("You won't hear Discord while recording"),
+            );
 
             // Move Discord to our capture sink
-            let moved = Command::new("pactl")
-                .args([
-                    "move-sink-input",
-                    &sink_input_idx.to_string(),
-                    "discrec_capture",
-                ])
-                .output()
-                .ok()
-                .map(|o| o.status.success())
-                .unwrap_or(false);
-
-            if !moved {
-                log::warn!("Failed to move Discord sink input — falling back to system capture");
-                let _ = unload_module(null_sink_module);
-                if let Some(lb) = loopback_module {
-                    let _ = unload_module(lb);
-                }
-                return None;
-            }
-
-            log::info!("Discord audio routed to discrec_capture sink");
-            Some(Self {
+            let moved = report.check(
+                RoutingStep::MoveStream,
+                run_pactl(&["move-sink-input", &sink_input_idx.to_string(), SINK_NAME]),
+            );
+            let routed = Routed {
                 null_sink_module,
                 loopback_module: loopback_module.unwrap_or(0),
                 sink_input_idx,
                 original_sink,
-            })
+                app_modules: Vec::new(),
+            };
+            if let Err(e) = routed.save() {
+                log::warn!("Failed to remember the routing for cleanup: {:#}", e);
+            }
+            let mut routing = Self {
+                routed: Arc::new(Mutex::new(routed)),
+                channels: channels as u16,
+                watcher: None,
+            };
+            // Dropping the routing unloads the modules again
+            if moved.is_none() {
+                log::warn!("Failed to move Discord sink input — falling back to system capture");
+                return None;
+            }
+            log::info!("Discord audio routed to discrec_capture sink");
+
+            let targets = routing.route_apps(report, apps);
+            match follow_streams(Arc::clone(&routing.routed), targets) {
+                Ok(watcher) => routing.watcher = Some(watcher),
+                Err(e) => log::warn!("Won't follow Discord if it restarts: {:#}", e),
+            }
+            Some(routing)
+        }
+
+        /// Move `apps`' streams onto the capture sink, or onto a channel pair
+        /// of their own, and return where each app's streams go.
+        fn route_apps(&self, report: &mut RoutingReport, apps: &[RoutedApp]) -> Vec<Target> {
+            let mut targets = vec![Target {
+                app: "discord".to_string(),
+                sink: SINK_NAME.to_string(),
+            }];
+            let mut shared = vec!["Discord".to_string()];
+            let mut own = Vec::new();
+            for app in apps {
+                let sink = if app.own_channels {
+                    // A pair that failed to load goes to the next app
+                    let pair = own.len();
+                    let sink = format!("{}_app{}", SINK_NAME, pair);
+                    match report.check(
+                        RoutingStep::RouteApp,
+                        add_channel_pair(&sink, pair).with_context(|| app.name.clone()),
+                    ) {
+                        Some(modules) => self.routed.lock().app_modules.extend(modules),
+                        None => continue,
+                    }
+                    own.push(app.name.clone());
+                    sink
+                } else {
+                    shared.push(app.name.clone());
+                    SINK_NAME.to_string()
+                };
+                let moved = report.check(
+                    RoutingStep::RouteApp,
+                    move_streams(&app.name, &sink).with_context(|| app.name.clone()),
+                );
+                if let Some(count) = moved {
+                    log::info!("Routed {} stream(s) of {} to {}", count, app.name, sink);
+                }
+                targets.push(Target {
+                    app: app.name.to_lowercase(),
+                    sink,
+                });
+            }
+            if let Err(e) = self.routed.lock().save() {
+                log::warn!("Failed to remember the routing for cleanup: {:#}", e);
+            }
+            report.channel_pairs = std::iter::once(shared.join(" + ")).chain(own).collect();
+            targets
         }
 
         pub fn monitor_source(&self) -> &str {
             "discrec_capture.monitor"
         }
+
+        pub fn channels(&self) -> u16 {
+            self.channels
+        }
     }
 
-    impl Drop for DiscordRouting {
-        fn drop(&mut self) {
+    impl Routed {
+        fn save(&self) -> Result<()> {
+            let data = serde_json::to_string(self)?;
+            std::fs::write(state_path(), data).context("Failed to write routing state")
+        }
+
+        fn restore(&self) {
             // Move Discord back to original sink
             let _ = Command::new("pactl")
                 .args([
@@ -545,7 +1910,15 @@ mod pulse_routing {
                 ])
                 .output();
             log::info!("Restored Discord to original sink #{}", self.original_sink);
+            self.unload_modules();
+        }
 
+        /// Unload everything routing loaded; streams still on the sinks
+        /// return to the default output.
+        fn unload_modules(&self) {
+            for &module in self.app_modules.iter().rev() {
+                let _ = unload_module(module);
+            }
             if self.loopback_module != 0 {
                 let _ = unload_module(self.loopback_module);
             }
@@ -554,13 +1927,189 @@ mod pulse_routing {
         }
     }
 
-    fn run_pactl(args: &[&str]) -> Option<u32> {
-        let output = Command::new("pactl").args(args).output().ok()?;
+    impl Drop for DiscordRouting {
+        fn drop(&mut self) {
+            if let Some((mut child, handle)) = self.watcher.take() {
+                let _ = child.kill();
+                let _ = child.wait();
+                let _ = handle.join();
+            }
+            self.routed.lock().restore();
+            let _ = std::fs::remove_file(state_path());
+        }
+    }
+
+    /// A sink feeding channel pair `pair` after Discord's on the capture
+    /// sink, plus a loopback so the app stays audible. Returns the modules.
+    fn add_channel_pair(sink: &str, pair: usize) -> Result<Vec<u32>> {
+        let remap = run_pactl(&[
+            "load-module",
+            "module-remap-sink",
+            &format!("sink_name={}", sink),
+            &format!("master={}", SINK_NAME),
+            "channels=2",
+            &format!("master_channel_map=aux{},aux{}", pair * 2, pair * 2 + 1),
+            "channel_map=front-left,front-right",
+            "remix=no",
+        ])?;
+        match run_pactl(&[
+            "load-module",
+            "module-loopback",
+            &format!("source={}.monitor", sink),
+            "latency_msec=1",
+        ]) {
+            Ok(loopback) => Ok(vec![remap, loopback]),
+            Err(e) => {
+                log::warn!("You won't hear {} while recording: {:#}", sink, e);
+                Ok(vec![remap])
+            }
+        }
+    }
+
+    /// Move every stream `app` plays onto `sink`, returning how many there were.
+    fn move_streams(app: &str, sink: &str) -> Result<usize> {
+        let inputs: Vec<SinkInput> = sink_inputs()?
+            .into_iter()
+            .filter(|input| input.is(app))
+            .collect();
+        for input in &inputs {
+            run_pactl(&["move-sink-input", &input.index.to_string(), sink])?;
+        }
+        Ok(inputs.len())
+    }
+
+    /// Watch the sound server's events and move every new stream of Discord
+    /// or a routed app onto its sink, so one that crashes and restarts
+    /// mid-recording keeps being captured instead of playing to the default
+    /// output.
+    fn follow_streams(
+        routed: Arc<Mutex<Routed>>,
+        targets: Vec<Target>,
+    ) -> Result<(Child, thread::JoinHandle<()>)> {
+        let mut child = Command::new("pactl")
+            .arg("subscribe")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Failed to run pactl subscribe")?;
+        let stdout = child
+            .stdout
+            .take()
+            .context("pactl subscribe has no output")?;
+        let handle = thread::spawn(move || {
+            // Ends once pactl is killed
+            for line in BufReader::new(stdout).lines().map_while(|line| line.ok()) {
+                let Some(index) = line
+                    .strip_prefix("Event 'new' on sink-input #")
+                    .and_then(|index| index.trim().parse::<u32>().ok())
+                else {
+                    continue;
+                };
+                let input = sink_inputs()
+                    .ok()
+                    .and_then(|inputs| inputs.into_iter().find(|input| input.index == index));
+                let Some(target) = input
+                    .as_ref()
+                    .and_then(|input| targets.iter().find(|target| input.is(&target.app)))
+                else {
+                    continue;
+                };
+                match run_pactl(&["move-sink-input", &index.to_string(), &target.sink]) {
+                    Ok(_) if target.sink == SINK_NAME && target.app == "discord" => {
+                        log::info!("Discord came back as sink input #{index}, routed it again");
+                        let mut routed = routed.lock();
+                        routed.sink_input_idx = index;
+                        if let Err(e) = routed.save() {
+                            log::warn!("Failed to remember the routing for cleanup: {:#}", e);
+                        }
+                    }
+                    Ok(_) => log::info!(
+                        "Routed new {} stream #{index} to {}",
+                        target.app,
+                        target.sink
+                    ),
+                    Err(e) => log::warn!("Failed to route {}'s new stream: {:#}", target.app, e),
+                }
+            }
+        });
+        Ok((child, handle))
+    }
+
+    /// Undo routing a killed DiscRec left behind: move Discord back and
+    /// unload the modules, skipping any the sound server has since reused
+    /// for something else.
+    pub fn clean_up_stale() {
+        let path = state_path();
+        let Ok(data) = std::fs::read_to_string(&path) else {
+            return;
+        };
+        let _ = std::fs::remove_file(&path);
+        let Ok(mut stale) = serde_json::from_str::<Routed>(&data) else {
+            log::warn!("Ignoring unreadable routing state in {}", path.display());
+            return;
+        };
+        let ours = our_modules();
+        stale.app_modules.retain(|module| ours.contains(module));
+        if !ours.contains(&stale.loopback_module) {
+            stale.loopback_module = 0;
+        }
+        if ours.contains(&stale.null_sink_module) {
+            log::warn!("Cleaning up Discord routing left by an earlier run");
+            stale.restore();
+        } else {
+            for &module in stale.app_modules.iter().chain([&stale.loopback_module]) {
+                if module != 0 {
+                    let _ = unload_module(module);
+                }
+            }
+        }
+    }
+
+    /// Where the active routing is kept; the runtime directory is cleared on
+    /// logout, as are the sound server's modules.
+    fn state_path() -> PathBuf {
+        dirs::runtime_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("discrec-routing.json")
+    }
+
+    /// Modules loaded with DiscRec's sink in their arguments.
+    fn our_modules() -> Vec<u32> {
+        let Ok(output) = Command::new("pactl")
+            .args(["list", "short", "modules"])
+            .output()
+        else {
+            return Vec::new();
+        };
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|line| line.contains(SINK_NAME))
+            .filter_map(|line| line.split_whitespace().next()?.parse().ok())
+            .collect()
+    }
+
+    /// Run pactl, returning the number it prints (a module index, or 0 for
+    /// commands that print nothing).
+    fn run_pactl(args: &[&str]) -> Result<u32> {
+        let output = Command::new("pactl")
+            .args(args)
+            .output()
+            .context("pactl isn't available")?;
         if !output.status.success() {
-            return None;
+            anyhow::bail!(
+                "pactl {} failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
         }
         let stdout = String::from_utf8_lossy(&output.stdout);
-        stdout.trim().parse().ok()
+        let stdout = stdout.trim();
+        if stdout.is_empty() {
+            return Ok(0);
+        }
+        stdout
+            .parse()
+            .with_context(|| format!("Unexpected pactl output: {}", stdout))
     }
 
     fn unload_module(id: u32) -> bool {
@@ -572,43 +2121,111 @@ mod pulse_routing {
             .unwrap_or(false)
     }
 
-    /// Parse `pactl list sink-inputs` to find Discord's sink input index and current sink.
-    fn find_discord_sink_input() -> Option<(u32, u32)> {
+    /// One app's stream as listed by `pactl list sink-inputs`.
+    struct SinkInput {
+        index: u32,
+        sink: Option<u32>,
+        name: Option<String>,
+        binary: Option<String>,
+    }
+
+    impl SinkInput {
+        /// Whether the stream's application name or binary contains `app`,
+        /// ignoring case.
+        fn is(&self, app: &str) -> bool {
+            let app = app.to_lowercase();
+            [&self.name, &self.binary]
+                .into_iter()
+                .flatten()
+                .any(|name| name.to_lowercase().contains(&app))
+        }
+    }
+
+    /// Names of the apps playing audio right now, other than Discord.
+    pub fn playing_apps() -> Result<Vec<String>> {
+        let mut apps: Vec<String> = sink_inputs()?
+            .into_iter()
+            .filter(|input| !input.is("discord"))
+            .filter_map(|input| input.name.or(input.binary))
+            .collect();
+        apps.sort_by_key(|name| name.to_lowercase());
+        apps.dedup();
+        Ok(apps)
+    }
+
+    /// Discord's first sink input index and its current sink.
+    fn find_discord_sink_input() -> Result<(u32, u32)> {
+        sink_inputs()?
+            .into_iter()
+            .filter(|input| input.is("discord"))
+            .find_map(|input| Some((input.index, input.sink?)))
+            .context("Discord isn't playing any audio; join a call or start Discord first")
+    }
+
+    /// Parse `pactl list sink-inputs`.
+    fn sink_inputs() -> Result<Vec<SinkInput>> {
         let output = Command::new("pactl")
             .args(["list", "sink-inputs"])
             .output()
-            .ok()?;
+            .context("pactl isn't available, install pulseaudio-utils")?;
         if !output.status.success() {
-            log::warn!("pactl not available — cannot set up per-app capture");
-            return None;
+            anyhow::bail!("pactl couldn't list audio streams; is PulseAudio or PipeWire running?");
         }
 
         let text = String::from_utf8_lossy(&output.stdout);
-        let mut current_idx: Option<u32> = None;
-        let mut current_sink: Option<u32> = None;
-
+        let mut inputs: Vec<SinkInput> = Vec::new();
         for line in text.lines() {
             let trimmed = line.trim();
             if let Some(rest) = trimmed.strip_prefix("Sink Input #") {
-                current_idx = rest.parse().ok();
-                current_sink = None;
-            } else if let Some(rest) = trimmed.strip_prefix("Sink: ") {
-                current_sink = rest.trim().parse().ok();
-            } else if trimmed.contains("application.name") {
-                let lower = trimmed.to_lowercase();
-                if lower.contains("discord") {
-                    if let (Some(idx), Some(sink)) = (current_idx, current_sink) {
-                        return Some((idx, sink));
-                    }
+                if let Ok(index) = rest.parse() {
+                    inputs.push(SinkInput {
+                        index,
+                        sink: None,
+                        name: None,
+                        binary: None,
+                    });
                 }
+                continue;
+            }
+            let Some(input) = inputs.last_mut() else {
+                continue;
+            };
+            if let Some(rest) = trimmed.strip_prefix("Sink: ") {
+                input.sink = rest.trim().parse().ok();
+            } else if let Some(value) = property(trimmed, "application.name") {
+                input.name = Some(value);
+            } else if let Some(value) = property(trimmed, "application.process.binary") {
+                input.binary = Some(value);
             }
         }
+        Ok(inputs)
+    }
 
-        log::info!("Discord sink input not found in pactl output");
-        None
+    /// The value of a `key = "value"` property line.
+    fn property(line: &str, key: &str) -> Option<String> {
+        let value = line.strip_prefix(key)?.trim_start().strip_prefix('=')?;
+        Some(value.trim().trim_matches('"').to_string())
     }
 }
 
+/// A capture config for `device` with exactly `channels`, at 48 kHz where
+/// the device allows.
+#[cfg(target_os = "linux")]
+fn config_with_channels(
+    device: &cpal::Device,
+    channels: u16,
+) -> Option<cpal::SupportedStreamConfig> {
+    use cpal::traits::DeviceTrait;
+
+    let range = device
+        .supported_input_configs()
+        .ok()?
+        .find(|config| config.channels() == channels)?;
+    let rate =
+        cpal::SampleRate(MIX_SAMPLE_RATE).clamp(range.min_sample_rate(), range.max_sample_rate());
+    Some(range.with_sample_rate(rate))
+}
+
 #[cfg(target_os = "linux")]
 fn get_loopback_device(host: &cpal::Host, preferred_source: Option<&str>) -> Result<cpal::Device> {
     use anyhow::Context;
@@ -635,13 +2252,7 @@ fn get_loopback_device(host: &cpal::Host, preferred_source: Option<&str>) -> Res
         log::warn!("Preferred source '{preferred}' not found, falling back to monitor");
     }
 
-    // PulseAudio/PipeWire monitor sources contain "monitor" in the name
-    let monitor_keywords = ["monitor", "Monitor"];
-    if let Some(device) = host.input_devices()?.find(|d| {
-        d.name()
-            .map(|n| monitor_keywords.iter().any(|kw| n.contains(kw)))
-            .unwrap_or(false)
-    }) {
+    if let Some(device) = super::devices::find_monitor_device(host) {
         log::info!(
             "Found monitor device: {}",
             device.name().unwrap_or_default()
@@ -655,6 +2266,22 @@ fn get_loopback_device(host: &cpal::Host, preferred_source: Option<&str>) -> Res
         .context("No input device available. Ensure PulseAudio or PipeWire is running.")
 }
 
+#[cfg(target_os = "windows")]
+fn get_loopback_device(host: &cpal::Host, _preferred_source: Option<&str>) -> Result<cpal::Device> {
+    use anyhow::Context;
+    use cpal::traits::{DeviceTrait, HostTrait};
+
+    // cpal's WASAPI backend records an output device in loopback mode
+    let device = host
+        .default_output_device()
+        .context("No output device available for system audio capture")?;
+    log::info!(
+        "Using loopback of output device: {}",
+        device.name().unwrap_or_default()
+    );
+    Ok(device)
+}
+
 #[cfg(target_os = "macos")]
 fn get_loopback_device(host: &cpal::Host, _preferred_source: Option<&str>) -> Result<cpal::Device> {
     use anyhow::Context;
@@ -666,22 +2293,7 @@ fn get_loopback_device(host: &cpal::Host, _preferred_source: Option<&str>) -> Re
         log::info!("Available input devices: {:?}", names);
     }
 
-    // Look for known virtual audio devices used for system audio capture
-    let virtual_keywords = [
-        "blackhole",
-        "loopback",
-        "soundflower",
-        "virtual",
-        "screencapture",
-    ];
-    if let Some(device) = host.input_devices()?.find(|d| {
-        d.name()
-            .map(|n| {
-                let lower = n.to_lowercase();
-                virtual_keywords.iter().any(|kw| lower.contains(kw))
-            })
-            .unwrap_or(false)
-    }) {
+    if let Some(device) = super::devices::find_monitor_device(host) {
         log::info!(
             "Found virtual audio device: {}",
             device.name().unwrap_or_default()
@@ -691,5 +2303,5 @@ fn get_loopback_device(host: &cpal::Host, _preferred_source: Option<&str>) -> Re
 
     log::warn!("No virtual audio device found. Install BlackHole (https://existential.audio/blackhole/) for system audio capture.");
     host.default_input_device()
-        .context("No input device available. Install BlackHole for system audio capture on macOS.")
+        .context("No input device available. Install BlackHole from Settings → Audio setup for system audio capture on macOS.")
 }
@@ -0,0 +1,245 @@
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioDeviceInfo {
+    /// Stable identifier to remember the device by; see [`DevicePreference`].
+    pub id: String,
+    pub name: String,
+    pub is_input: bool,
+    pub is_output: bool,
+    pub is_default: bool,
+    /// Whether the device can be picked as the system capture device:
+    /// any input, plus output devices on Windows (recorded in loopback mode).
+    pub capturable: bool,
+}
+
+/// Every input and output device of the default audio host.
+///
+/// A device that is both (e.g. BlackHole) is listed once.
+pub fn list_audio_devices() -> Result<Vec<AudioDeviceInfo>> {
+    let host = cpal::default_host();
+    let default_input = host.default_input_device().and_then(|d| d.name().ok());
+    let default_output = host.default_output_device().and_then(|d| d.name().ok());
+    let ids = device_ids();
+    let id = |name: &String| ids.get(name).cloned().unwrap_or_else(|| name.clone());
+
+    let mut devices: Vec<AudioDeviceInfo> = Vec::new();
+    for device in host.input_devices()? {
+        let Ok(name) = device.name() else { continue };
+        if devices.iter().any(|d| d.name == name) {
+            continue;
+        }
+        devices.push(AudioDeviceInfo {
+            id: id(&name),
+            is_default: default_input.as_deref() == Some(name.as_str()),
+            name,
+            is_input: true,
+            is_output: false,
+            capturable: true,
+        });
+    }
+    for device in host.output_devices()? {
+        let Ok(name) = device.name() else { continue };
+        let is_default = default_output.as_deref() == Some(name.as_str());
+        if let Some(existing) = devices.iter_mut().find(|d| d.name == name) {
+            existing.is_output = true;
+            existing.is_default |= is_default;
+            continue;
+        }
+        devices.push(AudioDeviceInfo {
+            id: id(&name),
+            name,
+            is_input: false,
+            is_output: true,
+            is_default,
+            capturable: cfg!(target_os = "windows"),
+        });
+    }
+    Ok(devices)
+}
+
+/// The capture device called `name`, if it is still connected.
+pub fn find_capture_device(host: &cpal::Host, name: &str) -> Option<cpal::Device> {
+    let named = |d: &cpal::Device| d.name().is_ok_and(|n| n == name);
+    if let Some(device) = host.input_devices().ok()?.find(named) {
+        return Some(device);
+    }
+    // cpal's WASAPI backend records an output device in loopback mode
+    if cfg!(target_os = "windows") {
+        return host.output_devices().ok()?.find(named);
+    }
+    None
+}
+
+/// Any monitor, loopback or virtual device that carries system audio, but
+/// never a plain input like a microphone.
+#[cfg(target_os = "linux")]
+pub fn find_monitor_device(host: &cpal::Host) -> Option<cpal::Device> {
+    // PulseAudio/PipeWire monitor sources contain "monitor" in the name
+    let monitor_keywords = ["monitor", "Monitor"];
+    host.input_devices().ok()?.find(|d| {
+        d.name()
+            .map(|n| monitor_keywords.iter().any(|kw| n.contains(kw)))
+            .unwrap_or(false)
+    })
+}
+
+/// Any monitor, loopback or virtual device that carries system audio, but
+/// never a plain input like a microphone.
+#[cfg(target_os = "windows")]
+pub fn find_monitor_device(host: &cpal::Host) -> Option<cpal::Device> {
+    // Every output device can be recorded in loopback mode
+    host.default_output_device()
+        .or_else(|| host.output_devices().ok()?.next())
+}
+
+/// Any monitor, loopback or virtual device that carries system audio, but
+/// never a plain input like a microphone.
+#[cfg(target_os = "macos")]
+pub fn find_monitor_device(host: &cpal::Host) -> Option<cpal::Device> {
+    // Known virtual audio devices used for system audio capture
+    let virtual_keywords = [
+        "blackhole",
+        "loopback",
+        "soundflower",
+        "virtual",
+        "screencapture",
+    ];
+    host.input_devices().ok()?.find(|d| {
+        d.name()
+            .map(|n| {
+                let lower = n.to_lowercase();
+                virtual_keywords.iter().any(|kw| lower.contains(kw))
+            })
+            .unwrap_or(false)
+    })
+}
+
+// --- Remembered capture device ---
+
+/// What to record from when the chosen capture device isn't connected at
+/// record time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceFallback {
+    /// Don't start recording.
+    Fail,
+    /// Pick a device as if none was chosen, which can end up on the default
+    /// input when there is no monitor.
+    #[default]
+    Automatic,
+    /// Any monitor or loopback device, failing rather than recording a
+    /// microphone.
+    Monitor,
+}
+
+/// The capture device picked in settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevicePreference {
+    /// The WASAPI endpoint ID on Windows, which stays the same when the
+    /// device is renamed or moves to another port; the name elsewhere.
+    pub id: String,
+    /// Name when it was picked, shown while the device is missing.
+    pub name: String,
+}
+
+/// Which system capture device a recording used, kept in its session
+/// manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceChoice {
+    /// Name of the device picked in settings.
+    pub requested: String,
+    /// Device recorded from; `None` when DiscRec picked one itself.
+    pub used: Option<String>,
+    /// Fallback applied because the picked device wasn't connected.
+    pub fallback: Option<DeviceFallback>,
+}
+
+/// Find the device `preference` names, applying `fallback` when it's gone.
+pub fn resolve(preference: &DevicePreference, fallback: DeviceFallback) -> Result<DeviceChoice> {
+    let host = cpal::default_host();
+    let name = current_name(&preference.id).unwrap_or_else(|| preference.name.clone());
+    if find_capture_device(&host, &name).is_some() {
+        return Ok(DeviceChoice {
+            requested: preference.name.clone(),
+            used: Some(name),
+            fallback: None,
+        });
+    }
+
+    let used = match fallback {
+        DeviceFallback::Fail => {
+            anyhow::bail!("The capture device '{}' isn't connected", preference.name)
+        }
+        DeviceFallback::Automatic => None,
+        DeviceFallback::Monitor => Some(
+            find_monitor_device(&host)
+                .and_then(|device| device.name().ok())
+                .with_context(|| {
+                    format!(
+                        "The capture device '{}' isn't connected and there is no monitor device to use instead",
+                        preference.name
+                    )
+                })?,
+        ),
+    };
+    log::warn!(
+        "Capture device '{}' not found, using {}",
+        preference.name,
+        used.as_deref().unwrap_or("an automatic pick")
+    );
+    Ok(DeviceChoice {
+        requested: preference.name.clone(),
+        used,
+        fallback: Some(fallback),
+    })
+}
+
+/// Identifiers by device name, for devices whose name isn't their ID.
+#[cfg(target_os = "windows")]
+fn device_ids() -> HashMap<String, String> {
+    endpoints().into_iter().collect()
+}
+
+#[cfg(not(target_os = "windows"))]
+fn device_ids() -> HashMap<String, String> {
+    HashMap::new()
+}
+
+/// The name the device with `id` has now, if it's connected.
+#[cfg(target_os = "windows")]
+fn current_name(id: &str) -> Option<String> {
+    endpoints()
+        .into_iter()
+        .find(|(_, endpoint)| endpoint == id)
+        .map(|(name, _)| name)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn current_name(id: &str) -> Option<String> {
+    Some(id.to_string())
+}
+
+/// Friendly names and endpoint IDs of every active WASAPI device; cpal
+/// names devices by their friendly name.
+#[cfg(target_os = "windows")]
+fn endpoints() -> Vec<(String, String)> {
+    let _ = wasapi::initialize_mta();
+    let Ok(enumerator) = wasapi::DeviceEnumerator::new() else {
+        return Vec::new();
+    };
+    [wasapi::Direction::Capture, wasapi::Direction::Render]
+        .iter()
+        .filter_map(|direction| enumerator.get_device_collection(direction).ok())
+        .flat_map(|collection| {
+            (&collection)
+                .into_iter()
+                .flatten()
+                .filter_map(|device| Some((device.get_friendlyname().ok()?, device.get_id().ok()?)))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
@@ -1,15 +1,105 @@
 use anyhow::{Context, Result};
+use md5::{Digest, Md5};
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+use super::pcm::Quantizer;
+use super::worker;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum AudioFormat {
+    #[default]
     Wav,
     Flac,
     Mp3,
 }
 
+/// Sample format of WAV output.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WavBitDepth {
+    /// 32-bit float, the capture format as is.
+    #[default]
+    Float32,
+    /// 24-bit integer with dither.
+    Int24,
+    /// 16-bit integer with dither, CD quality and half the size of float.
+    Int16,
+}
+
+impl WavBitDepth {
+    fn bits(&self) -> u16 {
+        match self {
+            WavBitDepth::Float32 => 32,
+            WavBitDepth::Int24 => 24,
+            WavBitDepth::Int16 => 16,
+        }
+    }
+}
+
+static WAV_BIT_DEPTH: Mutex<WavBitDepth> = Mutex::new(WavBitDepth::Float32);
+
+/// Use `depth` for WAV files opened from now on.
+pub fn set_wav_bit_depth(depth: WavBitDepth) {
+    *WAV_BIT_DEPTH.lock() = depth;
+}
+
+/// Seconds between flushes of open files to disk; 0 leaves it to finalize.
+static FLUSH_INTERVAL_SECS: AtomicU32 = AtomicU32::new(0);
+/// Samples written between looks at the clock, so per-sample writes stay cheap.
+const FLUSH_CHECK_SAMPLES: usize = 4800;
+
+/// Flush files opened from now on every `secs` seconds, updating their
+/// headers where the format has a length in it, so little is lost if the
+/// app or machine dies mid-recording. `None` flushes only when they finalize.
+pub fn set_flush_interval(secs: Option<u32>) {
+    FLUSH_INTERVAL_SECS.store(secs.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// When a writer is due to flush, by the interval set when it opened.
+pub(crate) struct FlushTimer {
+    every: Option<Duration>,
+    last: Instant,
+    unchecked: usize,
+}
+
+impl FlushTimer {
+    pub(crate) fn new() -> Self {
+        let secs = FLUSH_INTERVAL_SECS.load(Ordering::Relaxed);
+        Self {
+            every: (secs > 0).then(|| Duration::from_secs(secs as u64)),
+            last: Instant::now(),
+            unchecked: 0,
+        }
+    }
+
+    /// Count `samples` more written and tell whether it's time to flush.
+    pub(crate) fn due(&mut self, samples: usize) -> bool {
+        let Some(every) = self.every else {
+            return false;
+        };
+        self.unchecked += samples;
+        if self.unchecked < FLUSH_CHECK_SAMPLES {
+            return false;
+        }
+        self.unchecked = 0;
+        if self.last.elapsed() < every {
+            return false;
+        }
+        self.last = Instant::now();
+        true
+    }
+}
+
+/// Typical FLAC size relative to the PCM it encodes, for voice.
+const FLAC_RATIO: f64 = 0.6;
+const MP3_KBPS: u32 = 192;
+const MP3_BYTES_PER_SEC: u64 = MP3_KBPS as u64 * 1000 / 8;
+
 impl AudioFormat {
     pub fn extension(&self) -> &str {
         match self {
@@ -18,11 +108,45 @@ impl AudioFormat {
             AudioFormat::Mp3 => "mp3",
         }
     }
+
+    /// Rough disk usage per second of one track while it records. Compressed
+    /// formats also keep a raw spill until they finalize, so that is counted.
+    pub fn estimated_bytes_per_sec(&self, channels: u16, sample_rate: u32) -> u64 {
+        let frames = channels as u64 * sample_rate as u64;
+        let raw = frames * std::mem::size_of::<f32>() as u64;
+        match self {
+            AudioFormat::Wav => frames * (WAV_BIT_DEPTH.lock().bits() / 8) as u64,
+            AudioFormat::Flac => {
+                let pcm = frames * (FLAC_BITS_PER_SAMPLE / 8) as u64;
+                raw + (pcm as f64 * FLAC_RATIO) as u64
+            }
+            AudioFormat::Mp3 => raw + MP3_BYTES_PER_SEC,
+        }
+    }
 }
 
 pub trait AudioEncoder: Send {
     fn write_sample(&mut self, sample: f32) -> Result<()>;
+
+    /// Write a block of interleaved samples, e.g. one capture packet.
+    ///
+    /// Encoders that lock, queue or buffer per call override this so a
+    /// packet costs one call instead of one per sample.
+    fn write_samples(&mut self, samples: &[f32]) -> Result<()> {
+        for &sample in samples {
+            self.write_sample(sample)?;
+        }
+        Ok(())
+    }
+
     fn path(&self) -> &str;
+
+    /// Every file written so far, in order. More than one once a segmented
+    /// recording has moved on to its next part.
+    fn paths(&self) -> Vec<String> {
+        vec![self.path().to_string()]
+    }
+
     fn finalize(self: Box<Self>) -> Result<()>;
 }
 
@@ -41,10 +165,61 @@ pub fn create_encoder(
     silence_trim: bool,
 ) -> Result<Box<dyn AudioEncoder>> {
     ensure_parent_dir(path)?;
+    let encoder = open_encoder(path, channels, sample_rate, format, silence_trim)?;
+    // WAV is already raw PCM; compressed formats keep a spill to retry from
+    let encoder: Box<dyn AudioEncoder> = if format == AudioFormat::Wav {
+        encoder
+    } else {
+        let spill = SpillInfo {
+            target: path.to_string(),
+            channels,
+            sample_rate,
+            format,
+            silence_trim,
+        };
+        Box::new(SpillEncoder::new(spill, encoder)?)
+    };
+    // Keep compression off the capture threads
+    worker::pooled(encoder)
+}
+
+/// Like [`create_encoder`], but starts a new numbered file every
+/// `segment_minutes` of audio. `part_path` returns where part `n` (from 1)
+/// is written; see [`part_name`].
+pub fn create_segmented_encoder(
+    channels: u16,
+    sample_rate: u32,
+    format: AudioFormat,
+    silence_trim: bool,
+    segment_minutes: u32,
+    mut part_path: impl FnMut(u32) -> Result<String> + Send + 'static,
+) -> Result<Box<dyn AudioEncoder>> {
+    let open_part: PartOpener = Box::new(move |part| {
+        let path = part_path(part)?;
+        create_encoder(&path, channels, sample_rate, format, false)
+    });
+    let segment_samples =
+        segment_minutes.max(1) as u64 * 60 * sample_rate as u64 * channels.max(1) as u64;
+    let encoder = Box::new(SegmentedEncoder::new(segment_samples, open_part)?);
+    // Trim the recording as a whole; parts after the first keep their silence
+    if silence_trim {
+        Ok(Box::new(SilenceTrimEncoder::new(encoder)))
+    } else {
+        Ok(encoder)
+    }
+}
+
+fn open_encoder(
+    path: &str,
+    channels: u16,
+    sample_rate: u32,
+    format: AudioFormat,
+    silence_trim: bool,
+) -> Result<Box<dyn AudioEncoder>> {
     let inner: Box<dyn AudioEncoder> = match format {
         AudioFormat::Wav => Box::new(WavWriter::new(path, channels, sample_rate)?),
         AudioFormat::Flac => Box::new(FlacWriter::new(path, channels, sample_rate)?),
-        AudioFormat::Mp3 => Box::new(Mp3Writer::new(path, channels, sample_rate)?),
+        AudioFormat::Mp3 => Box::new(Mp3Writer::new(path, channels, sample_rate, MP3_KBPS)?),
     };
     if silence_trim {
         Ok(Box::new(SilenceTrimEncoder::new(inner)))
@@ -53,14 +228,299 @@ pub fn create_encoder(
     }
 }
 
+/// An MP3 encoder at `kbps` (rounded to the nearest bitrate LAME offers), for
+/// converting finished recordings. `sample_rate` must be one MP3 supports.
+pub fn create_mp3_encoder(
+    path: &str,
+    channels: u16,
+    sample_rate: u32,
+    kbps: u32,
+) -> Result<Box<dyn AudioEncoder>> {
+    ensure_parent_dir(path)?;
+    Ok(Box::new(Mp3Writer::new(path, channels, sample_rate, kbps)?))
+}
+
+/// An Ogg Opus encoder at `kbps` for 48 kHz mono or stereo, for converting
+/// finished recordings.
+pub fn create_opus_encoder(path: &str, channels: u16, kbps: u32) -> Result<Box<dyn AudioEncoder>> {
+    ensure_parent_dir(path)?;
+    Ok(Box::new(OpusWriter::new(path, channels, kbps)?))
+}
+
+// --- Raw PCM spill (kept when a compressed encode fails) ---
+
+/// Suffix of the raw interleaved f32 little-endian spill next to an output file.
+pub const SPILL_SUFFIX: &str = ".spill";
+/// Suffix of the spill's JSON sidecar describing how to re-encode it.
+pub const SPILL_INFO_SUFFIX: &str = ".spill.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpillInfo {
+    /// Output file the spill belongs to.
+    pub target: String,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub format: AudioFormat,
+    pub silence_trim: bool,
+}
+
+/// Delete an unfinished output along with any spill, e.g. after a cancelled
+/// conversion. Spills are only worth keeping for recordings.
+pub fn remove_partial(path: &str) {
+    for file in [
+        path.to_string(),
+        format!("{}{}", path, SPILL_SUFFIX),
+        format!("{}{}", path, SPILL_INFO_SUFFIX),
+    ] {
+        let _ = std::fs::remove_file(file);
+    }
+}
+
+/// Tees every sample into a raw spill file. The spill is deleted once the
+/// real encoder finalizes, and kept for `encode_spill` if it fails.
+struct SpillEncoder {
+    inner: Option<Box<dyn AudioEncoder>>,
+    spill: std::io::BufWriter<std::fs::File>,
+    spill_path: String,
+    info_path: String,
+    path: String,
+    inner_error: Option<String>,
+    flush: FlushTimer,
+}
+
+impl SpillEncoder {
+    fn new(info: SpillInfo, inner: Box<dyn AudioEncoder>) -> Result<Self> {
+        let spill_path = format!("{}{}", info.target, SPILL_SUFFIX);
+        let info_path = format!("{}{}", info.target, SPILL_INFO_SUFFIX);
+        std::fs::write(&info_path, serde_json::to_string_pretty(&info)?)
+            .context("Failed to write spill info")?;
+        let file = std::fs::File::create(&spill_path).context("Failed to create spill file")?;
+        Ok(Self {
+            inner: Some(inner),
+            spill: std::io::BufWriter::new(file),
+            spill_path,
+            info_path,
+            path: info.target,
+            inner_error: None,
+            flush: FlushTimer::new(),
+        })
+    }
+
+    fn remove_spill(&self) {
+        let _ = std::fs::remove_file(&self.spill_path);
+        let _ = std::fs::remove_file(&self.info_path);
+    }
+}
+
+impl AudioEncoder for SpillEncoder {
+    fn write_sample(&mut self, sample: f32) -> Result<()> {
+        self.write_samples(std::slice::from_ref(&sample))
+    }
+
+    fn write_samples(&mut self, samples: &[f32]) -> Result<()> {
+        use std::io::Write;
+        let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        self.spill
+            .write_all(&bytes)
+            .context("Failed to write spill")?;
+        if self.flush.due(samples.len()) {
+            self.spill.flush().context("Failed to flush spill")?;
+        }
+
+        // Keep spilling after an encoder error so the audio can be re-encoded later
+        if let Some(inner) = self.inner.as_mut() {
+            if let Err(e) = inner.write_samples(samples) {
+                log::error!("Encoder failed, continuing to spill {}: {}", self.path, e);
+                self.inner_error = Some(e.to_string());
+                self.inner = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn finalize(mut self: Box<Self>) -> Result<()> {
+        use std::io::Write;
+        self.spill.flush().context("Failed to flush spill")?;
+
+        let result = match self.inner.take() {
+            Some(inner) => inner.finalize(),
+            None => Err(anyhow::anyhow!(self.inner_error.take().unwrap_or_default())),
+        };
+        match result {
+            Ok(()) => {
+                self.remove_spill();
+                Ok(())
+            }
+            Err(e) => Err(e.context(format!(
+                "Encoding failed; raw audio kept in {} for retry",
+                self.spill_path
+            ))),
+        }
+    }
+}
+
+/// Re-encode a kept spill over its (broken) output file and delete the spill.
+///
+/// Returns the output path.
+pub fn encode_spill(info_path: &std::path::Path) -> Result<String> {
+    use std::io::Read;
+
+    let info: SpillInfo = serde_json::from_str(
+        &std::fs::read_to_string(info_path).context("Failed to read spill info")?,
+    )
+    .context("Invalid spill info")?;
+    let output = info_path
+        .to_string_lossy()
+        .strip_suffix(SPILL_INFO_SUFFIX)
+        .context("Not a spill info file")?
+        .to_string();
+    let spill_path = format!("{}{}", output, SPILL_SUFFIX);
+
+    let mut encoder = open_encoder(
+        &output,
+        info.channels,
+        info.sample_rate,
+        info.format,
+        info.silence_trim,
+    )?;
+    let mut reader = std::io::BufReader::new(
+        std::fs::File::open(&spill_path).context("Failed to open spill file")?,
+    );
+    let mut bytes = [0u8; 4];
+    loop {
+        match reader.read_exact(&mut bytes) {
+            Ok(()) => encoder.write_sample(f32::from_le_bytes(bytes))?,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e).context("Failed to read spill file"),
+        }
+    }
+    encoder.finalize()?;
+
+    let _ = std::fs::remove_file(&spill_path);
+    let _ = std::fs::remove_file(info_path);
+    log::info!("Re-encoded spill -> {}", output);
+    Ok(output)
+}
+
+// --- Segments (a new numbered file every N minutes) ---
+
+/// Opens the encoder for part `n` (from 1) of a segmented recording.
+type PartOpener = Box<dyn FnMut(u32) -> Result<Box<dyn AudioEncoder>> + Send>;
+
+/// File name of part `part` of `filename`: `discord-<ts>.wav` becomes
+/// `discord-<ts>-part02.wav`.
+pub fn part_name(filename: &str, part: u32) -> String {
+    match filename.rsplit_once('.') {
+        Some((stem, extension)) => format!("{}-part{:02}.{}", stem, part, extension),
+        None => format!("{}-part{:02}", filename, part),
+    }
+}
+
+/// Writes into one part until it holds a segment's worth of samples, then
+/// moves on to the next. The boundary falls between two samples of the same
+/// write, so nothing is lost; full parts finalize on their own thread so the
+/// capture thread doesn't wait for them.
+struct SegmentedEncoder {
+    open_part: PartOpener,
+    current: Box<dyn AudioEncoder>,
+    part: u32,
+    segment_samples: u64,
+    /// Samples the current part still takes.
+    remaining: u64,
+    paths: Vec<String>,
+    closing: Vec<std::thread::JoinHandle<Result<()>>>,
+}
+
+impl SegmentedEncoder {
+    fn new(segment_samples: u64, mut open_part: PartOpener) -> Result<Self> {
+        let current = open_part(1)?;
+        Ok(Self {
+            paths: vec![current.path().to_string()],
+            open_part,
+            current,
+            part: 1,
+            segment_samples,
+            remaining: segment_samples,
+            closing: Vec::new(),
+        })
+    }
+
+    fn next_part(&mut self) -> Result<()> {
+        let next = (self.open_part)(self.part + 1)?;
+        self.part += 1;
+        self.paths.push(next.path().to_string());
+        self.remaining = self.segment_samples;
+        log::info!("Recording continues in {}", next.path());
+
+        let full = std::mem::replace(&mut self.current, next);
+        let path = full.path().to_string();
+        let handle = std::thread::Builder::new()
+            .name("segment-finalize".into())
+            .spawn(move || full.finalize())
+            .with_context(|| format!("Failed to finalize {}", path))?;
+        self.closing.push(handle);
+        Ok(())
+    }
+}
+
+impl AudioEncoder for SegmentedEncoder {
+    fn write_sample(&mut self, sample: f32) -> Result<()> {
+        self.write_samples(std::slice::from_ref(&sample))
+    }
+
+    fn write_samples(&mut self, mut samples: &[f32]) -> Result<()> {
+        while !samples.is_empty() {
+            // Only move on once there is audio for the next part
+            if self.remaining == 0 {
+                self.next_part()?;
+            }
+            let len = self.remaining.min(samples.len() as u64) as usize;
+            self.current.write_samples(&samples[..len])?;
+            self.remaining -= len as u64;
+            samples = &samples[len..];
+        }
+        Ok(())
+    }
+
+    fn path(&self) -> &str {
+        self.current.path()
+    }
+
+    fn paths(&self) -> Vec<String> {
+        self.paths.clone()
+    }
+
+    fn finalize(self: Box<Self>) -> Result<()> {
+        let mut result = self.current.finalize();
+        for handle in self.closing {
+            let closed = handle
+                .join()
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("Finalizing a segment panicked")));
+            if let Err(e) = closed {
+                log::error!("Failed to finalize segment: {:#}", e);
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+}
+
 // --- Silence trim wrapper (leading + trailing) ---
 
-const SILENCE_THRESHOLD: f32 = 0.005;
+pub(crate) const SILENCE_THRESHOLD: f32 = 0.005;
 
 struct SilenceTrimEncoder {
     inner: Box<dyn AudioEncoder>,
     gate_open: bool,
     trailing_buf: Vec<f32>,
+    /// Silence held back beyond this is written out to stay within the memory cap.
+    max_trailing: usize,
 }
 
 impl SilenceTrimEncoder {
@@ -69,8 +529,15 @@ impl SilenceTrimEncoder {
             inner,
             gate_open: false,
             trailing_buf: Vec::new(),
+            max_trailing: worker::max_buffer_samples(),
         }
     }
+
+    fn flush_trailing(&mut self) -> Result<()> {
+        self.inner.write_samples(&self.trailing_buf)?;
+        self.trailing_buf.clear();
+        Ok(())
+    }
 }
 
 impl AudioEncoder for SilenceTrimEncoder {
@@ -87,12 +554,12 @@ impl AudioEncoder for SilenceTrimEncoder {
         } else if is_silent {
             // Might be trailing silence — buffer it
             self.trailing_buf.push(sample);
+            if self.trailing_buf.len() >= self.max_trailing {
+                self.flush_trailing()?;
+            }
         } else {
             // Non-silent after a silent stretch — flush buffer then write
-            for &s in &self.trailing_buf {
-                self.inner.write_sample(s)?;
-            }
-            self.trailing_buf.clear();
+            self.flush_trailing()?;
             self.inner.write_sample(sample)?;
         }
         Ok(())
@@ -102,6 +569,10 @@ impl AudioEncoder for SilenceTrimEncoder {
         self.inner.path()
     }
 
+    fn paths(&self) -> Vec<String> {
+        self.inner.paths()
+    }
+
     fn finalize(self: Box<Self>) -> Result<()> {
         // Discard trailing_buf (it's trailing silence)
         let trimmed = self.trailing_buf.len();
@@ -117,29 +588,45 @@ impl AudioEncoder for SilenceTrimEncoder {
 struct WavWriter {
     writer: hound::WavWriter<std::io::BufWriter<std::fs::File>>,
     path: String,
+    /// Set for integer output.
+    quantizer: Option<Quantizer>,
+    flush: FlushTimer,
 }
 
 impl WavWriter {
     fn new(path: &str, channels: u16, sample_rate: u32) -> Result<Self> {
+        let depth = *WAV_BIT_DEPTH.lock();
         let spec = hound::WavSpec {
             channels,
             sample_rate,
-            bits_per_sample: 32,
-            sample_format: hound::SampleFormat::Float,
+            bits_per_sample: depth.bits(),
+            sample_format: match depth {
+                WavBitDepth::Float32 => hound::SampleFormat::Float,
+                _ => hound::SampleFormat::Int,
+            },
         };
         let writer = hound::WavWriter::create(path, spec).context("Failed to create WAV file")?;
         Ok(Self {
             writer,
             path: path.to_string(),
+            quantizer: (depth != WavBitDepth::Float32).then(|| Quantizer::new(depth.bits() as u32)),
+            flush: FlushTimer::new(),
         })
     }
 }
 
 impl AudioEncoder for WavWriter {
     fn write_sample(&mut self, sample: f32) -> Result<()> {
-        self.writer
-            .write_sample(sample)
-            .context("Failed to write audio sample")
+        let written = match self.quantizer.as_mut() {
+            Some(quantizer) => self.writer.write_sample(quantizer.quantize(sample)),
+            None => self.writer.write_sample(sample),
+        };
+        written.context("Failed to write audio sample")?;
+        if self.flush.due(1) {
+            // Also rewrites the header with the length so far
+            self.writer.flush().context("Failed to flush WAV file")?;
+        }
+        Ok(())
     }
 
     fn path(&self) -> &str {
@@ -153,29 +640,162 @@ impl AudioEncoder for WavWriter {
     }
 }
 
-// --- FLAC encoder (buffers samples, encodes on finalize) ---
+// --- FLAC encoder (streams frames to disk, patches STREAMINFO on finalize) ---
+
+const FLAC_BITS_PER_SAMPLE: usize = 24;
 
 struct FlacWriter {
+    file: std::io::BufWriter<std::fs::File>,
     path: String,
-    channels: u16,
-    sample_rate: u32,
-    samples: Vec<f32>,
+    channels: usize,
+    block_size: usize,
+    config: flacenc::error::Verified<flacenc::config::Encoder>,
+    stream_info: flacenc::component::StreamInfo,
+    pending: Vec<i32>,
+    quantizer: Quantizer,
+    frame_number: usize,
+    total_samples: usize,
+    md5: Md5,
+    flush: FlushTimer,
 }
 
 impl FlacWriter {
     fn new(path: &str, channels: u16, sample_rate: u32) -> Result<Self> {
+        use flacenc::error::Verify;
+        use std::io::Write;
+
+        let config = flacenc::config::Encoder::default()
+            .into_verified()
+            .map_err(|e| anyhow::anyhow!("FLAC config error: {:?}", e))?;
+        let stream_info = flacenc::component::StreamInfo::new(
+            sample_rate as usize,
+            channels as usize,
+            FLAC_BITS_PER_SAMPLE,
+        )
+        .map_err(|e| anyhow::anyhow!("FLAC stream info error: {:?}", e))?;
+
+        let file = std::fs::File::create(path).context("Failed to create FLAC file")?;
+        let mut file = std::io::BufWriter::new(file);
+        // Placeholder header — rewritten with the final STREAMINFO on finalize
+        file.write_all(&flac_header_bytes(&stream_info)?)
+            .context("Failed to write FLAC header")?;
+
+        let block_size = config.block_size;
         Ok(Self {
+            file,
             path: path.to_string(),
-            channels,
-            sample_rate,
-            samples: Vec::new(),
+            channels: channels as usize,
+            block_size,
+            config,
+            stream_info,
+            pending: Vec::with_capacity(block_size * channels as usize),
+            quantizer: Quantizer::new(FLAC_BITS_PER_SAMPLE as u32),
+            frame_number: 0,
+            total_samples: 0,
+            md5: Md5::new(),
+            flush: FlushTimer::new(),
         })
     }
+
+    /// Encode the pending samples as one frame and append it to the file.
+    fn write_frame(&mut self) -> Result<()> {
+        use flacenc::component::BitRepr;
+        use flacenc::source::{Fill, FrameBuf};
+        use std::io::Write;
+
+        let block_len = self.pending.len() / self.channels;
+        let mut framebuf = FrameBuf::with_size(self.channels, block_len)
+            .map_err(|e| anyhow::anyhow!("FLAC frame buffer error: {:?}", e))?;
+        framebuf
+            .fill_interleaved(&self.pending)
+            .map_err(|e| anyhow::anyhow!("FLAC frame buffer error: {:?}", e))?;
+
+        let frame = flacenc::encode_fixed_size_frame(
+            &self.config,
+            &framebuf,
+            self.frame_number,
+            &self.stream_info,
+        )
+        .map_err(|e| anyhow::anyhow!("FLAC encode failed: {:?}", e))?;
+
+        let mut sink = flacenc::bitsink::ByteSink::new();
+        frame
+            .write(&mut sink)
+            .map_err(|e| anyhow::anyhow!("FLAC write failed: {:?}", e))?;
+        self.file
+            .write_all(sink.as_slice())
+            .context("Failed to write FLAC frame")?;
+
+        // STREAMINFO MD5 covers the samples exactly as a decoder returns them:
+        // interleaved, little-endian, 3 bytes per 24-bit sample.
+        let bytes_per_sample = FLAC_BITS_PER_SAMPLE / 8;
+        for s in &self.pending {
+            self.md5.update(&s.to_le_bytes()[..bytes_per_sample]);
+        }
+
+        self.stream_info.update_frame_info(&frame);
+        self.total_samples += block_len;
+        self.frame_number += 1;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Write out the frames so far with their sample count in STREAMINFO.
+    /// The MD5 stays unset until finalize, which decoders take as unknown.
+    fn flush_to_disk(&mut self) -> Result<()> {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let mut stream_info = self.stream_info.clone();
+        stream_info.set_total_samples(self.total_samples);
+        let header = flac_header_bytes(&stream_info)?;
+        let end = self
+            .file
+            .stream_position()
+            .context("Failed to flush FLAC file")?;
+        self.file
+            .seek(SeekFrom::Start(0))
+            .and_then(|_| self.file.write_all(&header))
+            .and_then(|_| self.file.seek(SeekFrom::Start(end)))
+            .and_then(|_| self.file.flush())
+            .context("Failed to flush FLAC file")?;
+        Ok(())
+    }
+}
+
+fn flac_header_bytes(stream_info: &flacenc::component::StreamInfo) -> Result<Vec<u8>> {
+    use flacenc::component::BitRepr;
+
+    let header = flacenc::component::Stream::with_stream_info(stream_info.clone());
+    let mut sink = flacenc::bitsink::ByteSink::new();
+    header
+        .write(&mut sink)
+        .map_err(|e| anyhow::anyhow!("FLAC header write failed: {:?}", e))?;
+    Ok(sink.as_slice().to_vec())
 }
 
 impl AudioEncoder for FlacWriter {
     fn write_sample(&mut self, sample: f32) -> Result<()> {
-        self.samples.push(sample);
+        self.write_samples(std::slice::from_ref(&sample))
+    }
+
+    fn write_samples(&mut self, mut samples: &[f32]) -> Result<()> {
+        let written = samples.len();
+        let block_len = self.block_size * self.channels;
+        // Frames must hold exactly one block, so fill up to the boundary each time
+        while !samples.is_empty() {
+            let take = (block_len - self.pending.len()).min(samples.len());
+            let (head, rest) = samples.split_at(take);
+            let quantizer = &mut self.quantizer;
+            self.pending
+                .extend(head.iter().map(|&s| quantizer.quantize(s)));
+            samples = rest;
+            if self.pending.len() >= block_len {
+                self.write_frame()?;
+            }
+        }
+        if self.flush.due(written) {
+            self.flush_to_disk()?;
+        }
         Ok(())
     }
 
@@ -183,72 +803,194 @@ impl AudioEncoder for FlacWriter {
         &self.path
     }
 
-    fn finalize(self: Box<Self>) -> Result<()> {
-        use flacenc::component::BitRepr;
-        use flacenc::error::Verify;
-
-        let bits_per_sample: usize = 24;
-        let scale = (1i32 << (bits_per_sample - 1)) - 1;
+    fn finalize(mut self: Box<Self>) -> Result<()> {
+        use std::io::{Seek, SeekFrom, Write};
 
-        let int_samples: Vec<i32> = self
-            .samples
-            .iter()
-            .map(|&s| (s.clamp(-1.0, 1.0) * scale as f32) as i32)
-            .collect();
-
-        let config = flacenc::config::Encoder::default()
-            .into_verified()
-            .map_err(|e| anyhow::anyhow!("FLAC config error: {:?}", e))?;
-
-        let source = flacenc::source::MemSource::from_samples(
-            &int_samples,
-            self.channels as usize,
-            bits_per_sample,
-            self.sample_rate as usize,
-        );
-
-        let flac_stream = flacenc::encode_with_fixed_block_size(&config, source, config.block_size)
-            .map_err(|e| anyhow::anyhow!("FLAC encode failed: {:?}", e))?;
+        if !self.pending.is_empty() {
+            // The encoder cannot emit frames shorter than its minimum block
+            // size; pad the tail with silence (and count it) so the MD5 still
+            // matches what a decoder produces.
+            let min_len = flacenc::constant::MIN_BLOCK_SIZE * self.channels;
+            if self.pending.len() < min_len {
+                self.pending.resize(min_len, 0);
+            }
+            self.write_frame()?;
+        }
 
-        let mut sink = flacenc::bitsink::ByteSink::new();
-        flac_stream
-            .write(&mut sink)
-            .map_err(|e| anyhow::anyhow!("FLAC write failed: {:?}", e))?;
+        let digest: [u8; 16] = self.md5.clone().finalize().into();
+        self.stream_info.set_md5_digest(&digest);
+        self.stream_info.set_total_samples(self.total_samples);
+        if self.frame_number != 1 {
+            // Only the last frame may be shorter than the nominal block size
+            self.stream_info
+                .set_block_sizes(self.block_size, self.block_size)
+                .map_err(|e| anyhow::anyhow!("FLAC stream info error: {:?}", e))?;
+        }
+        if self.frame_number == 0 {
+            // No audio at all — frame sizes are "unknown"
+            self.stream_info
+                .set_frame_sizes(0, 0)
+                .map_err(|e| anyhow::anyhow!("FLAC stream info error: {:?}", e))?;
+        }
 
-        std::fs::write(&self.path, sink.as_slice()).context("Failed to write FLAC file")?;
+        let header = flac_header_bytes(&self.stream_info)?;
+        self.file
+            .seek(SeekFrom::Start(0))
+            .and_then(|_| self.file.write_all(&header))
+            .and_then(|_| self.file.flush())
+            .context("Failed to finalize FLAC file")?;
 
         log::info!(
-            "FLAC encoded: {} samples -> {} bytes",
-            self.samples.len(),
-            sink.as_slice().len()
+            "FLAC finalized: {} frames, {} samples per channel, md5 {}",
+            self.frame_number,
+            self.total_samples,
+            digest
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
         );
         Ok(())
     }
 }
 
-// --- MP3 encoder (buffers samples, encodes on finalize via LAME) ---
+// --- MP3 encoder (streams to disk via LAME) ---
+
+/// Samples converted and encoded per LAME call.
+const MP3_CHUNK_SAMPLES: usize = 8192;
 
 struct Mp3Writer {
+    encoder: mp3lame_encoder::Encoder,
+    file: std::io::BufWriter<std::fs::File>,
     path: String,
-    channels: u16,
-    sample_rate: u32,
-    samples: Vec<f32>,
+    channels: usize,
+    pending: Vec<i16>,
+    quantizer: Quantizer,
+    chunk_len: usize,
+    mp3_buffer: Vec<u8>,
+    total_samples: usize,
+    total_bytes: usize,
+    flush: FlushTimer,
 }
 
+/// Bitrates LAME accepts, in kbps.
+const MP3_BITRATES: [(u32, mp3lame_encoder::Bitrate); 16] = {
+    use mp3lame_encoder::Bitrate::*;
+    [
+        (8, Kbps8),
+        (16, Kbps16),
+        (24, Kbps24),
+        (32, Kbps32),
+        (40, Kbps40),
+        (48, Kbps48),
+        (64, Kbps64),
+        (80, Kbps80),
+        (96, Kbps96),
+        (112, Kbps112),
+        (128, Kbps128),
+        (160, Kbps160),
+        (192, Kbps192),
+        (224, Kbps224),
+        (256, Kbps256),
+        (320, Kbps320),
+    ]
+};
+
 impl Mp3Writer {
-    fn new(path: &str, channels: u16, sample_rate: u32) -> Result<Self> {
+    fn new(path: &str, channels: u16, sample_rate: u32, kbps: u32) -> Result<Self> {
+        let (_, bitrate) = MP3_BITRATES
+            .into_iter()
+            .min_by_key(|(rate, _)| rate.abs_diff(kbps))
+            .expect("bitrate table is not empty");
+        let mut builder = mp3lame_encoder::Builder::new()
+            .ok_or_else(|| anyhow::anyhow!("Failed to create MP3 encoder"))?;
+
+        builder
+            .set_sample_rate(sample_rate)
+            .map_err(|e| anyhow::anyhow!("MP3: failed to set sample rate: {:?}", e))?;
+        builder
+            .set_num_channels(channels as u8)
+            .map_err(|e| anyhow::anyhow!("MP3: failed to set channels: {:?}", e))?;
+        builder
+            .set_brate(bitrate)
+            .map_err(|e| anyhow::anyhow!("MP3: failed to set bitrate: {:?}", e))?;
+        builder
+            .set_quality(mp3lame_encoder::Quality::Best)
+            .map_err(|e| anyhow::anyhow!("MP3: failed to set quality: {:?}", e))?;
+
+        let encoder = builder
+            .build()
+            .map_err(|e| anyhow::anyhow!("MP3: failed to build encoder: {:?}", e))?;
+        let file = std::fs::File::create(path).context("Failed to create MP3 file")?;
+
         Ok(Self {
+            encoder,
+            file: std::io::BufWriter::new(file),
             path: path.to_string(),
-            channels,
-            sample_rate,
-            samples: Vec::new(),
+            channels: channels as usize,
+            pending: Vec::with_capacity(MP3_CHUNK_SAMPLES * channels as usize),
+            quantizer: Quantizer::new(16),
+            // Whole frames only, so LAME never sees half a stereo pair
+            chunk_len: MP3_CHUNK_SAMPLES * channels as usize,
+            mp3_buffer: Vec::with_capacity(mp3lame_encoder::max_required_buffer_size(
+                MP3_CHUNK_SAMPLES * channels as usize,
+            )),
+            total_samples: 0,
+            total_bytes: 0,
+            flush: FlushTimer::new(),
         })
     }
+
+    /// Encode the pending samples and append the MP3 data to the file.
+    fn write_chunk(&mut self) -> Result<()> {
+        use mp3lame_encoder::InterleavedPcm;
+        use std::io::Write;
+
+        self.mp3_buffer.clear();
+        self.mp3_buffer
+            .reserve(mp3lame_encoder::max_required_buffer_size(
+                self.pending.len(),
+            ));
+        self.encoder
+            .encode_to_vec(InterleavedPcm(&self.pending), &mut self.mp3_buffer)
+            .map_err(|e| anyhow::anyhow!("MP3 encode failed: {:?}", e))?;
+        self.file
+            .write_all(&self.mp3_buffer)
+            .context("Failed to write MP3 file")?;
+
+        self.total_samples += self.pending.len();
+        self.total_bytes += self.mp3_buffer.len();
+        self.pending.clear();
+        Ok(())
+    }
 }
 
 impl AudioEncoder for Mp3Writer {
     fn write_sample(&mut self, sample: f32) -> Result<()> {
-        self.samples.push(sample);
+        self.write_samples(std::slice::from_ref(&sample))
+    }
+
+    fn write_samples(&mut self, mut samples: &[f32]) -> Result<()> {
+        let written = samples.len();
+        while !samples.is_empty() {
+            let take = (self.chunk_len - self.pending.len()).min(samples.len());
+            let (head, rest) = samples.split_at(take);
+            let quantizer = &mut self.quantizer;
+            self.pending
+                .extend(head.iter().map(|&s| quantizer.quantize(s) as i16));
+            samples = rest;
+            if self.pending.len() >= self.chunk_len {
+                self.write_chunk()?;
+            }
+        }
+        if self.flush.due(written) {
+            use std::io::Write;
+            // Encode what's pending unless it ends mid-frame; LAME keeps
+            // back less than a frame of its own
+            if !self.pending.is_empty() && self.pending.len() % self.channels == 0 {
+                self.write_chunk()?;
+            }
+            self.file.flush().context("Failed to flush MP3 file")?;
+        }
         Ok(())
     }
 
@@ -256,61 +998,205 @@ impl AudioEncoder for Mp3Writer {
         &self.path
     }
 
-    fn finalize(self: Box<Self>) -> Result<()> {
-        use mp3lame_encoder::{Builder, FlushNoGap, InterleavedPcm};
+    fn finalize(mut self: Box<Self>) -> Result<()> {
+        use mp3lame_encoder::FlushNoGap;
+        use std::io::Write;
 
-        let mut builder =
-            Builder::new().ok_or_else(|| anyhow::anyhow!("Failed to create MP3 encoder"))?;
+        if !self.pending.is_empty() {
+            // Complete a trailing partial frame with silence
+            let frames = self.pending.len().div_ceil(self.channels);
+            self.pending.resize(frames * self.channels, 0);
+            self.write_chunk()?;
+        }
 
-        builder
-            .set_sample_rate(self.sample_rate)
-            .map_err(|e| anyhow::anyhow!("MP3: failed to set sample rate: {:?}", e))?;
-        builder
-            .set_num_channels(self.channels as u8)
-            .map_err(|e| anyhow::anyhow!("MP3: failed to set channels: {:?}", e))?;
-        builder
-            .set_brate(mp3lame_encoder::Bitrate::Kbps192)
-            .map_err(|e| anyhow::anyhow!("MP3: failed to set bitrate: {:?}", e))?;
-        builder
-            .set_quality(mp3lame_encoder::Quality::Best)
-            .map_err(|e| anyhow::anyhow!("MP3: failed to set quality: {:?}", e))?;
+        self.mp3_buffer.clear();
+        self.mp3_buffer
+            .reserve(mp3lame_encoder::max_required_buffer_size(0));
+        self.encoder
+            .flush_to_vec::<FlushNoGap>(&mut self.mp3_buffer)
+            .map_err(|e| anyhow::anyhow!("MP3 flush failed: {:?}", e))?;
+        self.file
+            .write_all(&self.mp3_buffer)
+            .and_then(|_| self.file.flush())
+            .context("Failed to write MP3 file")?;
+        self.total_bytes += self.mp3_buffer.len();
 
-        let mut encoder = builder
-            .build()
-            .map_err(|e| anyhow::anyhow!("MP3: failed to build encoder: {:?}", e))?;
+        log::info!(
+            "MP3 encoded: {} samples -> {} bytes",
+            self.total_samples,
+            self.total_bytes
+        );
+        Ok(())
+    }
+}
 
-        // Convert f32 samples to i16 for LAME
-        let int_samples: Vec<i16> = self
-            .samples
-            .iter()
-            .map(|&s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
-            .collect();
+// --- Ogg Opus encoder (for converting finished recordings) ---
 
-        let input = InterleavedPcm(&int_samples);
-        let mut mp3_buffer =
-            Vec::with_capacity(mp3lame_encoder::max_required_buffer_size(int_samples.len()));
+/// Samples per channel in one 20 ms Opus frame at 48 kHz.
+const OPUS_FRAME_SAMPLES: usize = 960;
+/// Room for the largest packet libopus produces.
+const OPUS_MAX_PACKET: usize = 4000;
+const OPUS_SERIAL: u32 = 0x4449_5343; // "DISC"
 
-        let encoded_size = encoder
-            .encode(input, mp3_buffer.spare_capacity_mut())
-            .map_err(|e| anyhow::anyhow!("MP3 encode failed: {:?}", e))?;
-        unsafe {
-            mp3_buffer.set_len(mp3_buffer.len().wrapping_add(encoded_size));
+/// The RFC 7845 identification and comment headers of an Ogg Opus stream.
+pub fn ogg_opus_headers(channels: u8, pre_skip: u16) -> [Vec<u8>; 2] {
+    let mut head = Vec::with_capacity(19);
+    head.extend_from_slice(b"OpusHead");
+    head.push(1); // version
+    head.push(channels);
+    head.extend_from_slice(&pre_skip.to_le_bytes());
+    head.extend_from_slice(&48000u32.to_le_bytes()); // input sample rate
+    head.extend_from_slice(&0i16.to_le_bytes()); // output gain
+    head.push(0); // channel mapping family
+
+    let vendor = b"DiscRec";
+    let mut tags = Vec::new();
+    tags.extend_from_slice(b"OpusTags");
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor);
+    tags.extend_from_slice(&0u32.to_le_bytes()); // no user comments
+    [head, tags]
+}
+
+struct OpusWriter {
+    encoder: audiopus::coder::Encoder,
+    writer: ogg::PacketWriter<'static, std::io::BufWriter<std::fs::File>>,
+    path: String,
+    channels: usize,
+    pre_skip: u64,
+    pending: Vec<f32>,
+    packet: Vec<u8>,
+    /// Samples per channel handed to the encoder, without final padding.
+    samples: u64,
+    /// Held back by one packet so the final one can be flagged end-of-stream.
+    held: Option<Vec<u8>>,
+    flush: FlushTimer,
+}
+
+impl OpusWriter {
+    fn new(path: &str, channels: u16, kbps: u32) -> Result<Self> {
+        use audiopus::{Application, Bitrate, Channels, SampleRate};
+
+        let layout = match channels {
+            1 => Channels::Mono,
+            2 => Channels::Stereo,
+            n => anyhow::bail!("Opus: {} channels aren't supported", n),
+        };
+        let mut encoder =
+            audiopus::coder::Encoder::new(SampleRate::Hz48000, layout, Application::Audio)
+                .map_err(|e| anyhow::anyhow!("Failed to create Opus encoder: {}", e))?;
+        encoder
+            .set_bitrate(Bitrate::BitsPerSecond(kbps.clamp(6, 510) as i32 * 1000))
+            .map_err(|e| anyhow::anyhow!("Opus: failed to set bitrate: {}", e))?;
+        let pre_skip = encoder
+            .lookahead()
+            .map_err(|e| anyhow::anyhow!("Opus: failed to read lookahead: {}", e))?;
+
+        let file = std::fs::File::create(path).context("Failed to create Opus file")?;
+        let mut writer = ogg::PacketWriter::new(std::io::BufWriter::new(file));
+        for header in ogg_opus_headers(channels as u8, pre_skip as u16) {
+            writer
+                .write_packet(header, OPUS_SERIAL, ogg::PacketWriteEndInfo::EndPage, 0)
+                .context("Failed to write Opus header")?;
         }
 
-        let flush_size = encoder
-            .flush::<FlushNoGap>(mp3_buffer.spare_capacity_mut())
-            .map_err(|e| anyhow::anyhow!("MP3 flush failed: {:?}", e))?;
-        unsafe {
-            mp3_buffer.set_len(mp3_buffer.len().wrapping_add(flush_size));
+        Ok(Self {
+            encoder,
+            writer,
+            path: path.to_string(),
+            channels: channels as usize,
+            pre_skip: pre_skip as u64,
+            pending: Vec::with_capacity(OPUS_FRAME_SAMPLES * channels as usize),
+            packet: vec![0; OPUS_MAX_PACKET],
+            samples: 0,
+            held: None,
+            flush: FlushTimer::new(),
+        })
+    }
+
+    /// Encode one full frame from `pending`, writing the packet before it.
+    /// When a flush is due that packet ends its page, so it reaches the disk.
+    fn encode_frame(&mut self) -> Result<()> {
+        use std::io::Write;
+
+        let flush = self.flush.due(self.pending.len());
+        let len = self
+            .encoder
+            .encode_float(&self.pending, &mut self.packet)
+            .map_err(|e| anyhow::anyhow!("Opus encode failed: {}", e))?;
+        self.pending.clear();
+        if let Some(data) = self.held.replace(self.packet[..len].to_vec()) {
+            // Granule positions count output samples, including pre-skip
+            let granule = self.pre_skip + self.samples - OPUS_FRAME_SAMPLES as u64;
+            let end = if flush {
+                ogg::PacketWriteEndInfo::EndPage
+            } else {
+                ogg::PacketWriteEndInfo::NormalPacket
+            };
+            self.writer
+                .write_packet(data, OPUS_SERIAL, end, granule)
+                .context("Failed to write Opus packet")?;
+            if flush {
+                self.writer
+                    .inner_mut()
+                    .flush()
+                    .context("Failed to flush Opus file")?;
+            }
         }
+        Ok(())
+    }
+}
 
-        std::fs::write(&self.path, &mp3_buffer).context("Failed to write MP3 file")?;
+impl AudioEncoder for OpusWriter {
+    fn write_sample(&mut self, sample: f32) -> Result<()> {
+        self.write_samples(std::slice::from_ref(&sample))
+    }
 
-        log::info!(
-            "MP3 encoded: {} samples -> {} bytes",
-            self.samples.len(),
-            mp3_buffer.len()
-        );
+    fn write_samples(&mut self, mut samples: &[f32]) -> Result<()> {
+        let frame_len = OPUS_FRAME_SAMPLES * self.channels;
+        while !samples.is_empty() {
+            let take = (frame_len - self.pending.len()).min(samples.len());
+            let (head, rest) = samples.split_at(take);
+            self.pending.extend_from_slice(head);
+            samples = rest;
+            if self.pending.len() == frame_len {
+                self.samples += OPUS_FRAME_SAMPLES as u64;
+                self.encode_frame()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn finalize(mut self: Box<Self>) -> Result<()> {
+        use std::io::Write;
+
+        // Pad the last frame with silence; its granule position marks where
+        // the real audio ends
+        let real_samples = self.samples + (self.pending.len() / self.channels) as u64;
+        if !self.pending.is_empty() {
+            self.pending.resize(OPUS_FRAME_SAMPLES * self.channels, 0.0);
+            self.samples += OPUS_FRAME_SAMPLES as u64;
+            self.encode_frame()?;
+        }
+        if let Some(data) = self.held.take() {
+            self.writer
+                .write_packet(
+                    data,
+                    OPUS_SERIAL,
+                    ogg::PacketWriteEndInfo::EndStream,
+                    self.pre_skip + real_samples,
+                )
+                .context("Failed to write Opus packet")?;
+        }
+        self.writer
+            .inner_mut()
+            .flush()
+            .context("Failed to finalize Opus file")?;
+        log::info!("Opus encoded: {} samples -> {}", real_samples, self.path);
         Ok(())
     }
 }
@@ -0,0 +1,160 @@
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use super::encoder::AudioEncoder;
+use super::source::CaptureSource;
+
+/// Comfortable average loudness for voice recordings.
+const TARGET_RMS_DB: f32 = -20.0;
+/// Highest peak a suggested gain may push the signal to.
+const PEAK_CEILING_DB: f32 = -1.0;
+/// Below this the source is treated as silent.
+const SILENCE_FLOOR_DB: f32 = -60.0;
+/// Reported instead of -inf for digital silence.
+const MIN_DB: f32 = -96.0;
+const CLIP_LEVEL: f32 = 0.999;
+/// Suggestions smaller than this are not worth acting on.
+const GAIN_TOLERANCE_DB: f32 = 3.0;
+/// Share of the held peak kept per metering step.
+const PEAK_DECAY: f32 = 0.95;
+/// Held peaks below this drop straight to the current level.
+const PEAK_FLOOR: f32 = 0.001;
+
+/// Update the peak meter in `bits` with the loudest sample of the latest
+/// audio. Louder audio raises it at once and it otherwise decays slowly, so
+/// a transient is still there when the next level event is pushed.
+pub fn hold_peak(bits: &AtomicU32, peak: f32) {
+    let held = f32::from_bits(bits.load(Ordering::Relaxed)) * PEAK_DECAY;
+    let level = if held > PEAK_FLOOR {
+        held.max(peak)
+    } else {
+        peak
+    };
+    bits.store(level.to_bits(), Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LevelStats {
+    peak: f32,
+    sum_squares: f64,
+    samples: u64,
+    clipped: u64,
+}
+
+/// Measures levels instead of writing a file; used for the pre-recording preview.
+pub struct LevelMeter {
+    stats: LevelStats,
+    shared: Arc<Mutex<LevelStats>>,
+}
+
+impl LevelMeter {
+    pub fn new(shared: Arc<Mutex<LevelStats>>) -> Self {
+        Self {
+            stats: LevelStats::default(),
+            shared,
+        }
+    }
+}
+
+impl AudioEncoder for LevelMeter {
+    fn write_sample(&mut self, sample: f32) -> Result<()> {
+        let abs = sample.abs();
+        self.stats.peak = self.stats.peak.max(abs);
+        self.stats.sum_squares += (sample as f64) * (sample as f64);
+        self.stats.samples += 1;
+        if abs >= CLIP_LEVEL {
+            self.stats.clipped += 1;
+        }
+        Ok(())
+    }
+
+    fn path(&self) -> &str {
+        "level preview"
+    }
+
+    fn finalize(self: Box<Self>) -> Result<()> {
+        *self.shared.lock() = self.stats;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LevelReport {
+    pub source: String,
+    pub peak_db: f32,
+    pub rms_db: f32,
+    pub clipped_samples: u64,
+    /// Change to apply to the source's volume, in dB; 0 when levels are fine.
+    pub suggested_gain_db: f32,
+    pub advice: String,
+    /// Things to know about the source beyond its levels.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+}
+
+impl LevelReport {
+    pub fn from_stats(source: CaptureSource, stats: &LevelStats) -> Self {
+        let peak_db = to_db(stats.peak);
+        let rms_db = if stats.samples == 0 {
+            MIN_DB
+        } else {
+            to_db((stats.sum_squares / stats.samples as f64).sqrt() as f32)
+        };
+
+        let (gain, advice) = if rms_db <= SILENCE_FLOOR_DB {
+            (
+                0.0,
+                "No signal detected. Check that the source is playing audio.".to_string(),
+            )
+        } else if stats.clipped > 0 {
+            let gain = (PEAK_CEILING_DB - peak_db).min(-GAIN_TOLERANCE_DB);
+            (
+                gain,
+                format!(
+                    "Clipping detected. Lower the volume by about {:.0} dB.",
+                    -gain
+                ),
+            )
+        } else {
+            let gain = (TARGET_RMS_DB - rms_db).min(PEAK_CEILING_DB - peak_db);
+            if gain >= GAIN_TOLERANCE_DB {
+                (
+                    gain,
+                    format!("Too quiet. Raise the volume by about {:.0} dB.", gain),
+                )
+            } else if gain <= -GAIN_TOLERANCE_DB {
+                (
+                    gain,
+                    format!("Quite loud. Lower the volume by about {:.0} dB.", -gain),
+                )
+            } else {
+                (0.0, "Levels look good.".to_string())
+            }
+        };
+
+        Self {
+            source: source.to_string(),
+            peak_db: round_tenth(peak_db),
+            rms_db: round_tenth(rms_db),
+            clipped_samples: stats.clipped,
+            suggested_gain_db: round_tenth(gain),
+            advice,
+            notes: Vec::new(),
+        }
+    }
+}
+
+fn to_db(amplitude: f32) -> f32 {
+    if amplitude <= 0.0 {
+        MIN_DB
+    } else {
+        (20.0 * amplitude.log10()).max(MIN_DB)
+    }
+}
+
+fn round_tenth(value: f32) -> f32 {
+    (value * 10.0).round() / 10.0
+}
@@ -0,0 +1,250 @@
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use super::encoder::AudioEncoder;
+
+pub const MIX_SAMPLE_RATE: u32 = 48000;
+pub const MIX_CHANNELS: u16 = 2;
+/// How far one input may run ahead before the others are treated as silent.
+/// Loopback devices deliver nothing at all while nothing is playing.
+const MAX_LAG_FRAMES: usize = MIX_SAMPLE_RATE as usize / 5;
+/// Frames an input converts before handing them to the shared mix.
+const PUSH_FRAMES: usize = 480;
+
+type Frame = [f32; 2];
+
+struct MixState {
+    encoder: Option<Box<dyn AudioEncoder>>,
+    queues: Vec<VecDeque<Frame>>,
+    open: Vec<bool>,
+    error: Option<String>,
+}
+
+impl MixState {
+    /// Mix and write every frame that can no longer change.
+    fn drain(&mut self, flush: bool) -> Result<()> {
+        if let Some(e) = &self.error {
+            anyhow::bail!(e.clone());
+        }
+        let longest = self.queues.iter().map(VecDeque::len).max().unwrap_or(0);
+        // Closed inputs never get more data, so they don't hold the mix back
+        let ready = self
+            .queues
+            .iter()
+            .zip(&self.open)
+            .filter(|(_, &open)| open)
+            .map(|(q, _)| q.len())
+            .min()
+            .unwrap_or(longest);
+        let frames = if flush {
+            longest
+        } else {
+            ready.max(longest.saturating_sub(MAX_LAG_FRAMES))
+        };
+        if frames == 0 {
+            return Ok(());
+        }
+
+        let Some(encoder) = self.encoder.as_mut() else {
+            return Ok(());
+        };
+        let mut out = Vec::with_capacity(frames * 2);
+        for _ in 0..frames {
+            let mut mixed = [0f32; 2];
+            for queue in self.queues.iter_mut() {
+                if let Some(frame) = queue.pop_front() {
+                    mixed[0] += frame[0];
+                    mixed[1] += frame[1];
+                }
+            }
+            out.extend(mixed.map(|sample| sample.clamp(-1.0, 1.0)));
+        }
+        if let Err(e) = encoder.write_samples(&out) {
+            self.error = Some(e.to_string());
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+/// Mixes several capture sources into one 48 kHz stereo file.
+#[derive(Clone)]
+pub struct Mixer {
+    state: Arc<Mutex<MixState>>,
+    path: String,
+}
+
+impl Mixer {
+    pub fn new(encoder: Box<dyn AudioEncoder>, inputs: usize) -> Self {
+        let path = encoder.path().to_string();
+        Self {
+            state: Arc::new(Mutex::new(MixState {
+                encoder: Some(encoder),
+                queues: vec![VecDeque::new(); inputs],
+                open: vec![true; inputs],
+                error: None,
+            })),
+            path,
+        }
+    }
+
+    /// Sink for input `index`, in that source's own channel count and rate.
+    pub fn input(&self, index: usize, channels: u16, sample_rate: u32) -> MixInput {
+        MixInput {
+            mixer: self.clone(),
+            index,
+            channels: channels.max(1) as usize,
+            step: sample_rate as f64 / MIX_SAMPLE_RATE as f64,
+            frame: Vec::with_capacity(channels as usize),
+            prev: [0.0; 2],
+            phase: 0.0,
+            pending: Vec::with_capacity(PUSH_FRAMES * 2),
+        }
+    }
+
+    /// Write out everything still queued and finalize the file.
+    ///
+    /// Call once all inputs have stopped.
+    pub fn finalize(&self) -> Result<String> {
+        let encoder = {
+            let mut state = self.state.lock();
+            state.open.iter_mut().for_each(|open| *open = false);
+            let drained = state.drain(true);
+            let encoder = state.encoder.take();
+            drained.map(|_| encoder)?
+        };
+        if let Some(encoder) = encoder {
+            encoder.finalize()?;
+        }
+        log::info!("Mixed recording saved: {}", self.path);
+        Ok(self.path.clone())
+    }
+}
+
+/// One source's feed into a [`Mixer`]: downmixes to stereo and resamples
+/// to 48 kHz by linear interpolation.
+pub struct MixInput {
+    mixer: Mixer,
+    index: usize,
+    channels: usize,
+    /// Source frames per output frame.
+    step: f64,
+    frame: Vec<f32>,
+    prev: Frame,
+    phase: f64,
+    pending: Vec<Frame>,
+}
+
+impl MixInput {
+    fn push_frame(&mut self, frame: Frame) {
+        if self.step == 1.0 {
+            self.pending.push(frame);
+            return;
+        }
+        while self.phase < 1.0 {
+            let t = self.phase as f32;
+            self.pending.push([
+                self.prev[0] + (frame[0] - self.prev[0]) * t,
+                self.prev[1] + (frame[1] - self.prev[1]) * t,
+            ]);
+            self.phase += self.step;
+        }
+        self.phase -= 1.0;
+        self.prev = frame;
+    }
+
+    fn send(&mut self) -> Result<()> {
+        let mut state = self.mixer.state.lock();
+        state.queues[self.index].extend(self.pending.drain(..));
+        state.drain(false)
+    }
+}
+
+impl AudioEncoder for MixInput {
+    fn write_sample(&mut self, sample: f32) -> Result<()> {
+        self.frame.push(sample);
+        if self.frame.len() < self.channels {
+            return Ok(());
+        }
+        // Mono is centred; beyond stereo only front left/right are kept
+        let stereo = match self.frame.as_slice() {
+            [mono] => [*mono, *mono],
+            [left, right, ..] => [*left, *right],
+            [] => unreachable!(),
+        };
+        self.frame.clear();
+        self.push_frame(stereo);
+
+        if self.pending.len() >= PUSH_FRAMES {
+            self.send()?;
+        }
+        Ok(())
+    }
+
+    fn path(&self) -> &str {
+        &self.mixer.path
+    }
+
+    /// Hand over the remaining audio; the file is finalized by [`Mixer::finalize`].
+    fn finalize(mut self: Box<Self>) -> Result<()> {
+        self.send()
+    }
+}
+
+impl Drop for MixInput {
+    fn drop(&mut self) {
+        // A failed capture must not hold back the other inputs
+        self.mixer.state.lock().open[self.index] = false;
+    }
+}
+
+/// Converts source frames to stereo at the output rate by linear
+/// interpolation.
+pub struct Resampler {
+    channels: usize,
+    /// Source frames per output frame.
+    step: f64,
+    prev: Frame,
+    phase: f64,
+}
+
+impl Resampler {
+    pub fn new(channels: usize, source_rate: u32, output_rate: u32) -> Self {
+        Self {
+            channels,
+            step: source_rate as f64 / output_rate as f64,
+            prev: [0.0; 2],
+            phase: 0.0,
+        }
+    }
+
+    /// Add one interleaved source frame, appending whatever output frames
+    /// it completes to `out`.
+    pub fn push(&mut self, frame: &[f32], out: &mut Vec<Frame>) {
+        if frame.len() < self.channels {
+            return;
+        }
+        // Mono is centred; beyond stereo only front left/right are kept
+        let frame = match frame {
+            [mono] => [*mono, *mono],
+            [left, right, ..] => [*left, *right],
+            [] => return,
+        };
+        if self.step == 1.0 {
+            out.push(frame);
+            return;
+        }
+        while self.phase < 1.0 {
+            let t = self.phase as f32;
+            out.push([
+                self.prev[0] + (frame[0] - self.prev[0]) * t,
+                self.prev[1] + (frame[1] - self.prev[1]) * t,
+            ]);
+            self.phase += self.step;
+        }
+        self.phase -= 1.0;
+        self.prev = frame;
+    }
+}
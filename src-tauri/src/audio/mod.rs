@@ -1,2 +1,11 @@
 pub mod capture;
+pub mod devices;
 pub mod encoder;
+pub mod levels;
+pub mod mixer;
+pub mod pcm;
+pub mod playback;
+pub mod postprocess;
+pub mod source;
+pub mod virtual_device;
+pub mod worker;
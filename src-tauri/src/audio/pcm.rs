@@ -0,0 +1,134 @@
+//! Conversion between float samples (-1.0..1.0) and signed integer PCM.
+//!
+//! Full scale is 2^(bits-1) in both directions, so the most negative integer
+//! is exactly -1.0 and back, and +1.0 clips to the most positive integer.
+//! Every reader and writer goes through here so all formats agree on levels.
+
+/// Float value of one step above full scale for a `bits`-bit sample.
+fn full_scale(bits: u32) -> f32 {
+    (1u64 << (bits.clamp(1, 32) - 1)) as f32
+}
+
+/// A `bits`-bit integer sample as float.
+pub fn to_float(sample: i32, bits: u32) -> f32 {
+    sample as f32 / full_scale(bits)
+}
+
+/// A 16-bit sample (Discord voice, most capture devices) as float.
+pub fn i16_to_float(sample: i16) -> f32 {
+    to_float(sample as i32, 16)
+}
+
+/// `steps` (a sample times full scale) rounded to an integer in range.
+fn clip(steps: f32, scale: f32) -> i32 {
+    steps.round().clamp(-scale, scale - 1.0) as i32
+}
+
+/// A float sample as 16-bit, rounded without dither, for live output where
+/// the samples are played rather than kept.
+pub fn float_to_i16(sample: f32) -> i16 {
+    let scale = full_scale(16);
+    clip(sample * scale, scale) as i16
+}
+
+/// Converts float samples to `bits`-bit integers with TPDF dither: two
+/// uniform random values of up to one step each are added before rounding,
+/// so quiet passages turn into faint noise instead of truncation distortion.
+pub struct Quantizer {
+    scale: f32,
+    rng: u32,
+}
+
+impl Quantizer {
+    pub fn new(bits: u32) -> Self {
+        Self {
+            scale: full_scale(bits),
+            rng: 0x9E37_79B9,
+        }
+    }
+
+    /// Uniform in [0, 1), from a xorshift generator.
+    fn uniform(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    pub fn quantize(&mut self, sample: f32) -> i32 {
+        let dither = self.uniform() - self.uniform();
+        clip(sample * self.scale + dither, self.scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The conversion without dither.
+    fn to_int(sample: f32, bits: u32) -> i32 {
+        clip(sample * full_scale(bits), full_scale(bits))
+    }
+
+    #[test]
+    fn full_scale_is_symmetric() {
+        assert_eq!(to_int(-1.0, 16), i16::MIN as i32);
+        assert_eq!(to_int(1.0, 16), i16::MAX as i32);
+        assert_eq!(to_int(-1.0, 24), -(1 << 23));
+        assert_eq!(to_int(1.0, 24), (1 << 23) - 1);
+        assert_eq!(float_to_i16(-1.0), i16::MIN);
+        assert_eq!(float_to_i16(1.0), i16::MAX);
+        assert_eq!(i16_to_float(i16::MIN), -1.0);
+        assert_eq!(to_float(-(1 << 23), 24), -1.0);
+    }
+
+    #[test]
+    fn out_of_range_clips() {
+        assert_eq!(to_int(1.5, 16), i16::MAX as i32);
+        assert_eq!(to_int(-1.5, 16), i16::MIN as i32);
+        assert_eq!(float_to_i16(1.5), i16::MAX);
+        assert_eq!(float_to_i16(-1.5), i16::MIN);
+        assert_eq!(to_int(f32::INFINITY, 24), (1 << 23) - 1);
+        assert_eq!(to_int(f32::NEG_INFINITY, 24), -(1 << 23));
+    }
+
+    #[test]
+    fn every_16_bit_value_round_trips() {
+        for sample in i16::MIN..=i16::MAX {
+            assert_eq!(to_int(i16_to_float(sample), 16), sample as i32);
+            assert_eq!(float_to_i16(i16_to_float(sample)), sample);
+        }
+    }
+
+    #[test]
+    fn levels_agree_across_bit_depths() {
+        for sample in [-32768i32, -12345, -1, 0, 1, 777, 32767] {
+            assert_eq!(to_int(to_float(sample, 16), 24), sample << 8);
+        }
+    }
+
+    #[test]
+    fn dither_stays_within_one_step() {
+        let mut quantizer = Quantizer::new(16);
+        for i in -1000..=1000 {
+            let sample = i as f32 / 1000.0;
+            let plain = to_int(sample, 16);
+            let dithered = quantizer.quantize(sample);
+            assert!((dithered - plain).abs() <= 1, "{} -> {}", sample, dithered);
+        }
+        for _ in 0..1000 {
+            assert!((-1..=1).contains(&quantizer.quantize(0.0)));
+        }
+    }
+
+    #[test]
+    fn dither_keeps_sub_step_levels_on_average() {
+        // A quarter step truncates to silence but should survive as an average
+        let mut quantizer = Quantizer::new(16);
+        let sample = 0.25 / 32768.0;
+        let runs = 100_000;
+        let sum: i64 = (0..runs).map(|_| quantizer.quantize(sample) as i64).sum();
+        let mean = sum as f64 / runs as f64;
+        assert!((mean - 0.25).abs() < 0.02, "mean {}", mean);
+    }
+}
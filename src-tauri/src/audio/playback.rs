@@ -0,0 +1,424 @@
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::SampleFormat;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use super::mixer::Resampler;
+use super::pcm::float_to_i16;
+use crate::library::{decode, frame_count, probe, recording_format};
+
+pub const POSITION: &str = "playback://position";
+pub const ENDED: &str = "playback://ended";
+
+/// Minimum time between position events.
+const POSITION_INTERVAL: Duration = Duration::from_millis(250);
+/// Decoded audio kept ahead of the output, in seconds.
+const BUFFER_SECS: usize = 2;
+
+type Frame = [f32; 2];
+
+/// Where playback is at, as returned by the playback commands and sent with
+/// every position event.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PlaybackStatus {
+    /// The loaded recording; `None` when nothing is.
+    pub path: Option<String>,
+    pub playing: bool,
+    pub position_secs: f64,
+    pub duration_secs: Option<f64>,
+}
+
+enum PlayerMsg {
+    Seek(f64),
+    Stop,
+}
+
+/// State shared between the decoder thread, the output callback and commands.
+struct Shared {
+    /// Stereo frames at the output rate, ready to play.
+    buffer: Mutex<VecDeque<Frame>>,
+    paused: AtomicBool,
+    /// Output frames played since `base_secs`.
+    played: AtomicU64,
+    base_secs: Mutex<f64>,
+    output_rate: AtomicU64,
+    ended: AtomicBool,
+}
+
+impl Shared {
+    fn position_secs(&self) -> f64 {
+        let rate = self.output_rate.load(Ordering::Relaxed).max(1);
+        *self.base_secs.lock() + self.played.load(Ordering::Relaxed) as f64 / rate as f64
+    }
+
+    /// Drop what is buffered and count from `secs`.
+    fn restart_at(&self, secs: f64) {
+        self.buffer.lock().clear();
+        *self.base_secs.lock() = secs;
+        self.played.store(0, Ordering::Relaxed);
+    }
+}
+
+struct Playback {
+    path: String,
+    duration_secs: Option<f64>,
+    shared: Arc<Shared>,
+    commands: mpsc::Sender<PlayerMsg>,
+}
+
+impl Playback {
+    fn status(&self) -> PlaybackStatus {
+        let ended = self.shared.ended.load(Ordering::Relaxed);
+        let position = self.shared.position_secs();
+        PlaybackStatus {
+            path: Some(self.path.clone()),
+            playing: !ended && !self.shared.paused.load(Ordering::Relaxed),
+            position_secs: self
+                .duration_secs
+                .map_or(position, |duration| position.min(duration)),
+            duration_secs: self.duration_secs,
+        }
+    }
+}
+
+/// Plays one recording at a time on the default output device, so takes can
+/// be auditioned without leaving the app. WAV and FLAC are supported.
+pub struct Player {
+    current: Mutex<Option<Playback>>,
+}
+
+impl Player {
+    pub fn new() -> Self {
+        Self {
+            current: Mutex::new(None),
+        }
+    }
+
+    pub fn status(&self) -> PlaybackStatus {
+        self.current
+            .lock()
+            .as_ref()
+            .map(Playback::status)
+            .unwrap_or_default()
+    }
+
+    /// Play `path` from `position_secs`, or from the start. Playing the
+    /// paused recording again without a position resumes it.
+    pub fn play(
+        &self,
+        app: &AppHandle,
+        path: &Path,
+        position_secs: Option<f64>,
+    ) -> Result<PlaybackStatus> {
+        let path_str = path.to_string_lossy().to_string();
+        {
+            let current = self.current.lock();
+            if let Some(playback) = current.as_ref() {
+                let ended = playback.shared.ended.load(Ordering::Relaxed);
+                if playback.path == path_str && !ended {
+                    if let Some(secs) = position_secs {
+                        let _ = playback.commands.send(PlayerMsg::Seek(secs));
+                        playback.shared.restart_at(secs);
+                    }
+                    playback.shared.paused.store(false, Ordering::Relaxed);
+                    return Ok(playback.status());
+                }
+            }
+        }
+        self.stop();
+
+        let format = recording_format(path).context("Not a supported recording format")?;
+        if format != "wav" && format != "flac" {
+            anyhow::bail!(
+                "{} files can't be played in the app yet",
+                format.to_uppercase()
+            );
+        }
+        let start = position_secs.unwrap_or(0.0).max(0.0);
+        let shared = Arc::new(Shared {
+            buffer: Mutex::new(VecDeque::new()),
+            paused: AtomicBool::new(false),
+            played: AtomicU64::new(0),
+            base_secs: Mutex::new(start),
+            output_rate: AtomicU64::new(0),
+            ended: AtomicBool::new(false),
+        });
+        let (commands, rx) = mpsc::channel();
+        let duration_secs = duration_secs(path, &format);
+
+        // The output stream lives entirely on this thread
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let thread_shared = Arc::clone(&shared);
+        let thread_path = path.to_path_buf();
+        let app = app.clone();
+        thread::spawn(move || {
+            let emit = |status: PlaybackStatus| {
+                if let Err(e) = app.emit(POSITION, status) {
+                    log::warn!("Failed to emit {}: {}", POSITION, e);
+                }
+            };
+            let outcome = run(
+                &thread_path,
+                &format,
+                start,
+                duration_secs,
+                &thread_shared,
+                &rx,
+                &ready_tx,
+                emit,
+            );
+            thread_shared.ended.store(true, Ordering::Relaxed);
+            match outcome {
+                Ok(true) => {
+                    if let Err(e) = app.emit(ENDED, thread_path.to_string_lossy()) {
+                        log::warn!("Failed to emit {}: {}", ENDED, e);
+                    }
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    log::warn!("Playback failed: {:#}", e);
+                    let _ = ready_tx.send(Err(e));
+                }
+            }
+        });
+        ready_rx.recv().context("Playback stopped unexpectedly")??;
+
+        let playback = Playback {
+            path: path_str,
+            duration_secs,
+            shared,
+            commands,
+        };
+        let status = playback.status();
+        *self.current.lock() = Some(playback);
+        Ok(status)
+    }
+
+    pub fn pause(&self) -> PlaybackStatus {
+        let current = self.current.lock();
+        let Some(playback) = current.as_ref() else {
+            return PlaybackStatus::default();
+        };
+        playback.shared.paused.store(true, Ordering::Relaxed);
+        playback.status()
+    }
+
+    pub fn seek(&self, position_secs: f64) -> Result<PlaybackStatus> {
+        let current = self.current.lock();
+        let playback = current.as_ref().context("Nothing is playing")?;
+        if playback.shared.ended.load(Ordering::Relaxed) {
+            anyhow::bail!("Playback has ended");
+        }
+        let position_secs = position_secs.max(0.0);
+        playback.shared.restart_at(position_secs);
+        let _ = playback.commands.send(PlayerMsg::Seek(position_secs));
+        Ok(playback.status())
+    }
+
+    pub fn stop(&self) {
+        if let Some(playback) = self.current.lock().take() {
+            let _ = playback.commands.send(PlayerMsg::Stop);
+        }
+    }
+}
+
+/// Length of a WAV or FLAC file, read from its header.
+fn duration_secs(path: &Path, format: &str) -> Option<f64> {
+    let frames = frame_count(path, format)?;
+    let rate = probe(path, format)?.sample_rate;
+    Some(frames as f64 / rate as f64)
+}
+
+/// Open the output and decode into it until the end, a stop or an error.
+/// Returns whether playback reached the end.
+#[allow(clippy::too_many_arguments)]
+fn run(
+    path: &Path,
+    format: &str,
+    mut start: f64,
+    duration_secs: Option<f64>,
+    shared: &Arc<Shared>,
+    commands: &mpsc::Receiver<PlayerMsg>,
+    ready: &mpsc::Sender<Result<()>>,
+    emit: impl Fn(PlaybackStatus),
+) -> Result<bool> {
+    let host = cpal::default_host();
+    let device = host
+        .default_output_device()
+        .context("No output device available")?;
+    let config = device
+        .default_output_config()
+        .context("Failed to get output config")?;
+    let output_rate = config.sample_rate().0;
+    shared
+        .output_rate
+        .store(output_rate as u64, Ordering::Relaxed);
+    let stream = build_stream(&device, config.sample_format(), &config.into(), shared)?;
+    stream.play().context("Failed to start playback")?;
+    log::info!("Playing {}", path.display());
+    let _ = ready.send(Ok(()));
+
+    let status = || PlaybackStatus {
+        path: Some(path.to_string_lossy().to_string()),
+        playing: !shared.paused.load(Ordering::Relaxed),
+        position_secs: shared.position_secs(),
+        duration_secs,
+    };
+    let mut last_emit = Instant::now();
+    let max_buffered = output_rate as usize * BUFFER_SECS;
+
+    loop {
+        // Decode from `start`, restarting on a seek
+        let mut seek = None;
+        let mut stopped = false;
+        let mut resampler: Option<Resampler> = None;
+        let mut skipped = 0u64;
+        decode(path, format, |channels, sample_rate, block| {
+            let resampler = resampler
+                .get_or_insert_with(|| Resampler::new(channels as usize, sample_rate, output_rate));
+            let skip = (start * sample_rate as f64) as u64;
+            let mut frames = Vec::with_capacity(block.len());
+            for frame in block.chunks(channels as usize) {
+                if skipped < skip {
+                    skipped += 1;
+                    continue;
+                }
+                resampler.push(frame, &mut frames);
+            }
+            shared.buffer.lock().extend(frames);
+
+            // Stay a couple of seconds ahead of the output
+            loop {
+                match commands.try_recv() {
+                    Ok(PlayerMsg::Seek(secs)) => {
+                        seek = Some(secs);
+                        return Ok(false);
+                    }
+                    Ok(PlayerMsg::Stop) | Err(mpsc::TryRecvError::Disconnected) => {
+                        stopped = true;
+                        return Ok(false);
+                    }
+                    Err(mpsc::TryRecvError::Empty) => {}
+                }
+                if last_emit.elapsed() >= POSITION_INTERVAL {
+                    last_emit = Instant::now();
+                    emit(status());
+                }
+                if shared.buffer.lock().len() < max_buffered {
+                    return Ok(true);
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+        })?;
+        if stopped {
+            return Ok(false);
+        }
+        if let Some(secs) = seek {
+            shared.restart_at(secs);
+            start = secs;
+            continue;
+        }
+
+        // Everything is decoded; let the output drain
+        loop {
+            match commands.recv_timeout(Duration::from_millis(20)) {
+                Ok(PlayerMsg::Seek(secs)) => {
+                    shared.restart_at(secs);
+                    start = secs;
+                    break;
+                }
+                Ok(PlayerMsg::Stop) | Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    return Ok(false)
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+            }
+            if last_emit.elapsed() >= POSITION_INTERVAL {
+                last_emit = Instant::now();
+                emit(status());
+            }
+            if shared.buffer.lock().is_empty() {
+                drop(stream);
+                emit(PlaybackStatus {
+                    playing: false,
+                    ..status()
+                });
+                return Ok(true);
+            }
+        }
+    }
+}
+
+fn build_stream(
+    device: &cpal::Device,
+    sample_format: SampleFormat,
+    config: &cpal::StreamConfig,
+    shared: &Arc<Shared>,
+) -> Result<cpal::Stream> {
+    let channels = config.channels as usize;
+    let err_fn = |err: cpal::StreamError| {
+        log::error!("Playback stream error: {}", err);
+    };
+    let stream = match sample_format {
+        SampleFormat::F32 => {
+            let shared = Arc::clone(shared);
+            device.build_output_stream(
+                config,
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                    fill(&shared, channels, data, |s| s);
+                },
+                err_fn,
+                None,
+            )
+        }
+        SampleFormat::I16 => {
+            let shared = Arc::clone(shared);
+            device.build_output_stream(
+                config,
+                move |data: &mut [i16], _: &cpal::OutputCallbackInfo| {
+                    fill(&shared, channels, data, float_to_i16);
+                },
+                err_fn,
+                None,
+            )
+        }
+        fmt => anyhow::bail!("Unsupported output sample format: {:?}", fmt),
+    };
+    stream.context("Failed to open the output device")
+}
+
+/// Fill an output buffer of `channels`-channel frames from the shared
+/// buffer, or with silence while paused or starved.
+fn fill<T: Copy>(shared: &Shared, channels: usize, data: &mut [T], convert: impl Fn(f32) -> T) {
+    let silence = convert(0.0);
+    if shared.paused.load(Ordering::Relaxed) {
+        data.fill(silence);
+        return;
+    }
+    let mut buffer = shared.buffer.lock();
+    let mut played = 0;
+    for out in data.chunks_mut(channels) {
+        let Some([left, right]) = buffer.pop_front() else {
+            out.fill(silence);
+            continue;
+        };
+        played += 1;
+        match out {
+            [mono] => *mono = convert((left + right) / 2.0),
+            [l, r, rest @ ..] => {
+                *l = convert(left);
+                *r = convert(right);
+                rest.fill(silence);
+            }
+            [] => {}
+        }
+    }
+    shared.played.fetch_add(played, Ordering::Relaxed);
+}
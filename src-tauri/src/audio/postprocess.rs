@@ -0,0 +1,325 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::f64::consts::PI;
+use std::path::Path;
+
+use super::encoder::{create_encoder, remove_partial, AudioEncoder, AudioFormat};
+use crate::library::{decode, frame_count, recording_format};
+
+/// Integrated loudness podcast platforms ask for.
+pub const DEFAULT_TARGET_LUFS: f64 = -16.0;
+/// Highest sample peak normalization may raise a recording to.
+const PEAK_CEILING_DB: f64 = -1.0;
+/// Blocks quieter than this don't count towards the loudness.
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+/// Blocks this far below the ungated loudness don't count either.
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+/// What normalizing a recording did.
+#[derive(Debug, Clone, Serialize)]
+pub struct Normalized {
+    /// Integrated loudness before, in LUFS.
+    pub measured_lufs: f64,
+    /// Gain applied, in dB.
+    pub gain_db: f64,
+    /// Whether the gain was held back to keep peaks under the ceiling, so
+    /// the target wasn't reached.
+    pub peak_limited: bool,
+}
+
+/// Integrated loudness and sample peak of a recording.
+#[derive(Debug, Clone, Copy)]
+pub struct Loudness {
+    /// `None` when everything is below the absolute gate, e.g. silence.
+    pub integrated_lufs: Option<f64>,
+    pub peak: f32,
+}
+
+/// Measure the integrated loudness of the WAV or FLAC file at `path` after
+/// EBU R128 / ITU-R BS.1770, with all channels weighted equally.
+pub fn measure(
+    path: &Path,
+    progress: impl Fn(u64),
+    cancelled: impl Fn() -> bool,
+) -> Result<Loudness> {
+    let format = supported_format(path)?;
+    let mut meter: Option<Meter> = None;
+    let mut done = 0u64;
+    decode(path, &format, |channels, sample_rate, block| {
+        if cancelled() {
+            anyhow::bail!("Cancelled");
+        }
+        let meter = meter.get_or_insert_with(|| Meter::new(channels as usize, sample_rate));
+        meter.push(block);
+        done += (block.len() / channels.max(1) as usize) as u64;
+        progress(done);
+        Ok(true)
+    })?;
+    Ok(meter.map_or(
+        Loudness {
+            integrated_lufs: None,
+            peak: 0.0,
+        },
+        |meter| meter.finish(),
+    ))
+}
+
+/// Bring the WAV or FLAC recording at `path` to `target_lufs` integrated
+/// loudness with a single gain change, in place.
+///
+/// The gain is held back where it would push a sample over -1 dBFS. The
+/// result is written to a hidden file next to the recording and only
+/// replaces it once complete, so a failure leaves the original intact.
+/// `progress` is called with frames done and the total for both passes.
+pub fn normalize(
+    path: &Path,
+    target_lufs: f64,
+    progress: impl Fn(u64, Option<u64>),
+    cancelled: impl Fn() -> bool,
+) -> Result<Normalized> {
+    let format = supported_format(path)?;
+    let output = match format.as_str() {
+        "wav" => AudioFormat::Wav,
+        _ => AudioFormat::Flac,
+    };
+    let frames = frame_count(path, &format);
+    let total = frames.map(|frames| frames * 2);
+
+    let loudness = measure(path, |done| progress(done, total), &cancelled)?;
+    let measured_lufs = loudness
+        .integrated_lufs
+        .context("The recording is silent, there is nothing to normalize")?;
+    let wanted_db = target_lufs - measured_lufs;
+    let headroom_db = PEAK_CEILING_DB - 20.0 * (loudness.peak.max(f32::EPSILON) as f64).log10();
+    // Peaks only hold back raising the level, never force it down
+    let gain_db = if wanted_db > 0.0 {
+        wanted_db.min(headroom_db.max(0.0))
+    } else {
+        wanted_db
+    };
+    let peak_limited = gain_db < wanted_db;
+    if gain_db.abs() < 0.05 {
+        log::info!("{} is already at {:.1} LUFS", path.display(), measured_lufs);
+        return Ok(Normalized {
+            measured_lufs,
+            gain_db: 0.0,
+            peak_limited,
+        });
+    }
+
+    let file_name = path.file_name().context("Invalid file name")?;
+    let temp = path
+        .with_file_name(format!(".{}.normalize", file_name.to_string_lossy()))
+        .to_string_lossy()
+        .to_string();
+    let offset = frames.unwrap_or(0);
+    let gain = 10f64.powf(gain_db / 20.0) as f32;
+    let result = apply_gain(
+        path,
+        &format,
+        &temp,
+        output,
+        gain,
+        |done| progress(offset + done, total),
+        &cancelled,
+    );
+    if result.is_err() {
+        remove_partial(&temp);
+    }
+    result?;
+    std::fs::rename(&temp, path).context("Failed to replace the recording")?;
+    log::info!(
+        "Normalized {} from {:.1} LUFS by {:+.1} dB{}",
+        path.display(),
+        measured_lufs,
+        gain_db,
+        if peak_limited { " (peak limited)" } else { "" }
+    );
+    Ok(Normalized {
+        measured_lufs,
+        gain_db,
+        peak_limited,
+    })
+}
+
+fn supported_format(path: &Path) -> Result<String> {
+    let format = recording_format(path).context("Not a supported recording format")?;
+    if format != "wav" && format != "flac" {
+        anyhow::bail!("{} files can't be normalized yet", format.to_uppercase());
+    }
+    Ok(format)
+}
+
+/// Re-encode `source` to `target` with every sample scaled by `gain`.
+fn apply_gain(
+    source: &Path,
+    format: &str,
+    target: &str,
+    output: AudioFormat,
+    gain: f32,
+    progress: impl Fn(u64),
+    cancelled: &impl Fn() -> bool,
+) -> Result<()> {
+    let mut encoder: Option<Box<dyn AudioEncoder>> = None;
+    let mut scaled = Vec::new();
+    let mut done = 0u64;
+    decode(source, format, |channels, sample_rate, block| {
+        if cancelled() {
+            anyhow::bail!("Cancelled");
+        }
+        let encoder = match &mut encoder {
+            Some(encoder) => encoder,
+            None => encoder.insert(create_encoder(
+                target,
+                channels,
+                sample_rate,
+                output,
+                false,
+            )?),
+        };
+        scaled.clear();
+        scaled.extend(block.iter().map(|sample| (sample * gain).clamp(-1.0, 1.0)));
+        encoder.write_samples(&scaled)?;
+        done += (block.len() / channels.max(1) as usize) as u64;
+        progress(done);
+        Ok(true)
+    })?;
+    encoder.context("The recording is empty")?.finalize()
+}
+
+/// Second-order IIR section, transposed direct form II.
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// BS.1770 K-weighting, a high shelf then a high-pass, designed for
+/// `sample_rate` the way libebur128 does so rates other than 48 kHz match.
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = sample_rate as f64;
+
+    let f0 = 1681.974450955533;
+    let gain_db = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+    [shelf, high_pass]
+}
+
+/// Gated loudness measurement over 400 ms blocks overlapping by 75%.
+struct Meter {
+    filters: Vec<[Biquad; 2]>,
+    /// Samples per 100 ms step.
+    step: usize,
+    /// Filtered energy summed over channels for the current step.
+    energy: f64,
+    in_step: usize,
+    /// The last four steps' energies, making up the current block.
+    steps: [f64; 4],
+    seen_steps: usize,
+    blocks: Vec<f64>,
+    peak: f32,
+    frame: usize,
+}
+
+impl Meter {
+    fn new(channels: usize, sample_rate: u32) -> Self {
+        Self {
+            filters: vec![k_weighting(sample_rate); channels.max(1)],
+            step: (sample_rate as usize / 10).max(1),
+            energy: 0.0,
+            in_step: 0,
+            steps: [0.0; 4],
+            seen_steps: 0,
+            blocks: Vec::new(),
+            peak: 0.0,
+            frame: 0,
+        }
+    }
+
+    fn push(&mut self, samples: &[f32]) {
+        let channels = self.filters.len();
+        for &sample in samples {
+            self.peak = self.peak.max(sample.abs());
+            let [shelf, high_pass] = &mut self.filters[self.frame];
+            let filtered = high_pass.process(shelf.process(sample as f64));
+            self.energy += filtered * filtered;
+            self.frame += 1;
+            if self.frame < channels {
+                continue;
+            }
+            self.frame = 0;
+            self.in_step += 1;
+            if self.in_step == self.step {
+                self.end_step();
+            }
+        }
+    }
+
+    fn end_step(&mut self) {
+        self.steps.rotate_left(1);
+        self.steps[3] = self.energy / self.step as f64;
+        self.energy = 0.0;
+        self.in_step = 0;
+        self.seen_steps += 1;
+        if self.seen_steps >= 4 {
+            self.blocks.push(self.steps.iter().sum::<f64>() / 4.0);
+        }
+    }
+
+    fn finish(self) -> Loudness {
+        let loudness = |energy: f64| -0.691 + 10.0 * energy.log10();
+        let mean = |blocks: &[f64]| blocks.iter().sum::<f64>() / blocks.len() as f64;
+
+        let audible: Vec<f64> = self
+            .blocks
+            .into_iter()
+            .filter(|&energy| loudness(energy) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        let integrated_lufs = (!audible.is_empty()).then(|| {
+            let threshold = loudness(mean(&audible)) + RELATIVE_GATE_LU;
+            let gated: Vec<f64> = audible
+                .iter()
+                .copied()
+                .filter(|&energy| loudness(energy) > threshold)
+                .collect();
+            loudness(mean(&gated))
+        });
+        Loudness {
+            integrated_lufs,
+            peak: self.peak,
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Where a recording takes its audio from.
+///
+/// Parsed from the identifiers the frontend sends: `discord-app`, `system`,
+/// `mic` and `bot:<guild>/<channel>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureSource {
+    /// Only the Discord desktop client's output.
+    DiscordApp,
+    /// Everything playing on the default output device.
+    System,
+    /// The default input device.
+    Mic,
+    /// The bot joined to a voice channel, one track per speaker.
+    Bot { guild_id: u64, channel_id: u64 },
+}
+
+impl CaptureSource {
+    pub fn is_local(&self) -> bool {
+        !matches!(self, CaptureSource::Bot { .. })
+    }
+
+    /// Whether this source records from the capture device picked in
+    /// settings. Discord's own output is captured per app where possible;
+    /// on Linux that can fall back to the device.
+    pub fn uses_capture_device(&self) -> bool {
+        match self {
+            CaptureSource::System => true,
+            CaptureSource::DiscordApp => cfg!(target_os = "linux"),
+            CaptureSource::Mic | CaptureSource::Bot { .. } => false,
+        }
+    }
+
+    /// Short tag used to name this source's file when several run at once.
+    pub fn file_tag(&self) -> &'static str {
+        match self {
+            CaptureSource::DiscordApp => "discord",
+            CaptureSource::System => "system",
+            CaptureSource::Mic => "mic",
+            CaptureSource::Bot { .. } => "bot",
+        }
+    }
+}
+
+impl FromStr for CaptureSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "discord-app" => Ok(CaptureSource::DiscordApp),
+            "system" => Ok(CaptureSource::System),
+            "mic" => Ok(CaptureSource::Mic),
+            other => {
+                let Some(target) = other.strip_prefix("bot:") else {
+                    anyhow::bail!("Unknown audio source: {}", other);
+                };
+                let (guild, channel) = target
+                    .split_once('/')
+                    .ok_or_else(|| anyhow::anyhow!("Bot source must be bot:<guild>/<channel>"))?;
+                Ok(CaptureSource::Bot {
+                    guild_id: guild
+                        .parse()
+                        .map_err(|_| anyhow::anyhow!("Invalid guild ID"))?,
+                    channel_id: channel
+                        .parse()
+                        .map_err(|_| anyhow::anyhow!("Invalid channel ID"))?,
+                })
+            }
+        }
+    }
+}
+
+impl fmt::Display for CaptureSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureSource::DiscordApp => write!(f, "discord-app"),
+            CaptureSource::System => write!(f, "system"),
+            CaptureSource::Mic => write!(f, "mic"),
+            CaptureSource::Bot {
+                guild_id,
+                channel_id,
+            } => write!(f, "bot:{}/{}", guild_id, channel_id),
+        }
+    }
+}
+
+/// Parse and validate the `sources` list of a start request.
+pub fn parse_sources(ids: &[String]) -> Result<Vec<CaptureSource>> {
+    let mut sources: Vec<CaptureSource> = Vec::new();
+    for id in ids {
+        let source: CaptureSource = id.parse()?;
+        if !source_available(source) {
+            anyhow::bail!(
+                "Audio source '{}' is not supported on this platform",
+                source
+            );
+        }
+        if !source.is_local() && sources.iter().any(|s| !s.is_local()) {
+            anyhow::bail!("Only one bot channel can be recorded at a time");
+        }
+        if !sources.contains(&source) {
+            sources.push(source);
+        }
+    }
+    if sources.is_empty() {
+        anyhow::bail!("No audio source selected");
+    }
+    Ok(sources)
+}
+
+/// What a recording captures when the caller doesn't choose.
+pub fn default_sources() -> Vec<CaptureSource> {
+    if source_available(CaptureSource::DiscordApp) {
+        vec![CaptureSource::DiscordApp]
+    } else {
+        vec![CaptureSource::System]
+    }
+}
+
+/// Whether this platform can capture from the given kind of source.
+pub fn source_available(source: CaptureSource) -> bool {
+    match source {
+        // macOS before 13 has no per-app capture and records "system" instead
+        CaptureSource::DiscordApp => cfg!(any(
+            target_os = "windows",
+            target_os = "linux",
+            target_os = "macos"
+        )),
+        CaptureSource::System | CaptureSource::Mic | CaptureSource::Bot { .. } => true,
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct SourceInfo {
+    pub id: String,
+    pub label: String,
+    pub available: bool,
+}
+
+/// Local sources for the frontend's source picker.
+pub fn local_source_info() -> Vec<SourceInfo> {
+    [
+        (CaptureSource::DiscordApp, "Discord app"),
+        (CaptureSource::System, "System audio"),
+        (CaptureSource::Mic, "Microphone"),
+    ]
+    .into_iter()
+    .map(|(source, label)| SourceInfo {
+        id: source.to_string(),
+        label: label.to_string(),
+        available: source_available(source),
+    })
+    .collect()
+}
@@ -0,0 +1,372 @@
+use anyhow::Result;
+use serde::Serialize;
+
+/// State of the macOS system audio setup.
+///
+/// macOS cannot loop back an output device, so capture goes through a
+/// virtual device (BlackHole). To keep hearing audio, the default output
+/// must be a Multi-Output Device feeding both the speakers and BlackHole.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct VirtualDeviceSetup {
+    /// False on platforms that capture system audio without a virtual device.
+    pub required: bool,
+    /// Name of the installed virtual device (BlackHole), if any.
+    pub virtual_device: Option<String>,
+    /// Name of a Multi-Output Device that includes the virtual device, if any.
+    pub multi_output: Option<String>,
+    pub default_output: Option<String>,
+    /// Audio playing on the default output reaches the virtual device.
+    pub ready: bool,
+    /// What the user should do next; empty when ready.
+    pub next_step: String,
+}
+
+/// Inspect the virtual device and output routing.
+pub fn check() -> Result<VirtualDeviceSetup> {
+    #[cfg(target_os = "macos")]
+    {
+        macos::check()
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        Ok(VirtualDeviceSetup::default())
+    }
+}
+
+/// Open a downloaded BlackHole installer package, or its download page.
+pub fn open_installer() -> Result<()> {
+    #[cfg(target_os = "macos")]
+    {
+        macos::open_installer()
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        anyhow::bail!("A virtual audio device is only needed on macOS")
+    }
+}
+
+/// Create (or reuse) a Multi-Output Device of the current speakers and
+/// BlackHole, and make it the default output.
+pub fn setup_multi_output() -> Result<VirtualDeviceSetup> {
+    #[cfg(target_os = "macos")]
+    {
+        macos::setup_multi_output()?;
+        check()
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        anyhow::bail!("A virtual audio device is only needed on macOS")
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use anyhow::{Context, Result};
+    use core_foundation::array::CFArray;
+    use core_foundation::base::{CFType, TCFType};
+    use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+    use core_foundation::number::CFNumber;
+    use core_foundation::string::{CFString, CFStringRef};
+    use std::ffi::c_void;
+
+    use super::VirtualDeviceSetup;
+
+    const BLACKHOLE_DOWNLOAD_URL: &str = "https://existential.audio/blackhole/";
+    /// UID of the Multi-Output Device DiscRec creates, so it can be found again.
+    const MULTI_OUTPUT_UID: &str = "com.discrec.multi-output";
+    const MULTI_OUTPUT_NAME: &str = "DiscRec Multi-Output";
+
+    type AudioObjectId = u32;
+    type OsStatus = i32;
+
+    #[repr(C)]
+    struct PropertyAddress {
+        selector: u32,
+        scope: u32,
+        element: u32,
+    }
+
+    #[link(name = "CoreAudio", kind = "framework")]
+    extern "C" {
+        fn AudioObjectGetPropertyDataSize(
+            object: AudioObjectId,
+            address: *const PropertyAddress,
+            qualifier_size: u32,
+            qualifier: *const c_void,
+            out_size: *mut u32,
+        ) -> OsStatus;
+        fn AudioObjectGetPropertyData(
+            object: AudioObjectId,
+            address: *const PropertyAddress,
+            qualifier_size: u32,
+            qualifier: *const c_void,
+            io_size: *mut u32,
+            out_data: *mut c_void,
+        ) -> OsStatus;
+        fn AudioObjectSetPropertyData(
+            object: AudioObjectId,
+            address: *const PropertyAddress,
+            qualifier_size: u32,
+            qualifier: *const c_void,
+            size: u32,
+            data: *const c_void,
+        ) -> OsStatus;
+        fn AudioHardwareCreateAggregateDevice(
+            description: CFDictionaryRef,
+            out_device: *mut AudioObjectId,
+        ) -> OsStatus;
+    }
+
+    const fn fourcc(code: &[u8; 4]) -> u32 {
+        u32::from_be_bytes(*code)
+    }
+
+    const SYSTEM_OBJECT: AudioObjectId = 1;
+    const SCOPE_GLOBAL: u32 = fourcc(b"glob");
+    const SCOPE_OUTPUT: u32 = fourcc(b"outp");
+    const ELEMENT_MAIN: u32 = 0;
+    const PROP_DEVICES: u32 = fourcc(b"dev#");
+    const PROP_DEFAULT_OUTPUT: u32 = fourcc(b"dOut");
+    const PROP_DEVICE_UID: u32 = fourcc(b"uid ");
+    const PROP_NAME: u32 = fourcc(b"lnam");
+    const PROP_STREAMS: u32 = fourcc(b"stm#");
+    const PROP_ACTIVE_SUBDEVICES: u32 = fourcc(b"agrp");
+
+    struct Device {
+        id: AudioObjectId,
+        uid: String,
+        name: String,
+        has_output: bool,
+        subdevices: Vec<AudioObjectId>,
+    }
+
+    fn address(selector: u32, scope: u32) -> PropertyAddress {
+        PropertyAddress {
+            selector,
+            scope,
+            element: ELEMENT_MAIN,
+        }
+    }
+
+    fn data_size(object: AudioObjectId, addr: &PropertyAddress) -> Option<u32> {
+        let mut size = 0u32;
+        let status =
+            unsafe { AudioObjectGetPropertyDataSize(object, addr, 0, std::ptr::null(), &mut size) };
+        (status == 0).then_some(size)
+    }
+
+    fn read_ids(object: AudioObjectId, addr: &PropertyAddress) -> Vec<AudioObjectId> {
+        let Some(size) = data_size(object, addr) else {
+            return Vec::new();
+        };
+        let mut ids =
+            vec![0 as AudioObjectId; size as usize / std::mem::size_of::<AudioObjectId>()];
+        let mut size = (ids.len() * std::mem::size_of::<AudioObjectId>()) as u32;
+        let status = unsafe {
+            AudioObjectGetPropertyData(
+                object,
+                addr,
+                0,
+                std::ptr::null(),
+                &mut size,
+                ids.as_mut_ptr() as *mut c_void,
+            )
+        };
+        if status != 0 {
+            return Vec::new();
+        }
+        ids.truncate(size as usize / std::mem::size_of::<AudioObjectId>());
+        ids
+    }
+
+    fn read_string(object: AudioObjectId, selector: u32) -> Option<String> {
+        let addr = address(selector, SCOPE_GLOBAL);
+        let mut value: CFStringRef = std::ptr::null();
+        let mut size = std::mem::size_of::<CFStringRef>() as u32;
+        let status = unsafe {
+            AudioObjectGetPropertyData(
+                object,
+                &addr,
+                0,
+                std::ptr::null(),
+                &mut size,
+                &mut value as *mut CFStringRef as *mut c_void,
+            )
+        };
+        if status != 0 || value.is_null() {
+            return None;
+        }
+        Some(unsafe { CFString::wrap_under_create_rule(value) }.to_string())
+    }
+
+    fn devices() -> Vec<Device> {
+        read_ids(SYSTEM_OBJECT, &address(PROP_DEVICES, SCOPE_GLOBAL))
+            .into_iter()
+            .map(|id| Device {
+                id,
+                uid: read_string(id, PROP_DEVICE_UID).unwrap_or_default(),
+                name: read_string(id, PROP_NAME).unwrap_or_default(),
+                has_output: data_size(id, &address(PROP_STREAMS, SCOPE_OUTPUT))
+                    .is_some_and(|size| size > 0),
+                subdevices: read_ids(id, &address(PROP_ACTIVE_SUBDEVICES, SCOPE_GLOBAL)),
+            })
+            .collect()
+    }
+
+    fn default_output() -> Option<AudioObjectId> {
+        read_ids(SYSTEM_OBJECT, &address(PROP_DEFAULT_OUTPUT, SCOPE_GLOBAL))
+            .first()
+            .copied()
+    }
+
+    fn is_virtual(device: &Device) -> bool {
+        device.name.to_lowercase().contains("blackhole")
+    }
+
+    pub fn check() -> Result<VirtualDeviceSetup> {
+        let devices = devices();
+        let blackhole = devices.iter().find(|d| is_virtual(d));
+        let multi_output = blackhole.and_then(|bh| {
+            devices
+                .iter()
+                .filter(|d| d.subdevices.contains(&bh.id))
+                // Prefer ours when the user also made one by hand
+                .max_by_key(|d| d.uid == MULTI_OUTPUT_UID)
+        });
+        let default_id = default_output();
+        let default_output = devices.iter().find(|d| Some(d.id) == default_id);
+
+        let ready = match (blackhole, default_output) {
+            (Some(bh), Some(out)) => out.id == bh.id || out.subdevices.contains(&bh.id),
+            _ => false,
+        };
+        let next_step = if blackhole.is_none() {
+            "Install BlackHole 2ch, then check again.".to_string()
+        } else if multi_output.is_none() {
+            "Create a Multi-Output Device so you keep hearing audio while it is captured."
+                .to_string()
+        } else if !ready {
+            "Select the Multi-Output Device as your sound output.".to_string()
+        } else {
+            String::new()
+        };
+
+        Ok(VirtualDeviceSetup {
+            required: true,
+            virtual_device: blackhole.map(|d| d.name.clone()),
+            multi_output: multi_output.map(|d| d.name.clone()),
+            default_output: default_output.map(|d| d.name.clone()),
+            ready,
+            next_step,
+        })
+    }
+
+    pub fn setup_multi_output() -> Result<()> {
+        let devices = devices();
+        let blackhole = devices
+            .iter()
+            .find(|d| is_virtual(d))
+            .context("BlackHole is not installed")?;
+
+        let device_id = match devices.iter().find(|d| d.uid == MULTI_OUTPUT_UID) {
+            Some(existing) => existing.id,
+            None => {
+                // Speakers: the current output unless it's already virtual,
+                // otherwise the first real output device
+                let default_id = default_output();
+                let speakers = devices
+                    .iter()
+                    .filter(|d| d.has_output && !is_virtual(d) && d.subdevices.is_empty())
+                    .max_by_key(|d| Some(d.id) == default_id)
+                    .context("No speakers or headphones found to play audio through")?;
+                create_multi_output(&speakers.uid, &blackhole.uid)?
+            }
+        };
+
+        let addr = address(PROP_DEFAULT_OUTPUT, SCOPE_GLOBAL);
+        let status = unsafe {
+            AudioObjectSetPropertyData(
+                SYSTEM_OBJECT,
+                &addr,
+                0,
+                std::ptr::null(),
+                std::mem::size_of::<AudioObjectId>() as u32,
+                &device_id as *const AudioObjectId as *const c_void,
+            )
+        };
+        if status != 0 {
+            anyhow::bail!(
+                "Failed to set the default output device (status {})",
+                status
+            );
+        }
+        log::info!("Default output set to {}", MULTI_OUTPUT_NAME);
+        Ok(())
+    }
+
+    fn create_multi_output(speakers_uid: &str, blackhole_uid: &str) -> Result<AudioObjectId> {
+        let key = CFString::new;
+        let subdevice = |uid: &str, drift: bool| {
+            CFDictionary::from_CFType_pairs(&[
+                (key("uid"), CFString::new(uid).as_CFType()),
+                (key("drift"), CFNumber::from(drift as i32).as_CFType()),
+            ])
+        };
+        // The speakers are the clock source; BlackHole follows with drift correction
+        let subdevices = CFArray::from_CFTypes(&[
+            subdevice(speakers_uid, false),
+            subdevice(blackhole_uid, true),
+        ]);
+        let description: CFDictionary<CFString, CFType> = CFDictionary::from_CFType_pairs(&[
+            (key("name"), CFString::new(MULTI_OUTPUT_NAME).as_CFType()),
+            (key("uid"), CFString::new(MULTI_OUTPUT_UID).as_CFType()),
+            (key("subdevices"), subdevices.as_CFType()),
+            (key("master"), CFString::new(speakers_uid).as_CFType()),
+            // Stacked = Multi-Output Device rather than an aggregate input
+            (key("stacked"), CFNumber::from(1).as_CFType()),
+        ]);
+
+        let mut device_id: AudioObjectId = 0;
+        let status = unsafe {
+            AudioHardwareCreateAggregateDevice(description.as_concrete_TypeRef(), &mut device_id)
+        };
+        if status != 0 {
+            anyhow::bail!("Failed to create Multi-Output Device (status {})", status);
+        }
+        log::info!(
+            "Created {} ({} + {})",
+            MULTI_OUTPUT_NAME,
+            speakers_uid,
+            blackhole_uid
+        );
+        Ok(device_id)
+    }
+
+    pub fn open_installer() -> Result<()> {
+        let target = find_downloaded_installer()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|| BLACKHOLE_DOWNLOAD_URL.to_string());
+        log::info!("Opening BlackHole installer: {}", target);
+        std::process::Command::new("open")
+            .arg(&target)
+            .spawn()
+            .context("Failed to open BlackHole installer")?;
+        Ok(())
+    }
+
+    /// Newest BlackHole package in the Downloads folder.
+    fn find_downloaded_installer() -> Option<std::path::PathBuf> {
+        std::fs::read_dir(dirs::download_dir()?)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                let name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                name.starts_with("blackhole") && name.ends_with(".pkg")
+            })
+            .max_by_key(|path| path.metadata().and_then(|m| m.modified()).ok())
+    }
+}
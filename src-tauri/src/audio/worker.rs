@@ -0,0 +1,354 @@
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::encoder::AudioEncoder;
+use crate::latency;
+use crate::session::is_session_path;
+
+/// Samples handed to a worker at once (~50 ms of 48 kHz stereo).
+const CHUNK_SAMPLES: usize = 4800;
+
+pub const DEFAULT_ENCODER_THREADS: u32 = 2;
+pub const MAX_ENCODER_THREADS: u32 = 16;
+pub const DEFAULT_ENCODER_BUFFER_MB: u32 = 64;
+pub const MIN_ENCODER_BUFFER_MB: u32 = 4;
+pub const MAX_ENCODER_BUFFER_MB: u32 = 1024;
+
+/// Resource limits for encoding, so recording can share a machine with a
+/// game and streaming software.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EncoderLimits {
+    /// Worker threads that run the encoders; 0 encodes on the capture threads.
+    pub threads: u32,
+    /// Upper bound on audio queued or buffered in memory, in MiB.
+    pub max_buffer_mb: u32,
+}
+
+impl Default for EncoderLimits {
+    fn default() -> Self {
+        Self {
+            threads: DEFAULT_ENCODER_THREADS,
+            max_buffer_mb: DEFAULT_ENCODER_BUFFER_MB,
+        }
+    }
+}
+
+impl EncoderLimits {
+    pub fn clamped(self) -> Self {
+        Self {
+            threads: self.threads.min(MAX_ENCODER_THREADS),
+            max_buffer_mb: self
+                .max_buffer_mb
+                .clamp(MIN_ENCODER_BUFFER_MB, MAX_ENCODER_BUFFER_MB),
+        }
+    }
+
+    /// Memory budget expressed in f32 samples.
+    pub fn max_buffer_samples(&self) -> usize {
+        self.max_buffer_mb as usize * 1024 * 1024 / std::mem::size_of::<f32>()
+    }
+}
+
+/// First write error of an encoder, reported back to its capture thread.
+type ErrorSlot = Arc<Mutex<Option<String>>>;
+
+enum Job {
+    Open {
+        id: u64,
+        encoder: Box<dyn AudioEncoder>,
+        error: ErrorSlot,
+    },
+    Write {
+        id: u64,
+        samples: Vec<f32>,
+        /// When the first of the samples reached the encoder, for recordings.
+        queued_at: Option<Instant>,
+    },
+    Finalize {
+        id: u64,
+        reply: mpsc::Sender<Result<()>>,
+    },
+    Discard {
+        id: u64,
+    },
+}
+
+struct Pool {
+    limits: EncoderLimits,
+    workers: Vec<SyncSender<Job>>,
+    next: AtomicUsize,
+}
+
+static POOL: Mutex<Option<Arc<Pool>>> = Mutex::new(None);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Apply new limits. Encoders already running keep their current worker
+/// until they finalize; old workers exit once idle.
+pub fn configure(limits: EncoderLimits) {
+    let limits = limits.clamped();
+    let mut pool = POOL.lock();
+    if pool.as_ref().map(|p| p.limits) == Some(limits) {
+        return;
+    }
+    *pool = Some(Arc::new(Pool::spawn(limits)));
+    log::info!(
+        "Encoder limits: {} worker threads, {} MiB buffer",
+        limits.threads,
+        limits.max_buffer_mb
+    );
+}
+
+/// Limits currently in effect.
+pub fn limits() -> EncoderLimits {
+    current().limits
+}
+
+fn current() -> Arc<Pool> {
+    POOL.lock()
+        .get_or_insert_with(|| Arc::new(Pool::spawn(EncoderLimits::default())))
+        .clone()
+}
+
+impl Pool {
+    fn spawn(limits: EncoderLimits) -> Self {
+        let threads = limits.threads as usize;
+        // Each worker's queue gets an equal share of the memory budget
+        let queue_chunks = (limits.max_buffer_samples() / CHUNK_SAMPLES)
+            .checked_div(threads)
+            .unwrap_or(0)
+            .max(1);
+        let workers = (0..threads)
+            .map(|i| {
+                let (tx, rx) = mpsc::sync_channel(queue_chunks);
+                std::thread::Builder::new()
+                    .name(format!("encoder-{}", i))
+                    .spawn(move || run_worker(rx))
+                    .expect("failed to spawn encoder worker");
+                tx
+            })
+            .collect();
+        Self {
+            limits,
+            workers,
+            next: AtomicUsize::new(0),
+        }
+    }
+}
+
+fn run_worker(rx: Receiver<Job>) {
+    let mut encoders: HashMap<u64, (Box<dyn AudioEncoder>, ErrorSlot)> = HashMap::new();
+
+    while let Ok(job) = rx.recv() {
+        match job {
+            Job::Open { id, encoder, error } => {
+                encoders.insert(id, (encoder, error));
+            }
+            Job::Write {
+                id,
+                samples,
+                queued_at,
+            } => {
+                let Some((encoder, error)) = encoders.get_mut(&id) else {
+                    continue;
+                };
+                if error.lock().is_some() {
+                    continue;
+                }
+                match encoder.write_samples(&samples) {
+                    Ok(()) => {
+                        if let Some(queued_at) = queued_at {
+                            latency::record_write(queued_at);
+                        }
+                    }
+                    Err(e) => {
+                        log::error!("Encoder write failed for {}: {}", encoder.path(), e);
+                        *error.lock() = Some(e.to_string());
+                    }
+                }
+            }
+            Job::Finalize { id, reply } => {
+                let result = match encoders.remove(&id) {
+                    Some((encoder, error)) => match error.lock().take() {
+                        Some(e) => {
+                            // Still finalize so wrappers can keep what they have
+                            let _ = encoder.finalize();
+                            Err(anyhow::anyhow!(e))
+                        }
+                        None => encoder.finalize(),
+                    },
+                    None => Err(anyhow::anyhow!("Encoder is not running")),
+                };
+                let _ = reply.send(result);
+            }
+            Job::Discard { id } => {
+                encoders.remove(&id);
+            }
+        }
+    }
+}
+
+/// Moves an encoder onto a worker thread. Samples are batched, and writes
+/// block once the worker's queue holds its share of the memory budget.
+struct PooledEncoder {
+    id: u64,
+    worker: SyncSender<Job>,
+    pending: Vec<f32>,
+    /// When the oldest pending sample arrived; only tracked for recordings.
+    pending_since: Option<Instant>,
+    timed: bool,
+    error: ErrorSlot,
+    path: String,
+    finalized: bool,
+}
+
+impl PooledEncoder {
+    fn send(&self, job: Job) -> Result<()> {
+        self.worker
+            .send(job)
+            .map_err(|_| anyhow::anyhow!("Encoder worker for {} stopped", self.path))
+    }
+
+    fn flush_pending(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let samples = std::mem::replace(&mut self.pending, Vec::with_capacity(CHUNK_SAMPLES));
+        let queued_at = self.pending_since.take();
+        self.send(Job::Write {
+            id: self.id,
+            samples,
+            queued_at,
+        })
+    }
+
+    fn note_arrival(&mut self) {
+        if self.timed && self.pending_since.is_none() {
+            self.pending_since = Some(Instant::now());
+        }
+    }
+}
+
+impl AudioEncoder for PooledEncoder {
+    fn write_sample(&mut self, sample: f32) -> Result<()> {
+        self.note_arrival();
+        self.pending.push(sample);
+        if self.pending.len() >= CHUNK_SAMPLES {
+            if let Some(e) = self.error.lock().clone() {
+                anyhow::bail!(e);
+            }
+            self.flush_pending()?;
+        }
+        Ok(())
+    }
+
+    fn write_samples(&mut self, samples: &[f32]) -> Result<()> {
+        self.note_arrival();
+        self.pending.extend_from_slice(samples);
+        if self.pending.len() >= CHUNK_SAMPLES {
+            if let Some(e) = self.error.lock().clone() {
+                anyhow::bail!(e);
+            }
+            self.flush_pending()?;
+        }
+        Ok(())
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn finalize(mut self: Box<Self>) -> Result<()> {
+        self.flush_pending()?;
+        let (reply, result) = mpsc::channel();
+        self.send(Job::Finalize { id: self.id, reply })?;
+        self.finalized = true;
+        result
+            .recv()
+            .map_err(|_| anyhow::anyhow!("Encoder worker for {} stopped", self.path))?
+    }
+}
+
+impl Drop for PooledEncoder {
+    fn drop(&mut self) {
+        if !self.finalized {
+            let _ = self.worker.send(Job::Discard { id: self.id });
+        }
+    }
+}
+
+/// Times writes made on the capture thread itself, when pooling is off.
+struct TimedEncoder(Box<dyn AudioEncoder>);
+
+impl AudioEncoder for TimedEncoder {
+    fn write_sample(&mut self, sample: f32) -> Result<()> {
+        self.write_samples(&[sample])
+    }
+
+    fn write_samples(&mut self, samples: &[f32]) -> Result<()> {
+        let started = Instant::now();
+        self.0.write_samples(samples)?;
+        latency::record_write(started);
+        Ok(())
+    }
+
+    fn path(&self) -> &str {
+        self.0.path()
+    }
+
+    fn paths(&self) -> Vec<String> {
+        self.0.paths()
+    }
+
+    fn finalize(self: Box<Self>) -> Result<()> {
+        self.0.finalize()
+    }
+}
+
+/// Run `encoder` on the worker pool, or return it unchanged when pooling is
+/// off. Writes to recordings are timed for the latency report.
+pub fn pooled(encoder: Box<dyn AudioEncoder>) -> Result<Box<dyn AudioEncoder>> {
+    let pool = current();
+    let timed = is_session_path(encoder.path());
+    if pool.workers.is_empty() {
+        return Ok(if timed {
+            Box::new(TimedEncoder(encoder))
+        } else {
+            encoder
+        });
+    }
+
+    let worker =
+        pool.workers[pool.next.fetch_add(1, Ordering::Relaxed) % pool.workers.len()].clone();
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let error = Arc::new(Mutex::new(None));
+    let path = encoder.path().to_string();
+    worker
+        .send(Job::Open {
+            id,
+            encoder,
+            error: error.clone(),
+        })
+        .map_err(|_| anyhow::anyhow!("Encoder worker stopped"))?;
+
+    Ok(Box::new(PooledEncoder {
+        id,
+        worker,
+        pending: Vec::with_capacity(CHUNK_SAMPLES),
+        pending_since: None,
+        timed,
+        error,
+        path,
+        finalized: false,
+    }))
+}
+
+/// Most audio a buffering encoder may hold before it must write out.
+pub fn max_buffer_samples() -> usize {
+    limits().max_buffer_samples()
+}
@@ -0,0 +1,152 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::import::unique_path;
+
+const BACKUP_FORMAT: &str = "discrec-backup";
+const BACKUP_VERSION: u32 = 1;
+
+/// Files in the config folder that make up the app's state: settings, the
+/// archive index, sent recording notices and the consent log.
+const FILES: &[&str] = &[
+    "settings.json",
+    "archives.json",
+    "recording_notices.json",
+    "consent_log.jsonl",
+];
+
+/// Keyring accounts backed up, and whether each is a token that backups only
+/// hold when asked to.
+const SECRETS: &[(&str, bool)] = &[
+    (crate::bundle::KEYRING_ACCOUNT, false),
+    (crate::discord::bot::KEYRING_ACCOUNT, true),
+    (crate::integrations::obs::KEYRING_ACCOUNT, true),
+    (crate::upload::google_drive::KEYRING_ACCOUNT, true),
+    (crate::upload::webdav::KEYRING_ACCOUNT, true),
+];
+
+/// Settings, indexes and key material in one file, for moving to a new
+/// machine. Recordings themselves stay where they are.
+#[derive(Debug, Serialize, Deserialize)]
+struct Backup {
+    format: String,
+    version: u32,
+    created_at: String,
+    /// Contents of each config file, by name.
+    files: BTreeMap<String, String>,
+    /// Keyring entries, by account.
+    secrets: BTreeMap<String, String>,
+}
+
+/// What a restore put back.
+#[derive(Debug, Clone, Serialize)]
+pub struct Restored {
+    pub created_at: String,
+    pub files: Vec<String>,
+    pub secrets: Vec<String>,
+}
+
+/// Write a backup into `destination` and return its path. The bot token, OBS
+/// password and upload credentials are only included with `include_tokens`;
+/// the export signing key always is, so bundles made on the new machine
+/// verify the same way.
+pub fn create(destination: &Path, include_tokens: bool) -> Result<PathBuf> {
+    let dir = crate::data_dir::config_dir();
+    let mut files = BTreeMap::new();
+    for &name in FILES {
+        match std::fs::read_to_string(dir.join(name)) {
+            Ok(data) => {
+                files.insert(name.to_string(), data);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", name)),
+        }
+    }
+
+    let mut secrets = BTreeMap::new();
+    for &(account, is_token) in SECRETS {
+        if is_token && !include_tokens {
+            continue;
+        }
+        let secret = crate::secrets::get(account)
+            .with_context(|| format!("Failed to read {} from the keyring", account))?;
+        if let Some(secret) = secret {
+            secrets.insert(account.to_string(), secret);
+        }
+    }
+
+    let now = chrono::Local::now();
+    let backup = Backup {
+        format: BACKUP_FORMAT.to_string(),
+        version: BACKUP_VERSION,
+        created_at: now.to_rfc3339(),
+        files,
+        secrets,
+    };
+    let path = unique_path(&destination.join(format!(
+        "discrec-backup-{}.json",
+        now.format("%Y-%m-%d_%H%M%S")
+    )));
+    std::fs::write(&path, serde_json::to_string_pretty(&backup)?)
+        .context("Failed to write backup")?;
+    log::info!(
+        "Backed up {} file(s) and {} secret(s) to {}",
+        backup.files.len(),
+        backup.secrets.len(),
+        path.display()
+    );
+    Ok(path)
+}
+
+/// Put the files and keyring entries from the backup at `path` in place.
+/// Config files it replaces are kept next to it with a `.bak` suffix. The
+/// app has to restart to pick everything up.
+pub fn restore(path: &Path) -> Result<Restored> {
+    let data = std::fs::read_to_string(path).context("Failed to read backup")?;
+    let backup: Backup = serde_json::from_str(&data).context("Not a DiscRec backup")?;
+    if backup.format != BACKUP_FORMAT {
+        anyhow::bail!("Not a DiscRec backup");
+    }
+    if backup.version > BACKUP_VERSION {
+        anyhow::bail!("This backup was made by a newer version of DiscRec");
+    }
+    // Only ever write the files and accounts a backup is made of
+    if let Some(name) = backup.files.keys().find(|n| !FILES.contains(&n.as_str())) {
+        anyhow::bail!("Unexpected file {} in backup", name);
+    }
+    if let Some(account) = backup
+        .secrets
+        .keys()
+        .find(|a| !SECRETS.iter().any(|(known, _)| known == a))
+    {
+        anyhow::bail!("Unexpected secret {} in backup", account);
+    }
+
+    let dir = crate::data_dir::config_dir();
+    std::fs::create_dir_all(dir).context("Failed to create config folder")?;
+    for (name, contents) in &backup.files {
+        let target = dir.join(name);
+        if target.exists() {
+            let _ = std::fs::copy(&target, dir.join(format!("{}.bak", name)));
+        }
+        std::fs::write(&target, contents).with_context(|| format!("Failed to restore {}", name))?;
+    }
+    for (account, secret) in &backup.secrets {
+        crate::secrets::set(account, secret)
+            .with_context(|| format!("Failed to restore {} to the keyring", account))?;
+    }
+
+    log::info!(
+        "Restored {} file(s) and {} secret(s) from the backup of {}",
+        backup.files.len(),
+        backup.secrets.len(),
+        backup.created_at
+    );
+    Ok(Restored {
+        created_at: backup.created_at,
+        files: backup.files.into_keys().collect(),
+        secrets: backup.secrets.into_keys().collect(),
+    })
+}
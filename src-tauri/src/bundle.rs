@@ -0,0 +1,201 @@
+use anyhow::{Context, Result};
+use ed25519_dalek::{Signer, SigningKey};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::discord::consent::{self, ConsentEvent};
+use crate::discord::manifest::{SessionInfo, SESSION_SUFFIX};
+use crate::import::unique_path;
+
+const BUNDLE_VERSION: u32 = 1;
+const MANIFEST_FILE: &str = "manifest.json";
+const SIGNATURE_FILE: &str = "manifest.json.sig";
+const CHECKSUMS_FILE: &str = "SHA256SUMS";
+
+// Signing key via OS keyring, next to the bot token
+pub const KEYRING_ACCOUNT: &str = "export_signing_key";
+
+/// Everything an auditor needs to check a bundle, signed as a whole.
+#[derive(Debug, Serialize)]
+struct BundleManifest {
+    bundle_version: u32,
+    exported_at: String,
+    session: SessionInfo,
+    participants: Vec<Participant>,
+    /// Consent log for the guild up to the end of the session.
+    consent: Vec<ConsentEvent>,
+    files: Vec<BundleFile>,
+    /// Hex Ed25519 key that `manifest.json.sig` verifies against.
+    public_key: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Participant {
+    user_id: String,
+    name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct BundleFile {
+    name: String,
+    size: u64,
+    sha256: String,
+}
+
+/// Export the bot session described by `manifest_path` into a new folder in
+/// `destination` and return the folder.
+///
+/// The bundle holds copies of the audio, the session manifest, checksums,
+/// the participant list, the consent log and any chat log, all described by a manifest
+/// signed with this install's export key. Every file is made read-only.
+pub fn export(manifest_path: &Path, destination: &Path) -> Result<PathBuf> {
+    let data = std::fs::read_to_string(manifest_path).context("Failed to read session")?;
+    let session: SessionInfo = serde_json::from_str(&data).context("Invalid session manifest")?;
+    let source_dir = manifest_path.parent().context("Invalid session path")?;
+    let manifest_name = manifest_path
+        .file_name()
+        .context("Invalid session path")?
+        .to_string_lossy()
+        .to_string();
+    let stem = manifest_name.trim_end_matches(SESSION_SUFFIX);
+
+    let mut names: Vec<String> = session.tracks.iter().map(|t| t.file.clone()).collect();
+    names.extend(session.mix.clone());
+    names.extend(session.mix_parts.clone());
+    names.extend(session.timeline.clone());
+    names.extend(session.consent.clone());
+    names.extend(session.chat.clone());
+    names.push(manifest_name.clone());
+    for name in &names {
+        if !source_dir.join(name).is_file() {
+            anyhow::bail!("{} is missing, the bundle would be incomplete", name);
+        }
+    }
+
+    let key = signing_key()?;
+    let bundle_dir = unique_path(&destination.join(format!("{}-bundle", stem)));
+    std::fs::create_dir_all(&bundle_dir).context("Failed to create bundle folder")?;
+    let result = write_bundle(&bundle_dir, source_dir, &names, session, &key);
+    if result.is_err() {
+        let _ = remove_bundle(&bundle_dir);
+    }
+    result?;
+    log::info!("Exported {} to {}", stem, bundle_dir.display());
+    Ok(bundle_dir)
+}
+
+fn write_bundle(
+    bundle_dir: &Path,
+    source_dir: &Path,
+    names: &[String],
+    session: SessionInfo,
+    key: &SigningKey,
+) -> Result<()> {
+    let mut files = Vec::with_capacity(names.len());
+    for name in names {
+        let target = bundle_dir.join(name);
+        let size = std::fs::copy(source_dir.join(name), &target)
+            .with_context(|| format!("Failed to copy {}", name))?;
+        files.push(BundleFile {
+            name: name.clone(),
+            size,
+            sha256: sha256_file(&target)?,
+        });
+    }
+
+    let checksums: String = files
+        .iter()
+        .map(|f| format!("{}  {}\n", f.sha256, f.name))
+        .collect();
+    std::fs::write(bundle_dir.join(CHECKSUMS_FILE), checksums)
+        .context("Failed to write checksums")?;
+
+    let mut participants: Vec<Participant> = Vec::new();
+    for track in &session.tracks {
+        let Some(user_id) = &track.user_id else {
+            continue;
+        };
+        if participants.iter().all(|p| &p.user_id != user_id) {
+            participants.push(Participant {
+                user_id: user_id.clone(),
+                name: track.name.clone(),
+            });
+        }
+    }
+
+    let manifest = BundleManifest {
+        bundle_version: BUNDLE_VERSION,
+        exported_at: chrono::Local::now().to_rfc3339(),
+        consent: consent::history(&session.channel.guild_id, &session.ended_at),
+        session,
+        participants,
+        files,
+        public_key: hex(key.verifying_key().as_bytes()),
+    };
+    let manifest_json = serde_json::to_string_pretty(&manifest)?;
+    let signature = key.sign(manifest_json.as_bytes());
+    std::fs::write(bundle_dir.join(MANIFEST_FILE), &manifest_json)
+        .context("Failed to write bundle manifest")?;
+    std::fs::write(bundle_dir.join(SIGNATURE_FILE), hex(&signature.to_bytes()))
+        .context("Failed to write signature")?;
+
+    for entry in std::fs::read_dir(bundle_dir)?.flatten() {
+        let path = entry.path();
+        let mut permissions = std::fs::metadata(&path)?.permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&path, permissions)
+            .with_context(|| format!("Failed to lock {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Undo a half-written bundle; its files may already be read-only.
+fn remove_bundle(bundle_dir: &Path) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(bundle_dir)?.flatten() {
+        let path = entry.path();
+        let mut permissions = std::fs::metadata(&path)?.permissions();
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        let _ = std::fs::set_permissions(&path, permissions);
+    }
+    std::fs::remove_dir_all(bundle_dir)
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("Failed to hash {}", path.display()))?;
+    Ok(hex(&hasher.finalize()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// This install's export key, created on first use and kept in the OS keyring.
+fn signing_key() -> Result<SigningKey> {
+    match crate::secrets::get(KEYRING_ACCOUNT).context("Failed to load export key")? {
+        Some(stored) => {
+            let bytes: Vec<u8> = (0..stored.len())
+                .step_by(2)
+                .filter_map(|i| u8::from_str_radix(stored.get(i..i + 2)?, 16).ok())
+                .collect();
+            let secret: [u8; 32] = bytes
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("Stored export key is corrupt"))?;
+            Ok(SigningKey::from_bytes(&secret))
+        }
+        None => {
+            let mut secret = [0u8; 32];
+            getrandom::getrandom(&mut secret)
+                .map_err(|e| anyhow::anyhow!("Failed to generate export key: {}", e))?;
+            crate::secrets::set(KEYRING_ACCOUNT, &hex(&secret))
+                .context("Failed to save export key to keyring")?;
+            log::info!("Created export signing key");
+            Ok(SigningKey::from_bytes(&secret))
+        }
+    }
+}
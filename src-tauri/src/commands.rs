@@ -1,22 +1,71 @@
-use crate::audio::capture::AudioCapture;
-use crate::audio::encoder::AudioFormat;
-use crate::discord::bot::{DiscordBot, GuildInfo, VoiceChannelInfo};
-use crate::settings::SettingsState;
+use crate::archive::{Archive, ArchiveState, ArchiveSummary, ArchivedRecording};
+use crate::audio::capture::{
+    preview_levels as preview_levels_blocking, AudioCapture, RoutedApp, RoutingReport,
+};
+use crate::audio::devices::{AudioDeviceInfo, DeviceFallback, DevicePreference};
+use crate::audio::encoder::{AudioFormat, WavBitDepth};
+use crate::audio::levels::LevelReport;
+use crate::audio::playback::{PlaybackStatus, Player};
+use crate::audio::source::{local_source_info, parse_sources, CaptureSource, SourceInfo};
+use crate::audio::virtual_device::{self, VirtualDeviceSetup};
+use crate::audio::worker::EncoderLimits;
+use crate::convert::ConvertFormat;
+use crate::discord::bot::{ChannelMember, DiscordBot, GuildInfo, VoiceChannelInfo};
+use crate::discord::consent::ConsentAction;
+use crate::discord::manifest::SessionListing;
+use crate::discord::packets::VoiceDecodeMode;
+use crate::discord::receiver::{BotAudio, ReceiverOptions, TrackReport};
+use crate::discord::speaking_feed::SpeakingFeed;
+use crate::discord::talk_time::SpeakerStats;
+use crate::discord::timeline::{SpeakerTimeline, DEFAULT_FPS, MAX_FPS, TIMELINE_SUFFIX};
+use crate::events::Recorder;
+use crate::import::ImportMode;
+use crate::integrations::obs::{self, ObsState, ObsStatus};
+use crate::jobs::{JobId, JobStatus, JobsState};
+use crate::library::{RecordingInfo, RecordingPage, RecordingQuery};
+use crate::migrate::{MigrateMode, MigrateProgress, MigrateReport};
+use crate::naming::{self, FilenamePreview, NameParts};
+use crate::overlay::{OverlaySource, OverlayState};
+use crate::retention::{CleanupReport, RetentionPolicy};
+use crate::session::{RecordingSession, SessionManifest};
+use crate::settings::{
+    AppSettings, DefaultDirBase, ObsSettings, OutputRoute, SettingsState, UploadSettings,
+    UploadTarget, WatchedChannel,
+};
+use crate::shortcuts::{find_conflicts, ShortcutUpdate};
+use crate::simulate::{SimulationOptions, SimulationState};
+use crate::storage::SizeEstimate;
+use crate::trash::TrashEntry;
+use crate::upload::google_drive::{self, AuthStatus, DeviceCode};
+use crate::upload::webdav;
+use crate::watch::WatchState;
+use anyhow::Context;
 use chrono::Local;
 use parking_lot::Mutex;
-use serde::Serialize;
-use std::path::Path;
-use tauri::{AppHandle, State};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{ipc::Channel, AppHandle, Emitter, Manager, State};
+use tauri_plugin_dialog::DialogExt;
 use tauri_plugin_notification::NotificationExt;
 use tokio::sync::Mutex as TokioMutex;
 
-pub struct RecorderState(pub Mutex<AudioCapture>);
+/// Local capture, plus whether the bot was started as one of its sources.
+pub struct RecorderState(pub Mutex<AudioCapture>, pub AtomicBool);
 pub struct DiscordState(pub TokioMutex<DiscordBot>);
+pub struct SpeakingFeedState(pub Arc<SpeakingFeed>);
 
 #[derive(Serialize, Clone)]
 pub struct RecordingStatus {
     pub is_recording: bool,
     pub peak_level: f32,
+    /// How Discord-only capture was set up on Linux, including any fallback
+    /// to all system audio.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub routing: Option<RoutingReport>,
 }
 
 #[derive(Serialize, Clone)]
@@ -26,61 +75,268 @@ pub struct DiscordStatus {
     pub peak_level: f32,
 }
 
+/// Start a recording from the given `sources` (see [`CaptureSource`]).
+///
+/// Local sources are captured into one session; a `bot:<guild>/<channel>`
+/// source starts the bot alongside them (`mixed` adds a stereo mix to its
+/// stems). Without `sources` the platform default is used. Returns the
+/// first local file's path, or the recordings folder when only the bot
+/// records.
 #[tauri::command]
-pub fn start_recording(
+#[allow(clippy::too_many_arguments)]
+pub async fn start_recording(
     state: State<'_, RecorderState>,
+    discord: State<'_, DiscordState>,
     settings: State<'_, SettingsState>,
+    overlay: State<'_, OverlayState>,
     format: Option<AudioFormat>,
+    sources: Option<Vec<String>>,
+    mixed: Option<bool>,
+    mix_local: Option<bool>,
 ) -> Result<String, String> {
-    let mut recorder = state.0.lock();
-    let fmt = format.unwrap_or(AudioFormat::Wav);
+    let sources = match sources {
+        Some(ids) => parse_sources(&ids).map_err(|e| e.to_string())?,
+        None => settings.0.lock().default_sources(),
+    };
+    let fmt = format.unwrap_or_else(|| settings.0.lock().default_format);
+    if state.0.lock().is_recording() {
+        return Err("Already recording".to_string());
+    }
 
-    let recordings_dir = crate::settings::recordings_dir(&settings);
-    let s = settings.0.lock();
-    let silence_trim = s.silence_trim;
-    let max_duration_secs = s.max_duration_secs;
-    drop(s);
+    let (
+        silence_trim,
+        max_duration_secs,
+        segment_minutes,
+        notices,
+        mix_local,
+        capture_device,
+        device_fallback,
+        routed_apps,
+    ) = {
+        let s = settings.0.lock();
+        (
+            s.silence_trim,
+            s.max_duration_secs,
+            s.segment_minutes,
+            s.session_notices(),
+            mix_local.unwrap_or(s.mix_local_sources),
+            s.capture_device(),
+            s.capture_device_fallback,
+            s.routed_apps.clone(),
+        )
+    };
+
+    // The bot is the likelier one to fail, so it goes first
+    let bot_source = sources.iter().find_map(|s| match *s {
+        CaptureSource::Bot {
+            guild_id,
+            channel_id,
+        } => Some((guild_id, channel_id)),
+        _ => None,
+    });
+    let started_at = Local::now();
+    let recordings_dir = crate::settings::session_dir(
+        &settings,
+        bot_source.map(|(guild_id, _)| guild_id),
+        started_at,
+    );
+    ensure_free_space(&settings, &recordings_dir)?;
+    if let Some((guild_id, channel_id)) = bot_source {
+        let (options, recorded) = {
+            let s = settings.0.lock();
+            (
+                receiver_options(&s, guild_id, fmt, mixed.unwrap_or(false)),
+                s.recorded_users(guild_id),
+            )
+        };
+        let bot = discord.0.lock().await;
+        bot.start_recording(
+            guild_id,
+            channel_id,
+            &recordings_dir.to_string_lossy(),
+            options,
+            recorded,
+            notices,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+        state.1.store(true, Ordering::Relaxed);
+    }
+
+    let local: Vec<CaptureSource> = sources.into_iter().filter(|s| s.is_local()).collect();
+    if local.is_empty() {
+        overlay.recording_started(OverlaySource::Bot);
+        return Ok(recordings_dir.to_string_lossy().to_string());
+    }
 
-    let timestamp = Local::now().format("%Y-%m-%d_%H%M%S");
-    let filename = format!("discord-{}.{}", timestamp, fmt.extension());
+    let template = settings.0.lock().filename_template().to_string();
+    let stem = naming::render(&template, started_at, &NameParts::default());
+    let filename = format!("{}.{}", stem, fmt.extension());
     let output_path = recordings_dir.join(&filename);
     let path_str = output_path.to_string_lossy().to_string();
 
-    recorder
-        .start(&path_str, fmt, silence_trim, max_duration_secs)
-        .map_err(|e| e.to_string())?;
+    let started = state.0.lock().start(
+        &path_str,
+        &local,
+        fmt,
+        silence_trim,
+        max_duration_secs,
+        segment_minutes,
+        mix_local,
+        capture_device.as_ref(),
+        device_fallback,
+        &routed_apps,
+    );
+    if let Err(e) = started {
+        if state.1.swap(false, Ordering::Relaxed) {
+            let bot = discord.0.lock().await;
+            let _ = bot.stop_recording().await;
+        }
+        return Err(e.to_string());
+    }
+    overlay.recording_started(OverlaySource::Local);
     Ok(path_str)
 }
 
+/// Stop local capture and, if it was started as a source, the bot.
+///
+/// Returns the first saved file's path.
 #[tauri::command]
-pub fn stop_recording(
+pub async fn stop_recording(
     app: AppHandle,
     state: State<'_, RecorderState>,
+    discord: State<'_, DiscordState>,
+    overlay: State<'_, OverlayState>,
 ) -> Result<Option<String>, String> {
-    let mut recorder = state.0.lock();
-    let result = recorder.stop().map_err(|e| e.to_string())?;
+    overlay.recording_stopped();
+    let local = state.0.lock().stop().map_err(|e| e.to_string());
+    let bot = if state.1.swap(false, Ordering::Relaxed) {
+        let bot = discord.0.lock().await;
+        app.state::<WatchState>()
+            .session_stopped(bot.recording_channel().await);
+        bot.stop_recording().await.map_err(|e| e.to_string())
+    } else {
+        Ok(Vec::new())
+    };
+    let (local, bot) = (local?, bot?);
 
     // Send desktop notification on successful save
-    if let Some(ref path) = result {
-        let filename = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    let body = match (local.first(), bot.len()) {
+        (Some(path), 0) if local.len() == 1 => {
+            Some(path.rsplit(['/', '\\']).next().unwrap_or(path).to_string())
+        }
+        (None, 0) => None,
+        _ => Some(format!("{} track(s) saved", local.len() + bot.len())),
+    };
+    if let Some(body) = body {
         let _ = app
             .notification()
             .builder()
             .title("Recording saved")
-            .body(filename)
+            .body(body)
             .show();
     }
 
-    Ok(result)
+    Ok(local.into_iter().chain(bot).next())
+}
+
+/// Stop local capture and any bot session without saving anything, for
+/// false starts. Returns whether a recording was running.
+#[tauri::command]
+pub async fn abort_recording(
+    app: AppHandle,
+    state: State<'_, RecorderState>,
+    discord: State<'_, DiscordState>,
+    overlay: State<'_, OverlayState>,
+) -> Result<bool, String> {
+    overlay.recording_stopped();
+    let local = state.0.lock().abort();
+    state.1.store(false, Ordering::Relaxed);
+    let bot = {
+        let bot = discord.0.lock().await;
+        let channel = bot.recording_channel().await;
+        let aborted = bot.abort_recording().await;
+        if aborted {
+            app.state::<WatchState>().session_stopped(channel);
+        }
+        aborted
+    };
+    Ok(local || bot)
 }
 
 #[tauri::command]
 pub fn get_status(state: State<'_, RecorderState>) -> RecordingStatus {
     let recorder = state.0.lock();
     RecordingStatus {
-        is_recording: recorder.is_recording(),
+        is_recording: recorder.is_recording() || state.1.load(Ordering::Relaxed),
         peak_level: recorder.peak_level(),
+        routing: recorder.routing_report(),
+    }
+}
+
+/// Sources the frontend can offer in its picker.
+#[tauri::command]
+pub async fn get_capture_sources(
+    discord: State<'_, DiscordState>,
+) -> Result<Vec<SourceInfo>, String> {
+    let mut sources = local_source_info();
+    let connected = discord.0.lock().await.is_connected();
+    sources.push(SourceInfo {
+        id: "bot".to_string(),
+        label: "Discord bot".to_string(),
+        available: connected,
+    });
+    Ok(sources)
+}
+
+/// Briefly open the given local sources and report their levels with a gain suggestion.
+#[tauri::command]
+pub async fn preview_levels(
+    state: State<'_, RecorderState>,
+    settings: State<'_, SettingsState>,
+    sources: Option<Vec<String>>,
+    duration_ms: Option<u64>,
+) -> Result<Vec<LevelReport>, String> {
+    let sources = match sources {
+        Some(ids) => parse_sources(&ids).map_err(|e| e.to_string())?,
+        None => settings.0.lock().default_sources(),
+    };
+    if state.0.lock().is_recording() {
+        return Err("Cannot preview levels while recording".to_string());
     }
+    let duration = Duration::from_millis(duration_ms.unwrap_or(3000).clamp(500, 10_000));
+    let (capture_device, device_fallback, routed_apps) = {
+        let s = settings.0.lock();
+        (
+            s.capture_device(),
+            s.capture_device_fallback,
+            s.routed_apps.clone(),
+        )
+    };
+
+    tauri::async_runtime::spawn_blocking(move || -> anyhow::Result<Vec<LevelReport>> {
+        let device = match capture_device {
+            Some(preference) if sources.iter().any(|s| s.uses_capture_device()) => {
+                crate::audio::devices::resolve(&preference, device_fallback)?.used
+            }
+            _ => None,
+        };
+        let mut reports =
+            preview_levels_blocking(&sources, device.as_deref(), &routed_apps, duration)?;
+        // The mic is recorded raw, but Discord sends it processed
+        let mic = CaptureSource::Mic.to_string();
+        if reports.iter().any(|r| r.source == mic) {
+            let note = crate::discord::processing::client_processing()
+                .and_then(|client| crate::discord::processing::mic_note(&client));
+            for report in reports.iter_mut().filter(|r| r.source == mic) {
+                report.notes.extend(note.clone());
+            }
+        }
+        Ok(reports)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -91,113 +347,419 @@ pub fn get_recordings_dir(settings: State<'_, SettingsState>) -> String {
 }
 
 #[tauri::command]
-pub fn open_folder(path: String) -> Result<(), String> {
-    let file_path = std::path::Path::new(&path);
-    let folder = file_path.parent().unwrap_or(file_path);
+pub fn open_folder(
+    settings: State<'_, SettingsState>,
+    archives: State<'_, ArchiveState>,
+    path: String,
+) -> Result<(), String> {
+    let file_path = ensure_in_known_dirs(&settings, &archives, Path::new(&path))?;
+    let folder = if file_path.is_dir() {
+        file_path.as_path()
+    } else {
+        file_path.parent().unwrap_or(&file_path)
+    };
+    crate::file_manager::open_folder(folder).map_err(|e| e.to_string())
+}
 
-    if !folder.exists() {
-        return Err(format!("Folder does not exist: {}", folder.display()));
+/// Show a recording in the file manager with the file itself selected.
+#[tauri::command]
+pub fn reveal_in_file_manager(
+    settings: State<'_, SettingsState>,
+    archives: State<'_, ArchiveState>,
+    path: String,
+) -> Result<(), String> {
+    let file_path = ensure_in_known_dirs(&settings, &archives, Path::new(&path))?;
+    if !file_path.is_file() {
+        return Err(format!("Not a file: {}", file_path.display()));
     }
+    crate::file_manager::reveal(&file_path).map_err(|e| e.to_string())
+}
 
-    #[cfg(target_os = "windows")]
+/// Security: only open paths inside the current or default recordings
+/// directory, or an archive. Returns the canonical path.
+fn ensure_in_known_dirs(
+    settings: &SettingsState,
+    archives: &ArchiveState,
+    path: &Path,
+) -> Result<PathBuf, String> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|_| format!("Path does not exist: {}", path.display()))?;
+    let mut known = vec![
+        crate::settings::recordings_dir(settings),
+        crate::settings::default_recordings_dir(),
+    ];
+    known.extend(archives.0.lock().iter().map(|a| PathBuf::from(&a.path)));
+    if known
+        .iter()
+        .filter_map(|dir| dir.canonicalize().ok())
+        .any(|dir| canonical.starts_with(dir))
     {
-        std::process::Command::new("explorer")
-            .arg(folder.as_os_str())
-            .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
+        Ok(canonical)
+    } else {
+        Err("Cannot open paths outside the recordings directory".to_string())
     }
+}
 
-    #[cfg(target_os = "macos")]
-    {
-        std::process::Command::new("open")
-            .arg(folder.as_os_str())
-            .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
+// --- Recording history commands ---
+
+/// A page of the recordings matching `query`, newest first by default. The
+/// whole library when no query is given.
+#[tauri::command]
+pub fn list_recordings(
+    settings: State<'_, SettingsState>,
+    query: Option<RecordingQuery>,
+) -> Result<RecordingPage, String> {
+    let query = query.unwrap_or_default();
+    query.validate().map_err(|e| format!("{:#}", e))?;
+    let dir = crate::settings::recordings_dir(&settings);
+
+    if !dir.exists() {
+        return Ok(query.page(Vec::new()));
     }
 
-    #[cfg(target_os = "linux")]
-    {
-        std::process::Command::new("xdg-open")
-            .arg(folder.as_os_str())
-            .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
+    // Routed recordings and session folders live in subfolders
+    let mut recordings = crate::library::scan(&dir, true).map_err(|e| e.to_string())?;
+    let complete = !query.filters();
+    recordings.retain(|recording| query.matches(recording));
+
+    // Tie bot session files to their manifest, which sits next to them
+    let mut session_of: HashMap<PathBuf, String> = HashMap::new();
+    for listing in crate::discord::manifest::list(&dir).unwrap_or_default() {
+        let Some(session_dir) = Path::new(&listing.path).parent() else {
+            continue;
+        };
+        for file in listing.session.recordings() {
+            session_of.insert(session_dir.join(file), listing.path.clone());
+        }
+    }
+    for recording in &mut recordings {
+        recording.session = session_of.get(Path::new(&recording.path)).cloned();
     }
+    crate::durations::fill(&mut recordings, complete);
 
-    Ok(())
+    query.sort(&mut recordings);
+    Ok(query.page(recordings))
 }
 
-// --- Recording history commands ---
+/// Make preview clips for `paths` in the background, sending each recording's
+/// path on `on_ready` once its preview can be played. Existing previews are
+/// reported straight away.
+#[tauri::command]
+pub async fn generate_previews(
+    settings: State<'_, SettingsState>,
+    archives: State<'_, ArchiveState>,
+    paths: Vec<String>,
+    on_ready: Channel<String>,
+) -> Result<(), String> {
+    let recordings: Vec<(String, PathBuf)> = paths
+        .into_iter()
+        .filter_map(|path| {
+            let canonical = ensure_in_known_dirs(&settings, &archives, Path::new(&path)).ok()?;
+            crate::preview::supported(&canonical).then_some((path, canonical))
+        })
+        .collect();
+    tauri::async_runtime::spawn_blocking(move || {
+        for (path, recording) in recordings {
+            match crate::preview::generate(&recording) {
+                Ok(Some(_)) => {
+                    let _ = on_ready.send(path);
+                }
+                Ok(None) => {}
+                Err(e) => log::warn!("Failed to make preview of {}: {:#}", path, e),
+            }
+        }
+    });
+    Ok(())
+}
 
-#[derive(Serialize, Clone)]
-pub struct RecordingInfo {
-    pub path: String,
-    pub filename: String,
-    pub size: u64,
-    pub modified: String,
-    pub format: String,
+/// The preview clip of a recording as MP3 bytes.
+#[tauri::command]
+pub fn read_preview(
+    settings: State<'_, SettingsState>,
+    archives: State<'_, ArchiveState>,
+    path: String,
+) -> Result<tauri::ipc::Response, String> {
+    let recording = ensure_in_known_dirs(&settings, &archives, Path::new(&path))?;
+    let preview = crate::preview::cached(&recording).ok_or("No preview yet")?;
+    let bytes = std::fs::read(preview).map_err(|e| format!("Failed to read preview: {}", e))?;
+    Ok(tauri::ipc::Response::new(bytes))
 }
 
+/// Bot sessions in the library with their stems, so recordings can be
+/// grouped per session.
 #[tauri::command]
-pub fn list_recordings(settings: State<'_, SettingsState>) -> Result<Vec<RecordingInfo>, String> {
+pub fn list_sessions(settings: State<'_, SettingsState>) -> Result<Vec<SessionListing>, String> {
     let dir = crate::settings::recordings_dir(&settings);
-
     if !dir.exists() {
         return Ok(Vec::new());
     }
+    crate::discord::manifest::list(&dir).map_err(|e| e.to_string())
+}
 
-    let mut recordings = Vec::new();
-    let entries = std::fs::read_dir(&dir).map_err(|e| e.to_string())?;
+/// Export a bot session as a signed, read-only bundle in `destination` and
+/// return the bundle folder.
+#[tauri::command]
+pub async fn export_session_bundle(
+    settings: State<'_, SettingsState>,
+    path: String,
+    destination: String,
+) -> Result<String, String> {
+    let manifest = PathBuf::from(&path);
+    ensure_in_recordings_dir(&settings, &manifest)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::bundle::export(&manifest, Path::new(&destination))
+            .map(|dir| dir.to_string_lossy().to_string())
+            .map_err(|e| format!("Failed to export session: {:#}", e))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
 
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if !path.is_file() {
-            continue;
-        }
+/// Move a recording to the trash, from where `restore_recording` can put
+/// it back.
+#[tauri::command]
+pub fn delete_recording(
+    settings: State<'_, SettingsState>,
+    archives: State<'_, ArchiveState>,
+    path: String,
+) -> Result<TrashEntry, String> {
+    let file_path = Path::new(&path);
+    archives
+        .ensure_writable(file_path)
+        .map_err(|e| e.to_string())?;
+    ensure_in_recordings_dir(&settings, file_path)?;
+    let dir = crate::settings::recordings_dir(&settings);
+    crate::trash::move_to_trash(&dir, file_path).map_err(|e| format!("{:#}", e))
+}
 
-        let ext = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_lowercase();
+/// Put a deleted recording back where it was.
+#[tauri::command]
+pub fn restore_recording(
+    settings: State<'_, SettingsState>,
+    id: String,
+) -> Result<RecordingInfo, String> {
+    let dir = crate::settings::recordings_dir(&settings);
+    let mut info = crate::trash::restore(&dir, &id).map_err(|e| format!("{:#}", e))?;
+    crate::durations::fill(std::slice::from_mut(&mut info), false);
+    Ok(info)
+}
 
-        if !matches!(ext.as_str(), "wav" | "flac" | "mp3") {
-            continue;
-        }
+#[tauri::command]
+pub fn list_trash(settings: State<'_, SettingsState>) -> Vec<TrashEntry> {
+    crate::trash::list(&crate::settings::recordings_dir(&settings))
+}
 
-        let metadata = std::fs::metadata(&path).map_err(|e| e.to_string())?;
-        let modified = metadata
-            .modified()
-            .ok()
-            .map(|t| {
-                let dt: chrono::DateTime<chrono::Local> = t.into();
-                dt.format("%Y-%m-%d %H:%M:%S").to_string()
-            })
+/// Delete everything in the trash for good. Returns the bytes freed.
+#[tauri::command]
+pub async fn empty_trash(settings: State<'_, SettingsState>) -> Result<u64, String> {
+    let dir = crate::settings::recordings_dir(&settings);
+    tauri::async_runtime::spawn_blocking(move || crate::trash::empty(&dir, None))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("{:#}", e))
+}
+
+/// Keep only `start_secs..end_secs` of a WAV or FLAC recording (to the end
+/// without `end_secs`), replacing the file. Returns the updated listing.
+#[tauri::command]
+pub async fn trim_recording(
+    settings: State<'_, SettingsState>,
+    archives: State<'_, ArchiveState>,
+    path: String,
+    start_secs: f64,
+    end_secs: Option<f64>,
+) -> Result<RecordingInfo, String> {
+    let file_path = PathBuf::from(&path);
+    archives
+        .ensure_writable(&file_path)
+        .map_err(|e| e.to_string())?;
+    ensure_in_recordings_dir(&settings, &file_path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::trim::trim(&file_path, start_secs, end_secs)?;
+        let mut info =
+            crate::library::recording_info(&file_path).context("Trimmed recording is missing")?;
+        crate::durations::fill(std::slice::from_mut(&mut info), false);
+        Ok(info)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("{:#}", e))
+}
+
+/// A finished conversion: the new file's listing, and the original if it
+/// was moved to the trash.
+#[derive(Serialize, Clone)]
+pub struct ConvertedRecording {
+    pub recording: RecordingInfo,
+    pub trashed: Option<TrashEntry>,
+}
+
+/// Convert a WAV or FLAC recording to `target_format` next to it as a
+/// background job whose result is a [`ConvertedRecording`]. `quality` is the
+/// MP3 or Opus bitrate in kbps. With `delete_original`, the source file is
+/// moved to the trash once the conversion has succeeded.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn convert_recording(
+    app: AppHandle,
+    settings: State<'_, SettingsState>,
+    archives: State<'_, ArchiveState>,
+    jobs: State<'_, JobsState>,
+    path: String,
+    target_format: ConvertFormat,
+    quality: Option<u32>,
+    delete_original: bool,
+) -> Result<JobId, String> {
+    let source = PathBuf::from(&path);
+    archives
+        .ensure_writable(&source)
+        .map_err(|e| e.to_string())?;
+    ensure_in_recordings_dir(&settings, &source)?;
+    let dir = crate::settings::recordings_dir(&settings);
+    Ok(jobs.spawn(&app, "convert", move |job| {
+        let name = source
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
             .unwrap_or_default();
+        let output = crate::convert::convert(
+            &source,
+            target_format,
+            quality,
+            |done, total| job.progress(done, total, name.as_str()),
+            || job.is_cancelled(),
+        )?;
+        let trashed = delete_original
+            .then(|| crate::trash::move_to_trash(&dir, &source))
+            .transpose()
+            .context("Converted, but failed to move the original to the trash")?;
+        Ok(ConvertedRecording {
+            recording: crate::library::recording_info(&output)
+                .context("Converted recording is missing")?,
+            trashed,
+        })
+    }))
+}
 
-        recordings.push(RecordingInfo {
-            path: path.to_string_lossy().to_string(),
-            filename: path
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string(),
-            size: metadata.len(),
-            modified,
-            format: ext,
-        });
-    }
+/// Mix the WAV or FLAC tracks at `paths`, e.g. a bot session's speaker
+/// stems, into one stereo file at `output` with per-track gain and pan, as a
+/// background job whose result is the new file's listing. The format follows
+/// `output`'s extension, and a numbered name is used if it exists.
+#[tauri::command]
+pub fn mix_recordings(
+    app: AppHandle,
+    settings: State<'_, SettingsState>,
+    archives: State<'_, ArchiveState>,
+    jobs: State<'_, JobsState>,
+    paths: Vec<String>,
+    output: String,
+    options: crate::mixdown::MixOptions,
+) -> Result<JobId, String> {
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    let output = PathBuf::from(output);
+    let folder = output
+        .parent()
+        .ok_or_else(|| "Invalid output path".to_string())?;
+    archives
+        .ensure_writable(folder)
+        .map_err(|e| e.to_string())?;
+    ensure_in_recordings_dir(&settings, folder)?;
+    Ok(jobs.spawn(&app, "mix", move |job| {
+        let output = crate::mixdown::mix(
+            &paths,
+            &output,
+            &options,
+            |done, total| job.progress(done, total, ""),
+            || job.is_cancelled(),
+        )?;
+        crate::library::recording_info(&output).context("Mixed recording is missing")
+    }))
+}
+
+/// Line up a bot session with a local recording of the same call, report
+/// their offset and drift, and recommend which to keep, as a background job
+/// whose result is the comparison. `manifest` is the session's
+/// `-session.json`; without `local`, the recording made next to it at the
+/// same time is used.
+#[tauri::command]
+pub fn compare_session_sources(
+    app: AppHandle,
+    settings: State<'_, SettingsState>,
+    jobs: State<'_, JobsState>,
+    manifest: String,
+    local: Option<String>,
+) -> Result<JobId, String> {
+    let manifest = PathBuf::from(manifest);
+    ensure_in_recordings_dir(&settings, &manifest)?;
+    let local = match local {
+        Some(local) => PathBuf::from(local),
+        None => crate::compare::find_local(&manifest)
+            .ok_or("No local recording was made alongside this session")?,
+    };
+    ensure_in_recordings_dir(&settings, &local)?;
+    Ok(jobs.spawn(&app, "compare", move |job| {
+        crate::compare::compare(
+            &local,
+            &manifest,
+            |done, total, current| job.progress(done, Some(total), current),
+            || job.is_cancelled(),
+        )
+    }))
+}
 
-    // Sort newest first
-    recordings.sort_by(|a, b| b.modified.cmp(&a.modified));
-    Ok(recordings)
+/// Files picked for import that don't match what DiscRec records, so the UI
+/// can offer to normalize them.
+#[tauri::command]
+pub fn find_nonstandard_recordings(paths: Vec<String>) -> Vec<RecordingInfo> {
+    paths
+        .iter()
+        .filter_map(|path| crate::library::recording_info(Path::new(path)))
+        .filter(|r| r.audio.is_some_and(|a| !a.is_standard()))
+        .collect()
 }
 
+/// Copy recordings (e.g. from an archive) into the library, or hard-link
+/// them with `mode: "link"`. With `normalize`, WAV and FLAC files are
+/// converted to 48 kHz stereo when they aren't already. Each gets a
+/// `.import.json` sidecar with its source, original format and duration.
+///
+/// Runs as a job; its result is the imported recordings' `RecordingInfo`s.
+/// Cancelling stops mid-file and removes that file's partial conversion; the
+/// files imported before it are kept.
 #[tauri::command]
-pub fn delete_recording(settings: State<'_, SettingsState>, path: String) -> Result<(), String> {
-    let file_path = Path::new(&path);
+pub fn import_recordings(
+    app: AppHandle,
+    settings: State<'_, SettingsState>,
+    jobs: State<'_, JobsState>,
+    paths: Vec<String>,
+    normalize: bool,
+    mode: Option<ImportMode>,
+) -> JobId {
+    let library = crate::settings::recordings_dir(&settings);
+    let mode = mode.unwrap_or_default();
+    jobs.spawn(&app, "import", move |job| {
+        let total = Some(paths.len() as u64);
+        let mut imported = Vec::new();
+        for (done, path) in paths.iter().enumerate() {
+            job.check_cancelled()?;
+            job.progress(done as u64, total, path.as_str());
+            let target = crate::import::import(Path::new(path), &library, normalize, mode, || {
+                job.is_cancelled()
+            })
+            .with_context(|| format!("Failed to import {}", path))?;
+            imported.push(
+                crate::library::recording_info(&target)
+                    .with_context(|| format!("Imported file is missing: {}", target.display()))?,
+            );
+        }
+        job.progress(paths.len() as u64, total, "");
+        Ok(imported)
+    })
+}
 
-    // Security: ensure the file is inside the recordings directory
-    let recordings_dir = crate::settings::recordings_dir(&settings);
+/// Security: refuse to touch files outside the recordings directory.
+fn ensure_in_recordings_dir(settings: &SettingsState, file_path: &Path) -> Result<(), String> {
+    let recordings_dir = crate::settings::recordings_dir(settings);
 
     let canonical_file = file_path
         .canonicalize()
@@ -207,18 +769,173 @@ pub fn delete_recording(settings: State<'_, SettingsState>, path: String) -> Res
         .map_err(|e| format!("Recordings dir not found: {}", e))?;
 
     if !canonical_file.starts_with(&canonical_dir) {
-        return Err("Cannot delete files outside the recordings directory".to_string());
+        return Err("File is outside the recordings directory".to_string());
+    }
+    Ok(())
+}
+
+// --- Playback commands ---
+
+/// Play a recording in the app, from `position_secs` or the start. Playing
+/// the paused recording again resumes it.
+#[tauri::command]
+pub fn play_recording(
+    app: AppHandle,
+    settings: State<'_, SettingsState>,
+    archives: State<'_, ArchiveState>,
+    player: State<'_, Player>,
+    path: String,
+    position_secs: Option<f64>,
+) -> Result<PlaybackStatus, String> {
+    let recording = ensure_in_known_dirs(&settings, &archives, Path::new(&path))?;
+    player
+        .play(&app, &recording, position_secs)
+        .map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub fn pause_playback(player: State<'_, Player>) -> PlaybackStatus {
+    player.pause()
+}
+
+#[tauri::command]
+pub fn seek_playback(
+    player: State<'_, Player>,
+    position_secs: f64,
+) -> Result<PlaybackStatus, String> {
+    player.seek(position_secs).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn stop_playback(player: State<'_, Player>) {
+    player.stop();
+}
+
+#[tauri::command]
+pub fn get_playback_position(player: State<'_, Player>) -> PlaybackStatus {
+    player.status()
+}
+
+// --- Archive commands ---
+
+#[tauri::command]
+pub fn list_archives(archives: State<'_, ArchiveState>) -> Vec<ArchiveSummary> {
+    archives.0.lock().iter().map(Archive::summary).collect()
+}
+
+/// Index `path` as a read-only archive; its recordings stay searchable while
+/// it is offline and can't be deleted from DiscRec.
+#[tauri::command]
+pub async fn add_archive(
+    settings: State<'_, SettingsState>,
+    archives: State<'_, ArchiveState>,
+    path: String,
+    label: Option<String>,
+) -> Result<ArchiveSummary, String> {
+    let dir = Path::new(&path)
+        .canonicalize()
+        .map_err(|_| format!("Folder not found: {}", path))?;
+    if !dir.is_dir() {
+        return Err("Path is not a directory".to_string());
+    }
+    let library = crate::settings::recordings_dir(&settings);
+    let library = library.canonicalize().unwrap_or(library);
+    if dir.starts_with(&library) || library.starts_with(&dir) {
+        return Err("The recordings folder cannot be an archive".to_string());
     }
+    if let Some(existing) = archives.overlaps(&dir) {
+        return Err(format!("Overlaps the archive '{}'", existing));
+    }
+
+    let label = label.filter(|l| !l.trim().is_empty()).unwrap_or_else(|| {
+        dir.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| dir.to_string_lossy().to_string())
+    });
+    let archive = tauri::async_runtime::spawn_blocking(move || Archive::index(&dir, &label))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("{:#}", e))?;
+    let summary = archive.summary();
+
+    archives.0.lock().push(archive);
+    archives.save();
+    Ok(summary)
+}
+
+/// Rescan a connected archive, e.g. after copying another season onto it.
+#[tauri::command]
+pub async fn reindex_archive(
+    archives: State<'_, ArchiveState>,
+    path: String,
+) -> Result<ArchiveSummary, String> {
+    let label = archives
+        .0
+        .lock()
+        .iter()
+        .find(|a| a.path == path)
+        .map(|a| a.label.clone())
+        .ok_or("Unknown archive")?;
+    let dir = PathBuf::from(&path);
+    if !dir.is_dir() {
+        return Err(format!("Archive '{}' is not connected", label));
+    }
+    let archive = tauri::async_runtime::spawn_blocking(move || Archive::index(&dir, &label))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("{:#}", e))?;
+    let summary = archive.summary();
+
+    {
+        let mut list = archives.0.lock();
+        if let Some(existing) = list.iter_mut().find(|a| a.path == path) {
+            *existing = archive;
+        }
+    }
+    archives.save();
+    Ok(summary)
+}
+
+/// Forget an archive's index. Its files are left alone.
+#[tauri::command]
+pub fn remove_archive(archives: State<'_, ArchiveState>, path: String) -> Vec<ArchiveSummary> {
+    archives.0.lock().retain(|a| a.path != path);
+    archives.save();
+    list_archives(archives)
+}
 
-    std::fs::remove_file(file_path).map_err(|e| format!("Failed to delete: {}", e))
+#[tauri::command]
+pub fn search_archives(
+    archives: State<'_, ArchiveState>,
+    query: Option<String>,
+) -> Vec<ArchivedRecording> {
+    archives.search(query.as_deref().unwrap_or(""))
 }
 
 // --- Discord bot commands ---
 
 #[tauri::command]
-pub async fn discord_connect(state: State<'_, DiscordState>, token: String) -> Result<(), String> {
+pub async fn discord_connect(
+    app: AppHandle,
+    state: State<'_, DiscordState>,
+    token: String,
+) -> Result<(), String> {
+    // Participants who reply STOP to a recording notice land in the exclusion list
+    let exclusion_app = app.clone();
+    let exclusion_hook = Arc::new(move |guild_id: u64, user_id: u64, excluded: bool| {
+        let settings = exclusion_app.state::<SettingsState>();
+        {
+            let mut s = settings.0.lock();
+            s.set_user_excluded(guild_id, user_id, excluded);
+        }
+        settings.save();
+    });
+    let presence_hook = crate::watch::presence_hook(app.clone());
+    let read_chat = app.state::<SettingsState>().0.lock().record_chat;
     let mut bot = state.0.lock().await;
-    bot.connect(&token).await.map_err(|e| e.to_string())
+    bot.connect(&token, exclusion_hook, presence_hook, read_chat)
+        .await
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -244,27 +961,99 @@ pub async fn discord_list_channels(
     bot.list_voice_channels(id).await.map_err(|e| e.to_string())
 }
 
+/// Record a voice channel per speaker; with `mixed`, also write a stereo mix.
 #[tauri::command]
 pub async fn discord_start_recording(
     state: State<'_, DiscordState>,
     settings: State<'_, SettingsState>,
+    overlay: State<'_, OverlayState>,
     guild_id: String,
     channel_id: String,
     format: Option<AudioFormat>,
+    mixed: Option<bool>,
 ) -> Result<(), String> {
     let gid: u64 = guild_id.parse().map_err(|_| "Invalid guild ID")?;
     let cid: u64 = channel_id.parse().map_err(|_| "Invalid channel ID")?;
-    let fmt = format.unwrap_or(AudioFormat::Wav);
-    let output_dir = crate::settings::recordings_dir(&settings)
-        .to_string_lossy()
-        .to_string();
+    let fmt = format.unwrap_or_else(|| settings.0.lock().default_format);
+    let bot = state.0.lock().await;
+    start_bot_session(
+        &bot,
+        &settings,
+        &overlay,
+        gid,
+        cid,
+        fmt,
+        mixed.unwrap_or(false),
+    )
+    .await
+}
 
-    let notify = settings.0.lock().notify_on_record;
+/// Start a bot recording of `channel_id` with the current settings; shared by
+/// the UI and watched channels.
+pub(crate) async fn start_bot_session(
+    bot: &DiscordBot,
+    settings: &SettingsState,
+    overlay: &OverlayState,
+    guild_id: u64,
+    channel_id: u64,
+    format: AudioFormat,
+    mixed: bool,
+) -> Result<(), String> {
+    let output_dir = crate::settings::session_dir(settings, Some(guild_id), Local::now());
+    ensure_free_space(settings, &output_dir)?;
+    let output_dir = output_dir.to_string_lossy().to_string();
+
+    let (notices, options, recorded) = {
+        let s = settings.0.lock();
+        (
+            s.session_notices(),
+            receiver_options(&s, guild_id, format, mixed),
+            s.recorded_users(guild_id),
+        )
+    };
+    if let Some(only) = &recorded.only {
+        log::info!("Recording only {} picked user(s)", only.len());
+    }
+    if !recorded.excluded.is_empty() {
+        log::info!(
+            "Leaving {} excluded user(s) out of the recording",
+            recorded.excluded.len()
+        );
+    }
 
-    let bot = state.0.lock().await;
-    bot.start_recording(gid, cid, &output_dir, fmt, notify)
-        .await
-        .map_err(|e| e.to_string())
+    bot.start_recording(
+        guild_id,
+        channel_id,
+        &output_dir,
+        options,
+        recorded,
+        notices,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    overlay.recording_started(OverlaySource::Bot);
+    Ok(())
+}
+
+fn receiver_options(
+    settings: &AppSettings,
+    guild_id: u64,
+    format: AudioFormat,
+    mixed: bool,
+) -> ReceiverOptions {
+    ReceiverOptions {
+        format,
+        decode_mode: settings.voice_decode_mode,
+        min_audible_secs: settings.min_speaker_secs,
+        write_mix: mixed,
+        align_tracks: settings.align_speaker_tracks,
+        segment_minutes: settings.segment_minutes,
+        skip_server_muted: settings.skip_server_muted.contains(&guild_id),
+        tick_dump_mb: settings.tick_dump_mb,
+        bot_audio: settings.bot_audio,
+        record_chat: settings.record_chat,
+        filename_template: settings.filename_template().to_string(),
+    }
 }
 
 #[tauri::command]
@@ -273,6 +1062,17 @@ pub async fn discord_stop_recording(
     state: State<'_, DiscordState>,
 ) -> Result<Vec<String>, String> {
     let bot = state.0.lock().await;
+    stop_bot_session(&app, &bot).await
+}
+
+/// Stop and save the bot recording, notifying the user of the saved tracks.
+pub(crate) async fn stop_bot_session(
+    app: &AppHandle,
+    bot: &DiscordBot,
+) -> Result<Vec<String>, String> {
+    app.state::<OverlayState>().recording_stopped();
+    app.state::<WatchState>()
+        .session_stopped(bot.recording_channel().await);
     let paths = bot.stop_recording().await.map_err(|e| e.to_string())?;
 
     if !paths.is_empty() {
@@ -312,47 +1112,513 @@ pub async fn discord_get_channel_members(
         .map_err(|e| e.to_string())
 }
 
+/// Pre-flight check: who is in the channel and who will be left out.
 #[tauri::command]
-pub fn save_bot_token(token: String) -> Result<(), String> {
-    crate::discord::bot::save_token(&token).map_err(|e| e.to_string())
+pub async fn discord_list_channel_members(
+    state: State<'_, DiscordState>,
+    settings: State<'_, SettingsState>,
+    guild_id: String,
+    channel_id: String,
+) -> Result<Vec<ChannelMember>, String> {
+    let gid: u64 = guild_id.parse().map_err(|_| "Invalid guild ID")?;
+    let cid: u64 = channel_id.parse().map_err(|_| "Invalid channel ID")?;
+    let recorded = settings.0.lock().recorded_users(gid);
+    let bot = state.0.lock().await;
+    bot.list_channel_members(gid, cid, &recorded)
+        .await
+        .map_err(|e| e.to_string())
 }
 
+/// Pre-flight note on how Discord's own voice processing may make the bot
+/// tracks sound unlike local mics.
 #[tauri::command]
-pub fn load_bot_token() -> Result<Option<String>, String> {
-    crate::discord::bot::load_token().map_err(|e| e.to_string())
+pub async fn discord_processing_note() -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        let client = crate::discord::processing::client_processing();
+        crate::discord::processing::preflight_note(client.as_ref())
+    })
+    .await
+    .map_err(|e| e.to_string())
 }
 
+/// Leave a user out of future recordings in a guild, or opt them back in.
 #[tauri::command]
-pub fn delete_bot_token() -> Result<(), String> {
-    crate::discord::bot::delete_token().map_err(|e| e.to_string())
+pub fn discord_set_user_excluded(
+    settings: State<'_, SettingsState>,
+    guild_id: String,
+    user_id: String,
+    excluded: bool,
+) -> Result<(), String> {
+    let gid: u64 = guild_id.parse().map_err(|_| "Invalid guild ID")?;
+    let uid: u64 = user_id.parse().map_err(|_| "Invalid user ID")?;
+    {
+        let mut s = settings.0.lock();
+        s.set_user_excluded(gid, uid, excluded);
+    }
+    settings.save();
+    let action = if excluded {
+        ConsentAction::ExcludedByHost
+    } else {
+        ConsentAction::IncludedByHost
+    };
+    crate::discord::consent::record(gid, uid, action);
+    Ok(())
 }
 
-// --- Silence trim commands ---
+/// Whether a guild's recorded users are picked one by one or everyone but
+/// an exclusion list.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordedUsersMode {
+    Only,
+    Except,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordedUsersInfo {
+    pub mode: RecordedUsersMode,
+    /// The picked users, or the excluded ones.
+    pub user_ids: Vec<String>,
+    /// The guild's exclusion list, which also applies to picked users.
+    pub excluded_ids: Vec<String>,
+}
 
+/// Who bot recordings in a guild record: only the picked users, or everyone
+/// except the excluded ones.
 #[tauri::command]
-pub fn get_silence_trim(settings: State<'_, SettingsState>) -> bool {
-    settings.0.lock().silence_trim
+pub fn discord_get_recorded_users(
+    settings: State<'_, SettingsState>,
+    guild_id: String,
+) -> Result<RecordedUsersInfo, String> {
+    let gid: u64 = guild_id.parse().map_err(|_| "Invalid guild ID")?;
+    let s = settings.0.lock();
+    let ids = |users: Option<&BTreeSet<u64>>| -> Vec<String> {
+        users
+            .into_iter()
+            .flatten()
+            .map(|id| id.to_string())
+            .collect()
+    };
+    let excluded = s.excluded_users.get(&gid);
+    let (mode, users) = match s.recorded_only.get(&gid) {
+        Some(only) => (RecordedUsersMode::Only, Some(only)),
+        None => (RecordedUsersMode::Except, excluded),
+    };
+    Ok(RecordedUsersInfo {
+        mode,
+        user_ids: ids(users),
+        excluded_ids: ids(excluded),
+    })
 }
 
+/// Record only `user_ids` in a guild, or everyone except them. Excluding
+/// replaces the guild's exclusion list, and the changes are noted in the
+/// consent log; picking users keeps the exclusions, so people who opted out
+/// stay out.
 #[tauri::command]
-pub fn set_silence_trim(settings: State<'_, SettingsState>, enabled: bool) -> bool {
+pub fn discord_set_recorded_users(
+    settings: State<'_, SettingsState>,
+    guild_id: String,
+    user_ids: Vec<String>,
+    mode: RecordedUsersMode,
+) -> Result<(), String> {
+    let gid: u64 = guild_id.parse().map_err(|_| "Invalid guild ID")?;
+    let users = user_ids
+        .iter()
+        .map(|id| {
+            id.parse::<u64>()
+                .map_err(|_| format!("Invalid user ID {}", id))
+        })
+        .collect::<Result<BTreeSet<u64>, String>>()?;
+    let mut changes = Vec::new();
     {
         let mut s = settings.0.lock();
-        s.silence_trim = enabled;
+        match mode {
+            RecordedUsersMode::Only => {
+                s.recorded_only.insert(gid, users);
+            }
+            RecordedUsersMode::Except => {
+                s.recorded_only.remove(&gid);
+                let previous = s.excluded_users.get(&gid).cloned().unwrap_or_default();
+                changes.extend(
+                    users
+                        .difference(&previous)
+                        .map(|&uid| (uid, ConsentAction::ExcludedByHost)),
+                );
+                changes.extend(
+                    previous
+                        .difference(&users)
+                        .map(|&uid| (uid, ConsentAction::IncludedByHost)),
+                );
+                if users.is_empty() {
+                    s.excluded_users.remove(&gid);
+                } else {
+                    s.excluded_users.insert(gid, users);
+                }
+            }
+        }
     }
     settings.save();
-    enabled
+    for (uid, action) in changes {
+        crate::discord::consent::record(gid, uid, action);
+    }
+    Ok(())
 }
 
-// --- Max duration commands ---
-
+/// Whether server-muted members are left out of recordings in a guild.
 #[tauri::command]
-pub fn get_max_duration(settings: State<'_, SettingsState>) -> Option<u32> {
-    settings.0.lock().max_duration_secs
+pub fn get_skip_server_muted(
+    settings: State<'_, SettingsState>,
+    guild_id: String,
+) -> Result<bool, String> {
+    let gid: u64 = guild_id.parse().map_err(|_| "Invalid guild ID")?;
+    Ok(settings.0.lock().skip_server_muted.contains(&gid))
 }
 
 #[tauri::command]
-pub fn set_max_duration(settings: State<'_, SettingsState>, seconds: Option<u32>) -> Option<u32> {
+pub fn set_skip_server_muted(
+    settings: State<'_, SettingsState>,
+    guild_id: String,
+    enabled: bool,
+) -> Result<(), String> {
+    let gid: u64 = guild_id.parse().map_err(|_| "Invalid guild ID")?;
+    {
+        let mut s = settings.0.lock();
+        if enabled {
+            s.skip_server_muted.insert(gid);
+        } else {
+            s.skip_server_muted.remove(&gid);
+        }
+    }
+    settings.save();
+    Ok(())
+}
+
+/// Speaker tracks of the last bot session, flagged when they are near-silent.
+#[tauri::command]
+pub async fn discord_get_track_stats(
+    state: State<'_, DiscordState>,
+) -> Result<Vec<TrackReport>, String> {
+    let bot = state.0.lock().await;
+    Ok(bot.last_tracks())
+}
+
+/// Talk time, share of airtime, interruptions and longest monologue per
+/// speaker, live while the bot records and for its last session after.
+#[tauri::command]
+pub async fn discord_get_speaker_stats(
+    state: State<'_, DiscordState>,
+) -> Result<Vec<SpeakerStats>, String> {
+    let bot = state.0.lock().await;
+    Ok(bot.speaker_stats().await)
+}
+
+/// Export who is speaking on every video frame, for driving animated avatars
+/// in an editor.
+///
+/// `path` is a `-timeline.json` saved with a bot session; without it the last
+/// session's timeline is used. Writes `<timeline>-<fps>fps.csv` (one 0/1
+/// column per speaker) or `.json` next to it and returns that path.
+#[tauri::command]
+pub async fn discord_export_speaker_frames(
+    state: State<'_, DiscordState>,
+    settings: State<'_, SettingsState>,
+    path: Option<String>,
+    fps: Option<u32>,
+    format: Option<String>,
+) -> Result<String, String> {
+    let path = match path {
+        Some(path) => path,
+        None => state
+            .0
+            .lock()
+            .await
+            .last_timeline()
+            .ok_or("No speaker timeline from the last session")?,
+    };
+    let timeline_path = Path::new(&path);
+    ensure_in_recordings_dir(&settings, timeline_path)?;
+    let stem = path
+        .strip_suffix(".json")
+        .filter(|_| path.ends_with(TIMELINE_SUFFIX))
+        .ok_or("Not a speaker timeline")?;
+
+    let fps = fps.unwrap_or(DEFAULT_FPS).clamp(1, MAX_FPS);
+    let timeline = SpeakerTimeline::load(timeline_path).map_err(|e| format!("{:#}", e))?;
+    let (contents, extension) = match format.as_deref().unwrap_or("csv") {
+        "csv" => (timeline.frames_csv(fps), "csv"),
+        "json" => (
+            timeline.frames_json(fps).map_err(|e| e.to_string())?,
+            "json",
+        ),
+        other => return Err(format!("Unknown export format: {}", other)),
+    };
+    let output = format!("{}-{}fps.{}", stem, fps, extension);
+    std::fs::write(&output, contents).map_err(|e| format!("Failed to write export: {}", e))?;
+    log::info!("Exported speaker frames: {}", output);
+    Ok(output)
+}
+
+/// Export a subtitle track with the wall-clock time every `interval_secs`
+/// (1 by default) for a session manifest or recording, for burning in or
+/// syncing with other cameras. Returns the `.srt` path.
+#[tauri::command]
+pub async fn export_timestamp_subtitles(
+    settings: State<'_, SettingsState>,
+    path: String,
+    interval_secs: Option<u32>,
+) -> Result<String, String> {
+    let source = PathBuf::from(&path);
+    ensure_in_recordings_dir(&settings, &source)?;
+    let interval = interval_secs
+        .unwrap_or(crate::timecode::DEFAULT_INTERVAL_SECS)
+        .clamp(1, crate::timecode::MAX_INTERVAL_SECS);
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::timecode::export(&source, interval)
+            .map(|output| output.to_string_lossy().to_string())
+            .map_err(|e| format!("Failed to export subtitles: {:#}", e))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Near-silent tracks taken out of the last bot session.
+#[derive(Serialize, Clone)]
+pub struct RemovedTracks {
+    /// Paths of the removed tracks, including any already gone.
+    pub paths: Vec<String>,
+    /// The tracks moved to the trash, which `restore_recording` can put back.
+    pub trashed: Vec<TrashEntry>,
+}
+
+/// Move near-silent tracks from the last bot session to the trash.
+///
+/// With `paths`, only those of them are removed; otherwise every near-empty
+/// track is.
+#[tauri::command]
+pub async fn cleanup_empty_tracks(
+    state: State<'_, DiscordState>,
+    settings: State<'_, SettingsState>,
+    paths: Option<Vec<String>>,
+) -> Result<RemovedTracks, String> {
+    let bot = state.0.lock().await;
+    let targets: Vec<String> = bot
+        .last_tracks()
+        .into_iter()
+        .filter(|t| t.near_empty)
+        .map(|t| t.path)
+        .filter(|p| paths.as_ref().map_or(true, |wanted| wanted.contains(p)))
+        .collect();
+
+    let dir = crate::settings::recordings_dir(&settings);
+    let mut deleted = Vec::new();
+    let mut trashed = Vec::new();
+    for path in targets {
+        let file_path = Path::new(&path);
+        if !file_path.exists() {
+            deleted.push(path);
+            continue;
+        }
+        ensure_in_recordings_dir(&settings, file_path)?;
+        let entry = crate::trash::move_to_trash(&dir, file_path)
+            .map_err(|e| format!("Failed to delete: {:#}", e))?;
+        log::info!("Removed near-empty track: {}", path);
+        trashed.push(entry);
+        deleted.push(path);
+    }
+    bot.forget_tracks(&deleted);
+    Ok(RemovedTracks {
+        paths: deleted,
+        trashed,
+    })
+}
+
+#[tauri::command]
+pub fn save_bot_token(token: String) -> Result<(), String> {
+    crate::discord::bot::save_token(&token).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn load_bot_token() -> Result<Option<String>, String> {
+    crate::discord::bot::load_token().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_bot_token() -> Result<(), String> {
+    crate::discord::bot::delete_token().map_err(|e| e.to_string())
+}
+
+// --- Silence trim commands ---
+
+#[tauri::command]
+pub fn get_silence_trim(settings: State<'_, SettingsState>) -> bool {
+    settings.0.lock().silence_trim
+}
+
+#[tauri::command]
+pub fn set_silence_trim(settings: State<'_, SettingsState>, enabled: bool) -> bool {
+    {
+        let mut s = settings.0.lock();
+        s.silence_trim = enabled;
+    }
+    settings.save();
+    enabled
+}
+
+// --- Microphone track commands ---
+
+#[tauri::command]
+pub fn get_record_mic(settings: State<'_, SettingsState>) -> bool {
+    settings.0.lock().record_mic
+}
+
+#[tauri::command]
+pub fn set_record_mic(settings: State<'_, SettingsState>, enabled: bool) -> bool {
+    {
+        let mut s = settings.0.lock();
+        s.record_mic = enabled;
+    }
+    settings.save();
+    enabled
+}
+
+#[tauri::command]
+pub fn get_mix_local_sources(settings: State<'_, SettingsState>) -> bool {
+    settings.0.lock().mix_local_sources
+}
+
+#[tauri::command]
+pub fn set_mix_local_sources(settings: State<'_, SettingsState>, enabled: bool) -> bool {
+    {
+        let mut s = settings.0.lock();
+        s.mix_local_sources = enabled;
+    }
+    settings.save();
+    enabled
+}
+
+// --- Capture device commands ---
+
+#[tauri::command]
+pub async fn list_audio_devices() -> Result<Vec<AudioDeviceInfo>, String> {
+    tauri::async_runtime::spawn_blocking(crate::audio::devices::list_audio_devices)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_capture_device(settings: State<'_, SettingsState>) -> Option<DevicePreference> {
+    settings.0.lock().capture_device()
+}
+
+/// Record system audio from the device with `id`, as listed by
+/// `list_audio_devices`; `None` picks one automatically.
+#[tauri::command]
+pub async fn set_capture_device(
+    settings: State<'_, SettingsState>,
+    id: Option<String>,
+) -> Result<Option<DevicePreference>, String> {
+    let preference = match id {
+        Some(id) => {
+            let devices =
+                tauri::async_runtime::spawn_blocking(crate::audio::devices::list_audio_devices)
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .map_err(|e| e.to_string())?;
+            let device = devices
+                .into_iter()
+                .find(|d| d.capturable && d.id == id)
+                .ok_or_else(|| format!("Cannot record from '{}'", id))?;
+            Some(DevicePreference {
+                id: device.id,
+                name: device.name,
+            })
+        }
+        None => None,
+    };
+
+    {
+        let mut s = settings.0.lock();
+        s.capture_device = preference.as_ref().map(|p| p.name.clone());
+        s.capture_device_id = preference.as_ref().map(|p| p.id.clone());
+    }
+    settings.save();
+    Ok(preference)
+}
+
+#[tauri::command]
+pub fn get_capture_device_fallback(settings: State<'_, SettingsState>) -> DeviceFallback {
+    settings.0.lock().capture_device_fallback
+}
+
+/// What to record from when the picked capture device isn't connected.
+#[tauri::command]
+pub fn set_capture_device_fallback(
+    settings: State<'_, SettingsState>,
+    fallback: DeviceFallback,
+) -> DeviceFallback {
+    {
+        let mut s = settings.0.lock();
+        s.capture_device_fallback = fallback;
+    }
+    settings.save();
+    fallback
+}
+
+// --- Routed app commands ---
+
+#[tauri::command]
+pub fn get_routed_apps(settings: State<'_, SettingsState>) -> Vec<RoutedApp> {
+    settings.0.lock().routed_apps.clone()
+}
+
+/// Apps whose audio is routed into Discord recordings too, on Linux; each
+/// is mixed in with Discord or kept on its own channel pair. Applies to
+/// recordings started after the change.
+#[tauri::command]
+pub fn set_routed_apps(settings: State<'_, SettingsState>, apps: Vec<RoutedApp>) -> Vec<RoutedApp> {
+    let mut routed: Vec<RoutedApp> = Vec::new();
+    for mut app in apps {
+        app.name = app.name.trim().to_string();
+        let duplicate = routed
+            .iter()
+            .any(|r| r.name.eq_ignore_ascii_case(&app.name));
+        if !app.name.is_empty() && !duplicate && !app.name.eq_ignore_ascii_case("discord") {
+            routed.push(app);
+        }
+    }
+    {
+        let mut s = settings.0.lock();
+        s.routed_apps = routed.clone();
+    }
+    settings.save();
+    routed
+}
+
+/// Apps playing audio right now that could be routed along with Discord.
+#[tauri::command]
+pub async fn list_playing_apps() -> Result<Vec<String>, String> {
+    #[cfg(target_os = "linux")]
+    {
+        tauri::async_runtime::spawn_blocking(crate::audio::capture::playing_apps)
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())
+    }
+    #[cfg(not(target_os = "linux"))]
+    Err("Routing other apps is only available on Linux".to_string())
+}
+
+// --- Max duration commands ---
+
+#[tauri::command]
+pub fn get_max_duration(settings: State<'_, SettingsState>) -> Option<u32> {
+    settings.0.lock().max_duration_secs
+}
+
+#[tauri::command]
+pub fn set_max_duration(settings: State<'_, SettingsState>, seconds: Option<u32>) -> Option<u32> {
     {
         let mut s = settings.0.lock();
         s.max_duration_secs = seconds;
@@ -361,90 +1627,1196 @@ pub fn set_max_duration(settings: State<'_, SettingsState>, seconds: Option<u32>
     seconds
 }
 
-// --- Shortcuts commands ---
+// --- Segment commands ---
 
 #[tauri::command]
-pub fn get_shortcuts(settings: State<'_, SettingsState>) -> crate::settings::ShortcutConfig {
-    settings.0.lock().shortcuts.clone()
+pub fn get_segment_minutes(settings: State<'_, SettingsState>) -> Option<u32> {
+    settings.0.lock().segment_minutes
 }
 
+/// Split new recordings every `minutes`; `None` or 0 keeps one file per track.
 #[tauri::command]
-pub fn set_shortcuts(
+pub fn set_segment_minutes(
     settings: State<'_, SettingsState>,
-    record: String,
-    stop: String,
-) -> crate::settings::ShortcutConfig {
-    let config = crate::settings::ShortcutConfig { record, stop };
+    minutes: Option<u32>,
+) -> Option<u32> {
+    let minutes = minutes.filter(|&m| m > 0);
     {
         let mut s = settings.0.lock();
-        s.shortcuts = config.clone();
+        s.segment_minutes = minutes;
     }
     settings.save();
-    settings.0.lock().shortcuts.clone()
+    minutes
 }
 
-// --- Notify on record commands ---
+// --- Disk space commands ---
 
+/// Length assumed for a recording without a max duration.
+const DEFAULT_ESTIMATE_SECS: u32 = 2 * 60 * 60;
+
+/// Expected disk usage of a recording and whether the library's disk has room
+/// for it. `duration_secs` defaults to the max duration setting, or two hours;
+/// bot sessions pass one channel per speaker.
 #[tauri::command]
-pub fn get_notify_on_record(settings: State<'_, SettingsState>) -> bool {
-    settings.0.lock().notify_on_record
+pub fn estimate_recording_size(
+    settings: State<'_, SettingsState>,
+    format: AudioFormat,
+    duration_secs: Option<u32>,
+    channels: Option<u16>,
+    speakers: Option<u32>,
+) -> SizeEstimate {
+    let duration_secs = duration_secs
+        .or(settings.0.lock().max_duration_secs)
+        .unwrap_or(DEFAULT_ESTIMATE_SECS);
+    crate::storage::estimate(
+        format,
+        duration_secs,
+        channels.unwrap_or(2),
+        speakers.unwrap_or(1),
+        &crate::settings::recordings_dir(&settings),
+    )
 }
 
 #[tauri::command]
-pub fn set_notify_on_record(settings: State<'_, SettingsState>, enabled: bool) -> bool {
+pub fn get_min_free_space_mb(settings: State<'_, SettingsState>) -> u32 {
+    settings.0.lock().min_free_space_mb()
+}
+
+/// Free space in MiB needed to start recording; a running recording stops
+/// at a quarter of it. 0 turns the guard off.
+#[tauri::command]
+pub fn set_min_free_space_mb(settings: State<'_, SettingsState>, mb: u32) -> u32 {
     {
         let mut s = settings.0.lock();
-        s.notify_on_record = enabled;
+        s.min_free_space_mb = Some(mb);
     }
     settings.save();
-    enabled
+    mb
 }
 
-// --- Output directory commands ---
+#[tauri::command]
+pub fn get_filename_template(settings: State<'_, SettingsState>) -> String {
+    settings.0.lock().filename_template().to_string()
+}
 
-#[derive(Serialize, Clone)]
-pub struct OutputDirInfo {
-    pub path: String,
-    pub is_custom: bool,
+/// Example file names for `template`, or why it can't be used.
+#[tauri::command]
+pub fn preview_filename_template(template: String) -> Result<FilenamePreview, String> {
+    naming::preview(template.trim())
 }
 
+/// Name new recordings by `template`; an empty one restores the default.
 #[tauri::command]
-pub fn get_output_dir(settings: State<'_, SettingsState>) -> OutputDirInfo {
-    let s = settings.0.lock();
-    let is_custom = s.output_dir.as_ref().is_some_and(|d| !d.is_empty());
-    drop(s);
-    OutputDirInfo {
-        path: crate::settings::recordings_dir(&settings)
-            .to_string_lossy()
-            .to_string(),
-        is_custom,
+pub fn set_filename_template(
+    settings: State<'_, SettingsState>,
+    template: String,
+) -> Result<FilenamePreview, String> {
+    let template = template.trim();
+    let template = if template.is_empty() {
+        naming::DEFAULT_TEMPLATE
+    } else {
+        template
+    };
+    let preview = naming::preview(template)?;
+    {
+        let mut s = settings.0.lock();
+        s.filename_template = (template != naming::DEFAULT_TEMPLATE).then(|| template.to_string());
     }
+    settings.save();
+    Ok(preview)
 }
 
 #[tauri::command]
-pub fn set_output_dir(
+pub fn get_session_folders(settings: State<'_, SettingsState>) -> bool {
+    settings.0.lock().session_folders
+}
+
+/// Save each new session in its own `<date>/<session>/` folder.
+#[tauri::command]
+pub fn set_session_folders(settings: State<'_, SettingsState>, enabled: bool) -> bool {
+    {
+        let mut s = settings.0.lock();
+        s.session_folders = enabled;
+    }
+    settings.save();
+    enabled
+}
+
+/// Refuse to start recording into `dir` when its disk is nearly full.
+fn ensure_free_space(settings: &SettingsState, dir: &Path) -> Result<(), String> {
+    let min_free_mb = settings.0.lock().min_free_space_mb();
+    crate::storage::ensure_free_space(dir, min_free_mb).map_err(|e| e.to_string())
+}
+
+// --- Shortcuts commands ---
+
+#[tauri::command]
+pub fn get_shortcuts(settings: State<'_, SettingsState>) -> crate::settings::ShortcutConfig {
+    settings.0.lock().shortcuts.clone()
+}
+
+/// Store new shortcuts unless either one conflicts; conflicts come back with
+/// suggested alternatives and leave the stored shortcuts untouched.
+#[tauri::command]
+pub async fn set_shortcuts(
+    app: AppHandle,
     settings: State<'_, SettingsState>,
-    path: Option<String>,
-) -> Result<OutputDirInfo, String> {
-    // Validate the path if provided
-    if let Some(ref p) = path {
-        if !p.is_empty() {
-            let dir = std::path::Path::new(p);
-            if !dir.exists() {
-                std::fs::create_dir_all(dir)
-                    .map_err(|e| format!("Cannot create directory: {}", e))?;
-            }
-            if !dir.is_dir() {
-                return Err("Path is not a directory".to_string());
-            }
+    record: String,
+    stop: String,
+) -> Result<ShortcutUpdate, String> {
+    let config = crate::settings::ShortcutConfig { record, stop };
+    let conflicts = find_conflicts(&app, &config);
+    let saved = conflicts.is_empty();
+    if saved {
+        {
+            let mut s = settings.0.lock();
+            s.shortcuts = config;
         }
+        settings.save();
     }
+    let shortcuts = settings.0.lock().shortcuts.clone();
+    Ok(ShortcutUpdate {
+        saved,
+        shortcuts,
+        conflicts,
+    })
+}
+
+// --- Notify on record commands ---
+
+#[tauri::command]
+pub fn get_notify_on_record(settings: State<'_, SettingsState>) -> bool {
+    settings.0.lock().notify_on_record
+}
 
+#[tauri::command]
+pub fn set_notify_on_record(settings: State<'_, SettingsState>, enabled: bool) -> bool {
     {
         let mut s = settings.0.lock();
-        s.output_dir = path;
+        s.notify_on_record = enabled;
+    }
+    settings.save();
+    enabled
+}
+
+#[tauri::command]
+pub fn get_notify_participants(settings: State<'_, SettingsState>) -> bool {
+    settings.0.lock().notify_participants
+}
+
+/// DM everyone recorded by the bot when a session ends; they can reply STOP
+/// to be excluded from future recordings in that server.
+#[tauri::command]
+pub fn set_notify_participants(settings: State<'_, SettingsState>, enabled: bool) -> bool {
+    {
+        let mut s = settings.0.lock();
+        s.notify_participants = enabled;
+    }
+    settings.save();
+    enabled
+}
+
+#[tauri::command]
+pub fn get_dnd_while_recording(settings: State<'_, SettingsState>) -> bool {
+    settings.0.lock().dnd_while_recording
+}
+
+/// Turn on the OS do-not-disturb mode while recording, so notification
+/// sounds stay out of loopback recordings; restored when recording stops.
+#[tauri::command]
+pub fn set_dnd_while_recording(
+    settings: State<'_, SettingsState>,
+    enabled: bool,
+) -> Result<bool, String> {
+    if enabled && !crate::dnd::supported() {
+        return Err("Do not disturb can't be controlled on this system".to_string());
+    }
+    {
+        let mut s = settings.0.lock();
+        s.dnd_while_recording = enabled;
+    }
+    settings.save();
+    Ok(enabled)
+}
+
+#[tauri::command]
+pub fn get_ask_consent(settings: State<'_, SettingsState>) -> bool {
+    settings.0.lock().ask_consent
+}
+
+/// Ask members for consent before bot recordings and record only those who
+/// react; each session saves a log of who agreed.
+#[tauri::command]
+pub fn set_ask_consent(settings: State<'_, SettingsState>, enabled: bool) -> bool {
+    {
+        let mut s = settings.0.lock();
+        s.ask_consent = enabled;
+    }
+    settings.save();
+    enabled
+}
+
+// --- Voice decode mode commands ---
+
+#[tauri::command]
+pub fn get_voice_decode_mode(settings: State<'_, SettingsState>) -> VoiceDecodeMode {
+    settings.0.lock().voice_decode_mode
+}
+
+#[tauri::command]
+pub fn set_voice_decode_mode(
+    settings: State<'_, SettingsState>,
+    mode: VoiceDecodeMode,
+) -> VoiceDecodeMode {
+    {
+        let mut s = settings.0.lock();
+        s.voice_decode_mode = mode;
+    }
+    settings.save();
+    mode
+}
+
+// --- Bot audio commands ---
+
+#[tauri::command]
+pub fn get_bot_audio(settings: State<'_, SettingsState>) -> BotAudio {
+    settings.0.lock().bot_audio
+}
+
+#[tauri::command]
+pub fn set_bot_audio(settings: State<'_, SettingsState>, mode: BotAudio) -> BotAudio {
+    {
+        let mut s = settings.0.lock();
+        s.bot_audio = mode;
+    }
+    settings.save();
+    mode
+}
+
+// --- Chat log commands ---
+
+#[tauri::command]
+pub fn get_record_chat(settings: State<'_, SettingsState>) -> bool {
+    settings.0.lock().record_chat
+}
+
+/// Needs the message content intent, so it takes effect the next time the
+/// bot connects.
+#[tauri::command]
+pub fn set_record_chat(settings: State<'_, SettingsState>, enabled: bool) -> bool {
+    {
+        let mut s = settings.0.lock();
+        s.record_chat = enabled;
+    }
+    settings.save();
+    enabled
+}
+
+// --- Output directory commands ---
+
+#[derive(Serialize, Clone)]
+pub struct OutputDirInfo {
+    pub path: String,
+    pub is_custom: bool,
+    /// Where recordings go without a custom folder.
+    pub default_path: String,
+    /// The folder the default was placed in, when the Music folder wasn't
+    /// available.
+    pub default_fallback: Option<DefaultDirBase>,
+}
+
+#[tauri::command]
+pub fn get_output_dir(settings: State<'_, SettingsState>) -> OutputDirInfo {
+    let s = settings.0.lock();
+    let is_custom = s.output_dir.as_ref().is_some_and(|d| !d.is_empty());
+    drop(s);
+    let default = crate::settings::default_dir();
+    OutputDirInfo {
+        path: crate::settings::recordings_dir(&settings)
+            .to_string_lossy()
+            .to_string(),
+        is_custom,
+        default_path: default.path.to_string_lossy().to_string(),
+        default_fallback: (!matches!(
+            default.base,
+            DefaultDirBase::Music | DefaultDirBase::Portable
+        ))
+        .then_some(default.base),
+    }
+}
+
+#[tauri::command]
+pub fn set_output_dir(
+    settings: State<'_, SettingsState>,
+    archives: State<'_, ArchiveState>,
+    path: Option<String>,
+) -> Result<OutputDirInfo, String> {
+    // Validate the path if provided
+    if let Some(ref p) = path {
+        if !p.is_empty() {
+            let dir = std::path::Path::new(p);
+            if let Some(label) = archives.overlaps(dir) {
+                return Err(format!("Overlaps the read-only archive '{}'", label));
+            }
+            if !dir.exists() {
+                std::fs::create_dir_all(dir)
+                    .map_err(|e| format!("Cannot create directory: {}", e))?;
+            }
+            if !dir.is_dir() {
+                return Err("Path is not a directory".to_string());
+            }
+        }
+    }
+
+    {
+        let mut s = settings.0.lock();
+        s.output_dir = path;
     }
     settings.save();
 
     Ok(get_output_dir(settings))
 }
+
+/// Ask the user for a folder, starting in `current`; `None` when they cancel.
+///
+/// Sandboxed Linux installs use the desktop portal's file chooser, which
+/// grants access to the chosen folder.
+#[tauri::command]
+pub async fn pick_folder(
+    app: AppHandle,
+    title: String,
+    current: Option<String>,
+) -> Result<Option<String>, String> {
+    let current = current.map(PathBuf::from);
+    #[cfg(target_os = "linux")]
+    if crate::sandbox::is_sandboxed() {
+        let folder = crate::sandbox::portal::pick_folder(&title, current.as_deref())
+            .await
+            .map_err(|e| format!("{:#}", e))?;
+        return Ok(folder.map(|folder| folder.to_string_lossy().to_string()));
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut dialog = app.dialog().file().set_title(title);
+        if let Some(dir) = current.filter(|dir| dir.is_dir()) {
+            dialog = dialog.set_directory(dir);
+        }
+        dialog
+            .blocking_pick_folder()
+            .map(|folder| folder.into_path().map_err(|e| e.to_string()))
+            .transpose()
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map(|folder| folder.map(|folder| folder.to_string_lossy().to_string()))
+}
+
+/// Move or copy the library to `path`, then make it the output directory.
+///
+/// Progress is streamed over `on_progress`; on failure the migrated files are
+/// rolled back and the output directory stays unchanged.
+#[tauri::command]
+pub async fn migrate_recordings(
+    state: State<'_, RecorderState>,
+    discord: State<'_, DiscordState>,
+    settings: State<'_, SettingsState>,
+    archives: State<'_, ArchiveState>,
+    path: String,
+    mode: MigrateMode,
+    on_progress: Channel<MigrateProgress>,
+) -> Result<MigrateReport, String> {
+    if path.is_empty() {
+        return Err("No folder selected".to_string());
+    }
+    if let Some(label) = archives.overlaps(Path::new(&path)) {
+        return Err(format!("Overlaps the read-only archive '{}'", label));
+    }
+    if state.0.lock().is_recording() || discord.0.lock().await.is_recording() {
+        return Err("Cannot move recordings while recording".to_string());
+    }
+
+    let from = crate::settings::recordings_dir(&settings);
+    let to = PathBuf::from(&path);
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        crate::migrate::migrate_library(&from, &to, mode, |progress| {
+            let _ = on_progress.send(progress.clone());
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("{:#}", e))?;
+
+    {
+        let mut s = settings.0.lock();
+        s.output_dir = Some(path);
+    }
+    settings.save();
+    Ok(report)
+}
+
+// --- Overlay file commands ---
+
+#[tauri::command]
+pub fn get_overlay_path(settings: State<'_, SettingsState>) -> Option<String> {
+    settings.0.lock().overlay_path.clone()
+}
+
+#[tauri::command]
+pub fn set_overlay_path(
+    settings: State<'_, SettingsState>,
+    path: Option<String>,
+) -> Option<String> {
+    let path = path.filter(|p| !p.trim().is_empty());
+    {
+        let mut s = settings.0.lock();
+        s.overlay_path = path.clone();
+    }
+    settings.save();
+    path
+}
+
+// --- Speaker track alignment commands ---
+
+#[tauri::command]
+pub fn get_align_speaker_tracks(settings: State<'_, SettingsState>) -> bool {
+    settings.0.lock().align_speaker_tracks
+}
+
+#[tauri::command]
+pub fn set_align_speaker_tracks(settings: State<'_, SettingsState>, enabled: bool) -> bool {
+    {
+        let mut s = settings.0.lock();
+        s.align_speaker_tracks = enabled;
+    }
+    settings.save();
+    enabled
+}
+
+// --- Speaking feed commands ---
+
+#[tauri::command]
+pub fn get_speaking_feed_port(settings: State<'_, SettingsState>) -> Option<u16> {
+    settings.0.lock().speaking_feed_port
+}
+
+/// Serve live speaking events on `ws://127.0.0.1:<port>`, or stop with `None`.
+#[tauri::command]
+pub async fn set_speaking_feed_port(
+    feed: State<'_, SpeakingFeedState>,
+    settings: State<'_, SettingsState>,
+    port: Option<u16>,
+) -> Result<Option<u16>, String> {
+    if port == Some(0) {
+        return Err("Choose a port between 1 and 65535".to_string());
+    }
+    feed.0.listen(port).await.map_err(|e| format!("{:#}", e))?;
+    {
+        let mut s = settings.0.lock();
+        s.speaking_feed_port = port;
+    }
+    settings.save();
+    Ok(port)
+}
+
+// --- Post-record command commands ---
+
+#[tauri::command]
+pub fn get_post_record_command(settings: State<'_, SettingsState>) -> Option<String> {
+    settings.0.lock().post_record_command.clone()
+}
+
+/// Run `command` after every recording is saved, or nothing with `None`.
+/// A bare program name is looked up on the PATH when it runs.
+#[tauri::command]
+pub fn set_post_record_command(
+    settings: State<'_, SettingsState>,
+    command: Option<String>,
+) -> Result<Option<String>, String> {
+    let command = command
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty());
+    if let Some(program) = &command {
+        let path = Path::new(program);
+        if path.components().count() > 1 && !path.is_file() {
+            return Err(format!("Program not found: {}", program));
+        }
+    }
+    {
+        let mut s = settings.0.lock();
+        s.post_record_command = command.clone();
+    }
+    settings.save();
+    Ok(command)
+}
+
+// --- Loudness normalization commands ---
+
+/// Loudness targets outside this range are almost certainly mistakes.
+const LUFS_RANGE: std::ops::RangeInclusive<f64> = -36.0..=-6.0;
+
+/// Normalize a WAV or FLAC recording in place to `target_lufs` integrated
+/// loudness (-16 LUFS without it), as a background job whose result is
+/// what was measured and applied.
+#[tauri::command]
+pub fn normalize_recording(
+    app: AppHandle,
+    settings: State<'_, SettingsState>,
+    archives: State<'_, ArchiveState>,
+    jobs: State<'_, JobsState>,
+    path: String,
+    target_lufs: Option<f64>,
+) -> Result<JobId, String> {
+    let target = target_lufs.unwrap_or(crate::audio::postprocess::DEFAULT_TARGET_LUFS);
+    if !LUFS_RANGE.contains(&target) {
+        return Err(format!("{} LUFS is outside the supported range", target));
+    }
+    let source = PathBuf::from(&path);
+    archives
+        .ensure_writable(&source)
+        .map_err(|e| e.to_string())?;
+    ensure_in_recordings_dir(&settings, &source)?;
+    Ok(jobs.spawn(&app, "normalize", move |job| {
+        crate::audio::postprocess::normalize(
+            &source,
+            target,
+            |done, total| job.progress(done, total, ""),
+            || job.is_cancelled(),
+        )
+    }))
+}
+
+#[tauri::command]
+pub fn get_normalize_lufs(settings: State<'_, SettingsState>) -> Option<f64> {
+    settings.0.lock().normalize_lufs
+}
+
+/// Normalize every saved WAV or FLAC recording to `target_lufs`, before the
+/// post-record command sees it; `None` turns it off.
+#[tauri::command]
+pub fn set_normalize_lufs(
+    settings: State<'_, SettingsState>,
+    target_lufs: Option<f64>,
+) -> Result<Option<f64>, String> {
+    if let Some(target) = target_lufs.filter(|t| !LUFS_RANGE.contains(t)) {
+        return Err(format!("{} LUFS is outside the supported range", target));
+    }
+    {
+        let mut s = settings.0.lock();
+        s.normalize_lufs = target_lufs;
+    }
+    settings.save();
+    Ok(target_lufs)
+}
+
+// --- Retention commands ---
+
+#[tauri::command]
+pub fn get_retention_policy(settings: State<'_, SettingsState>) -> RetentionPolicy {
+    settings.0.lock().retention_policy()
+}
+
+/// Prune old recordings by `policy` from now on, hourly in the background;
+/// leaving both limits empty turns it off.
+#[tauri::command]
+pub fn set_retention_policy(
+    settings: State<'_, SettingsState>,
+    policy: RetentionPolicy,
+) -> Result<RetentionPolicy, String> {
+    policy.validate()?;
+    {
+        let mut s = settings.0.lock();
+        s.max_recording_age_days = policy.max_recording_age_days;
+        s.max_total_size_gb = policy.max_total_size_gb;
+    }
+    settings.save();
+    Ok(policy)
+}
+
+/// Prune old recordings now by `policy`, or the saved one without it. With
+/// `dry_run` nothing is removed and the report lists what would be.
+#[tauri::command]
+pub async fn run_cleanup_now(
+    app: AppHandle,
+    settings: State<'_, SettingsState>,
+    policy: Option<RetentionPolicy>,
+    dry_run: Option<bool>,
+) -> Result<CleanupReport, String> {
+    let policy = policy.unwrap_or_else(|| settings.0.lock().retention_policy());
+    policy.validate()?;
+    if !policy.is_enabled() {
+        return Err("Set a maximum age or size first".to_string());
+    }
+    let dir = crate::settings::recordings_dir(&settings);
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::retention::clean_up(
+            &dir,
+            &policy,
+            &app.state::<ArchiveState>(),
+            dry_run.unwrap_or(false),
+        )
+        .map_err(|e| format!("{:#}", e))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// --- Empty recording commands ---
+
+#[tauri::command]
+pub fn get_delete_empty_recordings(settings: State<'_, SettingsState>) -> bool {
+    settings.0.lock().delete_empty_recordings
+}
+
+/// Delete recordings without audio at startup and after each stop.
+#[tauri::command]
+pub fn set_delete_empty_recordings(settings: State<'_, SettingsState>, enabled: bool) -> bool {
+    {
+        let mut s = settings.0.lock();
+        s.delete_empty_recordings = enabled;
+    }
+    settings.save();
+    enabled
+}
+
+/// Find recordings without any audio (empty files, WAV or FLAC headers
+/// without frames) in the library and delete them unless `dry_run`.
+/// Returns what was, or would be, removed.
+#[tauri::command]
+pub async fn remove_empty_recordings(
+    app: AppHandle,
+    settings: State<'_, SettingsState>,
+    dry_run: Option<bool>,
+) -> Result<Vec<RecordingInfo>, String> {
+    let dir = crate::settings::recordings_dir(&settings);
+    let dry_run = dry_run.unwrap_or(false);
+    let removed = tauri::async_runtime::spawn_blocking({
+        let app = app.clone();
+        move || crate::empty::sweep(&dir, &app.state::<ArchiveState>(), dry_run)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("{:#}", e))?;
+    if !dry_run && !removed.is_empty() {
+        let paths: Vec<&str> = removed.iter().map(|info| info.path.as_str()).collect();
+        let _ = app.emit(crate::empty::EMPTY_REMOVED, paths);
+    }
+    Ok(removed)
+}
+
+// --- Log redaction commands ---
+
+#[tauri::command]
+pub fn get_redact_logs(settings: State<'_, SettingsState>) -> bool {
+    settings.0.lock().redact_logs
+}
+
+/// Takes effect for log lines and dumps written from now on.
+#[tauri::command]
+pub fn set_redact_logs(settings: State<'_, SettingsState>, enabled: bool) -> bool {
+    {
+        let mut s = settings.0.lock();
+        s.redact_logs = enabled;
+    }
+    settings.save();
+    crate::redact::set_enabled(enabled);
+    enabled
+}
+
+// --- Latency commands ---
+
+/// Write latency and Discord receive jitter for the current or most recent
+/// recording.
+#[tauri::command]
+pub fn get_latency_report() -> crate::latency::LatencyReport {
+    crate::latency::report()
+}
+
+// --- Data folder commands ---
+
+/// Where settings, caches and logs live, and whether that's a portable
+/// folder next to the executable.
+#[tauri::command]
+pub fn get_data_dirs() -> crate::data_dir::DataDirs {
+    crate::data_dir::get().clone()
+}
+
+#[tauri::command]
+pub fn open_data_folder() -> Result<(), String> {
+    let dir = crate::data_dir::config_dir();
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    crate::file_manager::open_folder(dir).map_err(|e| e.to_string())
+}
+
+// --- Backup commands ---
+
+/// Save settings, indexes and key material to a single file in
+/// `destination`, for moving to a new machine.
+#[tauri::command]
+pub async fn backup_app_data(destination: String, include_tokens: bool) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::backup::create(Path::new(&destination), include_tokens)
+            .map(|path| path.to_string_lossy().to_string())
+            .map_err(|e| format!("Failed to back up: {:#}", e))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Restore a backup over the current configuration. Settings and the archive
+/// index are reloaded right away so nothing saves over them; the rest needs
+/// a restart. Refused while recording, which would keep using the old ones.
+#[tauri::command]
+pub async fn restore_app_data(
+    state: State<'_, RecorderState>,
+    discord: State<'_, DiscordState>,
+    settings: State<'_, SettingsState>,
+    archives: State<'_, ArchiveState>,
+    path: String,
+) -> Result<crate::backup::Restored, String> {
+    if state.0.lock().is_recording() || discord.0.lock().await.is_recording() {
+        return Err("Cannot restore a backup while recording".to_string());
+    }
+    let restored = crate::backup::restore(Path::new(&path))
+        .map_err(|e| format!("Failed to restore: {:#}", e))?;
+    {
+        let mut s = settings.0.lock();
+        *s = SettingsState::load().0.into_inner();
+        crate::settings::apply_runtime_settings(&s);
+    }
+    *archives.0.lock() = ArchiveState::load().0.into_inner();
+    Ok(restored)
+}
+
+// --- Tick dump commands ---
+
+#[tauri::command]
+pub fn get_tick_dump_mb(settings: State<'_, SettingsState>) -> Option<u32> {
+    settings.0.lock().tick_dump_mb
+}
+
+/// Dump voice ticks of the next bot recordings, up to `limit_mb` MiB each,
+/// or stop with `None`.
+#[tauri::command]
+pub fn set_tick_dump_mb(settings: State<'_, SettingsState>, limit_mb: Option<u32>) -> Option<u32> {
+    let limit_mb = limit_mb.filter(|&mb| mb > 0);
+    {
+        let mut s = settings.0.lock();
+        s.tick_dump_mb = limit_mb;
+    }
+    settings.save();
+    limit_mb
+}
+
+#[tauri::command]
+pub fn open_tick_dump_folder() -> Result<(), String> {
+    let dir = crate::discord::tick_dump::dump_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    crate::file_manager::open_folder(&dir).map_err(|e| e.to_string())
+}
+
+// --- OBS commands ---
+
+#[tauri::command]
+pub fn get_obs_settings(settings: State<'_, SettingsState>) -> ObsSettings {
+    settings.0.lock().obs.clone()
+}
+
+/// Store OBS settings and reconnect. `password` replaces the stored one when
+/// given; an empty one removes it.
+#[tauri::command]
+pub fn set_obs_settings(
+    app: AppHandle,
+    settings: State<'_, SettingsState>,
+    obs_state: State<'_, ObsState>,
+    config: ObsSettings,
+    password: Option<String>,
+) -> Result<(), String> {
+    if config.host.trim().is_empty() {
+        return Err("Enter the host OBS runs on".to_string());
+    }
+    if config.port == 0 {
+        return Err("Choose a port between 1 and 65535".to_string());
+    }
+    match password.as_deref() {
+        Some("") => obs::delete_password().map_err(|e| e.to_string())?,
+        Some(password) => obs::save_password(password).map_err(|e| e.to_string())?,
+        None => {}
+    }
+    let password = obs::load_password().map_err(|e| e.to_string())?;
+    {
+        let mut s = settings.0.lock();
+        s.obs = config.clone();
+    }
+    settings.save();
+    obs_state.configure(&app, config, password);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_obs_status(obs_state: State<'_, ObsState>) -> ObsStatus {
+    obs_state.status()
+}
+
+// --- Upload commands ---
+
+#[tauri::command]
+pub fn get_upload_settings(settings: State<'_, SettingsState>) -> UploadSettings {
+    settings.0.lock().upload.clone()
+}
+
+/// Store upload settings; they apply from the next saved recording.
+/// `webdav_password` replaces the stored one when given; an empty one
+/// removes it.
+#[tauri::command]
+pub fn set_upload_settings(
+    settings: State<'_, SettingsState>,
+    mut config: UploadSettings,
+    webdav_password: Option<String>,
+) -> Result<(), String> {
+    let drive = &mut config.google_drive;
+    drive.client_id = drive.client_id.trim().to_string();
+    drive.client_secret = drive.client_secret.trim().to_string();
+    drive.folder_id = drive
+        .folder_id
+        .take()
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty());
+    config.webdav.url = config.webdav.url.trim().to_string();
+    match config.target {
+        Some(UploadTarget::GoogleDrive) if config.google_drive.client_id.is_empty() => {
+            return Err("Enter the OAuth client ID to upload with".to_string());
+        }
+        Some(UploadTarget::WebDav) => webdav::validate(&config.webdav)?,
+        _ => {}
+    }
+    match webdav_password.as_deref() {
+        Some("") => webdav::delete_password().map_err(|e| e.to_string())?,
+        Some(password) => webdav::save_password(password).map_err(|e| e.to_string())?,
+        None => {}
+    }
+    {
+        let mut s = settings.0.lock();
+        s.upload = config;
+    }
+    settings.save();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_webdav_has_password() -> Result<bool, String> {
+    webdav::has_password().map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub fn get_google_drive_signed_in() -> Result<bool, String> {
+    google_drive::is_signed_in().map_err(|e| format!("{:#}", e))
+}
+
+/// Start signing in to Google Drive and return the code to enter at its
+/// verification URL. The outcome is sent as `upload://google-drive-auth`
+/// once the user entered it or the code expired.
+#[tauri::command]
+pub async fn start_google_drive_sign_in(
+    app: AppHandle,
+    settings: State<'_, SettingsState>,
+) -> Result<DeviceCode, String> {
+    let config = settings.0.lock().upload.google_drive.clone();
+    if config.client_id.is_empty() {
+        return Err("Enter the OAuth client ID to sign in with".to_string());
+    }
+    let code = {
+        let config = config.clone();
+        tauri::async_runtime::spawn_blocking(move || google_drive::request_device_code(&config))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("{:#}", e))?
+    };
+    let pending = code.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let outcome = google_drive::await_sign_in(&config, &pending);
+        if let Err(e) = &outcome {
+            log::warn!("Google Drive sign-in failed: {:#}", e);
+        }
+        let status = AuthStatus {
+            signed_in: outcome.is_ok(),
+            error: outcome.err().map(|e| format!("{:#}", e)),
+        };
+        if let Err(e) = app.emit(google_drive::AUTH, status) {
+            log::warn!("Failed to emit {}: {}", google_drive::AUTH, e);
+        }
+    });
+    Ok(code)
+}
+
+#[tauri::command]
+pub fn sign_out_google_drive() -> Result<(), String> {
+    google_drive::sign_out().map_err(|e| format!("{:#}", e))
+}
+
+// --- Minimum speaker duration commands ---
+
+#[tauri::command]
+pub fn get_min_speaker_secs(settings: State<'_, SettingsState>) -> Option<u32> {
+    settings.0.lock().min_speaker_secs
+}
+
+#[tauri::command]
+pub fn set_min_speaker_secs(
+    settings: State<'_, SettingsState>,
+    seconds: Option<u32>,
+) -> Option<u32> {
+    let seconds = seconds.filter(|&s| s > 0);
+    {
+        let mut s = settings.0.lock();
+        s.min_speaker_secs = seconds;
+    }
+    settings.save();
+    seconds
+}
+
+// --- macOS virtual device setup commands ---
+
+#[tauri::command]
+pub fn get_virtual_device_setup() -> Result<VirtualDeviceSetup, String> {
+    virtual_device::check().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn open_blackhole_installer() -> Result<(), String> {
+    virtual_device::open_installer().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn setup_multi_output_device() -> Result<VirtualDeviceSetup, String> {
+    virtual_device::setup_multi_output().map_err(|e| e.to_string())
+}
+
+// --- Encoder resource limit commands ---
+
+#[tauri::command]
+pub fn get_encoder_limits(settings: State<'_, SettingsState>) -> EncoderLimits {
+    settings.0.lock().encoder_limits()
+}
+
+/// Applies to recordings started after the change.
+#[tauri::command]
+pub fn set_encoder_limits(
+    settings: State<'_, SettingsState>,
+    threads: Option<u32>,
+    max_buffer_mb: Option<u32>,
+) -> EncoderLimits {
+    let limits = {
+        let mut s = settings.0.lock();
+        s.encoder_threads = threads;
+        s.encoder_buffer_mb = max_buffer_mb;
+        s.encoder_limits()
+    };
+    settings.save();
+    crate::audio::worker::configure(limits);
+    limits
+}
+
+// --- Format commands ---
+
+#[tauri::command]
+pub fn get_default_format(settings: State<'_, SettingsState>) -> AudioFormat {
+    settings.0.lock().default_format
+}
+
+/// Format used whenever a recording is started without one.
+#[tauri::command]
+pub fn set_default_format(settings: State<'_, SettingsState>, format: AudioFormat) -> AudioFormat {
+    {
+        let mut s = settings.0.lock();
+        s.default_format = format;
+    }
+    settings.save();
+    format
+}
+
+// --- WAV bit depth commands ---
+
+#[tauri::command]
+pub fn get_wav_bit_depth(settings: State<'_, SettingsState>) -> WavBitDepth {
+    settings.0.lock().wav_bit_depth
+}
+
+/// Applies to recordings started after the change.
+#[tauri::command]
+pub fn set_wav_bit_depth(settings: State<'_, SettingsState>, depth: WavBitDepth) -> WavBitDepth {
+    {
+        let mut s = settings.0.lock();
+        s.wav_bit_depth = depth;
+    }
+    settings.save();
+    crate::audio::encoder::set_wav_bit_depth(depth);
+    depth
+}
+
+// --- Flush interval commands ---
+
+#[tauri::command]
+pub fn get_flush_interval_secs(settings: State<'_, SettingsState>) -> Option<u32> {
+    settings.0.lock().flush_interval_secs
+}
+
+/// Applies to recordings started after the change; `None` or 0 turns
+/// periodic flushing off.
+#[tauri::command]
+pub fn set_flush_interval_secs(
+    settings: State<'_, SettingsState>,
+    secs: Option<u32>,
+) -> Option<u32> {
+    let secs = secs.filter(|&s| s > 0);
+    {
+        let mut s = settings.0.lock();
+        s.flush_interval_secs = secs;
+    }
+    settings.save();
+    crate::audio::encoder::set_flush_interval(secs);
+    secs
+}
+
+// --- Output route commands ---
+
+#[tauri::command]
+pub fn get_output_routes(settings: State<'_, SettingsState>) -> Vec<OutputRoute> {
+    settings.0.lock().output_routes.clone()
+}
+
+/// Replace the output routes; they are checked in order at each session start.
+#[tauri::command]
+pub fn set_output_routes(
+    settings: State<'_, SettingsState>,
+    routes: Vec<OutputRoute>,
+) -> Result<Vec<OutputRoute>, String> {
+    for route in &routes {
+        route.validate()?;
+    }
+    {
+        let mut s = settings.0.lock();
+        s.output_routes = routes.clone();
+    }
+    settings.save();
+    Ok(routes)
+}
+
+// --- Watched channel commands ---
+
+#[tauri::command]
+pub fn get_watched_channels(settings: State<'_, SettingsState>) -> Vec<WatchedChannel> {
+    settings.0.lock().watched_channels.clone()
+}
+
+/// Replace the watched channels; the bot starts recording a watched channel
+/// once `min_members` people are in it and stops when it empties.
+#[tauri::command]
+pub fn set_watched_channels(
+    settings: State<'_, SettingsState>,
+    channels: Vec<WatchedChannel>,
+) -> Result<Vec<WatchedChannel>, String> {
+    for channel in &channels {
+        channel.validate()?;
+    }
+    {
+        let mut s = settings.0.lock();
+        s.watched_channels = channels.clone();
+    }
+    settings.save();
+    Ok(channels)
+}
+
+// --- Job commands ---
+
+#[tauri::command]
+pub fn get_job_status(jobs: State<'_, JobsState>, id: JobId) -> Result<JobStatus, String> {
+    jobs.status(id).ok_or_else(|| "Unknown job".to_string())
+}
+
+/// Running and recently finished jobs, oldest first.
+#[tauri::command]
+pub fn list_jobs(jobs: State<'_, JobsState>) -> Vec<JobStatus> {
+    jobs.list()
+}
+
+/// Ask a job to stop; it finishes as `cancelled` at its next checkpoint.
+#[tauri::command]
+pub fn cancel_job(jobs: State<'_, JobsState>, id: JobId) -> Result<(), String> {
+    if jobs.cancel(id) {
+        Ok(())
+    } else {
+        Err("Unknown job".to_string())
+    }
+}
+
+// --- Failed session recovery commands ---
+
+#[tauri::command]
+pub fn list_failed_sessions(settings: State<'_, SettingsState>) -> Vec<SessionManifest> {
+    crate::session::failed_sessions(&crate::settings::recordings_dir(&settings))
+}
+
+/// Re-encode the raw spills a failed session kept and move its files into the library.
+#[tauri::command]
+pub async fn retry_finalize(
+    settings: State<'_, SettingsState>,
+    session_id: String,
+) -> Result<Vec<String>, String> {
+    if session_id.contains(['/', '\\']) || session_id.starts_with('.') {
+        return Err("Invalid session ID".to_string());
+    }
+    let root = crate::settings::recordings_dir(&settings);
+    tauri::async_runtime::spawn_blocking(move || {
+        let library_dir = crate::session::find_session(&root, &session_id)
+            .ok_or_else(|| anyhow::anyhow!("Session not found"))?;
+        let session = RecordingSession::open(&library_dir, &session_id)?;
+        session.retry_finalize()
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+// --- Simulation commands ---
+
+/// Play out a fake `source` recording for `duration_secs` (10 without it)
+/// with `speakers` people (2 without it) taking turns, for working on the UI
+/// and integrations without Discord or audio hardware. Sends the usual
+/// recording events and speaking feed updates and saves small dummy WAV
+/// files, plus a session manifest for the bot, to the recordings folder.
+#[tauri::command]
+pub fn simulate_recording(
+    settings: State<'_, SettingsState>,
+    simulation: State<'_, SimulationState>,
+    feed: State<'_, SpeakingFeedState>,
+    source: Recorder,
+    duration_secs: Option<u32>,
+    speakers: Option<usize>,
+) -> Result<(), String> {
+    let duration_secs = duration_secs.unwrap_or(10);
+    if !(1..=crate::simulate::MAX_SECS).contains(&duration_secs) {
+        return Err(format!(
+            "Simulations last 1 to {} seconds",
+            crate::simulate::MAX_SECS
+        ));
+    }
+    let dir = crate::settings::session_dir(&settings, None, Local::now());
+    simulation
+        .start(
+            SimulationOptions {
+                source,
+                duration_secs,
+                speakers: speakers.unwrap_or(2),
+            },
+            dir,
+            Arc::clone(&feed.0),
+        )
+        .map_err(|e| e.to_string())
+}
+
+/// End a simulated recording early, keeping what it saved. Returns whether
+/// one was running.
+#[tauri::command]
+pub fn stop_simulation(simulation: State<'_, SimulationState>) -> bool {
+    simulation.stop()
+}
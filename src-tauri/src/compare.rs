@@ -0,0 +1,412 @@
+use anyhow::{Context, Result};
+use chrono::DateTime;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::discord::manifest::{self, SessionInfo};
+use crate::library::{decode, frame_count, probe, recording_format};
+use crate::timecode::started_from_name;
+
+/// Loudness envelope points per second; also the resolution of the offset.
+const ENVELOPE_RATE: u32 = 100;
+/// Length of the stretches lined up at the start and end of the session.
+const WINDOW_SECS: u32 = 120;
+/// How far from the file names' start times the offset is searched.
+const MAX_OFFSET_SECS: u32 = 30;
+/// Below this the two don't look like the same audio.
+const MIN_MATCH: f64 = 0.5;
+/// Share of the other source one has to cover not to count as cut short.
+const MIN_COVERAGE: f64 = 0.95;
+/// Share of clipped samples worth preferring the other source over.
+const CLIPPING_THRESHOLD: f64 = 1e-4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeepSource {
+    Bot,
+    Local,
+    Both,
+}
+
+/// One side of a comparison.
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceSummary {
+    pub paths: Vec<String>,
+    pub bytes: u64,
+    pub duration_secs: f64,
+    /// Share of samples at full scale.
+    pub clipped_ratio: f64,
+}
+
+/// How a local recording and a bot session of the same call line up, and
+/// which one is worth keeping.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionComparison {
+    pub local: SourceSummary,
+    pub bot: SourceSummary,
+    /// Seconds into the local recording the bot session starts; negative
+    /// when the bot started first.
+    pub offset_secs: f64,
+    /// How far the two drift apart per hour of recording, in ms; positive
+    /// when the local recording runs long. `None` for sessions too short to
+    /// tell.
+    pub drift_ms_per_hour: Option<f64>,
+    /// Correlation of the two at the best offset, 0 to 1.
+    pub match_score: f64,
+    pub keep: KeepSource,
+    pub reason: String,
+}
+
+/// A recording reduced to its loudness every 10 ms.
+struct Envelope {
+    points: Vec<f32>,
+    clipped: u64,
+    samples: u64,
+}
+
+impl Envelope {
+    fn duration_secs(&self) -> f64 {
+        self.points.len() as f64 / ENVELOPE_RATE as f64
+    }
+
+    fn clipped_ratio(&self) -> f64 {
+        self.clipped as f64 / self.samples.max(1) as f64
+    }
+
+    /// Add `other` point by point, as if the two were mixed.
+    fn add(&mut self, other: Envelope) {
+        if other.points.len() > self.points.len() {
+            self.points.resize(other.points.len(), 0.0);
+        }
+        for (point, value) in self.points.iter_mut().zip(other.points) {
+            *point += value;
+        }
+        self.clipped += other.clipped;
+        self.samples += other.samples;
+    }
+}
+
+/// Line up the local recording at `local` with the bot session whose
+/// manifest is `manifest_path` (its mix, or else its speaker tracks summed)
+/// and recommend which to keep. Both have to be WAV or FLAC.
+///
+/// `progress` is called with files read and the total.
+pub fn compare(
+    local: &Path,
+    manifest_path: &Path,
+    progress: impl Fn(u64, u64, &str),
+    cancelled: impl Fn() -> bool,
+) -> Result<SessionComparison> {
+    let data = std::fs::read_to_string(manifest_path).context("Failed to read session manifest")?;
+    let session: SessionInfo = serde_json::from_str(&data).context("Not a session manifest")?;
+    let dir = manifest_path.parent().context("Invalid manifest path")?;
+    let (bot_files, mixed): (Vec<PathBuf>, bool) = match &session.mix {
+        Some(mix) if readable(&dir.join(mix)) => (
+            std::iter::once(mix)
+                .chain(&session.mix_parts)
+                .map(|file| dir.join(file))
+                .collect(),
+            true,
+        ),
+        _ => (
+            session
+                .tracks
+                .iter()
+                .map(|track| dir.join(&track.file))
+                .filter(|path| path.exists())
+                .collect(),
+            false,
+        ),
+    };
+    if bot_files.is_empty() {
+        anyhow::bail!("The session has no tracks left");
+    }
+    if let Some(path) = std::iter::once(local)
+        .chain(bot_files.iter().map(PathBuf::as_path))
+        .find(|path| !readable(path))
+    {
+        anyhow::bail!(
+            "{} can't be compared; only WAV and FLAC recordings can",
+            path.display()
+        );
+    }
+
+    let total = bot_files.len() as u64 + 1;
+    let name = |path: &Path| {
+        path.file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    };
+    progress(0, total, &name(local));
+    let local_envelope = envelope(local, &cancelled)?;
+    let mut bot_envelope = Envelope {
+        points: Vec::new(),
+        clipped: 0,
+        samples: 0,
+    };
+    for (i, path) in bot_files.iter().enumerate() {
+        progress(i as u64 + 1, total, &name(path));
+        let part = envelope(path, &cancelled)?;
+        if mixed {
+            // Mix parts follow each other rather than play together
+            bot_envelope.points.extend(part.points);
+            bot_envelope.clipped += part.clipped;
+            bot_envelope.samples += part.samples;
+        } else {
+            bot_envelope.add(part);
+        }
+    }
+    progress(total, total, "");
+
+    // Where the file names put the bot session in the local recording
+    let expected = DateTime::parse_from_rfc3339(&session.started_at)
+        .ok()
+        .zip(
+            local
+                .file_stem()
+                .and_then(|s| started_from_name(&s.to_string_lossy())),
+        )
+        .map(|(bot, local)| (bot - local).num_milliseconds() * ENVELOPE_RATE as i64 / 1000)
+        .unwrap_or(0);
+    let (offset, match_score, drift_ms_per_hour) =
+        line_up(&local_envelope.points, &bot_envelope.points, expected)
+            .context("The recordings don't overlap")?;
+
+    let local_len = local_envelope.points.len() as i64;
+    let bot_len = bot_envelope.points.len() as i64;
+    let overlap = ((offset + bot_len).min(local_len) - offset.max(0)).max(0) as f64;
+    let local_covers = overlap / bot_len.max(1) as f64;
+    let bot_covers = overlap / local_len.max(1) as f64;
+    let (keep, reason) = recommend(
+        match_score,
+        local_covers,
+        bot_covers,
+        local_envelope.clipped_ratio(),
+        bot_envelope.clipped_ratio(),
+        session.tracks.len(),
+    );
+
+    let summary = |paths: &[&Path], envelope: &Envelope| SourceSummary {
+        paths: paths
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect(),
+        bytes: paths
+            .iter()
+            .filter_map(|p| std::fs::metadata(p).ok())
+            .map(|m| m.len())
+            .sum(),
+        duration_secs: envelope.duration_secs(),
+        clipped_ratio: envelope.clipped_ratio(),
+    };
+    let bot_paths: Vec<&Path> = bot_files.iter().map(PathBuf::as_path).collect();
+    Ok(SessionComparison {
+        local: summary(&[local], &local_envelope),
+        bot: summary(&bot_paths, &bot_envelope),
+        offset_secs: offset as f64 / ENVELOPE_RATE as f64,
+        drift_ms_per_hour,
+        match_score,
+        keep,
+        reason,
+    })
+}
+
+/// The local recording that was made alongside the session at
+/// `manifest_path`: a WAV or FLAC file next to it, not part of any session,
+/// whose name puts it closest to the session's start.
+pub fn find_local(manifest_path: &Path) -> Option<PathBuf> {
+    let data = std::fs::read_to_string(manifest_path).ok()?;
+    let session: SessionInfo = serde_json::from_str(&data).ok()?;
+    let started_at = DateTime::parse_from_rfc3339(&session.started_at).ok()?;
+    let ended_at = DateTime::parse_from_rfc3339(&session.ended_at).ok()?;
+    let slack = chrono::Duration::seconds(MAX_OFFSET_SECS as i64);
+    crate::library::scan(manifest_path.parent()?, false)
+        .ok()?
+        .into_iter()
+        .map(|info| PathBuf::from(info.path))
+        .filter(|path| readable(path) && manifest::for_file(path).is_none())
+        .filter_map(|path| {
+            let stem = path.file_stem()?.to_string_lossy().to_string();
+            let start = started_from_name(&stem)?;
+            (start >= started_at - slack && start <= ended_at)
+                .then(|| ((start - started_at).num_seconds().abs(), stem.len(), path))
+        })
+        .min()
+        .map(|(_, _, path)| path)
+}
+
+fn readable(path: &Path) -> bool {
+    recording_format(path).is_some_and(|format| format == "wav" || format == "flac")
+}
+
+/// Mono RMS of `path` every 1 / `ENVELOPE_RATE` seconds.
+fn envelope(path: &Path, cancelled: &impl Fn() -> bool) -> Result<Envelope> {
+    let format = recording_format(path).context("Not a recording")?;
+    let frames = probe(path, &format)
+        .zip(frame_count(path, &format))
+        .map(|(audio, frames)| frames * ENVELOPE_RATE as u64 / audio.sample_rate.max(1) as u64)
+        .unwrap_or(0);
+    let mut envelope = Envelope {
+        points: Vec::with_capacity(frames as usize),
+        clipped: 0,
+        samples: 0,
+    };
+    let (mut sum, mut count) = (0f64, 0u32);
+    decode(path, &format, |channels, sample_rate, samples| {
+        if cancelled() {
+            anyhow::bail!("Cancelled");
+        }
+        let per_point = (sample_rate / ENVELOPE_RATE).max(1);
+        for frame in samples.chunks(channels.max(1) as usize) {
+            let mono = frame.iter().sum::<f32>() / frame.len() as f32;
+            envelope.clipped += frame.iter().filter(|s| s.abs() >= 0.999).count() as u64;
+            envelope.samples += frame.len() as u64;
+            sum += (mono * mono) as f64;
+            count += 1;
+            if count == per_point {
+                envelope.points.push((sum / count as f64).sqrt() as f32);
+                (sum, count) = (0.0, 0);
+            }
+        }
+        Ok(true)
+    })?;
+    Ok(envelope)
+}
+
+/// Offset of `bot` in `local` in envelope points, searched around
+/// `expected`, with the match score and the drift between a stretch near
+/// the start and one near the end.
+fn line_up(local: &[f32], bot: &[f32], expected: i64) -> Option<(i64, f64, Option<f64>)> {
+    let window = (WINDOW_SECS * ENVELOPE_RATE) as usize;
+    let range = (MAX_OFFSET_SECS * ENVELOPE_RATE) as i64;
+    if bot.len() < window * 3 {
+        let (offset, score) = best_lag(local, bot, 0, bot.len(), expected, range)?;
+        return Some((offset, score, None));
+    }
+    let early = bot.len() / 10;
+    let late = bot.len() * 9 / 10 - window;
+    let (start_offset, start_score) = best_lag(local, bot, early, window, expected, range)?;
+    // The end only moves by drift, so a narrower search will do
+    match best_lag(local, bot, late, window, start_offset, range / 3) {
+        Some((end_offset, end_score)) if end_score >= MIN_MATCH => {
+            let elapsed_secs = (late - early) as f64 / ENVELOPE_RATE as f64;
+            let drift_ms = (end_offset - start_offset) as f64 * 1000.0 / ENVELOPE_RATE as f64;
+            Some((
+                start_offset,
+                start_score.min(end_score),
+                Some(drift_ms * 3600.0 / elapsed_secs),
+            ))
+        }
+        _ => Some((start_offset, start_score, None)),
+    }
+}
+
+/// The lag within `expected ± range` at which `bot[start..start + len]`
+/// correlates best with `local`, and that correlation.
+fn best_lag(
+    local: &[f32],
+    bot: &[f32],
+    start: usize,
+    len: usize,
+    expected: i64,
+    range: i64,
+) -> Option<(i64, f64)> {
+    let part = &bot[start..(start + len).min(bot.len())];
+    let (part_mean, part_norm) = centered_norm(part);
+    let mut best: Option<(i64, f64)> = None;
+    for lag in expected - range..=expected + range {
+        let from = start as i64 + lag;
+        if from < 0 {
+            continue;
+        }
+        let from = from as usize;
+        // At least half the stretch has to overlap
+        let overlap = local.len().saturating_sub(from).min(part.len());
+        if overlap < part.len() / 2 || overlap == 0 {
+            continue;
+        }
+        let theirs = &local[from..from + overlap];
+        let (their_mean, their_norm) = centered_norm(theirs);
+        if part_norm == 0.0 || their_norm == 0.0 {
+            continue;
+        }
+        let dot: f64 = part[..overlap]
+            .iter()
+            .zip(theirs)
+            .map(|(&a, &b)| (a as f64 - part_mean) * (b as f64 - their_mean))
+            .sum();
+        let score = dot / (part_norm * their_norm);
+        if best.map_or(true, |(_, s)| score > s) {
+            best = Some((lag, score));
+        }
+    }
+    best.map(|(lag, score)| (lag, score.max(0.0)))
+}
+
+fn centered_norm(points: &[f32]) -> (f64, f64) {
+    let mean = points.iter().map(|&p| p as f64).sum::<f64>() / points.len().max(1) as f64;
+    let norm = points
+        .iter()
+        .map(|&p| (p as f64 - mean).powi(2))
+        .sum::<f64>()
+        .sqrt();
+    (mean, norm)
+}
+
+fn recommend(
+    match_score: f64,
+    local_covers: f64,
+    bot_covers: f64,
+    local_clipped: f64,
+    bot_clipped: f64,
+    speakers: usize,
+) -> (KeepSource, String) {
+    let percent = |share: f64| ((1.0 - share) * 100.0).round();
+    if match_score < MIN_MATCH {
+        return (
+            KeepSource::Both,
+            "They don't line up well enough to be sure they're the same audio".to_string(),
+        );
+    }
+    if local_covers < MIN_COVERAGE {
+        return (
+            KeepSource::Bot,
+            format!(
+                "The local recording misses {}% of the bot session",
+                percent(local_covers)
+            ),
+        );
+    }
+    if local_clipped > CLIPPING_THRESHOLD && local_clipped > bot_clipped * 2.0 {
+        return (
+            KeepSource::Bot,
+            "The local recording clips where the bot's doesn't".to_string(),
+        );
+    }
+    if bot_clipped > CLIPPING_THRESHOLD && bot_clipped > local_clipped * 2.0 {
+        return (
+            KeepSource::Local,
+            "The bot session clips where the local recording doesn't".to_string(),
+        );
+    }
+    if bot_covers < MIN_COVERAGE {
+        return (
+            KeepSource::Both,
+            format!(
+                "The bot missed {}% of the local recording, but that may be before or after the call",
+                percent(bot_covers)
+            ),
+        );
+    }
+    if speakers > 1 {
+        return (
+            KeepSource::Bot,
+            "Same audio, and the bot's separate speaker tracks are easier to edit".to_string(),
+        );
+    }
+    (
+        KeepSource::Local,
+        "Same audio, and the local recording has it in one file".to_string(),
+    )
+}
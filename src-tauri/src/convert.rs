@@ -0,0 +1,178 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::audio::encoder::{
+    create_encoder, create_mp3_encoder, create_opus_encoder, remove_partial, AudioEncoder,
+    AudioFormat,
+};
+use crate::audio::mixer::{Mixer, MIX_CHANNELS, MIX_SAMPLE_RATE};
+use crate::import::unique_path;
+use crate::library::{decode, frame_count, probe, recording_format};
+
+/// Bitrates used when the caller doesn't pick one.
+const DEFAULT_MP3_KBPS: u32 = 192;
+const DEFAULT_OPUS_KBPS: u32 = 96;
+/// Sample rates MP3 can hold.
+const MP3_RATES: [u32; 9] = [8000, 11025, 12000, 16000, 22050, 24000, 32000, 44100, 48000];
+
+/// What a finished recording can be converted to.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConvertFormat {
+    Flac,
+    Mp3,
+    Opus,
+}
+
+impl ConvertFormat {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "flac" => Some(ConvertFormat::Flac),
+            "mp3" => Some(ConvertFormat::Mp3),
+            "opus" => Some(ConvertFormat::Opus),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &str {
+        match self {
+            ConvertFormat::Flac => "flac",
+            ConvertFormat::Mp3 => "mp3",
+            ConvertFormat::Opus => "opus",
+        }
+    }
+}
+
+/// Transcode the WAV or FLAC recording at `source` to `target` next to it
+/// and return the new file's path. `kbps` sets the MP3 or Opus bitrate and
+/// is ignored for FLAC. Existing files are never overwritten.
+///
+/// Opus only takes 48 kHz mono or stereo, so other layouts are converted to
+/// 48 kHz stereo on the way, as are rates MP3 can't hold.
+///
+/// `progress` is called with frames done and the total, if known. The
+/// conversion stops with a "Cancelled" error as soon as `cancelled` returns
+/// true, leaving nothing behind.
+pub fn convert(
+    source: &Path,
+    target: ConvertFormat,
+    kbps: Option<u32>,
+    progress: impl Fn(u64, Option<u64>),
+    cancelled: impl Fn() -> bool,
+) -> Result<PathBuf> {
+    let format = recording_format(source).context("Not a supported recording format")?;
+    if format != "wav" && format != "flac" {
+        anyhow::bail!("Only WAV and FLAC recordings can be converted");
+    }
+    if format == target.extension() {
+        anyhow::bail!("The recording is already {}", format.to_uppercase());
+    }
+    let audio = probe(source, &format).context("Failed to read the recording's header")?;
+    let output = unique_path(&source.with_extension(target.extension()));
+    let output_str = output.to_string_lossy().to_string();
+
+    let resample = match target {
+        ConvertFormat::Flac => false,
+        ConvertFormat::Mp3 => {
+            !MP3_RATES.contains(&audio.sample_rate) || audio.channels > MIX_CHANNELS
+        }
+        ConvertFormat::Opus => {
+            audio.sample_rate != MIX_SAMPLE_RATE || audio.channels > MIX_CHANNELS
+        }
+    };
+    let (channels, sample_rate) = if resample {
+        (MIX_CHANNELS, MIX_SAMPLE_RATE)
+    } else {
+        (audio.channels, audio.sample_rate)
+    };
+    let total = frame_count(source, &format);
+    let result =
+        create_output(&output_str, target, channels, sample_rate, kbps).and_then(|encoder| {
+            transcode(
+                source,
+                &format,
+                encoder,
+                resample,
+                |done| progress(done, total),
+                &cancelled,
+            )
+        });
+    if result.is_err() {
+        remove_partial(&output_str);
+    }
+    result?;
+    log::info!("Converted {} to {}", source.display(), output.display());
+    Ok(output)
+}
+
+/// Open an encoder writing `target` to `path`, at `kbps` or a default
+/// bitrate for MP3 and Opus. Opus only takes 48 kHz.
+pub fn create_output(
+    path: &str,
+    target: ConvertFormat,
+    channels: u16,
+    sample_rate: u32,
+    kbps: Option<u32>,
+) -> Result<Box<dyn AudioEncoder>> {
+    match target {
+        ConvertFormat::Flac => {
+            create_encoder(path, channels, sample_rate, AudioFormat::Flac, false)
+        }
+        ConvertFormat::Mp3 => create_mp3_encoder(
+            path,
+            channels,
+            sample_rate,
+            kbps.unwrap_or(DEFAULT_MP3_KBPS),
+        ),
+        ConvertFormat::Opus => {
+            create_opus_encoder(path, channels, kbps.unwrap_or(DEFAULT_OPUS_KBPS))
+        }
+    }
+}
+
+/// Decode `source` into `encoder`, through a single-input mix when
+/// `resample` is set.
+fn transcode(
+    source: &Path,
+    format: &str,
+    encoder: Box<dyn AudioEncoder>,
+    resample: bool,
+    progress: impl Fn(u64),
+    cancelled: &impl Fn() -> bool,
+) -> Result<()> {
+    let mut done = 0u64;
+    let mut check = |channels: u16, block: &[f32]| {
+        if cancelled() {
+            anyhow::bail!("Cancelled");
+        }
+        done += (block.len() / channels as usize) as u64;
+        progress(done);
+        Ok(())
+    };
+
+    if !resample {
+        let mut encoder = encoder;
+        decode(source, format, |channels, _, block| {
+            check(channels, block)?;
+            encoder.write_samples(block)?;
+            Ok(true)
+        })?;
+        return encoder.finalize();
+    }
+
+    let mixer = Mixer::new(encoder, 1);
+    let mut input = None;
+    decode(source, format, |channels, sample_rate, block| {
+        check(channels, block)?;
+        input
+            .get_or_insert_with(|| Box::new(mixer.input(0, channels, sample_rate)))
+            .write_samples(block)?;
+        Ok(true)
+    })?;
+    if let Some(input) = input {
+        input.finalize()?;
+    }
+    mixer.finalize()?;
+    Ok(())
+}
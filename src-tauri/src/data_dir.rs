@@ -0,0 +1,160 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Placed next to the executable, makes the install portable. Left empty,
+/// data goes in a `DiscRec-data` folder beside the executable; otherwise its
+/// first line names the folder, absolute or relative to the executable.
+const PORTABLE_MARKER: &str = "portable.txt";
+const PORTABLE_DIR: &str = "DiscRec-data";
+
+/// Where the app keeps its own files, as opposed to recordings.
+#[derive(Debug, Clone, Serialize)]
+pub struct DataDirs {
+    /// Settings, indexes and the consent log.
+    pub config: PathBuf,
+    /// Previews and tick dumps, which can be thrown away.
+    pub cache: PathBuf,
+    /// Debug logs; `None` leaves them where the log plugin puts them.
+    pub logs: Option<PathBuf>,
+    /// Everything lives in one folder chosen by a portable marker.
+    pub portable: bool,
+    /// Whether the current user can write settings to `config`.
+    pub config_writable: bool,
+}
+
+/// Resolved once per run, so the marker can't move data mid-session.
+pub fn get() -> &'static DataDirs {
+    static DIRS: OnceLock<DataDirs> = OnceLock::new();
+    DIRS.get_or_init(|| {
+        let mut dirs = match portable_root() {
+            // The folder is shared by whoever uses the stick, so each OS
+            // user gets their own settings in it
+            Some(root) => {
+                log::info!("Portable mode, keeping data in {}", root.display());
+                let user_dir = root.join("users").join(os_user());
+                adopt_shared_data(&root, &user_dir);
+                DataDirs {
+                    cache: user_dir.join("cache"),
+                    logs: Some(user_dir.join("logs")),
+                    config: user_dir,
+                    portable: true,
+                    config_writable: false,
+                }
+            }
+            None => DataDirs {
+                config: dirs::config_dir()
+                    .unwrap_or_else(|| PathBuf::from("."))
+                    .join("DiscRec"),
+                cache: dirs::cache_dir()
+                    .unwrap_or_else(|| PathBuf::from("."))
+                    .join("DiscRec"),
+                logs: None,
+                portable: false,
+                config_writable: false,
+            },
+        };
+        dirs.config_writable = is_writable(&dirs.config);
+        if !dirs.config_writable {
+            log::warn!(
+                "Can't write to {} as {}, settings won't be saved",
+                dirs.config.display(),
+                os_user()
+            );
+        }
+        dirs
+    })
+}
+
+pub fn config_dir() -> &'static Path {
+    &get().config
+}
+
+pub fn cache_dir() -> &'static Path {
+    &get().cache
+}
+
+/// Where recordings go by default in portable mode, next to the data.
+pub fn portable_recordings_dir() -> Option<PathBuf> {
+    get()
+        .portable
+        .then(portable_root)
+        .flatten()
+        .map(|root| root.join("Recordings"))
+}
+
+/// Name of the OS user running the app, safe to use in paths and keyring
+/// entries.
+pub fn os_user() -> &'static str {
+    static USER: OnceLock<String> = OnceLock::new();
+    USER.get_or_init(|| {
+        let name = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .ok()
+            .filter(|name| !name.is_empty())
+            .or_else(|| {
+                let home = dirs::home_dir()?;
+                Some(home.file_name()?.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| "default".to_string());
+        name.chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    })
+}
+
+/// Whether files can be created in `dir`, creating it if needed. Checked
+/// with a real write, since permissions alone don't show e.g. a read-only
+/// mount or another user's folder.
+fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(".write-check-{}", std::process::id()));
+    let writable = std::fs::create_dir_all(dir).is_ok() && std::fs::write(&probe, b"").is_ok();
+    let _ = std::fs::remove_file(&probe);
+    writable
+}
+
+/// Portable folders made before data was kept per user hold it at the top.
+/// The first user to start up takes it over, so it isn't left behind.
+fn adopt_shared_data(root: &Path, user_dir: &Path) {
+    const SHARED: &[&str] = &[
+        "settings.json",
+        "archives.json",
+        "recording_notices.json",
+        "consent_log.jsonl",
+        "cache",
+        "logs",
+    ];
+    if user_dir.exists() || !SHARED.iter().any(|name| root.join(name).exists()) {
+        return;
+    }
+    if let Err(e) = std::fs::create_dir_all(user_dir) {
+        log::warn!("Failed to create {}: {}", user_dir.display(), e);
+        return;
+    }
+    for name in SHARED {
+        let from = root.join(name);
+        if from.exists() {
+            if let Err(e) = std::fs::rename(&from, user_dir.join(name)) {
+                log::warn!("Failed to move {} into {}: {}", name, user_dir.display(), e);
+            }
+        }
+    }
+    log::info!("Moved portable data into {}", user_dir.display());
+}
+
+fn portable_root() -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+    let marker = std::fs::read_to_string(exe_dir.join(PORTABLE_MARKER)).ok()?;
+    let chosen = marker.lines().next().map(str::trim).unwrap_or_default();
+    if chosen.is_empty() {
+        return Some(exe_dir.join(PORTABLE_DIR));
+    }
+    // Relative paths keep working when the stick gets another drive letter
+    Some(exe_dir.join(chosen))
+}
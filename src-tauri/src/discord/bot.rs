@@ -1,15 +1,27 @@
 use anyhow::{Context as AnyhowContext, Result};
 use serenity::all::{ChannelId, ChannelType, GatewayIntents, GuildId};
 use serenity::async_trait;
+use serenity::builder::{CreateEmbed, CreateMessage};
 use serenity::client::{Client, Context, EventHandler};
+use serenity::model::channel::{Message, Reaction};
 use serenity::model::gateway::Ready;
+use serenity::model::voice::VoiceState;
 use songbird::{CoreEvent, SerenityInit, Songbird};
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 use tokio::sync::{Mutex as TokioMutex, RwLock};
 
-use super::receiver::{ReceiverState, VoiceHandler};
-use crate::audio::encoder::AudioFormat;
+use super::chat_log::{CHAT_SUFFIX, CHAT_TEXT_SUFFIX};
+use super::consent::{ConsentGate, CONSENT_EMOJI, CONSENT_SUFFIX};
+use super::manifest::{ChannelRef, SESSION_SUFFIX};
+use super::names::NameResolver;
+use super::notices::{self, ExclusionHook, NoticeReply, RecordingNotices};
+use super::receiver::{ReceiverOptions, ReceiverState, RecordedUsers, TrackReport, VoiceHandler};
+use super::reconnect::Reconnector;
+use super::speaking_feed::SpeakingFeed;
+use super::talk_time::SpeakerStats;
+use super::timeline::{VoiceFlags, TIMELINE_SUFFIX};
+use crate::events::{Recorder, RecordingEvent, StatusHook, StopReason, LEVEL_INTERVAL};
 
 #[derive(serde::Serialize, Clone, Debug)]
 pub struct GuildInfo {
@@ -24,9 +36,63 @@ pub struct VoiceChannelInfo {
     pub guild_id: String,
 }
 
+/// Someone in a voice channel, as shown before recording it.
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct ChannelMember {
+    pub id: String,
+    pub name: String,
+    /// Left out of recordings in this guild, having opted out or not been
+    /// picked.
+    pub excluded: bool,
+}
+
+/// Who to tell about a bot recording.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SessionNotices {
+    /// Post in the voice channel's text chat when recording starts.
+    pub channel: bool,
+    /// DM every recorded participant when the session ends.
+    pub participants: bool,
+    /// Ask for consent in the voice channel's text chat and only record
+    /// members who react to the request.
+    pub consent: bool,
+}
+
+/// Told `(guild_id, channel_id, members)` whenever someone joins or leaves a
+/// voice channel, counting people but not bots.
+pub type PresenceHook = Arc<dyn Fn(u64, u64, usize) + Send + Sync>;
+
+/// Consent request of the session being recorded, shared with the gateway
+/// handler so reactions reach it.
+type SharedConsent = Arc<parking_lot::Mutex<Option<Arc<ConsentGate>>>>;
+
 struct ReadyNotifier {
     ctx_store: Arc<RwLock<Option<Context>>>,
     ready_flag: Arc<AtomicBool>,
+    notices: Arc<RecordingNotices>,
+    exclusion_hook: ExclusionHook,
+    presence_hook: PresenceHook,
+    consent: SharedConsent,
+    receiver_state: Arc<TokioMutex<Option<Arc<ReceiverState>>>>,
+}
+
+impl ReadyNotifier {
+    /// Grant or withdraw consent when someone reacts to the consent request.
+    fn consent_reaction(&self, ctx: &Context, reaction: &Reaction, consented: bool) {
+        let Some(gate) = self.consent.lock().clone() else {
+            return;
+        };
+        let Some(user_id) = reaction.user_id else {
+            return;
+        };
+        if !gate.is_prompt(reaction.message_id.get())
+            || !reaction.emoji.unicode_eq(&CONSENT_EMOJI.to_string())
+            || user_id == ctx.cache.current_user().id
+        {
+            return;
+        }
+        gate.set(user_id.get(), consented);
+    }
 }
 
 #[async_trait]
@@ -36,6 +102,77 @@ impl EventHandler for ReadyNotifier {
         *self.ctx_store.write().await = Some(ctx);
         self.ready_flag.store(true, Ordering::SeqCst);
     }
+
+    /// STOP / START replies to recording notices, and the recorded
+    /// channel's text chat.
+    async fn message(&self, ctx: Context, msg: Message) {
+        if let Some(guild_id) = msg.guild_id {
+            if let Some(state) = self.receiver_state.lock().await.as_ref() {
+                let recorded = state.channel();
+                if msg.channel_id.to_string() == recorded.channel_id
+                    && guild_id.to_string() == recorded.guild_id
+                {
+                    state.chat_message(&msg);
+                }
+            }
+            return;
+        }
+        if msg.author.bot {
+            return;
+        }
+        let Some(reply) = NoticeReply::parse(&msg.content) else {
+            return;
+        };
+        let confirmation = notices::handle_reply(
+            &ctx,
+            &self.notices,
+            &self.exclusion_hook,
+            msg.author.id.get(),
+            reply,
+        );
+        if let Some(text) = confirmation {
+            if let Err(e) = msg.channel_id.say(&ctx.http, text).await {
+                log::warn!("Failed to confirm opt-out: {}", e);
+            }
+        }
+    }
+
+    /// Report the headcount of the channels someone left and joined.
+    async fn voice_state_update(&self, ctx: Context, old: Option<VoiceState>, new: VoiceState) {
+        let Some(guild_id) = new.guild_id else {
+            return;
+        };
+        let mut channels: Vec<ChannelId> = old
+            .and_then(|old| old.channel_id)
+            .into_iter()
+            .chain(new.channel_id)
+            .collect();
+        // Mutes and deafens repeat the same channel
+        channels.dedup();
+        for channel in channels {
+            let people = people_in(&ctx, guild_id, channel);
+            (self.presence_hook)(guild_id.get(), channel.get(), people);
+        }
+
+        // Mutes during a session go into its speaker timeline
+        if let Some(state) = self.receiver_state.lock().await.as_ref() {
+            let recorded = state.channel();
+            if new.channel_id.map(|c| c.to_string()) == Some(recorded.channel_id.clone())
+                && guild_id.to_string() == recorded.guild_id
+                && new.user_id != ctx.cache.current_user().id
+            {
+                state.voice_state_changed(new.user_id.get(), voice_flags(&new));
+            }
+        }
+    }
+
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        self.consent_reaction(&ctx, &reaction, true);
+    }
+
+    async fn reaction_remove(&self, ctx: Context, reaction: Reaction) {
+        self.consent_reaction(&ctx, &reaction, false);
+    }
 }
 
 pub struct DiscordBot {
@@ -45,11 +182,25 @@ pub struct DiscordBot {
     receiver_state: Arc<TokioMutex<Option<Arc<ReceiverState>>>>,
     is_recording: Arc<AtomicBool>,
     peak_level_bits: Arc<AtomicU32>,
+    /// Pushes the held peak to the UI while recording.
+    level_task: parking_lot::Mutex<Option<tokio::task::JoinHandle<()>>>,
     current_guild: TokioMutex<Option<GuildId>>,
+    /// Speaker tracks saved by the most recent session.
+    last_tracks: parking_lot::Mutex<Vec<TrackReport>>,
+    /// Speaker timeline of the most recent session, if anyone spoke.
+    last_timeline: parking_lot::Mutex<Option<String>>,
+    /// Talk time per speaker in the most recent session.
+    last_speaker_stats: parking_lot::Mutex<Vec<SpeakerStats>>,
+    speaking_feed: Arc<SpeakingFeed>,
+    notices: Arc<RecordingNotices>,
+    consent: SharedConsent,
+    /// DM participants when the current session ends.
+    notify_participants: AtomicBool,
+    status_hook: Option<StatusHook>,
 }
 
 impl DiscordBot {
-    pub fn new() -> Self {
+    pub fn new(speaking_feed: Arc<SpeakingFeed>) -> Self {
         Self {
             ctx_store: Arc::new(RwLock::new(None)),
             songbird: None,
@@ -57,7 +208,27 @@ impl DiscordBot {
             receiver_state: Arc::new(TokioMutex::new(None)),
             is_recording: Arc::new(AtomicBool::new(false)),
             peak_level_bits: Arc::new(AtomicU32::new(0)),
+            level_task: parking_lot::Mutex::new(None),
             current_guild: TokioMutex::new(None),
+            last_tracks: parking_lot::Mutex::new(Vec::new()),
+            last_timeline: parking_lot::Mutex::new(None),
+            last_speaker_stats: parking_lot::Mutex::new(Vec::new()),
+            speaking_feed,
+            notices: Arc::new(RecordingNotices::load()),
+            consent: Arc::new(parking_lot::Mutex::new(None)),
+            notify_participants: AtomicBool::new(false),
+            status_hook: None,
+        }
+    }
+
+    /// Report level, start, stop and failures to `hook` from now on.
+    pub fn set_status_hook(&mut self, hook: StatusHook) {
+        self.status_hook = Some(hook);
+    }
+
+    fn emit(&self, event: RecordingEvent) {
+        if let Some(hook) = &self.status_hook {
+            hook(Recorder::Bot, event);
         }
     }
 
@@ -69,11 +240,32 @@ impl DiscordBot {
         self.is_recording.load(Ordering::Relaxed)
     }
 
+    /// The `(guild_id, channel_id)` being recorded, if any.
+    pub async fn recording_channel(&self) -> Option<(u64, u64)> {
+        let state = self.receiver_state.lock().await;
+        let channel = state.as_ref()?.channel();
+        Some((
+            channel.guild_id.parse().ok()?,
+            channel.channel_id.parse().ok()?,
+        ))
+    }
+
     pub fn peak_level(&self) -> f32 {
         f32::from_bits(self.peak_level_bits.load(Ordering::Relaxed))
     }
 
-    pub async fn connect(&mut self, token: &str) -> Result<()> {
+    /// Connect with `token`. `exclusion_hook` applies participants' replies to
+    /// recording notices; `presence_hook` follows who is in voice channels.
+    /// `read_chat` asks for the privileged message content intent, which
+    /// recording text chat needs and the bot must be granted in the
+    /// Developer Portal.
+    pub async fn connect(
+        &mut self,
+        token: &str,
+        exclusion_hook: ExclusionHook,
+        presence_hook: PresenceHook,
+        read_chat: bool,
+    ) -> Result<()> {
         if self.is_connected() {
             anyhow::bail!("Already connected to Discord");
         }
@@ -81,11 +273,22 @@ impl DiscordBot {
         self.ready_flag.store(false, Ordering::SeqCst);
         *self.ctx_store.write().await = None;
 
-        let intents = GatewayIntents::non_privileged() | GatewayIntents::GUILD_VOICE_STATES;
+        // DM content arrives without the privileged message content intent
+        let mut intents = GatewayIntents::non_privileged()
+            | GatewayIntents::GUILD_VOICE_STATES
+            | GatewayIntents::DIRECT_MESSAGES;
+        if read_chat {
+            intents |= GatewayIntents::MESSAGE_CONTENT;
+        }
 
         let handler = ReadyNotifier {
             ctx_store: Arc::clone(&self.ctx_store),
             ready_flag: Arc::clone(&self.ready_flag),
+            notices: Arc::clone(&self.notices),
+            consent: Arc::clone(&self.consent),
+            exclusion_hook,
+            presence_hook,
+            receiver_state: Arc::clone(&self.receiver_state),
         };
 
         let songbird = Songbird::serenity();
@@ -174,8 +377,9 @@ impl DiscordBot {
         guild_id: u64,
         channel_id: u64,
         output_dir: &str,
-        format: AudioFormat,
-        notify: bool,
+        options: ReceiverOptions,
+        recorded: RecordedUsers,
+        notices: SessionNotices,
     ) -> Result<()> {
         if self.is_recording() {
             anyhow::bail!("Already recording");
@@ -186,18 +390,62 @@ impl DiscordBot {
         let gid = GuildId::new(guild_id);
         let cid = ChannelId::new(channel_id);
 
-        let handler_lock = songbird
-            .join(gid, cid)
-            .await
-            .context("Failed to join voice channel")?;
+        // Ask before joining, so nobody is recorded until they agree
+        let consent = if notices.consent {
+            Some(self.request_consent(gid, cid).await?)
+        } else {
+            None
+        };
+
+        // Applies to calls joined from now on; receiver writes mono per speaker
+        songbird.set_config(
+            songbird::Config::default()
+                .decode_mode(options.decode_mode.songbird_mode())
+                .decode_channels(songbird::driver::Channels::Mono),
+        );
+
+        let handler_lock = match songbird.join(gid, cid).await {
+            Ok(handler) => handler,
+            Err(e) => {
+                self.consent.lock().take();
+                return Err(e).context("Failed to join voice channel");
+            }
+        };
+
+        // People already here get their tracks opened up front
+        let members: Vec<(u64, String)> = if notices.consent {
+            Vec::new()
+        } else {
+            self.list_channel_members(guild_id, channel_id, &recorded)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|m| Some((m.id.parse().ok()?, m.name)))
+                .collect()
+        };
 
         // Create shared receiver state
+        let ctx = self.ctx_store.read().await.clone();
+        let channel = channel_ref(ctx.as_ref(), gid, cid).await;
+        let name_resolver = ctx.map(|ctx| NameResolver::new(ctx, gid));
         let recv_state = ReceiverState::new(
             output_dir,
-            format,
+            channel,
+            options,
             Arc::clone(&self.is_recording),
             Arc::clone(&self.peak_level_bits),
-        );
+            Arc::clone(&self.speaking_feed),
+            name_resolver,
+            recorded,
+            consent,
+            self.status_hook.clone(),
+        )?;
+        recv_state.prepare_speakers(&members);
+        if let Some(ctx) = self.ctx_store.read().await.as_ref() {
+            for (user_id, flags) in voice_states_in(ctx, gid, cid) {
+                recv_state.voice_state_changed(user_id, flags);
+            }
+        }
 
         // Register event handlers (cloned from same Arc)
         {
@@ -210,12 +458,33 @@ impl DiscordBot {
                 CoreEvent::VoiceTick.into(),
                 VoiceHandler::new(Arc::clone(&recv_state)),
             );
+            handler.add_global_event(
+                CoreEvent::DriverDisconnect.into(),
+                Reconnector::new(
+                    Arc::clone(songbird),
+                    gid,
+                    cid,
+                    Arc::clone(&self.is_recording),
+                    self.status_hook.clone(),
+                ),
+            );
         }
 
         // Store receiver state for finalization later
         *self.receiver_state.lock().await = Some(recv_state);
         self.is_recording.store(true, Ordering::Relaxed);
+        self.notify_participants
+            .store(notices.participants, Ordering::Relaxed);
         *self.current_guild.lock().await = Some(gid);
+        self.speaking_feed.session_started();
+        self.emit(RecordingEvent::Started {
+            path: None,
+            device: None,
+        });
+        if let Some(hook) = self.status_hook.clone() {
+            let peak_level_bits = Arc::clone(&self.peak_level_bits);
+            *self.level_task.lock() = Some(tokio::spawn(push_levels(hook, peak_level_bits)));
+        }
 
         log::info!(
             "Recording started in guild {} channel {}",
@@ -224,7 +493,7 @@ impl DiscordBot {
         );
 
         // Send notification to the voice channel's text chat
-        if notify {
+        if notices.channel && !notices.consent {
             let ctx_guard = self.ctx_store.read().await;
             if let Some(ctx) = ctx_guard.as_ref() {
                 match cid.say(&ctx.http, "🔴 Recording started by DiscRec").await {
@@ -237,6 +506,40 @@ impl DiscordBot {
         Ok(())
     }
 
+    /// Post the consent request in `channel`'s text chat and start accepting
+    /// reactions to it.
+    async fn request_consent(
+        &self,
+        guild: GuildId,
+        channel: ChannelId,
+    ) -> Result<Arc<ConsentGate>> {
+        let ctx = self
+            .ctx_store
+            .read()
+            .await
+            .clone()
+            .context("Not connected to Discord")?;
+        let embed = CreateEmbed::new()
+            .title("🔴 DiscRec wants to record this channel")
+            .description(format!(
+                "React with {} to be recorded. Members who don't react aren't recorded, \
+                 and removing your reaction stops recording you.",
+                CONSENT_EMOJI
+            ));
+        let prompt = channel
+            .send_message(&ctx.http, CreateMessage::new().embed(embed))
+            .await
+            .context("Failed to post the consent request")?;
+
+        let gate = Arc::new(ConsentGate::new(guild.get(), prompt.id.get()));
+        *self.consent.lock() = Some(Arc::clone(&gate));
+        if let Err(e) = prompt.react(&ctx.http, CONSENT_EMOJI).await {
+            log::warn!("Failed to add the consent reaction: {}", e);
+        }
+        log::info!("Asked for recording consent in channel {}", channel);
+        Ok(gate)
+    }
+
     pub async fn get_channel_member_count(&self, guild_id: u64, channel_id: u64) -> Result<usize> {
         let ctx_guard = self.ctx_store.read().await;
         let ctx = ctx_guard.as_ref().context("Not connected to Discord")?;
@@ -259,63 +562,272 @@ impl DiscordBot {
         Ok(count)
     }
 
+    /// Everyone in the voice channel except the bot, flagging who `excluded`
+    /// leaves out of the recording.
+    pub async fn list_channel_members(
+        &self,
+        guild_id: u64,
+        channel_id: u64,
+        recorded: &RecordedUsers,
+    ) -> Result<Vec<ChannelMember>> {
+        let ctx_guard = self.ctx_store.read().await;
+        let ctx = ctx_guard.as_ref().context("Not connected to Discord")?;
+
+        let gid = GuildId::new(guild_id);
+        let cid = ChannelId::new(channel_id);
+        let bot_id = ctx.cache.current_user().id;
+
+        let mut members: Vec<ChannelMember> = ctx
+            .cache
+            .guild(gid)
+            .map(|guild| {
+                guild
+                    .voice_states
+                    .values()
+                    .filter(|vs| vs.channel_id == Some(cid) && vs.user_id != bot_id)
+                    .map(|vs| {
+                        let name = vs
+                            .member
+                            .as_ref()
+                            .or_else(|| guild.members.get(&vs.user_id))
+                            .map(|m| m.display_name().to_string())
+                            .unwrap_or_else(|| vs.user_id.to_string());
+                        ChannelMember {
+                            id: vs.user_id.to_string(),
+                            name,
+                            excluded: !recorded.allows(vs.user_id.get()),
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        members.sort_by_key(|m| m.name.to_lowercase());
+
+        Ok(members)
+    }
+
     pub async fn stop_recording(&self) -> Result<Vec<String>> {
         if !self.is_recording() {
             return Ok(Vec::new());
         }
 
+        // Finalize encoders
+        if let Some(state) = self.end_session().await {
+            let saved = state.finalize_all().inspect_err(|e| {
+                self.emit(RecordingEvent::Error {
+                    message: e.to_string(),
+                })
+            })?;
+            crate::latency::log_report();
+            let (timeline, paths): (Vec<String>, Vec<String>) = saved
+                .into_iter()
+                .filter(|p| {
+                    ![
+                        SESSION_SUFFIX,
+                        CONSENT_SUFFIX,
+                        CHAT_SUFFIX,
+                        CHAT_TEXT_SUFFIX,
+                    ]
+                    .iter()
+                    .any(|suffix| p.ends_with(suffix))
+                })
+                .partition(|p| p.ends_with(TIMELINE_SUFFIX));
+            *self.last_tracks.lock() = state.track_reports(&paths);
+            *self.last_timeline.lock() = timeline.into_iter().next();
+            *self.last_speaker_stats.lock() = state.speaker_stats();
+            if self.notify_participants.load(Ordering::Relaxed) {
+                self.notify_participants(&state).await;
+            }
+            self.emit(RecordingEvent::Stopped {
+                reason: StopReason::Requested,
+                paths: paths.clone(),
+            });
+            return Ok(paths);
+        }
+
+        self.emit(RecordingEvent::Stopped {
+            reason: StopReason::Requested,
+            paths: Vec::new(),
+        });
+        Ok(Vec::new())
+    }
+
+    /// Leave the voice channel and delete everything recorded this session,
+    /// for false starts. Returns whether there was a recording to abort.
+    pub async fn abort_recording(&self) -> bool {
+        if !self.is_recording() {
+            return false;
+        }
+        if let Some(state) = self.end_session().await {
+            state.discard_all();
+        }
+        self.emit(RecordingEvent::Stopped {
+            reason: StopReason::Aborted,
+            paths: Vec::new(),
+        });
+        true
+    }
+
+    /// Mark the session stopped, leave the voice channel and hand back the
+    /// receiver so its tracks can be saved or discarded.
+    async fn end_session(&self) -> Option<Arc<ReceiverState>> {
         self.is_recording.store(false, Ordering::Relaxed);
+        if let Some(task) = self.level_task.lock().take() {
+            task.abort();
+        }
         self.peak_level_bits
             .store(0f32.to_bits(), Ordering::Relaxed);
+        self.speaking_feed.session_stopped();
 
-        // Leave the voice channel
+        // Leave the voice channel, dropping the call with this session's handlers
         if let Some(songbird) = &self.songbird {
             if let Some(gid) = self.current_guild.lock().await.take() {
-                let _ = songbird.leave(gid).await;
+                let _ = songbird.remove(gid).await;
                 log::info!("Left voice channel in guild {}", gid);
             }
         }
 
-        // Finalize encoders
-        let recv = self.receiver_state.lock().await.take();
-        if let Some(state) = recv {
-            return state.finalize_all();
+        self.consent.lock().take();
+        self.receiver_state.lock().await.take()
+    }
+
+    /// DM everyone recorded in `state`'s session, in the background.
+    async fn notify_participants(&self, state: &ReceiverState) {
+        let users = state.recorded_users();
+        let Some(ctx) = self.ctx_store.read().await.clone() else {
+            return;
+        };
+        if users.is_empty() {
+            return;
         }
+        let channel = state.channel().clone();
+        let notices = Arc::clone(&self.notices);
+        tokio::spawn(async move {
+            notices::send(&ctx, &notices, &channel, &users).await;
+        });
+    }
 
-        Ok(Vec::new())
+    /// Per-speaker silence statistics for the last finished session.
+    pub fn last_tracks(&self) -> Vec<TrackReport> {
+        self.last_tracks.lock().clone()
+    }
+
+    /// Speaker timeline saved by the last finished session.
+    pub fn last_timeline(&self) -> Option<String> {
+        self.last_timeline.lock().clone()
+    }
+
+    /// Talk time per speaker in the session being recorded, or else the
+    /// last one.
+    pub async fn speaker_stats(&self) -> Vec<SpeakerStats> {
+        match self.receiver_state.lock().await.as_ref() {
+            Some(state) => state.speaker_stats(),
+            None => self.last_speaker_stats.lock().clone(),
+        }
+    }
+
+    /// Forget tracks that were deleted from disk.
+    pub fn forget_tracks(&self, paths: &[String]) {
+        self.last_tracks.lock().retain(|t| !paths.contains(&t.path));
     }
 }
 
+fn voice_flags(state: &VoiceState) -> VoiceFlags {
+    VoiceFlags {
+        self_mute: state.self_mute,
+        self_deaf: state.self_deaf,
+        server_mute: state.mute,
+        server_deaf: state.deaf,
+    }
+}
+
+/// Mute and deafen state of everyone but the bot in `channel`.
+fn voice_states_in(ctx: &Context, guild: GuildId, channel: ChannelId) -> Vec<(u64, VoiceFlags)> {
+    let bot_id = ctx.cache.current_user().id;
+    ctx.cache
+        .guild(guild)
+        .map(|guild| {
+            guild
+                .voice_states
+                .values()
+                .filter(|vs| vs.channel_id == Some(channel) && vs.user_id != bot_id)
+                .map(|vs| (vs.user_id.get(), voice_flags(vs)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// People in `channel`, leaving out bots including this one.
+fn people_in(ctx: &Context, guild: GuildId, channel: ChannelId) -> usize {
+    let bot_id = ctx.cache.current_user().id;
+    ctx.cache
+        .guild(guild)
+        .map(|guild| {
+            guild
+                .voice_states
+                .values()
+                .filter(|vs| vs.channel_id == Some(channel) && vs.user_id != bot_id)
+                .filter(|vs| {
+                    !vs.member
+                        .as_ref()
+                        .or_else(|| guild.members.get(&vs.user_id))
+                        .is_some_and(|m| m.user.bot)
+                })
+                .count()
+        })
+        .unwrap_or(0)
+}
+
+/// Send the held peak to the UI every [`LEVEL_INTERVAL`], like the local
+/// capture's monitor, until aborted when the session ends.
+async fn push_levels(hook: StatusHook, peak_level_bits: Arc<AtomicU32>) {
+    let mut interval = tokio::time::interval(LEVEL_INTERVAL);
+    loop {
+        interval.tick().await;
+        let peak = f32::from_bits(peak_level_bits.load(Ordering::Relaxed));
+        hook(Recorder::Bot, RecordingEvent::Level { peak });
+    }
+}
+
+/// Guild and channel of a session, named when Discord can tell us.
+async fn channel_ref(
+    ctx: Option<&Context>,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> ChannelRef {
+    let mut channel = ChannelRef {
+        guild_id: guild_id.to_string(),
+        guild_name: None,
+        channel_id: channel_id.to_string(),
+        channel_name: None,
+    };
+    let Some(ctx) = ctx else {
+        return channel;
+    };
+    channel.guild_name = guild_id.name(&ctx.cache);
+    channel.channel_name = match channel_id.name(ctx).await {
+        Ok(name) => Some(name),
+        Err(e) => {
+            log::warn!("Failed to look up channel {}: {}", channel_id, e);
+            None
+        }
+    };
+    channel
+}
+
 // Token management via OS keyring
-const KEYRING_SERVICE: &str = "com.discrec.app";
-const KEYRING_USER: &str = "discord_bot_token";
+pub const KEYRING_ACCOUNT: &str = "discord_bot_token";
 
 pub fn save_token(token: &str) -> Result<()> {
-    let entry =
-        keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).context("Failed to access keyring")?;
-    entry
-        .set_password(token)
-        .context("Failed to save token to keyring")?;
+    crate::secrets::set(KEYRING_ACCOUNT, token).context("Failed to save token to keyring")?;
     log::info!("Bot token saved to OS keyring");
     Ok(())
 }
 
 pub fn load_token() -> Result<Option<String>> {
-    let entry =
-        keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).context("Failed to access keyring")?;
-    match entry.get_password() {
-        Ok(token) => Ok(Some(token)),
-        Err(keyring::Error::NoEntry) => Ok(None),
-        Err(e) => Err(anyhow::anyhow!("Failed to load token: {}", e)),
-    }
+    crate::secrets::get(KEYRING_ACCOUNT).context("Failed to load token")
 }
 
 pub fn delete_token() -> Result<()> {
-    let entry =
-        keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).context("Failed to access keyring")?;
-    match entry.delete_credential() {
-        Ok(()) => Ok(()),
-        Err(keyring::Error::NoEntry) => Ok(()),
-        Err(e) => Err(anyhow::anyhow!("Failed to delete token: {}", e)),
-    }
+    crate::secrets::delete(KEYRING_ACCOUNT).context("Failed to delete token")
 }
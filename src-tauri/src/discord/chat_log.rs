@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serenity::model::channel::Message;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::Instant;
+
+/// Suffix of the chat log saved next to a session's tracks.
+pub const CHAT_SUFFIX: &str = "-chat.json";
+/// Suffix of its plain text copy, for reading without tools.
+pub const CHAT_TEXT_SUFFIX: &str = "-chat.txt";
+
+/// A message posted in the recorded channel's text chat.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatMessage {
+    pub at: String,
+    /// Milliseconds into the recording, to line it up with the audio.
+    pub offset_ms: u64,
+    pub message_id: String,
+    pub author_id: String,
+    /// Nickname in the guild, else the display or user name.
+    pub author: String,
+    /// Posted by a bot, e.g. a dice roller.
+    pub bot: bool,
+    pub content: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<String>,
+    /// Title, description and fields of each embed, which is where most bots
+    /// put their output.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub embeds: Vec<String>,
+}
+
+/// Messages in the recorded channel's text chat during a session.
+pub struct ChatLog {
+    started: Instant,
+    messages: Vec<ChatMessage>,
+}
+
+impl ChatLog {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            messages: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, msg: &Message) {
+        let author = msg
+            .member
+            .as_ref()
+            .and_then(|member| member.nick.clone())
+            .or_else(|| msg.author.global_name.clone())
+            .unwrap_or_else(|| msg.author.name.clone());
+        self.messages.push(ChatMessage {
+            at: chrono::Local::now().to_rfc3339(),
+            offset_ms: self.started.elapsed().as_millis() as u64,
+            message_id: msg.id.to_string(),
+            author_id: msg.author.id.to_string(),
+            author,
+            bot: msg.author.bot,
+            content: msg.content.clone(),
+            attachments: msg.attachments.iter().map(|a| a.url.clone()).collect(),
+            embeds: msg.embeds.iter().map(embed_text).collect(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Write the log as JSON to `json` and as timestamped lines to `text`.
+    pub fn save(&self, json: &Path, text: &Path) -> Result<()> {
+        std::fs::write(json, serde_json::to_string_pretty(&self.messages)?)
+            .context("Failed to write chat log")?;
+        std::fs::write(text, self.to_text()).context("Failed to write chat log")
+    }
+
+    fn to_text(&self) -> String {
+        let mut out = String::new();
+        for msg in &self.messages {
+            let secs = msg.offset_ms / 1000;
+            let _ = write!(
+                out,
+                "[{:02}:{:02}:{:02}] {}",
+                secs / 3600,
+                secs / 60 % 60,
+                secs % 60,
+                msg.author
+            );
+            if msg.bot {
+                out.push_str(" (bot)");
+            }
+            let _ = writeln!(out, ": {}", msg.content);
+            for extra in msg.embeds.iter().chain(&msg.attachments) {
+                for line in extra.lines() {
+                    let _ = writeln!(out, "    {}", line);
+                }
+            }
+        }
+        out
+    }
+}
+
+fn embed_text(embed: &serenity::model::channel::Embed) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    parts.extend(embed.title.clone());
+    parts.extend(embed.description.clone());
+    for field in &embed.fields {
+        parts.push(format!("{}: {}", field.name, field.value));
+    }
+    parts.extend(embed.footer.as_ref().map(|footer| footer.text.clone()));
+    parts.join("\n")
+}
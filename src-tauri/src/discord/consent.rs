@@ -0,0 +1,150 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// Suffix of the consent log saved next to a session's tracks.
+pub const CONSENT_SUFFIX: &str = "-consent.json";
+/// Reaction members agree to be recorded with.
+pub const CONSENT_EMOJI: char = '✅';
+
+/// Something that changed whether a user may be recorded in a guild.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentAction {
+    /// Sent a DM saying they were recorded.
+    Notified,
+    /// Replied STOP to a notice.
+    OptedOut,
+    /// Replied START to a notice.
+    OptedIn,
+    /// The host added them to the exclusion list.
+    ExcludedByHost,
+    /// The host removed them from the exclusion list.
+    IncludedByHost,
+    /// Reacted to a session's consent request.
+    Consented,
+    /// Took their reaction to a consent request back.
+    WithdrewConsent,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsentEvent {
+    pub at: String,
+    pub guild_id: String,
+    pub user_id: String,
+    pub action: ConsentAction,
+}
+
+fn path() -> PathBuf {
+    crate::data_dir::config_dir().join("consent_log.jsonl")
+}
+
+/// Append an event to the consent log and return it. The log is only ever
+/// appended to, so exports can show the full history behind a recording.
+pub fn record(guild_id: u64, user_id: u64, action: ConsentAction) -> ConsentEvent {
+    let event = ConsentEvent {
+        at: chrono::Local::now().to_rfc3339(),
+        guild_id: guild_id.to_string(),
+        user_id: user_id.to_string(),
+        action,
+    };
+    let path = path();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let written = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| {
+            let line = serde_json::to_string(&event).unwrap_or_default();
+            writeln!(file, "{}", line)
+        });
+    if let Err(e) = written {
+        log::warn!("Failed to write consent log: {}", e);
+    }
+    event
+}
+
+/// Every event for `guild_id` logged up to `until` (RFC 3339), oldest first.
+pub fn history(guild_id: &str, until: &str) -> Vec<ConsentEvent> {
+    let Ok(file) = std::fs::File::open(path()) else {
+        return Vec::new();
+    };
+    let until = chrono::DateTime::parse_from_rfc3339(until).ok();
+    std::io::BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<ConsentEvent>(&line).ok())
+        .filter(|event| event.guild_id == guild_id)
+        .filter(|event| {
+            let at = chrono::DateTime::parse_from_rfc3339(&event.at).ok();
+            match (at, until) {
+                (Some(at), Some(until)) => at <= until,
+                _ => true,
+            }
+        })
+        .collect()
+}
+
+/// Members who agreed to be recorded this session by reacting to the bot's
+/// consent request. Everyone else's audio is dropped.
+pub struct ConsentGate {
+    guild_id: u64,
+    /// The request message.
+    prompt_id: u64,
+    consented: Mutex<HashSet<u64>>,
+    events: Mutex<Vec<ConsentEvent>>,
+}
+
+impl ConsentGate {
+    pub fn new(guild_id: u64, prompt_id: u64) -> Self {
+        Self {
+            guild_id,
+            prompt_id,
+            consented: Mutex::new(HashSet::new()),
+            events: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn is_prompt(&self, message_id: u64) -> bool {
+        self.prompt_id == message_id
+    }
+
+    /// Apply a reaction being added or removed, logging any change both for
+    /// the session and in the consent log.
+    pub fn set(&self, user_id: u64, consented: bool) {
+        let changed = {
+            let mut users = self.consented.lock();
+            if consented {
+                users.insert(user_id)
+            } else {
+                users.remove(&user_id)
+            }
+        };
+        if !changed {
+            return;
+        }
+        let action = if consented {
+            ConsentAction::Consented
+        } else {
+            ConsentAction::WithdrewConsent
+        };
+        log::info!("User {} {:?} to the recording", user_id, action);
+        let event = record(self.guild_id, user_id, action);
+        self.events.lock().push(event);
+    }
+
+    pub fn allows(&self, user_id: u64) -> bool {
+        self.consented.lock().contains(&user_id)
+    }
+
+    /// Write this session's consent events to `path`.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let events = self.events.lock();
+        std::fs::write(path, serde_json::to_string_pretty(&*events)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Suffix of the session manifest saved next to a bot session's tracks.
+pub const SESSION_SUFFIX: &str = "-session.json";
+
+/// The voice channel a bot session recorded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelRef {
+    pub guild_id: String,
+    pub guild_name: Option<String>,
+    pub channel_id: String,
+    pub channel_name: Option<String>,
+}
+
+/// What a bot session wrote and who is on each track, so the stems can be
+/// shown and handled as one recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub started_at: String,
+    pub ended_at: String,
+    #[serde(flatten)]
+    pub channel: ChannelRef,
+    pub sample_rate: u32,
+    /// Extension of the speaker tracks, e.g. `flac` or `opus` for packet modes.
+    pub format: String,
+    pub tracks: Vec<SessionTrack>,
+    /// File name of the stereo mix, when one was written.
+    pub mix: Option<String>,
+    /// Further parts of the mix, in order, when the session was split into
+    /// segments; `mix` is the first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mix_parts: Vec<String>,
+    /// File name of the speaker timeline, when anyone spoke.
+    pub timeline: Option<String>,
+    /// File name of the consent log, when members were asked for consent.
+    pub consent: Option<String>,
+    /// File names of the text chat log, JSON then plain text, when chat was
+    /// recorded and anyone wrote.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chat: Vec<String>,
+    /// Why the stems may sound unlike local mic recordings, e.g. Discord's
+    /// own noise suppression.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTrack {
+    /// File name next to the manifest.
+    pub file: String,
+    pub ssrc: u32,
+    pub user_id: Option<String>,
+    /// Display name in the recorded guild, when it could be looked up.
+    pub name: Option<String>,
+    /// A bot account's audio, e.g. a music bot's, kept on its own track.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bot: bool,
+    /// Level of the quietest stretches the speaker sent, in dBFS, once
+    /// enough was heard.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noise_floor_db: Option<f32>,
+    /// The speaker's audio sounds noise-suppressed by their Discord client.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub likely_processed: bool,
+}
+
+/// A manifest found in the library.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionListing {
+    /// Path of the manifest itself.
+    pub path: String,
+    #[serde(flatten)]
+    pub session: SessionInfo,
+}
+
+impl SessionInfo {
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .context("Failed to write session manifest")
+    }
+
+    /// File names of the session's recordings: speaker tracks and the mix.
+    pub fn recordings(&self) -> impl Iterator<Item = &str> {
+        self.tracks
+            .iter()
+            .map(|t| t.file.as_str())
+            .chain(self.mix.as_deref())
+            .chain(self.mix_parts.iter().map(String::as_str))
+    }
+}
+
+/// Bot sessions saved in `dir` or its non-hidden subfolders, newest first.
+pub fn list(dir: &Path) -> std::io::Result<Vec<SessionListing>> {
+    let mut sessions: Vec<SessionListing> = manifest_paths(dir)?
+        .into_iter()
+        .filter_map(|path| {
+            let data = std::fs::read_to_string(&path).ok()?;
+            match serde_json::from_str(&data) {
+                Ok(session) => Some(SessionListing {
+                    path: path.to_string_lossy().to_string(),
+                    session,
+                }),
+                Err(e) => {
+                    log::warn!(
+                        "Skipping invalid session manifest {}: {}",
+                        path.display(),
+                        e
+                    );
+                    None
+                }
+            }
+        })
+        .collect();
+    sessions.sort_by(|a, b| b.session.started_at.cmp(&a.session.started_at));
+    Ok(sessions)
+}
+
+fn manifest_paths(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            if !entry.file_name().to_string_lossy().starts_with('.') {
+                paths.extend(manifest_paths(&path).unwrap_or_default());
+            }
+        } else if path.to_string_lossy().ends_with(SESSION_SUFFIX) {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+/// The manifest of the session `file` belongs to, looked up next to it.
+pub fn for_file(file: &Path) -> Option<SessionListing> {
+    let name = file.file_name()?.to_string_lossy().to_string();
+    std::fs::read_dir(file.parent()?)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.to_string_lossy().ends_with(SESSION_SUFFIX))
+        .find_map(|path| {
+            let data = std::fs::read_to_string(&path).ok()?;
+            let session: SessionInfo = serde_json::from_str(&data).ok()?;
+            let listed = session.recordings().any(|file| file == name);
+            listed.then(|| SessionListing {
+                path: path.to_string_lossy().to_string(),
+                session,
+            })
+        })
+}
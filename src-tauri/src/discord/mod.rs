@@ -1,2 +1,14 @@
 pub mod bot;
+pub mod chat_log;
+pub mod consent;
+pub mod manifest;
+pub mod names;
+pub mod notices;
+pub mod packets;
+pub mod processing;
 pub mod receiver;
+pub mod reconnect;
+pub mod speaking_feed;
+pub mod talk_time;
+pub mod tick_dump;
+pub mod timeline;
@@ -0,0 +1,89 @@
+use serenity::all::{GuildId, UserId};
+use serenity::client::Context;
+
+/// Longest display name kept in a file name, in characters.
+const MAX_LABEL_CHARS: usize = 40;
+
+/// Looks up speakers' display names in the guild being recorded.
+#[derive(Clone)]
+pub struct NameResolver {
+    ctx: Context,
+    guild_id: GuildId,
+}
+
+impl NameResolver {
+    pub fn new(ctx: Context, guild_id: GuildId) -> Self {
+        Self { ctx, guild_id }
+    }
+
+    /// Guild nickname, else global display name, else username. Tries the
+    /// cache first and only asks the API for members it doesn't hold.
+    pub async fn display_name(&self, user_id: u64) -> Option<String> {
+        let user_id = UserId::new(user_id);
+        let cached = self.ctx.cache.guild(self.guild_id).and_then(|guild| {
+            guild
+                .members
+                .get(&user_id)
+                .map(|member| member.display_name().to_string())
+        });
+        if cached.is_some() {
+            return cached;
+        }
+        match self.guild_id.member(&self.ctx.http, user_id).await {
+            Ok(member) => Some(member.display_name().to_string()),
+            Err(e) => {
+                log::warn!("Failed to look up user {}: {}", user_id, e);
+                None
+            }
+        }
+    }
+
+    /// Whether `user_id` is a bot account, if the cache holds them as a
+    /// member or through their voice state, which everyone in a voice
+    /// channel has.
+    pub fn cached_is_bot(&self, user_id: u64) -> Option<bool> {
+        let user_id = UserId::new(user_id);
+        let guild = self.ctx.cache.guild(self.guild_id)?;
+        guild
+            .members
+            .get(&user_id)
+            .or_else(|| guild.voice_states.get(&user_id)?.member.as_ref())
+            .map(|member| member.user.bot)
+    }
+
+    /// Whether `user_id` is a bot account, e.g. a music bot, from the cache
+    /// or else the API; `None` if the lookup failed.
+    pub async fn is_bot(&self, user_id: u64) -> Option<bool> {
+        if let Some(bot) = self.cached_is_bot(user_id) {
+            return Some(bot);
+        }
+        let user_id = UserId::new(user_id);
+        match self.ctx.http.get_user(user_id).await {
+            Ok(user) => Some(user.bot),
+            Err(e) => {
+                log::warn!("Failed to look up user {}: {}", user_id, e);
+                None
+            }
+        }
+    }
+}
+
+/// A display name reduced to something safe in a file name on every
+/// platform, or `None` if nothing usable is left.
+pub fn file_label(name: &str) -> Option<String> {
+    let mut label = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            label.push(c);
+        } else if !label.is_empty() && !label.ends_with('-') {
+            label.push('-');
+        }
+    }
+    let label: String = label
+        .trim_end_matches('-')
+        .chars()
+        .take(MAX_LABEL_CHARS)
+        .collect();
+    let label = label.trim_end_matches('-');
+    (!label.is_empty()).then(|| label.to_string())
+}
@@ -0,0 +1,172 @@
+use parking_lot::Mutex;
+use serenity::all::{GuildId, UserId};
+use serenity::client::Context;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::consent::{self, ConsentAction};
+use super::manifest::ChannelRef;
+
+/// Called with `(guild_id, user_id, excluded)` when a participant opts out
+/// of or back into recordings by replying to a notice.
+pub type ExclusionHook = Arc<dyn Fn(u64, u64, bool) + Send + Sync>;
+
+/// A participant's answer to a recording notice.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoticeReply {
+    Stop,
+    Start,
+}
+
+impl NoticeReply {
+    pub fn parse(content: &str) -> Option<Self> {
+        match content.trim().to_uppercase().as_str() {
+            "STOP" => Some(Self::Stop),
+            "START" => Some(Self::Start),
+            _ => None,
+        }
+    }
+}
+
+/// Guilds each user was told they were recorded in, so a STOP reply knows
+/// where to exclude them. Kept on disk since replies can come days later.
+pub struct RecordingNotices(Mutex<BTreeMap<u64, BTreeSet<u64>>>);
+
+impl RecordingNotices {
+    pub fn load() -> Self {
+        let sent = std::fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self(Mutex::new(sent))
+    }
+
+    fn path() -> PathBuf {
+        crate::data_dir::config_dir().join("recording_notices.json")
+    }
+
+    fn save(&self) {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let sent = self.0.lock();
+        let _ = std::fs::write(
+            path,
+            serde_json::to_string_pretty(&*sent).unwrap_or_default(),
+        );
+    }
+
+    fn record(&self, user_id: u64, guild_id: u64) {
+        let added = self.0.lock().entry(user_id).or_default().insert(guild_id);
+        if added {
+            self.save();
+        }
+    }
+
+    /// Guilds `user_id` was notified about.
+    pub fn guilds(&self, user_id: u64) -> Vec<u64> {
+        self.0
+            .lock()
+            .get(&user_id)
+            .map(|guilds| guilds.iter().copied().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// DM everyone in `users` that they were recorded in `channel`.
+pub async fn send(ctx: &Context, notices: &RecordingNotices, channel: &ChannelRef, users: &[u64]) {
+    let Ok(guild_id) = channel.guild_id.parse::<u64>() else {
+        return;
+    };
+    let host = match ctx.http.get_current_application_info().await {
+        Ok(info) => info
+            .owner
+            .map(|owner| owner.global_name.unwrap_or(owner.name)),
+        Err(e) => {
+            log::warn!("Failed to look up the bot's owner: {}", e);
+            None
+        }
+    };
+    let place = match (&channel.channel_name, &channel.guild_name) {
+        (Some(ch), Some(guild)) => format!("#{} on {}", ch, guild),
+        (None, Some(guild)) => guild.clone(),
+        (Some(ch), None) => format!("#{}", ch),
+        (None, None) => "a voice channel".to_string(),
+    };
+    let host = host
+        .map(|name| format!(", hosted by {}", name))
+        .unwrap_or_default();
+    let text = format!(
+        "🔴 You were recorded by DiscRec in {}{}. Reply STOP to opt out of future recordings there.",
+        place, host
+    );
+
+    for &user_id in users {
+        let user = UserId::new(user_id);
+        let sent = match user.create_dm_channel(&ctx.http).await {
+            Ok(dm) => dm.say(&ctx.http, &text).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        match sent {
+            Ok(()) => {
+                notices.record(user_id, guild_id);
+                consent::record(guild_id, user_id, ConsentAction::Notified);
+            }
+            // Users who don't accept DMs from server members end up here
+            Err(e) => log::warn!("Failed to notify user {}: {}", user_id, e),
+        }
+    }
+}
+
+/// Apply a STOP/START reply to every guild the user was notified about and
+/// return the confirmation to send back, or `None` if there was no notice.
+pub fn handle_reply(
+    ctx: &Context,
+    notices: &RecordingNotices,
+    hook: &ExclusionHook,
+    user_id: u64,
+    reply: NoticeReply,
+) -> Option<String> {
+    let guilds = notices.guilds(user_id);
+    if guilds.is_empty() {
+        return None;
+    }
+    let excluded = reply == NoticeReply::Stop;
+    let action = if excluded {
+        ConsentAction::OptedOut
+    } else {
+        ConsentAction::OptedIn
+    };
+    for &guild_id in &guilds {
+        hook(guild_id, user_id, excluded);
+        consent::record(guild_id, user_id, action);
+    }
+    log::info!(
+        "User {} opted {} recordings in {} guild(s)",
+        user_id,
+        if excluded { "out of" } else { "back into" },
+        guilds.len()
+    );
+
+    let names: Vec<String> = guilds
+        .iter()
+        .map(|&id| {
+            GuildId::new(id)
+                .name(&ctx.cache)
+                .unwrap_or_else(|| id.to_string())
+        })
+        .collect();
+    Some(if excluded {
+        format!(
+            "Got it, you won't be recorded in {} anymore. Reply START to opt back in.",
+            names.join(", ")
+        )
+    } else {
+        format!(
+            "You can be recorded in {} again. Reply STOP to opt out.",
+            names.join(", ")
+        )
+    })
+}
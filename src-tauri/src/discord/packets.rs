@@ -0,0 +1,258 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use songbird::events::context_data::RtpData;
+use songbird::packet::rtp::RtpExtensionPacket;
+use songbird::packet::{Packet, PacketSize};
+use std::io::{BufWriter, Write};
+use std::time::Instant;
+
+use crate::audio::encoder::{ogg_opus_headers, FlushTimer};
+
+/// How received voice packets are processed during a bot recording.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VoiceDecodeMode {
+    /// Decrypt and decode to PCM, written with the selected audio format.
+    #[default]
+    Decode,
+    /// Decrypt only and store the original Opus packets in Ogg Opus files.
+    Passthrough,
+    /// Store the raw (still encrypted) RTP packets in rtpdump files.
+    Rtp,
+}
+
+impl VoiceDecodeMode {
+    pub fn songbird_mode(self) -> songbird::driver::DecodeMode {
+        match self {
+            VoiceDecodeMode::Decode => songbird::driver::DecodeMode::Decode,
+            VoiceDecodeMode::Passthrough => songbird::driver::DecodeMode::Decrypt,
+            VoiceDecodeMode::Rtp => songbird::driver::DecodeMode::Pass,
+        }
+    }
+
+    /// File extension for packet-level modes; `None` when decoding to PCM.
+    pub fn packet_extension(self) -> Option<&'static str> {
+        match self {
+            VoiceDecodeMode::Decode => None,
+            VoiceDecodeMode::Passthrough => Some("opus"),
+            VoiceDecodeMode::Rtp => Some("rtpdump"),
+        }
+    }
+}
+
+/// Writes received voice packets without decoding them.
+pub trait PacketWriter: Send {
+    fn write_packet(&mut self, packet: &RtpData) -> Result<()>;
+    fn path(&self) -> &str;
+    fn finalize(self: Box<Self>) -> Result<()>;
+}
+
+pub fn create_packet_writer(path: &str, mode: VoiceDecodeMode) -> Result<Box<dyn PacketWriter>> {
+    if let Some(parent) = std::path::Path::new(path).parent() {
+        std::fs::create_dir_all(parent).context("Failed to create recording directory")?;
+    }
+    match mode {
+        VoiceDecodeMode::Passthrough => Ok(Box::new(OggOpusWriter::new(path)?)),
+        VoiceDecodeMode::Rtp => Ok(Box::new(RtpDumpWriter::new(path)?)),
+        VoiceDecodeMode::Decode => anyhow::bail!("Decode mode writes PCM, not packets"),
+    }
+}
+
+// --- Ogg Opus (decrypted packets, no re-encoding) ---
+
+const OGG_SERIAL: u32 = 0x4449_5343; // "DISC"
+
+struct OggOpusWriter {
+    writer: ogg::PacketWriter<'static, BufWriter<std::fs::File>>,
+    path: String,
+    granule: u64,
+    /// Held back by one packet so the final one can be flagged end-of-stream.
+    held: Option<(Vec<u8>, u64)>,
+    flush: FlushTimer,
+}
+
+impl OggOpusWriter {
+    fn new(path: &str) -> Result<Self> {
+        use ogg::PacketWriteEndInfo::EndPage;
+
+        let file = std::fs::File::create(path).context("Failed to create Opus file")?;
+        let mut writer = ogg::PacketWriter::new(BufWriter::new(file));
+
+        // Discord sends 48 kHz stereo Opus
+        for header in ogg_opus_headers(2, 0) {
+            writer
+                .write_packet(header, OGG_SERIAL, EndPage, 0)
+                .context("Failed to write Opus header")?;
+        }
+
+        Ok(Self {
+            writer,
+            path: path.to_string(),
+            granule: 0,
+            held: None,
+            flush: FlushTimer::new(),
+        })
+    }
+}
+
+impl PacketWriter for OggOpusWriter {
+    fn write_packet(&mut self, packet: &RtpData) -> Result<()> {
+        let Some(opus) = opus_payload(packet) else {
+            return Ok(());
+        };
+        let samples = opus_packet_samples(opus);
+        self.granule += samples as u64;
+
+        if let Some((data, granule)) = self.held.replace((opus.to_vec(), self.granule)) {
+            // Ending the page when a flush is due gets the packet to disk
+            let flush = self.flush.due(samples);
+            let end = if flush {
+                ogg::PacketWriteEndInfo::EndPage
+            } else {
+                ogg::PacketWriteEndInfo::NormalPacket
+            };
+            self.writer
+                .write_packet(data, OGG_SERIAL, end, granule)
+                .context("Failed to write Opus packet")?;
+            if flush {
+                self.writer
+                    .inner_mut()
+                    .flush()
+                    .context("Failed to flush Opus file")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn finalize(mut self: Box<Self>) -> Result<()> {
+        if let Some((data, granule)) = self.held.take() {
+            self.writer
+                .write_packet(
+                    data,
+                    OGG_SERIAL,
+                    ogg::PacketWriteEndInfo::EndStream,
+                    granule,
+                )
+                .context("Failed to write Opus packet")?;
+        }
+        self.writer
+            .inner_mut()
+            .flush()
+            .context("Failed to finalize Opus file")?;
+        log::info!(
+            "Opus passthrough saved: {} samples -> {}",
+            self.granule,
+            self.path
+        );
+        Ok(())
+    }
+}
+
+/// The decrypted Opus frame inside an RTP packet, skipping any header extension.
+fn opus_payload(packet: &RtpData) -> Option<&[u8]> {
+    let rtp = packet.rtp();
+    let body = packet
+        .packet
+        .get(packet.packet.len() - rtp.payload().len()..)?
+        .get(packet.payload_offset..packet.payload_end_pad)?;
+    let start = if rtp.get_extension() != 0 {
+        RtpExtensionPacket::new(body)?.packet_size()
+    } else {
+        0
+    };
+    body.get(start..).filter(|p| !p.is_empty())
+}
+
+/// Samples per channel at 48 kHz in an Opus packet, from its TOC byte (RFC 6716 §3.1).
+fn opus_packet_samples(packet: &[u8]) -> usize {
+    let toc = packet[0];
+    let config = toc >> 3;
+    let frame_samples = match config {
+        // SILK: 10, 20, 40, 60 ms
+        0..=11 => [480, 960, 1920, 2880][(config % 4) as usize],
+        // Hybrid: 10, 20 ms
+        12..=15 => [480, 960][(config % 2) as usize],
+        // CELT: 2.5, 5, 10, 20 ms
+        _ => [120, 240, 480, 960][(config % 4) as usize],
+    };
+    let frames = match toc & 0x3 {
+        0 => 1,
+        1 | 2 => 2,
+        _ => packet.get(1).map(|c| (c & 0x3f) as usize).unwrap_or(0),
+    };
+    frame_samples * frames
+}
+
+// --- rtpdump (raw packets, readable by rtptools and Wireshark) ---
+
+const RTP_PACKET_SAMPLES: usize = 960;
+
+struct RtpDumpWriter {
+    file: BufWriter<std::fs::File>,
+    path: String,
+    started: Instant,
+    packets: u64,
+    flush: FlushTimer,
+}
+
+impl RtpDumpWriter {
+    fn new(path: &str) -> Result<Self> {
+        let file = std::fs::File::create(path).context("Failed to create RTP dump file")?;
+        let mut file = BufWriter::new(file);
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut header = b"#!rtpplay1.0 0.0.0.0/0\n".to_vec();
+        header.extend_from_slice(&(now.as_secs() as u32).to_be_bytes());
+        header.extend_from_slice(&now.subsec_micros().to_be_bytes());
+        header.extend_from_slice(&0u32.to_be_bytes()); // source address
+        header.extend_from_slice(&0u16.to_be_bytes()); // port
+        header.extend_from_slice(&0u16.to_be_bytes()); // padding
+        file.write_all(&header)
+            .context("Failed to write RTP dump header")?;
+
+        Ok(Self {
+            file,
+            path: path.to_string(),
+            started: Instant::now(),
+            packets: 0,
+            flush: FlushTimer::new(),
+        })
+    }
+}
+
+impl PacketWriter for RtpDumpWriter {
+    fn write_packet(&mut self, packet: &RtpData) -> Result<()> {
+        let data = &packet.packet[..];
+        let offset_ms = self.started.elapsed().as_millis() as u32;
+        let mut record = Vec::with_capacity(8 + data.len());
+        record.extend_from_slice(&((8 + data.len()) as u16).to_be_bytes());
+        record.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        record.extend_from_slice(&offset_ms.to_be_bytes());
+        record.extend_from_slice(data);
+        self.file
+            .write_all(&record)
+            .context("Failed to write RTP packet")?;
+        self.packets += 1;
+        // Still encrypted, so count each as the 20 ms frame Discord sends
+        if self.flush.due(RTP_PACKET_SAMPLES) {
+            self.file.flush().context("Failed to flush RTP dump")?;
+        }
+        Ok(())
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn finalize(mut self: Box<Self>) -> Result<()> {
+        self.file.flush().context("Failed to finalize RTP dump")?;
+        log::info!("RTP dump saved: {} packets -> {}", self.packets, self.path);
+        Ok(())
+    }
+}
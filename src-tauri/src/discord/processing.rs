@@ -0,0 +1,238 @@
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Discord builds that keep their own settings.
+const CLIENT_DIRS: &[&str] = &["discord", "discordptb", "discordcanary"];
+/// Key of the voice settings in the client's local storage.
+const SETTINGS_KEY: &[u8] = b"MediaEngineStore";
+/// How far past the key its JSON value is looked at.
+const SETTINGS_WINDOW: usize = 16 * 1024;
+
+/// Frames quieter than this, but not digital silence, count towards the
+/// noise floor.
+const FLOOR_MIN_DB: i32 = -100;
+/// Frames louder than this are taken as speech.
+const SPEECH_DB: f32 = -35.0;
+/// A noise floor below this between words means a noise gate or suppressor
+/// took the room out; an open mic sits well above it.
+const SUPPRESSED_FLOOR_DB: f32 = -70.0;
+/// Share of frames the noise floor is read at.
+const FLOOR_PERCENTILE: f32 = 0.1;
+/// Frames of 20 ms needed before a track is judged: 10 s sent, 3 s spoken.
+const MIN_FRAMES: u32 = 500;
+const MIN_SPEECH_FRAMES: u32 = 150;
+
+/// Voice processing this computer's Discord client applies to its user's
+/// microphone before sending it, as read from the client's settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ClientProcessing {
+    /// Krisp noise suppression.
+    pub krisp: bool,
+    /// Discord's standard noise suppression.
+    pub noise_suppression: bool,
+    pub echo_cancellation: bool,
+    pub automatic_gain_control: bool,
+}
+
+impl ClientProcessing {
+    /// The processing that is on, e.g. "Krisp noise suppression and echo
+    /// cancellation"; `None` when there is none.
+    pub fn describe(&self) -> Option<String> {
+        let parts: Vec<&str> = [
+            (self.krisp, "Krisp noise suppression"),
+            (self.noise_suppression && !self.krisp, "noise suppression"),
+            (self.echo_cancellation, "echo cancellation"),
+            (self.automatic_gain_control, "automatic gain control"),
+        ]
+        .into_iter()
+        .filter_map(|(on, name)| on.then_some(name))
+        .collect();
+        match parts.as_slice() {
+            [] => None,
+            [one] => Some(one.to_string()),
+            [rest @ .., last] => Some(format!("{} and {}", rest.join(", "), last)),
+        }
+    }
+}
+
+/// The voice processing of the Discord client on this computer, from the
+/// most recently used build. `None` when no client settings can be read,
+/// e.g. Discord runs in a browser or was never set up here.
+pub fn client_processing() -> Option<ClientProcessing> {
+    let config = dirs::config_dir()?;
+    let mut files: Vec<(std::time::SystemTime, PathBuf)> = CLIENT_DIRS
+        .iter()
+        .filter_map(|client| {
+            std::fs::read_dir(config.join(client).join("Local Storage").join("leveldb")).ok()
+        })
+        .flatten()
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.ends_with(".log") || name.ends_with(".ldb")
+        })
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+    // The latest write of the settings wins
+    files.sort_by(|a, b| b.0.cmp(&a.0));
+    files
+        .iter()
+        .find_map(|(_, path)| parse_settings(&std::fs::read(path).ok()?))
+}
+
+/// The last readable copy of the voice settings in a local storage file.
+/// Older copies linger until the database is compacted, and compressed
+/// ones can't be read, so it looks back until one parses.
+fn parse_settings(data: &[u8]) -> Option<ClientProcessing> {
+    let mut end = data.len();
+    while let Some(at) = find_last(&data[..end], SETTINGS_KEY) {
+        let value = &data[at..(at + SETTINGS_WINDOW).min(data.len())];
+        let flags = [
+            flag(value, b"\"noiseCancellation\":"),
+            flag(value, b"\"noiseSuppression\":"),
+            flag(value, b"\"echoCancellation\":"),
+            flag(value, b"\"automaticGainControl\":"),
+        ];
+        if flags.iter().any(Option::is_some) {
+            // Discord's defaults for anything not saved
+            let [krisp, noise_suppression, echo_cancellation, automatic_gain_control] = flags;
+            return Some(ClientProcessing {
+                krisp: krisp.unwrap_or(false),
+                noise_suppression: noise_suppression.unwrap_or(true),
+                echo_cancellation: echo_cancellation.unwrap_or(true),
+                automatic_gain_control: automatic_gain_control.unwrap_or(true),
+            });
+        }
+        end = at;
+    }
+    None
+}
+
+fn find_last(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).rposition(|w| w == needle)
+}
+
+/// The boolean after the first `key` in `value`.
+fn flag(value: &[u8], key: &[u8]) -> Option<bool> {
+    let at = value.windows(key.len()).position(|w| w == key)? + key.len();
+    let rest = &value[at..];
+    if rest.starts_with(b"true") {
+        Some(true)
+    } else if rest.starts_with(b"false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Tally of one speaker's frame levels, to tell whether their audio went
+/// through noise suppression before Discord sent it. Suppressed voices drop
+/// to near silence between words; an open mic keeps its room noise.
+#[derive(Debug, Clone)]
+pub struct NoiseFloor {
+    /// Frames by level, one bin per dB from `FLOOR_MIN_DB` up to 0 dBFS.
+    bins: [u32; (-FLOOR_MIN_DB) as usize],
+    frames: u32,
+    speech_frames: u32,
+}
+
+impl Default for NoiseFloor {
+    fn default() -> Self {
+        Self {
+            bins: [0; (-FLOOR_MIN_DB) as usize],
+            frames: 0,
+            speech_frames: 0,
+        }
+    }
+}
+
+impl NoiseFloor {
+    /// Count one received frame. Digital silence, as Discord sends when a
+    /// speaker stops, says nothing about their processing and is skipped.
+    pub fn add(&mut self, frame: &[f32]) {
+        if frame.is_empty() {
+            return;
+        }
+        let sum_squares: f64 = frame.iter().map(|&s| (s as f64) * (s as f64)).sum();
+        if sum_squares == 0.0 {
+            return;
+        }
+        let rms = (sum_squares / frame.len() as f64).sqrt() as f32;
+        let db = 20.0 * rms.log10();
+        let bin = (db.floor() as i32).clamp(FLOOR_MIN_DB, -1) - FLOOR_MIN_DB;
+        self.bins[bin as usize] += 1;
+        self.frames += 1;
+        if db > SPEECH_DB {
+            self.speech_frames += 1;
+        }
+    }
+
+    /// Level of the quietest frames, in dBFS; `None` until enough speech was
+    /// heard to judge.
+    pub fn floor_db(&self) -> Option<f32> {
+        if self.frames < MIN_FRAMES || self.speech_frames < MIN_SPEECH_FRAMES {
+            return None;
+        }
+        let target = (self.frames as f32 * FLOOR_PERCENTILE).ceil() as u32;
+        let mut seen = 0;
+        for (bin, &count) in self.bins.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return Some((bin as i32 + FLOOR_MIN_DB) as f32);
+            }
+        }
+        None
+    }
+
+    /// Whether the speaker's audio looks noise-suppressed.
+    pub fn likely_processed(&self) -> bool {
+        self.floor_db()
+            .is_some_and(|floor| floor < SUPPRESSED_FLOOR_DB)
+    }
+}
+
+/// Why a bot session's stems may not sound like local mic recordings, for
+/// its manifest. `processed` names the speakers whose tracks sound
+/// noise-suppressed.
+pub fn session_notes(client: Option<ClientProcessing>, processed: &[String]) -> Vec<String> {
+    let mut notes = Vec::new();
+    if let Some(processing) = client.as_ref().and_then(ClientProcessing::describe) {
+        notes.push(format!(
+            "Discord on the recording computer applies {} to its user's voice before sending it, so their track is processed audio, not the raw mic",
+            processing
+        ));
+    }
+    if !processed.is_empty() {
+        notes.push(format!(
+            "{} went quiet between words, as Krisp or Discord's noise suppression leaves it; expect their tracks to differ from a local mic recording",
+            processed.join(", ")
+        ));
+    }
+    notes
+}
+
+/// What to expect from Discord's processing before a bot session starts:
+/// this computer's own settings, and that other speakers' clients may
+/// process their voices as well.
+pub fn preflight_note(client: Option<&ClientProcessing>) -> String {
+    let others = "Each speaker's Discord may apply noise suppression or echo cancellation before sending, so bot tracks can sound cleaner than local mics";
+    match client.and_then(ClientProcessing::describe) {
+        Some(processing) => format!(
+            "Your Discord applies {} to your voice. {}",
+            processing, others
+        ),
+        None => format!("{}.", others),
+    }
+}
+
+/// Why a local mic recording won't match the user's own voice as Discord
+/// sends it, for the level preview.
+pub fn mic_note(client: &ClientProcessing) -> Option<String> {
+    client.describe().map(|processing| {
+        format!(
+            "Discord applies {} to your voice before sending it; this track records the mic without it",
+            processing
+        )
+    })
+}
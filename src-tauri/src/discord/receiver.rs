@@ -1,96 +1,1093 @@
 use anyhow::Result;
 use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use serenity::async_trait;
+use songbird::events::context_data::RtpData;
 use songbird::{Event, EventContext, EventHandler as VoiceEventHandler};
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 
-use crate::audio::encoder::{create_encoder, AudioEncoder, AudioFormat};
+use super::chat_log::{ChatLog, CHAT_SUFFIX, CHAT_TEXT_SUFFIX};
+use super::consent::{ConsentGate, CONSENT_SUFFIX};
+use super::manifest::{ChannelRef, SessionInfo, SessionTrack, SESSION_SUFFIX};
+use super::names::{file_label, NameResolver};
+use super::packets::{create_packet_writer, PacketWriter, VoiceDecodeMode};
+use super::processing::NoiseFloor;
+use super::speaking_feed::SpeakingFeed;
+use super::talk_time::{SpeakerStats, TalkTime};
+use super::tick_dump::TickDump;
+use super::timeline::{speaker_id, TimelineBuilder, VoiceFlags, TIMELINE_SUFFIX};
+use crate::audio::encoder::{
+    create_encoder, create_segmented_encoder, part_name, remove_partial, AudioEncoder, AudioFormat,
+    SILENCE_THRESHOLD,
+};
+use crate::audio::levels::hold_peak;
+use crate::audio::pcm::i16_to_float;
+use crate::events::{Recorder, RecordingEvent, StatusHook};
+use crate::naming::{self, NameParts};
+use crate::session::RecordingSession;
+
+/// Tracks at least this silent are reported as near-empty after a session.
+const EMPTY_TRACK_SILENCE_RATIO: f32 = 0.99;
+
+/// Per-speaker sample tally, used to spot near-empty tracks.
+struct TrackStats {
+    /// The track's file, or its parts when segmented.
+    file_names: Vec<String>,
+    samples: u64,
+    silent_samples: u64,
+    /// Samples in the file, including alignment padding.
+    written: u64,
+    noise_floor: NoiseFloor,
+}
+
+impl TrackStats {
+    /// Write silence until the track holds `position` samples.
+    fn pad_to(&mut self, encoder: &mut Box<dyn AudioEncoder>, position: u64) -> Result<()> {
+        const SILENCE: [f32; TICK_SAMPLES] = [0.0; TICK_SAMPLES];
+        while self.written < position {
+            let len = (position - self.written).min(TICK_SAMPLES as u64) as usize;
+            encoder.write_samples(&SILENCE[..len])?;
+            self.written += len as u64;
+        }
+        Ok(())
+    }
+}
+
+/// A saved speaker track with its silence statistics.
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackReport {
+    pub path: String,
+    pub user_id: Option<String>,
+    /// Display name in the recorded guild, when it could be looked up.
+    pub name: Option<String>,
+    pub duration_secs: f32,
+    /// Share of samples below the silence threshold, 0.0–1.0.
+    pub silence_ratio: f32,
+    pub near_empty: bool,
+    /// The speaker's audio sounds noise-suppressed by their Discord client.
+    pub likely_processed: bool,
+}
+
+/// What to do with audio from other bots in the channel, e.g. music bots.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BotAudio {
+    /// Record them like anyone else.
+    #[default]
+    Record,
+    /// Leave them out of the tracks and the mix.
+    Ignore,
+    /// Record them to a `music-bot` track, kept out of the speaker timeline
+    /// and talk time.
+    SeparateTrack,
+}
+
+/// Who in a guild may be recorded, before any consent is asked for.
+#[derive(Debug, Clone, Default)]
+pub struct RecordedUsers {
+    /// Users who asked not to be recorded, or whom the host left out.
+    pub excluded: HashSet<u64>,
+    /// When set, only these users are recorded.
+    pub only: Option<HashSet<u64>>,
+}
+
+impl RecordedUsers {
+    pub fn allows(&self, user_id: u64) -> bool {
+        !self.excluded.contains(&user_id)
+            && self
+                .only
+                .as_ref()
+                .map_or(true, |only| only.contains(&user_id))
+    }
+
+    /// Whether nobody is left out.
+    pub fn is_everyone(&self) -> bool {
+        self.excluded.is_empty() && self.only.is_none()
+    }
+}
+
+/// Per-session settings for a bot recording.
+#[derive(Debug, Clone, Copy)]
+pub struct ReceiverOptions {
+    pub format: AudioFormat,
+    pub decode_mode: VoiceDecodeMode,
+    /// Speaker tracks with less audible audio than this are discarded at finalize.
+    pub min_audible_secs: Option<u32>,
+    /// Also write a stereo mix of all speakers next to the per-speaker stems.
+    pub write_mix: bool,
+    /// Pad speaker tracks with silence so they all start with the session
+    /// and end together, ready to drop into a DAW.
+    pub align_tracks: bool,
+    /// Start a new `-partNN` file for each track every this many minutes.
+    /// Packet-level tracks are never split.
+    pub segment_minutes: Option<u32>,
+    /// Write nothing for speakers while they are server-muted, not even
+    /// alignment silence, so moderated stretches are cut from their tracks.
+    pub skip_server_muted: bool,
+    /// Log every voice tick to a debug dump capped at this many MiB.
+    pub tick_dump_mb: Option<u32>,
+    pub bot_audio: BotAudio,
+    /// Keep the messages posted in the channel's text chat during the session.
+    pub record_chat: bool,
+    /// File name template, see [`crate::naming`].
+    pub filename_template: String,
+}
+
+/// Mono samples per speaker in one 20 ms voice tick at 48 kHz.
+const TICK_SAMPLES: usize = 960;
+
+/// Speakers held back while a moderator has them server-muted.
+#[derive(Default)]
+struct ServerMutes {
+    muted: HashSet<u64>,
+    /// Session samples each user spent muted, which their track skips.
+    skipped: HashMap<u64, u64>,
+}
+
+/// A track opened before its speaker said anything.
+struct SpareTrack {
+    label: String,
+    encoder: Box<dyn AudioEncoder>,
+}
 
 /// Shared state between all VoiceHandler clones registered with songbird.
 pub struct ReceiverState {
     ssrc_map: Mutex<HashMap<u32, u64>>,
+    /// Display names by user ID; `None` while a lookup is pending or after it failed.
+    names: Mutex<HashMap<u64, Option<String>>>,
+    name_resolver: Option<NameResolver>,
+    bot_audio: BotAudio,
+    /// Whether each speaker is a bot account; `None` while the lookup is
+    /// pending. Only looked up when bots aren't simply recorded.
+    bots: Mutex<HashMap<u64, Option<bool>>>,
+    /// Who may be recorded in this guild.
+    recorded: RecordedUsers,
+    /// When consent was asked for, only members who gave it are recorded.
+    consent: Option<Arc<ConsentGate>>,
+    /// Label each speaker's file was created with, so it can be renamed once
+    /// the speaker's name is known.
+    file_labels: Mutex<HashMap<u32, String>>,
     encoders: Mutex<HashMap<u32, Box<dyn AudioEncoder>>>,
-    output_dir: String,
+    /// Tracks opened ahead for people already in the channel, by user ID, so
+    /// their first words aren't spent opening files.
+    spares: Mutex<HashMap<u64, SpareTrack>>,
+    track_stats: Mutex<HashMap<u32, TrackStats>>,
+    /// Real-time stereo mix of every speaker, when requested.
+    mix_encoder: Mutex<Option<Box<dyn AudioEncoder>>>,
+    /// File names the mix was written to, known once it is finalized.
+    mix_files: Mutex<Vec<String>>,
+    packet_writers: Mutex<HashMap<u32, Box<dyn PacketWriter>>>,
+    timeline: Mutex<TimelineBuilder>,
+    talk_time: Mutex<TalkTime>,
+    speaking_feed: Arc<SpeakingFeed>,
+    session: Arc<RecordingSession>,
+    /// The template rendered without a speaker, shared by the session-wide files.
+    session_stem: String,
+    filename_template: String,
+    started_at: String,
+    channel: ChannelRef,
     format: AudioFormat,
+    decode_mode: VoiceDecodeMode,
+    min_audible_secs: Option<u32>,
+    align_tracks: bool,
+    segment_minutes: Option<u32>,
+    skip_server_muted: bool,
+    server_mutes: Mutex<ServerMutes>,
+    tick_dump: Mutex<Option<TickDump>>,
+    chat_log: Option<Mutex<ChatLog>>,
+    /// Voice ticks recorded so far; the session clock for aligned tracks.
+    ticks: AtomicU64,
     sample_rate: u32,
     channels: u16,
     pub is_recording: Arc<AtomicBool>,
     pub peak_level_bits: Arc<AtomicU32>,
+    status_hook: Option<StatusHook>,
+    /// Only the first write failure of a session is reported to the UI.
+    error_reported: AtomicBool,
 }
 
 impl ReceiverState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         output_dir: &str,
-        format: AudioFormat,
+        channel: ChannelRef,
+        options: ReceiverOptions,
         is_recording: Arc<AtomicBool>,
         peak_level_bits: Arc<AtomicU32>,
-    ) -> Arc<Self> {
-        Arc::new(Self {
+        speaking_feed: Arc<SpeakingFeed>,
+        name_resolver: Option<NameResolver>,
+        recorded: RecordedUsers,
+        consent: Option<Arc<ConsentGate>>,
+        status_hook: Option<StatusHook>,
+    ) -> Result<Arc<Self>> {
+        let session = Arc::new(RecordingSession::create(
+            std::path::Path::new(output_dir),
+            "discord",
+        )?);
+        crate::latency::reset_capture();
+        crate::latency::reset_receive();
+        let segment_minutes = options.segment_minutes.filter(|&m| m > 0);
+        let session_stem = naming::render(
+            &options.filename_template,
+            chrono::Local::now(),
+            &name_parts(&channel, None),
+        );
+
+        let mix_encoder = if options.write_mix && options.decode_mode == VoiceDecodeMode::Decode {
+            let filename = format!("{}-mix.{}", session_stem, options.format.extension());
+            let encoder = open_track(&session, filename, 2, options.format, segment_minutes)?;
+            log::info!("Created mix encoder -> {}", encoder.path());
+            Some(encoder)
+        } else {
+            if options.write_mix {
+                log::warn!("Mixed output needs decoded voice; skipping mix in packet mode");
+            }
+            None
+        };
+        let align_tracks = options.align_tracks && options.decode_mode == VoiceDecodeMode::Decode;
+        if options.align_tracks && !align_tracks {
+            log::warn!("Aligned tracks need decoded voice; packet tracks are left unpadded");
+        }
+
+        let tick_dump = options.tick_dump_mb.filter(|&mb| mb > 0).and_then(|mb| {
+            match TickDump::create(&session_stem, mb) {
+                Ok(dump) => Some(dump),
+                Err(e) => {
+                    log::warn!("Tick dump unavailable: {:#}", e);
+                    None
+                }
+            }
+        });
+
+        Ok(Arc::new(Self {
             ssrc_map: Mutex::new(HashMap::new()),
+            names: Mutex::new(HashMap::new()),
+            name_resolver,
+            bot_audio: options.bot_audio,
+            bots: Mutex::new(HashMap::new()),
+            recorded,
+            consent,
+            file_labels: Mutex::new(HashMap::new()),
             encoders: Mutex::new(HashMap::new()),
-            output_dir: output_dir.to_string(),
-            format,
+            spares: Mutex::new(HashMap::new()),
+            track_stats: Mutex::new(HashMap::new()),
+            mix_encoder: Mutex::new(mix_encoder),
+            mix_files: Mutex::new(Vec::new()),
+            packet_writers: Mutex::new(HashMap::new()),
+            timeline: Mutex::new(TimelineBuilder::new()),
+            talk_time: Mutex::new(TalkTime::new()),
+            speaking_feed,
+            session,
+            session_stem,
+            filename_template: options.filename_template,
+            started_at: chrono::Local::now().to_rfc3339(),
+            channel,
+            format: options.format,
+            decode_mode: options.decode_mode,
+            min_audible_secs: options.min_audible_secs,
+            align_tracks,
+            segment_minutes,
+            skip_server_muted: options.skip_server_muted,
+            server_mutes: Mutex::new(ServerMutes::default()),
+            tick_dump: Mutex::new(tick_dump),
+            chat_log: options.record_chat.then(|| Mutex::new(ChatLog::new())),
+            ticks: AtomicU64::new(0),
             sample_rate: 48000,
             channels: 1, // mono per speaker
             is_recording,
             peak_level_bits,
-        })
+            status_hook,
+            error_reported: AtomicBool::new(false),
+        }))
+    }
+
+    /// Open tracks ahead of time for `members` (user ID and display name)
+    /// already in the channel. Unused ones are removed when the session ends.
+    pub fn prepare_speakers(&self, members: &[(u64, String)]) {
+        if self.decode_mode != VoiceDecodeMode::Decode {
+            return;
+        }
+        let now = chrono::Local::now();
+        let mut spares = self.spares.lock();
+        for (user_id, name) in members {
+            if !self.recorded.allows(*user_id) || spares.contains_key(user_id) {
+                continue;
+            }
+            let mut label = file_label(name).unwrap_or_else(|| speaker_id(0, Some(*user_id)));
+            if spares.values().any(|spare| spare.label == label) {
+                label = format!("{}-{}", label, user_id);
+            }
+            let filename = format!(
+                "{}.{}",
+                self.track_stem(now, &label),
+                self.format.extension()
+            );
+            match open_track(
+                &self.session,
+                filename,
+                self.channels,
+                self.format,
+                self.segment_minutes,
+            ) {
+                Ok(encoder) => {
+                    self.names.lock().insert(*user_id, Some(name.clone()));
+                    spares.insert(*user_id, SpareTrack { label, encoder });
+                }
+                Err(e) => log::warn!("Failed to prepare a track for user {}: {:#}", user_id, e),
+            }
+        }
+        log::info!("Prepared {} speaker track(s)", spares.len());
     }
 
-    /// Finalize all per-speaker encoders and return saved file paths.
+    /// Close and delete the tracks prepared for people who never spoke.
+    fn discard_spares(&self) {
+        for (user_id, spare) in self.spares.lock().drain() {
+            let paths = spare.encoder.paths();
+            if let Err(e) = spare.encoder.finalize() {
+                log::warn!("Failed to close unused track of user {}: {:#}", user_id, e);
+            }
+            for path in paths {
+                remove_partial(&path);
+            }
+        }
+    }
+
+    /// Log a write failure and tell the UI about the first one.
+    fn report_error(&self, message: String) {
+        log::error!("{}", message);
+        if let Some(hook) = &self.status_hook {
+            if !self.error_reported.swap(true, Ordering::Relaxed) {
+                hook(Recorder::Bot, RecordingEvent::Error { message });
+            }
+        }
+    }
+
+    /// Finalize all per-speaker encoders, move them into the library and
+    /// return the saved file paths.
     pub fn finalize_all(&self) -> Result<Vec<String>> {
+        self.discard_spares();
+        if let Some(dump) = self.tick_dump.lock().take() {
+            dump.finish();
+        }
+        let finished = match self.finalize_writers() {
+            Ok(finished) => finished,
+            Err(e) => {
+                self.session.mark_failed(&e.to_string());
+                return Err(e);
+            }
+        };
+        let tracks = self.rename_tracks(&finished);
+        self.discard_short_tracks();
+        // Losing the metadata is no reason to fail the recording
+        let timeline = self.save_timeline().unwrap_or_else(|e| {
+            log::warn!("Failed to save speaker timeline: {:#}", e);
+            None
+        });
+        let consent = self.save_consent().unwrap_or_else(|e| {
+            log::warn!("Failed to save consent log: {:#}", e);
+            None
+        });
+        let chat = self.save_chat().unwrap_or_else(|e| {
+            log::warn!("Failed to save chat log: {:#}", e);
+            Vec::new()
+        });
+        if let Err(e) = self.save_session_info(&tracks, timeline, consent, chat) {
+            log::warn!("Failed to save session manifest: {:#}", e);
+        }
+        self.session.commit()
+    }
+
+    /// Close every encoder and delete the session with all its tracks.
+    pub fn discard_all(&self) {
+        self.discard_spares();
+        if let Some(dump) = self.tick_dump.lock().take() {
+            dump.finish();
+        }
+        if let Err(e) = self.finalize_writers() {
+            log::warn!("Aborted session didn't finish cleanly: {:#}", e);
+        }
+        self.session.discard();
+    }
+
+    /// Write who spoke when next to the tracks, for video editing exports.
+    /// Returns the file name, or `None` when nobody spoke.
+    fn save_timeline(&self) -> Result<Option<String>> {
+        let names = self.resolved_names();
+        let timeline = self.timeline.lock().finish(&self.ssrc_map.lock(), &names);
+        if timeline.is_empty() {
+            return Ok(None);
+        }
+        let filename = format!("{}{}", self.session_stem, TIMELINE_SUFFIX);
+        timeline.save(&self.session.work_path(&filename)?)?;
+        Ok(Some(filename))
+    }
+
+    /// Write who consented to being recorded, and when, next to the tracks.
+    /// Returns the file name, or `None` when consent wasn't asked for.
+    fn save_consent(&self) -> Result<Option<String>> {
+        let Some(consent) = &self.consent else {
+            return Ok(None);
+        };
+        let filename = format!("{}{}", self.session_stem, CONSENT_SUFFIX);
+        consent.save(&self.session.work_path(&filename)?)?;
+        Ok(Some(filename))
+    }
+
+    /// Write the messages posted in the channel's text chat next to the
+    /// tracks, as JSON and as text. Returns both file names, or none when the
+    /// chat wasn't recorded or stayed quiet.
+    fn save_chat(&self) -> Result<Vec<String>> {
+        let Some(chat_log) = &self.chat_log else {
+            return Ok(Vec::new());
+        };
+        let chat_log = chat_log.lock();
+        if chat_log.is_empty() {
+            return Ok(Vec::new());
+        }
+        let json = format!("{}{}", self.session_stem, CHAT_SUFFIX);
+        let text = format!("{}{}", self.session_stem, CHAT_TEXT_SUFFIX);
+        chat_log.save(
+            &self.session.work_path(&json)?,
+            &self.session.work_path(&text)?,
+        )?;
+        Ok(vec![json, text])
+    }
+
+    /// Write the session manifest listing the kept tracks and their speakers.
+    fn save_session_info(
+        &self,
+        tracks: &[(u32, String)],
+        timeline: Option<String>,
+        consent: Option<String>,
+        chat: Vec<String>,
+    ) -> Result<()> {
+        let names = self.resolved_names();
+        let ssrc_map = self.ssrc_map.lock().clone();
+        let stats = self.track_stats.lock();
+        let work_dir = self.session.work_dir();
+        let mut tracks: Vec<SessionTrack> = tracks
+            .iter()
+            // Short tracks may have been discarded since
+            .filter(|(_, file)| work_dir.join(file).exists())
+            .map(|(ssrc, file)| {
+                let user_id = ssrc_map.get(ssrc).copied();
+                let noise_floor = stats.get(ssrc).map(|track| &track.noise_floor);
+                SessionTrack {
+                    file: file.clone(),
+                    ssrc: *ssrc,
+                    user_id: user_id.map(|id| id.to_string()),
+                    name: user_id.and_then(|id| names.get(&id)).cloned(),
+                    bot: self.is_bot(*ssrc) == Some(true),
+                    noise_floor_db: noise_floor.and_then(NoiseFloor::floor_db),
+                    likely_processed: noise_floor.is_some_and(NoiseFloor::likely_processed),
+                }
+            })
+            .collect();
+        drop(stats);
+        tracks.sort_by(|a, b| a.file.cmp(&b.file));
+
+        // Each part of a segmented track names its speaker again
+        let mut processed: Vec<String> = tracks
+            .iter()
+            .filter(|t| t.likely_processed)
+            .map(|t| t.name.clone().unwrap_or_else(|| format!("SSRC {}", t.ssrc)))
+            .collect();
+        processed.sort();
+        processed.dedup();
+        let notes =
+            super::processing::session_notes(super::processing::client_processing(), &processed);
+
+        let mut mix_files = self.mix_files.lock().clone().into_iter();
+        let info = SessionInfo {
+            started_at: self.started_at.clone(),
+            ended_at: chrono::Local::now().to_rfc3339(),
+            channel: self.channel.clone(),
+            sample_rate: self.sample_rate,
+            format: self
+                .decode_mode
+                .packet_extension()
+                .unwrap_or(self.format.extension())
+                .to_string(),
+            tracks,
+            mix: mix_files.next(),
+            mix_parts: mix_files.collect(),
+            timeline,
+            consent,
+            chat,
+            notes,
+        };
+        let filename = format!("{}{}", self.session_stem, SESSION_SUFFIX);
+        info.save(&self.session.work_path(&filename)?)
+    }
+
+    /// Drop finalized tracks with too little audible audio before they reach
+    /// the library. Packet-level tracks carry no statistics and are kept.
+    fn discard_short_tracks(&self) {
+        let Some(min_secs) = self.min_audible_secs.filter(|&s| s > 0) else {
+            return;
+        };
+        let min_samples = min_secs as u64 * self.sample_rate as u64 * self.channels as u64;
+
+        for track in self.track_stats.lock().values() {
+            let audible = track.samples - track.silent_samples;
+            if audible >= min_samples {
+                continue;
+            }
+            for file_name in &track.file_names {
+                let path = self.session.work_dir().join(file_name);
+                match std::fs::remove_file(&path) {
+                    Ok(()) => log::info!(
+                        "Discarded {} ({:.1}s audible, minimum {}s)",
+                        file_name,
+                        audible as f32 / (self.sample_rate * self.channels as u32) as f32,
+                        min_secs
+                    ),
+                    Err(e) => log::warn!("Failed to discard {}: {}", file_name, e),
+                }
+            }
+        }
+    }
+
+    /// Silence statistics for the decoded tracks among `committed` paths.
+    pub fn track_reports(&self, committed: &[String]) -> Vec<TrackReport> {
+        let names = self.resolved_names();
+        let stats = self.track_stats.lock();
+        let ssrc_map = self.ssrc_map.lock();
+        let samples_per_sec = (self.sample_rate * self.channels as u32) as f32;
+
+        let mut reports: Vec<TrackReport> = stats
+            .iter()
+            .filter_map(|(ssrc, track)| {
+                // A segmented track is reported by its first part
+                let path = committed.iter().find(|p| {
+                    std::path::Path::new(p)
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|name| track.file_names.iter().any(|f| f == name))
+                })?;
+                let silence_ratio = if track.samples == 0 {
+                    1.0
+                } else {
+                    track.silent_samples as f32 / track.samples as f32
+                };
+                Some(TrackReport {
+                    path: path.clone(),
+                    user_id: ssrc_map.get(ssrc).map(|id| id.to_string()),
+                    name: ssrc_map.get(ssrc).and_then(|id| names.get(id)).cloned(),
+                    duration_secs: track.samples as f32 / samples_per_sec,
+                    silence_ratio,
+                    near_empty: silence_ratio >= EMPTY_TRACK_SILENCE_RATIO,
+                    likely_processed: track.noise_floor.likely_processed(),
+                })
+            })
+            .collect();
+        reports.sort_by(|a, b| a.path.cmp(&b.path));
+        reports
+    }
+
+    /// Finalize every writer, even after one fails, and report the first error.
+    ///
+    /// Returns each finalized speaker track's SSRC and paths.
+    fn finalize_writers(&self) -> Result<Vec<(u32, Vec<String>)>> {
         let mut encoders = self.encoders.lock();
+        let mut packet_writers = self.packet_writers.lock();
         let ssrc_map = self.ssrc_map.lock();
-        let mut paths = Vec::new();
+        let mut first_error = None;
+        let mut finished = Vec::new();
+
+        if let Some(mix) = self.mix_encoder.lock().take() {
+            log::info!("Finalizing mix: {}", mix.path());
+            *self.mix_files.lock() = file_names(&mix.paths());
+            if let Err(e) = mix.finalize() {
+                log::error!("Failed to finalize mix: {:#}", e);
+                first_error.get_or_insert(e);
+            }
+        }
 
         for (ssrc, encoder) in encoders.drain() {
-            let path = encoder.path().to_string();
             log::info!(
                 "Finalizing speaker {} (user {:?}): {}",
                 ssrc,
                 ssrc_map.get(&ssrc),
-                path
+                encoder.path()
             );
-            encoder.finalize()?;
-            paths.push(path);
+            let paths = encoder.paths();
+            match encoder.finalize() {
+                Ok(()) => finished.push((ssrc, paths)),
+                Err(e) => {
+                    log::error!("Failed to finalize speaker {}: {:#}", ssrc, e);
+                    first_error.get_or_insert(e);
+                }
+            }
         }
 
-        Ok(paths)
+        for (ssrc, writer) in packet_writers.drain() {
+            log::info!(
+                "Finalizing packets for speaker {} (user {:?}): {}",
+                ssrc,
+                ssrc_map.get(&ssrc),
+                writer.path()
+            );
+            let path = writer.path().to_string();
+            match writer.finalize() {
+                Ok(()) => finished.push((ssrc, vec![path])),
+                Err(e) => {
+                    log::error!("Failed to finalize packets for speaker {}: {:#}", ssrc, e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        first_error.map_or(Ok(finished), Err)
     }
 
-    fn get_or_create_encoder(&self, ssrc: u32) -> Result<()> {
-        let mut encoders = self.encoders.lock();
-        if encoders.contains_key(&ssrc) {
-            return Ok(());
+    /// Rename tracks that were opened before their speaker's name was known.
+    ///
+    /// Returns the SSRC and final file name of each track, or of each part
+    /// of a segmented track.
+    fn rename_tracks(&self, finished: &[(u32, Vec<String>)]) -> Vec<(u32, String)> {
+        let mut tracks = Vec::new();
+        for (ssrc, paths) in finished {
+            let old_label = self.file_labels.lock().get(ssrc).cloned();
+            let label = self.unique_label(*ssrc);
+            let mut renamed = false;
+            let mut names = Vec::new();
+            for old_name in file_names(paths) {
+                let new_name = match &old_label {
+                    Some(old_label) if *old_label != label => relabel(&old_name, old_label, &label),
+                    _ => old_name.clone(),
+                };
+                if new_name == old_name {
+                    names.push(old_name);
+                    continue;
+                }
+                match self.session.rename_file(&old_name, &new_name) {
+                    Ok(()) => {
+                        renamed = true;
+                        names.push(new_name);
+                    }
+                    Err(e) => {
+                        log::warn!("Keeping {} unrenamed: {:#}", old_name, e);
+                        names.push(old_name);
+                    }
+                }
+            }
+            if renamed {
+                self.file_labels.lock().insert(*ssrc, label);
+            }
+            if let Some(track) = self.track_stats.lock().get_mut(ssrc) {
+                track.file_names = names.clone();
+            }
+            tracks.extend(names.into_iter().map(|name| (*ssrc, name)));
         }
+        tracks
+    }
 
+    pub fn channel(&self) -> &ChannelRef {
+        &self.channel
+    }
+
+    /// Keep a message posted in the recorded channel's text chat, unless
+    /// chat isn't being recorded or its author is left out of the recording.
+    pub fn chat_message(&self, msg: &serenity::model::channel::Message) {
+        let Some(chat_log) = &self.chat_log else {
+            return;
+        };
+        let user_id = msg.author.id.get();
+        let consented = self
+            .consent
+            .as_ref()
+            .map_or(true, |consent| consent.allows(user_id));
+        if !msg.author.bot && (!self.recorded.allows(user_id) || !consented) {
+            return;
+        }
+        chat_log.lock().push(msg);
+    }
+
+    /// Note a member of the recorded channel muting, deafening or being
+    /// muted, for the speaker timeline. People not being recorded are skipped.
+    pub fn voice_state_changed(&self, user_id: u64, flags: VoiceFlags) {
+        let consented = self
+            .consent
+            .as_ref()
+            .map_or(true, |consent| consent.allows(user_id));
+        if !self.recorded.allows(user_id) || !consented {
+            return;
+        }
+        if self.skip_server_muted {
+            let mut mutes = self.server_mutes.lock();
+            if flags.server_mute {
+                mutes.muted.insert(user_id);
+            } else {
+                mutes.muted.remove(&user_id);
+            }
+        }
+        self.timeline.lock().voice_state(user_id, flags);
+    }
+
+    /// Users who got a track this session.
+    pub fn recorded_users(&self) -> Vec<u64> {
         let ssrc_map = self.ssrc_map.lock();
-        let label = if let Some(user_id) = ssrc_map.get(&ssrc) {
-            format!("user-{}", user_id)
+        let mut users: Vec<u64> = self
+            .file_labels
+            .lock()
+            .keys()
+            .filter_map(|ssrc| ssrc_map.get(ssrc).copied())
+            .collect();
+        users.sort_unstable();
+        users.dedup();
+        users
+    }
+
+    /// Talk time, interruptions and longest monologue per speaker so far.
+    pub fn speaker_stats(&self) -> Vec<SpeakerStats> {
+        let names = self.resolved_names();
+        self.talk_time.lock().stats(&self.ssrc_map.lock(), &names)
+    }
+
+    /// Display names looked up so far.
+    fn resolved_names(&self) -> HashMap<u64, String> {
+        self.names
+            .lock()
+            .iter()
+            .filter_map(|(&id, name)| Some((id, name.clone()?)))
+            .collect()
+    }
+
+    /// Look up a newly mapped speaker's display name in the background.
+    fn resolve_name(self: &Arc<Self>, user_id: u64) {
+        let Some(resolver) = self.name_resolver.clone() else {
+            return;
+        };
+        match self.names.lock().entry(user_id) {
+            Entry::Occupied(_) => return,
+            Entry::Vacant(entry) => {
+                entry.insert(None);
+            }
+        }
+        let state = Arc::clone(self);
+        tokio::spawn(async move {
+            if let Some(name) = resolver.display_name(user_id).await {
+                log::info!("User {} is {}", user_id, name);
+                state.names.lock().insert(user_id, Some(name));
+            }
+        });
+    }
+
+    /// Find out whether `user_id` is a bot, unless bots are recorded like
+    /// anyone else. The cache answers for anyone in the channel right away,
+    /// so their first words aren't held back waiting on the API. A failed
+    /// lookup counts as a person.
+    fn resolve_bot(self: &Arc<Self>, user_id: u64) {
+        if self.bot_audio == BotAudio::Record {
+            return;
+        }
+        let Some(resolver) = self.name_resolver.clone() else {
+            return;
+        };
+        match self.bots.lock().entry(user_id) {
+            Entry::Occupied(_) => return,
+            Entry::Vacant(entry) => {
+                let cached = resolver.cached_is_bot(user_id);
+                entry.insert(cached);
+                if let Some(bot) = cached {
+                    if bot {
+                        log::info!("User {} is a bot", user_id);
+                    }
+                    return;
+                }
+            }
+        }
+        let state = Arc::clone(self);
+        tokio::spawn(async move {
+            let bot = resolver.is_bot(user_id).await.unwrap_or(false);
+            if bot {
+                log::info!("User {} is a bot", user_id);
+            }
+            state.bots.lock().insert(user_id, Some(bot));
+        });
+    }
+
+    /// Whether `ssrc` belongs to a bot account; `None` until that's known.
+    /// Always `Some(false)` when bots are recorded like anyone else.
+    fn is_bot(&self, ssrc: u32) -> Option<bool> {
+        if self.bot_audio == BotAudio::Record || self.name_resolver.is_none() {
+            return Some(false);
+        }
+        let user_id = self.user_id(ssrc)?;
+        self.bots.lock().get(&user_id).copied().flatten()
+    }
+
+    /// A speaker's sanitized display name once known, otherwise their
+    /// `user-<id>` / `ssrc-<n>` ID. Bots kept apart are all `music-bot`.
+    fn speaker_label(&self, ssrc: u32) -> String {
+        if self.is_bot(ssrc) == Some(true) {
+            return "music-bot".to_string();
+        }
+        let user_id = self.user_id(ssrc);
+        user_id
+            .and_then(|id| self.names.lock().get(&id).cloned().flatten())
+            .and_then(|name| file_label(&name))
+            .unwrap_or_else(|| speaker_id(ssrc, user_id))
+    }
+
+    /// [`Self::speaker_label`], made distinct from other speakers' files.
+    fn unique_label(&self, ssrc: u32) -> String {
+        let label = self.speaker_label(ssrc);
+        let taken = self
+            .file_labels
+            .lock()
+            .iter()
+            .any(|(&other, l)| other != ssrc && *l == label)
+            || self
+                .spares
+                .lock()
+                .values()
+                .any(|spare| spare.label == label);
+        if taken {
+            format!("{}-{}", label, ssrc)
         } else {
-            format!("ssrc-{}", ssrc)
+            label
+        }
+    }
+
+    /// Move a speaker's track over to the new SSRC Discord gave them, e.g.
+    /// after the bot rejoined the call, so they keep writing to the same file.
+    fn adopt_ssrc(&self, user_id: u64, ssrc: u32) {
+        let previous: Vec<u32> = self
+            .ssrc_map
+            .lock()
+            .iter()
+            .filter(|&(&other, &user)| other != ssrc && user == user_id)
+            .map(|(&other, _)| other)
+            .collect();
+        let mut labels = self.file_labels.lock();
+        if labels.contains_key(&ssrc) {
+            return;
+        }
+        let Some(old) = previous.into_iter().find(|old| labels.contains_key(old)) else {
+            return;
+        };
+        if let Some(label) = labels.remove(&old) {
+            labels.insert(ssrc, label);
+        }
+        drop(labels);
+
+        {
+            let mut encoders = self.encoders.lock();
+            if let Some(encoder) = encoders.remove(&old) {
+                encoders.insert(ssrc, encoder);
+            }
+            let mut stats = self.track_stats.lock();
+            if let Some(track) = stats.remove(&old) {
+                stats.insert(ssrc, track);
+            }
+        }
+        let mut writers = self.packet_writers.lock();
+        if let Some(writer) = writers.remove(&old) {
+            writers.insert(ssrc, writer);
+        }
+        log::info!(
+            "User {} moved from SSRC {} to {}; continuing their track",
+            user_id,
+            old,
+            ssrc
+        );
+    }
+
+    fn user_id(&self, ssrc: u32) -> Option<u64> {
+        self.ssrc_map.lock().get(&ssrc).copied()
+    }
+
+    /// Whether `ssrc`'s speaker is server-muted and held back.
+    fn is_server_muted(&self, ssrc: u32) -> bool {
+        self.skip_server_muted
+            && self
+                .user_id(ssrc)
+                .is_some_and(|id| self.server_mutes.lock().muted.contains(&id))
+    }
+
+    /// Where `ssrc`'s track should be at `position` on the session clock,
+    /// leaving out the time its speaker spent server-muted.
+    fn track_position(&self, ssrc: u32, position: u64) -> u64 {
+        let Some(user_id) = self.user_id(ssrc) else {
+            return position;
         };
-        drop(ssrc_map);
+        let skipped = self
+            .server_mutes
+            .lock()
+            .skipped
+            .get(&user_id)
+            .copied()
+            .unwrap_or(0);
+        position.saturating_sub(skipped)
+    }
 
-        let timestamp = chrono::Local::now().format("%Y-%m-%d_%H%M%S");
+    /// Whether to drop `ssrc`'s audio. While anyone is left out, consent
+    /// is required or bots are told apart, speakers not yet known to be
+    /// allowed are held back too.
+    fn is_excluded(&self, ssrc: u32) -> bool {
+        match self.is_bot(ssrc) {
+            None => return true,
+            Some(true) if self.bot_audio == BotAudio::Ignore => return true,
+            _ => {}
+        }
+        let user_id = self.user_id(ssrc);
+        if let Some(consent) = &self.consent {
+            if !user_id.is_some_and(|id| consent.allows(id)) {
+                return true;
+            }
+        }
+        if self.recorded.is_everyone() {
+            return false;
+        }
+        user_id.map_or(true, |user_id| !self.recorded.allows(user_id))
+    }
+
+    fn speaker_filename(&self, ssrc: u32, extension: &str) -> String {
+        let label = self.unique_label(ssrc);
         let filename = format!(
-            "discord-{}-{}.{}",
-            timestamp,
-            label,
-            self.format.extension()
+            "{}.{}",
+            self.track_stem(chrono::Local::now(), &label),
+            extension
         );
-        let path = std::path::Path::new(&self.output_dir)
-            .join(&filename)
-            .to_string_lossy()
-            .to_string();
+        self.file_labels.lock().insert(ssrc, label);
+        filename
+    }
 
-        let encoder = create_encoder(&path, self.channels, self.sample_rate, self.format, false)?;
-        log::info!("Created encoder for speaker {} -> {}", ssrc, path);
+    /// File name of a speaker's track opened at `now`, without extension.
+    fn track_stem(&self, now: chrono::DateTime<chrono::Local>, label: &str) -> String {
+        naming::render(
+            &self.filename_template,
+            now,
+            &name_parts(&self.channel, Some(label)),
+        )
+    }
+
+    fn get_or_create_encoder(&self, ssrc: u32) -> Result<()> {
+        let mut encoders = self.encoders.lock();
+        if encoders.contains_key(&ssrc) {
+            return Ok(());
+        }
+
+        let spare = self
+            .user_id(ssrc)
+            .and_then(|user_id| self.spares.lock().remove(&user_id));
+        let encoder = match spare {
+            Some(spare) => {
+                self.file_labels.lock().insert(ssrc, spare.label);
+                log::info!(
+                    "Using prepared track for speaker {} -> {}",
+                    ssrc,
+                    spare.encoder.path()
+                );
+                spare.encoder
+            }
+            None => {
+                let filename = self.speaker_filename(ssrc, self.format.extension());
+                let encoder = open_track(
+                    &self.session,
+                    filename,
+                    self.channels,
+                    self.format,
+                    self.segment_minutes,
+                )?;
+                log::info!("Created encoder for speaker {} -> {}", ssrc, encoder.path());
+                encoder
+            }
+        };
+        self.track_stats.lock().insert(
+            ssrc,
+            TrackStats {
+                file_names: file_names(&encoder.paths()),
+                samples: 0,
+                silent_samples: 0,
+                written: 0,
+                noise_floor: NoiseFloor::default(),
+            },
+        );
         encoders.insert(ssrc, encoder);
         Ok(())
     }
+
+    fn write_packet(&self, ssrc: u32, packet: &RtpData) -> Result<()> {
+        let mut writers = self.packet_writers.lock();
+        let writer = match writers.entry(ssrc) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let extension = self.decode_mode.packet_extension().unwrap_or("bin");
+                let filename = self.speaker_filename(ssrc, extension);
+                let path = self
+                    .session
+                    .work_path(&filename)?
+                    .to_string_lossy()
+                    .to_string();
+                let writer = create_packet_writer(&path, self.decode_mode)?;
+                log::info!("Created packet writer for speaker {} -> {}", ssrc, path);
+                entry.insert(writer)
+            }
+        };
+        writer.write_packet(packet)
+    }
+}
+
+/// The channel's names, or what's known of them, for file names so it's
+/// clear where a session was recorded.
+fn name_parts<'a>(channel: &'a ChannelRef, speaker: Option<&'a str>) -> NameParts<'a> {
+    NameParts {
+        guild: channel.guild_name.as_deref(),
+        channel: channel.channel_name.as_deref(),
+        speaker,
+    }
+}
+
+/// Open `filename` in the session at 48 kHz, split into parts every
+/// `segment_minutes` when set.
+fn open_track(
+    session: &Arc<RecordingSession>,
+    filename: String,
+    channels: u16,
+    format: AudioFormat,
+    segment_minutes: Option<u32>,
+) -> Result<Box<dyn AudioEncoder>> {
+    let Some(minutes) = segment_minutes else {
+        let path = session.work_path(&filename)?.to_string_lossy().to_string();
+        return create_encoder(&path, channels, 48000, format, false);
+    };
+    let session = Arc::clone(session);
+    create_segmented_encoder(channels, 48000, format, false, minutes, move |part| {
+        let path = session.work_path(&part_name(&filename, part))?;
+        Ok(path.to_string_lossy().to_string())
+    })
+}
+
+fn file_names(paths: &[String]) -> Vec<String> {
+    paths
+        .iter()
+        .filter_map(|p| {
+            std::path::Path::new(p)
+                .file_name()?
+                .to_str()
+                .map(str::to_string)
+        })
+        .collect()
+}
+
+/// `name` with the last occurrence of the speaker label `from` replaced by
+/// `to`, keeping any part number and the extension.
+fn relabel(name: &str, from: &str, to: &str) -> String {
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    let base = match stem.rsplit_once("-part") {
+        Some((base, part)) if !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()) => base,
+        _ => stem,
+    };
+    match base.rfind(from) {
+        Some(at) => format!("{}{}{}", &name[..at], to, &name[at + from.len()..]),
+        None => name.to_string(),
+    }
 }
 
 /// Songbird event handler — wraps shared state via Arc so it can be cloned
@@ -113,54 +1110,183 @@ impl VoiceEventHandler for VoiceHandler {
                 if let Some(user_id) = speaking.user_id {
                     let mut map = state.ssrc_map.lock();
                     map.insert(speaking.ssrc, user_id.0);
+                    drop(map);
                     log::info!(
                         "Speaker mapping: SSRC {} -> user {}",
                         speaking.ssrc,
                         user_id.0
                     );
+                    state.adopt_ssrc(user_id.0, speaking.ssrc);
+                    if state.recorded.allows(user_id.0) {
+                        state.resolve_name(user_id.0);
+                        state.resolve_bot(user_id.0);
+                    }
                 }
             }
             EventContext::VoiceTick(tick) => {
                 if !state.is_recording.load(Ordering::Relaxed) {
                     return None;
                 }
+                crate::latency::record_tick();
+                if let Some(dump) = state.tick_dump.lock().as_mut() {
+                    dump.record(
+                        tick,
+                        |ssrc| state.user_id(ssrc),
+                        |ssrc| state.is_excluded(ssrc),
+                    );
+                }
+
+                // Packet-level modes skip decoding entirely (no level metering)
+                if state.decode_mode != VoiceDecodeMode::Decode {
+                    let mut heard = Vec::new();
+                    for (&ssrc, voice_data) in &tick.speaking {
+                        if state.is_excluded(ssrc) || state.is_server_muted(ssrc) {
+                            continue;
+                        }
+                        if let Some(ref packet) = voice_data.packet {
+                            // A music bot's audio isn't anyone speaking
+                            if state.is_bot(ssrc) != Some(true) {
+                                heard.push(ssrc);
+                                state.timeline.lock().speaking(ssrc);
+                                state
+                                    .speaking_feed
+                                    .heard(ssrc, state.user_id(ssrc), None, true);
+                            }
+                            if let Err(e) = state.write_packet(ssrc, packet) {
+                                state.report_error(format!(
+                                    "Failed to write packet for SSRC {}: {}",
+                                    ssrc, e
+                                ));
+                            }
+                        }
+                    }
+                    state.talk_time.lock().tick(&heard);
+                    state.speaking_feed.tick_done();
+                    return None;
+                }
 
                 let mut global_peak: f32 = 0.0;
+                let mut heard = Vec::new();
+                let tick_start = state.ticks.fetch_add(1, Ordering::Relaxed) * TICK_SAMPLES as u64;
+                let mut mix = state
+                    .mix_encoder
+                    .lock()
+                    .is_some()
+                    .then(|| vec![0f32; TICK_SAMPLES]);
+                if state.skip_server_muted {
+                    let mut mutes = state.server_mutes.lock();
+                    let ServerMutes { muted, skipped } = &mut *mutes;
+                    for &user_id in muted.iter() {
+                        *skipped.entry(user_id).or_default() += TICK_SAMPLES as u64;
+                    }
+                }
 
                 for (&ssrc, voice_data) in &tick.speaking {
+                    if state.is_excluded(ssrc) || state.is_server_muted(ssrc) {
+                        continue;
+                    }
                     if let Some(ref audio) = voice_data.decoded_voice {
                         // Track peak level across all speakers
-                        let peak = audio
+                        let norm_peak = audio
                             .iter()
-                            .fold(0.0f32, |max, &s| max.max((s as f32).abs()));
-                        let norm_peak = peak / i16::MAX as f32;
+                            .fold(0.0f32, |max, &s| max.max(i16_to_float(s).abs()));
                         if norm_peak > global_peak {
                             global_peak = norm_peak;
                         }
+                        let audible = norm_peak > SILENCE_THRESHOLD;
+                        // A music bot's audio isn't anyone speaking
+                        if state.is_bot(ssrc) != Some(true) {
+                            if audible {
+                                heard.push(ssrc);
+                                state.timeline.lock().speaking(ssrc);
+                            }
+                            state.speaking_feed.heard(
+                                ssrc,
+                                state.user_id(ssrc),
+                                Some(norm_peak),
+                                audible,
+                            );
+                        }
+
+                        if let Some(ref mut mix) = mix {
+                            if mix.len() < audio.len() {
+                                mix.resize(audio.len(), 0.0);
+                            }
+                            for (m, &sample) in mix.iter_mut().zip(audio.iter()) {
+                                *m += i16_to_float(sample);
+                            }
+                        }
 
                         // Ensure we have an encoder for this speaker
                         if let Err(e) = state.get_or_create_encoder(ssrc) {
-                            log::error!("Failed to create encoder for SSRC {}: {}", ssrc, e);
+                            state.report_error(format!(
+                                "Failed to create encoder for SSRC {}: {}",
+                                ssrc, e
+                            ));
                             continue;
                         }
 
                         // Write samples
                         let mut encoders = state.encoders.lock();
                         if let Some(encoder) = encoders.get_mut(&ssrc) {
-                            for &sample in audio.iter() {
-                                let float_sample = sample as f32 / i16::MAX as f32;
-                                if let Err(e) = encoder.write_sample(float_sample) {
-                                    log::error!("Failed to write sample: {}", e);
-                                    break;
+                            let mut stats = state.track_stats.lock();
+                            let Some(track) = stats.get_mut(&ssrc) else {
+                                continue;
+                            };
+                            // A speaker who joins late starts at their place in the session
+                            if state.align_tracks {
+                                let position = state.track_position(ssrc, tick_start);
+                                if let Err(e) = track.pad_to(encoder, position) {
+                                    log::error!("Failed to pad speaker {}: {}", ssrc, e);
                                 }
                             }
+                            let samples: Vec<f32> =
+                                audio.iter().map(|&s| i16_to_float(s)).collect();
+                            let silent = samples
+                                .iter()
+                                .filter(|s| s.abs() <= SILENCE_THRESHOLD)
+                                .count() as u64;
+                            if let Err(e) = encoder.write_samples(&samples) {
+                                state.report_error(format!("Failed to write samples: {}", e));
+                            }
+                            track.samples += audio.len() as u64;
+                            track.silent_samples += silent;
+                            track.noise_floor.add(&samples);
+                            track.written += audio.len() as u64;
+                        }
+                    }
+                }
+
+                // Quiet and absent speakers get this tick as silence
+                if state.align_tracks {
+                    let mut encoders = state.encoders.lock();
+                    let mut stats = state.track_stats.lock();
+                    for (&ssrc, encoder) in encoders.iter_mut() {
+                        let Some(track) = stats.get_mut(&ssrc) else {
+                            continue;
+                        };
+                        let position = state.track_position(ssrc, tick_start + TICK_SAMPLES as u64);
+                        if let Err(e) = track.pad_to(encoder, position) {
+                            log::error!("Failed to pad speaker {}: {}", ssrc, e);
+                        }
+                    }
+                }
+
+                // The mix advances every tick, silent or not, to stay in real time
+                if let Some(mix) = mix {
+                    if let Some(encoder) = state.mix_encoder.lock().as_mut() {
+                        // Mono mix to both channels
+                        let stereo: Vec<f32> =
+                            mix.iter().flat_map(|s| [s.clamp(-1.0, 1.0); 2]).collect();
+                        if let Err(e) = encoder.write_samples(&stereo) {
+                            state.report_error(format!("Failed to write mix samples: {}", e));
                         }
                     }
                 }
 
-                state
-                    .peak_level_bits
-                    .store(global_peak.to_bits(), Ordering::Relaxed);
+                state.talk_time.lock().tick(&heard);
+                state.speaking_feed.tick_done();
+                hold_peak(&state.peak_level_bits, global_peak);
             }
             _ => {}
         }
@@ -0,0 +1,116 @@
+use serenity::all::{ChannelId, GuildId};
+use serenity::async_trait;
+use songbird::{Event, EventContext, EventHandler as VoiceEventHandler, Songbird};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::events::{Recorder, RecordingEvent, StatusHook};
+
+/// Rejoin attempts before giving up on a dropped voice connection.
+const MAX_ATTEMPTS: u32 = 6;
+const FIRST_RETRY: Duration = Duration::from_secs(2);
+const MAX_RETRY: Duration = Duration::from_secs(30);
+
+/// Rejoins the voice channel when the bot's voice connection drops during a
+/// session. Serenity reconnects the gateway by itself and songbird retries
+/// the voice connection a few times; this takes over once songbird gives up.
+/// The call keeps its event handlers, so the session's receiver carries on
+/// writing to the same files.
+#[derive(Clone)]
+pub struct Reconnector {
+    songbird: Arc<Songbird>,
+    guild: GuildId,
+    channel: ChannelId,
+    is_recording: Arc<AtomicBool>,
+    status_hook: Option<StatusHook>,
+    rejoining: Arc<AtomicBool>,
+}
+
+impl Reconnector {
+    pub fn new(
+        songbird: Arc<Songbird>,
+        guild: GuildId,
+        channel: ChannelId,
+        is_recording: Arc<AtomicBool>,
+        status_hook: Option<StatusHook>,
+    ) -> Self {
+        Self {
+            songbird,
+            guild,
+            channel,
+            is_recording,
+            status_hook,
+            rejoining: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn recording(&self) -> bool {
+        self.is_recording.load(Ordering::Relaxed)
+    }
+
+    fn emit(&self, event: RecordingEvent) {
+        if let Some(hook) = &self.status_hook {
+            hook(Recorder::Bot, event);
+        }
+    }
+
+    async fn rejoin(self) {
+        let mut delay = FIRST_RETRY;
+        for attempt in 1..=MAX_ATTEMPTS {
+            tokio::time::sleep(delay).await;
+            if !self.recording() {
+                break;
+            }
+            match self.songbird.join(self.guild, self.channel).await {
+                Ok(_) if !self.recording() => {
+                    // Stopped while we were rejoining
+                    let _ = self.songbird.remove(self.guild).await;
+                    break;
+                }
+                Ok(_) => {
+                    log::info!(
+                        "Rejoined voice channel {} after {} attempt(s)",
+                        self.channel,
+                        attempt
+                    );
+                    self.rejoining.store(false, Ordering::SeqCst);
+                    self.emit(RecordingEvent::Resumed { attempts: attempt });
+                    return;
+                }
+                Err(e) => log::warn!("Rejoin attempt {} failed: {}", attempt, e),
+            }
+            delay = (delay * 2).min(MAX_RETRY);
+        }
+        self.rejoining.store(false, Ordering::SeqCst);
+        if self.recording() {
+            self.emit(RecordingEvent::Error {
+                message: "Couldn't rejoin the voice channel; stop the recording to save what was captured"
+                    .to_string(),
+            });
+        }
+    }
+}
+
+#[async_trait]
+impl VoiceEventHandler for Reconnector {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::DriverDisconnect(data) = ctx else {
+            return None;
+        };
+        // Leaving at the end of a session stops recording first
+        if !self.recording() || self.rejoining.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+        let reason = data
+            .reason
+            .map(|reason| format!("{:?}", reason))
+            .unwrap_or_else(|| "disconnected".to_string());
+        log::warn!("Voice connection lost mid-session ({}); rejoining", reason);
+        self.emit(RecordingEvent::Interrupted {
+            message: format!("Lost the voice connection ({}); reconnecting…", reason),
+        });
+        tokio::spawn(self.clone().rejoin());
+        None
+    }
+}
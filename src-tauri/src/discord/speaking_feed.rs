@@ -0,0 +1,263 @@
+use anyhow::{Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::async_runtime::JoinHandle;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::task::JoinSet;
+use tokio_tungstenite::tungstenite::Message;
+
+use super::timeline::{speaker_id, HANGOVER_MS};
+
+/// How often per-speaker levels are sent while someone is in the channel.
+const LEVELS_INTERVAL: Duration = Duration::from_millis(100);
+/// Events a slow client may fall behind by before it skips ahead.
+const BACKLOG: usize = 256;
+
+/// A message on the feed, sent as JSON text with a `type` field.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum FeedEvent {
+    /// A bot recording started or stopped. Sent to new clients first.
+    Session { recording: bool },
+    /// A speaker started or stopped talking.
+    Speaking {
+        id: String,
+        user_id: Option<String>,
+        speaking: bool,
+    },
+    /// Everyone heard since the last update, with their peak level.
+    Levels { speakers: Vec<SpeakerLevel> },
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct SpeakerLevel {
+    id: String,
+    user_id: Option<String>,
+    speaking: bool,
+    /// Peak 0.0–1.0 since the last update; `null` when voice isn't decoded.
+    level: Option<f32>,
+}
+
+struct Speaker {
+    user_id: Option<u64>,
+    speaking: bool,
+    last_audible: Instant,
+    level: Option<f32>,
+}
+
+impl Speaker {
+    fn id(&self, ssrc: u32) -> String {
+        speaker_id(ssrc, self.user_id)
+    }
+
+    fn event(&self, ssrc: u32, speaking: bool) -> FeedEvent {
+        FeedEvent::Speaking {
+            id: self.id(ssrc),
+            user_id: self.user_id.map(|id| id.to_string()),
+            speaking,
+        }
+    }
+}
+
+/// Live speaking state of bot recordings, served to local WebSocket clients
+/// such as VTuber/PNGTuber tools.
+///
+/// The receiver reports every voice tick; clients get `speaking` events when
+/// someone starts or stops talking and `levels` updates ten times a second.
+pub struct SpeakingFeed {
+    events: broadcast::Sender<String>,
+    recording: Mutex<bool>,
+    speakers: Mutex<HashMap<u32, Speaker>>,
+    last_levels: Mutex<Instant>,
+    server: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl SpeakingFeed {
+    pub fn new() -> Self {
+        let (events, _) = broadcast::channel(BACKLOG);
+        Self {
+            events,
+            recording: Mutex::new(false),
+            speakers: Mutex::new(HashMap::new()),
+            last_levels: Mutex::new(Instant::now()),
+            server: Mutex::new(None),
+        }
+    }
+
+    fn send(&self, event: &FeedEvent) {
+        // Nobody listening is the normal case
+        if self.events.receiver_count() == 0 {
+            return;
+        }
+        if let Ok(json) = serde_json::to_string(event) {
+            let _ = self.events.send(json);
+        }
+    }
+
+    pub fn session_started(&self) {
+        self.speakers.lock().clear();
+        *self.recording.lock() = true;
+        self.send(&FeedEvent::Session { recording: true });
+    }
+
+    pub fn session_stopped(&self) {
+        let speakers = std::mem::take(&mut *self.speakers.lock());
+        for (ssrc, speaker) in speakers.into_iter().filter(|(_, s)| s.speaking) {
+            self.send(&speaker.event(ssrc, false));
+        }
+        *self.recording.lock() = false;
+        self.send(&FeedEvent::Session { recording: false });
+    }
+
+    /// Report one speaker's audio in the current voice tick. `level` is the
+    /// tick's peak when voice is decoded.
+    pub fn heard(&self, ssrc: u32, user_id: Option<u64>, level: Option<f32>, audible: bool) {
+        let now = Instant::now();
+        let mut speakers = self.speakers.lock();
+        let speaker = speakers.entry(ssrc).or_insert(Speaker {
+            user_id,
+            speaking: false,
+            last_audible: now,
+            level: None,
+        });
+        speaker.user_id = speaker.user_id.or(user_id);
+        speaker.level = match (speaker.level, level) {
+            (Some(previous), Some(level)) => Some(previous.max(level)),
+            (previous, level) => level.or(previous),
+        };
+        if !audible {
+            return;
+        }
+        speaker.last_audible = now;
+        if !speaker.speaking {
+            speaker.speaking = true;
+            let event = speaker.event(ssrc, true);
+            drop(speakers);
+            self.send(&event);
+        }
+    }
+
+    /// Close the current voice tick: end pauses longer than the hangover and
+    /// send levels when they are due.
+    pub fn tick_done(&self) {
+        let now = Instant::now();
+        let hangover = Duration::from_millis(HANGOVER_MS);
+        let mut events = Vec::new();
+        {
+            let mut speakers = self.speakers.lock();
+            for (&ssrc, speaker) in speakers.iter_mut() {
+                if speaker.speaking && now.duration_since(speaker.last_audible) > hangover {
+                    speaker.speaking = false;
+                    events.push(speaker.event(ssrc, false));
+                }
+            }
+
+            let mut last_levels = self.last_levels.lock();
+            if now.duration_since(*last_levels) >= LEVELS_INTERVAL {
+                *last_levels = now;
+                let mut levels: Vec<SpeakerLevel> = speakers
+                    .iter_mut()
+                    .map(|(&ssrc, speaker)| SpeakerLevel {
+                        id: speaker.id(ssrc),
+                        user_id: speaker.user_id.map(|id| id.to_string()),
+                        speaking: speaker.speaking,
+                        level: speaker.level.take(),
+                    })
+                    .collect();
+                levels.sort_by(|a, b| a.id.cmp(&b.id));
+                events.push(FeedEvent::Levels { speakers: levels });
+            }
+        }
+        for event in &events {
+            self.send(event);
+        }
+    }
+
+    /// Everything a client needs to catch up on when it connects.
+    fn snapshot(&self) -> Vec<FeedEvent> {
+        let mut events = vec![FeedEvent::Session {
+            recording: *self.recording.lock(),
+        }];
+        events.extend(
+            self.speakers
+                .lock()
+                .iter()
+                .filter(|(_, s)| s.speaking)
+                .map(|(&ssrc, speaker)| speaker.event(ssrc, true)),
+        );
+        events
+    }
+
+    /// Serve the feed on `ws://127.0.0.1:<port>`, or stop serving with `None`.
+    pub async fn listen(self: &Arc<Self>, port: Option<u16>) -> Result<()> {
+        let previous = self.server.lock().take();
+        if let Some(task) = previous {
+            task.abort();
+            // Wait for the old listener to close so its port can be reused
+            let _ = task.await;
+        }
+        let Some(port) = port else {
+            return Ok(());
+        };
+
+        // Loopback only: the feed is for tools on this machine
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .await
+            .with_context(|| format!("Failed to listen on port {}", port))?;
+        log::info!("Speaking feed listening on ws://127.0.0.1:{}", port);
+
+        let feed = Arc::clone(self);
+        let task = tauri::async_runtime::spawn(async move {
+            // Dropped with the server, which disconnects every client
+            let mut clients = JoinSet::new();
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => {
+                        let Ok((stream, _)) = accepted else { continue };
+                        let feed = Arc::clone(&feed);
+                        clients.spawn(async move {
+                            if let Err(e) = feed.serve_client(stream).await {
+                                log::debug!("Speaking feed client disconnected: {}", e);
+                            }
+                        });
+                    }
+                    Some(_) = clients.join_next() => {}
+                }
+            }
+        });
+        *self.server.lock() = Some(task);
+        Ok(())
+    }
+
+    async fn serve_client(&self, stream: TcpStream) -> Result<()> {
+        let mut socket = tokio_tungstenite::accept_async(stream).await?;
+        let mut events = self.events.subscribe();
+        for event in self.snapshot() {
+            socket
+                .send(Message::Text(serde_json::to_string(&event)?))
+                .await?;
+        }
+
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(json) => socket.send(Message::Text(json)).await?,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                // Clients have nothing to say; only watch for them leaving
+                message = socket.next() => match message {
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.into()),
+                },
+            }
+        }
+        Ok(())
+    }
+}
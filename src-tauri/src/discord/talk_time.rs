@@ -0,0 +1,124 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use super::timeline::{speaker_id, HANGOVER_MS, TICK_MS};
+
+/// Ticks of quiet that don't end someone's turn, the same pause the
+/// timeline bridges.
+const PAUSE_TICKS: u64 = HANGOVER_MS / TICK_MS;
+
+/// How much one speaker talked during a bot session.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeakerStats {
+    /// See [`speaker_id`].
+    pub id: String,
+    pub user_id: Option<String>,
+    /// Display name in the recorded guild, when it could be looked up.
+    pub name: Option<String>,
+    pub talk_ms: u64,
+    /// Fraction of everyone's talk time, from 0.0 to 1.0.
+    pub share: f64,
+    /// Turns started while someone else was still talking.
+    pub interruptions: u64,
+    /// Longest turn, counting pauses shorter than the timeline's hangover.
+    pub longest_monologue_ms: u64,
+}
+
+#[derive(Default)]
+struct Turns {
+    ticks: u64,
+    turns: u64,
+    interruptions: u64,
+    /// Tick the current or last turn began.
+    turn_start: u64,
+    /// Last tick the speaker was heard.
+    last_heard: u64,
+    longest_turn: u64,
+}
+
+impl Turns {
+    fn in_turn(&self, tick: u64) -> bool {
+        self.turns > 0 && tick - self.last_heard <= PAUSE_TICKS
+    }
+}
+
+/// Counts the voice ticks each SSRC is heard in, and their turns.
+#[derive(Default)]
+pub struct TalkTime {
+    tick: u64,
+    speakers: HashMap<u32, Turns>,
+}
+
+impl TalkTime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Note one voice tick in which `heard` were audible.
+    pub fn tick(&mut self, heard: &[u32]) {
+        let tick = self.tick;
+        self.tick += 1;
+        let talking: HashSet<u32> = self
+            .speakers
+            .iter()
+            .filter(|(_, turns)| turns.in_turn(tick))
+            .map(|(&ssrc, _)| ssrc)
+            .collect();
+        for &ssrc in heard {
+            let turns = self.speakers.entry(ssrc).or_default();
+            if !turns.in_turn(tick) {
+                turns.turns += 1;
+                turns.turn_start = tick;
+                if talking.iter().any(|&other| other != ssrc) {
+                    turns.interruptions += 1;
+                }
+            }
+            turns.ticks += 1;
+            turns.last_heard = tick;
+            turns.longest_turn = turns.longest_turn.max(tick + 1 - turns.turn_start);
+        }
+    }
+
+    /// Statistics so far, one entry per person (through the SSRC → user map)
+    /// with the most talkative first.
+    pub fn stats(
+        &self,
+        ssrc_map: &HashMap<u32, u64>,
+        names: &HashMap<u64, String>,
+    ) -> Vec<SpeakerStats> {
+        let mut stats: Vec<SpeakerStats> = Vec::new();
+        for (ssrc, turns) in &self.speakers {
+            let user = ssrc_map.get(ssrc).copied();
+            let id = speaker_id(*ssrc, user);
+            // A user who reconnects gets a new SSRC; keep one entry per person
+            match stats.iter_mut().find(|s| s.id == id) {
+                Some(existing) => {
+                    existing.talk_ms += turns.ticks * TICK_MS;
+                    existing.interruptions += turns.interruptions;
+                    existing.longest_monologue_ms = existing
+                        .longest_monologue_ms
+                        .max(turns.longest_turn * TICK_MS);
+                }
+                None => stats.push(SpeakerStats {
+                    id,
+                    user_id: user.map(|id| id.to_string()),
+                    name: user.and_then(|user| names.get(&user)).cloned(),
+                    talk_ms: turns.ticks * TICK_MS,
+                    share: 0.0,
+                    interruptions: turns.interruptions,
+                    longest_monologue_ms: turns.longest_turn * TICK_MS,
+                }),
+            }
+        }
+        let total: u64 = stats.iter().map(|s| s.talk_ms).sum();
+        for speaker in stats.iter_mut() {
+            speaker.share = if total > 0 {
+                speaker.talk_ms as f64 / total as f64
+            } else {
+                0.0
+            };
+        }
+        stats.sort_by(|a, b| b.talk_ms.cmp(&a.talk_ms).then_with(|| a.id.cmp(&b.id)));
+        stats
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use songbird::events::context_data::VoiceTick;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+
+const HEADER: &str =
+    "tick,elapsed_ms,ssrc,user_id,sequence,rtp_timestamp,payload_len,decoded_samples\n";
+
+/// Where tick dumps are written, outside the recordings library.
+pub fn dump_dir() -> PathBuf {
+    crate::data_dir::cache_dir().join("tick-dumps")
+}
+
+/// Debug log of every 20 ms voice tick the bot receives, one CSV row per
+/// speaker and tick, for diagnosing desynced tracks. Packets lost in a tick
+/// leave the RTP columns empty. Writing stops once the file reaches its cap.
+pub struct TickDump {
+    file: BufWriter<File>,
+    path: PathBuf,
+    started: Instant,
+    ticks: u64,
+    written: u64,
+    limit: u64,
+    full: bool,
+}
+
+impl TickDump {
+    /// Start `<stem>-ticks.csv` in [`dump_dir`], capped at `limit_mb` MiB.
+    pub fn create(stem: &str, limit_mb: u32) -> Result<Self> {
+        let dir = dump_dir();
+        std::fs::create_dir_all(&dir).context("Failed to create tick dump folder")?;
+        let path = dir.join(format!("{}-ticks.csv", stem));
+        let mut file = BufWriter::new(File::create(&path).context("Failed to create tick dump")?);
+        file.write_all(HEADER.as_bytes())?;
+        log::info!("Dumping voice ticks to {}", path.display());
+        Ok(Self {
+            file,
+            path,
+            started: Instant::now(),
+            ticks: 0,
+            written: HEADER.len() as u64,
+            limit: limit_mb as u64 * 1024 * 1024,
+            full: false,
+        })
+    }
+
+    /// Log one tick. `user_id` maps an SSRC to its speaker where known;
+    /// speakers `excluded` says aren't recorded are left out.
+    pub fn record(
+        &mut self,
+        tick: &VoiceTick,
+        user_id: impl Fn(u32) -> Option<u64>,
+        excluded: impl Fn(u32) -> bool,
+    ) {
+        let index = self.ticks;
+        self.ticks += 1;
+        if self.full {
+            return;
+        }
+        let elapsed_ms = self.started.elapsed().as_millis();
+        let mut ssrcs: Vec<u32> = tick
+            .speaking
+            .keys()
+            .copied()
+            .filter(|&ssrc| !excluded(ssrc))
+            .collect();
+        ssrcs.sort_unstable();
+
+        let mut rows = String::new();
+        for ssrc in ssrcs {
+            let data = &tick.speaking[&ssrc];
+            let user = user_id(ssrc).map(crate::redact::id).unwrap_or_default();
+            let (sequence, timestamp, payload_len) = match &data.packet {
+                Some(packet) => {
+                    let rtp = packet.rtp();
+                    (
+                        rtp.get_sequence().0 .0.to_string(),
+                        rtp.get_timestamp().0 .0.to_string(),
+                        packet
+                            .payload_end_pad
+                            .saturating_sub(packet.payload_offset)
+                            .to_string(),
+                    )
+                }
+                None => Default::default(),
+            };
+            let decoded = data
+                .decoded_voice
+                .as_ref()
+                .map(|voice| voice.len().to_string())
+                .unwrap_or_default();
+            rows.push_str(&format!(
+                "{},{},{},{},{},{},{},{}\n",
+                index, elapsed_ms, ssrc, user, sequence, timestamp, payload_len, decoded
+            ));
+        }
+        if rows.is_empty() {
+            return;
+        }
+
+        if self.written + rows.len() as u64 > self.limit {
+            self.full = true;
+            let _ = self.file.write_all(b"# size limit reached, dump stopped\n");
+            log::warn!("Tick dump reached its size limit: {}", self.path.display());
+            return;
+        }
+        if let Err(e) = self.file.write_all(rows.as_bytes()) {
+            log::warn!("Failed to write tick dump: {}", e);
+            self.full = true;
+            return;
+        }
+        self.written += rows.len() as u64;
+    }
+
+    pub fn finish(mut self) {
+        if let Err(e) = self.file.flush() {
+            log::warn!("Failed to flush tick dump: {}", e);
+        }
+        log::info!(
+            "Tick dump saved: {} ticks, {} KiB -> {}",
+            self.ticks,
+            self.written / 1024,
+            self.path.display()
+        );
+    }
+}
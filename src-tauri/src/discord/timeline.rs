@@ -0,0 +1,233 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Instant;
+
+/// Suffix of the speaker timeline saved next to a bot session's tracks.
+pub const TIMELINE_SUFFIX: &str = "-timeline.json";
+/// Length of one voice tick.
+pub const TICK_MS: u64 = 20;
+/// Pauses shorter than this don't end a span, so avatars don't flicker
+/// between words.
+pub const HANGOVER_MS: u64 = 200;
+pub const DEFAULT_FPS: u32 = 30;
+pub const MAX_FPS: u32 = 240;
+
+/// When each speaker was talking during a bot session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeakerTimeline {
+    pub started_at: String,
+    pub duration_ms: u64,
+    pub speakers: Vec<SpeakerSpans>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeakerSpans {
+    /// See [`speaker_id`].
+    pub id: String,
+    pub user_id: Option<String>,
+    /// Display name in the recorded guild, when it could be looked up.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// `[start_ms, end_ms)` ranges relative to the start of the session.
+    pub spans: Vec<[u64; 2]>,
+    /// Mute and deafen changes, so a stem going quiet can be explained.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub voice_states: Vec<VoiceStateChange>,
+}
+
+/// Whether someone can be heard, as set by themselves or a moderator.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct VoiceFlags {
+    pub self_mute: bool,
+    pub self_deaf: bool,
+    pub server_mute: bool,
+    pub server_deaf: bool,
+}
+
+/// A speaker's mute/deafen state from `at_ms` into the session on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoiceStateChange {
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub flags: VoiceFlags,
+}
+
+/// `user-<id>`, or `ssrc-<n>` while Discord hasn't named the speaker.
+pub fn speaker_id(ssrc: u32, user_id: Option<u64>) -> String {
+    match user_id {
+        Some(user) => format!("user-{}", user),
+        None => format!("ssrc-{}", ssrc),
+    }
+}
+
+/// Collects speaking spans from voice ticks as they arrive.
+pub struct TimelineBuilder {
+    started: Instant,
+    started_at: String,
+    spans: HashMap<u32, Vec<[u64; 2]>>,
+    voice_states: HashMap<u64, Vec<VoiceStateChange>>,
+}
+
+impl TimelineBuilder {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            started_at: chrono::Local::now().to_rfc3339(),
+            spans: HashMap::new(),
+            voice_states: HashMap::new(),
+        }
+    }
+
+    /// Note `user_id`'s mute/deafen state if it changed. Everyone starts out
+    /// unmuted, so only people muted at the start are noted then.
+    pub fn voice_state(&mut self, user_id: u64, flags: VoiceFlags) {
+        let changes = self.voice_states.entry(user_id).or_default();
+        if changes.last().map_or(VoiceFlags::default(), |c| c.flags) == flags {
+            return;
+        }
+        changes.push(VoiceStateChange {
+            at_ms: self.started.elapsed().as_millis() as u64,
+            flags,
+        });
+    }
+
+    /// Mark `ssrc` as audible during the current tick.
+    pub fn speaking(&mut self, ssrc: u32) {
+        let now = self.started.elapsed().as_millis() as u64;
+        let spans = self.spans.entry(ssrc).or_default();
+        match spans.last_mut() {
+            Some(last) if now <= last[1] + HANGOVER_MS => last[1] = now + TICK_MS,
+            _ => spans.push([now, now + TICK_MS]),
+        }
+    }
+
+    /// Close the timeline, naming speakers through the SSRC → user map and
+    /// their display `names`.
+    pub fn finish(
+        &self,
+        ssrc_map: &HashMap<u32, u64>,
+        names: &HashMap<u64, String>,
+    ) -> SpeakerTimeline {
+        let mut speakers: Vec<SpeakerSpans> = Vec::new();
+        for (ssrc, spans) in &self.spans {
+            let user_id = ssrc_map.get(ssrc).map(|id| id.to_string());
+            let id = speaker_id(*ssrc, ssrc_map.get(ssrc).copied());
+            // A user who reconnects gets a new SSRC; keep one row per person
+            match speakers.iter_mut().find(|s| s.id == id) {
+                Some(existing) => {
+                    existing.spans.extend_from_slice(spans);
+                    existing.spans.sort_by_key(|s| s[0]);
+                }
+                None => speakers.push(SpeakerSpans {
+                    id,
+                    user_id,
+                    name: ssrc_map.get(ssrc).and_then(|user| names.get(user)).cloned(),
+                    spans: spans.clone(),
+                    voice_states: Vec::new(),
+                }),
+            }
+        }
+        for (user, changes) in &self.voice_states {
+            let id = speaker_id(0, Some(*user));
+            match speakers.iter_mut().find(|s| s.id == id) {
+                Some(speaker) => speaker.voice_states = changes.clone(),
+                // Never heard, e.g. muted throughout; listed so the silence is explained
+                None => speakers.push(SpeakerSpans {
+                    id,
+                    user_id: Some(user.to_string()),
+                    name: names.get(user).cloned(),
+                    spans: Vec::new(),
+                    voice_states: changes.clone(),
+                }),
+            }
+        }
+        speakers.sort_by(|a, b| a.id.cmp(&b.id));
+
+        SpeakerTimeline {
+            started_at: self.started_at.clone(),
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            speakers,
+        }
+    }
+}
+
+impl SpeakerTimeline {
+    pub fn is_empty(&self) -> bool {
+        self.speakers.is_empty()
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read_to_string(path).context("Failed to read speaker timeline")?;
+        serde_json::from_str(&data).context("Invalid speaker timeline")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .context("Failed to write speaker timeline")
+    }
+
+    /// For every frame at `fps`, which speakers (by index) are talking.
+    fn frames(&self, fps: u32) -> Vec<Vec<usize>> {
+        let fps = fps as u64;
+        let count = (self.duration_ms * fps).div_ceil(1000) as usize;
+        let mut frames = vec![Vec::new(); count];
+        for (index, speaker) in self.speakers.iter().enumerate() {
+            for &[start, end] in &speaker.spans {
+                // Frame i covers [i / fps, (i + 1) / fps) seconds
+                let first = (start * fps / 1000) as usize;
+                let last = ((end * fps).div_ceil(1000) as usize).min(count);
+                for frame in frames.iter_mut().take(last).skip(first) {
+                    frame.push(index);
+                }
+            }
+        }
+        frames
+    }
+
+    /// One row per frame with a 0/1 column per speaker, for spreadsheet-style
+    /// import or expression-driven avatars.
+    pub fn frames_csv(&self, fps: u32) -> String {
+        let mut out = String::from("frame,time");
+        for speaker in &self.speakers {
+            out.push(',');
+            out.push_str(&speaker.id);
+        }
+        out.push('\n');
+        for (i, active) in self.frames(fps).iter().enumerate() {
+            out.push_str(&format!("{},{:.3}", i, i as f64 / fps as f64));
+            for index in 0..self.speakers.len() {
+                out.push_str(if active.contains(&index) { ",1" } else { ",0" });
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// `{ fps, frame_count, speakers, frames }` where each frame lists the ids
+    /// of everyone talking.
+    pub fn frames_json(&self, fps: u32) -> Result<String> {
+        let frames: Vec<Vec<&str>> = self
+            .frames(fps)
+            .iter()
+            .map(|active| {
+                active
+                    .iter()
+                    .map(|&i| self.speakers[i].id.as_str())
+                    .collect()
+            })
+            .collect();
+        let export = serde_json::json!({
+            "fps": fps,
+            "frame_count": frames.len(),
+            "speakers": self.speakers.iter().map(|s| serde_json::json!({
+                "id": s.id,
+                "user_id": s.user_id,
+                "name": s.name,
+            })).collect::<Vec<_>>(),
+            "frames": frames,
+        });
+        Ok(serde_json::to_string(&export)?)
+    }
+}
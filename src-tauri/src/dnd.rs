@@ -0,0 +1,168 @@
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+
+use crate::events::{Recorder, RecordingEvent, StatusHook};
+use crate::settings::SettingsState;
+
+/// Whether this system has a do-not-disturb mode DiscRec can switch: GNOME's
+/// notification banners on Linux and focus sessions on Windows 11. macOS has
+/// no public API for Focus.
+pub fn supported() -> bool {
+    platform::supported()
+}
+
+/// What to undo once the last recording stops.
+#[derive(Default)]
+struct Session {
+    recorders: Vec<Recorder>,
+    /// `None` when do not disturb was already on, or failed to turn on.
+    restore: Option<platform::Restore>,
+}
+
+/// Wrap `inner` so do not disturb is on while any recorder runs, when the
+/// setting asks for it, and back to how it was after the last one stops.
+pub fn status_hook(app: AppHandle, inner: StatusHook) -> StatusHook {
+    let session = Arc::new(Mutex::new(Session::default()));
+    Arc::new(move |source, event| {
+        match &event {
+            RecordingEvent::Started { .. } => {
+                let mut session = session.lock();
+                let first = session.recorders.is_empty();
+                if !session.recorders.contains(&source) {
+                    session.recorders.push(source);
+                }
+                let wanted = app.state::<SettingsState>().0.lock().dnd_while_recording;
+                if first && wanted {
+                    session.restore = enable().unwrap_or_else(|e| {
+                        log::warn!("Failed to turn on do not disturb: {}", e);
+                        None
+                    });
+                }
+            }
+            RecordingEvent::Stopped { .. } => {
+                let mut session = session.lock();
+                session.recorders.retain(|r| *r != source);
+                if session.recorders.is_empty() {
+                    if let Some(restore) = session.restore.take() {
+                        match platform::disable(restore) {
+                            Ok(()) => log::info!("Do not disturb restored"),
+                            Err(e) => log::warn!("Failed to turn off do not disturb: {}", e),
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        inner(source, event);
+    })
+}
+
+fn enable() -> Result<Option<platform::Restore>> {
+    let restore = platform::enable()?;
+    if restore.is_some() {
+        log::info!("Do not disturb on while recording");
+    }
+    Ok(restore)
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use anyhow::{Context, Result};
+    use std::process::Command;
+
+    const SCHEMA: &str = "org.gnome.desktop.notifications";
+    const KEY: &str = "show-banners";
+
+    /// GNOME had banners on before recording.
+    pub struct Restore;
+
+    pub fn supported() -> bool {
+        get().is_ok()
+    }
+
+    pub fn enable() -> Result<Option<Restore>> {
+        if get()? != "true" {
+            return Ok(None);
+        }
+        set("false")?;
+        Ok(Some(Restore))
+    }
+
+    pub fn disable(_: Restore) -> Result<()> {
+        set("true")
+    }
+
+    fn get() -> Result<String> {
+        let output = Command::new("gsettings")
+            .args(["get", SCHEMA, KEY])
+            .output()
+            .context("gsettings isn't available")?;
+        if !output.status.success() {
+            anyhow::bail!("Not a GNOME desktop");
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn set(value: &str) -> Result<()> {
+        let status = Command::new("gsettings")
+            .args(["set", SCHEMA, KEY, value])
+            .status()
+            .context("gsettings isn't available")?;
+        if !status.success() {
+            anyhow::bail!("gsettings exited with {}", status);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use anyhow::{Context, Result};
+    use windows::UI::Shell::{FocusSession, FocusSessionManager};
+
+    /// The focus session DiscRec started.
+    pub struct Restore(FocusSession);
+
+    pub fn supported() -> bool {
+        FocusSessionManager::IsSupported().unwrap_or(false)
+    }
+
+    pub fn enable() -> Result<Option<Restore>> {
+        if !supported() {
+            anyhow::bail!("Focus sessions need Windows 11");
+        }
+        let manager = FocusSessionManager::GetDefault().context("Focus sessions unavailable")?;
+        if manager.IsFocusActive()? {
+            return Ok(None);
+        }
+        let session = manager
+            .TryStartFocusSession()
+            .context("Failed to start a focus session")?;
+        Ok(Some(Restore(session)))
+    }
+
+    pub fn disable(restore: Restore) -> Result<()> {
+        restore.0.End().context("Failed to end the focus session")
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use anyhow::Result;
+
+    pub enum Restore {}
+
+    pub fn supported() -> bool {
+        false
+    }
+
+    pub fn enable() -> Result<Option<Restore>> {
+        anyhow::bail!("Not supported on this system")
+    }
+
+    pub fn disable(restore: Restore) -> Result<()> {
+        match restore {}
+    }
+}
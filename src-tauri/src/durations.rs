@@ -0,0 +1,82 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::library::{duration_secs, RecordingInfo};
+
+const CACHE_FILE: &str = "durations.json";
+
+/// Serializes reads and writes of the cache between overlapping listings.
+static CACHE_LOCK: Mutex<()> = Mutex::new(());
+
+/// A recording's length as of the size and modification time it had then.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    size: u64,
+    modified_ms: u64,
+    duration_secs: Option<f64>,
+}
+
+fn cache_path() -> PathBuf {
+    crate::data_dir::cache_dir().join(CACHE_FILE)
+}
+
+fn modified_ms(path: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    let since_epoch = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some(since_epoch.as_millis() as u64)
+}
+
+/// Fill in the length of each of `recordings`, reading headers only for
+/// files that are new or changed since the last listing. With `complete`,
+/// `recordings` is the whole library and entries for anything else are
+/// dropped, so the cache doesn't grow with deleted files.
+pub fn fill(recordings: &mut [RecordingInfo], complete: bool) {
+    let _guard = CACHE_LOCK.lock();
+    let path = cache_path();
+    let mut cache: HashMap<String, Entry> = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default();
+    let before = cache.len();
+    let mut changed = false;
+
+    for recording in recordings.iter_mut() {
+        let file = Path::new(&recording.path);
+        let Some(modified_ms) = modified_ms(file) else {
+            continue;
+        };
+        let fresh = cache
+            .get(&recording.path)
+            .filter(|e| e.size == recording.size && e.modified_ms == modified_ms);
+        recording.duration_secs = match fresh {
+            Some(entry) => entry.duration_secs,
+            None => {
+                let duration = duration_secs(file, &recording.format);
+                cache.insert(
+                    recording.path.clone(),
+                    Entry {
+                        size: recording.size,
+                        modified_ms,
+                        duration_secs: duration,
+                    },
+                );
+                changed = true;
+                duration
+            }
+        };
+    }
+    if complete {
+        let listed: HashSet<&str> = recordings.iter().map(|r| r.path.as_str()).collect();
+        cache.retain(|path, _| listed.contains(path.as_str()));
+    }
+
+    if changed || cache.len() != before {
+        let written = std::fs::create_dir_all(crate::data_dir::cache_dir())
+            .and_then(|_| std::fs::write(&path, serde_json::to_string(&cache).unwrap_or_default()));
+        if let Err(e) = written {
+            log::warn!("Failed to save recording durations: {}", e);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::archive::ArchiveState;
+use crate::library::{decode, recording_format, RecordingInfo};
+use crate::settings::SettingsState;
+
+/// Sent with the paths of empty recordings removed automatically.
+pub const EMPTY_REMOVED: &str = "library://empty-removed";
+
+/// Whether `path` is a recording without any audio, as failed sessions
+/// leave behind: an empty file, or a WAV or FLAC with a header but no
+/// frames. Files that can't be read are not counted, since they may still
+/// be recovered.
+pub fn is_empty(path: &Path) -> bool {
+    let Some(format) = recording_format(path) else {
+        return false;
+    };
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.len() == 0 => return true,
+        Ok(_) => {}
+        Err(_) => return false,
+    }
+    if format != "wav" && format != "flac" {
+        return false;
+    }
+    let mut has_audio = false;
+    decode(path, &format, |_, _, _| {
+        has_audio = true;
+        Ok(false)
+    })
+    .is_ok()
+        && !has_audio
+}
+
+/// Delete the recording at `path` along with its import sidecar.
+fn remove(path: &Path) -> Result<()> {
+    std::fs::remove_file(path).with_context(|| format!("Failed to delete {}", path.display()))?;
+    let _ = std::fs::remove_file(crate::import::sidecar_path(path));
+    Ok(())
+}
+
+/// Delete the empty recordings among `paths`, telling the UI which went,
+/// and return the others.
+pub fn remove_from(app: &AppHandle, paths: &[String]) -> Vec<String> {
+    let (empty, kept): (Vec<String>, Vec<String>) = paths
+        .iter()
+        .cloned()
+        .partition(|path| is_empty(Path::new(path)));
+    let removed: Vec<String> = empty
+        .into_iter()
+        .filter(|path| match remove(Path::new(path)) {
+            Ok(()) => {
+                log::info!("Deleted empty recording {}", path);
+                true
+            }
+            Err(e) => {
+                log::warn!("{:#}", e);
+                false
+            }
+        })
+        .collect();
+    if !removed.is_empty() {
+        let _ = app.emit(EMPTY_REMOVED, removed);
+    }
+    kept
+}
+
+/// Empty recordings in `dir` and its non-hidden subfolders, deleted unless
+/// `dry_run`. Recordings in read-only archives are left alone.
+pub fn sweep(dir: &Path, archives: &ArchiveState, dry_run: bool) -> Result<Vec<RecordingInfo>> {
+    let mut removed = Vec::new();
+    for info in crate::library::scan(dir, true).context("Failed to read recordings folder")? {
+        let path = Path::new(&info.path);
+        if archives.ensure_writable(path).is_err() || !is_empty(path) {
+            continue;
+        }
+        if !dry_run {
+            if let Err(e) = remove(path) {
+                log::warn!("{:#}", e);
+                continue;
+            }
+        }
+        removed.push(info);
+    }
+    if !dry_run && !removed.is_empty() {
+        log::info!("Deleted {} empty recording(s)", removed.len());
+    }
+    Ok(removed)
+}
+
+/// Delete empty recordings left in the recordings folder by earlier runs,
+/// telling the UI which went.
+pub fn sweep_at_startup(app: &AppHandle) {
+    let dir = crate::settings::recordings_dir(&app.state::<SettingsState>());
+    match sweep(&dir, &app.state::<ArchiveState>(), false) {
+        Ok(removed) if !removed.is_empty() => {
+            let paths: Vec<String> = removed.into_iter().map(|info| info.path).collect();
+            let _ = app.emit(EMPTY_REMOVED, paths);
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to look for empty recordings: {:#}", e),
+    }
+}
@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Runtime};
+
+use crate::audio::devices::DeviceChoice;
+
+pub const LEVEL: &str = "recording://level";
+pub const STARTED: &str = "recording://started";
+pub const STOPPED: &str = "recording://stopped";
+pub const ERROR: &str = "recording://error";
+pub const INTERRUPTED: &str = "recording://interrupted";
+pub const RESUMED: &str = "recording://resumed";
+
+/// Minimum time between level events.
+pub const LEVEL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Which recorder an event comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Recorder {
+    Local,
+    Bot,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    /// Stopped from the UI, tray or a shortcut.
+    Requested,
+    /// Hit the configured maximum duration; the files still need saving.
+    MaxDuration,
+    /// Aborted as a false start; nothing was saved.
+    Aborted,
+}
+
+/// A change in a recorder's state, pushed to the frontend instead of polled.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum RecordingEvent {
+    Level {
+        peak: f32,
+    },
+    Started {
+        path: Option<String>,
+        /// The system capture device used, when one was picked in settings.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        device: Option<DeviceChoice>,
+    },
+    Stopped {
+        reason: StopReason,
+        paths: Vec<String>,
+    },
+    Error {
+        message: String,
+    },
+    /// The bot lost its voice connection and is trying to rejoin.
+    Interrupted {
+        message: String,
+    },
+    /// The bot rejoined after an interruption and recording continues.
+    Resumed {
+        attempts: u32,
+    },
+}
+
+impl RecordingEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Level { .. } => LEVEL,
+            Self::Started { .. } => STARTED,
+            Self::Stopped { .. } => STOPPED,
+            Self::Error { .. } => ERROR,
+            Self::Interrupted { .. } => INTERRUPTED,
+            Self::Resumed { .. } => RESUMED,
+        }
+    }
+}
+
+/// Receives recorder events; lets the capture code report without knowing
+/// about Tauri.
+pub type StatusHook = Arc<dyn Fn(Recorder, RecordingEvent) + Send + Sync>;
+
+#[derive(Serialize, Clone)]
+struct Payload {
+    source: Recorder,
+    #[serde(flatten)]
+    event: RecordingEvent,
+}
+
+/// A hook that emits every event to all windows.
+pub fn emitter<R: Runtime>(app: AppHandle<R>) -> StatusHook {
+    Arc::new(move |source, event| {
+        let name = event.name();
+        if let Err(e) = app.emit(name, Payload { source, event }) {
+            log::warn!("Failed to emit {}: {}", name, e);
+        }
+    })
+}
@@ -0,0 +1,106 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Open a folder in the platform file manager.
+pub fn open_folder(folder: &Path) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    if crate::sandbox::is_sandboxed() {
+        return crate::sandbox::portal::open_folder(folder);
+    }
+
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(target_os = "linux")]
+    let program = "xdg-open";
+
+    Command::new(program)
+        .arg(display_path(folder))
+        .spawn()
+        .context("Failed to open folder")?;
+    Ok(())
+}
+
+/// Show a file in the platform file manager with the file selected.
+///
+/// `file` must be an absolute, canonical path to an existing file.
+pub fn reveal(file: &Path) -> Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+
+        // explorer only understands `/select,"<path>"` unsplit; Windows file
+        // names cannot contain quotes, so the raw argument stays intact
+        Command::new("explorer")
+            .raw_arg(format!("/select,\"{}\"", display_path(file).display()))
+            .spawn()
+            .context("Failed to open Explorer")?;
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        Command::new("open")
+            .arg("-R")
+            .arg(file)
+            .spawn()
+            .context("Failed to open Finder")?;
+    }
+
+    #[cfg(target_os = "linux")]
+    if crate::sandbox::is_sandboxed() {
+        crate::sandbox::portal::reveal(file)?;
+    } else {
+        // org.freedesktop.FileManager1 is implemented by Nautilus, Dolphin,
+        // Nemo, Thunar and others; fall back to opening the folder
+        let shown = Command::new("dbus-send")
+            .args([
+                "--session",
+                "--print-reply",
+                "--dest=org.freedesktop.FileManager1",
+                "--type=method_call",
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1.ShowItems",
+            ])
+            .arg(format!("array:string:{}", file_uri(file)))
+            .arg("string:")
+            .output()
+            .map(|out| out.status.success())
+            .unwrap_or(false);
+        if !shown {
+            log::info!("FileManager1 unavailable, opening the folder instead");
+            open_folder(file.parent().unwrap_or(file))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Strip the `\\?\` prefix `canonicalize` adds on Windows, which Explorer rejects.
+fn display_path(path: &Path) -> PathBuf {
+    let raw = path.to_string_lossy();
+    if let Some(unc) = raw.strip_prefix(r"\\?\UNC\") {
+        PathBuf::from(format!(r"\\{}", unc))
+    } else if let Some(local) = raw.strip_prefix(r"\\?\") {
+        PathBuf::from(local)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// `file://` URI with everything but unreserved characters percent-encoded.
+#[cfg(target_os = "linux")]
+fn file_uri(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut uri = String::from("file://");
+    for &byte in path.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
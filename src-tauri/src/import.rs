@@ -0,0 +1,221 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::audio::encoder::{create_encoder, remove_partial, AudioEncoder, AudioFormat};
+use crate::audio::mixer::{Mixer, MIX_CHANNELS, MIX_SAMPLE_RATE};
+use crate::library::{decode, frame_count, probe, recording_format, AudioProperties};
+
+/// Bytes copied between cancellation checks.
+const COPY_CHUNK: usize = 1 << 20;
+/// Appended to an imported recording's file name for its metadata sidecar.
+pub const SIDECAR_SUFFIX: &str = ".import.json";
+
+/// How files that aren't converted get into the library.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    #[default]
+    Copy,
+    /// Hard-link instead of copying, so large files take no extra space.
+    /// Changes made in place, e.g. normalizing, then affect the original
+    /// too. Falls back to copying when the library is on another drive.
+    Link,
+}
+
+/// How a recording ended up in the library.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMethod {
+    Copied,
+    Linked,
+    /// Converted to 48 kHz stereo.
+    Converted,
+}
+
+/// Where an imported recording came from and what it was, saved next to it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportSidecar {
+    pub source: String,
+    pub imported_at: String,
+    pub method: ImportMethod,
+    /// The original file's format and layout, before any conversion.
+    pub format: String,
+    pub audio: Option<AudioProperties>,
+    /// For formats DiscRec can read the header of.
+    pub duration_secs: Option<f64>,
+}
+
+impl ImportSidecar {
+    /// Save next to the imported recording at `recording`.
+    pub fn save(&self, recording: &Path) -> Result<()> {
+        std::fs::write(sidecar_path(recording), serde_json::to_string_pretty(self)?)
+            .context("Failed to write import sidecar")
+    }
+}
+
+/// Where the sidecar of the recording at `path` goes.
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(SIDECAR_SUFFIX);
+    path.with_file_name(name)
+}
+
+/// Copy or link the recording at `source` into `library_dir` and return its
+/// new path, with an [`ImportSidecar`] describing the original next to it.
+///
+/// With `normalize`, WAV and FLAC files that aren't in DiscRec's own layout
+/// are converted to 48 kHz stereo in the same format on the way in, so mixing
+/// and merging never meet arbitrary rates. Other files are copied or linked
+/// unchanged. Existing files are never overwritten.
+///
+/// A copy or conversion stops with a "Cancelled" error as soon as
+/// `cancelled` returns true, leaving nothing behind in the library.
+pub fn import(
+    source: &Path,
+    library_dir: &Path,
+    normalize: bool,
+    mode: ImportMode,
+    cancelled: impl Fn() -> bool,
+) -> Result<PathBuf> {
+    let format = recording_format(source).context("Not a supported recording format")?;
+    let file_name = source.file_name().context("Invalid file name")?;
+    let target = unique_path(&library_dir.join(file_name));
+    let audio = probe(source, &format);
+    let duration_secs = audio.and_then(|audio| {
+        let frames = frame_count(source, &format)?;
+        Some(frames as f64 / audio.sample_rate.max(1) as f64)
+    });
+
+    let needs_conversion = normalize && audio.is_some_and(|audio| !audio.is_standard());
+    let method = if needs_conversion {
+        let output_format = if format == "flac" {
+            AudioFormat::Flac
+        } else {
+            AudioFormat::Wav
+        };
+        let target_str = target.to_string_lossy().to_string();
+        let result = convert(source, &format, &target_str, output_format, &cancelled);
+        if result.is_err() {
+            remove_partial(&target_str);
+        }
+        result?;
+        log::info!("Imported {} as 48 kHz stereo", source.display());
+        ImportMethod::Converted
+    } else {
+        std::fs::create_dir_all(library_dir).context("Failed to create recordings directory")?;
+        let linked = mode == ImportMode::Link && link(source, &target);
+        if linked {
+            ImportMethod::Linked
+        } else {
+            let copied = copy(source, &target, &cancelled);
+            if copied.is_err() {
+                let _ = std::fs::remove_file(&target);
+            }
+            copied?;
+            ImportMethod::Copied
+        }
+    };
+
+    let sidecar = ImportSidecar {
+        source: source.to_string_lossy().to_string(),
+        imported_at: chrono::Local::now().to_rfc3339(),
+        method,
+        format,
+        audio,
+        duration_secs,
+    };
+    if let Err(e) = sidecar.save(&target) {
+        log::warn!(
+            "Failed to save import details for {}: {:#}",
+            target.display(),
+            e
+        );
+    }
+    Ok(target)
+}
+
+/// Hard-link `source` to `target`, returning whether that worked.
+fn link(source: &Path, target: &Path) -> bool {
+    match std::fs::hard_link(source, target) {
+        Ok(()) => true,
+        Err(e) => {
+            log::info!("Copying {} instead of linking it: {}", source.display(), e);
+            false
+        }
+    }
+}
+
+/// Decode `source` and feed it through a single-input mix, which downmixes
+/// to stereo and resamples to 48 kHz.
+fn convert(
+    source: &Path,
+    format: &str,
+    target: &str,
+    output: AudioFormat,
+    cancelled: &impl Fn() -> bool,
+) -> Result<()> {
+    let encoder = create_encoder(target, MIX_CHANNELS, MIX_SAMPLE_RATE, output, false)?;
+    let mixer = Mixer::new(encoder, 1);
+
+    let mut input = None;
+    decode(source, format, |channels, sample_rate, block| {
+        if cancelled() {
+            anyhow::bail!("Cancelled");
+        }
+        input
+            .get_or_insert_with(|| Box::new(mixer.input(0, channels, sample_rate)))
+            .write_samples(block)?;
+        Ok(true)
+    })?;
+    if let Some(input) = input {
+        input.finalize()?;
+    }
+    mixer.finalize()?;
+    Ok(())
+}
+
+/// Copy `source` to `target` in chunks, so a long copy can be cancelled.
+fn copy(source: &Path, target: &Path, cancelled: &impl Fn() -> bool) -> Result<()> {
+    use std::io::{Read, Write};
+
+    let mut reader = std::fs::File::open(source).context("Failed to open recording")?;
+    let mut writer =
+        std::io::BufWriter::new(std::fs::File::create(target).context("Failed to create copy")?);
+    let mut chunk = vec![0u8; COPY_CHUNK];
+    loop {
+        if cancelled() {
+            anyhow::bail!("Cancelled");
+        }
+        let read = reader
+            .read(&mut chunk)
+            .context("Failed to read recording")?;
+        if read == 0 {
+            break;
+        }
+        writer
+            .write_all(&chunk[..read])
+            .context("Failed to copy recording")?;
+    }
+    writer.flush().context("Failed to copy recording")?;
+    Ok(())
+}
+
+/// `path`, or `name (2).ext`, `name (3).ext`, … if it is taken.
+pub fn unique_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (2..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, ext)))
+        .find(|candidate| !candidate.exists())
+        .expect("some numbered name is free")
+}
@@ -0,0 +1,356 @@
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures_util::{SinkExt, StreamExt};
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::events::{RecordingEvent, StatusHook};
+use crate::settings::{ObsOutput, ObsSettings};
+
+/// Sent when the followed OBS output starts or stops and DiscRec should too.
+pub const OUTPUT: &str = "obs://output";
+/// Sent when the connection to OBS comes up, drops or fails.
+pub const STATUS: &str = "obs://status";
+
+/// obs-websocket 5 protocol version.
+const RPC_VERSION: u32 = 1;
+/// Event subscription bit for output (record/stream) state changes.
+const OUTPUT_EVENTS: u32 = 1 << 6;
+/// Wait between connection attempts while OBS isn't reachable.
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+const OUTPUT_STARTED: &str = "OBS_WEBSOCKET_OUTPUT_STARTED";
+const OUTPUT_STOPPED: &str = "OBS_WEBSOCKET_OUTPUT_STOPPED";
+
+// Password via OS keyring, next to the bot token
+pub const KEYRING_ACCOUNT: &str = "obs_websocket_password";
+
+mod op {
+    pub const HELLO: u64 = 0;
+    pub const IDENTIFY: u64 = 1;
+    pub const IDENTIFIED: u64 = 2;
+    pub const EVENT: u64 = 5;
+    pub const REQUEST: u64 = 6;
+    pub const REQUEST_RESPONSE: u64 = 7;
+}
+
+/// Connection state shown in settings.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ObsStatus {
+    pub connected: bool,
+    pub recording: bool,
+    pub streaming: bool,
+    /// Why the last connection attempt failed.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct OutputPayload {
+    active: bool,
+}
+
+#[derive(Default)]
+struct Link {
+    /// Requests for OBS; `None` while disconnected.
+    requests: Option<mpsc::UnboundedSender<Message>>,
+    status: ObsStatus,
+}
+
+impl Link {
+    /// Whether the output DiscRec follows is running.
+    fn followed_active(&self, output: ObsOutput) -> bool {
+        match output {
+            ObsOutput::Record => self.status.recording,
+            ObsOutput::Stream => self.status.streaming,
+            ObsOutput::Either => self.status.recording || self.status.streaming,
+        }
+    }
+
+    fn send(&self, request_type: &str) {
+        let Some(requests) = &self.requests else {
+            return;
+        };
+        let request = json!({
+            "op": op::REQUEST,
+            "d": { "requestType": request_type, "requestId": request_type },
+        });
+        log::info!("Asking OBS to {}", request_type);
+        let _ = requests.send(Message::Text(request.to_string()));
+    }
+}
+
+/// Keeps DiscRec in step with OBS over obs-websocket.
+///
+/// With `follow_obs`, OBS starting or stopping the chosen output is sent to
+/// the UI as [`OUTPUT`], which starts or stops recording the same way the
+/// record button does. With `drive_obs`, DiscRec starting or stopping sends
+/// the matching request to OBS.
+pub struct ObsState {
+    config: Mutex<ObsSettings>,
+    link: Arc<Mutex<Link>>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl ObsState {
+    pub fn new() -> Self {
+        Self {
+            config: Mutex::new(ObsSettings::default()),
+            link: Arc::new(Mutex::new(Link::default())),
+            task: Mutex::new(None),
+        }
+    }
+
+    pub fn status(&self) -> ObsStatus {
+        self.link.lock().status.clone()
+    }
+
+    /// Apply new settings: drop any connection and, when enabled, keep
+    /// connecting to OBS in the background until it is reachable.
+    pub fn configure(&self, app: &AppHandle, config: ObsSettings, password: Option<String>) {
+        if let Some(task) = self.task.lock().take() {
+            task.abort();
+        }
+        *self.link.lock() = Link::default();
+        *self.config.lock() = config.clone();
+        emit_status(app, ObsStatus::default());
+        if !config.enabled {
+            return;
+        }
+
+        let app = app.clone();
+        let link = Arc::clone(&self.link);
+        let task = tauri::async_runtime::spawn(async move {
+            loop {
+                let outcome = connect(&app, &config, password.as_deref(), &link).await;
+                let status = {
+                    let mut link = link.lock();
+                    link.requests = None;
+                    link.status = ObsStatus {
+                        error: outcome.err().map(|e| format!("{:#}", e)),
+                        ..ObsStatus::default()
+                    };
+                    link.status.clone()
+                };
+                if let Some(error) = &status.error {
+                    log::warn!("OBS connection failed: {}", error);
+                } else {
+                    log::info!("OBS closed the connection");
+                }
+                emit_status(&app, status);
+                tokio::time::sleep(RETRY_INTERVAL).await;
+            }
+        });
+        *self.task.lock() = Some(task);
+    }
+
+    /// Start or stop OBS along with a DiscRec recording, when asked to.
+    fn recording_changed(&self, recording: bool) {
+        let config = self.config.lock().clone();
+        if !config.enabled || !config.drive_obs {
+            return;
+        }
+        let link = self.link.lock();
+        let status = &link.status;
+        match (config.output, recording) {
+            (ObsOutput::Stream, true) if !status.streaming => link.send("StartStream"),
+            (ObsOutput::Stream, false) if status.streaming => link.send("StopStream"),
+            // A running stream is enough when following either output
+            (ObsOutput::Either, true) if status.streaming => {}
+            (ObsOutput::Record | ObsOutput::Either, true) if !status.recording => {
+                link.send("StartRecord")
+            }
+            (ObsOutput::Record | ObsOutput::Either, false) if status.recording => {
+                link.send("StopRecord")
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Wrap `inner` so recorders starting and stopping also reach OBS.
+pub fn status_hook(app: AppHandle, inner: StatusHook) -> StatusHook {
+    Arc::new(move |source, event| {
+        match &event {
+            RecordingEvent::Started { .. } => app.state::<ObsState>().recording_changed(true),
+            RecordingEvent::Stopped { .. } => app.state::<ObsState>().recording_changed(false),
+            _ => {}
+        }
+        inner(source, event);
+    })
+}
+
+fn emit_status(app: &AppHandle, status: ObsStatus) {
+    if let Err(e) = app.emit(STATUS, status) {
+        log::warn!("Failed to emit {}: {}", STATUS, e);
+    }
+}
+
+/// Connect, identify and relay events until OBS goes away.
+async fn connect(
+    app: &AppHandle,
+    config: &ObsSettings,
+    password: Option<&str>,
+    link: &Mutex<Link>,
+) -> Result<()> {
+    let url = format!("ws://{}:{}", config.host, config.port);
+    let (socket, _) = tokio_tungstenite::connect_async(&url)
+        .await
+        .with_context(|| format!("OBS isn't reachable at {}", url))?;
+    let (mut sink, mut stream) = socket.split();
+
+    let hello = next_message(&mut stream, op::HELLO).await?;
+    let mut identify = json!({
+        "rpcVersion": RPC_VERSION,
+        "eventSubscriptions": OUTPUT_EVENTS,
+    });
+    if let Some(auth) = hello.get("authentication") {
+        let password = password.context("OBS asks for a password")?;
+        let salt = auth["salt"].as_str().unwrap_or_default();
+        let challenge = auth["challenge"].as_str().unwrap_or_default();
+        identify["authentication"] = auth_response(password, salt, challenge).into();
+    }
+    sink.send(Message::Text(
+        json!({ "op": op::IDENTIFY, "d": identify }).to_string(),
+    ))
+    .await?;
+    next_message(&mut stream, op::IDENTIFIED).await?;
+    log::info!("Connected to OBS at {}", url);
+
+    let (requests, mut outgoing) = mpsc::unbounded_channel();
+    {
+        let mut link = link.lock();
+        link.requests = Some(requests);
+        link.status.connected = true;
+        // Where OBS is at right now; only later changes are followed
+        link.send("GetRecordStatus");
+        link.send("GetStreamStatus");
+        emit_status(app, link.status.clone());
+    }
+
+    loop {
+        tokio::select! {
+            message = stream.next() => {
+                let message = match message {
+                    Some(message) => message?,
+                    None => return Ok(()),
+                };
+                match message {
+                    Message::Text(text) => {
+                        if let Ok(message) = serde_json::from_str::<Value>(&text) {
+                            handle_message(app, config, link, &message);
+                        }
+                    }
+                    Message::Close(_) => return Ok(()),
+                    _ => {}
+                }
+            }
+            Some(request) = outgoing.recv() => sink.send(request).await?,
+        }
+    }
+}
+
+/// Wait for the next message with opcode `expected` and return its data.
+/// OBS closes the socket with a reason when identifying fails.
+async fn next_message<S>(stream: &mut S, expected: u64) -> Result<Value>
+where
+    S: StreamExt<Item = tokio_tungstenite::tungstenite::Result<Message>> + Unpin,
+{
+    while let Some(message) = stream.next().await {
+        match message? {
+            Message::Text(text) => {
+                let message: Value = serde_json::from_str(&text)?;
+                if message["op"].as_u64() == Some(expected) {
+                    return Ok(message["d"].clone());
+                }
+            }
+            Message::Close(Some(frame)) if !frame.reason.is_empty() => {
+                anyhow::bail!("OBS refused the connection: {}", frame.reason)
+            }
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+    anyhow::bail!("OBS closed the connection")
+}
+
+fn handle_message(app: &AppHandle, config: &ObsSettings, link: &Mutex<Link>, message: &Value) {
+    let data = &message["d"];
+    let mut link = link.lock();
+    let was_active = link.followed_active(config.output);
+
+    match message["op"].as_u64() {
+        Some(op::EVENT) => {
+            let active = match data["eventData"]["outputState"].as_str() {
+                Some(OUTPUT_STARTED) => true,
+                Some(OUTPUT_STOPPED) => false,
+                _ => return,
+            };
+            match data["eventType"].as_str() {
+                Some("RecordStateChanged") => link.status.recording = active,
+                Some("StreamStateChanged") => link.status.streaming = active,
+                _ => return,
+            }
+        }
+        Some(op::REQUEST_RESPONSE) => {
+            if data["requestStatus"]["result"].as_bool() != Some(true) {
+                log::warn!(
+                    "OBS couldn't {}: {}",
+                    data["requestType"].as_str().unwrap_or("?"),
+                    data["requestStatus"]["comment"]
+                        .as_str()
+                        .unwrap_or("no reason given")
+                );
+                return;
+            }
+            let active = data["responseData"]["outputActive"].as_bool();
+            match (data["requestType"].as_str(), active) {
+                (Some("GetRecordStatus"), Some(active)) => link.status.recording = active,
+                (Some("GetStreamStatus"), Some(active)) => link.status.streaming = active,
+                _ => return,
+            }
+            emit_status(app, link.status.clone());
+            return;
+        }
+        _ => return,
+    }
+
+    emit_status(app, link.status.clone());
+    let active = link.followed_active(config.output);
+    if config.follow_obs && active != was_active {
+        log::info!(
+            "OBS {}; {} recording",
+            if active { "started" } else { "stopped" },
+            if active { "starting" } else { "stopping" }
+        );
+        if let Err(e) = app.emit(OUTPUT, OutputPayload { active }) {
+            log::warn!("Failed to emit {}: {}", OUTPUT, e);
+        }
+    }
+}
+
+/// obs-websocket's challenge response:
+/// `base64(sha256(base64(sha256(password + salt)) + challenge))`.
+fn auth_response(password: &str, salt: &str, challenge: &str) -> String {
+    let secret = STANDARD.encode(Sha256::digest(format!("{}{}", password, salt)));
+    STANDARD.encode(Sha256::digest(format!("{}{}", secret, challenge)))
+}
+
+pub fn save_password(password: &str) -> Result<()> {
+    crate::secrets::set(KEYRING_ACCOUNT, password).context("Failed to save OBS password to keyring")
+}
+
+pub fn load_password() -> Result<Option<String>> {
+    crate::secrets::get(KEYRING_ACCOUNT).context("Failed to load OBS password")
+}
+
+pub fn delete_password() -> Result<()> {
+    crate::secrets::delete(KEYRING_ACCOUNT).context("Failed to delete OBS password")
+}
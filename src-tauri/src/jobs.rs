@@ -0,0 +1,205 @@
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Runtime};
+
+pub const PROGRESS: &str = "job://progress";
+pub const FINISHED: &str = "job://finished";
+
+/// Minimum time between progress events of one job.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+/// Finished jobs kept around for `get_job_status`.
+const KEEP_FINISHED: usize = 50;
+
+pub type JobId = u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// Where a long-running operation is at, as sent with every job event.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub id: JobId,
+    /// What the job does, e.g. `import`.
+    pub kind: String,
+    pub state: JobState,
+    pub done: u64,
+    /// Units of work in total, when known up front.
+    pub total: Option<u64>,
+    /// What is being worked on right now, e.g. a file name.
+    pub current: String,
+    pub error: Option<String>,
+    /// The operation's return value once it completed.
+    pub result: Option<serde_json::Value>,
+}
+
+type Emit = Arc<dyn Fn(&'static str, &JobStatus) + Send + Sync>;
+
+/// Handle a running operation reports progress through and checks for
+/// cancellation with.
+pub struct Job {
+    status: Mutex<JobStatus>,
+    cancelled: AtomicBool,
+    last_progress: Mutex<Option<Instant>>,
+    emit: Emit,
+}
+
+impl Job {
+    /// Report `done` of `total` units, working on `current`. Events are
+    /// throttled, except for the last unit.
+    pub fn progress(&self, done: u64, total: Option<u64>, current: impl Into<String>) {
+        let snapshot = {
+            let mut status = self.status.lock();
+            status.done = done;
+            status.total = total;
+            status.current = current.into();
+            status.clone()
+        };
+        let mut last = self.last_progress.lock();
+        let finishing = total.is_some_and(|t| done >= t);
+        if !finishing && last.is_some_and(|at| at.elapsed() < PROGRESS_INTERVAL) {
+            return;
+        }
+        *last = Some(Instant::now());
+        (self.emit)(PROGRESS, &snapshot);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Fail with "Cancelled" once the job was cancelled; call between units of work.
+    pub fn check_cancelled(&self) -> Result<()> {
+        if self.is_cancelled() {
+            anyhow::bail!("Cancelled");
+        }
+        Ok(())
+    }
+
+    pub fn status(&self) -> JobStatus {
+        self.status.lock().clone()
+    }
+
+    fn finish(&self, outcome: Result<serde_json::Value>) {
+        let snapshot = {
+            let mut status = self.status.lock();
+            match outcome {
+                Ok(result) => {
+                    status.state = JobState::Completed;
+                    status.result = Some(result);
+                }
+                Err(_) if self.is_cancelled() => status.state = JobState::Cancelled,
+                Err(e) => {
+                    status.state = JobState::Failed;
+                    status.error = Some(format!("{:#}", e));
+                }
+            }
+            status.current.clear();
+            status.clone()
+        };
+        (self.emit)(FINISHED, &snapshot);
+    }
+}
+
+/// Long-running operations started from the UI, e.g. imports. Each returns
+/// a job ID straight away and reports through `job://` events; recently
+/// finished jobs stay queryable.
+pub struct JobsState {
+    next_id: AtomicU64,
+    jobs: Mutex<BTreeMap<JobId, Arc<Job>>>,
+}
+
+impl JobsState {
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            jobs: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Run `work` on a blocking thread as a job of `kind` and return its ID.
+    pub fn spawn<R, T, F>(&self, app: &AppHandle<R>, kind: &str, work: F) -> JobId
+    where
+        R: Runtime,
+        T: Serialize,
+        F: FnOnce(&Job) -> Result<T> + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let app = app.clone();
+        let job = Arc::new(Job {
+            status: Mutex::new(JobStatus {
+                id,
+                kind: kind.to_string(),
+                state: JobState::Running,
+                done: 0,
+                total: None,
+                current: String::new(),
+                error: None,
+                result: None,
+            }),
+            cancelled: AtomicBool::new(false),
+            last_progress: Mutex::new(None),
+            emit: Arc::new(move |name, status| {
+                if let Err(e) = app.emit(name, status) {
+                    log::warn!("Failed to emit {}: {}", name, e);
+                }
+            }),
+        });
+        self.insert(Arc::clone(&job));
+
+        tauri::async_runtime::spawn_blocking(move || {
+            let outcome = work(&job).and_then(|value| Ok(serde_json::to_value(value)?));
+            if let Err(e) = &outcome {
+                log::warn!("Job {} failed: {:#}", id, e);
+            }
+            job.finish(outcome);
+        });
+        id
+    }
+
+    fn insert(&self, job: Arc<Job>) {
+        let mut jobs = self.jobs.lock();
+        let id = job.status().id;
+        jobs.insert(id, job);
+        // Forget the oldest finished jobs
+        let finished: Vec<JobId> = jobs
+            .iter()
+            .filter(|(_, job)| job.status().state != JobState::Running)
+            .map(|(&id, _)| id)
+            .collect();
+        for id in finished
+            .iter()
+            .take(finished.len().saturating_sub(KEEP_FINISHED))
+        {
+            jobs.remove(id);
+        }
+    }
+
+    pub fn status(&self, id: JobId) -> Option<JobStatus> {
+        self.jobs.lock().get(&id).map(|job| job.status())
+    }
+
+    pub fn list(&self) -> Vec<JobStatus> {
+        self.jobs.lock().values().map(|job| job.status()).collect()
+    }
+
+    /// Ask a running job to stop. It stops at its next cancellation check.
+    pub fn cancel(&self, id: JobId) -> bool {
+        let jobs = self.jobs.lock();
+        let Some(job) = jobs.get(&id) else {
+            return false;
+        };
+        job.cancelled.store(true, Ordering::Relaxed);
+        true
+    }
+}
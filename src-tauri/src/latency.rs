@@ -0,0 +1,195 @@
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use crate::discord::timeline::TICK_MS;
+
+/// Delays are binned per millisecond up to this; longer ones share the last bin.
+const MAX_BIN_MS: usize = 2000;
+/// A voice tick this far behind schedule counts as late.
+const LATE_TICK_MS: f64 = TICK_MS as f64;
+
+static CAPTURE_TO_DISK: Mutex<Option<DelayStats>> = Mutex::new(None);
+static DISCORD_RECEIVE: Mutex<Option<TickJitter>> = Mutex::new(None);
+
+/// Delays measured since the last session started, in milliseconds.
+#[derive(Debug, Clone, Serialize)]
+pub struct DelaySummary {
+    pub count: u64,
+    pub mean_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+/// How evenly the bot's 20 ms voice ticks arrived.
+#[derive(Debug, Clone, Serialize)]
+pub struct JitterSummary {
+    pub ticks: u64,
+    /// Smoothed deviation from the 20 ms interval, as RTP receivers
+    /// estimate interarrival jitter (RFC 3550).
+    pub jitter_ms: f64,
+    pub max_deviation_ms: f64,
+    /// Ticks that came a whole tick or more behind schedule.
+    pub late_ticks: u64,
+}
+
+/// Latency figures for comparing against other recorders and spotting
+/// regressions.
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyReport {
+    /// From audio reaching its encoder to being written to the session file.
+    pub capture_to_disk: Option<DelaySummary>,
+    pub discord_receive: Option<JitterSummary>,
+}
+
+struct DelayStats {
+    bins: Vec<u64>,
+    count: u64,
+    sum_ms: f64,
+    max_ms: f64,
+}
+
+impl DelayStats {
+    fn new() -> Self {
+        Self {
+            bins: vec![0; MAX_BIN_MS + 1],
+            count: 0,
+            sum_ms: 0.0,
+            max_ms: 0.0,
+        }
+    }
+
+    fn add(&mut self, delay: Duration) {
+        let ms = delay.as_secs_f64() * 1000.0;
+        self.bins[(ms as usize).min(MAX_BIN_MS)] += 1;
+        self.count += 1;
+        self.sum_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+    }
+
+    fn summary(&self) -> Option<DelaySummary> {
+        if self.count == 0 {
+            return None;
+        }
+        let wanted = (self.count * 95).div_ceil(100);
+        let mut seen = 0;
+        let p95_bin = self
+            .bins
+            .iter()
+            .position(|&n| {
+                seen += n;
+                seen >= wanted
+            })
+            .unwrap_or(MAX_BIN_MS);
+        Some(DelaySummary {
+            count: self.count,
+            mean_ms: self.sum_ms / self.count as f64,
+            // Upper edge of the bin, never past the slowest write seen
+            p95_ms: ((p95_bin + 1) as f64).min(self.max_ms),
+            max_ms: self.max_ms,
+        })
+    }
+}
+
+struct TickJitter {
+    last: Option<Instant>,
+    ticks: u64,
+    jitter_ms: f64,
+    max_deviation_ms: f64,
+    late_ticks: u64,
+}
+
+impl TickJitter {
+    fn new() -> Self {
+        Self {
+            last: None,
+            ticks: 0,
+            jitter_ms: 0.0,
+            max_deviation_ms: 0.0,
+            late_ticks: 0,
+        }
+    }
+
+    fn tick(&mut self, now: Instant) {
+        self.ticks += 1;
+        let Some(last) = self.last.replace(now) else {
+            return;
+        };
+        let deviation = (now - last).as_secs_f64() * 1000.0 - TICK_MS as f64;
+        self.jitter_ms += (deviation.abs() - self.jitter_ms) / 16.0;
+        self.max_deviation_ms = self.max_deviation_ms.max(deviation.abs());
+        if deviation >= LATE_TICK_MS {
+            self.late_ticks += 1;
+        }
+    }
+
+    fn summary(&self) -> Option<JitterSummary> {
+        (self.ticks > 1).then_some(JitterSummary {
+            ticks: self.ticks,
+            jitter_ms: self.jitter_ms,
+            max_deviation_ms: self.max_deviation_ms,
+            late_ticks: self.late_ticks,
+        })
+    }
+}
+
+/// Start measuring write delays afresh, when a recording starts.
+pub fn reset_capture() {
+    *CAPTURE_TO_DISK.lock() = Some(DelayStats::new());
+}
+
+/// Start measuring voice tick jitter afresh, when a bot session starts.
+pub fn reset_receive() {
+    *DISCORD_RECEIVE.lock() = Some(TickJitter::new());
+}
+
+/// Note that audio handed to an encoder at `queued_at` is now written.
+pub fn record_write(queued_at: Instant) {
+    if let Some(stats) = CAPTURE_TO_DISK.lock().as_mut() {
+        stats.add(queued_at.elapsed());
+    }
+}
+
+/// Note a voice tick from the bot's call arriving now.
+pub fn record_tick() {
+    if let Some(jitter) = DISCORD_RECEIVE.lock().as_mut() {
+        jitter.tick(Instant::now());
+    }
+}
+
+/// Figures for the current or most recent session.
+pub fn report() -> LatencyReport {
+    LatencyReport {
+        capture_to_disk: CAPTURE_TO_DISK
+            .lock()
+            .as_ref()
+            .and_then(DelayStats::summary),
+        discord_receive: DISCORD_RECEIVE
+            .lock()
+            .as_ref()
+            .and_then(TickJitter::summary),
+    }
+}
+
+/// Write the figures to the log, for bug reports comparing sessions.
+pub fn log_report() {
+    let report = report();
+    if let Some(delay) = report.capture_to_disk {
+        log::info!(
+            "Capture to disk latency over {} writes: mean {:.1} ms, p95 {:.0} ms, max {:.1} ms",
+            delay.count,
+            delay.mean_ms,
+            delay.p95_ms,
+            delay.max_ms
+        );
+    }
+    if let Some(jitter) = report.discord_receive {
+        log::info!(
+            "Discord receive jitter over {} ticks: {:.2} ms, max deviation {:.1} ms, {} late",
+            jitter.ticks,
+            jitter.jitter_ms,
+            jitter.max_deviation_ms,
+            jitter.late_ticks
+        );
+    }
+}
@@ -1,29 +1,133 @@
+mod archive;
 mod audio;
+mod backup;
+mod bundle;
 mod commands;
+mod compare;
+mod convert;
+mod data_dir;
 mod discord;
+mod dnd;
+mod durations;
+mod empty;
+mod events;
+mod file_manager;
+mod import;
+mod integrations;
+mod jobs;
+mod latency;
+mod library;
+mod media_keys;
+mod migrate;
+mod mixdown;
+mod naming;
+mod overlay;
+mod post_record;
+mod preview;
+mod redact;
+mod retention;
+#[cfg(target_os = "linux")]
+mod sandbox;
+mod secrets;
+mod session;
 mod settings;
+mod shortcuts;
+mod simulate;
+mod storage;
+mod timecode;
+mod trash;
+mod trim;
+mod upload;
+mod watch;
 
-use commands::{DiscordState, RecorderState};
+use commands::{DiscordState, RecorderState, SpeakingFeedState};
 use parking_lot::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 use tauri::{
     menu::{Menu, MenuItem, PredefinedMenuItem},
     tray::{TrayIconBuilder, TrayIconEvent},
-    AppHandle, Manager, Wry,
+    AppHandle, Emitter, Manager, Wry,
 };
+use tauri_plugin_log::{Target, TargetKind};
+use tauri_plugin_notification::NotificationExt;
+
+/// Stop recording before exiting on SIGTERM or SIGINT, so the files of both
+/// local capture and a bot session are finalized and Linux routing hands
+/// Discord back to its own sink.
+#[cfg(unix)]
+async fn stop_on_signal(app: AppHandle) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let (Ok(mut terminate), Ok(mut interrupt)) = (
+        signal(SignalKind::terminate()),
+        signal(SignalKind::interrupt()),
+    ) else {
+        log::warn!("Failed to install signal handlers");
+        return;
+    };
+    tokio::select! {
+        _ = terminate.recv() => {}
+        _ = interrupt.recv() => {}
+    }
+    log::info!("Signal received, stopping before exit");
+    {
+        let state = app.state::<RecorderState>();
+        let mut recorder = state.0.lock();
+        if recorder.is_recording() {
+            if let Err(e) = recorder.stop() {
+                log::error!("Failed to stop recording: {}", e);
+            }
+        }
+    }
+    {
+        let discord = app.state::<DiscordState>();
+        let bot = discord.0.lock().await;
+        if bot.is_recording() {
+            if let Err(e) = commands::stop_bot_session(&app, &bot).await {
+                log::error!("Failed to stop bot recording: {}", e);
+            }
+        }
+    }
+    app.exit(0);
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let speaking_feed = Arc::new(discord::speaking_feed::SpeakingFeed::new());
+
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .setup(|app| {
+            settings::apply_runtime_settings(&app.state::<settings::SettingsState>().0.lock());
             if cfg!(debug_assertions) {
+                let mut log_builder = tauri_plugin_log::Builder::default();
+                if let Some(dir) = &data_dir::get().logs {
+                    log_builder = log_builder.clear_targets().targets([
+                        Target::new(TargetKind::Stdout),
+                        Target::new(TargetKind::Folder {
+                            path: dir.clone(),
+                            file_name: None,
+                        }),
+                    ]);
+                }
                 app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
+                    log_builder
                         .level(log::LevelFilter::Info)
+                        .format(|out, message, record| {
+                            out.finish(format_args!(
+                                "{}[{}][{}] {}",
+                                chrono::Local::now().format("[%Y-%m-%d][%H:%M:%S]"),
+                                record.target(),
+                                record.level(),
+                                redact::text(&message.to_string())
+                            ))
+                        })
                         .build(),
                 )?;
             }
@@ -48,33 +152,46 @@ pub fn run() {
                             let _ = window.set_focus();
                         }
                     }
+                    // The same path as the UI, so every setting and event applies
                     "record" => {
-                        let state = app.state::<RecorderState>();
-                        let settings_state = app.state::<settings::SettingsState>();
-                        let mut recorder = state.0.lock();
-                        if !recorder.is_recording() {
-                            let recordings_dir = settings::recordings_dir(&settings_state);
-                            let s = settings_state.0.lock();
-                            let silence_trim = s.silence_trim;
-                            let max_duration = s.max_duration_secs;
-                            drop(s);
-                            let timestamp = chrono::Local::now().format("%Y-%m-%d_%H%M%S");
-                            let filename = format!("discord-{}.wav", timestamp);
-                            let path = recordings_dir.join(&filename);
-                            let _ = recorder.start(
-                                &path.to_string_lossy(),
-                                audio::encoder::AudioFormat::Wav,
-                                silence_trim,
-                                max_duration,
-                            );
-                        }
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            let started = commands::start_recording(
+                                app.state(),
+                                app.state(),
+                                app.state(),
+                                app.state(),
+                                None,
+                                None,
+                                None,
+                                None,
+                            )
+                            .await;
+                            if let Err(e) = started {
+                                log::warn!("Not recording from the tray: {}", e);
+                                let _ = app
+                                    .notification()
+                                    .builder()
+                                    .title("Can't start recording")
+                                    .body(e)
+                                    .show();
+                            }
+                        });
                     }
                     "stop" => {
-                        let state = app.state::<RecorderState>();
-                        let mut recorder = state.0.lock();
-                        if recorder.is_recording() {
-                            let _ = recorder.stop();
-                        }
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            let stopped = commands::stop_recording(
+                                app.clone(),
+                                app.state(),
+                                app.state(),
+                                app.state(),
+                            )
+                            .await;
+                            if let Err(e) = stopped {
+                                log::error!("Failed to stop recording from the tray: {}", e);
+                            }
+                        });
                     }
                     "quit" => {
                         app.exit(0);
@@ -96,21 +213,138 @@ pub fn run() {
                 })
                 .build(app)?;
 
+            overlay::spawn_writer(app.handle().clone());
+            retention::spawn_cleanup(app.handle().clone());
+            if app
+                .state::<settings::SettingsState>()
+                .0
+                .lock()
+                .delete_empty_recordings
+            {
+                let app = app.handle().clone();
+                std::thread::spawn(move || empty::sweep_at_startup(&app));
+            }
+            storage::spawn_guard(app.handle().clone());
+            media_keys::spawn(app.handle().clone());
+            {
+                let dir = settings::recordings_dir(&app.state::<settings::SettingsState>());
+                std::thread::spawn(move || {
+                    trash::purge_expired(&dir);
+                    // Sessions a crash left recording, for list_failed_sessions
+                    let interrupted = session::recover_interrupted(&dir);
+                    if interrupted > 0 {
+                        log::warn!("Found {} interrupted recording session(s)", interrupted);
+                    }
+                });
+            }
+
+            // Discord stays muted on DiscRec's sink if a run was killed mid-recording
+            #[cfg(target_os = "linux")]
+            std::thread::spawn(audio::capture::clean_up_stale_routing);
+            #[cfg(unix)]
+            tauri::async_runtime::spawn(stop_on_signal(app.handle().clone()));
+
+            // Recordings land somewhere unexpected when there is no Music folder
+            let has_custom_dir = app
+                .state::<settings::SettingsState>()
+                .0
+                .lock()
+                .output_dir
+                .as_ref()
+                .is_some_and(|dir| !dir.is_empty());
+            let default_dir = settings::default_dir();
+            if !has_custom_dir
+                && !matches!(
+                    default_dir.base,
+                    settings::DefaultDirBase::Music | settings::DefaultDirBase::Portable
+                )
+            {
+                log::warn!(
+                    "No Music folder; recordings default to {}",
+                    default_dir.path.display()
+                );
+                let _ = app.emit(
+                    settings::DEFAULT_DIR_FALLBACK,
+                    default_dir.path.to_string_lossy(),
+                );
+            }
+
+            // Push recorder status to the UI instead of having it poll, on
+            // to OBS when it follows DiscRec, do not disturb while
+            // recording, and saved files to the post-record command and
+            // uploads
+            let status_hook = post_record::status_hook(
+                app.handle().clone(),
+                dnd::status_hook(
+                    app.handle().clone(),
+                    integrations::obs::status_hook(
+                        app.handle().clone(),
+                        events::emitter(app.handle().clone()),
+                    ),
+                ),
+            );
+            app.state::<RecorderState>()
+                .0
+                .lock()
+                .set_status_hook(Arc::clone(&status_hook));
+            app.state::<simulate::SimulationState>()
+                .set_status_hook(Arc::clone(&status_hook));
+            if let Ok(mut bot) = app.state::<DiscordState>().0.try_lock() {
+                bot.set_status_hook(status_hook);
+            }
+
+            let obs = app.state::<settings::SettingsState>().0.lock().obs.clone();
+            if obs.enabled {
+                let password = integrations::obs::load_password().unwrap_or_else(|e| {
+                    log::warn!("{:#}", e);
+                    None
+                });
+                app.state::<integrations::obs::ObsState>()
+                    .configure(app.handle(), obs, password);
+            }
+
+            let feed_port = app
+                .state::<settings::SettingsState>()
+                .0
+                .lock()
+                .speaking_feed_port;
+            if feed_port.is_some() {
+                let feed = Arc::clone(&app.state::<SpeakingFeedState>().0);
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = feed.listen(feed_port).await {
+                        log::warn!("Speaking feed unavailable: {:#}", e);
+                    }
+                });
+            }
+
             Ok(())
         })
-        .manage(RecorderState(Mutex::new(
-            audio::capture::AudioCapture::new(),
-        )))
+        .manage(RecorderState(
+            Mutex::new(audio::capture::AudioCapture::new()),
+            AtomicBool::new(false),
+        ))
         .manage(DiscordState(tokio::sync::Mutex::new(
-            discord::bot::DiscordBot::new(),
+            discord::bot::DiscordBot::new(Arc::clone(&speaking_feed)),
         )))
+        .manage(SpeakingFeedState(speaking_feed))
         .manage(settings::SettingsState::load())
+        .manage(archive::ArchiveState::load())
+        .manage(integrations::obs::ObsState::new())
+        .manage(overlay::OverlayState::new())
+        .manage(audio::playback::Player::new())
+        .manage(jobs::JobsState::new())
+        .manage(simulate::SimulationState::new())
+        .manage(watch::WatchState::new())
         .invoke_handler(tauri::generate_handler![
             commands::start_recording,
             commands::stop_recording,
+            commands::abort_recording,
             commands::get_status,
+            commands::get_capture_sources,
+            commands::preview_levels,
             commands::get_recordings_dir,
             commands::open_folder,
+            commands::reveal_in_file_manager,
             commands::discord_connect,
             commands::discord_disconnect,
             commands::discord_list_guilds,
@@ -119,21 +353,152 @@ pub fn run() {
             commands::discord_stop_recording,
             commands::discord_get_status,
             commands::list_recordings,
+            commands::list_sessions,
+            commands::generate_previews,
+            commands::play_recording,
+            commands::pause_playback,
+            commands::seek_playback,
+            commands::stop_playback,
+            commands::get_playback_position,
+            commands::read_preview,
+            commands::export_session_bundle,
             commands::delete_recording,
+            commands::restore_recording,
+            commands::list_trash,
+            commands::empty_trash,
+            commands::trim_recording,
+            commands::convert_recording,
+            commands::mix_recordings,
+            commands::compare_session_sources,
+            commands::find_nonstandard_recordings,
+            commands::import_recordings,
+            commands::list_archives,
+            commands::add_archive,
+            commands::reindex_archive,
+            commands::remove_archive,
+            commands::search_archives,
             commands::discord_get_channel_members,
+            commands::discord_list_channel_members,
+            commands::discord_processing_note,
+            commands::discord_set_user_excluded,
+            commands::discord_get_recorded_users,
+            commands::discord_set_recorded_users,
+            commands::get_skip_server_muted,
+            commands::set_skip_server_muted,
+            commands::discord_get_track_stats,
+            commands::discord_get_speaker_stats,
+            commands::discord_export_speaker_frames,
+            commands::export_timestamp_subtitles,
+            commands::cleanup_empty_tracks,
             commands::save_bot_token,
             commands::load_bot_token,
             commands::delete_bot_token,
             commands::get_output_dir,
+            commands::pick_folder,
             commands::set_output_dir,
+            commands::migrate_recordings,
             commands::get_silence_trim,
             commands::set_silence_trim,
+            commands::get_record_mic,
+            commands::set_record_mic,
+            commands::get_mix_local_sources,
+            commands::set_mix_local_sources,
+            commands::list_audio_devices,
+            commands::get_capture_device,
+            commands::set_capture_device,
+            commands::get_capture_device_fallback,
+            commands::set_capture_device_fallback,
+            commands::get_routed_apps,
+            commands::set_routed_apps,
+            commands::list_playing_apps,
             commands::get_max_duration,
             commands::set_max_duration,
+            commands::get_segment_minutes,
+            commands::set_segment_minutes,
+            commands::estimate_recording_size,
+            commands::get_min_free_space_mb,
+            commands::set_min_free_space_mb,
+            commands::get_filename_template,
+            commands::set_filename_template,
+            commands::preview_filename_template,
+            commands::get_session_folders,
+            commands::set_session_folders,
             commands::get_shortcuts,
             commands::set_shortcuts,
             commands::get_notify_on_record,
             commands::set_notify_on_record,
+            commands::get_notify_participants,
+            commands::set_notify_participants,
+            commands::get_dnd_while_recording,
+            commands::set_dnd_while_recording,
+            commands::get_ask_consent,
+            commands::set_ask_consent,
+            commands::get_voice_decode_mode,
+            commands::set_voice_decode_mode,
+            commands::get_bot_audio,
+            commands::set_bot_audio,
+            commands::get_record_chat,
+            commands::set_record_chat,
+            commands::get_overlay_path,
+            commands::set_overlay_path,
+            commands::get_align_speaker_tracks,
+            commands::set_align_speaker_tracks,
+            commands::get_speaking_feed_port,
+            commands::set_speaking_feed_port,
+            commands::get_post_record_command,
+            commands::set_post_record_command,
+            commands::normalize_recording,
+            commands::get_normalize_lufs,
+            commands::set_normalize_lufs,
+            commands::get_retention_policy,
+            commands::set_retention_policy,
+            commands::run_cleanup_now,
+            commands::get_delete_empty_recordings,
+            commands::set_delete_empty_recordings,
+            commands::remove_empty_recordings,
+            commands::get_redact_logs,
+            commands::set_redact_logs,
+            commands::get_latency_report,
+            commands::get_data_dirs,
+            commands::open_data_folder,
+            commands::backup_app_data,
+            commands::restore_app_data,
+            commands::get_tick_dump_mb,
+            commands::set_tick_dump_mb,
+            commands::open_tick_dump_folder,
+            commands::get_obs_settings,
+            commands::set_obs_settings,
+            commands::get_obs_status,
+            commands::get_upload_settings,
+            commands::set_upload_settings,
+            commands::get_webdav_has_password,
+            commands::get_google_drive_signed_in,
+            commands::start_google_drive_sign_in,
+            commands::sign_out_google_drive,
+            commands::get_min_speaker_secs,
+            commands::set_min_speaker_secs,
+            commands::get_encoder_limits,
+            commands::set_encoder_limits,
+            commands::get_default_format,
+            commands::set_default_format,
+            commands::get_wav_bit_depth,
+            commands::set_wav_bit_depth,
+            commands::get_flush_interval_secs,
+            commands::set_flush_interval_secs,
+            commands::get_virtual_device_setup,
+            commands::open_blackhole_installer,
+            commands::setup_multi_output_device,
+            commands::get_output_routes,
+            commands::set_output_routes,
+            commands::list_failed_sessions,
+            commands::retry_finalize,
+            commands::get_watched_channels,
+            commands::set_watched_channels,
+            commands::get_job_status,
+            commands::list_jobs,
+            commands::cancel_job,
+            commands::simulate_recording,
+            commands::stop_simulation,
         ])
         .on_window_event(|window, event| {
             if let tauri::WindowEvent::CloseRequested { api, .. } = event {
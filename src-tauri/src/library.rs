@@ -0,0 +1,413 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::audio::mixer::{MIX_CHANNELS, MIX_SAMPLE_RATE};
+use crate::audio::pcm;
+
+/// File extensions listed as recordings.
+pub const RECORDING_EXTENSIONS: [&str; 4] = ["wav", "flac", "mp3", "opus"];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecordingInfo {
+    pub path: String,
+    pub filename: String,
+    pub size: u64,
+    pub modified: String,
+    pub format: String,
+    /// Stream layout, for formats DiscRec can read the header of.
+    #[serde(default)]
+    pub audio: Option<AudioProperties>,
+    /// Manifest of the bot session the file belongs to, when listed with
+    /// its sessions.
+    #[serde(default)]
+    pub session: Option<String>,
+    /// Length in seconds, when listed with durations.
+    #[serde(default)]
+    pub duration_secs: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct AudioProperties {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+}
+
+impl AudioProperties {
+    /// Whether this matches what DiscRec records: 48 kHz, mono or stereo,
+    /// 16/24-bit integer or 32-bit float. Anything else is worth normalizing
+    /// before mixing or merging.
+    pub fn is_standard(&self) -> bool {
+        self.sample_rate == MIX_SAMPLE_RATE
+            && (1..=MIX_CHANNELS).contains(&self.channels)
+            && matches!(self.bits_per_sample, 16 | 24 | 32)
+    }
+}
+
+/// Lowercase extension of `path` if it is a recording format.
+pub fn recording_format(path: &Path) -> Option<String> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    RECORDING_EXTENSIONS.contains(&ext.as_str()).then_some(ext)
+}
+
+/// Describe the recording at `path`, or `None` if it isn't one.
+pub fn recording_info(path: &Path) -> Option<RecordingInfo> {
+    let format = recording_format(path)?;
+    let metadata = std::fs::metadata(path).ok().filter(|m| m.is_file())?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .map(|t| {
+            let dt: chrono::DateTime<chrono::Local> = t.into();
+            dt.format("%Y-%m-%d %H:%M:%S").to_string()
+        })
+        .unwrap_or_default();
+
+    Some(RecordingInfo {
+        path: path.to_string_lossy().to_string(),
+        filename: path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        size: metadata.len(),
+        modified,
+        audio: probe(path, &format),
+        format,
+        session: None,
+        duration_secs: None,
+    })
+}
+
+/// Read the stream layout from a WAV or FLAC header.
+pub fn probe(path: &Path, format: &str) -> Option<AudioProperties> {
+    match format {
+        "wav" => {
+            let spec = hound::WavReader::open(path).ok()?.spec();
+            Some(AudioProperties {
+                sample_rate: spec.sample_rate,
+                channels: spec.channels,
+                bits_per_sample: spec.bits_per_sample,
+            })
+        }
+        "flac" => {
+            let info = claxon::FlacReader::open(path).ok()?.streaminfo();
+            Some(AudioProperties {
+                sample_rate: info.sample_rate,
+                channels: info.channels as u16,
+                bits_per_sample: info.bits_per_sample as u16,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Length of a WAV or FLAC file in frames, read from its header.
+pub fn frame_count(path: &Path, format: &str) -> Option<u64> {
+    match format {
+        "wav" => Some(hound::WavReader::open(path).ok()?.duration() as u64),
+        "flac" => claxon::FlacReader::open(path).ok()?.streaminfo().samples,
+        _ => None,
+    }
+}
+
+/// Length of a recording in seconds, read from its headers: the frame
+/// count of WAV and FLAC, the Xing/VBRI frame count or bitrate of MP3 and
+/// the last granule position of Opus.
+pub fn duration_secs(path: &Path, format: &str) -> Option<f64> {
+    match format {
+        "wav" | "flac" => {
+            let sample_rate = probe(path, format)?.sample_rate;
+            let frames = frame_count(path, format)?;
+            (sample_rate > 0).then(|| frames as f64 / sample_rate as f64)
+        }
+        "mp3" => mp3_duration_secs(path),
+        "opus" => opus_duration_secs(path),
+        _ => None,
+    }
+}
+
+/// How much of a file is searched for headers.
+const HEADER_SCAN_BYTES: u64 = 64 * 1024;
+
+fn read_at(file: &mut std::fs::File, offset: u64, len: u64) -> Option<Vec<u8>> {
+    use std::io::{Read, Seek, SeekFrom};
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    let mut buf = Vec::new();
+    file.take(len).read_to_end(&mut buf).ok()?;
+    Some(buf)
+}
+
+fn mp3_duration_secs(path: &Path) -> Option<f64> {
+    const BITRATES_V1: [u32; 15] = [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ];
+    const BITRATES_V2: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+    const SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+    let mut file = std::fs::File::open(path).ok()?;
+    let size = file.metadata().ok()?.len();
+    // Skip an ID3v2 tag, whose size is stored in 7-bit bytes
+    let id3 = read_at(&mut file, 0, 10)?;
+    let mut start = 0;
+    if id3.len() == 10 && id3.starts_with(b"ID3") {
+        let tag = id3[6..10]
+            .iter()
+            .fold(0u64, |n, &b| (n << 7) | (b & 0x7f) as u64);
+        start = 10 + tag + if id3[5] & 0x10 != 0 { 10 } else { 0 };
+    }
+    let buf = read_at(&mut file, start, HEADER_SCAN_BYTES)?;
+    let at = buf.windows(4).position(|h| {
+        h[0] == 0xff
+            && h[1] & 0xe0 == 0xe0
+            && (h[1] >> 3) & 3 != 1
+            && (h[1] >> 1) & 3 == 1
+            && h[2] >> 4 != 0xf
+            && (h[2] >> 2) & 3 != 3
+    })?;
+    let header = &buf[at..];
+    // Layer III: version 3 is MPEG-1, 2 is MPEG-2, 0 is MPEG-2.5
+    let version = (header[1] >> 3) & 3;
+    let mpeg1 = version == 3;
+    let bitrate = if mpeg1 { BITRATES_V1 } else { BITRATES_V2 }[(header[2] >> 4) as usize];
+    let sample_rate = SAMPLE_RATES[((header[2] >> 2) & 3) as usize]
+        >> match version {
+            3 => 0,
+            2 => 1,
+            _ => 2,
+        };
+    let samples_per_frame = if mpeg1 { 1152 } else { 576 };
+    let mono = header[3] >> 6 == 3;
+
+    // A Xing/Info or VBRI frame counts the frames, which VBR files need
+    let side_info = match (mpeg1, mono) {
+        (true, false) => 32,
+        (true, true) | (false, false) => 17,
+        (false, true) => 9,
+    };
+    let be32 = |at: usize| -> Option<u32> {
+        header
+            .get(at..at + 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let xing = 4 + side_info;
+    let frames = match header.get(xing..xing + 4) {
+        Some(b"Xing") | Some(b"Info") if be32(xing + 4)? & 1 != 0 => be32(xing + 8),
+        _ if header.get(36..40) == Some(&b"VBRI"[..]) => be32(36 + 14),
+        _ => None,
+    };
+    match frames {
+        Some(frames) => Some(frames as f64 * samples_per_frame as f64 / sample_rate as f64),
+        None if bitrate > 0 => {
+            let audio_bytes = size.saturating_sub(start + at as u64);
+            Some(audio_bytes as f64 * 8.0 / (bitrate as f64 * 1000.0))
+        }
+        None => None,
+    }
+}
+
+fn opus_duration_secs(path: &Path) -> Option<f64> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let size = file.metadata().ok()?.len();
+    let head = read_at(&mut file, 0, 4096)?;
+    let opus_head = head.windows(8).position(|w| w == b"OpusHead")?;
+    let pre_skip = u16::from_le_bytes(head.get(opus_head + 10..opus_head + 12)?.try_into().ok()?);
+    // The last page's granule position is the sample count at 48 kHz
+    let tail_start = size.saturating_sub(HEADER_SCAN_BYTES);
+    let tail = read_at(&mut file, tail_start, HEADER_SCAN_BYTES)?;
+    let page = tail.windows(5).rposition(|w| w == b"OggS\0")?;
+    let granule = i64::from_le_bytes(tail.get(page + 6..page + 14)?.try_into().ok()?);
+    (granule > 0).then(|| (granule - pre_skip as i64).max(0) as f64 / 48_000.0)
+}
+
+/// Samples handed to a decode callback at a time.
+const DECODE_BLOCK: usize = 8192;
+
+/// Decode a WAV or FLAC file, passing `(channels, sample_rate, samples)` to
+/// `on_block` with interleaved -1.0..1.0 samples a block at a time, until it
+/// returns `false` or the file ends.
+pub fn decode(
+    path: &Path,
+    format: &str,
+    mut on_block: impl FnMut(u16, u32, &[f32]) -> Result<bool>,
+) -> Result<()> {
+    let mut block = Vec::with_capacity(DECODE_BLOCK);
+    match format {
+        "wav" => {
+            let mut reader = hound::WavReader::open(path).context("Failed to open WAV file")?;
+            let spec = reader.spec();
+            let mut samples: Box<dyn Iterator<Item = hound::Result<f32>>> = match spec.sample_format
+            {
+                hound::SampleFormat::Float => Box::new(reader.samples::<f32>()),
+                hound::SampleFormat::Int => Box::new(
+                    reader
+                        .samples::<i32>()
+                        .map(move |s| s.map(|s| pcm::to_float(s, spec.bits_per_sample as u32))),
+                ),
+            };
+            loop {
+                block.clear();
+                for sample in samples.by_ref().take(DECODE_BLOCK) {
+                    block.push(sample.context("Failed to read WAV file")?);
+                }
+                if block.is_empty() || !on_block(spec.channels, spec.sample_rate, &block)? {
+                    return Ok(());
+                }
+            }
+        }
+        "flac" => {
+            let mut reader = claxon::FlacReader::open(path).context("Failed to open FLAC file")?;
+            let info = reader.streaminfo();
+            let mut frames = reader.blocks();
+            let mut buffer = Vec::new();
+            while let Some(frame) = frames
+                .read_next_or_eof(buffer)
+                .context("Failed to read FLAC file")?
+            {
+                block.clear();
+                for i in 0..frame.duration() {
+                    for ch in 0..frame.channels() {
+                        block.push(pcm::to_float(frame.sample(ch, i), info.bits_per_sample));
+                    }
+                }
+                if !on_block(info.channels as u16, info.sample_rate, &block)? {
+                    break;
+                }
+                buffer = frame.into_buffer();
+            }
+            Ok(())
+        }
+        _ => anyhow::bail!("Cannot decode {} files", format),
+    }
+}
+
+/// Recordings directly inside `dir`, or in any folder below it with `recursive`.
+///
+/// Hidden folders (such as `.sessions`) are skipped.
+pub fn scan(dir: &Path, recursive: bool) -> std::io::Result<Vec<RecordingInfo>> {
+    let mut recordings = Vec::new();
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            if recursive && !entry.file_name().to_string_lossy().starts_with('.') {
+                if let Ok(nested) = scan(&path, true) {
+                    recordings.extend(nested);
+                }
+            }
+            continue;
+        }
+        recordings.extend(recording_info(&path));
+    }
+    Ok(recordings)
+}
+
+/// What to sort a recording listing by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingSort {
+    #[default]
+    Date,
+    Size,
+    Duration,
+    Name,
+}
+
+/// Which recordings to list and in what order, a page at a time.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RecordingQuery {
+    /// Recordings to skip, after filtering and sorting.
+    pub offset: usize,
+    /// Page size; all the rest when unset.
+    pub limit: Option<usize>,
+    pub sort: RecordingSort,
+    /// Oldest, smallest, shortest or A first instead of the reverse.
+    pub ascending: bool,
+    /// Only these formats, e.g. `["wav", "flac"]`; any when empty.
+    pub formats: Vec<String>,
+    /// First and last day modified, as `YYYY-MM-DD`, both included.
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// Part of the file name, in any case.
+    pub search: Option<String>,
+}
+
+impl RecordingQuery {
+    /// Whether anything is filtered out, so a listing is the whole library.
+    pub fn filters(&self) -> bool {
+        !self.formats.is_empty()
+            || self.from.is_some()
+            || self.to.is_some()
+            || self.search.as_deref().is_some_and(|s| !s.trim().is_empty())
+    }
+
+    /// Check the date range, which is compared as text.
+    pub fn validate(&self) -> Result<()> {
+        for day in [&self.from, &self.to].into_iter().flatten() {
+            chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d")
+                .with_context(|| format!("\"{}\" isn't a date like 2024-05-01", day))?;
+        }
+        Ok(())
+    }
+
+    pub fn matches(&self, info: &RecordingInfo) -> bool {
+        let day = info.modified.get(..10).unwrap_or_default();
+        let search = self
+            .search
+            .as_deref()
+            .map(|s| s.trim().to_lowercase())
+            .unwrap_or_default();
+        (self.formats.is_empty()
+            || self
+                .formats
+                .iter()
+                .any(|f| f.eq_ignore_ascii_case(&info.format)))
+            && self.from.as_deref().map_or(true, |from| day >= from)
+            && self.to.as_deref().map_or(true, |to| day <= to)
+            && (search.is_empty() || info.filename.to_lowercase().contains(&search))
+    }
+
+    /// Sort `recordings`, with unknown durations last either way and the
+    /// newest first among equals.
+    pub fn sort(&self, recordings: &mut [RecordingInfo]) {
+        recordings.sort_by(|a, b| {
+            let order = match self.sort {
+                RecordingSort::Date => a.modified.cmp(&b.modified),
+                RecordingSort::Size => a.size.cmp(&b.size),
+                RecordingSort::Name => a.filename.to_lowercase().cmp(&b.filename.to_lowercase()),
+                RecordingSort::Duration => match (a.duration_secs, b.duration_secs) {
+                    (Some(x), Some(y)) => x.total_cmp(&y),
+                    (Some(_), None) => return std::cmp::Ordering::Less,
+                    (None, Some(_)) => return std::cmp::Ordering::Greater,
+                    (None, None) => std::cmp::Ordering::Equal,
+                },
+            };
+            let order = if self.ascending {
+                order
+            } else {
+                order.reverse()
+            };
+            order.then_with(|| b.modified.cmp(&a.modified))
+        });
+    }
+
+    /// The requested page of already sorted `recordings`.
+    pub fn page(&self, recordings: Vec<RecordingInfo>) -> RecordingPage {
+        let total = recordings.len();
+        let recordings = recordings
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect();
+        RecordingPage { recordings, total }
+    }
+}
+
+/// One page of a recording listing.
+#[derive(Debug, Serialize)]
+pub struct RecordingPage {
+    pub recordings: Vec<RecordingInfo>,
+    /// Recordings matching the query across all pages.
+    pub total: usize,
+}
@@ -0,0 +1,199 @@
+use tauri::AppHandle;
+
+/// Publish the preview player to the system's media session, MPRIS on Linux
+/// and the System Media Transport Controls on Windows, so media keys and the
+/// desktop's media widgets show and control it. Elsewhere nothing happens.
+pub fn spawn(app: AppHandle) {
+    #[cfg(any(target_os = "linux", windows))]
+    std::thread::spawn(move || platform::run(app));
+    #[cfg(not(any(target_os = "linux", windows)))]
+    let _ = app;
+}
+
+#[cfg(any(target_os = "linux", windows))]
+mod platform {
+    use anyhow::{Context, Result};
+    use souvlaki::{
+        MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, MediaPosition,
+        PlatformConfig, SeekDirection,
+    };
+    use std::path::Path;
+    use std::time::{Duration, Instant};
+    use tauri::{AppHandle, Emitter, Manager};
+
+    use crate::audio::playback::{PlaybackStatus, Player, POSITION};
+
+    /// How often the player is looked at for changes to publish.
+    const POLL_INTERVAL: Duration = Duration::from_millis(250);
+    /// How far the position may drift from where the media session thinks it
+    /// is before it is told again, i.e. what counts as a seek.
+    const MAX_DRIFT_SECS: f64 = 1.5;
+    /// Seek step for media keys that don't say how far.
+    const SEEK_STEP: Duration = Duration::from_secs(10);
+
+    /// What the media session was last told.
+    struct Published {
+        path: Option<String>,
+        playing: bool,
+        position_secs: f64,
+        at: Instant,
+    }
+
+    impl Published {
+        fn expected_position(&self) -> f64 {
+            match self.playing {
+                true => self.position_secs + self.at.elapsed().as_secs_f64(),
+                false => self.position_secs,
+            }
+        }
+    }
+
+    pub fn run(app: AppHandle) {
+        let mut controls = match open(&app) {
+            Ok(controls) => controls,
+            Err(e) => {
+                log::warn!("Media keys unavailable: {:#}", e);
+                return;
+            }
+        };
+        let mut published: Option<Published> = None;
+        loop {
+            let status = app.state::<Player>().status();
+            if let Err(e) = publish(&mut controls, &status, &mut published) {
+                log::warn!("Failed to update the media session: {:#}", e);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    fn open(app: &AppHandle) -> Result<MediaControls> {
+        #[cfg(windows)]
+        let hwnd = {
+            let window = app.get_webview_window("main").context("No main window")?;
+            Some(window.hwnd().context("No window handle")?.0 as *mut std::ffi::c_void)
+        };
+        #[cfg(not(windows))]
+        let hwnd = None;
+
+        let mut controls = MediaControls::new(PlatformConfig {
+            dbus_name: "discrec",
+            display_name: "DiscRec",
+            hwnd,
+        })
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+        let app = app.clone();
+        controls
+            .attach(move |event| handle(&app, event))
+            .map_err(|e| anyhow::anyhow!("{:?}", e))
+            .context("Failed to listen for media keys")?;
+        Ok(controls)
+    }
+
+    /// Tell the media session about a new recording, a pause or resume, a
+    /// seek, or that playback stopped.
+    fn publish(
+        controls: &mut MediaControls,
+        status: &PlaybackStatus,
+        published: &mut Option<Published>,
+    ) -> Result<()> {
+        let changed = match published.as_ref() {
+            None => status.path.is_some(),
+            Some(last) => {
+                last.path != status.path
+                    || last.playing != status.playing
+                    || (last.expected_position() - status.position_secs).abs() > MAX_DRIFT_SECS
+            }
+        };
+        if !changed {
+            return Ok(());
+        }
+
+        let path_changed = published.as_ref().map(|last| &last.path) != Some(&status.path);
+        if path_changed {
+            let title = status
+                .path
+                .as_deref()
+                .and_then(|path| Path::new(path).file_name())
+                .map(|name| name.to_string_lossy().to_string());
+            controls
+                .set_metadata(MediaMetadata {
+                    title: title.as_deref(),
+                    artist: Some("DiscRec"),
+                    duration: status.duration_secs.map(Duration::from_secs_f64),
+                    ..Default::default()
+                })
+                .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+        }
+
+        let progress = Some(MediaPosition(Duration::from_secs_f64(
+            status.position_secs.max(0.0),
+        )));
+        let playback = match (&status.path, status.playing) {
+            (None, _) => MediaPlayback::Stopped,
+            (Some(_), true) => MediaPlayback::Playing { progress },
+            (Some(_), false) => MediaPlayback::Paused { progress },
+        };
+        controls
+            .set_playback(playback)
+            .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+        *published = Some(Published {
+            path: status.path.clone(),
+            playing: status.playing,
+            position_secs: status.position_secs,
+            at: Instant::now(),
+        });
+        Ok(())
+    }
+
+    /// Carry out a media key press on the player and show the result in the
+    /// UI, as the playback commands would.
+    fn handle(app: &AppHandle, event: MediaControlEvent) {
+        if let MediaControlEvent::Raise = event {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+            return;
+        }
+        let player = app.state::<Player>();
+        let status = player.status();
+        let Some(path) = status.path.clone() else {
+            return;
+        };
+        let seek_by = |direction: SeekDirection, step: Duration| {
+            let step = step.as_secs_f64();
+            let target = match direction {
+                SeekDirection::Forward => status.position_secs + step,
+                SeekDirection::Backward => status.position_secs - step,
+            };
+            let target = status
+                .duration_secs
+                .map_or(target, |duration| target.min(duration));
+            player.seek(target.max(0.0))
+        };
+        let result = match event {
+            MediaControlEvent::Play => player.play(app, Path::new(&path), None),
+            MediaControlEvent::Pause => Ok(player.pause()),
+            MediaControlEvent::Toggle if status.playing => Ok(player.pause()),
+            MediaControlEvent::Toggle => player.play(app, Path::new(&path), None),
+            MediaControlEvent::Seek(direction) => seek_by(direction, SEEK_STEP),
+            MediaControlEvent::SeekBy(direction, step) => seek_by(direction, step),
+            MediaControlEvent::SetPosition(MediaPosition(position)) => {
+                player.seek(position.as_secs_f64())
+            }
+            MediaControlEvent::Stop => {
+                player.stop();
+                Ok(PlaybackStatus::default())
+            }
+            _ => return,
+        };
+        match result {
+            Ok(status) => {
+                if let Err(e) = app.emit(POSITION, status) {
+                    log::warn!("Failed to emit {}: {}", POSITION, e);
+                }
+            }
+            Err(e) => log::warn!("Media key failed: {:#}", e),
+        }
+    }
+}
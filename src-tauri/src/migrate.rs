@@ -0,0 +1,238 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::library::recording_format;
+use crate::session::SESSIONS_DIR;
+use crate::trash::TRASH_DIR;
+
+/// Suffix of a file still being copied into the new library.
+const PARTIAL_SUFFIX: &str = ".migrating";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MigrateMode {
+    /// Leave the old library empty.
+    Move,
+    /// Keep the old library as it is.
+    Copy,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrateProgress {
+    pub done: usize,
+    pub total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    /// Path of the file being migrated, relative to the library.
+    pub current: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrateReport {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// A file that made it into the new library, and how to take it back out.
+struct Migrated {
+    from: PathBuf,
+    to: PathBuf,
+    renamed: bool,
+}
+
+/// Relative paths of recordings plus their metadata: kept sessions with
+/// their manifests and raw spills.
+fn library_files(library_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    collect_recordings(library_dir, Path::new(""), &mut files)
+        .context("Failed to read recordings directory")?;
+    Ok(files)
+}
+
+/// Recordings in `rel` and the non-hidden folders below it, where output
+/// routes and session folders put them, along with their sessions folders.
+fn collect_recordings(root: &Path, rel: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(root.join(rel))?.flatten() {
+        let name = entry.file_name();
+        let rel = rel.join(&name);
+        let path = entry.path();
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            if name == SESSIONS_DIR || name == TRASH_DIR {
+                collect_tree(root, &rel, files)?;
+            } else if !name.to_string_lossy().starts_with('.') {
+                collect_recordings(root, &rel, files)?;
+            }
+        } else if path.is_file() && recording_format(&path).is_some() {
+            files.push(rel);
+        }
+    }
+    Ok(())
+}
+
+fn collect_tree(root: &Path, rel: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let Ok(entries) = std::fs::read_dir(root.join(rel)) else {
+        return Ok(());
+    };
+    for entry in entries.flatten() {
+        let rel = rel.join(entry.file_name());
+        let file_type = entry
+            .file_type()
+            .context("Failed to read session directory")?;
+        if file_type.is_dir() {
+            collect_tree(root, &rel, files)?;
+        } else if file_type.is_file() {
+            files.push(rel);
+        }
+    }
+    Ok(())
+}
+
+/// Move or copy the library in `from` to `to`.
+///
+/// Nothing is overwritten. Files first land in `to` (renamed where possible,
+/// otherwise copied under a temporary name); if any of them fails, everything
+/// already migrated is put back and the old library is left untouched. In
+/// move mode the originals are only deleted once every file has arrived.
+pub fn migrate_library(
+    from: &Path,
+    to: &Path,
+    mode: MigrateMode,
+    mut on_progress: impl FnMut(&MigrateProgress),
+) -> Result<MigrateReport> {
+    let from = from
+        .canonicalize()
+        .context("Recordings directory not found")?;
+    std::fs::create_dir_all(to).context("Cannot create directory")?;
+    let to = to.canonicalize().context("Cannot open directory")?;
+    if to.starts_with(&from) || from.starts_with(&to) {
+        anyhow::bail!("The new folder cannot be inside the current one, or the other way round");
+    }
+
+    let files = library_files(&from)?;
+    let mut bytes_total = 0;
+    for rel in &files {
+        if to.join(rel).exists() {
+            anyhow::bail!("{} already exists in the new folder", rel.display());
+        }
+        bytes_total += std::fs::metadata(from.join(rel)).map_or(0, |m| m.len());
+    }
+
+    let mut progress = MigrateProgress {
+        done: 0,
+        total: files.len(),
+        bytes_done: 0,
+        bytes_total,
+        current: String::new(),
+    };
+    let mut migrated: Vec<Migrated> = Vec::with_capacity(files.len());
+
+    for rel in &files {
+        progress.current = rel.to_string_lossy().to_string();
+        on_progress(&progress);
+
+        let source = from.join(rel);
+        let target = to.join(rel);
+        match migrate_file(&source, &target, mode) {
+            Ok(renamed) => {
+                progress.bytes_done += std::fs::metadata(&target).map_or(0, |m| m.len());
+                migrated.push(Migrated {
+                    from: source,
+                    to: target,
+                    renamed,
+                });
+            }
+            Err(e) => {
+                log::error!("Migrating {} failed, rolling back: {}", rel.display(), e);
+                roll_back(&migrated, &to, &files);
+                return Err(e.context(format!("Failed to migrate {}", rel.display())));
+            }
+        }
+        progress.done += 1;
+    }
+
+    if mode == MigrateMode::Move {
+        for file in migrated.iter().filter(|f| !f.renamed) {
+            if let Err(e) = std::fs::remove_file(&file.from) {
+                log::warn!("Could not remove {}: {}", file.from.display(), e);
+            }
+        }
+        for folder in top_folders(&files) {
+            remove_empty_dirs(&from.join(folder));
+        }
+    }
+
+    progress.current.clear();
+    on_progress(&progress);
+    log::info!(
+        "Migrated {} recording file(s) from {} to {}",
+        files.len(),
+        from.display(),
+        to.display()
+    );
+    Ok(MigrateReport {
+        files: files.len(),
+        bytes: progress.bytes_done,
+    })
+}
+
+/// Returns whether the file was renamed (and so no longer exists at `from`).
+fn migrate_file(from: &Path, to: &Path, mode: MigrateMode) -> Result<bool> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent).context("Cannot create directory")?;
+    }
+    if mode == MigrateMode::Move && std::fs::rename(from, to).is_ok() {
+        return Ok(true);
+    }
+
+    let mut partial = to.as_os_str().to_owned();
+    partial.push(PARTIAL_SUFFIX);
+    let partial = PathBuf::from(partial);
+    let copied = std::fs::copy(from, &partial).and_then(|_| std::fs::rename(&partial, to));
+    if let Err(e) = copied {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e.into());
+    }
+    Ok(false)
+}
+
+fn roll_back(migrated: &[Migrated], to: &Path, files: &[PathBuf]) {
+    for file in migrated.iter().rev() {
+        let restored = if file.renamed {
+            std::fs::rename(&file.to, &file.from)
+        } else {
+            std::fs::remove_file(&file.to)
+        };
+        if let Err(e) = restored {
+            log::error!("Rollback failed for {}: {}", file.from.display(), e);
+        }
+    }
+    for folder in top_folders(files) {
+        remove_empty_dirs(&to.join(folder));
+    }
+}
+
+/// The folders directly in the library that `files` are in, to tidy up
+/// once they moved.
+fn top_folders(files: &[PathBuf]) -> BTreeSet<PathBuf> {
+    files
+        .iter()
+        .filter(|rel| rel.components().count() > 1)
+        .filter_map(|rel| rel.components().next())
+        .map(|folder| PathBuf::from(folder.as_os_str()))
+        .collect()
+}
+
+/// Remove `dir` and its subdirectories if they hold no files.
+fn remove_empty_dirs(dir: &Path) {
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                remove_empty_dirs(&entry.path());
+            }
+        }
+    }
+    // Fails (and is meant to) while anything is left inside
+    let _ = std::fs::remove_dir(dir);
+}
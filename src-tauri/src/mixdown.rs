@@ -0,0 +1,234 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::audio::encoder::{create_encoder, remove_partial, AudioEncoder, AudioFormat};
+use crate::audio::mixer::{Resampler, MIX_CHANNELS, MIX_SAMPLE_RATE};
+use crate::convert::{create_output, ConvertFormat};
+use crate::import::unique_path;
+use crate::library::{decode, frame_count, probe, recording_format};
+
+/// Frames summed and written at a time.
+const CHUNK_FRAMES: usize = 4800;
+/// Decoded blocks a track may queue ahead of the mix.
+const QUEUED_BLOCKS: usize = 8;
+
+type Frame = [f32; 2];
+
+/// Level and position of one track in a mixdown.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct TrackMix {
+    /// Gain in dB; 0 leaves the track as recorded.
+    pub gain_db: f32,
+    /// -1.0 is hard left, 0.0 centre and 1.0 hard right.
+    pub pan: f32,
+    /// Leave the track out of the mix.
+    pub muted: bool,
+}
+
+impl TrackMix {
+    /// Left and right multipliers, panning by balance so a centred track
+    /// keeps its level.
+    fn weights(&self) -> Frame {
+        let gain = 10f32.powf(self.gain_db / 20.0);
+        let pan = self.pan.clamp(-1.0, 1.0);
+        [gain * (1.0 - pan).min(1.0), gain * (1.0 + pan).min(1.0)]
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MixOptions {
+    /// Settings for each track, in the order of the paths; tracks without
+    /// an entry are mixed unchanged.
+    pub tracks: Vec<TrackMix>,
+    /// Bitrate for MP3 and Opus output.
+    pub kbps: Option<u32>,
+}
+
+/// Sum the WAV or FLAC recordings at `paths` into one 48 kHz stereo file at
+/// `output`, whose extension (wav, flac, mp3 or opus) picks the format, and
+/// return the path written. An existing file is never overwritten; a
+/// numbered name is used instead.
+///
+/// Tracks are lined up by their first sample, as bot sessions write them
+/// with speaker track alignment on, and the mix runs as long as the longest.
+///
+/// `progress` is called with frames done and the total, if known. The mix
+/// stops with a "Cancelled" error as soon as `cancelled` returns true,
+/// leaving nothing behind.
+pub fn mix(
+    paths: &[PathBuf],
+    output: &Path,
+    options: &MixOptions,
+    progress: impl Fn(u64, Option<u64>),
+    cancelled: impl Fn() -> bool,
+) -> Result<PathBuf> {
+    if paths.is_empty() {
+        anyhow::bail!("No tracks to mix");
+    }
+    let mut tracks = Vec::with_capacity(paths.len());
+    let mut total = Some(0u64);
+    for (index, path) in paths.iter().enumerate() {
+        let format = recording_format(path)
+            .filter(|format| format == "wav" || format == "flac")
+            .with_context(|| format!("{} isn't a WAV or FLAC recording", path.display()))?;
+        let mix = options.tracks.get(index).copied().unwrap_or_default();
+        if mix.muted {
+            continue;
+        }
+        let frames = probe(path, &format).and_then(|audio| {
+            let frames = frame_count(path, &format)?;
+            Some(frames * MIX_SAMPLE_RATE as u64 / audio.sample_rate.max(1) as u64)
+        });
+        total = total.zip(frames).map(|(total, frames)| total.max(frames));
+        tracks.push((path.as_path(), format, mix.weights()));
+    }
+    if tracks.is_empty() {
+        anyhow::bail!("Every track is muted");
+    }
+
+    let output = unique_path(output);
+    let output_str = output.to_string_lossy().to_string();
+    let encoder = match output.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("wav") => create_encoder(
+            &output_str,
+            MIX_CHANNELS,
+            MIX_SAMPLE_RATE,
+            AudioFormat::Wav,
+            false,
+        ),
+        ext => {
+            let target = ext
+                .and_then(ConvertFormat::from_extension)
+                .context("Mixdowns can be saved as WAV, FLAC, MP3 or Opus")?;
+            create_output(
+                &output_str,
+                target,
+                MIX_CHANNELS,
+                MIX_SAMPLE_RATE,
+                options.kbps,
+            )
+        }
+    }?;
+
+    let result = sum(&tracks, encoder, |done| progress(done, total), &cancelled);
+    if result.is_err() {
+        remove_partial(&output_str);
+    }
+    result?;
+    log::info!("Mixed {} track(s) into {}", tracks.len(), output.display());
+    Ok(output)
+}
+
+/// Decode every track on its own thread and write their weighted sum to
+/// `encoder`, a chunk at a time so no track runs ahead of the others.
+fn sum(
+    tracks: &[(&Path, String, Frame)],
+    mut encoder: Box<dyn AudioEncoder>,
+    progress: impl Fn(u64),
+    cancelled: &impl Fn() -> bool,
+) -> Result<()> {
+    thread::scope(|scope| {
+        let mut feeds: Vec<Feed> = tracks
+            .iter()
+            .map(|(path, format, weights)| {
+                let (sender, receiver) = mpsc::sync_channel(QUEUED_BLOCKS);
+                scope.spawn(move || {
+                    if let Err(e) = decode_track(path, format, *weights, &sender) {
+                        let _ = sender.send(Err(e));
+                    }
+                });
+                Feed {
+                    receiver,
+                    queue: VecDeque::new(),
+                    ended: false,
+                }
+            })
+            .collect();
+
+        let mut done = 0u64;
+        let mut out = Vec::with_capacity(CHUNK_FRAMES * 2);
+        loop {
+            if cancelled() {
+                anyhow::bail!("Cancelled");
+            }
+            for feed in feeds.iter_mut() {
+                feed.fill()?;
+            }
+            let frames = if feeds.iter().any(|feed| !feed.ended) {
+                CHUNK_FRAMES
+            } else {
+                feeds.iter().map(|feed| feed.queue.len()).max().unwrap_or(0)
+            };
+            if frames == 0 {
+                break;
+            }
+
+            out.clear();
+            for _ in 0..frames {
+                let mut mixed = [0f32; 2];
+                for feed in feeds.iter_mut() {
+                    if let Some(frame) = feed.queue.pop_front() {
+                        mixed[0] += frame[0];
+                        mixed[1] += frame[1];
+                    }
+                }
+                out.extend(mixed.map(|sample| sample.clamp(-1.0, 1.0)));
+            }
+            encoder.write_samples(&out)?;
+            done += frames as u64;
+            progress(done);
+        }
+        encoder.finalize()
+    })
+}
+
+/// One track's decoded frames on their way into the mix.
+struct Feed {
+    receiver: mpsc::Receiver<Result<Vec<Frame>>>,
+    queue: VecDeque<Frame>,
+    ended: bool,
+}
+
+impl Feed {
+    /// Wait until a full chunk is queued or the track has ended.
+    fn fill(&mut self) -> Result<()> {
+        while !self.ended && self.queue.len() < CHUNK_FRAMES {
+            match self.receiver.recv() {
+                Ok(block) => self.queue.extend(block?),
+                Err(_) => self.ended = true,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decode `path` to weighted 48 kHz stereo blocks, until the file ends or
+/// the mix stops listening.
+fn decode_track(
+    path: &Path,
+    format: &str,
+    weights: Frame,
+    sender: &mpsc::SyncSender<Result<Vec<Frame>>>,
+) -> Result<()> {
+    let mut resampler: Option<Resampler> = None;
+    decode(path, format, |channels, sample_rate, block| {
+        let resampler = resampler
+            .get_or_insert_with(|| Resampler::new(channels as usize, sample_rate, MIX_SAMPLE_RATE));
+        let mut frames = Vec::with_capacity(block.len() / channels.max(1) as usize + 1);
+        for frame in block.chunks(channels.max(1) as usize) {
+            resampler.push(frame, &mut frames);
+        }
+        for frame in frames.iter_mut() {
+            frame[0] *= weights[0];
+            frame[1] *= weights[1];
+        }
+        Ok(sender.send(Ok(frames)).is_ok())
+    })
+    .with_context(|| format!("Failed to decode {}", path.display()))
+}
@@ -0,0 +1,151 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::discord::names::file_label;
+
+/// How recordings are named unless the user sets a template, which gives
+/// `discord-2024-05-01_203000-guild-channel-speaker`.
+pub const DEFAULT_TEMPLATE: &str = "discord-{timestamp}-{guild}-{channel}-{speaker}";
+const MAX_TEMPLATE_CHARS: usize = 120;
+/// Characters Windows, macOS or Linux refuse in a file name.
+const FORBIDDEN: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+/// Dropped along with a placeholder that is empty, so a local recording
+/// doesn't end in `--`.
+const SEPARATORS: &[char] = &['-', '_', '.', ' '];
+const PLACEHOLDERS: &[&str] = &["timestamp", "date", "time", "guild", "channel", "speaker"];
+
+/// What goes into a recording's name besides its start time. Guild and
+/// channel names are reduced to file-safe labels, the speaker is one
+/// already; unknown ones are left out.
+#[derive(Default, Clone, Copy)]
+pub struct NameParts<'a> {
+    pub guild: Option<&'a str>,
+    pub channel: Option<&'a str>,
+    pub speaker: Option<&'a str>,
+}
+
+/// `template` filled in for a recording started at `started`, without the
+/// extension. A speaker label is appended when the template has no
+/// `{speaker}`, so tracks of one session can't share a name.
+pub fn render(template: &str, started: DateTime<Local>, parts: &NameParts) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('}') else {
+            out.push_str(&rest[open..]);
+            rest = "";
+            break;
+        };
+        let value = match &rest[open + 1..open + close] {
+            "timestamp" => Some(started.format("%Y-%m-%d_%H%M%S").to_string()),
+            "date" => Some(started.format("%Y-%m-%d").to_string()),
+            "time" => Some(started.format("%H%M%S").to_string()),
+            "guild" => parts.guild.and_then(file_label),
+            "channel" => parts.channel.and_then(file_label),
+            "speaker" => parts.speaker.map(str::to_string),
+            _ => None,
+        };
+        match value {
+            Some(value) => out.push_str(&value),
+            None => out.truncate(out.trim_end_matches(SEPARATORS).len()),
+        }
+        rest = &rest[open + close + 1..];
+    }
+    out.push_str(rest);
+    let mut name = out.trim_matches(SEPARATORS).to_string();
+    if !template.contains("{speaker}") {
+        if let Some(speaker) = parts.speaker {
+            name = format!("{}-{}", name, speaker);
+        }
+    }
+    name
+}
+
+/// Check that `template` names every recording uniquely and safely.
+pub fn validate(template: &str) -> Result<(), String> {
+    if template.trim().is_empty() {
+        return Err("Enter a file name template".to_string());
+    }
+    if template.chars().count() > MAX_TEMPLATE_CHARS {
+        return Err(format!(
+            "Keep the template under {} characters",
+            MAX_TEMPLATE_CHARS
+        ));
+    }
+    if let Some(c) = template
+        .chars()
+        .find(|c| FORBIDDEN.contains(c) || c.is_control())
+    {
+        return Err(format!("File names can't contain \"{}\"", c));
+    }
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        if rest[..open].contains('}') {
+            return Err("A \"}\" isn't opened".to_string());
+        }
+        let close = rest[open..]
+            .find('}')
+            .ok_or_else(|| "A \"{\" isn't closed".to_string())?;
+        let name = &rest[open + 1..open + close];
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(format!(
+                "Unknown placeholder {{{}}}; use {}",
+                name,
+                PLACEHOLDERS
+                    .iter()
+                    .map(|p| format!("{{{}}}", p))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        rest = &rest[open + close + 1..];
+    }
+    if rest.contains('}') {
+        return Err("A \"}\" isn't opened".to_string());
+    }
+    let timed = template.contains("{timestamp}")
+        || (template.contains("{date}") && template.contains("{time}"));
+    if !timed {
+        return Err(
+            "Include {timestamp}, or {date} and {time}, so recordings don't overwrite each other"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Example names for a template, shown before it is saved.
+#[derive(Debug, Clone, Serialize)]
+pub struct FilenamePreview {
+    pub template: String,
+    /// A recording of local audio.
+    pub local: String,
+    /// One speaker's track from the bot.
+    pub speaker: String,
+}
+
+pub fn preview(template: &str) -> Result<FilenamePreview, String> {
+    validate(template)?;
+    let now = Local::now();
+    let place = NameParts {
+        guild: Some("My Server"),
+        channel: Some("General"),
+        speaker: None,
+    };
+    Ok(FilenamePreview {
+        template: template.to_string(),
+        local: format!("{}.wav", render(template, now, &NameParts::default())),
+        speaker: format!(
+            "{}.wav",
+            render(
+                template,
+                now,
+                &NameParts {
+                    speaker: Some("alice"),
+                    ..place
+                }
+            )
+        ),
+    })
+}
@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::commands::RecorderState;
+use crate::settings::SettingsState;
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Which recorder an overlay session follows.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverlaySource {
+    Local,
+    Bot,
+}
+
+/// Recording state mirrored into the overlay file for OBS text sources.
+pub struct OverlayState(Mutex<Option<(Instant, OverlaySource)>>);
+
+impl OverlayState {
+    pub fn new() -> Self {
+        Self(Mutex::new(None))
+    }
+
+    pub fn recording_started(&self, source: OverlaySource) {
+        *self.0.lock() = Some((Instant::now(), source));
+    }
+
+    pub fn recording_stopped(&self) {
+        *self.0.lock() = None;
+    }
+}
+
+#[derive(Serialize, PartialEq)]
+struct OverlaySnapshot {
+    recording: bool,
+    source: Option<OverlaySource>,
+    elapsed_secs: u64,
+    /// `HH:MM:SS`, ready to show as-is.
+    elapsed: String,
+}
+
+/// Keep the configured overlay file up to date for the lifetime of the app.
+pub fn spawn_writer<R: Runtime>(app: AppHandle<R>) {
+    std::thread::spawn(move || {
+        let mut last_written: Option<(String, String)> = None;
+        loop {
+            std::thread::sleep(REFRESH_INTERVAL);
+
+            let path = app.state::<SettingsState>().0.lock().overlay_path.clone();
+            let Some(path) = path.filter(|p| !p.is_empty()) else {
+                last_written = None;
+                continue;
+            };
+
+            let snapshot = snapshot(&app);
+            let contents = if is_json(&path) {
+                serde_json::to_string(&snapshot).unwrap_or_default()
+            } else if snapshot.recording {
+                format!("REC {}", snapshot.elapsed)
+            } else {
+                String::new()
+            };
+
+            let current = Some((path, contents));
+            if current == last_written {
+                continue;
+            }
+            let (path, contents) = current.as_ref().unwrap();
+            match write_atomic(Path::new(path), contents) {
+                Ok(()) => last_written = current,
+                Err(e) => {
+                    log::warn!("Failed to write overlay file {}: {}", path, e);
+                    // Retry less eagerly on a broken path
+                    std::thread::sleep(Duration::from_secs(5));
+                }
+            }
+        }
+    });
+}
+
+fn snapshot<R: Runtime>(app: &AppHandle<R>) -> OverlaySnapshot {
+    let overlay = app.state::<OverlayState>();
+    let mut current = overlay.0.lock();
+
+    // Local captures can end on their own (max duration)
+    if let Some((_, OverlaySource::Local)) = *current {
+        let recorder = app.state::<RecorderState>();
+        if !recorder.0.lock().is_recording() && !recorder.1.load(Ordering::Relaxed) {
+            *current = None;
+        }
+    }
+
+    let elapsed_secs = current.map(|(t, _)| t.elapsed().as_secs()).unwrap_or(0);
+    OverlaySnapshot {
+        recording: current.is_some(),
+        source: current.map(|(_, s)| s),
+        elapsed_secs,
+        elapsed: format!(
+            "{:02}:{:02}:{:02}",
+            elapsed_secs / 3600,
+            (elapsed_secs / 60) % 60,
+            elapsed_secs % 60
+        ),
+    }
+}
+
+fn is_json(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .map(|e| e.eq_ignore_ascii_case("json"))
+        .unwrap_or(false)
+}
+
+/// Replace the file in one step so OBS never reads a half-written update.
+fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context("Failed to create overlay directory")?;
+    }
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, contents).context("Failed to write overlay")?;
+    std::fs::rename(&tmp, path).context("Failed to replace overlay")
+}
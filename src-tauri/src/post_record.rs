@@ -0,0 +1,132 @@
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+
+use crate::audio::postprocess;
+use crate::discord::manifest;
+use crate::events::{Recorder, RecordingEvent, StatusHook};
+use crate::settings::SettingsState;
+
+/// Wrap `inner` so empty recordings are deleted when that's turned on, and
+/// every saved recording is normalized when that's turned on, handed to the
+/// configured post-record command, then uploaded.
+pub fn status_hook(app: AppHandle, inner: StatusHook) -> StatusHook {
+    Arc::new(move |source, event| {
+        // Empty files from a failed session go before anything else sees them
+        let event = match event {
+            RecordingEvent::Stopped { reason, paths }
+                if app
+                    .state::<SettingsState>()
+                    .0
+                    .lock()
+                    .delete_empty_recordings =>
+            {
+                RecordingEvent::Stopped {
+                    reason,
+                    paths: crate::empty::remove_from(&app, &paths),
+                }
+            }
+            event => event,
+        };
+        if let RecordingEvent::Stopped { paths, .. } = &event {
+            let (normalize_lufs, command, upload) = {
+                let s = app.state::<SettingsState>();
+                let s = s.0.lock();
+                (
+                    s.normalize_lufs,
+                    s.post_record_command.clone(),
+                    s.upload.target.is_some(),
+                )
+            };
+            if !paths.is_empty() && (normalize_lufs.is_some() || command.is_some() || upload) {
+                let paths = paths.clone();
+                let app = app.clone();
+                std::thread::spawn(move || {
+                    if let Some(target) = normalize_lufs {
+                        normalize(&paths, target);
+                    }
+                    if let Some(command) = command {
+                        run(&command, source, &paths);
+                    }
+                    if upload {
+                        crate::upload::after_record(&app, &paths);
+                    }
+                });
+            }
+        }
+        inner(source, event);
+    })
+}
+
+/// Bring every WAV and FLAC file in `paths` to `target` LUFS, logging the
+/// ones that fail.
+fn normalize(paths: &[String], target: f64) {
+    for path in paths {
+        let path = Path::new(path);
+        if !matches!(
+            crate::library::recording_format(path).as_deref(),
+            Some("wav" | "flac")
+        ) {
+            continue;
+        }
+        if let Err(e) = postprocess::normalize(path, target, |_, _| {}, || false) {
+            log::warn!("Failed to normalize {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Run `program` with the saved files as arguments and the session's
+/// details in `DISCREC_*` environment variables.
+fn run(program: &str, source: Recorder, paths: &[String]) {
+    let mut command = Command::new(program);
+    command.args(paths);
+    for (key, value) in environment(source, paths) {
+        command.env(key, value);
+    }
+    log::info!(
+        "Running post-record command {} on {} file(s)",
+        program,
+        paths.len()
+    );
+    match command.status() {
+        Ok(status) if status.success() => log::info!("Post-record command finished"),
+        Ok(status) => log::warn!("Post-record command exited with {}", status),
+        Err(e) => log::warn!("Failed to run post-record command {}: {}", program, e),
+    }
+}
+
+fn environment(source: Recorder, paths: &[String]) -> Vec<(&'static str, String)> {
+    let source = match source {
+        Recorder::Local => "local",
+        Recorder::Bot => "bot",
+    };
+    let mut env = vec![
+        ("DISCREC_SOURCE", source.to_string()),
+        ("DISCREC_FILE_COUNT", paths.len().to_string()),
+    ];
+    let Some(first) = paths.first().map(Path::new) else {
+        return env;
+    };
+    if let Some(dir) = first.parent() {
+        env.push(("DISCREC_OUTPUT_DIR", dir.to_string_lossy().to_string()));
+    }
+
+    // Bot sessions also describe the channel they recorded
+    let Some(listing) = manifest::for_file(first) else {
+        return env;
+    };
+    let session = listing.session;
+    env.push(("DISCREC_SESSION_MANIFEST", listing.path));
+    env.push(("DISCREC_STARTED_AT", session.started_at));
+    env.push(("DISCREC_ENDED_AT", session.ended_at));
+    env.push(("DISCREC_GUILD_ID", session.channel.guild_id));
+    env.push(("DISCREC_CHANNEL_ID", session.channel.channel_id));
+    if let Some(name) = session.channel.guild_name {
+        env.push(("DISCREC_GUILD_NAME", name));
+    }
+    if let Some(name) = session.channel.channel_name {
+        env.push(("DISCREC_CHANNEL_NAME", name));
+    }
+    env
+}
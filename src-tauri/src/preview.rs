@@ -0,0 +1,157 @@
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::audio::encoder::{create_encoder, AudioEncoder, AudioFormat};
+use crate::audio::mixer::{Mixer, MIX_CHANNELS, MIX_SAMPLE_RATE};
+use crate::library::{decode, recording_format};
+
+/// Length of a preview clip.
+const PREVIEW_SECS: usize = 30;
+/// Peak level previews are normalized to (-1 dBFS).
+const PREVIEW_PEAK: f32 = 0.891;
+
+/// Recordings a preview is currently being made for, so overlapping
+/// requests don't encode the same clip twice.
+static IN_PROGRESS: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// Where the preview of `recording` lives. Keyed on path, size and
+/// modification time, so a changed file gets a fresh preview.
+fn cache_path(recording: &Path) -> Option<PathBuf> {
+    let metadata = std::fs::metadata(recording).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?;
+    let mut hasher = Sha256::new();
+    hasher.update(recording.to_string_lossy().as_bytes());
+    hasher.update(metadata.len().to_le_bytes());
+    hasher.update(modified.as_nanos().to_le_bytes());
+    let key: String = hasher.finalize()[..12]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    Some(
+        crate::data_dir::cache_dir()
+            .join("previews")
+            .join(format!("{}.mp3", key)),
+    )
+}
+
+/// The existing preview of `recording`, if one has been made.
+pub fn cached(recording: &Path) -> Option<PathBuf> {
+    cache_path(recording).filter(|path| path.is_file())
+}
+
+/// Whether a preview can be made for `recording`. MP3 and Opus files are
+/// small enough to play directly.
+pub fn supported(recording: &Path) -> bool {
+    recording_format(recording).is_some_and(|f| f == "wav" || f == "flac")
+}
+
+/// Make the preview of `recording` unless it exists: the loudest
+/// `PREVIEW_SECS` seconds, peak-normalized, as 48 kHz stereo MP3.
+///
+/// Returns `None` if another request is already making it.
+pub fn generate(recording: &Path) -> Result<Option<PathBuf>> {
+    let target = cache_path(recording).context("Recording not found")?;
+    if target.is_file() {
+        return Ok(Some(target));
+    }
+    if !IN_PROGRESS.lock().insert(recording.to_path_buf()) {
+        return Ok(None);
+    }
+    let result = encode_preview(recording, &target);
+    IN_PROGRESS.lock().remove(recording);
+    if result.is_err() {
+        let _ = std::fs::remove_file(&target);
+    }
+    result?;
+    Ok(Some(target))
+}
+
+fn encode_preview(recording: &Path, target: &Path) -> Result<()> {
+    let format = recording_format(recording).context("Not a supported recording format")?;
+
+    // First pass: energy per second, to find the loudest stretch
+    let mut energy: Vec<f64> = Vec::new();
+    let mut window = 0f64;
+    let mut filled = 0usize;
+    decode(recording, &format, |channels, sample_rate, block| {
+        let second = sample_rate as usize * channels as usize;
+        for &sample in block {
+            window += (sample as f64) * (sample as f64);
+            filled += 1;
+            if filled == second {
+                energy.push(window);
+                window = 0.0;
+                filled = 0;
+            }
+        }
+        Ok(true)
+    })?;
+    if filled > 0 {
+        energy.push(window);
+    }
+    let start_secs = loudest_start(&energy, PREVIEW_SECS);
+
+    // Second pass: keep only that stretch
+    let mut clip = Vec::new();
+    let mut layout = None;
+    let mut skipped = 0usize;
+    decode(recording, &format, |channels, sample_rate, block| {
+        let second = sample_rate as usize * channels as usize;
+        layout = Some((channels, sample_rate));
+        let skip = (start_secs * second)
+            .saturating_sub(skipped)
+            .min(block.len());
+        skipped += skip;
+        let wanted = PREVIEW_SECS * second - clip.len();
+        let block = &block[skip..];
+        clip.extend_from_slice(&block[..wanted.min(block.len())]);
+        Ok(clip.len() < PREVIEW_SECS * second)
+    })?;
+    let (channels, sample_rate) = layout.context("Recording is empty")?;
+
+    let peak = clip.iter().fold(0f32, |max, s| max.max(s.abs()));
+    if peak > 0.0 {
+        let gain = PREVIEW_PEAK / peak;
+        clip.iter_mut().for_each(|s| *s *= gain);
+    }
+
+    let target_str = target.to_string_lossy().to_string();
+    let encoder = create_encoder(
+        &target_str,
+        MIX_CHANNELS,
+        MIX_SAMPLE_RATE,
+        AudioFormat::Mp3,
+        false,
+    )?;
+    let mixer = Mixer::new(encoder, 1);
+    let mut input = Box::new(mixer.input(0, channels, sample_rate));
+    input.write_samples(&clip)?;
+    input.finalize()?;
+    mixer.finalize()?;
+    log::info!("Made preview of {}", recording.display());
+    Ok(())
+}
+
+/// Start (in seconds) of the `len`-second stretch with the most energy.
+fn loudest_start(energy: &[f64], len: usize) -> usize {
+    if energy.len() <= len {
+        return 0;
+    }
+    let mut sum: f64 = energy[..len].iter().sum();
+    let (mut best, mut best_start) = (sum, 0);
+    for start in 1..=energy.len() - len {
+        sum += energy[start + len - 1] - energy[start - 1];
+        if sum > best {
+            best = sum;
+            best_start = start;
+        }
+    }
+    best_start
+}
@@ -0,0 +1,83 @@
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+/// Discord IDs (snowflakes) are 17 to 20 digits long.
+const SNOWFLAKE_DIGITS: std::ops::RangeInclusive<usize> = 17..=20;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Redact identifiers in log lines and diagnostics written from now on.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Random per-run salt, so hashed IDs can't be matched against known ones
+/// but stay consistent within one log.
+fn salt() -> &'static [u8; 16] {
+    static SALT: OnceLock<[u8; 16]> = OnceLock::new();
+    SALT.get_or_init(|| {
+        let mut salt = [0u8; 16];
+        if getrandom::getrandom(&mut salt).is_err() {
+            let nanos = chrono::Local::now().timestamp_nanos_opt().unwrap_or(0);
+            salt[..8].copy_from_slice(&nanos.to_le_bytes());
+        }
+        salt
+    })
+}
+
+/// A short salted hash standing in for `id`, e.g. `#3fa2c1`.
+pub fn id(id: impl std::fmt::Display) -> String {
+    let id = id.to_string();
+    if !enabled() {
+        return id;
+    }
+    let digest = Sha256::new()
+        .chain_update(salt())
+        .chain_update(id.as_bytes())
+        .finalize();
+    format!(
+        "#{}",
+        digest[..3]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    )
+}
+
+/// `text` with Discord IDs hashed and the home folder shortened to `~`,
+/// when redaction is on.
+pub fn text(text: &str) -> Cow<'_, str> {
+    if !enabled() {
+        return Cow::Borrowed(text);
+    }
+    let home = dirs::home_dir()
+        .map(|home| home.to_string_lossy().to_string())
+        .filter(|home| home.len() > 1);
+    let text = match &home {
+        Some(home) if text.contains(home.as_str()) => text.replace(home.as_str(), "~"),
+        _ => text.to_string(),
+    };
+
+    let mut redacted = String::with_capacity(text.len());
+    let mut digits = String::new();
+    for c in text.chars().map(Some).chain([None]) {
+        if let Some(digit) = c.filter(char::is_ascii_digit) {
+            digits.push(digit);
+            continue;
+        }
+        if SNOWFLAKE_DIGITS.contains(&digits.len()) {
+            redacted.push_str(&id(&digits));
+        } else {
+            redacted.push_str(&digits);
+        }
+        digits.clear();
+        redacted.extend(c);
+    }
+    Cow::Owned(redacted)
+}
@@ -0,0 +1,247 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+
+use crate::archive::ArchiveState;
+use crate::discord::manifest::{self, SessionInfo};
+use crate::settings::{self, SettingsState};
+
+/// How often the background cleanup runs, the first time shortly after start.
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const FIRST_CLEANUP_DELAY: Duration = Duration::from_secs(5 * 60);
+/// Files written to this recently are left alone, which always covers the
+/// one being recorded.
+const ACTIVE_WINDOW: Duration = Duration::from_secs(10 * 60);
+const BYTES_PER_GB: f64 = 1_000_000_000.0;
+
+/// Limits old recordings are pruned to; either can be off.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Recordings last written longer ago than this are removed.
+    pub max_recording_age_days: Option<u32>,
+    /// The oldest recordings are removed until the rest fit in this.
+    pub max_total_size_gb: Option<f64>,
+}
+
+impl RetentionPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.max_recording_age_days.is_some() || self.max_total_size_gb.is_some()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_recording_age_days == Some(0) {
+            return Err("Keep recordings for at least a day".to_string());
+        }
+        if let Some(gb) = self.max_total_size_gb {
+            if !gb.is_finite() || gb <= 0.0 {
+                return Err("Enter a size above 0 GB".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PruneReason {
+    Age,
+    Size,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PrunedFile {
+    pub path: String,
+    pub size: u64,
+    pub modified: String,
+    pub reason: PruneReason,
+}
+
+/// What a cleanup removed, or with `dry_run` would have.
+#[derive(Debug, Clone, Serialize)]
+pub struct CleanupReport {
+    pub dry_run: bool,
+    /// Recordings, oldest first.
+    pub files: Vec<PrunedFile>,
+    /// Session manifests, timelines, consent and chat logs of bot sessions
+    /// that have no recordings left.
+    pub session_files: Vec<String>,
+    pub freed_bytes: u64,
+    /// Of `freed_bytes`, what deleted recordings took up in the trash.
+    pub trash_bytes: u64,
+    /// Size of the recordings that stay, including the trash.
+    pub remaining_bytes: u64,
+}
+
+struct Candidate {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+    display_modified: String,
+}
+
+/// Prune recordings in `dir` and its non-hidden subfolders by `policy`:
+/// everything older than the age limit, and to fit the size limit first the
+/// trash, which still takes up the space, then the oldest recordings.
+/// Recordings written to in the last few minutes and those in read-only
+/// archives are never touched, and a bot session's manifest and logs go
+/// with the last of its recordings.
+pub fn clean_up(
+    dir: &Path,
+    policy: &RetentionPolicy,
+    archives: &ArchiveState,
+    dry_run: bool,
+) -> Result<CleanupReport> {
+    let now = SystemTime::now();
+    let mut candidates: Vec<Candidate> = crate::library::scan(dir, true)
+        .context("Failed to read recordings folder")?
+        .into_iter()
+        .filter_map(|info| {
+            let modified = std::fs::metadata(&info.path).ok()?.modified().ok()?;
+            Some(Candidate {
+                path: PathBuf::from(&info.path),
+                size: info.size,
+                modified,
+                display_modified: info.modified,
+            })
+        })
+        .collect();
+    candidates.sort_by_key(|c| c.modified);
+
+    let max_age = policy
+        .max_recording_age_days
+        .map(|days| Duration::from_secs(days as u64 * 24 * 60 * 60));
+    let max_bytes = policy
+        .max_total_size_gb
+        .map(|gb| (gb * BYTES_PER_GB) as u64);
+    let trash = crate::trash::list(dir);
+    let mut remaining_bytes: u64 = candidates.iter().map(|c| c.size).sum::<u64>()
+        + trash.iter().map(|entry| entry.size).sum::<u64>();
+
+    // Deleted recordings go first, the longest deleted first
+    let mut trash_bytes = 0;
+    for entry in trash.iter().rev() {
+        if !max_bytes.is_some_and(|max| remaining_bytes > max) {
+            break;
+        }
+        if !dry_run {
+            if let Err(e) = crate::trash::remove(dir, entry) {
+                log::warn!("{:#}", e);
+                continue;
+            }
+        }
+        remaining_bytes -= entry.size;
+        trash_bytes += entry.size;
+    }
+
+    let mut files = Vec::new();
+    for candidate in &candidates {
+        let age = now.duration_since(candidate.modified).unwrap_or_default();
+        if age < ACTIVE_WINDOW || archives.ensure_writable(&candidate.path).is_err() {
+            continue;
+        }
+        let reason = if max_age.is_some_and(|max| age > max) {
+            PruneReason::Age
+        } else if max_bytes.is_some_and(|max| remaining_bytes > max) {
+            PruneReason::Size
+        } else {
+            continue;
+        };
+        if !dry_run {
+            if let Err(e) = std::fs::remove_file(&candidate.path) {
+                log::warn!("Failed to remove {}: {}", candidate.path.display(), e);
+                continue;
+            }
+            let _ = std::fs::remove_file(crate::import::sidecar_path(&candidate.path));
+        }
+        remaining_bytes -= candidate.size;
+        files.push(PrunedFile {
+            path: candidate.path.to_string_lossy().to_string(),
+            size: candidate.size,
+            modified: candidate.display_modified.clone(),
+            reason,
+        });
+    }
+
+    let removed: HashSet<PathBuf> = files.iter().map(|f| PathBuf::from(&f.path)).collect();
+    let mut session_files = Vec::new();
+    for listing in manifest::list(dir).unwrap_or_default() {
+        let manifest_path = PathBuf::from(&listing.path);
+        let Some(session_dir) = manifest_path.parent() else {
+            continue;
+        };
+        let recordings: Vec<PathBuf> = listing
+            .session
+            .recordings()
+            .map(|file| session_dir.join(file))
+            .collect();
+        let pruned = recordings.iter().any(|path| removed.contains(path));
+        let left = recordings
+            .iter()
+            .any(|path| path.exists() && !removed.contains(path));
+        if !pruned || left {
+            continue;
+        }
+        for path in std::iter::once(manifest_path.clone())
+            .chain(logs_of(&listing.session).map(|f| session_dir.join(f)))
+        {
+            if !path.exists() {
+                continue;
+            }
+            if !dry_run {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    log::warn!("Failed to remove {}: {}", path.display(), e);
+                    continue;
+                }
+            }
+            session_files.push(path.to_string_lossy().to_string());
+        }
+    }
+
+    let freed_bytes: u64 = files.iter().map(|f| f.size).sum::<u64>() + trash_bytes;
+    if !dry_run && freed_bytes > 0 {
+        log::info!(
+            "Retention cleanup removed {} recording(s) and emptied {} MB from the trash, {} MB in all",
+            files.len(),
+            trash_bytes / 1_000_000,
+            freed_bytes / 1_000_000
+        );
+    }
+    Ok(CleanupReport {
+        dry_run,
+        files,
+        session_files,
+        freed_bytes,
+        trash_bytes,
+        remaining_bytes,
+    })
+}
+
+fn logs_of(session: &SessionInfo) -> impl Iterator<Item = &str> {
+    session
+        .timeline
+        .as_deref()
+        .into_iter()
+        .chain(session.consent.as_deref())
+        .chain(session.chat.iter().map(String::as_str))
+}
+
+/// Apply the retention policy from settings every hour, while one is set.
+pub fn spawn_cleanup(app: AppHandle) {
+    std::thread::spawn(move || {
+        std::thread::sleep(FIRST_CLEANUP_DELAY);
+        loop {
+            let state = app.state::<SettingsState>();
+            let policy = state.0.lock().retention_policy();
+            if policy.is_enabled() {
+                let dir = settings::recordings_dir(&state);
+                if let Err(e) = clean_up(&dir, &policy, &app.state::<ArchiveState>(), false) {
+                    log::warn!("Retention cleanup failed: {:#}", e);
+                }
+            }
+            std::thread::sleep(CLEANUP_INTERVAL);
+        }
+    });
+}
@@ -0,0 +1,71 @@
+use std::sync::OnceLock;
+
+/// Whether DiscRec runs in a Linux sandbox (Flatpak or Snap), where the host
+/// filesystem and programs like `xdg-open` are out of reach and folders have
+/// to be picked and opened through the XDG desktop portal instead.
+/// `GTK_USE_PORTAL=1` forces portal use outside a sandbox too.
+pub fn is_sandboxed() -> bool {
+    static SANDBOXED: OnceLock<bool> = OnceLock::new();
+    *SANDBOXED.get_or_init(|| {
+        let sandboxed = std::path::Path::new("/.flatpak-info").exists()
+            || std::env::var_os("SNAP").is_some()
+            || std::env::var("GTK_USE_PORTAL").is_ok_and(|v| v == "1");
+        if sandboxed {
+            log::info!("Running sandboxed; using the desktop portal for files");
+        }
+        sandboxed
+    })
+}
+
+/// File chooser and file manager access through the XDG desktop portal.
+pub mod portal {
+    use anyhow::{Context, Result};
+    use ashpd::desktop::file_chooser::SelectedFiles;
+    use ashpd::desktop::open_uri::{OpenDirectoryRequest, OpenFileRequest};
+    use ashpd::desktop::ResponseError;
+    use std::fs::File;
+    use std::path::{Path, PathBuf};
+
+    /// Ask the user for a folder; `None` when they cancel.
+    pub async fn pick_folder(title: &str, current: Option<&Path>) -> Result<Option<PathBuf>> {
+        let request = SelectedFiles::open_file()
+            .title(title)
+            .directory(true)
+            .modal(true)
+            .current_folder::<&Path>(current.filter(|dir| dir.is_dir()))
+            .context("Invalid starting folder")?
+            .send()
+            .await
+            .context("File chooser portal unavailable")?;
+        match request.response() {
+            Ok(selected) => Ok(selected
+                .uris()
+                .first()
+                .and_then(|uri| uri.to_file_path().ok())),
+            Err(ashpd::Error::Response(ResponseError::Cancelled)) => Ok(None),
+            Err(e) => Err(e).context("Failed to pick a folder"),
+        }
+    }
+
+    /// Open `folder` in the file manager.
+    pub fn open_folder(folder: &Path) -> Result<()> {
+        let dir = File::open(folder).context("Failed to open folder")?;
+        tauri::async_runtime::block_on(async {
+            OpenFileRequest::default().send_file(&dir).await?.response()
+        })
+        .context("Failed to open folder through the desktop portal")
+    }
+
+    /// Open the folder holding `file` in the file manager, with the file
+    /// selected where the file manager supports it.
+    pub fn reveal(file: &Path) -> Result<()> {
+        let file = File::open(file).context("Failed to open file")?;
+        tauri::async_runtime::block_on(async {
+            OpenDirectoryRequest::default()
+                .send(&file)
+                .await?
+                .response()
+        })
+        .context("Failed to show file through the desktop portal")
+    }
+}
@@ -0,0 +1,59 @@
+use anyhow::{Context, Result};
+
+use crate::data_dir::os_user;
+
+const KEYRING_SERVICE: &str = "com.discrec.app";
+
+/// Keyring entry for `account`, namespaced by OS user so people sharing a
+/// machine, or a keychain, never read each other's secrets.
+fn entry(account: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, &format!("{}@{}", account, os_user()))
+        .context("Failed to access keyring")
+}
+
+/// The entry secrets were kept under before they were namespaced.
+fn legacy_entry(account: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, account).context("Failed to access keyring")
+}
+
+/// The secret stored for `account`, if any. One kept under the old shared
+/// name is moved to this user's on first read.
+pub fn get(account: &str) -> Result<Option<String>> {
+    match entry(account)?.get_password() {
+        Ok(secret) => return Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => {}
+        Err(e) => anyhow::bail!("{}", e),
+    }
+    let legacy = legacy_entry(account)?;
+    let secret = match legacy.get_password() {
+        Ok(secret) => secret,
+        Err(keyring::Error::NoEntry) => return Ok(None),
+        Err(e) => anyhow::bail!("{}", e),
+    };
+    set(account, &secret)?;
+    if let Err(e) = legacy.delete_credential() {
+        log::warn!(
+            "Failed to remove {} from the shared keyring entry: {}",
+            account,
+            e
+        );
+    }
+    log::info!("Moved {} to a keyring entry for this user", account);
+    Ok(Some(secret))
+}
+
+pub fn set(account: &str, secret: &str) -> Result<()> {
+    entry(account)?.set_password(secret)?;
+    Ok(())
+}
+
+/// Remove the secret for `account`, including any left under the old name.
+pub fn delete(account: &str) -> Result<()> {
+    for entry in [entry(account)?, legacy_entry(account)?] {
+        match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => anyhow::bail!("{}", e),
+        }
+    }
+    Ok(())
+}
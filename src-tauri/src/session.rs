@@ -0,0 +1,364 @@
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+
+use crate::audio::capture::RoutingReport;
+use crate::audio::devices::DeviceChoice;
+use crate::audio::encoder::{encode_spill, SPILL_INFO_SUFFIX};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Hidden folder inside the recordings directory holding in-progress sessions.
+pub const SESSIONS_DIR: &str = ".sessions";
+const MANIFEST_FILE: &str = "manifest.json";
+/// Error given to sessions a killed or crashed run left recording.
+const INTERRUPTED: &str = "Interrupted: DiscRec closed before the recording was finalized";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStatus {
+    Recording,
+    Committed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionManifest {
+    pub id: String,
+    pub kind: String,
+    pub started_at: String,
+    #[serde(default)]
+    pub finished_at: Option<String>,
+    pub status: SessionStatus,
+    /// File names inside the session directory, in creation order.
+    #[serde(default)]
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// The system capture device recorded from, when one was picked in
+    /// settings, and whether a fallback replaced it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capture_device: Option<DeviceChoice>,
+    /// How Discord's audio was routed for capture on Linux, and whether it
+    /// fell back to all system audio.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing: Option<RoutingReport>,
+    /// Process recording the session, to tell a live session from one a
+    /// crash left behind.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+}
+
+/// A recording run's private working directory.
+///
+/// Encoders write into `<library>/.sessions/<id>/`; files only move into the
+/// library once the whole session finalized successfully. Failed sessions stay
+/// on disk with their manifest so they can be inspected or recovered, and so
+/// do sessions a crash interrupted, once [`recover_interrupted`] finds them.
+pub struct RecordingSession {
+    library_dir: PathBuf,
+    work_dir: PathBuf,
+    manifest: Mutex<SessionManifest>,
+}
+
+impl RecordingSession {
+    pub fn create(library_dir: &Path, kind: &str) -> Result<Self> {
+        let now = chrono::Local::now();
+        let id = format!("{}-{}", kind, now.format("%Y%m%d-%H%M%S%3f"));
+        let work_dir = library_dir.join(SESSIONS_DIR).join(&id);
+        std::fs::create_dir_all(&work_dir).context("Failed to create session directory")?;
+
+        let session = Self {
+            library_dir: library_dir.to_path_buf(),
+            work_dir,
+            manifest: Mutex::new(SessionManifest {
+                id,
+                kind: kind.to_string(),
+                started_at: now.to_rfc3339(),
+                finished_at: None,
+                status: SessionStatus::Recording,
+                files: Vec::new(),
+                error: None,
+                capture_device: None,
+                routing: None,
+                pid: Some(std::process::id()),
+            }),
+        };
+        session.save_manifest()?;
+        log::info!("Session started: {}", session.work_dir.display());
+        Ok(session)
+    }
+
+    /// Reopen a session left on disk, e.g. to retry a failed finalize.
+    pub fn open(library_dir: &Path, id: &str) -> Result<Self> {
+        let work_dir = library_dir.join(SESSIONS_DIR).join(id);
+        let data = std::fs::read_to_string(work_dir.join(MANIFEST_FILE))
+            .context("Session manifest not found")?;
+        let manifest: SessionManifest =
+            serde_json::from_str(&data).context("Invalid session manifest")?;
+        Ok(Self {
+            library_dir: library_dir.to_path_buf(),
+            work_dir,
+            manifest: Mutex::new(manifest),
+        })
+    }
+
+    pub fn id(&self) -> String {
+        self.manifest.lock().id.clone()
+    }
+
+    pub fn work_dir(&self) -> &Path {
+        &self.work_dir
+    }
+
+    pub fn set_capture_device(&self, device: DeviceChoice) -> Result<()> {
+        self.manifest.lock().capture_device = Some(device);
+        self.save_manifest()
+    }
+
+    pub fn set_routing(&self, report: RoutingReport) -> Result<()> {
+        self.manifest.lock().routing = Some(report);
+        self.save_manifest()
+    }
+
+    /// Register a new artifact and return where it should be written.
+    pub fn work_path(&self, filename: &str) -> Result<PathBuf> {
+        self.manifest.lock().files.push(filename.to_string());
+        self.save_manifest()?;
+        Ok(self.work_dir.join(filename))
+    }
+
+    /// Whether an artifact called `filename` is already registered.
+    pub fn has_file(&self, filename: &str) -> bool {
+        self.manifest.lock().files.iter().any(|f| f == filename)
+    }
+
+    /// Rename a finished artifact inside the working directory.
+    pub fn rename_file(&self, from: &str, to: &str) -> Result<()> {
+        if self.has_file(to) {
+            anyhow::bail!("{} already exists in the session", to);
+        }
+        std::fs::rename(self.work_dir.join(from), self.work_dir.join(to))
+            .with_context(|| format!("Failed to rename {} to {}", from, to))?;
+        {
+            let mut manifest = self.manifest.lock();
+            if let Some(name) = manifest.files.iter_mut().find(|f| *f == from) {
+                *name = to.to_string();
+            }
+        }
+        self.save_manifest()
+    }
+
+    /// Move every artifact into the library and remove the working directory.
+    ///
+    /// Returns the final library paths in creation order.
+    pub fn commit(&self) -> Result<Vec<String>> {
+        let files = self.manifest.lock().files.clone();
+        let mut committed = Vec::new();
+
+        for name in &files {
+            let from = self.work_dir.join(name);
+            if !from.exists() {
+                // Registered but never written (e.g. encoder failed to open)
+                continue;
+            }
+            // Never over a recording that already has the name
+            let to = crate::import::unique_path(&self.library_dir.join(name));
+            if let Err(e) = move_file(&from, &to) {
+                let err = format!("Failed to move {} into library: {}", name, e);
+                self.mark_failed(&err);
+                anyhow::bail!(err);
+            }
+            committed.push(to.to_string_lossy().to_string());
+        }
+
+        {
+            let mut manifest = self.manifest.lock();
+            manifest.status = SessionStatus::Committed;
+            manifest.finished_at = Some(chrono::Local::now().to_rfc3339());
+        }
+        let _ = std::fs::remove_dir_all(&self.work_dir);
+        // Session folders would each keep an empty sessions folder otherwise
+        if let Some(sessions_dir) = self.work_dir.parent() {
+            let _ = std::fs::remove_dir(sessions_dir);
+        }
+        log::info!(
+            "Session {} committed: {} file(s)",
+            self.id(),
+            committed.len()
+        );
+        Ok(committed)
+    }
+
+    /// Record a failure in the manifest and keep the artifacts for recovery.
+    pub fn mark_failed(&self, error: &str) {
+        {
+            let mut manifest = self.manifest.lock();
+            manifest.status = SessionStatus::Failed;
+            manifest.finished_at = Some(chrono::Local::now().to_rfc3339());
+            manifest.error = Some(error.to_string());
+        }
+        let _ = self.save_manifest();
+        log::warn!(
+            "Session {} failed, artifacts kept in {}: {}",
+            self.id(),
+            self.work_dir.display(),
+            error
+        );
+    }
+
+    /// Delete the working directory and everything recorded into it.
+    pub fn discard(&self) {
+        match std::fs::remove_dir_all(&self.work_dir) {
+            Ok(()) => log::info!("Session {} discarded", self.id()),
+            Err(e) => log::warn!(
+                "Failed to delete {} of discarded session: {}",
+                self.work_dir.display(),
+                e
+            ),
+        }
+    }
+
+    /// Re-encode every spill kept by failed encoders, then commit the session.
+    pub fn retry_finalize(&self) -> Result<Vec<String>> {
+        let spills: Vec<PathBuf> = std::fs::read_dir(&self.work_dir)
+            .context("Failed to read session directory")?
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.to_string_lossy().ends_with(SPILL_INFO_SUFFIX))
+            .collect();
+
+        for info in &spills {
+            if let Err(e) = encode_spill(info) {
+                let err = format!("Retry failed for {}: {}", info.display(), e);
+                self.mark_failed(&err);
+                anyhow::bail!(err);
+            }
+        }
+        {
+            let mut manifest = self.manifest.lock();
+            manifest.error = None;
+        }
+        self.commit()
+    }
+
+    fn save_manifest(&self) -> Result<()> {
+        let data = serde_json::to_string_pretty(&*self.manifest.lock())?;
+        std::fs::write(self.work_dir.join(MANIFEST_FILE), data)
+            .context("Failed to write session manifest")
+    }
+}
+
+/// Whether `path` is inside a session's working directory, i.e. a file
+/// being recorded rather than converted or mixed.
+pub fn is_session_path(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .any(|c| c.as_os_str() == SESSIONS_DIR)
+}
+
+/// The library and its non-hidden subfolders at any depth, each of which
+/// may hold a sessions folder since output routes and session folders start
+/// sessions below the library.
+fn session_roots(library_dir: &Path) -> Vec<PathBuf> {
+    let mut roots = vec![library_dir.to_path_buf()];
+    if let Ok(entries) = std::fs::read_dir(library_dir) {
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|t| t.is_dir())
+                && !entry.file_name().to_string_lossy().starts_with('.')
+            {
+                roots.extend(session_roots(&entry.path()));
+            }
+        }
+    }
+    roots
+}
+
+/// The folder whose sessions folder holds session `id`.
+pub fn find_session(library_dir: &Path, id: &str) -> Option<PathBuf> {
+    session_roots(library_dir)
+        .into_iter()
+        .find(|root| root.join(SESSIONS_DIR).join(id).is_dir())
+}
+
+/// Every session manifest below the library, with the folder whose
+/// sessions folder holds it.
+fn manifests(library_dir: &Path) -> Vec<(PathBuf, SessionManifest)> {
+    session_roots(library_dir)
+        .into_iter()
+        .filter_map(|root| {
+            let entries = std::fs::read_dir(root.join(SESSIONS_DIR)).ok()?;
+            Some((root, entries))
+        })
+        .flat_map(|(root, entries)| {
+            entries.flatten().filter_map(move |e| {
+                let data = std::fs::read_to_string(e.path().join(MANIFEST_FILE)).ok()?;
+                let manifest = serde_json::from_str::<SessionManifest>(&data).ok()?;
+                Some((root.clone(), manifest))
+            })
+        })
+        .collect()
+}
+
+/// Manifests of sessions that failed to finalize, newest first.
+pub fn failed_sessions(library_dir: &Path) -> Vec<SessionManifest> {
+    let mut failed: Vec<SessionManifest> = manifests(library_dir)
+        .into_iter()
+        .map(|(_, manifest)| manifest)
+        .filter(|m| m.status == SessionStatus::Failed)
+        .collect();
+    failed.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+    failed
+}
+
+/// Mark sessions still recording without a DiscRec process behind them,
+/// left by a run that was killed or crashed, as failed, so they are listed
+/// with the other failed sessions and `retry_finalize` can recover their
+/// audio. Returns how many were found.
+pub fn recover_interrupted(library_dir: &Path) -> usize {
+    let running = running_instances();
+    let mut interrupted = 0;
+    for (root, manifest) in manifests(library_dir) {
+        if manifest.status != SessionStatus::Recording
+            || manifest.pid.is_some_and(|pid| running.contains(&pid))
+        {
+            continue;
+        }
+        match RecordingSession::open(&root, &manifest.id) {
+            Ok(session) => {
+                session.mark_failed(INTERRUPTED);
+                interrupted += 1;
+            }
+            Err(e) => log::warn!("Failed to reopen session {}: {:#}", manifest.id, e),
+        }
+    }
+    interrupted
+}
+
+/// Process ids of the running DiscRec instances, this one included, any of
+/// which may be recording.
+fn running_instances() -> Vec<u32> {
+    use sysinfo::{ProcessRefreshKind, RefreshKind, System};
+
+    let own = std::process::id();
+    let refreshes = RefreshKind::nothing().with_processes(ProcessRefreshKind::nothing());
+    let system = System::new_with_specifics(refreshes);
+    let Some(name) = system
+        .process(sysinfo::Pid::from_u32(own))
+        .map(|process| process.name().to_os_string())
+    else {
+        return vec![own];
+    };
+    system
+        .processes_by_exact_name(&name)
+        .map(|process| process.pid().as_u32())
+        .collect()
+}
+
+/// Rename, falling back to copy + delete when crossing filesystems.
+pub(crate) fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to)?;
+    std::fs::remove_file(from)
+}
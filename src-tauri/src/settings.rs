@@ -1,6 +1,18 @@
+use crate::audio::capture::RoutedApp;
+use crate::audio::devices::{DeviceFallback, DevicePreference};
+use crate::audio::encoder::{AudioFormat, WavBitDepth};
+use crate::audio::source::{default_sources, CaptureSource};
+use crate::audio::worker::EncoderLimits;
+use crate::discord::bot::SessionNotices;
+use crate::discord::packets::VoiceDecodeMode;
+use crate::discord::receiver::{BotAudio, RecordedUsers};
+use crate::naming::{self, NameParts};
+use crate::retention::RetentionPolicy;
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShortcutConfig {
@@ -26,18 +38,473 @@ impl Default for ShortcutConfig {
     }
 }
 
+/// Sends recordings started at certain times or in a certain guild into a
+/// subfolder of the library, e.g. Tuesdays to `Campaign A`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputRoute {
+    /// Subfolder of the recordings directory.
+    pub folder: String,
+    /// Days the rule applies on (`"mon"` … `"sun"`); empty means every day.
+    #[serde(default)]
+    pub weekdays: Vec<String>,
+    /// Local hours `start_hour..end_hour` the rule applies in; wraps past
+    /// midnight when the end is before the start. Either bound may be left out.
+    #[serde(default)]
+    pub start_hour: Option<u8>,
+    #[serde(default)]
+    pub end_hour: Option<u8>,
+    /// Only sessions recording this guild through the bot.
+    #[serde(default)]
+    pub guild_id: Option<String>,
+}
+
+impl OutputRoute {
+    /// Check the rule can be applied before it is saved.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut components = Path::new(&self.folder).components();
+        if !matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        ) || self.folder.starts_with('.')
+        {
+            return Err(format!("'{}' is not a plain folder name", self.folder));
+        }
+        if let Some(day) = self
+            .weekdays
+            .iter()
+            .find(|d| chrono::Weekday::from_str(d).is_err())
+        {
+            return Err(format!("Unknown weekday '{}'", day));
+        }
+        if self
+            .start_hour
+            .into_iter()
+            .chain(self.end_hour)
+            .any(|h| h > 24)
+        {
+            return Err("Hours must be between 0 and 24".to_string());
+        }
+        if let Some(guild) = &self.guild_id {
+            guild.parse::<u64>().map_err(|_| "Invalid guild ID")?;
+        }
+        Ok(())
+    }
+
+    fn matches(&self, now: &chrono::DateTime<chrono::Local>, guild_id: Option<u64>) -> bool {
+        use chrono::{Datelike, Timelike};
+
+        let day_ok = self.weekdays.is_empty()
+            || self
+                .weekdays
+                .iter()
+                .any(|d| chrono::Weekday::from_str(d).ok() == Some(now.weekday()));
+        let hour = now.hour() as u8;
+        let hour_ok = match (self.start_hour, self.end_hour) {
+            (None, None) => true,
+            (Some(start), None) => hour >= start,
+            (None, Some(end)) => hour < end,
+            (Some(start), Some(end)) if start <= end => (start..end).contains(&hour),
+            (Some(start), Some(end)) => hour >= start || hour < end,
+        };
+        let guild_ok = match &self.guild_id {
+            None => true,
+            Some(id) => guild_id.map(|g| g.to_string()).as_ref() == Some(id),
+        };
+        day_ok && hour_ok && guild_ok
+    }
+}
+
+/// A voice channel the bot records by itself once enough people are in it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchedChannel {
+    pub guild_id: String,
+    pub channel_id: String,
+    /// People (not counting bots) needed to start recording.
+    #[serde(default = "default_min_members")]
+    pub min_members: u32,
+    #[serde(default = "default_watch_format")]
+    pub format: AudioFormat,
+}
+
+fn default_min_members() -> u32 {
+    1
+}
+fn default_watch_format() -> AudioFormat {
+    AudioFormat::Wav
+}
+
+impl WatchedChannel {
+    pub fn validate(&self) -> Result<(), String> {
+        self.guild_id
+            .parse::<u64>()
+            .map_err(|_| "Invalid guild ID")?;
+        self.channel_id
+            .parse::<u64>()
+            .map_err(|_| "Invalid channel ID")?;
+        if self.min_members == 0 {
+            return Err("At least one member is needed to start".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Which OBS output DiscRec follows and drives.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObsOutput {
+    #[default]
+    Record,
+    Stream,
+    /// Follow whichever of the two is running; only ever drives recording.
+    Either,
+}
+
+/// Keeps DiscRec recordings in step with OBS through obs-websocket. The
+/// password lives in the OS keyring.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObsSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_obs_host")]
+    pub host: String,
+    #[serde(default = "default_obs_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub output: ObsOutput,
+    /// Start and stop DiscRec when OBS does.
+    #[serde(default)]
+    pub follow_obs: bool,
+    /// Start and stop OBS when DiscRec does.
+    #[serde(default)]
+    pub drive_obs: bool,
+}
+
+fn default_obs_host() -> String {
+    "127.0.0.1".to_string()
+}
+fn default_obs_port() -> u16 {
+    4455
+}
+
+impl Default for ObsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: default_obs_host(),
+            port: default_obs_port(),
+            output: ObsOutput::default(),
+            follow_obs: false,
+            drive_obs: false,
+        }
+    }
+}
+
+/// Where saved recordings are uploaded automatically.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadTarget {
+    GoogleDrive,
+    #[serde(rename = "webdav")]
+    WebDav,
+}
+
+/// Uploads every saved recording once it is finalized. Credentials live in
+/// the OS keyring.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadSettings {
+    /// `None` keeps uploads off.
+    #[serde(default)]
+    pub target: Option<UploadTarget>,
+    /// Also upload bot sessions' manifests, so the stems can be told apart.
+    #[serde(default = "default_true")]
+    pub include_manifests: bool,
+    #[serde(default)]
+    pub google_drive: GoogleDriveSettings,
+    #[serde(default)]
+    pub webdav: WebDavSettings,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for UploadSettings {
+    fn default() -> Self {
+        Self {
+            target: None,
+            include_manifests: true,
+            google_drive: GoogleDriveSettings::default(),
+            webdav: WebDavSettings::default(),
+        }
+    }
+}
+
+/// Signing in goes through the OAuth device flow with the user's own OAuth
+/// client; the refresh token lives in the OS keyring.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GoogleDriveSettings {
+    /// OAuth client of the "TVs and Limited Input devices" type.
+    #[serde(default)]
+    pub client_id: String,
+    /// Secret of `client_id`, which Google doesn't treat as confidential for
+    /// this client type.
+    #[serde(default)]
+    pub client_secret: String,
+    /// Drive folder ID uploads go to; `None` puts them in My Drive.
+    #[serde(default)]
+    pub folder_id: Option<String>,
+}
+
+/// A WebDAV folder, e.g. on Nextcloud. The (app) password lives in the OS
+/// keyring.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebDavSettings {
+    /// Folder uploads go to, e.g.
+    /// `https://cloud.example.com/remote.php/dav/files/alice/DiscRec`.
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub username: String,
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AppSettings {
     #[serde(default)]
     pub output_dir: Option<String>,
     #[serde(default)]
     pub silence_trim: bool,
+    /// Also record the default microphone into its own `-mic` file.
+    #[serde(default)]
+    pub record_mic: bool,
+    /// Mix all local sources (e.g. Discord + mic) into one stereo file.
+    #[serde(default)]
+    pub mix_local_sources: bool,
+    /// Device recorded as system audio, by name; `None` picks one automatically.
+    #[serde(default)]
+    pub capture_device: Option<String>,
+    /// Stable ID of `capture_device`; unset for devices picked before IDs
+    /// were kept, which are found by name.
+    #[serde(default)]
+    pub capture_device_id: Option<String>,
+    /// What to record when `capture_device` isn't connected.
+    #[serde(default)]
+    pub capture_device_fallback: DeviceFallback,
+    /// Other apps routed into the recording along with Discord on Linux.
+    #[serde(default)]
+    pub routed_apps: Vec<RoutedApp>,
     #[serde(default)]
     pub max_duration_secs: Option<u32>,
+    /// Split recordings into a new `-partNN` file every this many minutes.
+    #[serde(default)]
+    pub segment_minutes: Option<u32>,
+    /// Format of recordings started without one, e.g. from the tray.
+    #[serde(default)]
+    pub default_format: AudioFormat,
+    /// Sample format of WAV recordings.
+    #[serde(default)]
+    pub wav_bit_depth: WavBitDepth,
+    /// Flush recordings to disk every this many seconds; unset leaves it to
+    /// the end of the recording.
+    #[serde(default)]
+    pub flush_interval_secs: Option<u32>,
     #[serde(default)]
     pub shortcuts: ShortcutConfig,
     #[serde(default)]
     pub notify_on_record: bool,
+    /// DM everyone recorded by the bot when the session ends.
+    #[serde(default)]
+    pub notify_participants: bool,
+    /// Ask voice channel members to react before the bot records them.
+    #[serde(default)]
+    pub ask_consent: bool,
+    /// Turn on the OS do-not-disturb mode while recording.
+    #[serde(default)]
+    pub dnd_while_recording: bool,
+    #[serde(default)]
+    pub voice_decode_mode: VoiceDecodeMode,
+    /// Text or `.json` file kept up to date with the recording state for OBS.
+    #[serde(default)]
+    pub overlay_path: Option<String>,
+    /// Pad bot speaker tracks with silence so they stay time-aligned.
+    #[serde(default)]
+    pub align_speaker_tracks: bool,
+    /// Local port of the live speaking WebSocket feed; `None` keeps it off.
+    #[serde(default)]
+    pub speaking_feed_port: Option<u16>,
+    /// Users left out of bot recordings, by guild, until they opt back in.
+    #[serde(default)]
+    pub excluded_users: BTreeMap<u64, BTreeSet<u64>>,
+    /// Guilds where only the picked users are recorded, with those users.
+    #[serde(default)]
+    pub recorded_only: BTreeMap<u64, BTreeSet<u64>>,
+    /// What bot recordings do with other bots' audio, e.g. music bots.
+    #[serde(default)]
+    pub bot_audio: BotAudio,
+    /// Save the recorded voice channel's text chat with bot sessions.
+    #[serde(default)]
+    pub record_chat: bool,
+    /// Guilds whose server-muted members aren't written while muted.
+    #[serde(default)]
+    pub skip_server_muted: BTreeSet<u64>,
+    /// Bot speaker tracks with less audible audio than this are discarded.
+    #[serde(default)]
+    pub min_speaker_secs: Option<u32>,
+    /// Encoder worker threads; `None` uses the default, 0 encodes on the capture threads.
+    #[serde(default)]
+    pub encoder_threads: Option<u32>,
+    /// Cap on audio held in memory while encoding, in MiB.
+    #[serde(default)]
+    pub encoder_buffer_mb: Option<u32>,
+    /// Subfolder rules checked in order when a recording starts.
+    #[serde(default)]
+    pub output_routes: Vec<OutputRoute>,
+    /// Voice channels recorded automatically while people are in them.
+    #[serde(default)]
+    pub watched_channels: Vec<WatchedChannel>,
+    #[serde(default)]
+    pub obs: ObsSettings,
+    #[serde(default)]
+    pub upload: UploadSettings,
+    /// Debug: log every received voice tick to a dump capped at this many
+    /// MiB; `None` keeps it off.
+    #[serde(default)]
+    pub tick_dump_mb: Option<u32>,
+    /// Hash Discord IDs and shorten paths in logs and debug dumps, so they
+    /// can be shared publicly.
+    #[serde(default)]
+    pub redact_logs: bool,
+    /// Program run with the file paths of every saved recording.
+    #[serde(default)]
+    pub post_record_command: Option<String>,
+    /// Loudness in LUFS every saved WAV or FLAC recording is normalized to;
+    /// `None` keeps it off.
+    #[serde(default)]
+    pub normalize_lufs: Option<f64>,
+    /// Recordings older than this many days are removed automatically.
+    #[serde(default)]
+    pub max_recording_age_days: Option<u32>,
+    /// The oldest recordings are removed automatically once all of them
+    /// take up more than this many GB.
+    #[serde(default)]
+    pub max_total_size_gb: Option<f64>,
+    /// Free disk space in MiB needed to start recording; a quarter of it
+    /// stops a running one. `None` uses the default, 0 turns the check off.
+    #[serde(default)]
+    pub min_free_space_mb: Option<u32>,
+    /// Delete recordings without any audio, as failed sessions leave, at
+    /// startup and when a recording stops.
+    #[serde(default)]
+    pub delete_empty_recordings: bool,
+    /// How recordings are named, see [`crate::naming`]; `None` keeps the
+    /// default.
+    #[serde(default)]
+    pub filename_template: Option<String>,
+    /// Save each session in a `<date>/<session>/` folder instead of
+    /// straight into the recordings directory.
+    #[serde(default)]
+    pub session_folders: bool,
+}
+
+impl AppSettings {
+    /// Sources recorded when a start request doesn't name any.
+    pub fn default_sources(&self) -> Vec<CaptureSource> {
+        let mut sources = default_sources();
+        if self.record_mic {
+            sources.push(CaptureSource::Mic);
+        }
+        sources
+    }
+
+    /// The capture device picked for system audio, if any.
+    pub fn capture_device(&self) -> Option<DevicePreference> {
+        let name = self.capture_device.clone()?;
+        Some(DevicePreference {
+            id: self
+                .capture_device_id
+                .clone()
+                .unwrap_or_else(|| name.clone()),
+            name,
+        })
+    }
+
+    /// Users not recorded in `guild_id`.
+    /// Who bot recordings in `guild_id` may record.
+    pub fn recorded_users(&self, guild_id: u64) -> RecordedUsers {
+        RecordedUsers {
+            excluded: self
+                .excluded_users
+                .get(&guild_id)
+                .map(|users| users.iter().copied().collect())
+                .unwrap_or_default(),
+            only: self
+                .recorded_only
+                .get(&guild_id)
+                .map(|users| users.iter().copied().collect()),
+        }
+    }
+
+    pub fn set_user_excluded(&mut self, guild_id: u64, user_id: u64, excluded: bool) {
+        let users = self.excluded_users.entry(guild_id).or_default();
+        if excluded {
+            users.insert(user_id);
+        } else {
+            users.remove(&user_id);
+            if users.is_empty() {
+                self.excluded_users.remove(&guild_id);
+            }
+        }
+    }
+
+    /// Subfolder of the first route matching a session starting now.
+    pub fn route_folder(&self, guild_id: Option<u64>) -> Option<String> {
+        let now = chrono::Local::now();
+        self.output_routes
+            .iter()
+            .find(|route| route.matches(&now, guild_id))
+            .map(|route| route.folder.clone())
+    }
+
+    /// The watch rule for a channel, if it is watched.
+    pub fn watched_channel(&self, guild_id: u64, channel_id: u64) -> Option<&WatchedChannel> {
+        self.watched_channels
+            .iter()
+            .find(|w| w.guild_id == guild_id.to_string() && w.channel_id == channel_id.to_string())
+    }
+
+    pub fn session_notices(&self) -> SessionNotices {
+        SessionNotices {
+            channel: self.notify_on_record,
+            participants: self.notify_participants,
+            consent: self.ask_consent,
+        }
+    }
+
+    pub fn encoder_limits(&self) -> EncoderLimits {
+        let defaults = EncoderLimits::default();
+        EncoderLimits {
+            threads: self.encoder_threads.unwrap_or(defaults.threads),
+            max_buffer_mb: self.encoder_buffer_mb.unwrap_or(defaults.max_buffer_mb),
+        }
+        .clamped()
+    }
+
+    pub fn min_free_space_mb(&self) -> u32 {
+        self.min_free_space_mb
+            .unwrap_or(crate::storage::DEFAULT_MIN_FREE_MB)
+    }
+
+    pub fn filename_template(&self) -> &str {
+        self.filename_template
+            .as_deref()
+            .unwrap_or(crate::naming::DEFAULT_TEMPLATE)
+    }
+
+    pub fn retention_policy(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            max_recording_age_days: self.max_recording_age_days,
+            max_total_size_gb: self.max_total_size_gb,
+        }
+    }
 }
 
 pub struct SettingsState(pub Mutex<AppSettings>);
@@ -49,10 +516,7 @@ impl SettingsState {
     }
 
     fn config_path() -> PathBuf {
-        dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("DiscRec")
-            .join("settings.json")
+        crate::data_dir::config_dir().join("settings.json")
     }
 
     fn read_from_disk() -> Option<AppSettings> {
@@ -74,6 +538,15 @@ impl SettingsState {
     }
 }
 
+/// Hand `settings` to the modules that keep their own process-wide copy,
+/// at startup and whenever the whole settings are replaced.
+pub fn apply_runtime_settings(settings: &AppSettings) {
+    crate::redact::set_enabled(settings.redact_logs);
+    crate::audio::worker::configure(settings.encoder_limits());
+    crate::audio::encoder::set_wav_bit_depth(settings.wav_bit_depth);
+    crate::audio::encoder::set_flush_interval(settings.flush_interval_secs);
+}
+
 /// Returns the effective recordings directory — custom if set, otherwise default.
 pub fn recordings_dir(settings: &SettingsState) -> PathBuf {
     let s = settings.0.lock();
@@ -86,9 +559,115 @@ pub fn recordings_dir(settings: &SettingsState) -> PathBuf {
     default_recordings_dir()
 }
 
+/// Where a session starting at `started` saves its files: the recordings
+/// directory, or the subfolder of the first output route that matches, and
+/// within that its own `<date>/<session>/` folder when session folders are on.
+/// `guild_id` is the guild the bot records, if any.
+pub fn session_dir(
+    settings: &SettingsState,
+    guild_id: Option<u64>,
+    started: chrono::DateTime<chrono::Local>,
+) -> PathBuf {
+    let dir = recordings_dir(settings);
+    let (route, session) = {
+        let s = settings.0.lock();
+        let session = s.session_folders.then(|| session_folder(&s, started));
+        (s.route_folder(guild_id), session)
+    };
+    let mut folder = PathBuf::new();
+    if let Some(route) = &route {
+        folder.push(route);
+    }
+    if let Some(session) = session {
+        folder.push(session);
+    }
+    if folder.as_os_str().is_empty() {
+        return dir;
+    }
+    let target = dir.join(&folder);
+    match std::fs::create_dir_all(&target) {
+        Ok(()) => {
+            if let Some(route) = route {
+                log::info!("Routing recording to {}", route);
+            }
+            target
+        }
+        Err(e) => {
+            log::warn!("Failed to create {}: {}", target.display(), e);
+            dir
+        }
+    }
+}
+
+/// `<date>/<session>` for a session starting at `started`, named by the file
+/// name template without guild, channel or speaker.
+fn session_folder(settings: &AppSettings, started: chrono::DateTime<chrono::Local>) -> PathBuf {
+    let name = naming::render(settings.filename_template(), started, &NameParts::default());
+    Path::new(&started.format("%Y-%m-%d").to_string()).join(name)
+}
+
+/// Sent at startup when recordings default to a fallback folder because the
+/// Music folder isn't available.
+pub const DEFAULT_DIR_FALLBACK: &str = "settings://default-dir-fallback";
+
+/// Which folder the default recordings directory was placed in, from most to
+/// least preferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultDirBase {
+    /// The user's Music folder, localized (e.g. `~/Musik` via XDG user dirs).
+    Music,
+    Documents,
+    Home,
+    /// The app data folder, which sandboxes like Flatpak always allow.
+    AppData,
+    /// Nothing else was available.
+    WorkingDir,
+    /// Next to the app's data in portable mode.
+    Portable,
+}
+
+#[derive(Debug, Clone)]
+pub struct DefaultDir {
+    pub path: PathBuf,
+    pub base: DefaultDirBase,
+}
+
+/// The default recordings directory: `DiscRec` in the first existing folder
+/// of Music, Documents, home and app data, falling back to the working
+/// directory; `Recordings` in the data folder in portable mode. Resolved once
+/// per run.
+pub fn default_dir() -> &'static DefaultDir {
+    static DEFAULT: std::sync::OnceLock<DefaultDir> = std::sync::OnceLock::new();
+    DEFAULT.get_or_init(|| {
+        if let Some(path) = crate::data_dir::portable_recordings_dir() {
+            return DefaultDir {
+                path,
+                base: DefaultDirBase::Portable,
+            };
+        }
+        let candidates = [
+            (DefaultDirBase::Music, dirs::audio_dir()),
+            (DefaultDirBase::Documents, dirs::document_dir()),
+            (DefaultDirBase::Home, dirs::home_dir()),
+            (DefaultDirBase::AppData, dirs::data_dir()),
+        ];
+        candidates
+            .into_iter()
+            .find_map(|(base, dir)| {
+                let dir = dir.filter(|dir| dir.is_dir())?;
+                Some(DefaultDir {
+                    path: dir.join("DiscRec"),
+                    base,
+                })
+            })
+            .unwrap_or_else(|| DefaultDir {
+                path: PathBuf::from(".").join("DiscRec"),
+                base: DefaultDirBase::WorkingDir,
+            })
+    })
+}
+
 pub fn default_recordings_dir() -> PathBuf {
-    dirs::audio_dir()
-        .or_else(dirs::home_dir)
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("DiscRec")
+    default_dir().path.clone()
 }
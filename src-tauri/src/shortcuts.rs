@@ -0,0 +1,114 @@
+use serde::Serialize;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
+
+use crate::settings::ShortcutConfig;
+
+/// Extra modifier combinations tried, in order, when suggesting a free shortcut.
+const SUGGESTION_MODIFIERS: &[&str] = &["ctrl+shift", "ctrl+alt", "alt+shift", "ctrl+alt+shift"];
+/// Keys rarely bound by other applications, used when the original key has no free variant.
+const FALLBACK_KEYS: &[&str] = &["F9", "F10", "F11", "F12"];
+const MAX_SUGGESTIONS: usize = 3;
+
+#[derive(Serialize, Clone)]
+pub struct ShortcutConflict {
+    /// `record` or `stop`.
+    pub action: String,
+    pub accelerator: String,
+    pub reason: String,
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ShortcutUpdate {
+    /// False when conflicts prevented the new shortcuts from being stored.
+    pub saved: bool,
+    pub shortcuts: ShortcutConfig,
+    pub conflicts: Vec<ShortcutConflict>,
+}
+
+/// Check both accelerators against each other and against the system.
+pub fn find_conflicts<R: Runtime>(
+    app: &AppHandle<R>,
+    config: &ShortcutConfig,
+) -> Vec<ShortcutConflict> {
+    let actions = [("record", &config.record), ("stop", &config.stop)];
+    let mut conflicts = Vec::new();
+
+    for (i, (action, accelerator)) in actions.iter().enumerate() {
+        let other = actions[1 - i].1;
+        let reason = if i == 1 && normalize(accelerator) == normalize(other) {
+            Some("Same shortcut as record".to_string())
+        } else {
+            probe(app, accelerator).err()
+        };
+
+        if let Some(reason) = reason {
+            log::warn!(
+                "Shortcut {} for {} unavailable: {}",
+                accelerator,
+                action,
+                reason
+            );
+            conflicts.push(ShortcutConflict {
+                action: action.to_string(),
+                accelerator: accelerator.to_string(),
+                reason,
+                suggestions: suggest(app, accelerator, other),
+            });
+        }
+    }
+
+    conflicts
+}
+
+/// Briefly register an accelerator system-wide to see whether the OS accepts it.
+fn probe<R: Runtime>(app: &AppHandle<R>, accelerator: &str) -> Result<(), String> {
+    let shortcut: Shortcut = accelerator
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid shortcut", accelerator))?;
+
+    let global = app.global_shortcut();
+    if global.is_registered(shortcut) {
+        return Ok(());
+    }
+    global
+        .register(shortcut)
+        .map_err(|_| "Already in use by another application".to_string())?;
+    let _ = global.unregister(shortcut);
+    Ok(())
+}
+
+/// Free alternatives close to the requested accelerator.
+fn suggest<R: Runtime>(app: &AppHandle<R>, accelerator: &str, taken: &str) -> Vec<String> {
+    let key = accelerator
+        .rsplit('+')
+        .next()
+        .map(str::trim)
+        .filter(|k| !k.is_empty() && k.parse::<Shortcut>().is_ok())
+        .unwrap_or("r");
+
+    let with_key = SUGGESTION_MODIFIERS
+        .iter()
+        .map(|mods| format!("{}+{}", mods, key.to_lowercase()));
+    let fallback = FALLBACK_KEYS
+        .iter()
+        .map(|key| format!("ctrl+shift+{}", key));
+
+    with_key
+        .chain(fallback)
+        .filter(|candidate| normalize(candidate) != normalize(taken))
+        .filter(|candidate| probe(app, candidate).is_ok())
+        .take(MAX_SUGGESTIONS)
+        .collect()
+}
+
+/// Compare accelerators regardless of case and modifier order.
+fn normalize(accelerator: &str) -> Vec<String> {
+    let mut parts: Vec<String> = accelerator
+        .split('+')
+        .map(|p| p.trim().to_lowercase())
+        .collect();
+    parts.sort();
+    parts
+}
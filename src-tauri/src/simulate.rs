@@ -0,0 +1,278 @@
+use anyhow::{Context, Result};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use parking_lot::Mutex;
+use serde::Deserialize;
+use std::f32::consts::TAU;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::audio::pcm::float_to_i16;
+use crate::discord::manifest::{ChannelRef, SessionInfo, SessionTrack, SESSION_SUFFIX};
+use crate::discord::speaking_feed::SpeakingFeed;
+use crate::events::{Recorder, RecordingEvent, StatusHook, StopReason, LEVEL_INTERVAL};
+
+/// Low, so the dummy files stay small.
+const SAMPLE_RATE: u32 = 8_000;
+/// Same length as a Discord voice tick.
+const TICK: Duration = Duration::from_millis(20);
+/// How long each made-up speaker talks before the next one does.
+const TURN_SECS: f32 = 2.5;
+/// Longest session that can be simulated.
+pub const MAX_SECS: u32 = 600;
+
+/// Made-up channel members as SSRC, user ID and name.
+const SPEAKERS: &[(u32, u64, &str)] = &[
+    (1001, 100_000_000_000_000_001, "Alice"),
+    (1002, 100_000_000_000_000_002, "Bob"),
+    (1003, 100_000_000_000_000_003, "Carol"),
+    (1004, 100_000_000_000_000_004, "Dave"),
+];
+
+/// What a simulated session pretends to be.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimulationOptions {
+    pub source: Recorder,
+    pub duration_secs: u32,
+    /// People taking turns talking; bot sessions get a track for each.
+    pub speakers: usize,
+}
+
+/// Fake recording sessions that send the same events as real ones and save
+/// small dummy files, for working on the UI and integrations without
+/// Discord or audio hardware.
+pub struct SimulationState {
+    running: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    status_hook: Mutex<Option<StatusHook>>,
+}
+
+impl SimulationState {
+    pub fn new() -> Self {
+        Self {
+            running: Arc::new(AtomicBool::new(false)),
+            stop: Arc::new(AtomicBool::new(false)),
+            status_hook: Mutex::new(None),
+        }
+    }
+
+    pub fn set_status_hook(&self, hook: StatusHook) {
+        *self.status_hook.lock() = Some(hook);
+    }
+
+    /// Simulate a session saving into `dir` on a background thread; it
+    /// stops by itself after the requested duration.
+    pub fn start(
+        &self,
+        options: SimulationOptions,
+        dir: PathBuf,
+        feed: Arc<SpeakingFeed>,
+    ) -> Result<()> {
+        let hook = self
+            .status_hook
+            .lock()
+            .clone()
+            .context("Recorder events aren't set up yet")?;
+        if self.running.swap(true, Ordering::SeqCst) {
+            anyhow::bail!("A simulation is already running");
+        }
+        self.stop.store(false, Ordering::SeqCst);
+        let running = Arc::clone(&self.running);
+        let stop = Arc::clone(&self.stop);
+        log::info!(
+            "Simulating a {}s {:?} recording with {} speaker(s)",
+            options.duration_secs,
+            options.source,
+            options.speakers
+        );
+        std::thread::spawn(move || {
+            let source = options.source;
+            match run(&options, &dir, &hook, &feed, &stop) {
+                Ok(paths) => hook(
+                    source,
+                    RecordingEvent::Stopped {
+                        reason: StopReason::Requested,
+                        paths,
+                    },
+                ),
+                Err(e) => {
+                    log::warn!("Simulated recording failed: {:#}", e);
+                    hook(
+                        source,
+                        RecordingEvent::Error {
+                            message: format!("{:#}", e),
+                        },
+                    );
+                }
+            }
+            running.store(false, Ordering::SeqCst);
+        });
+        Ok(())
+    }
+
+    /// End a running simulation early; its files are still saved.
+    pub fn stop(&self) -> bool {
+        let running = self.running.load(Ordering::SeqCst);
+        if running {
+            self.stop.store(true, Ordering::SeqCst);
+        }
+        running
+    }
+}
+
+type Writer = WavWriter<BufWriter<File>>;
+
+/// Play out the session tick by tick and return the saved files.
+fn run(
+    options: &SimulationOptions,
+    dir: &Path,
+    hook: &StatusHook,
+    feed: &SpeakingFeed,
+    stop: &AtomicBool,
+) -> Result<Vec<String>> {
+    let speakers = &SPEAKERS[..options.speakers.clamp(1, SPEAKERS.len())];
+    let bot = options.source == Recorder::Bot;
+    let started_at = chrono::Local::now();
+    let stem = format!("discord-{}-simulated", started_at.format("%Y-%m-%d_%H%M%S"));
+    std::fs::create_dir_all(dir).context("Failed to create recordings folder")?;
+
+    // One track per speaker from the bot, one mixed file locally
+    let files: Vec<String> = if bot {
+        speakers
+            .iter()
+            .map(|(_, _, name)| format!("{}-{}.wav", stem, name))
+            .collect()
+    } else {
+        vec![format!("{}.wav", stem)]
+    };
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut writers = files
+        .iter()
+        .map(|file| WavWriter::create(dir.join(file), spec).context("Failed to create dummy file"))
+        .collect::<Result<Vec<Writer>>>()?;
+
+    hook(
+        options.source,
+        RecordingEvent::Started {
+            path: Some(if bot {
+                dir.to_string_lossy().to_string()
+            } else {
+                dir.join(&files[0]).to_string_lossy().to_string()
+            }),
+            device: None,
+        },
+    );
+    if bot {
+        feed.session_started();
+    }
+
+    let samples_per_tick = (SAMPLE_RATE as u128 * TICK.as_millis() / 1000) as usize;
+    let duration = Duration::from_secs(options.duration_secs.min(MAX_SECS) as u64);
+    let start = Instant::now();
+    let mut last_level = start;
+    let mut peak = 0f32;
+    let mut sample_index = 0u64;
+    while start.elapsed() < duration && !stop.load(Ordering::SeqCst) {
+        let secs = start.elapsed().as_secs_f32();
+        let levels: Vec<f32> = (0..speakers.len())
+            .map(|i| speaker_level(i, speakers.len(), secs))
+            .collect();
+        if bot {
+            for (&(ssrc, user_id, _), &level) in speakers.iter().zip(&levels) {
+                feed.heard(ssrc, Some(user_id), Some(level), level > 0.0);
+            }
+            feed.tick_done();
+        }
+        for n in 0..samples_per_tick as u64 {
+            let t = (sample_index + n) as f32 / SAMPLE_RATE as f32;
+            let voices = levels
+                .iter()
+                .enumerate()
+                .map(|(i, level)| level * (TAU * 180.0 * (i + 1) as f32 * t).sin());
+            if bot {
+                for (writer, voice) in writers.iter_mut().zip(voices) {
+                    writer.write_sample(float_to_i16(voice))?;
+                }
+            } else {
+                writers[0].write_sample(float_to_i16(voices.sum::<f32>()))?;
+            }
+        }
+        sample_index += samples_per_tick as u64;
+        peak = levels.iter().copied().fold(peak, f32::max);
+        if last_level.elapsed() >= LEVEL_INTERVAL {
+            hook(options.source, RecordingEvent::Level { peak });
+            peak = 0.0;
+            last_level = Instant::now();
+        }
+        std::thread::sleep(TICK);
+    }
+
+    for writer in writers {
+        writer.finalize().context("Failed to finish dummy file")?;
+    }
+    let paths = files
+        .iter()
+        .map(|file| dir.join(file).to_string_lossy().to_string())
+        .collect();
+    if bot {
+        feed.session_stopped();
+        let manifest = SessionInfo {
+            started_at: started_at.to_rfc3339(),
+            ended_at: chrono::Local::now().to_rfc3339(),
+            channel: ChannelRef {
+                guild_id: "100000000000000000".to_string(),
+                guild_name: Some("Simulated server".to_string()),
+                channel_id: "100000000000000010".to_string(),
+                channel_name: Some("Simulated channel".to_string()),
+            },
+            sample_rate: SAMPLE_RATE,
+            format: "wav".to_string(),
+            tracks: speakers
+                .iter()
+                .zip(&files)
+                .map(|(&(ssrc, user_id, name), file)| SessionTrack {
+                    file: file.clone(),
+                    ssrc,
+                    user_id: Some(user_id.to_string()),
+                    name: Some(name.to_string()),
+                    bot: false,
+                    noise_floor_db: None,
+                    likely_processed: false,
+                })
+                .collect(),
+            mix: None,
+            mix_parts: Vec::new(),
+            timeline: None,
+            consent: None,
+            chat: Vec::new(),
+            notes: Vec::new(),
+        };
+        manifest.save(&dir.join(format!("{}{}", stem, SESSION_SUFFIX)))?;
+    }
+    Ok(paths)
+}
+
+/// Peak of speaker `index` at `secs` into the session. Speakers take turns,
+/// with a little overlap at the start of each turn, and pause between words.
+fn speaker_level(index: usize, count: usize, secs: f32) -> f32 {
+    let turn = (secs / TURN_SECS) as usize;
+    let into_turn = secs % TURN_SECS;
+    let talking = turn % count == index || (into_turn < 0.3 && (turn + count - 1) % count == index);
+    if !talking {
+        return 0.0;
+    }
+    // Syllables about four times a second, a short gap every word
+    let syllable = (secs * 4.0 * TAU).sin().abs();
+    if (secs * 1.5).fract() > 0.85 {
+        return 0.0;
+    }
+    0.15 + 0.45 * syllable
+}
@@ -0,0 +1,173 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::audio::encoder::AudioFormat;
+use crate::commands::{stop_bot_session, DiscordState, RecorderState};
+use crate::settings::SettingsState;
+
+/// Free space wanted on top of an estimate: a quarter more, and at least this.
+const HEADROOM_RATIO: f64 = 0.25;
+const MIN_HEADROOM_BYTES: u64 = 512 * 1024 * 1024;
+/// Free space needed to start recording unless set otherwise.
+pub const DEFAULT_MIN_FREE_MB: u32 = 1024;
+/// A running recording is stopped when free space drops below this share
+/// of the minimum needed to start, leaving room to finish the files.
+const STOP_FRACTION: u64 = 4;
+const GUARD_INTERVAL: Duration = Duration::from_secs(5);
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+/// Expected disk usage of a recording against the space left for it.
+#[derive(Debug, Clone, Serialize)]
+pub struct SizeEstimate {
+    /// Peak disk usage while recording.
+    pub bytes: u64,
+    /// `bytes` plus headroom.
+    pub required_bytes: u64,
+    /// Free space where recordings are saved, when it could be read.
+    pub available_bytes: Option<u64>,
+    /// False only when the free space is known to fall short of `required_bytes`.
+    pub fits: bool,
+}
+
+/// Estimate `speakers` tracks of `channels` channels at 48 kHz, recorded for
+/// `duration_secs` into `dir`.
+pub fn estimate(
+    format: AudioFormat,
+    duration_secs: u32,
+    channels: u16,
+    speakers: u32,
+    dir: &Path,
+) -> SizeEstimate {
+    let bytes = format.estimated_bytes_per_sec(channels.max(1), 48000)
+        * duration_secs as u64
+        * speakers.max(1) as u64;
+    let headroom = ((bytes as f64 * HEADROOM_RATIO) as u64).max(MIN_HEADROOM_BYTES);
+    let required_bytes = bytes + headroom;
+    let available_bytes = available_space(dir);
+    SizeEstimate {
+        bytes,
+        required_bytes,
+        available_bytes,
+        fits: available_bytes.map_or(true, |free| free >= required_bytes),
+    }
+}
+
+/// Free space on the disk `dir` is on.
+pub fn available_space(dir: &Path) -> Option<u64> {
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .filter(|disk| dir.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+}
+
+/// Empty the trash, the longest deleted first, until `dir` has `wanted`
+/// bytes free. Returns the free space after, or `None` if it can't be read.
+fn free_from_trash(dir: &Path, wanted: u64) -> Option<u64> {
+    let free = available_space(dir)?;
+    if free >= wanted {
+        return Some(free);
+    }
+    match crate::trash::free_up(dir, wanted - free) {
+        Ok(0) => Some(free),
+        Ok(_) => available_space(dir),
+        Err(e) => {
+            log::warn!("Failed to free up space from the trash: {:#}", e);
+            available_space(dir)
+        }
+    }
+}
+
+/// Refuse to start recording into `dir` with less than `min_free_mb` free,
+/// after emptying as much of the trash as that needs; 0 turns the check
+/// off, and so does free space that can't be read.
+pub fn ensure_free_space(dir: &Path, min_free_mb: u32) -> Result<()> {
+    let needed = min_free_mb as u64 * BYTES_PER_MB;
+    if needed == 0 {
+        return Ok(());
+    }
+    let Some(free) = free_from_trash(dir, needed) else {
+        return Ok(());
+    };
+    if free < needed {
+        anyhow::bail!(
+            "Only {} MB free on the disk recordings are saved to, {} MB needed. Free up space or choose another folder",
+            free / BYTES_PER_MB,
+            min_free_mb
+        );
+    }
+    Ok(())
+}
+
+/// Watch free space while anything records and stop it, saving what was
+/// recorded, before the disk fills up and leaves broken files.
+pub fn spawn_guard(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(GUARD_INTERVAL);
+        loop {
+            interval.tick().await;
+            check_space(&app).await;
+        }
+    });
+}
+
+async fn check_space(app: &AppHandle) {
+    let settings = app.state::<SettingsState>();
+    let min_free = settings.0.lock().min_free_space_mb() as u64 * BYTES_PER_MB;
+    let stop_below = min_free / STOP_FRACTION;
+    if stop_below == 0 {
+        return;
+    }
+    let recorder = app.state::<RecorderState>();
+    let local = recorder.0.lock().is_recording();
+    let discord = app.state::<DiscordState>();
+    let bot = discord.0.lock().await.is_recording();
+    if !local && !bot {
+        return;
+    }
+    let dir = crate::settings::recordings_dir(&settings);
+    let Some(free) = available_space(&dir) else {
+        return;
+    };
+    if free >= stop_below {
+        return;
+    }
+    // Deleted recordings go before the running one, back up to the minimum
+    // needed to start so the guard doesn't trip again straight away
+    let free = match free_from_trash(&dir, min_free) {
+        Some(free) if free >= stop_below => return,
+        Some(free) => free,
+        None => free,
+    };
+
+    log::warn!(
+        "Only {} MB left on the recordings disk; stopping the recording",
+        free / BYTES_PER_MB
+    );
+    let _ = app
+        .notification()
+        .builder()
+        .title("Disk almost full")
+        .body(format!(
+            "Recording stopped with {} MB left so the files could still be saved",
+            free / BYTES_PER_MB
+        ))
+        .show();
+    let result = if local {
+        crate::commands::stop_recording(app.clone(), app.state(), app.state(), app.state())
+            .await
+            .map(|_| ())
+    } else {
+        let bot = discord.0.lock().await;
+        stop_bot_session(app, &bot).await.map(|_| ())
+    };
+    if let Err(e) = result {
+        log::error!("Failed to stop recording on low disk space: {}", e);
+    }
+}
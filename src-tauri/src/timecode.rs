@@ -0,0 +1,125 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use crate::discord::manifest::{self, SessionInfo, SESSION_SUFFIX};
+use crate::import::unique_path;
+use crate::library::{frame_count, probe, recording_format};
+
+/// Suffix of the exported subtitle track.
+pub const TIMESTAMPS_SUFFIX: &str = "-timestamps.srt";
+pub const DEFAULT_INTERVAL_SECS: u32 = 1;
+pub const MAX_INTERVAL_SECS: u32 = 3600;
+/// The start time in recording names, e.g. `2024-05-01_203000`, with `_`
+/// standing for whatever separates `{date}` and `{time}` in the template.
+const FILE_TIMESTAMP: &str = "%Y-%m-%d_%H%M%S";
+/// Length of `2024-05-01`.
+const FILE_DATE_LEN: usize = 10;
+/// Length of `2024-05-01_203000`.
+const FILE_TIMESTAMP_LEN: usize = 17;
+
+/// Write a subtitle track showing the wall-clock time every `interval_secs`
+/// next to `path` and return its path, so editors can burn in or line up the
+/// original recording times.
+///
+/// `path` is a session manifest or a recording. The start time comes from
+/// the session manifest, or else the recording's file name; the length from
+/// the session's start and end, or else the recording's header.
+pub fn export(path: &Path, interval_secs: u32) -> Result<PathBuf> {
+    let name = path
+        .file_name()
+        .context("Invalid file name")?
+        .to_string_lossy()
+        .to_string();
+    let (started_at, duration_ms, stem) = if let Some(stem) = name.strip_suffix(SESSION_SUFFIX) {
+        let data = std::fs::read_to_string(path).context("Failed to read session manifest")?;
+        let session: SessionInfo = serde_json::from_str(&data).context("Not a session manifest")?;
+        let (started_at, duration_ms) = session_span(&session)?;
+        (started_at, duration_ms, stem.to_string())
+    } else {
+        let stem = path
+            .file_stem()
+            .context("Invalid file name")?
+            .to_string_lossy()
+            .to_string();
+        let (started_at, duration_ms) = match manifest::for_file(path) {
+            Some(listing) => session_span(&listing.session)?,
+            None => (
+                started_from_name(&stem).context("The recording's start time isn't known")?,
+                recording_duration_ms(path).context("Can't read the recording's length")?,
+            ),
+        };
+        (started_at, duration_ms, stem)
+    };
+
+    let output = unique_path(&path.with_file_name(format!("{}{}", stem, TIMESTAMPS_SUFFIX)));
+    std::fs::write(&output, srt(started_at, duration_ms, interval_secs))
+        .context("Failed to write subtitles")?;
+    log::info!("Exported timestamp subtitles: {}", output.display());
+    Ok(output)
+}
+
+/// Start and length of a bot session from its manifest.
+fn session_span(session: &SessionInfo) -> Result<(DateTime<FixedOffset>, u64)> {
+    let started_at =
+        DateTime::parse_from_rfc3339(&session.started_at).context("Invalid session start time")?;
+    let ended_at =
+        DateTime::parse_from_rfc3339(&session.ended_at).context("Invalid session end time")?;
+    let duration_ms = (ended_at - started_at).num_milliseconds().max(0) as u64;
+    Ok((started_at, duration_ms))
+}
+
+/// The local time in a DiscRec file name such as `discord-2024-05-01_203000-mic`,
+/// wherever the file name template put it.
+pub fn started_from_name(stem: &str) -> Option<DateTime<FixedOffset>> {
+    let naive = stem.char_indices().find_map(|(at, _)| {
+        let stamp = stem.get(at..at + FILE_TIMESTAMP_LEN)?;
+        let (date, time) = (stamp.get(..FILE_DATE_LEN)?, stamp.get(FILE_DATE_LEN + 1..)?);
+        NaiveDateTime::parse_from_str(&format!("{}_{}", date, time), FILE_TIMESTAMP).ok()
+    })?;
+    let local = Local.from_local_datetime(&naive).earliest()?;
+    Some(local.fixed_offset())
+}
+
+fn recording_duration_ms(path: &Path) -> Option<u64> {
+    let format = recording_format(path)?;
+    let audio = probe(path, &format)?;
+    let frames = frame_count(path, &format)?;
+    Some(frames * 1000 / audio.sample_rate.max(1) as u64)
+}
+
+/// One cue per `interval_secs` from `started_at`, the last one cut off at
+/// the end of the recording.
+fn srt(started_at: DateTime<FixedOffset>, duration_ms: u64, interval_secs: u32) -> String {
+    let interval_ms = interval_secs.max(1) as u64 * 1000;
+    let mut out = String::new();
+    let mut start_ms = 0;
+    let mut index = 1;
+    while start_ms < duration_ms {
+        let end_ms = (start_ms + interval_ms).min(duration_ms);
+        let at = started_at + chrono::Duration::milliseconds(start_ms as i64);
+        let _ = write!(
+            out,
+            "{}\n{} --> {}\n{}\n\n",
+            index,
+            srt_time(start_ms),
+            srt_time(end_ms),
+            at.format("%Y-%m-%d %H:%M:%S")
+        );
+        start_ms = end_ms;
+        index += 1;
+    }
+    out
+}
+
+/// `HH:MM:SS,mmm`
+fn srt_time(ms: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
@@ -0,0 +1,208 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::library::RecordingInfo;
+use crate::session::move_file;
+
+/// Hidden folder inside the recordings directory holding deleted recordings.
+pub const TRASH_DIR: &str = ".trash";
+const ENTRY_FILE: &str = "entry.json";
+/// Deleted recordings are kept this long before they go for good.
+const KEEP_FOR: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// A deleted recording, kept in `<library>/.trash/<id>/` with its import
+/// sidecar until it is restored or the trash is emptied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub id: String,
+    /// Where the recording was, relative to the recordings folder so the
+    /// trash moves along with the library.
+    pub original: PathBuf,
+    pub deleted_at: String,
+    pub size: u64,
+    /// File names inside the entry's folder: the recording, then its sidecar.
+    pub files: Vec<String>,
+}
+
+impl TrashEntry {
+    pub fn filename(&self) -> String {
+        self.original
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+fn trash_dir(library_dir: &Path) -> PathBuf {
+    library_dir.join(TRASH_DIR)
+}
+
+/// Move the recording at `path` in `library_dir` to the trash, along with
+/// its import sidecar.
+pub fn move_to_trash(library_dir: &Path, path: &Path) -> Result<TrashEntry> {
+    let library_dir = library_dir
+        .canonicalize()
+        .context("Recordings folder not found")?;
+    let path = path.canonicalize().context("Recording not found")?;
+    let original = path
+        .strip_prefix(&library_dir)
+        .context("Recording isn't in the recordings folder")?
+        .to_path_buf();
+    let size = std::fs::metadata(&path)
+        .context("Recording not found")?
+        .len();
+
+    let now = chrono::Local::now();
+    let base = now.format("%Y%m%d-%H%M%S%3f").to_string();
+    let trash = trash_dir(&library_dir);
+    let id = (1..)
+        .map(|n| match n {
+            1 => base.clone(),
+            n => format!("{}-{}", base, n),
+        })
+        .find(|id| !trash.join(id).exists())
+        .expect("some numbered id is free");
+    let dir = trash.join(&id);
+    std::fs::create_dir_all(&dir).context("Failed to create trash folder")?;
+
+    let name = original.file_name().context("Recording has no file name")?;
+    let mut files = Vec::new();
+    if let Err(e) = move_file(&path, &dir.join(name)) {
+        let _ = std::fs::remove_dir(&dir);
+        return Err(e).with_context(|| format!("Failed to move {} to the trash", path.display()));
+    }
+    files.push(name.to_string_lossy().to_string());
+    let sidecar = crate::import::sidecar_path(&path);
+    if let Some(sidecar_name) = sidecar.file_name().filter(|_| sidecar.is_file()) {
+        match move_file(&sidecar, &dir.join(sidecar_name)) {
+            Ok(()) => files.push(sidecar_name.to_string_lossy().to_string()),
+            Err(e) => log::warn!("Failed to move import sidecar to the trash: {}", e),
+        }
+    }
+
+    let entry = TrashEntry {
+        id,
+        original,
+        deleted_at: now.to_rfc3339(),
+        size,
+        files,
+    };
+    std::fs::write(dir.join(ENTRY_FILE), serde_json::to_string_pretty(&entry)?)
+        .context("Failed to write trash entry")?;
+    log::info!("Moved {} to the trash", path.display());
+    Ok(entry)
+}
+
+/// Put a trashed recording back where it was, or next to it as `name (2)`
+/// if that name has been taken since, and return its listing.
+pub fn restore(library_dir: &Path, id: &str) -> Result<RecordingInfo> {
+    let entry = read_entry(library_dir, id)?;
+    let dir = trash_dir(library_dir).join(&entry.id);
+    let target = crate::import::unique_path(&library_dir.join(&entry.original));
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).context("Failed to recreate recording folder")?;
+    }
+
+    let mut files = entry.files.iter();
+    let recording = files.next().context("Trash entry is empty")?;
+    move_file(&dir.join(recording), &target)
+        .with_context(|| format!("Failed to restore {}", entry.filename()))?;
+    for sidecar in files {
+        let to = crate::import::sidecar_path(&target);
+        if let Err(e) = move_file(&dir.join(sidecar), &to) {
+            log::warn!("Failed to restore import sidecar: {}", e);
+        }
+    }
+    if let Err(e) = std::fs::remove_dir_all(&dir) {
+        log::warn!("Failed to remove trash entry {}: {}", entry.id, e);
+    }
+    log::info!("Restored {}", target.display());
+    crate::library::recording_info(&target).context("Restored recording is missing")
+}
+
+/// Recordings in the trash, most recently deleted first.
+pub fn list(library_dir: &Path) -> Vec<TrashEntry> {
+    let Ok(dirs) = std::fs::read_dir(trash_dir(library_dir)) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<TrashEntry> = dirs
+        .flatten()
+        .filter_map(|dir| read_entry(library_dir, &dir.file_name().to_string_lossy()).ok())
+        .collect();
+    entries.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+    entries
+}
+
+/// Delete the trashed recordings for good, only those deleted longer ago
+/// than `older_than` if given. Returns the bytes freed.
+pub fn empty(library_dir: &Path, older_than: Option<Duration>) -> Result<u64> {
+    let cutoff = older_than
+        .and_then(|age| chrono::Duration::from_std(age).ok())
+        .map(|age| chrono::Local::now() - age);
+    let mut freed = 0;
+    for entry in list(library_dir) {
+        let expired = cutoff.map_or(true, |cutoff| {
+            chrono::DateTime::parse_from_rfc3339(&entry.deleted_at).is_ok_and(|at| at < cutoff)
+        });
+        if !expired {
+            continue;
+        }
+        remove(library_dir, &entry)?;
+        freed += entry.size;
+    }
+    // Folders left by a delete that didn't finish have no entry to list
+    let trash = trash_dir(library_dir);
+    if older_than.is_none() && trash.is_dir() {
+        std::fs::remove_dir_all(&trash).context("Failed to empty the trash")?;
+    }
+    Ok(freed)
+}
+
+/// Delete trashed recordings for good, the longest deleted first, until at
+/// least `bytes` are freed or the trash is empty. Returns the bytes freed.
+pub fn free_up(library_dir: &Path, bytes: u64) -> Result<u64> {
+    let mut freed = 0;
+    for entry in list(library_dir).into_iter().rev() {
+        if freed >= bytes {
+            break;
+        }
+        remove(library_dir, &entry)?;
+        freed += entry.size;
+    }
+    if freed > 0 {
+        log::info!("Emptied {} bytes from the trash to free up space", freed);
+    }
+    Ok(freed)
+}
+
+/// Space taken by the recordings in the trash.
+pub fn total_size(library_dir: &Path) -> u64 {
+    list(library_dir).iter().map(|entry| entry.size).sum()
+}
+
+/// Delete one trashed recording for good.
+pub fn remove(library_dir: &Path, entry: &TrashEntry) -> Result<()> {
+    std::fs::remove_dir_all(trash_dir(library_dir).join(&entry.id))
+        .with_context(|| format!("Failed to delete {} from the trash", entry.filename()))
+}
+
+/// Drop recordings that have been in the trash for more than 30 days.
+pub fn purge_expired(library_dir: &Path) {
+    match empty(library_dir, Some(KEEP_FOR)) {
+        Ok(0) => {}
+        Ok(freed) => log::info!("Emptied {} bytes of old recordings from the trash", freed),
+        Err(e) => log::warn!("Failed to purge the trash: {:#}", e),
+    }
+}
+
+fn read_entry(library_dir: &Path, id: &str) -> Result<TrashEntry> {
+    if id.is_empty() || id.contains(['/', '\\']) || id.starts_with('.') {
+        anyhow::bail!("Invalid trash entry");
+    }
+    let data = std::fs::read_to_string(trash_dir(library_dir).join(id).join(ENTRY_FILE))
+        .context("Recording is no longer in the trash")?;
+    serde_json::from_str(&data).context("Invalid trash entry")
+}
@@ -0,0 +1,94 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::audio::encoder::{create_encoder, remove_partial, AudioEncoder, AudioFormat};
+use crate::library::{decode, recording_format};
+
+/// Cut `path` down to `start_secs..end_secs` (to the end without
+/// `end_secs`), re-encoded in its own format.
+///
+/// The trimmed audio is written to a hidden file next to the recording and
+/// only replaces it once complete, so a failure leaves the original intact.
+/// WAV and FLAC recordings can be trimmed.
+pub fn trim(path: &Path, start_secs: f64, end_secs: Option<f64>) -> Result<()> {
+    let format = recording_format(path).context("Not a supported recording format")?;
+    let output = match format.as_str() {
+        "wav" => AudioFormat::Wav,
+        "flac" => AudioFormat::Flac,
+        _ => anyhow::bail!("{} files can't be trimmed yet", format.to_uppercase()),
+    };
+    let start_secs = start_secs.max(0.0);
+    if end_secs.is_some_and(|end| end <= start_secs) {
+        anyhow::bail!("The end of the range must come after its start");
+    }
+
+    let file_name = path.file_name().context("Invalid file name")?;
+    let temp = path
+        .with_file_name(format!(".{}.trim", file_name.to_string_lossy()))
+        .to_string_lossy()
+        .to_string();
+    let result = write_range(path, &format, &temp, output, start_secs, end_secs);
+    if result.is_err() {
+        remove_partial(&temp);
+    }
+    let frames = result?;
+    if frames == 0 {
+        remove_partial(&temp);
+        anyhow::bail!("Nothing left to keep in that range");
+    }
+
+    std::fs::rename(&temp, path).context("Failed to replace the recording")?;
+    log::info!(
+        "Trimmed {} to {:.1}s..{}",
+        path.display(),
+        start_secs,
+        end_secs.map_or("end".to_string(), |end| format!("{:.1}s", end))
+    );
+    Ok(())
+}
+
+/// Encode the frames of `source` inside the range to `target`, returning
+/// how many were kept.
+fn write_range(
+    source: &Path,
+    format: &str,
+    target: &str,
+    output: AudioFormat,
+    start_secs: f64,
+    end_secs: Option<f64>,
+) -> Result<u64> {
+    let mut encoder: Option<Box<dyn AudioEncoder>> = None;
+    let mut frame = 0u64;
+    let mut kept = 0u64;
+    decode(source, format, |channels, sample_rate, block| {
+        let channels = channels as usize;
+        let first = (start_secs * sample_rate as f64) as u64;
+        let end = end_secs.map(|end| (end * sample_rate as f64) as u64);
+        let encoder = match &mut encoder {
+            Some(encoder) => encoder,
+            None => encoder.insert(create_encoder(
+                target,
+                channels as u16,
+                sample_rate,
+                output,
+                false,
+            )?),
+        };
+
+        let block_frames = (block.len() / channels) as u64;
+        let from = first.saturating_sub(frame).min(block_frames);
+        let to = end.map_or(block_frames, |end| {
+            end.saturating_sub(frame).min(block_frames)
+        });
+        if to > from {
+            encoder.write_samples(&block[from as usize * channels..to as usize * channels])?;
+            kept += to - from;
+        }
+        frame += block_frames;
+        Ok(end.map_or(true, |end| frame < end))
+    })?;
+    if let Some(encoder) = encoder {
+        encoder.finalize()?;
+    }
+    Ok(kept)
+}
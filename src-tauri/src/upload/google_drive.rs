@@ -0,0 +1,281 @@
+use anyhow::{Context, Result};
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{LOCATION, RANGE};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::settings::GoogleDriveSettings;
+
+/// Sent when signing in through the device flow succeeds or fails.
+pub const AUTH: &str = "upload://google-drive-auth";
+
+const DEVICE_CODE_URL: &str = "https://oauth2.googleapis.com/device/code";
+const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const UPLOAD_URL: &str = "https://www.googleapis.com/upload/drive/v3/files?uploadType=resumable";
+const DEVICE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+/// Only files DiscRec uploaded itself, rather than the whole Drive.
+const SCOPE: &str = "https://www.googleapis.com/auth/drive.file";
+/// Bytes sent per upload request; Drive wants multiples of 256 KiB.
+const CHUNK_SIZE: u64 = 32 * 256 * 1024;
+/// Per request, so one slow chunk doesn't fail the upload.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+// Refresh token via OS keyring, next to the bot token
+pub const KEYRING_ACCOUNT: &str = "google_drive_refresh_token";
+
+/// What the user enters at `verification_url` on any device to let DiscRec
+/// upload to their Drive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCode {
+    #[serde(skip_serializing)]
+    device_code: String,
+    pub user_code: String,
+    pub verification_url: String,
+    /// Seconds until `user_code` stops working.
+    pub expires_in: u64,
+    /// Seconds Google wants between polls.
+    #[serde(default = "default_interval", skip_serializing)]
+    interval: u64,
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+/// Outcome of a sign-in, sent as [`AUTH`].
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthStatus {
+    pub signed_in: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+    error_description: Option<String>,
+}
+
+impl ErrorResponse {
+    fn message(&self) -> &str {
+        self.error_description.as_deref().unwrap_or(&self.error)
+    }
+}
+
+fn client() -> Result<Client> {
+    Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        // Drive answers unfinished uploads with 308, which isn't a redirect
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .context("Failed to set up HTTP client")
+}
+
+/// The JSON body of a successful response, or Google's reason for failing.
+fn parse<T: DeserializeOwned>(response: Response) -> Result<T> {
+    if response.status().is_success() {
+        return response.json().context("Unexpected response from Google");
+    }
+    Err(error_of(response))
+}
+
+fn error_of(response: Response) -> anyhow::Error {
+    let status = response.status();
+    let body = response.text().unwrap_or_default();
+    if let Ok(error) = serde_json::from_str::<ErrorResponse>(&body) {
+        return anyhow::anyhow!("{}", error.message());
+    }
+    // Drive API errors nest the message
+    match serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|v| v["error"]["message"].as_str().map(str::to_string))
+    {
+        Some(message) => anyhow::anyhow!("{}", message),
+        None => anyhow::anyhow!("Google answered {}", status),
+    }
+}
+
+/// Start the OAuth device flow for `config`'s client.
+pub fn request_device_code(config: &GoogleDriveSettings) -> Result<DeviceCode> {
+    let response = client()?
+        .post(DEVICE_CODE_URL)
+        .form(&[("client_id", config.client_id.as_str()), ("scope", SCOPE)])
+        .send()
+        .context("Failed to reach Google")?;
+    parse(response).context("Failed to start signing in to Google Drive")
+}
+
+/// Poll until the user entered `code` and keep the refresh token granted.
+pub fn await_sign_in(config: &GoogleDriveSettings, code: &DeviceCode) -> Result<()> {
+    let client = client()?;
+    let deadline = Instant::now() + Duration::from_secs(code.expires_in);
+    let mut interval = Duration::from_secs(code.interval.max(1));
+    loop {
+        if Instant::now() >= deadline {
+            anyhow::bail!("The sign-in code expired");
+        }
+        std::thread::sleep(interval);
+        let response = client
+            .post(TOKEN_URL)
+            .form(&[
+                ("client_id", config.client_id.as_str()),
+                ("client_secret", config.client_secret.as_str()),
+                ("device_code", code.device_code.as_str()),
+                ("grant_type", DEVICE_GRANT),
+            ])
+            .send()
+            .context("Failed to reach Google")?;
+        if response.status().is_success() {
+            let token: TokenResponse =
+                response.json().context("Unexpected response from Google")?;
+            let refresh_token = token
+                .refresh_token
+                .context("Google didn't grant offline access")?;
+            crate::secrets::set(KEYRING_ACCOUNT, &refresh_token)
+                .context("Failed to save Google Drive sign-in to keyring")?;
+            log::info!("Signed in to Google Drive");
+            return Ok(());
+        }
+        let error: ErrorResponse = response.json().context("Unexpected response from Google")?;
+        match error.error.as_str() {
+            "authorization_pending" => {}
+            "slow_down" => interval += Duration::from_secs(5),
+            "access_denied" => anyhow::bail!("Access to Google Drive was denied"),
+            "expired_token" => anyhow::bail!("The sign-in code expired"),
+            _ => anyhow::bail!("{}", error.message()),
+        }
+    }
+}
+
+pub fn is_signed_in() -> Result<bool> {
+    Ok(crate::secrets::get(KEYRING_ACCOUNT)
+        .context("Failed to load Google Drive sign-in")?
+        .is_some())
+}
+
+pub fn sign_out() -> Result<()> {
+    crate::secrets::delete(KEYRING_ACCOUNT).context("Failed to delete Google Drive sign-in")
+}
+
+fn access_token(client: &Client, config: &GoogleDriveSettings) -> Result<String> {
+    let refresh_token = crate::secrets::get(KEYRING_ACCOUNT)
+        .context("Failed to load Google Drive sign-in")?
+        .context("Sign in to Google Drive first")?;
+    let response = client
+        .post(TOKEN_URL)
+        .form(&[
+            ("client_id", config.client_id.as_str()),
+            ("client_secret", config.client_secret.as_str()),
+            ("refresh_token", refresh_token.as_str()),
+            ("grant_type", "refresh_token"),
+        ])
+        .send()
+        .context("Failed to reach Google")?;
+    let token: TokenResponse = parse(response).context("Google Drive sign-in was rejected")?;
+    Ok(token.access_token)
+}
+
+/// Upload `path` into the configured folder in chunks, calling `progress`
+/// with bytes sent and the file size after each. Stops between chunks once
+/// `cancelled` returns true.
+pub fn upload(
+    config: &GoogleDriveSettings,
+    path: &Path,
+    mut progress: impl FnMut(u64, u64),
+    cancelled: impl Fn() -> bool,
+) -> Result<()> {
+    let client = client()?;
+    let token = access_token(&client, config)?;
+    let name = path
+        .file_name()
+        .context("Not a file")?
+        .to_string_lossy()
+        .to_string();
+    let mut file = std::fs::File::open(path).context("Failed to open file")?;
+    let total = file.metadata().context("Failed to read file size")?.len();
+
+    let mut metadata = json!({ "name": name });
+    if let Some(folder) = &config.folder_id {
+        metadata["parents"] = json!([folder]);
+    }
+    let response = client
+        .post(UPLOAD_URL)
+        .bearer_auth(&token)
+        .header("X-Upload-Content-Length", total)
+        .json(&metadata)
+        .send()
+        .context("Failed to reach Google Drive")?;
+    if !response.status().is_success() {
+        return Err(error_of(response));
+    }
+    let session = response
+        .headers()
+        .get(LOCATION)
+        .and_then(|location| location.to_str().ok())
+        .context("Google Drive didn't start the upload")?
+        .to_string();
+
+    let mut sent = 0;
+    loop {
+        if cancelled() {
+            anyhow::bail!("Cancelled");
+        }
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE as usize);
+        (&mut file)
+            .take(CHUNK_SIZE)
+            .read_to_end(&mut chunk)
+            .context("Failed to read file")?;
+        let range = match chunk.len() as u64 {
+            0 => format!("bytes */{}", total),
+            len => format!("bytes {}-{}/{}", sent, sent + len - 1, total),
+        };
+        let last = chunk.is_empty();
+        let response = client
+            .put(&session)
+            .bearer_auth(&token)
+            .header("Content-Range", range)
+            .body(chunk)
+            .send()
+            .context("Failed to reach Google Drive")?;
+        match response.status() {
+            status if status.is_success() => {
+                progress(total, total);
+                return Ok(());
+            }
+            // "Resume Incomplete": Drive wants the rest, from what it kept
+            // of the chunk, which may be less than was sent
+            StatusCode::PERMANENT_REDIRECT if !last => {
+                sent = stored_bytes(&response).min(total);
+                file.seek(SeekFrom::Start(sent))
+                    .context("Failed to read file")?;
+                progress(sent, total);
+            }
+            StatusCode::PERMANENT_REDIRECT => {
+                anyhow::bail!("Google Drive didn't receive the whole file")
+            }
+            _ => return Err(error_of(response)),
+        }
+    }
+}
+
+/// Bytes of an unfinished upload Drive has stored, from the `bytes=0-N`
+/// range of its 308 reply; without one it has nothing yet.
+fn stored_bytes(response: &Response) -> u64 {
+    response
+        .headers()
+        .get(RANGE)
+        .and_then(|range| range.to_str().ok())
+        .and_then(|range| range.strip_prefix("bytes=0-"))
+        .and_then(|end| end.parse::<u64>().ok())
+        .map_or(0, |end| end + 1)
+}
@@ -0,0 +1,78 @@
+pub mod google_drive;
+pub mod webdav;
+
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::discord::manifest;
+use crate::jobs::JobsState;
+use crate::settings::{SettingsState, UploadTarget};
+
+impl UploadTarget {
+    fn label(&self) -> &'static str {
+        match self {
+            UploadTarget::GoogleDrive => "Google Drive",
+            UploadTarget::WebDav => "WebDAV",
+        }
+    }
+}
+
+/// Upload saved recordings to the chosen target, if any, as an `upload` job
+/// whose progress counts bytes sent. Its result is the names of the
+/// uploaded files.
+pub fn after_record(app: &AppHandle, paths: &[String]) {
+    let config = app.state::<SettingsState>().0.lock().upload.clone();
+    let Some(target) = config.target else {
+        return;
+    };
+    if paths.is_empty() {
+        return;
+    }
+    let files: Vec<(PathBuf, u64)> = files_for(paths, config.include_manifests)
+        .into_iter()
+        .filter_map(|path| {
+            let size = std::fs::metadata(&path).ok()?.len();
+            Some((path, size))
+        })
+        .collect();
+    app.state::<JobsState>().spawn(app, "upload", move |job| {
+        let total = Some(files.iter().map(|(_, size)| size).sum::<u64>());
+        let mut done = 0;
+        let mut uploaded = Vec::new();
+        for (path, size) in &files {
+            job.check_cancelled()?;
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let progress = |sent: u64, _: u64| job.progress(done + sent, total, name.as_str());
+            let cancelled = || job.is_cancelled();
+            match target {
+                UploadTarget::GoogleDrive => {
+                    google_drive::upload(&config.google_drive, path, progress, cancelled)
+                }
+                UploadTarget::WebDav => webdav::upload(&config.webdav, path, progress, cancelled),
+            }
+            .with_context(|| format!("Failed to upload {} to {}", name, target.label()))?;
+            done += size;
+            uploaded.push(name);
+        }
+        log::info!("Uploaded {} file(s) to {}", uploaded.len(), target.label());
+        Ok(uploaded)
+    });
+}
+
+/// `paths` plus, for bot sessions, the session manifest next to them.
+fn files_for(paths: &[String], include_manifests: bool) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+    if include_manifests {
+        if let Some(listing) = paths.first().and_then(|p| manifest::for_file(Path::new(p))) {
+            let manifest = PathBuf::from(listing.path);
+            if !files.contains(&manifest) {
+                files.push(manifest);
+            }
+        }
+    }
+    files
+}
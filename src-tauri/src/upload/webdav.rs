@@ -0,0 +1,248 @@
+use anyhow::{Context, Result};
+use reqwest::blocking::{Body, Client, RequestBuilder, Response};
+use reqwest::{Method, StatusCode, Url};
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::settings::WebDavSettings;
+
+/// Bytes per chunk of a Nextcloud chunked upload, which wants at least 5 MB
+/// for all but the last.
+const CHUNK_SIZE: u64 = 10 * 1024 * 1024;
+/// Tries per request before an upload fails.
+const ATTEMPTS: u32 = 3;
+/// Wait before the first retry; doubled for each further one.
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+/// Per request; a whole file goes in one request on plain WebDAV servers.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(3600);
+/// Where Nextcloud serves a user's files, with uploads next to it.
+const NEXTCLOUD_FILES: &str = "/remote.php/dav/files/";
+
+// Password via OS keyring, next to the bot token
+pub const KEYRING_ACCOUNT: &str = "webdav_password";
+
+pub fn save_password(password: &str) -> Result<()> {
+    crate::secrets::set(KEYRING_ACCOUNT, password)
+        .context("Failed to save WebDAV password to keyring")
+}
+
+pub fn has_password() -> Result<bool> {
+    Ok(crate::secrets::get(KEYRING_ACCOUNT)
+        .context("Failed to load WebDAV password")?
+        .is_some())
+}
+
+pub fn delete_password() -> Result<()> {
+    crate::secrets::delete(KEYRING_ACCOUNT).context("Failed to delete WebDAV password")
+}
+
+/// Check `url` can be uploaded to before it is saved.
+pub fn validate(config: &WebDavSettings) -> Result<(), String> {
+    let url = Url::parse(&config.url).map_err(|_| "Enter the folder's full URL")?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("The URL must start with https:// or http://".to_string());
+    }
+    if config.username.trim().is_empty() {
+        return Err("Enter the WebDAV username".to_string());
+    }
+    Ok(())
+}
+
+/// A WebDAV server with the login from settings and the keyring.
+struct Server {
+    client: Client,
+    username: String,
+    password: String,
+}
+
+impl Server {
+    fn request(&self, method: Method, url: &Url) -> RequestBuilder {
+        self.client
+            .request(method, url.clone())
+            .basic_auth(&self.username, Some(&self.password))
+    }
+
+    /// Send the request `build` makes, retrying network errors and server
+    /// trouble with growing pauses. `build` runs once per try.
+    fn send(&self, what: &str, build: impl Fn() -> Result<RequestBuilder>) -> Result<Response> {
+        let mut delay = RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            let error = match build()?.send() {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) if response.status() == StatusCode::UNAUTHORIZED => {
+                    anyhow::bail!("{}: the server rejected the username or password", what)
+                }
+                Ok(response) if !is_transient(response.status()) => {
+                    anyhow::bail!("{}: the server answered {}", what, response.status())
+                }
+                Ok(response) => anyhow::anyhow!("the server answered {}", response.status()),
+                Err(e) => anyhow::Error::from(e),
+            };
+            if attempt >= ATTEMPTS {
+                return Err(error.context(what.to_string()));
+            }
+            log::warn!("{}, retrying in {:?}: {:#}", what, delay, error);
+            std::thread::sleep(delay);
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+}
+
+/// Upload `path` into the configured folder, calling `progress` with bytes
+/// sent and the file size. Nextcloud gets it in chunks, which are retried
+/// one by one and can be cancelled between; other servers get one request.
+pub fn upload(
+    config: &WebDavSettings,
+    path: &Path,
+    mut progress: impl FnMut(u64, u64),
+    cancelled: impl Fn() -> bool,
+) -> Result<()> {
+    let password = crate::secrets::get(KEYRING_ACCOUNT)
+        .context("Failed to load WebDAV password")?
+        .context("Enter the WebDAV password first")?;
+    let server = Server {
+        client: Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .context("Failed to set up HTTP client")?,
+        username: config.username.clone(),
+        password,
+    };
+    let name = path
+        .file_name()
+        .context("Not a file")?
+        .to_string_lossy()
+        .to_string();
+    let total = std::fs::metadata(path)
+        .context("Failed to read file size")?
+        .len();
+    let mut destination = Url::parse(&config.url).context("Invalid WebDAV URL")?;
+    destination
+        .path_segments_mut()
+        .map_err(|_| anyhow::anyhow!("Invalid WebDAV URL"))?
+        .pop_if_empty()
+        .push(&name);
+
+    if cancelled() {
+        anyhow::bail!("Cancelled");
+    }
+    match nextcloud_uploads(&destination) {
+        Some(uploads) if total > CHUNK_SIZE => upload_chunked(
+            &server,
+            path,
+            total,
+            &destination,
+            uploads,
+            &mut progress,
+            &cancelled,
+        ),
+        _ => {
+            server.send(&format!("Failed to upload {}", name), || {
+                let file = std::fs::File::open(path).context("Failed to open file")?;
+                Ok(server
+                    .request(Method::PUT, &destination)
+                    .body(Body::sized(file, total)))
+            })?;
+            progress(total, total);
+            Ok(())
+        }
+    }
+}
+
+/// The uploads collection for `destination` when it is in a Nextcloud
+/// user's files, named for a new transfer.
+fn nextcloud_uploads(destination: &Url) -> Option<Url> {
+    let path = destination.path();
+    let start = path.find(NEXTCLOUD_FILES)?;
+    let user = path[start + NEXTCLOUD_FILES.len()..].split('/').next()?;
+    let mut transfer = [0u8; 8];
+    getrandom::getrandom(&mut transfer).ok()?;
+    let transfer: String = transfer.iter().map(|b| format!("{:02x}", b)).collect();
+    let mut uploads = destination.clone();
+    uploads.set_path(&format!(
+        "{}/remote.php/dav/uploads/{}/discrec-{}",
+        &path[..start],
+        user,
+        transfer
+    ));
+    Some(uploads)
+}
+
+/// Nextcloud chunked upload: create a transfer folder, put the chunks in
+/// it and have the server assemble them at `destination`. The transfer
+/// folder is removed again when anything fails.
+fn upload_chunked(
+    server: &Server,
+    path: &Path,
+    total: u64,
+    destination: &Url,
+    uploads: Url,
+    progress: &mut impl FnMut(u64, u64),
+    cancelled: &impl Fn() -> bool,
+) -> Result<()> {
+    let mkcol = Method::from_bytes(b"MKCOL").expect("valid method");
+    server.send("Failed to start the upload", || {
+        Ok(server
+            .request(mkcol.clone(), &uploads)
+            .header("Destination", destination.as_str()))
+    })?;
+
+    let result = (|| -> Result<()> {
+        let mut file = std::fs::File::open(path).context("Failed to open file")?;
+        let mut sent = 0;
+        for index in 1.. {
+            if cancelled() {
+                anyhow::bail!("Cancelled");
+            }
+            let mut chunk = Vec::with_capacity(CHUNK_SIZE as usize);
+            (&mut file)
+                .take(CHUNK_SIZE)
+                .read_to_end(&mut chunk)
+                .context("Failed to read file")?;
+            if chunk.is_empty() {
+                break;
+            }
+            let mut url = uploads.clone();
+            url.path_segments_mut()
+                .map_err(|_| anyhow::anyhow!("Invalid upload URL"))?
+                .push(&format!("{:05}", index));
+            server.send(&format!("Failed to upload part {}", index), || {
+                Ok(server
+                    .request(Method::PUT, &url)
+                    .header("Destination", destination.as_str())
+                    .header("OC-Total-Length", total)
+                    .body(chunk.clone()))
+            })?;
+            sent += chunk.len() as u64;
+            progress(sent, total);
+        }
+
+        let mut assembled = uploads.clone();
+        assembled
+            .path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("Invalid upload URL"))?
+            .push(".file");
+        let moved = Method::from_bytes(b"MOVE").expect("valid method");
+        server.send("Failed to finish the upload", || {
+            Ok(server
+                .request(moved.clone(), &assembled)
+                .header("Destination", destination.as_str())
+                .header("OC-Total-Length", total))
+        })?;
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = server.request(Method::DELETE, &uploads).send();
+    }
+    result
+}
@@ -0,0 +1,118 @@
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+
+use crate::commands::{start_bot_session, stop_bot_session, DiscordState};
+use crate::discord::bot::PresenceHook;
+use crate::overlay::OverlayState;
+use crate::settings::SettingsState;
+
+#[derive(Default)]
+struct Watched {
+    /// The watched channel the current bot session was started for, if any.
+    /// Only such sessions are stopped when their channel empties.
+    auto_started: Option<(u64, u64)>,
+    /// Last headcount reported per channel.
+    people: HashMap<(u64, u64), usize>,
+    /// Channels a recording was stopped in while people were still there.
+    /// They aren't recorded again until they have been seen empty, so a stop
+    /// sticks instead of the bot leaving starting the next session.
+    suppressed: HashSet<(u64, u64)>,
+}
+
+pub struct WatchState(Mutex<Watched>);
+
+impl WatchState {
+    pub fn new() -> Self {
+        Self(Mutex::new(Watched::default()))
+    }
+
+    /// Forget the auto-started session once recording of `channel` stops for
+    /// any reason, and hold off watching it while people are still in it.
+    pub fn session_stopped(&self, channel: Option<(u64, u64)>) {
+        let mut watched = self.0.lock();
+        watched.auto_started = None;
+        if let Some(channel) = channel {
+            if watched
+                .people
+                .get(&channel)
+                .is_some_and(|&people| people > 0)
+            {
+                watched.suppressed.insert(channel);
+            }
+        }
+    }
+}
+
+/// Starts and stops bot recordings of watched channels as people come and go.
+pub fn presence_hook(app: AppHandle) -> PresenceHook {
+    Arc::new(move |guild_id, channel_id, people| {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            channel_changed(&app, guild_id, channel_id, people).await;
+        });
+    })
+}
+
+async fn channel_changed(app: &AppHandle, guild_id: u64, channel_id: u64, people: usize) {
+    let discord = app.state::<DiscordState>();
+    let bot = discord.0.lock().await;
+    let watch = app.state::<WatchState>();
+    let channel = (guild_id, channel_id);
+    let (auto_started, suppressed) = {
+        let mut watched = watch.0.lock();
+        watched.people.insert(channel, people);
+        if people == 0 {
+            watched.suppressed.remove(&channel);
+        }
+        (
+            watched.auto_started == Some(channel),
+            watched.suppressed.contains(&channel),
+        )
+    };
+
+    if auto_started {
+        if people == 0 && bot.is_recording() {
+            log::info!("Watched channel {} is empty; stopping", channel_id);
+            if let Err(e) = stop_bot_session(app, &bot).await {
+                log::warn!("Failed to stop watched recording: {}", e);
+            }
+        }
+        return;
+    }
+
+    let rule = app
+        .state::<SettingsState>()
+        .0
+        .lock()
+        .watched_channel(guild_id, channel_id)
+        .cloned();
+    let Some(rule) = rule else {
+        return;
+    };
+    if people < rule.min_members as usize || bot.is_recording() || suppressed {
+        return;
+    }
+    log::info!(
+        "{} people in watched channel {}; recording",
+        people,
+        channel_id
+    );
+    let settings = app.state::<SettingsState>();
+    let overlay = app.state::<OverlayState>();
+    match start_bot_session(
+        &bot,
+        &settings,
+        &overlay,
+        guild_id,
+        channel_id,
+        rule.format,
+        false,
+    )
+    .await
+    {
+        Ok(()) => watch.0.lock().auto_started = Some(channel),
+        Err(e) => log::warn!("Failed to record watched channel {}: {}", channel_id, e),
+    }
+}
@@ -1,14 +1,16 @@
-import { useState, useEffect, useCallback, useRef } from "react";
+import { useState, useEffect, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { ask } from "@tauri-apps/plugin-dialog";
 import { useDiscord } from "./hooks/useDiscord";
 import { useRecorder } from "./hooks/useRecorder";
 import { useKeyboardShortcuts, type ShortcutConfig } from "./hooks/useKeyboardShortcuts";
+import { useObsSync } from "./hooks/useObsSync";
 import { RecordButton } from "./components/RecordButton";
 import { StatusBar } from "./components/StatusBar";
 import { AudioMeter } from "./components/AudioMeter";
 import { CompletedView } from "./components/CompletedView";
 import { SettingsPanel } from "./components/SettingsPanel";
-import { Disc3, AlertCircle, Settings } from "lucide-react";
+import { Disc3, AlertCircle, Settings, Trash2 } from "lucide-react";
 
 type Theme = "dark" | "light";
 
@@ -16,11 +18,9 @@ function App() {
   const discord = useDiscord();
   const recorder = useRecorder();
   const [showSettings, setShowSettings] = useState(false);
-  const [autoRecord, setAutoRecord] = useState(false);
   const [theme, setTheme] = useState<Theme>(() => {
     return (localStorage.getItem("discrec-theme") as Theme) || "dark";
   });
-  const prevMemberCount = useRef<number | null>(null);
   const [shortcuts, setShortcuts] = useState<ShortcutConfig>({ record: "ctrl+r", stop: "ctrl+s" });
 
   // Load shortcuts from settings
@@ -56,6 +56,19 @@ function App() {
     }
   };
 
+  const handleAbort = async () => {
+    const confirmed = await ask("Stop and delete everything recorded so far? This can't be undone.", {
+      title: "Abort recording",
+      kind: "warning",
+    });
+    if (!confirmed) return;
+    if (isDiscordMode) {
+      discord.abortRecording();
+    } else {
+      recorder.abortRecording();
+    }
+  };
+
   const handleReset = () => {
     if (isDiscordMode) {
       discord.reset();
@@ -77,19 +90,12 @@ function App() {
     shortcuts,
   });
 
-  // Detect auto-stop (max duration) — poll recording status
-  useEffect(() => {
-    if (!isRecording || isDiscordMode) return;
-    const check = setInterval(async () => {
-      try {
-        const status = await invoke<{ is_recording: boolean; peak_level: number }>("get_status");
-        if (!status.is_recording && recorder.state === "recording") {
-          recorder.stopRecording();
-        }
-      } catch { /* ignore */ }
-    }, 1000);
-    return () => clearInterval(check);
-  }, [isRecording, isDiscordMode, recorder]);
+  useObsSync({
+    onRecord: handleRecord,
+    onStop: handleStop,
+    isRecording,
+    canRecord,
+  });
 
   // Theme management
   const handleThemeChange = useCallback((newTheme: Theme) => {
@@ -103,45 +109,6 @@ function App() {
     document.documentElement.setAttribute("data-theme", theme);
   }, [theme]);
 
-  // Auto-record: poll channel member count when enabled
-  useEffect(() => {
-    if (!autoRecord || !isDiscordMode || !discord.selectedGuild || !discord.selectedChannel) {
-      prevMemberCount.current = null;
-      return;
-    }
-    if (isDone) return;
-
-    const poll = async () => {
-      try {
-        const count = await invoke<number>("discord_get_channel_members", {
-          guildId: discord.selectedGuild,
-          channelId: discord.selectedChannel,
-        });
-
-        const prev = prevMemberCount.current;
-        prevMemberCount.current = count;
-
-        // Skip first poll (establishing baseline)
-        if (prev === null) return;
-
-        // Someone joined and we're not recording → start
-        if (count > 0 && prev === 0 && !isRecording) {
-          discord.startRecording(recorder.format);
-        }
-        // Everyone left and we're recording → stop
-        if (count === 0 && prev > 0 && isRecording) {
-          discord.stopRecording();
-        }
-      } catch {
-        // ignore polling errors
-      }
-    };
-
-    const interval = setInterval(poll, 5000);
-    poll(); // initial poll
-    return () => clearInterval(interval);
-  }, [autoRecord, isDiscordMode, discord, isRecording, isDone, recorder.format]);
-
   return (
     <div className="relative flex items-center justify-center min-h-screen bg-bg-primary">
       {/* Settings overlay */}
@@ -160,8 +127,6 @@ function App() {
           onDiscordDisconnect={discord.disconnect}
           onSelectGuild={discord.selectGuild}
           onSelectChannel={discord.setSelectedChannel}
-          autoRecord={autoRecord}
-          onAutoRecordChange={setAutoRecord}
           theme={theme}
           onThemeChange={handleThemeChange}
         />
@@ -177,6 +142,7 @@ function App() {
           }
           duration={duration}
           onReset={handleReset}
+          checkEmptyTracks={isDiscordMode}
         />
       ) : (
         <div className="flex flex-col items-center gap-3">
@@ -207,7 +173,16 @@ function App() {
           {/* Status text */}
           <div className="h-10 flex items-center">
             {isRecording ? (
-              <StatusBar isRecording={true} duration={duration} />
+              <div className="flex items-center gap-3">
+                <StatusBar isRecording={true} duration={duration} />
+                <button
+                  onClick={handleAbort}
+                  className="p-1.5 rounded-lg text-text-muted/40 hover:text-record hover:bg-record/8 transition-all cursor-pointer"
+                  title="Abort and delete this recording"
+                >
+                  <Trash2 className="w-3.5 h-3.5" />
+                </button>
+              </div>
             ) : (
               <p className="text-[13px] text-text-muted/70">
                 {isDiscordMode
@@ -0,0 +1,213 @@
+import { useState, useEffect, useCallback } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Archive, FolderOpen, RefreshCw, Search, X, Lock, Import } from "lucide-react";
+import { cn } from "../lib/utils";
+import { runJob } from "../lib/jobs";
+
+interface ArchiveSummary {
+  path: string;
+  label: string;
+  indexed_at: string;
+  recordings: number;
+  size: number;
+  nonstandard: number;
+  online: boolean;
+}
+
+interface ArchivedRecording {
+  archive: string;
+  online: boolean;
+  path: string;
+  filename: string;
+  size: number;
+  modified: string;
+  format: string;
+}
+
+function formatSize(bytes: number): string {
+  if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+  if (bytes < 1024 * 1024 * 1024) return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+  return `${(bytes / (1024 * 1024 * 1024)).toFixed(1)} GB`;
+}
+
+/** Read-only archive locations: indexed, searchable, never modified. */
+export function ArchivePanel() {
+  const [archives, setArchives] = useState<ArchiveSummary[]>([]);
+  const [query, setQuery] = useState("");
+  const [results, setResults] = useState<ArchivedRecording[]>([]);
+  const [busy, setBusy] = useState<string | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    invoke<ArchiveSummary[]>("list_archives").then(setArchives).catch(() => {});
+  }, []);
+
+  const search = useCallback(async (q: string) => {
+    if (!q.trim()) {
+      setResults([]);
+      return;
+    }
+    try {
+      setResults(await invoke<ArchivedRecording[]>("search_archives", { query: q.trim() }));
+    } catch { /* ignore */ }
+  }, []);
+
+  useEffect(() => {
+    search(query);
+  }, [query, archives, search]);
+
+  const handleAdd = async () => {
+    const selected = await invoke<string | null>("pick_folder", { title: "Choose archive folder" });
+    if (!selected) return;
+    setError(null);
+    setBusy("add");
+    try {
+      const summary = await invoke<ArchiveSummary>("add_archive", { path: selected });
+      setArchives((prev) => [...prev, summary]);
+    } catch (e) {
+      setError(String(e));
+    } finally {
+      setBusy(null);
+    }
+  };
+
+  const handleReindex = async (path: string) => {
+    setError(null);
+    setBusy(path);
+    try {
+      const summary = await invoke<ArchiveSummary>("reindex_archive", { path });
+      setArchives((prev) => prev.map((a) => (a.path === path ? summary : a)));
+    } catch (e) {
+      setError(String(e));
+    } finally {
+      setBusy(null);
+    }
+  };
+
+  const handleRemove = async (path: string) => {
+    try {
+      setArchives(await invoke<ArchiveSummary[]>("remove_archive", { path }));
+    } catch { /* ignore */ }
+  };
+
+  const handleImport = async (path: string) => {
+    setError(null);
+    setBusy(path);
+    try {
+      await runJob(invoke<number>("import_recordings", { paths: [path], normalize: true }));
+    } catch (e) {
+      setError(String(e));
+    } finally {
+      setBusy(null);
+    }
+  };
+
+  const handleReveal = async (path: string) => {
+    try {
+      await invoke("reveal_in_file_manager", { path });
+    } catch (e) {
+      console.error("Failed to reveal recording:", e);
+    }
+  };
+
+  return (
+    <div className="flex flex-col gap-2">
+      <div className="flex items-center justify-between mb-1">
+        <p className="text-[11px] text-text-muted/50">
+          {archives.length ? `${archives.length} archive${archives.length !== 1 ? "s" : ""} · read-only` : "Index finished seasons on other drives"}
+        </p>
+        <button
+          onClick={handleAdd}
+          disabled={busy !== null}
+          className="px-3 py-1.5 rounded-lg bg-bg-primary border border-border/50 text-[11px] font-medium text-text-secondary hover:text-text-primary hover:bg-bg-elevated transition-all cursor-pointer shrink-0 disabled:opacity-50"
+        >
+          {busy === "add" ? "Indexing…" : "Add archive…"}
+        </button>
+      </div>
+
+      {archives.map((a) => (
+        <div key={a.path} className="group flex items-center gap-3 px-3 py-2.5 rounded-xl bg-bg-primary/60 border border-border/30">
+          <Archive className={cn("w-3.5 h-3.5 shrink-0", a.online ? "text-success" : "text-text-muted/40")} />
+          <div className="flex-1 min-w-0">
+            <p className="text-[12px] text-text-primary truncate leading-snug" title={a.path}>{a.label}</p>
+            <p className="text-[10px] text-text-muted/50 leading-snug mt-0.5">
+              {a.recordings} recording{a.recordings !== 1 ? "s" : ""} · {formatSize(a.size)}
+              {a.nonstandard > 0 && ` · ${a.nonstandard} to normalize`} · {a.online ? "connected" : "offline"}
+            </p>
+          </div>
+          <div className="flex items-center gap-1 opacity-0 group-hover:opacity-100 transition-opacity">
+            {a.online && (
+              <button
+                onClick={() => handleReindex(a.path)}
+                disabled={busy !== null}
+                className="p-1.5 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+                title="Reindex"
+              >
+                <RefreshCw className={cn("w-3.5 h-3.5", busy === a.path && "animate-spin")} />
+              </button>
+            )}
+            <button
+              onClick={() => handleRemove(a.path)}
+              className="p-1.5 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+              title="Forget archive (files are kept)"
+            >
+              <X className="w-3.5 h-3.5" />
+            </button>
+          </div>
+        </div>
+      ))}
+
+      {archives.length > 0 && (
+        <div className="flex items-center gap-2 px-3 py-2 rounded-xl bg-bg-primary border border-border/50">
+          <Search className="w-3.5 h-3.5 text-text-muted/40 shrink-0" />
+          <input
+            value={query}
+            onChange={(e) => setQuery(e.target.value)}
+            placeholder="Search archives"
+            className="flex-1 bg-transparent text-[12px] text-text-primary outline-none placeholder:text-text-muted/40"
+          />
+        </div>
+      )}
+
+      {results.length > 0 && (
+        <div className="flex flex-col gap-1.5 max-h-[200px] overflow-y-auto pr-1">
+          {results.map((rec) => (
+            <div
+              key={rec.path}
+              className="group flex items-center gap-3 px-3 py-2.5 rounded-xl bg-bg-primary/60 border border-border/30 hover:border-border/60 transition-colors"
+            >
+              <Lock className="w-3 h-3 text-text-muted/40 shrink-0" />
+              <div className="flex-1 min-w-0">
+                <p className="text-[12px] text-text-primary truncate leading-snug">{rec.filename}</p>
+                <p className="text-[10px] text-text-muted/50 leading-snug mt-0.5">
+                  {rec.archive} · {formatSize(rec.size)} · {rec.modified.slice(0, 16)}
+                </p>
+              </div>
+              {rec.online && (
+                <div className="flex items-center gap-1 opacity-0 group-hover:opacity-100 transition-opacity">
+                  <button
+                    onClick={() => handleImport(rec.path)}
+                    disabled={busy !== null}
+                    className="p-1.5 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+                    title="Copy into library (converted to 48 kHz stereo if needed)"
+                  >
+                    <Import className={cn("w-3.5 h-3.5", busy === rec.path && "animate-pulse")} />
+                  </button>
+                  <button
+                    onClick={() => handleReveal(rec.path)}
+                    className="p-1.5 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+                    title="Open folder"
+                  >
+                    <FolderOpen className="w-3.5 h-3.5" />
+                  </button>
+                </div>
+              )}
+            </div>
+          ))}
+        </div>
+      )}
+
+      {error && <p className="text-[11px] text-record/80">{error}</p>}
+    </div>
+  );
+}
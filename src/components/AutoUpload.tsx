@@ -0,0 +1,244 @@
+import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { open } from "@tauri-apps/plugin-shell";
+import { CloudUpload } from "lucide-react";
+import { cn } from "../lib/utils";
+import type { JobStatus } from "../lib/jobs";
+
+type UploadTarget = "google_drive" | "webdav";
+
+interface UploadSettings {
+  target: UploadTarget | null;
+  include_manifests: boolean;
+  google_drive: {
+    client_id: string;
+    client_secret: string;
+    folder_id: string | null;
+  };
+  webdav: {
+    url: string;
+    username: string;
+  };
+}
+
+interface DeviceCode {
+  user_code: string;
+  verification_url: string;
+  expires_in: number;
+}
+
+interface AuthStatus {
+  signed_in: boolean;
+  error: string | null;
+}
+
+const inputClass =
+  "px-2.5 py-1.5 rounded-lg bg-bg-primary border border-border/50 text-[12px] text-text-primary placeholder:text-text-muted/30 focus:outline-none focus:border-accent/50 transition-colors";
+
+const selectClass =
+  "text-[11px] bg-bg-primary border border-border/50 rounded-lg px-2 py-1 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors";
+
+const buttonClass =
+  "px-3 py-1.5 rounded-lg bg-bg-primary border border-border/50 text-[11px] font-medium text-text-secondary hover:text-text-primary hover:bg-bg-elevated transition-all cursor-pointer";
+
+function formatBytes(bytes: number): string {
+  if (bytes < 1024 * 1024) return `${Math.round(bytes / 1024)} KB`;
+  return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+}
+
+/** Uploads saved recordings to Google Drive or a WebDAV folder such as Nextcloud. */
+export function AutoUpload() {
+  const [config, setConfig] = useState<UploadSettings | null>(null);
+  const [signedIn, setSignedIn] = useState(false);
+  const [hasPassword, setHasPassword] = useState(false);
+  const [password, setPassword] = useState("");
+  const [deviceCode, setDeviceCode] = useState<DeviceCode | null>(null);
+  const [upload, setUpload] = useState<JobStatus | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    invoke<UploadSettings>("get_upload_settings").then(setConfig).catch(() => {});
+    invoke<boolean>("get_google_drive_signed_in").then(setSignedIn).catch(() => {});
+    invoke<boolean>("get_webdav_has_password").then(setHasPassword).catch(() => {});
+    const listeners = [
+      listen<AuthStatus>("upload://google-drive-auth", ({ payload }) => {
+        setDeviceCode(null);
+        setSignedIn(payload.signed_in);
+        setError(payload.error);
+      }),
+      listen<JobStatus>("job://progress", ({ payload }) => {
+        if (payload.kind === "upload") setUpload(payload);
+      }),
+      listen<JobStatus>("job://finished", ({ payload }) => {
+        if (payload.kind === "upload") setUpload(payload);
+      }),
+    ];
+    return () => {
+      listeners.forEach((unlisten) => unlisten.then((fn) => fn()));
+    };
+  }, []);
+
+  if (!config) return null;
+
+  const save = async (next: UploadSettings, webdavPassword?: string) => {
+    setConfig(next);
+    try {
+      await invoke("set_upload_settings", { config: next, webdavPassword: webdavPassword ?? null });
+      setError(null);
+    } catch (e) {
+      setError(String(e));
+    }
+  };
+
+  const updateDrive = (change: Partial<UploadSettings["google_drive"]>) =>
+    setConfig({ ...config, google_drive: { ...config.google_drive, ...change } });
+  const updateWebDav = (change: Partial<UploadSettings["webdav"]>) =>
+    setConfig({ ...config, webdav: { ...config.webdav, ...change } });
+
+  const handlePassword = async () => {
+    await save(config, password);
+    setHasPassword(password !== "");
+    setPassword("");
+  };
+
+  const handleSignIn = async () => {
+    try {
+      await invoke("set_upload_settings", { config, webdavPassword: null });
+      setDeviceCode(await invoke<DeviceCode>("start_google_drive_sign_in"));
+      setError(null);
+    } catch (e) {
+      setError(String(e));
+    }
+  };
+
+  const handleSignOut = async () => {
+    try {
+      await invoke("sign_out_google_drive");
+      setSignedIn(false);
+    } catch (e) {
+      setError(String(e));
+    }
+  };
+
+  const ready = config.target === "google_drive" ? signedIn : config.target === "webdav" ? hasPassword : false;
+  const statusText = !config.target
+    ? "Off"
+    : !ready
+      ? config.target === "google_drive" ? "Not signed in" : "No password saved"
+      : upload?.state === "running"
+        ? `Uploading ${upload.current} · ${formatBytes(upload.done)}${upload.total ? ` of ${formatBytes(upload.total)}` : ""}`
+        : upload?.state === "failed"
+          ? upload.error ?? "Upload failed"
+          : upload?.state === "completed"
+            ? "Last session uploaded"
+            : "Uploads new recordings";
+
+  return (
+    <div className="flex flex-col gap-3">
+      <div className="flex items-center justify-between gap-4">
+        <div className="flex items-center gap-3 min-w-0">
+          <CloudUpload className={cn("w-4 h-4 shrink-0", ready ? "text-success" : "text-text-muted/50")} />
+          <div className="min-w-0">
+            <p className="text-[13px] font-medium text-text-primary leading-snug">Auto-upload</p>
+            <p className="text-[11px] text-text-muted/60 leading-snug mt-0.5 truncate" title={statusText}>{statusText}</p>
+          </div>
+        </div>
+        <select
+          value={config.target ?? ""}
+          onChange={(e) => save({ ...config, target: (e.target.value || null) as UploadTarget | null })}
+          className={selectClass}
+        >
+          <option value="">Off</option>
+          <option value="google_drive">Google Drive</option>
+          <option value="webdav">WebDAV / Nextcloud</option>
+        </select>
+      </div>
+
+      {config.target === "google_drive" && (
+        <div className="flex flex-col gap-2.5 animate-fade-in">
+          <input
+            value={config.google_drive.client_id}
+            onChange={(e) => updateDrive({ client_id: e.target.value })}
+            onBlur={() => save(config)}
+            placeholder="OAuth client ID (TVs and Limited Input devices)"
+            className={inputClass}
+          />
+          <input
+            type="password"
+            value={config.google_drive.client_secret}
+            onChange={(e) => updateDrive({ client_secret: e.target.value })}
+            onBlur={() => save(config)}
+            placeholder="OAuth client secret"
+            className={inputClass}
+          />
+          <input
+            value={config.google_drive.folder_id ?? ""}
+            onChange={(e) => updateDrive({ folder_id: e.target.value || null })}
+            onBlur={() => save(config)}
+            placeholder="Folder ID (leave empty for My Drive)"
+            className={inputClass}
+          />
+          {deviceCode ? (
+            <p className="text-[11px] text-text-muted/60">
+              Enter <span className="font-mono text-text-primary">{deviceCode.user_code}</span> at{" "}
+              <button onClick={() => open(deviceCode.verification_url)} className="text-accent hover:underline cursor-pointer">
+                {deviceCode.verification_url}
+              </button>
+            </p>
+          ) : (
+            <div className="flex justify-end">
+              {signedIn ? (
+                <button onClick={handleSignOut} className={buttonClass}>Sign out</button>
+              ) : (
+                <button onClick={handleSignIn} className={buttonClass}>Sign in…</button>
+              )}
+            </div>
+          )}
+        </div>
+      )}
+
+      {config.target === "webdav" && (
+        <div className="flex flex-col gap-2.5 animate-fade-in">
+          <input
+            value={config.webdav.url}
+            onChange={(e) => updateWebDav({ url: e.target.value })}
+            onBlur={() => save(config)}
+            placeholder="https://cloud.example.com/remote.php/dav/files/you/DiscRec"
+            className={inputClass}
+          />
+          <input
+            value={config.webdav.username}
+            onChange={(e) => updateWebDav({ username: e.target.value })}
+            onBlur={() => save(config)}
+            placeholder="Username"
+            className={inputClass}
+          />
+          <input
+            type="password"
+            value={password}
+            onChange={(e) => setPassword(e.target.value)}
+            onBlur={() => password && handlePassword()}
+            onKeyDown={(e) => e.key === "Enter" && handlePassword()}
+            placeholder={hasPassword ? "App password (leave empty to keep)" : "App password"}
+            className={inputClass}
+          />
+        </div>
+      )}
+
+      {config.target && (
+        <label className="flex items-center gap-2 text-[12px] text-text-secondary cursor-pointer">
+          <input
+            type="checkbox"
+            checked={config.include_manifests}
+            onChange={(e) => save({ ...config, include_manifests: e.target.checked })}
+            className="accent-success cursor-pointer"
+          />
+          Upload bot session manifests too
+        </label>
+      )}
+
+      {error && <p className="text-[11px] text-record/80">{error}</p>}
+    </div>
+  );
+}
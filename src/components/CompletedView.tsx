@@ -1,13 +1,85 @@
-import { CheckCircle, FolderOpen, RotateCcw } from "lucide-react";
+import { useEffect, useState } from "react";
+import { CheckCircle, FolderOpen, RotateCcw, Trash2, Film, Undo2 } from "lucide-react";
 import { invoke } from "@tauri-apps/api/core";
 
+interface TrackReport {
+  path: string;
+  user_id: string | null;
+  name: string | null;
+  duration_secs: number;
+  silence_ratio: number;
+  near_empty: boolean;
+}
+
+/** A deleted track kept in the trash until restored or emptied. */
+interface TrashEntry {
+  id: string;
+  original: string;
+  deleted_at: string;
+  size: number;
+  files: string[];
+}
+
+interface RemovedTracks {
+  paths: string[];
+  trashed: TrashEntry[];
+}
+
 interface CompletedViewProps {
   filePath: string | null;
   duration: number;
   onReset: () => void;
+  /** Offer to remove near-silent speaker tracks (bot recordings). */
+  checkEmptyTracks?: boolean;
 }
 
-export function CompletedView({ filePath, duration, onReset }: CompletedViewProps) {
+export function CompletedView({ filePath, duration, onReset, checkEmptyTracks }: CompletedViewProps) {
+  const [emptyTracks, setEmptyTracks] = useState<TrackReport[]>([]);
+  const [frameExport, setFrameExport] = useState<string | null>(null);
+  const [removed, setRemoved] = useState<{ tracks: TrackReport[]; trashed: TrashEntry[] } | null>(null);
+
+  useEffect(() => {
+    if (!checkEmptyTracks) return;
+    invoke<TrackReport[]>("discord_get_track_stats")
+      .then((tracks) => setEmptyTracks(tracks.filter((t) => t.near_empty)))
+      .catch(() => {});
+  }, [checkEmptyTracks]);
+
+  const removeEmptyTracks = async () => {
+    try {
+      const { paths, trashed } = await invoke<RemovedTracks>("cleanup_empty_tracks", {
+        paths: emptyTracks.map((t) => t.path),
+      });
+      setRemoved({ tracks: emptyTracks.filter((t) => paths.includes(t.path)), trashed });
+      setEmptyTracks((tracks) => tracks.filter((t) => !paths.includes(t.path)));
+    } catch (e) {
+      console.error("Failed to remove empty tracks:", e);
+    }
+  };
+
+  const undoRemoveTracks = async () => {
+    if (!removed) return;
+    setRemoved(null);
+    try {
+      for (const entry of removed.trashed) {
+        await invoke("restore_recording", { id: entry.id });
+      }
+      setEmptyTracks((tracks) => [...tracks, ...removed.tracks]);
+    } catch (e) {
+      console.error("Failed to restore tracks:", e);
+    }
+  };
+
+  const exportSpeakerFrames = async (format: "csv" | "json") => {
+    try {
+      const path = await invoke<string>("discord_export_speaker_frames", { format, fps: 30 });
+      setFrameExport(`Saved ${path.split(/[/\\]/).pop()}`);
+      await invoke("reveal_in_file_manager", { path });
+    } catch (e) {
+      setFrameExport(String(e));
+    }
+  };
+
   const formatDuration = (seconds: number): string => {
     const mins = Math.floor(seconds / 60);
     const secs = seconds % 60;
@@ -20,9 +92,14 @@ export function CompletedView({ filePath, duration, onReset }: CompletedViewProp
   const openFolder = async () => {
     if (!filePath) return;
     try {
-      await invoke("open_folder", { path: filePath });
-    } catch (e) {
-      console.error("Failed to open folder:", e);
+      await invoke("reveal_in_file_manager", { path: filePath });
+    } catch {
+      // Bot sessions point at the folder rather than a single file
+      try {
+        await invoke("open_folder", { path: filePath });
+      } catch (e) {
+        console.error("Failed to open folder:", e);
+      }
     }
   };
 
@@ -45,6 +122,57 @@ export function CompletedView({ filePath, duration, onReset }: CompletedViewProp
         </p>
       </div>
 
+      {/* Near-silent speaker tracks */}
+      {emptyTracks.length > 0 && (
+        <div className="flex items-center gap-3 text-[12px] text-text-muted">
+          <span title={emptyTracks.map((t) => t.name ?? t.user_id ?? "unknown speaker").join(", ")}>
+            {emptyTracks.length} near-silent track{emptyTracks.length === 1 ? "" : "s"}
+          </span>
+          <button
+            onClick={removeEmptyTracks}
+            className="flex items-center gap-1.5 px-3 py-1 rounded-lg border border-border/50 hover:border-record/50 hover:text-record transition-all cursor-pointer"
+          >
+            <Trash2 className="w-3.5 h-3.5" />
+            Remove
+          </button>
+        </div>
+      )}
+
+      {removed && removed.trashed.length > 0 && (
+        <div className="flex items-center gap-3 text-[12px] text-text-muted">
+          <span>
+            Moved {removed.trashed.length} track{removed.trashed.length === 1 ? "" : "s"} to the trash, where they still take up space until emptied
+          </span>
+          <button
+            onClick={undoRemoveTracks}
+            className="flex items-center gap-1.5 px-3 py-1 rounded-lg border border-border/50 hover:border-border hover:text-text-primary transition-all cursor-pointer"
+          >
+            <Undo2 className="w-3.5 h-3.5" />
+            Undo
+          </button>
+        </div>
+      )}
+
+      {/* Per-frame speaker data for video editors */}
+      {checkEmptyTracks && (
+        <div className="flex flex-col items-center gap-1.5 text-[12px] text-text-muted">
+          <div className="flex items-center gap-3">
+            <Film className="w-3.5 h-3.5" />
+            <span>Speaker frames (30 fps)</span>
+            {(["csv", "json"] as const).map((format) => (
+              <button
+                key={format}
+                onClick={() => exportSpeakerFrames(format)}
+                className="px-3 py-1 rounded-lg border border-border/50 hover:border-border hover:text-text-primary transition-all cursor-pointer uppercase text-[11px]"
+              >
+                {format}
+              </button>
+            ))}
+          </div>
+          {frameExport && <p className="text-[11px] text-text-muted/60">{frameExport}</p>}
+        </div>
+      )}
+
       {/* Actions */}
       <div className="flex gap-3">
         <button
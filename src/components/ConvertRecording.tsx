@@ -0,0 +1,139 @@
+import { useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Loader2, X } from "lucide-react";
+import { runJob, cancelJob, type JobStatus } from "../lib/jobs";
+
+type ConvertFormat = "flac" | "mp3" | "opus";
+
+const bitrates: Record<Exclude<ConvertFormat, "flac">, number[]> = {
+  mp3: [128, 192, 256, 320],
+  opus: [64, 96, 128, 160],
+};
+
+const defaultBitrate = { mp3: 192, opus: 96 };
+
+const selectClass =
+  "text-[11px] bg-bg-primary border border-border/50 rounded-lg px-2 py-1 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors";
+
+/** A deleted recording kept in the trash until restored or emptied. */
+export interface TrashEntry {
+  id: string;
+  original: string;
+  deleted_at: string;
+  size: number;
+  files: string[];
+}
+
+interface ConvertedRecording {
+  /** The original, when it was moved to the trash. */
+  trashed: TrashEntry | null;
+}
+
+interface ConvertRecordingProps {
+  path: string;
+  format: string;
+  onDone: (trashed: TrashEntry | null) => void;
+  onClose: () => void;
+}
+
+/** Options for transcoding one recording, with progress while it runs. */
+export function ConvertRecording({ path, format, onDone, onClose }: ConvertRecordingProps) {
+  const [target, setTarget] = useState<ConvertFormat>(format === "flac" ? "mp3" : "flac");
+  const [quality, setQuality] = useState(defaultBitrate.mp3);
+  const [deleteOriginal, setDeleteOriginal] = useState(false);
+  const [job, setJob] = useState<JobStatus | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  const handleTarget = (next: ConvertFormat) => {
+    setTarget(next);
+    if (next !== "flac") setQuality(defaultBitrate[next]);
+  };
+
+  const handleConvert = async () => {
+    setError(null);
+    try {
+      const id = await invoke<number>("convert_recording", {
+        path,
+        targetFormat: target,
+        quality: target === "flac" ? null : quality,
+        deleteOriginal,
+      });
+      setJob({ id, kind: "convert", state: "running", done: 0, total: null, current: "", error: null, result: null });
+      const { trashed } = await runJob<ConvertedRecording>(id, setJob);
+      onDone(trashed);
+    } catch (e) {
+      if (e !== "Cancelled") setError(String(e));
+    } finally {
+      setJob(null);
+    }
+  };
+
+  if (job) {
+    const percent = job.total ? Math.round((job.done / job.total) * 100) : null;
+    return (
+      <div className="flex items-center gap-2 animate-fade-in">
+        <Loader2 className="w-3.5 h-3.5 text-text-muted/50 animate-spin shrink-0" />
+        <p className="flex-1 text-[11px] text-text-secondary">
+          Converting to {target.toUpperCase()}
+          {percent !== null && ` · ${percent}%`}
+        </p>
+        <button
+          onClick={() => cancelJob(job.id).catch(() => {})}
+          className="p-1 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+          title="Cancel conversion"
+        >
+          <X className="w-3 h-3" />
+        </button>
+      </div>
+    );
+  }
+
+  return (
+    <div className="flex flex-col gap-2 animate-fade-in">
+      <div className="flex items-center gap-2">
+        <select value={target} onChange={(e) => handleTarget(e.target.value as ConvertFormat)} className={selectClass}>
+          {(["flac", "mp3", "opus"] as const)
+            .filter((f) => f !== format)
+            .map((f) => (
+              <option key={f} value={f}>
+                {f.toUpperCase()}
+              </option>
+            ))}
+        </select>
+        {target !== "flac" && (
+          <select value={quality} onChange={(e) => setQuality(Number(e.target.value))} className={selectClass}>
+            {bitrates[target].map((kbps) => (
+              <option key={kbps} value={kbps}>
+                {kbps} kbps
+              </option>
+            ))}
+          </select>
+        )}
+        <label className="flex items-center gap-1.5 text-[11px] text-text-secondary cursor-pointer">
+          <input
+            type="checkbox"
+            checked={deleteOriginal}
+            onChange={(e) => setDeleteOriginal(e.target.checked)}
+            className="accent-success cursor-pointer"
+          />
+          Delete original
+        </label>
+        <div className="flex-1" />
+        <button
+          onClick={handleConvert}
+          className="px-3 py-1 rounded-lg bg-bg-elevated text-[11px] font-medium text-text-secondary hover:text-text-primary transition-all cursor-pointer"
+        >
+          Convert
+        </button>
+        <button
+          onClick={onClose}
+          className="p-1 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+          title="Close"
+        >
+          <X className="w-3 h-3" />
+        </button>
+      </div>
+      {error && <p className="text-[11px] text-record/80">{error}</p>}
+    </div>
+  );
+}
@@ -1,6 +1,8 @@
-import { useState } from "react";
-import { Plug, Unplug, Loader2, ChevronDown, CheckCircle2 } from "lucide-react";
+import { useState, useEffect, useCallback } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Plug, Unplug, Loader2, ChevronDown, CheckCircle2, MicOff, HardDrive, Gavel, ListChecks, Info } from "lucide-react";
 import { cn } from "../lib/utils";
+import type { AudioFormat } from "./FormatSelector";
 
 interface GuildInfo {
   id: string;
@@ -13,6 +15,31 @@ interface VoiceChannelInfo {
   guild_id: string;
 }
 
+interface ChannelMember {
+  id: string;
+  name: string;
+  excluded: boolean;
+}
+
+/** Only the picked users, or everyone but the excluded ones. */
+interface RecordedUsers {
+  mode: "only" | "except";
+  user_ids: string[];
+  excluded_ids: string[];
+}
+
+interface SizeEstimate {
+  bytes: number;
+  required_bytes: number;
+  available_bytes: number | null;
+  fits: boolean;
+}
+
+function formatBytes(bytes: number): string {
+  if (bytes >= 1024 ** 3) return `${(bytes / 1024 ** 3).toFixed(1)} GB`;
+  return `${Math.max(1, Math.round(bytes / 1024 ** 2))} MB`;
+}
+
 interface DiscordPanelProps {
   connected: boolean;
   connecting: boolean;
@@ -20,6 +47,7 @@ interface DiscordPanelProps {
   channels: VoiceChannelInfo[];
   selectedGuild: string | null;
   selectedChannel: string | null;
+  format: AudioFormat;
   onConnect: (token: string) => void;
   onDisconnect: () => void;
   onSelectGuild: (guildId: string) => void;
@@ -33,12 +61,133 @@ export function DiscordPanel({
   channels,
   selectedGuild,
   selectedChannel,
+  format,
   onConnect,
   onDisconnect,
   onSelectGuild,
   onSelectChannel,
 }: DiscordPanelProps) {
   const [tokenInput, setTokenInput] = useState("");
+  const [members, setMembers] = useState<ChannelMember[]>([]);
+  const [estimate, setEstimate] = useState<SizeEstimate | null>(null);
+  const [skipServerMuted, setSkipServerMuted] = useState(false);
+  const [recorded, setRecorded] = useState<RecordedUsers | null>(null);
+  const [processingNote, setProcessingNote] = useState<string | null>(null);
+
+  useEffect(() => {
+    if (!selectedGuild) return;
+    invoke<boolean>("get_skip_server_muted", { guildId: selectedGuild })
+      .then(setSkipServerMuted)
+      .catch(() => setSkipServerMuted(false));
+  }, [selectedGuild]);
+
+  const toggleSkipServerMuted = async () => {
+    if (!selectedGuild) return;
+    try {
+      await invoke("set_skip_server_muted", { guildId: selectedGuild, enabled: !skipServerMuted });
+      setSkipServerMuted(!skipServerMuted);
+    } catch (e) {
+      console.error("Failed to update server-mute setting:", e);
+    }
+  };
+
+  const loadMembers = useCallback(() => {
+    if (!selectedGuild || !selectedChannel) {
+      setMembers([]);
+      return;
+    }
+    invoke<ChannelMember[]>("discord_list_channel_members", {
+      guildId: selectedGuild,
+      channelId: selectedChannel,
+    })
+      .then(setMembers)
+      .catch(() => setMembers([]));
+  }, [selectedGuild, selectedChannel]);
+
+  const loadRecorded = useCallback(() => {
+    if (!selectedGuild) return;
+    invoke<RecordedUsers>("discord_get_recorded_users", { guildId: selectedGuild })
+      .then(setRecorded)
+      .catch(() => setRecorded(null));
+  }, [selectedGuild]);
+
+  useEffect(() => loadMembers(), [loadMembers]);
+  useEffect(() => loadRecorded(), [loadRecorded]);
+
+  const setRecordedUsers = async (mode: RecordedUsers["mode"], userIds: string[]) => {
+    await invoke("discord_set_recorded_users", { guildId: selectedGuild, userIds, mode });
+  };
+
+  const toggleExcluded = async (member: ChannelMember) => {
+    if (!selectedGuild) return;
+    try {
+      if (recorded?.mode === "only") {
+        const picked = new Set(recorded.user_ids);
+        if (member.excluded) {
+          picked.add(member.id);
+          // Picking someone who was excluded lets them back in
+          if (recorded.excluded_ids.includes(member.id)) {
+            await invoke("discord_set_user_excluded", { guildId: selectedGuild, userId: member.id, excluded: false });
+          }
+        } else {
+          picked.delete(member.id);
+        }
+        await setRecordedUsers("only", [...picked]);
+      } else {
+        await invoke("discord_set_user_excluded", {
+          guildId: selectedGuild,
+          userId: member.id,
+          excluded: !member.excluded,
+        });
+      }
+    } catch (e) {
+      console.error("Failed to update exclusion:", e);
+    }
+    loadRecorded();
+    loadMembers();
+  };
+
+  const toggleRecordedMode = async () => {
+    if (!recorded) return;
+    try {
+      if (recorded.mode === "only") {
+        await setRecordedUsers("except", recorded.excluded_ids);
+      } else {
+        // Start from whoever would be recorded right now
+        await setRecordedUsers("only", members.filter((m) => !m.excluded).map((m) => m.id));
+      }
+    } catch (e) {
+      console.error("Failed to update recorded users:", e);
+    }
+    loadRecorded();
+    loadMembers();
+  };
+
+  const excludedCount = members.filter((m) => m.excluded).length;
+  const speakerCount = members.length - excludedCount;
+
+  // One mono track per speaker who will be recorded
+  useEffect(() => {
+    if (!selectedChannel) {
+      setEstimate(null);
+      return;
+    }
+    invoke<SizeEstimate>("estimate_recording_size", {
+      format,
+      channels: 1,
+      speakers: Math.max(1, speakerCount),
+    })
+      .then(setEstimate)
+      .catch(() => setEstimate(null));
+  }, [selectedChannel, format, speakerCount]);
+
+  // Read from Discord's own settings, which can change between sessions
+  useEffect(() => {
+    if (!selectedChannel) return;
+    invoke<string>("discord_processing_note")
+      .then(setProcessingNote)
+      .catch(() => setProcessingNote(null));
+  }, [selectedChannel]);
 
   const handleConnect = () => {
     if (tokenInput.trim()) {
@@ -120,6 +269,19 @@ export function DiscordPanel({
           </select>
           <ChevronDown className="absolute right-3 top-1/2 -translate-y-1/2 w-4 h-4 text-text-muted/30 pointer-events-none" />
         </div>
+        {selectedGuild && (
+          <button
+            onClick={toggleSkipServerMuted}
+            className={cn(
+              "flex items-center gap-1.5 text-[11px] transition-colors cursor-pointer",
+              skipServerMuted ? "text-accent" : "text-text-muted/50 hover:text-text-secondary"
+            )}
+            title="Write nothing for members while a moderator has them server-muted"
+          >
+            <Gavel className="w-3 h-3" />
+            {skipServerMuted ? "Skipping server-muted members" : "Record server-muted members"}
+          </button>
+        )}
       </div>
 
       {/* Channel selector */}
@@ -148,11 +310,76 @@ export function DiscordPanel({
         </div>
       )}
 
+      {/* Who is in the channel; excluded users stay out of the recording */}
+      {selectedChannel && recorded && (
+        <button
+          onClick={toggleRecordedMode}
+          className={cn(
+            "flex items-center gap-1.5 text-[11px] transition-colors cursor-pointer animate-fade-in",
+            recorded.mode === "only" ? "text-accent" : "text-text-muted/50 hover:text-text-secondary"
+          )}
+          title="Record only the members you pick, instead of everyone who isn't excluded"
+        >
+          <ListChecks className="w-3 h-3" />
+          {recorded.mode === "only" ? "Recording picked members only" : "Recording everyone not excluded"}
+        </button>
+      )}
+      {selectedChannel && members.length > 0 && (
+        <div className="flex flex-wrap gap-1.5 animate-fade-in">
+          {members.map((m) => (
+            <button
+              key={m.id}
+              onClick={() => toggleExcluded(m)}
+              className={cn(
+                "flex items-center gap-1 px-2 py-1 rounded-lg text-[11px] border transition-colors cursor-pointer",
+                m.excluded
+                  ? "border-record/30 text-record/80 line-through"
+                  : "border-border/50 text-text-secondary hover:text-text-primary"
+              )}
+              title={
+                recorded?.mode === "only"
+                  ? m.excluded
+                    ? "Not picked — click to record"
+                    : "Picked — click to leave out"
+                  : m.excluded
+                    ? "Not recorded in this server — click to include"
+                    : "Click to leave out of recordings in this server"
+              }
+            >
+              {m.excluded && <MicOff className="w-3 h-3" />}
+              {m.name}
+            </button>
+          ))}
+        </div>
+      )}
+
       {/* Ready indicator */}
       {selectedChannel && (
         <div className="flex items-center gap-2 animate-fade-in">
           <CheckCircle2 className="w-4 h-4 text-success" />
-          <p className="text-[13px] text-success/80">Ready to record</p>
+          <p className="text-[13px] text-success/80">
+            Ready to record
+            {excludedCount > 0 && ` · ${excludedCount} excluded`}
+          </p>
+        </div>
+      )}
+
+      {/* Disk space for the session, with headroom */}
+      {selectedChannel && estimate && (
+        <div className="flex items-center gap-2 animate-fade-in">
+          <HardDrive className={cn("w-3.5 h-3.5", estimate.fits ? "text-text-muted/40" : "text-record/80")} />
+          <p className={cn("text-[11px]", estimate.fits ? "text-text-muted/60" : "text-record/80")}>
+            {estimate.fits ? "" : "Low disk space: "}~{formatBytes(estimate.bytes)} needed
+            {estimate.available_bytes !== null && ` · ${formatBytes(estimate.available_bytes)} free`}
+          </p>
+        </div>
+      )}
+
+      {/* Discord's own voice processing, so differing stems aren't a surprise */}
+      {selectedChannel && processingNote && (
+        <div className="flex items-start gap-2 animate-fade-in">
+          <Info className="w-3.5 h-3.5 mt-px shrink-0 text-text-muted/40" />
+          <p className="text-[11px] text-text-muted/60">{processingNote}</p>
         </div>
       )}
     </div>
@@ -0,0 +1,86 @@
+import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { FileText, RotateCcw } from "lucide-react";
+
+interface FilenamePreview {
+  template: string;
+  local: string;
+  speaker: string;
+}
+
+const DEFAULT_TEMPLATE = "discord-{timestamp}-{guild}-{channel}-{speaker}";
+
+/** How new recordings are named, with example names as the template is typed. */
+export function FilenameTemplate() {
+  const [template, setTemplate] = useState<string | null>(null);
+  const [preview, setPreview] = useState<FilenamePreview | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    invoke<string>("get_filename_template").then(setTemplate).catch(() => {});
+  }, []);
+
+  useEffect(() => {
+    if (template === null) return;
+    invoke<FilenamePreview>("preview_filename_template", { template })
+      .then((p) => {
+        setPreview(p);
+        setError(null);
+      })
+      .catch((e) => {
+        setPreview(null);
+        setError(String(e));
+      });
+  }, [template]);
+
+  if (template === null) return null;
+
+  const save = async (next: string) => {
+    try {
+      const saved = await invoke<FilenamePreview>("set_filename_template", { template: next });
+      setTemplate(saved.template);
+      setError(null);
+    } catch (e) {
+      setError(String(e));
+    }
+  };
+
+  return (
+    <div className="py-3">
+      <div className="flex items-center gap-3 mb-2">
+        <FileText className="w-4 h-4 shrink-0 text-text-muted/50" />
+        <div className="min-w-0">
+          <p className="text-[13px] font-medium text-text-primary leading-snug">File names</p>
+          <p className="text-[11px] text-text-muted/60 leading-snug mt-0.5">
+            Use {"{timestamp}"}, {"{date}"}, {"{time}"}, {"{guild}"}, {"{channel}"} and {"{speaker}"}
+          </p>
+        </div>
+      </div>
+      <div className="flex items-center gap-2">
+        <input
+          value={template}
+          onChange={(e) => setTemplate(e.target.value)}
+          onBlur={() => save(template)}
+          onKeyDown={(e) => e.key === "Enter" && save(template)}
+          placeholder={DEFAULT_TEMPLATE}
+          className="flex-1 min-w-0 px-2.5 py-1.5 rounded-lg bg-bg-primary border border-border/50 text-[12px] font-mono text-text-primary placeholder:text-text-muted/30 focus:outline-none focus:border-accent/50 transition-colors"
+        />
+        {template !== DEFAULT_TEMPLATE && (
+          <button
+            onClick={() => save("")}
+            className="p-1.5 rounded-lg text-text-muted/50 hover:text-text-primary transition-colors cursor-pointer"
+            title="Restore default"
+          >
+            <RotateCcw className="w-3.5 h-3.5" />
+          </button>
+        )}
+      </div>
+      {preview && (
+        <p className="text-[11px] text-text-muted/60 font-mono mt-1.5 truncate">
+          {preview.local} · {preview.speaker}
+        </p>
+      )}
+      {error && <p className="text-[11px] text-record/80 mt-1.5">{error}</p>}
+    </div>
+  );
+}
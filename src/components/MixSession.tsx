@@ -0,0 +1,158 @@
+import { useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Loader2, Volume2, VolumeX, X } from "lucide-react";
+import { runJob, cancelJob, type JobStatus } from "../lib/jobs";
+import { cn } from "../lib/utils";
+
+type MixFormat = "flac" | "wav" | "mp3" | "opus";
+
+interface MixTrack {
+  path: string;
+  name: string;
+}
+
+interface TrackMix {
+  gain_db: number;
+  pan: number;
+  muted: boolean;
+}
+
+const selectClass =
+  "text-[11px] bg-bg-primary border border-border/50 rounded-lg px-2 py-1 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors";
+
+interface MixSessionProps {
+  /** Path of the session manifest; the mix is saved next to it. */
+  manifest: string;
+  tracks: MixTrack[];
+  onDone: () => void;
+  onClose: () => void;
+}
+
+function formatPan(pan: number): string {
+  if (pan === 0) return "C";
+  return `${pan < 0 ? "L" : "R"}${Math.round(Math.abs(pan) * 100)}`;
+}
+
+/** Gain and pan per speaker track, mixed down to one stereo file. */
+export function MixSession({ manifest, tracks, onDone, onClose }: MixSessionProps) {
+  const [mix, setMix] = useState<TrackMix[]>(() => tracks.map(() => ({ gain_db: 0, pan: 0, muted: false })));
+  const [format, setFormat] = useState<MixFormat>("flac");
+  const [job, setJob] = useState<JobStatus | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  const update = (index: number, change: Partial<TrackMix>) =>
+    setMix((prev) => prev.map((track, i) => (i === index ? { ...track, ...change } : track)));
+
+  const handleMix = async () => {
+    setError(null);
+    try {
+      const output = `${manifest.replace(/-session\.json$/, "")}-mixdown.${format}`;
+      const id = await invoke<number>("mix_recordings", {
+        paths: tracks.map((t) => t.path),
+        output,
+        options: { tracks: mix },
+      });
+      setJob({ id, kind: "mix", state: "running", done: 0, total: null, current: "", error: null, result: null });
+      await runJob(id, setJob);
+      onDone();
+    } catch (e) {
+      if (e !== "Cancelled") setError(String(e));
+    } finally {
+      setJob(null);
+    }
+  };
+
+  if (job) {
+    const percent = job.total ? Math.round((job.done / job.total) * 100) : null;
+    return (
+      <div className="flex items-center gap-2 px-1.5 animate-fade-in">
+        <Loader2 className="w-3.5 h-3.5 text-text-muted/50 animate-spin shrink-0" />
+        <p className="flex-1 text-[11px] text-text-secondary">
+          Mixing {tracks.length} tracks
+          {percent !== null && ` · ${percent}%`}
+        </p>
+        <button
+          onClick={() => cancelJob(job.id).catch(() => {})}
+          className="p-1 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+          title="Cancel mixdown"
+        >
+          <X className="w-3 h-3" />
+        </button>
+      </div>
+    );
+  }
+
+  return (
+    <div className="flex flex-col gap-1.5 px-1.5 animate-fade-in">
+      {tracks.map((track, index) => (
+        <div key={track.path} className="flex items-center gap-2">
+          <button
+            onClick={() => update(index, { muted: !mix[index].muted })}
+            className={cn(
+              "p-1 rounded-lg transition-colors cursor-pointer",
+              mix[index].muted ? "text-record/70" : "text-text-muted/40 hover:text-text-primary"
+            )}
+            title={mix[index].muted ? "Include in mix" : "Leave out of mix"}
+          >
+            {mix[index].muted ? <VolumeX className="w-3 h-3" /> : <Volume2 className="w-3 h-3" />}
+          </button>
+          <p className="w-20 shrink-0 text-[11px] text-text-secondary truncate" title={track.name}>
+            {track.name}
+          </p>
+          <input
+            type="range"
+            min={-24}
+            max={12}
+            step={1}
+            value={mix[index].gain_db}
+            onChange={(e) => update(index, { gain_db: Number(e.target.value) })}
+            onDoubleClick={() => update(index, { gain_db: 0 })}
+            className="flex-1 min-w-0 accent-accent cursor-pointer"
+            title="Gain"
+          />
+          <span className="w-10 text-right text-[10px] tabular-nums text-text-muted/60">
+            {mix[index].gain_db > 0 ? "+" : ""}
+            {mix[index].gain_db} dB
+          </span>
+          <input
+            type="range"
+            min={-1}
+            max={1}
+            step={0.1}
+            value={mix[index].pan}
+            onChange={(e) => update(index, { pan: Number(e.target.value) })}
+            onDoubleClick={() => update(index, { pan: 0 })}
+            className="w-16 accent-accent cursor-pointer"
+            title="Pan"
+          />
+          <span className="w-7 text-right text-[10px] tabular-nums text-text-muted/60">{formatPan(mix[index].pan)}</span>
+        </div>
+      ))}
+      <div className="flex items-center gap-2 mt-1">
+        <select value={format} onChange={(e) => setFormat(e.target.value as MixFormat)} className={selectClass}>
+          {(["flac", "wav", "mp3", "opus"] as const).map((f) => (
+            <option key={f} value={f}>
+              {f.toUpperCase()}
+            </option>
+          ))}
+        </select>
+        <div className="flex-1" />
+        <button
+          onClick={handleMix}
+          disabled={mix.every((t) => t.muted)}
+          className="px-3 py-1 rounded-lg bg-bg-elevated text-[11px] font-medium text-text-secondary hover:text-text-primary transition-all cursor-pointer disabled:opacity-40 disabled:cursor-default"
+        >
+          Mix down
+        </button>
+        <button
+          onClick={onClose}
+          className="p-1 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+          title="Close"
+        >
+          <X className="w-3 h-3" />
+        </button>
+      </div>
+      {error && <p className="text-[11px] text-record/80">{error}</p>}
+    </div>
+  );
+}
@@ -0,0 +1,156 @@
+import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { Clapperboard } from "lucide-react";
+import { cn } from "../lib/utils";
+
+type ObsOutput = "record" | "stream" | "either";
+
+interface ObsSettings {
+  enabled: boolean;
+  host: string;
+  port: number;
+  output: ObsOutput;
+  follow_obs: boolean;
+  drive_obs: boolean;
+}
+
+interface ObsStatus {
+  connected: boolean;
+  recording: boolean;
+  streaming: boolean;
+  error: string | null;
+}
+
+const inputClass =
+  "px-2.5 py-1.5 rounded-lg bg-bg-primary border border-border/50 text-[12px] text-text-primary placeholder:text-text-muted/30 focus:outline-none focus:border-accent/50 transition-colors";
+
+const selectClass =
+  "text-[11px] bg-bg-primary border border-border/50 rounded-lg px-2 py-1 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors";
+
+function Check({ checked, onChange, label }: { checked: boolean; onChange: (v: boolean) => void; label: string }) {
+  return (
+    <label className="flex items-center gap-2 text-[12px] text-text-secondary cursor-pointer">
+      <input type="checkbox" checked={checked} onChange={(e) => onChange(e.target.checked)} className="accent-success cursor-pointer" />
+      {label}
+    </label>
+  );
+}
+
+/** Connection to obs-websocket that starts and stops recordings together with OBS. */
+export function ObsSync() {
+  const [config, setConfig] = useState<ObsSettings | null>(null);
+  const [password, setPassword] = useState("");
+  const [status, setStatus] = useState<ObsStatus | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    invoke<ObsSettings>("get_obs_settings").then(setConfig).catch(() => {});
+    invoke<ObsStatus>("get_obs_status").then(setStatus).catch(() => {});
+    const unlisten = listen<ObsStatus>("obs://status", ({ payload }) => setStatus(payload));
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  if (!config) return null;
+
+  const save = async (next: ObsSettings, newPassword?: string) => {
+    setConfig(next);
+    try {
+      await invoke("set_obs_settings", { config: next, password: newPassword ?? null });
+      setError(null);
+    } catch (e) {
+      setError(String(e));
+    }
+  };
+
+  const update = (change: Partial<ObsSettings>) => save({ ...config, ...change });
+
+  const handlePassword = () => {
+    save(config, password);
+    setPassword("");
+  };
+
+  const statusText = !config.enabled
+    ? "Off"
+    : status?.connected
+      ? [status.recording && "OBS recording", status.streaming && "OBS streaming"].filter(Boolean).join(" · ") || "Connected"
+      : status?.error ?? "Connecting…";
+
+  return (
+    <div className="flex flex-col gap-3">
+      <div className="flex items-center justify-between gap-4">
+        <div className="flex items-center gap-3 min-w-0">
+          <Clapperboard className={cn("w-4 h-4 shrink-0", config.enabled && status?.connected ? "text-success" : "text-text-muted/50")} />
+          <div className="min-w-0">
+            <p className="text-[13px] font-medium text-text-primary leading-snug">Sync with OBS</p>
+            <p className="text-[11px] text-text-muted/60 leading-snug mt-0.5 truncate" title={statusText}>{statusText}</p>
+          </div>
+        </div>
+        <button
+          onClick={() => update({ enabled: !config.enabled })}
+          className={cn(
+            "relative w-9 h-5 rounded-full transition-colors shrink-0 cursor-pointer",
+            config.enabled ? "bg-success" : "bg-border"
+          )}
+        >
+          <span
+            className={cn(
+              "absolute top-0.5 left-0.5 w-4 h-4 rounded-full bg-white shadow-sm transition-transform",
+              config.enabled && "translate-x-4"
+            )}
+          />
+        </button>
+      </div>
+
+      {config.enabled && (
+        <div className="flex flex-col gap-2.5 animate-fade-in">
+          <div className="flex items-center gap-2">
+            <input
+              value={config.host}
+              onChange={(e) => setConfig({ ...config, host: e.target.value })}
+              onBlur={() => save(config)}
+              placeholder="Host"
+              className={cn(inputClass, "flex-1 min-w-0")}
+            />
+            <input
+              type="number"
+              value={config.port}
+              min={1}
+              max={65535}
+              onChange={(e) => setConfig({ ...config, port: Number(e.target.value) })}
+              onBlur={() => save(config)}
+              className={cn(inputClass, "w-20")}
+            />
+          </div>
+          <input
+            type="password"
+            value={password}
+            onChange={(e) => setPassword(e.target.value)}
+            onBlur={() => password && handlePassword()}
+            onKeyDown={(e) => e.key === "Enter" && handlePassword()}
+            placeholder="obs-websocket password (leave empty to keep)"
+            className={inputClass}
+          />
+          <div className="flex items-center justify-between">
+            <p className="text-[11px] text-text-muted/60">OBS output</p>
+            <select
+              value={config.output}
+              onChange={(e) => update({ output: e.target.value as ObsOutput })}
+              className={selectClass}
+            >
+              <option value="record">Recording</option>
+              <option value="stream">Streaming</option>
+              <option value="either">Recording or streaming</option>
+            </select>
+          </div>
+          <Check checked={config.follow_obs} onChange={(v) => update({ follow_obs: v })} label="Record when OBS does" />
+          <Check checked={config.drive_obs} onChange={(v) => update({ drive_obs: v })} label="Start and stop OBS with DiscRec" />
+        </div>
+      )}
+
+      {error && <p className="text-[11px] text-record/80">{error}</p>}
+    </div>
+  );
+}
@@ -0,0 +1,155 @@
+import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { FolderTree, Plus, X } from "lucide-react";
+import { cn } from "../lib/utils";
+
+interface OutputRoute {
+  folder: string;
+  weekdays: string[];
+  start_hour: number | null;
+  end_hour: number | null;
+  guild_id: string | null;
+}
+
+interface GuildInfo {
+  id: string;
+  name: string;
+}
+
+const WEEKDAYS = [
+  { id: "mon", label: "M" },
+  { id: "tue", label: "T" },
+  { id: "wed", label: "W" },
+  { id: "thu", label: "T" },
+  { id: "fri", label: "F" },
+  { id: "sat", label: "S" },
+  { id: "sun", label: "S" },
+];
+
+const HOURS = Array.from({ length: 25 }, (_, h) => h);
+
+const selectClass =
+  "text-[11px] bg-bg-primary border border-border/50 rounded-lg px-2 py-1 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors";
+
+/** Rules that send recordings into library subfolders by day, time or server. */
+export function OutputRoutes({ guilds }: { guilds: GuildInfo[] }) {
+  const [routes, setRoutes] = useState<OutputRoute[]>([]);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    invoke<OutputRoute[]>("get_output_routes").then(setRoutes).catch(() => {});
+  }, []);
+
+  const save = async (next: OutputRoute[]) => {
+    setRoutes(next);
+    try {
+      setRoutes(await invoke<OutputRoute[]>("set_output_routes", { routes: next }));
+      setError(null);
+    } catch (e) {
+      setError(String(e));
+    }
+  };
+
+  const update = (index: number, change: Partial<OutputRoute>) =>
+    save(routes.map((r, i) => (i === index ? { ...r, ...change } : r)));
+
+  const handleAdd = () =>
+    setRoutes((prev) => [...prev, { folder: "", weekdays: [], start_hour: null, end_hour: null, guild_id: null }]);
+
+  const toggleDay = (index: number, day: string) => {
+    const days = routes[index].weekdays;
+    update(index, { weekdays: days.includes(day) ? days.filter((d) => d !== day) : [...days, day] });
+  };
+
+  return (
+    <div className="flex flex-col gap-2">
+      <div className="flex items-center justify-between mb-1">
+        <p className="text-[11px] text-text-muted/50">
+          {routes.length ? "First matching rule picks the subfolder" : "Send recordings to subfolders by day or server"}
+        </p>
+        <button
+          onClick={handleAdd}
+          className="px-3 py-1.5 rounded-lg bg-bg-primary border border-border/50 text-[11px] font-medium text-text-secondary hover:text-text-primary hover:bg-bg-elevated transition-all cursor-pointer shrink-0 flex items-center gap-1"
+        >
+          <Plus className="w-3 h-3" /> Add rule
+        </button>
+      </div>
+
+      {routes.map((route, i) => (
+        <div key={i} className="flex flex-col gap-2 px-3 py-2.5 rounded-xl bg-bg-primary/60 border border-border/30">
+          <div className="flex items-center gap-2">
+            <FolderTree className="w-3.5 h-3.5 text-text-muted/40 shrink-0" />
+            <input
+              defaultValue={route.folder}
+              onBlur={(e) => e.target.value !== route.folder && update(i, { folder: e.target.value.trim() })}
+              placeholder="Folder, e.g. Campaign A"
+              className="flex-1 min-w-0 bg-transparent text-[12px] text-text-primary outline-none placeholder:text-text-muted/40"
+            />
+            <button
+              onClick={() => save(routes.filter((_, j) => j !== i))}
+              className="p-1 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+              title="Remove rule"
+            >
+              <X className="w-3.5 h-3.5" />
+            </button>
+          </div>
+          <div className="flex items-center gap-1 flex-wrap">
+            {WEEKDAYS.map((day) => (
+              <button
+                key={day.id}
+                onClick={() => toggleDay(i, day.id)}
+                title={day.id}
+                className={cn(
+                  "w-6 h-6 rounded-md text-[10px] font-semibold transition-colors cursor-pointer",
+                  route.weekdays.includes(day.id)
+                    ? "bg-accent/20 text-accent"
+                    : "bg-bg-elevated/50 text-text-muted/50 hover:text-text-primary"
+                )}
+              >
+                {day.label}
+              </button>
+            ))}
+            <select
+              value={route.start_hour ?? ""}
+              onChange={(e) => update(i, { start_hour: e.target.value === "" ? null : Number(e.target.value) })}
+              className={cn(selectClass, "ml-2")}
+              title="From hour"
+            >
+              <option value="">any time</option>
+              {HOURS.slice(0, 24).map((h) => (
+                <option key={h} value={h}>from {h}:00</option>
+              ))}
+            </select>
+            <select
+              value={route.end_hour ?? ""}
+              onChange={(e) => update(i, { end_hour: e.target.value === "" ? null : Number(e.target.value) })}
+              className={selectClass}
+              title="Until hour"
+            >
+              <option value="">–</option>
+              {HOURS.slice(1).map((h) => (
+                <option key={h} value={h}>until {h}:00</option>
+              ))}
+            </select>
+            <select
+              value={route.guild_id ?? ""}
+              onChange={(e) => update(i, { guild_id: e.target.value || null })}
+              className={cn(selectClass, "max-w-[140px] truncate")}
+              title="Only bot recordings in this server"
+            >
+              <option value="">Any server</option>
+              {route.guild_id && !guilds.some((g) => g.id === route.guild_id) && (
+                <option value={route.guild_id}>{route.guild_id}</option>
+              )}
+              {guilds.map((g) => (
+                <option key={g.id} value={g.id}>{g.name}</option>
+              ))}
+            </select>
+          </div>
+        </div>
+      ))}
+
+      {error && <p className="text-[11px] text-record/80">{error}</p>}
+    </div>
+  );
+}
@@ -1,7 +1,13 @@
-import { useState, useEffect, useCallback } from "react";
-import { invoke } from "@tauri-apps/api/core";
-import { Trash2, FolderOpen, RefreshCw } from "lucide-react";
+import { useState, useEffect, useCallback, useRef } from "react";
+import { invoke, Channel } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { ask, message, open } from "@tauri-apps/plugin-dialog";
+import { Trash2, FolderOpen, RefreshCw, Import, Users, ShieldCheck, Play, Pause, Square, X, Loader2, Headphones, ArrowLeftToLine, ArrowRightToLine, FileAudio, Combine, Gauge, Captions, GitCompare, Search, ArrowDownWideNarrow, ArrowUpNarrowWide, Info, Undo2 } from "lucide-react";
 import { cn } from "../lib/utils";
+import { runJob, cancelJob, type JobStatus } from "../lib/jobs";
+import { usePlayback } from "../hooks/usePlayback";
+import { ConvertRecording, type TrashEntry } from "./ConvertRecording";
+import { MixSession } from "./MixSession";
 
 interface RecordingInfo {
   path: string;
@@ -9,6 +15,126 @@ interface RecordingInfo {
   size: number;
   modified: string;
   format: string;
+  audio: { sample_rate: number; channels: number; bits_per_sample: number } | null;
+  /** Manifest of the bot session the file belongs to. */
+  session: string | null;
+  duration_secs: number | null;
+}
+
+type RecordingSort = "date" | "size" | "duration" | "name";
+
+/** Which recordings `list_recordings` returns, a page at a time. */
+interface RecordingQuery {
+  offset?: number;
+  limit?: number;
+  sort?: RecordingSort;
+  ascending?: boolean;
+  formats?: string[];
+  from?: string | null;
+  to?: string | null;
+  search?: string | null;
+}
+
+interface RecordingPage {
+  recordings: RecordingInfo[];
+  total: number;
+}
+
+interface HistoryFilter {
+  search: string;
+  format: string;
+  from: string;
+  to: string;
+  sort: RecordingSort;
+  ascending: boolean;
+}
+
+const PAGE_SIZE = 50;
+const NO_FILTER: HistoryFilter = { search: "", format: "", from: "", to: "", sort: "date", ascending: false };
+
+function toQuery(filter: HistoryFilter, offset: number): RecordingQuery {
+  return {
+    offset,
+    limit: PAGE_SIZE,
+    sort: filter.sort,
+    ascending: filter.ascending,
+    formats: filter.format ? [filter.format] : [],
+    from: filter.from || null,
+    to: filter.to || null,
+    search: filter.search.trim() || null,
+  };
+}
+
+interface SessionTrack {
+  file: string;
+  ssrc: number;
+  user_id: string | null;
+  name: string | null;
+}
+
+interface SessionListing {
+  path: string;
+  started_at: string;
+  ended_at: string;
+  guild_id: string;
+  guild_name: string | null;
+  channel_id: string;
+  channel_name: string | null;
+  sample_rate: number;
+  format: string;
+  tracks: SessionTrack[];
+  mix: string | null;
+  /** Later parts of the mix when the session was split into segments. */
+  mix_parts?: string[];
+  timeline: string | null;
+  consent?: string | null;
+  /** Chat log files, JSON then text, when the text chat was saved. */
+  chat?: string[];
+  /** Why the stems may sound unlike local mics, e.g. Discord's noise suppression. */
+  notes?: string[];
+}
+
+/** What normalizing a recording measured and applied. */
+interface SessionComparison {
+  offset_secs: number;
+  drift_ms_per_hour: number | null;
+  match_score: number;
+  keep: "bot" | "local" | "both";
+  reason: string;
+}
+
+interface Normalized {
+  measured_lufs: number;
+  gain_db: number;
+  peak_limited: boolean;
+}
+
+/** A lone recording, or every file of one bot session. */
+type HistoryItem =
+  | { kind: "file"; recording: RecordingInfo }
+  | { kind: "session"; session: SessionListing; recordings: RecordingInfo[] };
+
+/** Group bot session stems together, keeping the newest-first order. */
+function groupBySession(recordings: RecordingInfo[], sessions: SessionListing[]): HistoryItem[] {
+  const byPath = new Map(sessions.map((session) => [session.path, session]));
+  const items: HistoryItem[] = [];
+  const groups = new Map<string, RecordingInfo[]>();
+  for (const recording of recordings) {
+    const session = recording.session ? byPath.get(recording.session) : undefined;
+    if (!session) {
+      items.push({ kind: "file", recording });
+      continue;
+    }
+    const group = groups.get(session.path);
+    if (group) {
+      group.push(recording);
+    } else {
+      const fresh = [recording];
+      groups.set(session.path, fresh);
+      items.push({ kind: "session", session, recordings: fresh });
+    }
+  }
+  return items;
 }
 
 function formatSize(bytes: number): string {
@@ -17,6 +143,12 @@ function formatSize(bytes: number): string {
   return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
 }
 
+function formatTime(secs: number): string {
+  const whole = Math.floor(secs);
+  const minutes = Math.floor(whole / 60);
+  return `${minutes}:${String(whole % 60).padStart(2, "0")}`;
+}
+
 function formatDate(dateStr: string): string {
   if (!dateStr) return "";
   // dateStr is "YYYY-MM-DD HH:MM:SS"
@@ -28,38 +160,283 @@ function formatDate(dateStr: string): string {
 
 export function RecordingHistory() {
   const [recordings, setRecordings] = useState<RecordingInfo[]>([]);
+  const [total, setTotal] = useState(0);
+  const [filter, setFilter] = useState<HistoryFilter>(NO_FILTER);
+  // The filter applied, which trails the search box while typing
+  const [applied, setApplied] = useState<HistoryFilter>(NO_FILTER);
+  const [loadingMore, setLoadingMore] = useState(false);
+  const [listError, setListError] = useState<string | null>(null);
+  const [trash, setTrash] = useState<TrashEntry[]>([]);
+  // The last delete, which can be undone
+  const [deleted, setDeleted] = useState<{ entry: TrashEntry; recording: RecordingInfo } | null>(null);
+  const [sessions, setSessions] = useState<SessionListing[]>([]);
   const [loading, setLoading] = useState(true);
+  const [previews, setPreviews] = useState<Set<string>>(new Set());
+  const [playing, setPlaying] = useState<string | null>(null);
+  const [importJob, setImportJob] = useState<JobStatus | null>(null);
+  const audioRef = useRef<HTMLAudioElement | null>(null);
+  const playback = usePlayback();
+  const [converting, setConverting] = useState<string | null>(null);
+  const [mixing, setMixing] = useState<string | null>(null);
+  const [normalizing, setNormalizing] = useState<Set<string>>(new Set());
+  const [comparing, setComparing] = useState<string | null>(null);
+
+  // Previews are made in the background; play buttons appear as they finish
+  const loadPreviews = (list: RecordingInfo[]) => {
+    const onReady = new Channel<string>();
+    onReady.onmessage = (path) => setPreviews((prev) => new Set(prev).add(path));
+    invoke("generate_previews", { paths: list.map((r) => r.path), onReady }).catch(() => {});
+  };
 
+  // Only the first load replaces the list with a spinner, so the filters
+  // keep focus while results update
   const refresh = useCallback(async () => {
-    setLoading(true);
     try {
-      const list = await invoke<RecordingInfo[]>("list_recordings");
-      setRecordings(list);
+      const [page, sessionList, trashed] = await Promise.all([
+        invoke<RecordingPage>("list_recordings", { query: toQuery(applied, 0) }),
+        invoke<SessionListing[]>("list_sessions"),
+        invoke<TrashEntry[]>("list_trash"),
+      ]);
+      setRecordings(page.recordings);
+      setTotal(page.total);
+      setSessions(sessionList);
+      setTrash(trashed);
+      setListError(null);
+      loadPreviews(page.recordings);
     } catch (e) {
       console.error("Failed to load recordings:", e);
+      setListError(String(e));
     } finally {
       setLoading(false);
     }
-  }, []);
+  }, [applied]);
 
   useEffect(() => {
     refresh();
   }, [refresh]);
 
-  const handleDelete = async (path: string) => {
+  useEffect(() => {
+    const timer = setTimeout(() => setApplied(filter), 250);
+    return () => clearTimeout(timer);
+  }, [filter]);
+
+  const handleLoadMore = async () => {
+    setLoadingMore(true);
+    try {
+      const page = await invoke<RecordingPage>("list_recordings", { query: toQuery(applied, recordings.length) });
+      setRecordings((prev) => [...prev, ...page.recordings]);
+      setTotal(page.total);
+      loadPreviews(page.recordings);
+    } catch (e) {
+      console.error("Failed to load recordings:", e);
+    } finally {
+      setLoadingMore(false);
+    }
+  };
+
+  // Empty recordings deleted at startup or after a stop
+  useEffect(() => {
+    const unlisten = listen<string[]>("library://empty-removed", () => refresh());
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [refresh]);
+
+  useEffect(() => () => audioRef.current?.pause(), []);
+
+  const handlePreview = async (path: string) => {
+    audioRef.current?.pause();
+    if (playback.status.playing) playback.stop();
+    if (playing === path) {
+      setPlaying(null);
+      return;
+    }
     try {
-      await invoke("delete_recording", { path });
-      setRecordings((prev) => prev.filter((r) => r.path !== path));
+      const bytes = await invoke<ArrayBuffer>("read_preview", { path });
+      const url = URL.createObjectURL(new Blob([bytes], { type: "audio/mpeg" }));
+      const audio = new Audio(url);
+      audio.onended = () => {
+        URL.revokeObjectURL(url);
+        setPlaying((current) => (current === path ? null : current));
+      };
+      audioRef.current = audio;
+      setPlaying(path);
+      await audio.play();
+    } catch (e) {
+      console.error("Failed to play preview:", e);
+      setPlaying(null);
+    }
+  };
+
+  // Full recordings play through the backend so they can be scrubbed
+  const handleAudition = (path: string) => {
+    audioRef.current?.pause();
+    setPlaying(null);
+    playback.toggle(path);
+  };
+
+  // Cut everything before or after the playhead
+  const handleTrim = async (path: string, keep: "after" | "before") => {
+    const position = playback.status.position_secs;
+    const range = keep === "after" ? "before" : "after";
+    const confirmed = await ask(`Remove everything ${range} ${formatTime(position)}? This can't be undone.`, {
+      title: "Trim recording",
+      kind: "warning",
+    });
+    if (!confirmed) return;
+    await playback.stop();
+    try {
+      const updated = await invoke<RecordingInfo>("trim_recording", {
+        path,
+        startSecs: keep === "after" ? position : 0,
+        endSecs: keep === "after" ? null : position,
+      });
+      setRecordings((prev) => prev.map((r) => (r.path === path ? { ...updated, session: r.session } : r)));
+    } catch (e) {
+      await message(String(e), { title: "Trim failed", kind: "error" });
+    }
+  };
+
+  const handleNormalize = async (path: string) => {
+    if (playback.status.path === path) await playback.stop();
+    setNormalizing((prev) => new Set(prev).add(path));
+    try {
+      const result = await runJob<Normalized>(invoke<number>("normalize_recording", { path }));
+      const measured = `Measured ${result.measured_lufs.toFixed(1)} LUFS`;
+      await message(
+        result.gain_db === 0
+          ? `${measured}, already on target.`
+          : `${measured}, applied ${result.gain_db > 0 ? "+" : ""}${result.gain_db.toFixed(1)} dB${result.peak_limited ? " (held back to avoid clipping)" : ""}.`,
+        { title: "Loudness normalized" }
+      );
+      refresh();
+    } catch (e) {
+      await message(String(e), { title: "Normalize failed", kind: "error" });
+    } finally {
+      setNormalizing((prev) => {
+        const next = new Set(prev);
+        next.delete(path);
+        return next;
+      });
+    }
+  };
+
+  const handleDelete = async (recording: RecordingInfo) => {
+    if (playback.status.path === recording.path) await playback.stop();
+    try {
+      const entry = await invoke<TrashEntry>("delete_recording", { path: recording.path });
+      setRecordings((prev) => prev.filter((r) => r.path !== recording.path));
+      setTotal((prev) => prev - 1);
+      setTrash((prev) => [entry, ...prev]);
+      setDeleted({ entry, recording });
     } catch (e) {
       console.error("Failed to delete recording:", e);
     }
   };
 
+  const handleUndoDelete = async () => {
+    if (!deleted) return;
+    setDeleted(null);
+    try {
+      await invoke<RecordingInfo>("restore_recording", { id: deleted.entry.id });
+      refresh();
+    } catch (e) {
+      await message(String(e), { title: "Restore failed", kind: "error" });
+    }
+  };
+
+  const handleEmptyTrash = async () => {
+    const size = formatSize(trash.reduce((sum, entry) => sum + entry.size, 0));
+    const confirmed = await ask(
+      `Delete ${trash.length} recording${trash.length !== 1 ? "s" : ""} (${size}) in the trash for good? This can't be undone.`,
+      { title: "Empty trash", kind: "warning" }
+    );
+    if (!confirmed) return;
+    try {
+      await invoke<number>("empty_trash");
+      setTrash([]);
+      setDeleted(null);
+    } catch (e) {
+      await message(String(e), { title: "Empty trash failed", kind: "error" });
+    }
+  };
+
+  const handleImport = async () => {
+    const selected = await open({
+      multiple: true,
+      title: "Import recordings",
+      filters: [{ name: "Recordings", extensions: ["wav", "flac", "mp3", "opus"] }],
+    });
+    if (!selected || selected.length === 0) return;
+    try {
+      const nonstandard = await invoke<RecordingInfo[]>("find_nonstandard_recordings", { paths: selected });
+      let normalize = false;
+      if (nonstandard.length > 0) {
+        const count = nonstandard.length === 1 ? "1 file isn't" : `${nonstandard.length} files aren't`;
+        normalize = await ask(
+          `${count} 48 kHz mono/stereo. Convert to 48 kHz stereo while importing? Mixing and merging expect it.`,
+          { title: "Import recordings", okLabel: "Convert", cancelLabel: "Keep as is" }
+        );
+      }
+      // Converted files are new files anyway, so linking only matters otherwise
+      const link =
+        !normalize &&
+        !(await ask("Copy the files into the library, or link them to save space? Linked files share changes made in DiscRec, like normalizing, with the originals.", {
+          title: "Import recordings",
+          okLabel: "Copy",
+          cancelLabel: "Link",
+        }));
+      const id = await invoke<number>("import_recordings", { paths: selected, normalize, mode: link ? "link" : "copy" });
+      setImportJob({ id, kind: "import", state: "running", done: 0, total: selected.length, current: "", error: null, result: null });
+      await runJob(id, setImportJob);
+    } catch (e) {
+      console.error("Failed to import recordings:", e);
+    } finally {
+      setImportJob(null);
+      refresh();
+    }
+  };
+
+  const handleExport = async (session: SessionListing) => {
+    const destination = await invoke<string | null>("pick_folder", { title: "Export session bundle to" });
+    if (!destination) return;
+    try {
+      const bundle = await invoke<string>("export_session_bundle", { path: session.path, destination });
+      await message(`Saved to ${bundle}`, { title: "Session exported" });
+    } catch (e) {
+      console.error("Failed to export session:", e);
+    }
+  };
+
+  const handleCompare = async (session: SessionListing) => {
+    setComparing(session.path);
+    try {
+      const result = await runJob<SessionComparison>(invoke<number>("compare_session_sources", { manifest: session.path }));
+      const keep = result.keep === "bot" ? "Keep the bot tracks" : result.keep === "local" ? "Keep the local recording" : "Keep both";
+      const offset = `The bot starts ${Math.abs(result.offset_secs).toFixed(2)} s ${result.offset_secs < 0 ? "before" : "into"} the local recording`;
+      const drift = result.drift_ms_per_hour === null ? "" : `, drifting ${Math.round(result.drift_ms_per_hour)} ms per hour`;
+      await message(`${keep}: ${result.reason}.\n\n${offset}${drift} (match ${Math.round(result.match_score * 100)}%).`, { title: "Compared with local recording" });
+    } catch (e) {
+      await message(String(e), { title: "Compare failed", kind: "error" });
+    } finally {
+      setComparing(null);
+    }
+  };
+
+  const handleExportTimestamps = async (session: SessionListing) => {
+    try {
+      const srt = await invoke<string>("export_timestamp_subtitles", { path: session.path });
+      await message(`Saved to ${srt}`, { title: "Timestamp subtitles exported" });
+    } catch (e) {
+      console.error("Failed to export timestamp subtitles:", e);
+    }
+  };
+
   const handleOpenFolder = async (path: string) => {
     try {
-      await invoke("open_folder", { path });
+      await invoke("reveal_in_file_manager", { path });
     } catch (e) {
-      console.error("Failed to open folder:", e);
+      console.error("Failed to reveal recording:", e);
     }
   };
 
@@ -76,6 +453,150 @@ export function RecordingHistory() {
     }
   };
 
+  const renderRow = (rec: RecordingInfo, speaker?: string) => {
+    const active = playback.status.path === rec.path;
+    return (
+      <div
+        key={rec.path}
+        className="group flex flex-col gap-2 px-3 py-2.5 rounded-xl bg-bg-primary/60 border border-border/30 hover:border-border/60 transition-colors"
+      >
+        <div className="flex items-center gap-3">
+          {/* Format badge */}
+          <span
+            className={cn(
+              "shrink-0 text-[9px] font-bold uppercase px-2 py-0.5 rounded-md",
+              formatBadgeColor(rec.format)
+            )}
+          >
+            {rec.format}
+          </span>
+
+          {/* File info */}
+          <div className="flex-1 min-w-0">
+            <p className="text-[12px] text-text-primary truncate leading-snug" title={rec.filename}>
+              {speaker ?? rec.filename}
+            </p>
+            <p className="text-[10px] text-text-muted/50 leading-snug mt-0.5">
+              {rec.duration_secs !== null && `${formatTime(rec.duration_secs)} · `}
+              {formatSize(rec.size)} · {formatDate(rec.modified)}
+            </p>
+          </div>
+
+          {/* Actions */}
+          <div className={cn("flex items-center gap-1 group-hover:opacity-100 transition-opacity", active ? "opacity-100" : "opacity-0")}>
+            {(rec.format === "wav" || rec.format === "flac") && (
+              <button
+                onClick={() => handleAudition(rec.path)}
+                className="p-1.5 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+                title={active && playback.status.playing ? "Pause" : "Play in app"}
+              >
+                {active && playback.status.playing ? <Pause className="w-3.5 h-3.5" /> : <Headphones className="w-3.5 h-3.5" />}
+              </button>
+            )}
+            {previews.has(rec.path) && (
+              <button
+                onClick={() => handlePreview(rec.path)}
+                className="p-1.5 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+                title="Preview loudest 30 seconds"
+              >
+                {playing === rec.path ? <Square className="w-3.5 h-3.5" /> : <Play className="w-3.5 h-3.5" />}
+              </button>
+            )}
+            {(rec.format === "wav" || rec.format === "flac") && (
+              <button
+                onClick={() => setConverting(converting === rec.path ? null : rec.path)}
+                className="p-1.5 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+                title="Convert"
+              >
+                <FileAudio className="w-3.5 h-3.5" />
+              </button>
+            )}
+            {(rec.format === "wav" || rec.format === "flac") && (
+              <button
+                onClick={() => handleNormalize(rec.path)}
+                disabled={normalizing.has(rec.path)}
+                className="p-1.5 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer disabled:cursor-default"
+                title="Normalize loudness to -16 LUFS"
+              >
+                {normalizing.has(rec.path) ? <Loader2 className="w-3.5 h-3.5 animate-spin" /> : <Gauge className="w-3.5 h-3.5" />}
+              </button>
+            )}
+            <button
+              onClick={() => handleOpenFolder(rec.path)}
+              className="p-1.5 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+              title="Open folder"
+            >
+              <FolderOpen className="w-3.5 h-3.5" />
+            </button>
+            <button
+              onClick={() => handleDelete(rec)}
+              className="p-1.5 rounded-lg text-text-muted/40 hover:text-record transition-colors cursor-pointer"
+              title="Move to trash"
+            >
+              <Trash2 className="w-3.5 h-3.5" />
+            </button>
+          </div>
+        </div>
+
+        {converting === rec.path && (
+          <ConvertRecording
+            path={rec.path}
+            format={rec.format}
+            onDone={(trashed) => {
+              setConverting(null);
+              if (trashed) {
+                setTrash((prev) => [trashed, ...prev]);
+                setDeleted({ entry: trashed, recording: rec });
+              }
+              refresh();
+            }}
+            onClose={() => setConverting(null)}
+          />
+        )}
+
+        {/* Scrub bar */}
+        {active && (
+          <div className="flex items-center gap-2 animate-fade-in">
+            <span className="text-[10px] tabular-nums text-text-muted/60">{formatTime(playback.status.position_secs)}</span>
+            <input
+              type="range"
+              min={0}
+              max={playback.status.duration_secs ?? playback.status.position_secs}
+              step={0.1}
+              value={playback.status.position_secs}
+              onChange={(e) => playback.seek(Number(e.target.value))}
+              className="flex-1 accent-accent cursor-pointer"
+            />
+            <span className="text-[10px] tabular-nums text-text-muted/60">
+              {playback.status.duration_secs != null ? formatTime(playback.status.duration_secs) : "--:--"}
+            </span>
+            <button
+              onClick={() => handleTrim(rec.path, "after")}
+              className="p-1 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+              title="Cut everything before this point"
+            >
+              <ArrowLeftToLine className="w-3 h-3" />
+            </button>
+            <button
+              onClick={() => handleTrim(rec.path, "before")}
+              className="p-1 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+              title="Cut everything after this point"
+            >
+              <ArrowRightToLine className="w-3 h-3" />
+            </button>
+            <button
+              onClick={playback.stop}
+              className="p-1 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+              title="Stop"
+            >
+              <X className="w-3 h-3" />
+            </button>
+          </div>
+        )}
+      </div>
+    );
+  };
+
   if (loading) {
     return (
       <div className="flex items-center justify-center py-6">
@@ -84,7 +605,10 @@ export function RecordingHistory() {
     );
   }
 
-  if (recordings.length === 0) {
+  const filtered =
+    applied.search.trim() !== "" || applied.format !== "" || applied.from !== "" || applied.to !== "";
+
+  if (recordings.length === 0 && !filtered && filter === applied && !listError && !deleted && trash.length === 0) {
     return (
       <p className="text-[11px] text-text-muted/50 text-center py-4">
         No recordings yet
@@ -97,62 +621,234 @@ export function RecordingHistory() {
       {/* Header with refresh */}
       <div className="flex items-center justify-between mb-1">
         <p className="text-[11px] text-text-muted/50">
-          {recordings.length} recording{recordings.length !== 1 ? "s" : ""}
+          {total} recording{total !== 1 ? "s" : ""}
+          {filtered && " found"}
         </p>
-        <button
-          onClick={refresh}
-          className="p-1.5 rounded-lg text-text-muted/40 hover:text-text-muted transition-colors cursor-pointer"
-        >
-          <RefreshCw className="w-3.5 h-3.5" />
-        </button>
+        <div className="flex items-center gap-1">
+          {trash.length > 0 && (
+            <button
+              onClick={handleEmptyTrash}
+              className="flex items-center gap-1 p-1.5 rounded-lg text-[10px] text-text-muted/40 hover:text-record transition-colors cursor-pointer"
+              title={`Empty trash: ${formatSize(trash.reduce((sum, entry) => sum + entry.size, 0))} still on disk`}
+            >
+              <Trash2 className="w-3.5 h-3.5" />
+              {trash.length}
+            </button>
+          )}
+          <button
+            onClick={handleImport}
+            className="p-1.5 rounded-lg text-text-muted/40 hover:text-text-muted transition-colors cursor-pointer"
+            title="Import recordings"
+          >
+            <Import className="w-3.5 h-3.5" />
+          </button>
+          <button
+            onClick={refresh}
+            className="p-1.5 rounded-lg text-text-muted/40 hover:text-text-muted transition-colors cursor-pointer"
+          >
+            <RefreshCw className="w-3.5 h-3.5" />
+          </button>
+        </div>
       </div>
 
-      {/* Recording list */}
-      <div className="flex flex-col gap-1.5 max-h-[200px] overflow-y-auto pr-1">
-        {recordings.map((rec) => (
-          <div
-            key={rec.path}
-            className="group flex items-center gap-3 px-3 py-2.5 rounded-xl bg-bg-primary/60 border border-border/30 hover:border-border/60 transition-colors"
+      {/* Filters */}
+      <div className="flex flex-col gap-1.5">
+        <div className="flex items-center gap-1.5">
+          <div className="flex-1 min-w-0 flex items-center gap-1.5 px-2 py-1 rounded-lg bg-bg-primary border border-border/50 focus-within:border-accent/50 transition-colors">
+            <Search className="w-3 h-3 shrink-0 text-text-muted/40" />
+            <input
+              value={filter.search}
+              onChange={(e) => setFilter((f) => ({ ...f, search: e.target.value }))}
+              placeholder="Search file names"
+              className="flex-1 min-w-0 bg-transparent text-[11px] text-text-primary placeholder:text-text-muted/30 outline-none"
+            />
+          </div>
+          <select
+            value={filter.format}
+            onChange={(e) => setFilter((f) => ({ ...f, format: e.target.value }))}
+            className="text-[11px] bg-bg-primary border border-border/50 rounded-lg px-2 py-1 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors"
           >
-            {/* Format badge */}
-            <span
-              className={cn(
-                "shrink-0 text-[9px] font-bold uppercase px-2 py-0.5 rounded-md",
-                formatBadgeColor(rec.format)
-              )}
-            >
-              {rec.format}
-            </span>
+            <option value="">All formats</option>
+            <option value="wav">WAV</option>
+            <option value="flac">FLAC</option>
+            <option value="mp3">MP3</option>
+            <option value="opus">Opus</option>
+          </select>
+        </div>
+        <div className="flex items-center gap-1.5">
+          <input
+            type="date"
+            value={filter.from}
+            onChange={(e) => setFilter((f) => ({ ...f, from: e.target.value }))}
+            className="min-w-0 flex-1 text-[11px] bg-bg-primary border border-border/50 rounded-lg px-2 py-1 text-text-secondary outline-none hover:border-border transition-colors"
+            title="From"
+          />
+          <input
+            type="date"
+            value={filter.to}
+            onChange={(e) => setFilter((f) => ({ ...f, to: e.target.value }))}
+            className="min-w-0 flex-1 text-[11px] bg-bg-primary border border-border/50 rounded-lg px-2 py-1 text-text-secondary outline-none hover:border-border transition-colors"
+            title="To"
+          />
+          <select
+            value={filter.sort}
+            onChange={(e) => setFilter((f) => ({ ...f, sort: e.target.value as RecordingSort }))}
+            className="text-[11px] bg-bg-primary border border-border/50 rounded-lg px-2 py-1 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors"
+          >
+            <option value="date">Date</option>
+            <option value="size">Size</option>
+            <option value="duration">Duration</option>
+            <option value="name">Name</option>
+          </select>
+          <button
+            onClick={() => setFilter((f) => ({ ...f, ascending: !f.ascending }))}
+            className="p-1.5 rounded-lg text-text-muted/40 hover:text-text-muted transition-colors cursor-pointer"
+            title={filter.ascending ? "Ascending" : "Descending"}
+          >
+            {filter.ascending ? <ArrowUpNarrowWide className="w-3.5 h-3.5" /> : <ArrowDownWideNarrow className="w-3.5 h-3.5" />}
+          </button>
+        </div>
+      </div>
 
-            {/* File info */}
-            <div className="flex-1 min-w-0">
-              <p className="text-[12px] text-text-primary truncate leading-snug">
-                {rec.filename}
-              </p>
-              <p className="text-[10px] text-text-muted/50 leading-snug mt-0.5">
-                {formatSize(rec.size)} · {formatDate(rec.modified)}
-              </p>
-            </div>
+      {deleted && (
+        <div className="flex items-center gap-2 px-3 py-2 rounded-xl bg-bg-primary/60 border border-border/30 animate-fade-in">
+          <Trash2 className="w-3.5 h-3.5 text-text-muted/50 shrink-0" />
+          <p className="flex-1 min-w-0 text-[11px] text-text-secondary truncate" title={deleted.recording.filename}>
+            Moved {deleted.recording.filename} to the trash, where it still takes up space until emptied
+          </p>
+          <button
+            onClick={handleUndoDelete}
+            className="flex items-center gap-1 px-2 py-1 rounded-lg text-[11px] text-accent hover:bg-accent/10 transition-colors cursor-pointer"
+          >
+            <Undo2 className="w-3 h-3" />
+            Undo
+          </button>
+          <button
+            onClick={() => setDeleted(null)}
+            className="p-1 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+            title="Dismiss"
+          >
+            <X className="w-3.5 h-3.5" />
+          </button>
+        </div>
+      )}
 
-            {/* Actions */}
-            <div className="flex items-center gap-1 opacity-0 group-hover:opacity-100 transition-opacity">
-              <button
-                onClick={() => handleOpenFolder(rec.path)}
-                className="p-1.5 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
-                title="Open folder"
-              >
-                <FolderOpen className="w-3.5 h-3.5" />
-              </button>
-              <button
-                onClick={() => handleDelete(rec.path)}
-                className="p-1.5 rounded-lg text-text-muted/40 hover:text-record transition-colors cursor-pointer"
-                title="Delete"
-              >
-                <Trash2 className="w-3.5 h-3.5" />
-              </button>
+      {listError && <p className="text-[11px] text-record/80">{listError}</p>}
+      {playback.error && <p className="text-[11px] text-record/80">{playback.error}</p>}
+
+      {importJob && (
+        <div className="flex items-center gap-2 px-3 py-2 rounded-xl bg-bg-primary/60 border border-border/30">
+          <Loader2 className="w-3.5 h-3.5 text-text-muted/50 animate-spin shrink-0" />
+          <p className="flex-1 min-w-0 text-[11px] text-text-secondary truncate">
+            Importing {Math.min(importJob.done + 1, importJob.total ?? 1)} of {importJob.total}
+            {importJob.current && ` · ${importJob.current.split(/[\\/]/).pop()}`}
+          </p>
+          <button
+            onClick={() => cancelJob(importJob.id).catch(() => {})}
+            className="p-1 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+            title="Cancel import"
+          >
+            <X className="w-3.5 h-3.5" />
+          </button>
+        </div>
+      )}
+
+      {/* Recording list */}
+      <div className="flex flex-col gap-1.5 max-h-[200px] overflow-y-auto pr-1">
+        {groupBySession(recordings, sessions).map((item) =>
+          item.kind === "file" ? (
+            renderRow(item.recording)
+          ) : (
+            <div
+              key={item.session.path}
+              className="flex flex-col gap-1 p-1.5 rounded-xl border border-border/30"
+            >
+              <div className="flex items-center gap-2 px-1.5 py-0.5">
+                <Users className="w-3 h-3 text-text-muted/40 shrink-0" />
+                <p className="flex-1 min-w-0 text-[11px] text-text-secondary truncate">
+                  {[item.session.guild_name, item.session.channel_name && `#${item.session.channel_name}`]
+                    .filter(Boolean)
+                    .join(" · ") || "Bot session"}
+                </p>
+                {item.session.notes && item.session.notes.length > 0 && (
+                  <span title={item.session.notes.join("\n\n")}>
+                    <Info className="w-3 h-3 text-text-muted/40 shrink-0" />
+                  </span>
+                )}
+                <p className="text-[10px] text-text-muted/50 shrink-0">
+                  {item.recordings.length} file{item.recordings.length !== 1 ? "s" : ""}
+                </p>
+                <button
+                  onClick={() => setMixing(mixing === item.session.path ? null : item.session.path)}
+                  className="p-1 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+                  title="Mix down tracks"
+                >
+                  <Combine className="w-3 h-3" />
+                </button>
+                <button
+                  onClick={() => handleCompare(item.session)}
+                  disabled={comparing !== null}
+                  className="p-1 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer disabled:opacity-50"
+                  title="Compare with the local recording"
+                >
+                  {comparing === item.session.path ? <Loader2 className="w-3 h-3 animate-spin" /> : <GitCompare className="w-3 h-3" />}
+                </button>
+                <button
+                  onClick={() => handleExportTimestamps(item.session)}
+                  className="p-1 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+                  title="Export timestamp subtitles (.srt)"
+                >
+                  <Captions className="w-3 h-3" />
+                </button>
+                <button
+                  onClick={() => handleExport(item.session)}
+                  className="p-1 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+                  title="Export signed bundle"
+                >
+                  <ShieldCheck className="w-3 h-3" />
+                </button>
+              </div>
+              {mixing === item.session.path && (
+                <MixSession
+                  manifest={item.session.path}
+                  tracks={item.session.tracks.flatMap((track) => {
+                    const rec = item.recordings.find((r) => r.filename === track.file);
+                    return rec && (rec.format === "wav" || rec.format === "flac")
+                      ? [{ path: rec.path, name: track.name ?? track.user_id ?? `SSRC ${track.ssrc}` }]
+                      : [];
+                  })}
+                  onDone={() => {
+                    setMixing(null);
+                    refresh();
+                  }}
+                  onClose={() => setMixing(null)}
+                />
+              )}
+              {item.recordings.map((rec) =>
+                renderRow(
+                  rec,
+                  item.session.tracks.find((t) => t.file === rec.filename)?.name ??
+                    (rec.filename === item.session.mix || item.session.mix_parts?.includes(rec.filename)
+                      ? "Mix"
+                      : undefined)
+                )
+              )}
             </div>
-          </div>
-        ))}
+          )
+        )}
+        {recordings.length === 0 && (
+          <p className="text-[11px] text-text-muted/50 text-center py-4">No recordings match</p>
+        )}
+        {recordings.length < total && (
+          <button
+            onClick={handleLoadMore}
+            disabled={loadingMore}
+            className="flex items-center justify-center gap-1.5 py-1.5 text-[11px] text-text-muted/60 hover:text-text-primary transition-colors cursor-pointer disabled:cursor-default"
+          >
+            {loadingMore && <Loader2 className="w-3 h-3 animate-spin" />}
+            Load {Math.min(PAGE_SIZE, total - recordings.length)} more of {total - recordings.length}
+          </button>
+        )}
       </div>
     </div>
   );
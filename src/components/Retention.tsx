@@ -0,0 +1,144 @@
+import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { ask, message } from "@tauri-apps/plugin-dialog";
+import { Eraser, Loader2 } from "lucide-react";
+import { cn } from "../lib/utils";
+
+interface RetentionPolicy {
+  max_recording_age_days: number | null;
+  max_total_size_gb: number | null;
+}
+
+interface CleanupReport {
+  dry_run: boolean;
+  files: { path: string; size: number; modified: string; reason: "age" | "size" }[];
+  session_files: string[];
+  freed_bytes: number;
+  /** Of `freed_bytes`, what deleted recordings took up in the trash. */
+  trash_bytes: number;
+  remaining_bytes: number;
+}
+
+const selectClass =
+  "text-[11px] bg-bg-primary border border-border/50 rounded-lg px-2 py-1 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors";
+
+const ageOptions: { label: string; value: number | null }[] = [
+  { label: "Any age", value: null },
+  { label: "30 days", value: 30 },
+  { label: "90 days", value: 90 },
+  { label: "180 days", value: 180 },
+  { label: "1 year", value: 365 },
+];
+
+const sizeOptions: { label: string; value: number | null }[] = [
+  { label: "Any size", value: null },
+  { label: "10 GB", value: 10 },
+  { label: "50 GB", value: 50 },
+  { label: "100 GB", value: 100 },
+  { label: "250 GB", value: 250 },
+];
+
+function formatGb(bytes: number): string {
+  return `${(bytes / 1e9).toFixed(1)} GB`;
+}
+
+/** Removes the oldest recordings past a maximum age or total size. */
+export function Retention() {
+  const [policy, setPolicy] = useState<RetentionPolicy | null>(null);
+  const [busy, setBusy] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    invoke<RetentionPolicy>("get_retention_policy").then(setPolicy).catch(() => {});
+  }, []);
+
+  if (!policy) return null;
+
+  const save = async (next: RetentionPolicy) => {
+    try {
+      setPolicy(await invoke<RetentionPolicy>("set_retention_policy", { policy: next }));
+      setError(null);
+    } catch (e) {
+      setError(String(e));
+    }
+  };
+
+  const handleCleanUp = async () => {
+    setBusy(true);
+    try {
+      const preview = await invoke<CleanupReport>("run_cleanup_now", { dryRun: true });
+      if (preview.freed_bytes === 0) {
+        await message("Nothing is past the limits.", { title: "Clean up recordings" });
+        return;
+      }
+      const parts = [
+        preview.trash_bytes > 0 && `empty ${formatGb(preview.trash_bytes)} from the trash`,
+        preview.files.length > 0 &&
+          `delete ${preview.files.length} recording${preview.files.length !== 1 ? "s" : ""}, the oldest from ${preview.files[0].modified}`,
+      ].filter(Boolean);
+      const what = parts.join(" and ");
+      const confirmed = await ask(
+        `${what.charAt(0).toUpperCase()}${what.slice(1)} to free ${formatGb(preview.freed_bytes)}? ${formatGb(preview.remaining_bytes)} stays.`,
+        { title: "Clean up recordings", kind: "warning", okLabel: "Delete" },
+      );
+      if (!confirmed) return;
+      const report = await invoke<CleanupReport>("run_cleanup_now", { dryRun: false });
+      await message(`Freed ${formatGb(report.freed_bytes)}.`, { title: "Recordings cleaned up" });
+      setError(null);
+    } catch (e) {
+      setError(String(e));
+    } finally {
+      setBusy(false);
+    }
+  };
+
+  const enabled = policy.max_recording_age_days !== null || policy.max_total_size_gb !== null;
+
+  return (
+    <div className="flex flex-col gap-2">
+      <div className="flex items-center justify-between gap-4">
+        <div className="flex items-center gap-3 min-w-0">
+          <Eraser className={cn("w-4 h-4 shrink-0", enabled ? "text-success" : "text-text-muted/50")} />
+          <div className="min-w-0">
+            <p className="text-[13px] font-medium text-text-primary leading-snug">Clean up old recordings</p>
+            <p className="text-[11px] text-text-muted/60 leading-snug mt-0.5">
+              {enabled ? "Checked every hour; the trash, then the oldest go first" : "Keep everything"}
+            </p>
+          </div>
+        </div>
+        <div className="flex items-center gap-2 shrink-0">
+          <select
+            value={policy.max_recording_age_days ?? ""}
+            onChange={(e) => save({ ...policy, max_recording_age_days: e.target.value ? Number(e.target.value) : null })}
+            className={selectClass}
+            title="Delete recordings older than"
+          >
+            {ageOptions.map((opt) => (
+              <option key={opt.label} value={opt.value ?? ""}>{opt.label}</option>
+            ))}
+          </select>
+          <select
+            value={policy.max_total_size_gb ?? ""}
+            onChange={(e) => save({ ...policy, max_total_size_gb: e.target.value ? Number(e.target.value) : null })}
+            className={selectClass}
+            title="Keep recordings within"
+          >
+            {sizeOptions.map((opt) => (
+              <option key={opt.label} value={opt.value ?? ""}>{opt.label}</option>
+            ))}
+          </select>
+          {enabled && (
+            <button
+              onClick={handleCleanUp}
+              disabled={busy}
+              className="px-3 py-1.5 rounded-lg bg-bg-primary border border-border/50 text-[11px] font-medium text-text-secondary hover:text-text-primary hover:bg-bg-elevated transition-all cursor-pointer disabled:opacity-50"
+            >
+              {busy ? <Loader2 className="w-3 h-3 animate-spin" /> : "Now…"}
+            </button>
+          )}
+        </div>
+      </div>
+      {error && <p className="text-[11px] text-record/80">{error}</p>}
+    </div>
+  );
+}
@@ -0,0 +1,102 @@
+import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { AppWindow, Plus, Split, X } from "lucide-react";
+import { cn } from "../lib/utils";
+
+interface RoutedApp {
+  name: string;
+  own_channels: boolean;
+}
+
+/** Other apps (Linux only) routed into the recording along with Discord. */
+export function RoutedApps() {
+  const [apps, setApps] = useState<RoutedApp[]>([]);
+  const [playing, setPlaying] = useState<string[] | null>(null);
+  const [draft, setDraft] = useState("");
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    invoke<RoutedApp[]>("get_routed_apps").then(setApps).catch(() => {});
+    // Fails outside Linux, which hides the section
+    invoke<string[]>("list_playing_apps").then(setPlaying).catch(() => setPlaying(null));
+  }, []);
+
+  const save = async (next: RoutedApp[]) => {
+    setApps(next);
+    try {
+      setApps(await invoke<RoutedApp[]>("set_routed_apps", { apps: next }));
+      setError(null);
+    } catch (e) {
+      setError(String(e));
+    }
+  };
+
+  const handleAdd = () => {
+    const name = draft.trim();
+    if (!name) return;
+    setDraft("");
+    save([...apps, { name, own_channels: false }]);
+  };
+
+  if (playing === null) return null;
+
+  return (
+    <div className="py-4">
+      <p className="text-[13px] font-medium text-text-primary mb-1">Other apps</p>
+      <p className="text-[11px] text-text-muted/50 mb-2">
+        Record game or music audio along with Discord, mixed in or on its own channels
+      </p>
+      <div className="flex flex-col gap-1.5">
+        {apps.map((app, i) => (
+          <div key={app.name} className="flex items-center gap-2 px-3 py-2 rounded-xl bg-bg-primary/60 border border-border/30">
+            <AppWindow className="w-3.5 h-3.5 text-text-muted/40 shrink-0" />
+            <p className="flex-1 min-w-0 text-[12px] text-text-primary truncate">{app.name}</p>
+            <button
+              onClick={() => save(apps.map((a, j) => (j === i ? { ...a, own_channels: !a.own_channels } : a)))}
+              className={cn(
+                "flex items-center gap-1 px-2 py-1 rounded-lg text-[10px] font-medium transition-colors cursor-pointer",
+                app.own_channels ? "bg-accent/20 text-accent" : "text-text-muted/50 hover:text-text-primary"
+              )}
+              title={app.own_channels ? "On its own channel pair" : "Mixed in with Discord"}
+            >
+              <Split className="w-3 h-3" />
+              {app.own_channels ? "Own channels" : "Mixed"}
+            </button>
+            <button
+              onClick={() => save(apps.filter((_, j) => j !== i))}
+              className="p-1 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+              title="Stop routing"
+            >
+              <X className="w-3.5 h-3.5" />
+            </button>
+          </div>
+        ))}
+        <div className="flex items-center gap-2">
+          <input
+            value={draft}
+            onChange={(e) => setDraft(e.target.value)}
+            onKeyDown={(e) => e.key === "Enter" && handleAdd()}
+            list="routed-app-suggestions"
+            placeholder="App name, e.g. spotify"
+            className="flex-1 min-w-0 bg-bg-primary border border-border/50 rounded-lg px-3 py-1.5 text-[11px] text-text-primary outline-none placeholder:text-text-muted/40"
+          />
+          <datalist id="routed-app-suggestions">
+            {playing
+              .filter((name) => !apps.some((a) => a.name.toLowerCase() === name.toLowerCase()))
+              .map((name) => (
+                <option key={name} value={name} />
+              ))}
+          </datalist>
+          <button
+            onClick={handleAdd}
+            disabled={!draft.trim()}
+            className="px-3 py-1.5 rounded-lg bg-bg-primary border border-border/50 text-[11px] font-medium text-text-secondary hover:text-text-primary hover:bg-bg-elevated transition-all cursor-pointer shrink-0 flex items-center gap-1 disabled:opacity-40 disabled:cursor-default"
+          >
+            <Plus className="w-3 h-3" /> Add
+          </button>
+        </div>
+      </div>
+      {error && <p className="text-[11px] text-record/80 mt-2">{error}</p>}
+    </div>
+  );
+}
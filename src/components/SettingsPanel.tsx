@@ -1,11 +1,20 @@
 import { useState, useEffect } from "react";
-import { invoke } from "@tauri-apps/api/core";
-import { open } from "@tauri-apps/plugin-dialog";
-import { X, Check, Loader2, Sun, Moon, FolderOpen, RotateCcw, VolumeX, Timer, Bell, Zap } from "lucide-react";
+import { invoke, Channel } from "@tauri-apps/api/core";
+import { open, ask, message } from "@tauri-apps/plugin-dialog";
+import { relaunch } from "@tauri-apps/plugin-process";
+import { X, Check, Loader2, Sun, Moon, FolderOpen, RotateCcw, VolumeX, Timer, AudioLines, Bell, BellOff, Speaker, Mic, Combine, Rows3, MessageSquare, Scissors, Hand, Bug, EyeOff, Terminal, Unplug, Gauge, Bot, Activity, MessagesSquare, DatabaseBackup, ArchiveRestore, HardDrive, Trash2, FolderTree, Save } from "lucide-react";
 import { useUpdater } from "../hooks/useUpdater";
 import { FormatSelector, type AudioFormat } from "./FormatSelector";
 import { DiscordPanel } from "./DiscordPanel";
 import { RecordingHistory } from "./RecordingHistory";
+import { ArchivePanel } from "./ArchivePanel";
+import { OutputRoutes } from "./OutputRoutes";
+import { RoutedApps } from "./RoutedApps";
+import { WatchedChannels } from "./WatchedChannels";
+import { ObsSync } from "./ObsSync";
+import { AutoUpload } from "./AutoUpload";
+import { Retention } from "./Retention";
+import { FilenameTemplate } from "./FilenameTemplate";
 import { cn } from "../lib/utils";
 
 interface GuildInfo {
@@ -19,6 +28,89 @@ interface VoiceChannelInfo {
   guild_id: string;
 }
 
+interface ShortcutUpdate {
+  saved: boolean;
+  shortcuts: { record: string; stop: string };
+  conflicts: { action: string; accelerator: string; reason: string; suggestions: string[] }[];
+}
+
+interface MigrateProgress {
+  done: number;
+  total: number;
+  bytes_done: number;
+  bytes_total: number;
+  current: string;
+}
+
+interface OutputDirInfo {
+  path: string;
+  is_custom: boolean;
+  default_path: string;
+  default_fallback: DefaultDirBase | null;
+}
+
+type DefaultDirBase = "documents" | "home" | "app_data" | "working_dir";
+
+const fallbackLabels: Record<DefaultDirBase, string> = {
+  documents: "Documents folder",
+  home: "home folder",
+  app_data: "app data folder",
+  working_dir: "working directory",
+};
+
+interface AudioDeviceInfo {
+  id: string;
+  name: string;
+  is_input: boolean;
+  is_output: boolean;
+  is_default: boolean;
+  capturable: boolean;
+}
+
+/** The capture device picked for system audio, remembered by a stable ID. */
+interface DevicePreference {
+  id: string;
+  name: string;
+}
+
+type DeviceFallback = "fail" | "automatic" | "monitor";
+
+const fallbackOptions: Record<DeviceFallback, string> = {
+  automatic: "Pick automatically",
+  monitor: "Any system audio device",
+  fail: "Don't record",
+};
+
+type BotAudio = "record" | "ignore" | "separate_track";
+
+interface DataDirs {
+  config: string;
+  cache: string;
+  logs: string | null;
+  portable: boolean;
+  config_writable: boolean;
+}
+
+interface LatencyReport {
+  capture_to_disk: { count: number; mean_ms: number; p95_ms: number; max_ms: number } | null;
+  discord_receive: { ticks: number; jitter_ms: number; max_deviation_ms: number; late_ticks: number } | null;
+}
+
+const botAudioOptions: Record<BotAudio, string> = {
+  record: "Record",
+  ignore: "Leave out",
+  separate_track: "Own track",
+};
+
+interface VirtualDeviceSetup {
+  required: boolean;
+  virtual_device: string | null;
+  multi_output: string | null;
+  default_output: string | null;
+  ready: boolean;
+  next_step: string;
+}
+
 interface SettingsPanelProps {
   format: AudioFormat;
   onFormatChange: (format: AudioFormat) => void;
@@ -33,8 +125,6 @@ interface SettingsPanelProps {
   onDiscordDisconnect: () => void;
   onSelectGuild: (guildId: string) => void;
   onSelectChannel: (channelId: string) => void;
-  autoRecord: boolean;
-  onAutoRecordChange: (enabled: boolean) => void;
   theme: "dark" | "light";
   onThemeChange: (theme: "dark" | "light") => void;
 }
@@ -89,6 +179,8 @@ function SettingRow({
   );
 }
 
+type WavBitDepth = "float32" | "int24" | "int16";
+
 /* ── Section wrapper ─────────────────────────────────────────── */
 function Section({ title, children }: { title: string; children: React.ReactNode }) {
   return (
@@ -117,52 +209,173 @@ export function SettingsPanel({
   onDiscordDisconnect,
   onSelectGuild,
   onSelectChannel,
-  autoRecord,
-  onAutoRecordChange,
   theme,
   onThemeChange,
 }: SettingsPanelProps) {
   const updater = useUpdater();
   const [outputDir, setOutputDir] = useState("");
   const [isCustomDir, setIsCustomDir] = useState(false);
+  const [defaultFallback, setDefaultFallback] = useState<string | null>(null);
+  const [pendingDir, setPendingDir] = useState<string | null>(null);
+  const [migrateProgress, setMigrateProgress] = useState<MigrateProgress | null>(null);
+  const [migrateError, setMigrateError] = useState<string | null>(null);
   const [silenceTrim, setSilenceTrim] = useState(false);
+  const [recordMic, setRecordMic] = useState(false);
+  const [mixLocal, setMixLocal] = useState(false);
   const [maxDuration, setMaxDuration] = useState<number | null>(null);
+  const [segmentMinutes, setSegmentMinutes] = useState<number | null>(null);
+  const [wavBitDepth, setWavBitDepth] = useState<WavBitDepth>("float32");
+  const [flushInterval, setFlushInterval] = useState<number | null>(null);
+  const [captureDevices, setCaptureDevices] = useState<AudioDeviceInfo[]>([]);
+  const [captureDevice, setCaptureDevice] = useState<DevicePreference | null>(null);
+  const [deviceFallback, setDeviceFallback] = useState<DeviceFallback>("automatic");
   const [recordKey, setRecordKey] = useState("ctrl+r");
   const [stopKey, setStopKey] = useState("ctrl+s");
   const [capturingKey, setCapturingKey] = useState<"record" | "stop" | null>(null);
+  const [shortcutConflict, setShortcutConflict] = useState<string | null>(null);
   const [notifyOnRecord, setNotifyOnRecord] = useState(false);
+  const [notifyParticipants, setNotifyParticipants] = useState(false);
+  const [askConsent, setAskConsent] = useState(false);
+  const [recordChat, setRecordChat] = useState(false);
+  const [dndWhileRecording, setDndWhileRecording] = useState(false);
+  const [dndError, setDndError] = useState<string | null>(null);
+  const [alignTracks, setAlignTracks] = useState(false);
+  const [botAudio, setBotAudio] = useState<BotAudio>("record");
+  const [tickDumpMb, setTickDumpMb] = useState<number | null>(null);
+  const [redactLogs, setRedactLogs] = useState(false);
+  const [deleteEmpty, setDeleteEmpty] = useState(false);
+  const [sessionFolders, setSessionFolders] = useState(false);
+  const [latency, setLatency] = useState<LatencyReport | null>(null);
+  const [dataDirs, setDataDirs] = useState<DataDirs | null>(null);
+  const [postRecordCommand, setPostRecordCommand] = useState("");
+  const [normalizeLufs, setNormalizeLufs] = useState<number | null>(null);
+  const [minFreeSpaceMb, setMinFreeSpaceMb] = useState<number>(1024);
+  const [postRecordError, setPostRecordError] = useState<string | null>(null);
+  const [deviceSetup, setDeviceSetup] = useState<VirtualDeviceSetup | null>(null);
+  const [deviceSetupBusy, setDeviceSetupBusy] = useState(false);
+  const [deviceSetupError, setDeviceSetupError] = useState<string | null>(null);
 
   useEffect(() => {
     let cancelled = false;
-    invoke<{ path: string; is_custom: boolean }>("get_output_dir").then((info) => {
+    invoke<OutputDirInfo>("get_output_dir").then((info) => {
       if (!cancelled) {
         setOutputDir(info.path);
         setIsCustomDir(info.is_custom);
+        setDefaultFallback(info.default_fallback);
       }
     }).catch(() => {});
     invoke<boolean>("get_silence_trim").then((val) => {
       if (!cancelled) setSilenceTrim(val);
     }).catch(() => {});
+    invoke<boolean>("get_record_mic").then((val) => {
+      if (!cancelled) setRecordMic(val);
+    }).catch(() => {});
+    invoke<boolean>("get_mix_local_sources").then((val) => {
+      if (!cancelled) setMixLocal(val);
+    }).catch(() => {});
+    invoke<AudioDeviceInfo[]>("list_audio_devices").then((val) => {
+      if (!cancelled) setCaptureDevices(val.filter((d) => d.capturable));
+    }).catch(() => {});
+    invoke<DevicePreference | null>("get_capture_device").then((val) => {
+      if (!cancelled) setCaptureDevice(val);
+    }).catch(() => {});
+    invoke<DeviceFallback>("get_capture_device_fallback").then((val) => {
+      if (!cancelled) setDeviceFallback(val);
+    }).catch(() => {});
     invoke<number | null>("get_max_duration").then((val) => {
       if (!cancelled) setMaxDuration(val);
     }).catch(() => {});
+    invoke<number | null>("get_segment_minutes").then((val) => {
+      if (!cancelled) setSegmentMinutes(val);
+    }).catch(() => {});
+    invoke<WavBitDepth>("get_wav_bit_depth").then((val) => {
+      if (!cancelled) setWavBitDepth(val);
+    }).catch(() => {});
+    invoke<number | null>("get_flush_interval_secs").then((val) => {
+      if (!cancelled) setFlushInterval(val);
+    }).catch(() => {});
     invoke<{ record: string; stop: string }>("get_shortcuts").then((s) => {
       if (!cancelled) { setRecordKey(s.record); setStopKey(s.stop); }
     }).catch(() => {});
     invoke<boolean>("get_notify_on_record").then((val) => {
       if (!cancelled) setNotifyOnRecord(val);
     }).catch(() => {});
+    invoke<boolean>("get_notify_participants").then((val) => {
+      if (!cancelled) setNotifyParticipants(val);
+    }).catch(() => {});
+    invoke<boolean>("get_ask_consent").then((val) => {
+      if (!cancelled) setAskConsent(val);
+    }).catch(() => {});
+    invoke<boolean>("get_record_chat").then((val) => {
+      if (!cancelled) setRecordChat(val);
+    }).catch(() => {});
+    invoke<boolean>("get_dnd_while_recording").then((val) => {
+      if (!cancelled) setDndWhileRecording(val);
+    }).catch(() => {});
+    invoke<boolean>("get_align_speaker_tracks").then((val) => {
+      if (!cancelled) setAlignTracks(val);
+    }).catch(() => {});
+    invoke<BotAudio>("get_bot_audio").then((val) => {
+      if (!cancelled) setBotAudio(val);
+    }).catch(() => {});
+    invoke<number | null>("get_tick_dump_mb").then((val) => {
+      if (!cancelled) setTickDumpMb(val);
+    }).catch(() => {});
+    invoke<string | null>("get_post_record_command").then((val) => {
+      if (!cancelled) setPostRecordCommand(val ?? "");
+    }).catch(() => {});
+    invoke<number | null>("get_normalize_lufs").then((val) => {
+      if (!cancelled) setNormalizeLufs(val);
+    }).catch(() => {});
+    invoke<number>("get_min_free_space_mb").then((val) => {
+      if (!cancelled) setMinFreeSpaceMb(val);
+    }).catch(() => {});
+    invoke<boolean>("get_delete_empty_recordings").then((val) => {
+      if (!cancelled) setDeleteEmpty(val);
+    }).catch(() => {});
+    invoke<boolean>("get_session_folders").then((val) => {
+      if (!cancelled) setSessionFolders(val);
+    }).catch(() => {});
+    invoke<boolean>("get_redact_logs").then((val) => {
+      if (!cancelled) setRedactLogs(val);
+    }).catch(() => {});
+    invoke<LatencyReport>("get_latency_report").then((val) => {
+      if (!cancelled) setLatency(val);
+    }).catch(() => {});
+    invoke<DataDirs>("get_data_dirs").then((val) => {
+      if (!cancelled) setDataDirs(val);
+    }).catch(() => {});
+    invoke<VirtualDeviceSetup>("get_virtual_device_setup").then((val) => {
+      if (!cancelled) setDeviceSetup(val);
+    }).catch(() => {});
     return () => { cancelled = true; };
   }, []);
 
   const handleBrowseDir = async () => {
-    const selected = await open({ directory: true, title: "Choose recordings folder" });
-    if (selected) {
-      try {
-        const info = await invoke<{ path: string; is_custom: boolean }>("set_output_dir", { path: selected });
-        setOutputDir(info.path);
-        setIsCustomDir(info.is_custom);
-      } catch { /* ignore */ }
+    const selected = await invoke<string | null>("pick_folder", { title: "Choose recordings folder", current: outputDir || null });
+    if (selected && selected !== outputDir) {
+      setMigrateError(null);
+      setPendingDir(selected);
+    }
+  };
+
+  const handleChangeDir = async (mode: "move" | "copy" | null) => {
+    if (!pendingDir) return;
+    setMigrateError(null);
+    try {
+      if (mode) {
+        const onProgress = new Channel<MigrateProgress>();
+        onProgress.onmessage = setMigrateProgress;
+        await invoke("migrate_recordings", { path: pendingDir, mode, onProgress });
+      }
+      const info = await invoke<OutputDirInfo>("set_output_dir", { path: pendingDir });
+      setOutputDir(info.path);
+      setIsCustomDir(info.is_custom);
+      setPendingDir(null);
+    } catch (e) {
+      setMigrateError(String(e));
+    } finally {
+      setMigrateProgress(null);
     }
   };
 
@@ -173,14 +386,42 @@ export function SettingsPanel({
     } catch { /* ignore */ }
   };
 
+  const handleRecordMic = async (enabled: boolean) => {
+    try {
+      const val = await invoke<boolean>("set_record_mic", { enabled });
+      setRecordMic(val);
+    } catch { /* ignore */ }
+  };
+
+  const handleMixLocal = async (enabled: boolean) => {
+    try {
+      const val = await invoke<boolean>("set_mix_local_sources", { enabled });
+      setMixLocal(val);
+    } catch { /* ignore */ }
+  };
+
   const handleResetDir = async () => {
     try {
-      const info = await invoke<{ path: string; is_custom: boolean }>("set_output_dir", { path: null });
+      const info = await invoke<OutputDirInfo>("set_output_dir", { path: null });
       setOutputDir(info.path);
       setIsCustomDir(info.is_custom);
     } catch { /* ignore */ }
   };
 
+  const handleCaptureDevice = async (id: string | null) => {
+    try {
+      const val = await invoke<DevicePreference | null>("set_capture_device", { id });
+      setCaptureDevice(val);
+    } catch { /* ignore */ }
+  };
+
+  const handleDeviceFallback = async (fallback: DeviceFallback) => {
+    try {
+      const val = await invoke<DeviceFallback>("set_capture_device_fallback", { fallback });
+      setDeviceFallback(val);
+    } catch { /* ignore */ }
+  };
+
   const handleMaxDuration = async (seconds: number | null) => {
     try {
       const val = await invoke<number | null>("set_max_duration", { seconds });
@@ -188,6 +429,88 @@ export function SettingsPanel({
     } catch { /* ignore */ }
   };
 
+  const handleWavBitDepth = async (depth: WavBitDepth) => {
+    try {
+      setWavBitDepth(await invoke<WavBitDepth>("set_wav_bit_depth", { depth }));
+    } catch { /* ignore */ }
+  };
+
+  const handleFlushInterval = async (secs: number | null) => {
+    try {
+      setFlushInterval(await invoke<number | null>("set_flush_interval_secs", { secs }));
+    } catch { /* ignore */ }
+  };
+
+  const handleSegmentMinutes = async (minutes: number | null) => {
+    try {
+      const val = await invoke<number | null>("set_segment_minutes", { minutes });
+      setSegmentMinutes(val);
+    } catch { /* ignore */ }
+  };
+
+  const handleTickDumpMb = async (limitMb: number | null) => {
+    try {
+      setTickDumpMb(await invoke<number | null>("set_tick_dump_mb", { limitMb }));
+    } catch { /* ignore */ }
+  };
+
+  const savePostRecordCommand = async (command: string) => {
+    try {
+      const val = await invoke<string | null>("set_post_record_command", { command: command || null });
+      setPostRecordCommand(val ?? "");
+      setPostRecordError(null);
+    } catch (e) {
+      setPostRecordError(String(e));
+    }
+  };
+
+  const handleNormalizeLufs = async (targetLufs: number | null) => {
+    try {
+      const val = await invoke<number | null>("set_normalize_lufs", { targetLufs });
+      setNormalizeLufs(val);
+    } catch { /* ignore */ }
+  };
+
+  const handleMinFreeSpace = async (mb: number) => {
+    try {
+      setMinFreeSpaceMb(await invoke<number>("set_min_free_space_mb", { mb }));
+    } catch { /* ignore */ }
+  };
+
+  const handleSessionFolders = async (enabled: boolean) => {
+    try {
+      setSessionFolders(await invoke<boolean>("set_session_folders", { enabled }));
+    } catch { /* ignore */ }
+  };
+
+  const handleBrowsePostRecord = async () => {
+    const selected = await open({ directory: false, title: "Choose a program to run after recording" });
+    if (selected) savePostRecordCommand(selected);
+  };
+
+  const handleDeleteEmpty = async (enabled: boolean) => {
+    try {
+      setDeleteEmpty(await invoke<boolean>("set_delete_empty_recordings", { enabled }));
+      if (!enabled) return;
+      const found = await invoke<{ path: string }[]>("remove_empty_recordings", { dryRun: true });
+      if (found.length === 0) return;
+      const confirmed = await ask(`${found.length} empty recording${found.length !== 1 ? "s are" : " is"} in the library already. Delete ${found.length !== 1 ? "them" : "it"} now?`, {
+        title: "Delete empty recordings",
+        kind: "warning",
+        okLabel: "Delete",
+      });
+      if (confirmed) await invoke("remove_empty_recordings", { dryRun: false });
+    } catch (e) {
+      await message(String(e), { title: "Delete empty recordings", kind: "error" });
+    }
+  };
+
+  const handleRedactLogs = async (enabled: boolean) => {
+    try {
+      setRedactLogs(await invoke<boolean>("set_redact_logs", { enabled }));
+    } catch { /* ignore */ }
+  };
+
   const handleKeyCapture = (target: "record" | "stop") => {
     setCapturingKey(target);
     const handler = (e: KeyboardEvent) => {
@@ -203,8 +526,19 @@ export function SettingsPanel({
         const combo = parts.join("+");
         const newRecord = target === "record" ? combo : recordKey;
         const newStop = target === "stop" ? combo : stopKey;
-        invoke("set_shortcuts", { record: newRecord, stop: newStop }).catch(() => {});
-        if (target === "record") setRecordKey(combo); else setStopKey(combo);
+        invoke<ShortcutUpdate>("set_shortcuts", { record: newRecord, stop: newStop })
+          .then((res) => {
+            setRecordKey(res.shortcuts.record);
+            setStopKey(res.shortcuts.stop);
+            const conflict = res.conflicts[0];
+            setShortcutConflict(
+              conflict
+                ? `${conflict.accelerator}: ${conflict.reason}` +
+                  (conflict.suggestions.length ? ` — try ${conflict.suggestions.join(", ")}` : "")
+                : null
+            );
+          })
+          .catch(() => {});
         setCapturingKey(null);
       }
       window.removeEventListener("keydown", handler);
@@ -219,6 +553,109 @@ export function SettingsPanel({
     } catch { /* ignore */ }
   };
 
+  const handleDndWhileRecording = async (enabled: boolean) => {
+    setDndError(null);
+    try {
+      const val = await invoke<boolean>("set_dnd_while_recording", { enabled });
+      setDndWhileRecording(val);
+    } catch (e) {
+      setDndError(String(e));
+    }
+  };
+
+  const handleNotifyParticipants = async (enabled: boolean) => {
+    try {
+      const val = await invoke<boolean>("set_notify_participants", { enabled });
+      setNotifyParticipants(val);
+    } catch { /* ignore */ }
+  };
+
+  const handleAskConsent = async (enabled: boolean) => {
+    try {
+      const val = await invoke<boolean>("set_ask_consent", { enabled });
+      setAskConsent(val);
+    } catch { /* ignore */ }
+  };
+
+  const handleBackup = async () => {
+    const destination = await invoke<string | null>("pick_folder", { title: "Save backup to" });
+    if (!destination) return;
+    const includeTokens = await ask("Include the bot token, OBS password and upload credentials? Keep the backup file private if you do.", {
+      title: "Back up DiscRec",
+      okLabel: "Include",
+      cancelLabel: "Leave out",
+    });
+    try {
+      const path = await invoke<string>("backup_app_data", { destination, includeTokens });
+      await message(`Saved to ${path}`, { title: "Backup saved" });
+    } catch (e) {
+      await message(String(e), { title: "Backup failed", kind: "error" });
+    }
+  };
+
+  const handleRestore = async () => {
+    const selected = await open({ directory: false, title: "Choose a DiscRec backup", filters: [{ name: "DiscRec backup", extensions: ["json"] }] });
+    if (typeof selected !== "string") return;
+    const confirmed = await ask("This replaces your current settings and keys, then restarts DiscRec. Recordings aren't touched.", {
+      title: "Restore backup",
+      kind: "warning",
+      okLabel: "Restore",
+    });
+    if (!confirmed) return;
+    try {
+      await invoke("restore_app_data", { path: selected });
+      await relaunch();
+    } catch (e) {
+      await message(String(e), { title: "Restore failed", kind: "error" });
+    }
+  };
+
+  const handleRecordChat = async (enabled: boolean) => {
+    try {
+      const val = await invoke<boolean>("set_record_chat", { enabled });
+      setRecordChat(val);
+    } catch { /* ignore */ }
+  };
+
+  const handleAlignTracks = async (enabled: boolean) => {
+    try {
+      const val = await invoke<boolean>("set_align_speaker_tracks", { enabled });
+      setAlignTracks(val);
+    } catch { /* ignore */ }
+  };
+
+  const handleBotAudio = async (mode: BotAudio) => {
+    try {
+      const val = await invoke<BotAudio>("set_bot_audio", { mode });
+      setBotAudio(val);
+    } catch { /* ignore */ }
+  };
+
+  const handleDeviceSetup = async () => {
+    if (!deviceSetup) return;
+    setDeviceSetupBusy(true);
+    setDeviceSetupError(null);
+    try {
+      if (!deviceSetup.virtual_device) {
+        await invoke("open_blackhole_installer");
+      } else if (!deviceSetup.ready) {
+        setDeviceSetup(await invoke<VirtualDeviceSetup>("setup_multi_output_device"));
+      } else {
+        setDeviceSetup(await invoke<VirtualDeviceSetup>("get_virtual_device_setup"));
+      }
+    } catch (e) {
+      setDeviceSetupError(String(e));
+    } finally {
+      setDeviceSetupBusy(false);
+    }
+  };
+
+  const recheckDeviceSetup = async () => {
+    try {
+      setDeviceSetup(await invoke<VirtualDeviceSetup>("get_virtual_device_setup"));
+    } catch { /* ignore */ }
+  };
+
   const durationOptions: { label: string; value: number | null }[] = [
     { label: "No limit", value: null },
     { label: "5 min", value: 300 },
@@ -228,6 +665,45 @@ export function SettingsPanel({
     { label: "2 hours", value: 7200 },
   ];
 
+  const segmentOptions: { label: string; value: number | null }[] = [
+    { label: "Off", value: null },
+    { label: "15 min", value: 15 },
+    { label: "30 min", value: 30 },
+    { label: "1 hour", value: 60 },
+    { label: "2 hours", value: 120 },
+  ];
+
+  const flushOptions: { label: string; value: number | null }[] = [
+    { label: "At the end", value: null },
+    { label: "5 sec", value: 5 },
+    { label: "10 sec", value: 10 },
+    { label: "30 sec", value: 30 },
+    { label: "1 min", value: 60 },
+  ];
+
+  const loudnessOptions: { label: string; value: number | null }[] = [
+    { label: "Off", value: null },
+    { label: "-14 LUFS (streaming)", value: -14 },
+    { label: "-16 LUFS (podcast)", value: -16 },
+    { label: "-19 LUFS (mono podcast)", value: -19 },
+    { label: "-23 LUFS (broadcast)", value: -23 },
+  ];
+
+  const freeSpaceOptions: { label: string; value: number }[] = [
+    { label: "Off", value: 0 },
+    { label: "512 MB", value: 512 },
+    { label: "1 GB", value: 1024 },
+    { label: "5 GB", value: 5120 },
+    { label: "10 GB", value: 10240 },
+  ];
+
+  const tickDumpOptions: { label: string; value: number | null }[] = [
+    { label: "Off", value: null },
+    { label: "Up to 10 MB", value: 10 },
+    { label: "Up to 50 MB", value: 50 },
+    { label: "Up to 200 MB", value: 200 },
+  ];
+
   const updateLabel =
     updater.status === "up-to-date" ? "Up to date" :
     updater.status === "available" ? `v${updater.version} available` :
@@ -272,6 +748,7 @@ export function SettingsPanel({
               channels={channels}
               selectedGuild={selectedGuild}
               selectedChannel={selectedChannel}
+              format={format}
               onConnect={onDiscordConnect}
               onDisconnect={onDiscordDisconnect}
               onSelectGuild={onSelectGuild}
@@ -285,9 +762,72 @@ export function SettingsPanel({
             </SettingRow>
           )}
 
-          {discordConnected && selectedChannel && (
-            <SettingRow icon={Zap} iconColor={autoRecord ? "text-success" : undefined} label="Auto-record" description="Start when someone joins the channel">
-              <Toggle enabled={autoRecord} onChange={onAutoRecordChange} />
+          {discordConnected && (
+            <SettingRow icon={MessageSquare} iconColor={notifyParticipants ? "text-success" : undefined} label="Notify participants" description="DM everyone recorded when the session ends; replying STOP excludes them">
+              <Toggle enabled={notifyParticipants} onChange={handleNotifyParticipants} />
+            </SettingRow>
+          )}
+
+          {discordConnected && (
+            <SettingRow icon={Hand} iconColor={askConsent ? "text-success" : undefined} label="Ask for consent" description="Only record members who react ✅ to the bot's request">
+              <Toggle enabled={askConsent} onChange={handleAskConsent} />
+            </SettingRow>
+          )}
+
+          {/* Needs the message content intent, so it's set before connecting */}
+          <SettingRow icon={MessagesSquare} iconColor={recordChat ? "text-success" : undefined} label="Save text chat" description="Keep the voice channel's chat with the session. Needs the Message Content intent in the Developer Portal; applies the next time the bot connects">
+            <Toggle enabled={recordChat} onChange={handleRecordChat} />
+          </SettingRow>
+
+          {discordConnected && (
+            <SettingRow icon={Rows3} iconColor={alignTracks ? "text-success" : undefined} label="Align speaker tracks" description="Pad with silence so stems line up in a DAW">
+              <Toggle enabled={alignTracks} onChange={handleAlignTracks} />
+            </SettingRow>
+          )}
+
+          {discordConnected && (
+            <SettingRow icon={Bot} label="Other bots" description="Music bots and the like in the channel">
+              <select
+                value={botAudio}
+                onChange={(e) => handleBotAudio(e.target.value as BotAudio)}
+                className="text-[11px] bg-bg-primary border border-border/50 rounded-lg px-3 py-1.5 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors"
+              >
+                {(Object.keys(botAudioOptions) as BotAudio[]).map((m) => (
+                  <option key={m} value={m}>{botAudioOptions[m]}</option>
+                ))}
+              </select>
+            </SettingRow>
+          )}
+
+          {discordConnected && (
+            <div className="py-4">
+              <p className="text-[13px] font-medium text-text-primary mb-2">Auto-record</p>
+              <WatchedChannels guilds={guilds} selectedGuild={selectedGuild} selectedChannel={selectedChannel} format={format} />
+            </div>
+          )}
+
+          {discordConnected && (
+            <SettingRow icon={Bug} iconColor={tickDumpMb ? "text-success" : undefined} label="Debug tick dump" description="Log every received voice packet, for desync reports">
+              <div className="flex items-center gap-2">
+                {tickDumpMb && (
+                  <button
+                    onClick={() => invoke("open_tick_dump_folder").catch(() => {})}
+                    className="p-1.5 rounded-lg text-text-muted/50 hover:text-text-primary transition-colors cursor-pointer"
+                    title="Open dump folder"
+                  >
+                    <FolderOpen className="w-3.5 h-3.5" />
+                  </button>
+                )}
+                <select
+                  value={tickDumpMb ?? ""}
+                  onChange={(e) => handleTickDumpMb(e.target.value ? Number(e.target.value) : null)}
+                  className="text-[11px] bg-bg-primary border border-border/50 rounded-lg px-3 py-1.5 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors"
+                >
+                  {tickDumpOptions.map((opt) => (
+                    <option key={opt.label} value={opt.value ?? ""}>{opt.label}</option>
+                  ))}
+                </select>
+              </div>
             </SettingRow>
           )}
         </Section>
@@ -298,6 +838,20 @@ export function SettingsPanel({
           <div className="py-4">
             <p className="text-[13px] font-medium text-text-primary mb-3">Format</p>
             <FormatSelector value={format} onChange={onFormatChange} />
+            {format === "wav" && (
+              <div className="flex items-center justify-between mt-3">
+                <p className="text-[11px] text-text-muted/60">Bit depth</p>
+                <select
+                  value={wavBitDepth}
+                  onChange={(e) => handleWavBitDepth(e.target.value as WavBitDepth)}
+                  className="text-[11px] bg-bg-primary border border-border/50 rounded-lg px-3 py-1.5 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors"
+                >
+                  <option value="float32">32-bit float</option>
+                  <option value="int24">24-bit</option>
+                  <option value="int16">16-bit</option>
+                </select>
+              </div>
+            )}
           </div>
 
           {/* Output directory */}
@@ -324,6 +878,64 @@ export function SettingsPanel({
                 Browse…
               </button>
             </div>
+            {!isCustomDir && defaultFallback && (
+              <p className="text-[11px] text-record/80 mt-2">
+                No Music folder was found, so recordings go to your {fallbackLabels[defaultFallback]}. Choose a folder to
+                keep them somewhere else.
+              </p>
+            )}
+            {pendingDir && (
+              <div className="mt-3 p-3 rounded-xl bg-bg-primary border border-border/50">
+                <p className="text-[11px] text-text-secondary truncate" title={pendingDir}>
+                  Bring existing recordings to {pendingDir}?
+                </p>
+                {migrateProgress ? (
+                  <div className="mt-2">
+                    <div className="h-1 rounded-full bg-bg-elevated overflow-hidden">
+                      <div
+                        className="h-full bg-accent transition-all"
+                        style={{ width: `${migrateProgress.bytes_total ? (migrateProgress.bytes_done / migrateProgress.bytes_total) * 100 : 100}%` }}
+                      />
+                    </div>
+                    <p className="text-[11px] text-text-muted/60 mt-1 truncate">
+                      {migrateProgress.done} / {migrateProgress.total} {migrateProgress.current}
+                    </p>
+                  </div>
+                ) : (
+                  <div className="flex items-center gap-2 mt-2">
+                    {(["move", "copy"] as const).map((mode) => (
+                      <button
+                        key={mode}
+                        onClick={() => handleChangeDir(mode)}
+                        className="px-3 py-1.5 rounded-lg bg-bg-elevated text-[11px] font-medium text-text-secondary hover:text-text-primary transition-all cursor-pointer capitalize"
+                      >
+                        {mode}
+                      </button>
+                    ))}
+                    <button
+                      onClick={() => handleChangeDir(null)}
+                      className="px-3 py-1.5 rounded-lg text-[11px] font-medium text-text-muted hover:text-text-primary transition-all cursor-pointer"
+                    >
+                      Leave them
+                    </button>
+                    <button
+                      onClick={() => setPendingDir(null)}
+                      className="ml-auto p-1.5 rounded-lg hover:bg-bg-elevated text-text-muted/40 hover:text-text-primary transition-all cursor-pointer"
+                      title="Cancel"
+                    >
+                      <X className="w-3.5 h-3.5" />
+                    </button>
+                  </div>
+                )}
+                {migrateError && <p className="text-[11px] text-record/80 mt-2">{migrateError}</p>}
+              </div>
+            )}
+          </div>
+
+          {/* Output routes */}
+          <div className="py-4">
+            <p className="text-[13px] font-medium text-text-primary mb-2">Subfolders</p>
+            <OutputRoutes guilds={guilds} />
           </div>
 
           {/* Silence trim */}
@@ -331,6 +943,58 @@ export function SettingsPanel({
             <Toggle enabled={silenceTrim} onChange={handleSilenceTrim} />
           </SettingRow>
 
+          {/* Microphone track */}
+          <SettingRow icon={Mic} iconColor={recordMic ? "text-success" : undefined} label="Record microphone" description="Save your mic as a separate -mic track">
+            <Toggle enabled={recordMic} onChange={handleRecordMic} />
+          </SettingRow>
+
+          {recordMic && (
+            <SettingRow icon={Combine} iconColor={mixLocal ? "text-success" : undefined} label="Single file" description="Mix mic and Discord into one stereo file">
+              <Toggle enabled={mixLocal} onChange={handleMixLocal} />
+            </SettingRow>
+          )}
+
+          {/* Do not disturb */}
+          <SettingRow icon={BellOff} iconColor={dndWhileRecording ? "text-success" : undefined} label="Do not disturb" description="Silence notifications while recording">
+            <Toggle enabled={dndWhileRecording} onChange={handleDndWhileRecording} />
+          </SettingRow>
+          {dndError && <p className="text-[11px] text-record/80 -mt-2 pb-2">{dndError}</p>}
+
+          {/* System audio device */}
+          {captureDevices.length > 0 && (
+            <SettingRow icon={AudioLines} label="System audio device" description="Where system audio is captured from">
+              <select
+                value={captureDevice?.id ?? ""}
+                onChange={(e) => handleCaptureDevice(e.target.value || null)}
+                className="text-[11px] bg-bg-primary border border-border/50 rounded-lg px-3 py-1.5 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors max-w-[180px] truncate"
+              >
+                <option value="">Automatic</option>
+                {captureDevice && !captureDevices.some((d) => d.id === captureDevice.id) && (
+                  <option value={captureDevice.id}>{captureDevice.name} (not connected)</option>
+                )}
+                {captureDevices.map((d) => (
+                  <option key={d.id} value={d.id}>{d.name}</option>
+                ))}
+              </select>
+            </SettingRow>
+          )}
+
+          {captureDevice && (
+            <SettingRow icon={Unplug} label="If it's missing" description="When the chosen device isn't connected">
+              <select
+                value={deviceFallback}
+                onChange={(e) => handleDeviceFallback(e.target.value as DeviceFallback)}
+                className="text-[11px] bg-bg-primary border border-border/50 rounded-lg px-3 py-1.5 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors"
+              >
+                {(Object.keys(fallbackOptions) as DeviceFallback[]).map((f) => (
+                  <option key={f} value={f}>{fallbackOptions[f]}</option>
+                ))}
+              </select>
+            </SettingRow>
+          )}
+
+          <RoutedApps />
+
           {/* Max duration */}
           <SettingRow icon={Timer} label="Max duration" description="Auto-stop after limit">
             <select
@@ -343,8 +1007,213 @@ export function SettingsPanel({
               ))}
             </select>
           </SettingRow>
+
+          {/* Segments */}
+          <SettingRow icon={Scissors} label="Split into parts" description="Start a new numbered file every…">
+            <select
+              value={segmentMinutes ?? ""}
+              onChange={(e) => handleSegmentMinutes(e.target.value ? Number(e.target.value) : null)}
+              className="text-[11px] bg-bg-primary border border-border/50 rounded-lg px-3 py-1.5 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors"
+            >
+              {segmentOptions.map((opt) => (
+                <option key={opt.label} value={opt.value ?? ""}>{opt.label}</option>
+              ))}
+            </select>
+          </SettingRow>
+
+          {/* Flush interval */}
+          <SettingRow icon={Save} label="Save to disk" description="Lose less if the app or computer crashes, at the cost of more disk writes">
+            <select
+              value={flushInterval ?? ""}
+              onChange={(e) => handleFlushInterval(e.target.value ? Number(e.target.value) : null)}
+              className="text-[11px] bg-bg-primary border border-border/50 rounded-lg px-3 py-1.5 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors"
+            >
+              {flushOptions.map((opt) => (
+                <option key={opt.label} value={opt.value ?? ""}>{opt.label}</option>
+              ))}
+            </select>
+          </SettingRow>
+
+          {/* File name template */}
+          <FilenameTemplate />
+
+          {/* Session folders */}
+          <SettingRow icon={FolderTree} iconColor={sessionFolders ? "text-success" : undefined} label="Folder per session" description="Save each session in a date folder, then one named after the session">
+            <Toggle enabled={sessionFolders} onChange={handleSessionFolders} />
+          </SettingRow>
+
+          {/* Disk space guard */}
+          <SettingRow icon={HardDrive} iconColor={minFreeSpaceMb > 0 ? "text-success" : undefined} label="Keep disk space free" description="Empties the trash first; then won't start below this, and stops and saves at a quarter of it">
+            <select
+              value={minFreeSpaceMb}
+              onChange={(e) => handleMinFreeSpace(Number(e.target.value))}
+              className="text-[11px] bg-bg-primary border border-border/50 rounded-lg px-3 py-1.5 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors"
+            >
+              {freeSpaceOptions.map((opt) => (
+                <option key={opt.label} value={opt.value}>{opt.label}</option>
+              ))}
+            </select>
+          </SettingRow>
+
+          {/* Loudness normalization */}
+          <SettingRow icon={Gauge} iconColor={normalizeLufs !== null ? "text-success" : undefined} label="Normalize loudness" description="Bring every saved WAV or FLAC file to a set loudness">
+            <select
+              value={normalizeLufs ?? ""}
+              onChange={(e) => handleNormalizeLufs(e.target.value ? Number(e.target.value) : null)}
+              className="text-[11px] bg-bg-primary border border-border/50 rounded-lg px-3 py-1.5 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors"
+            >
+              {loudnessOptions.map((opt) => (
+                <option key={opt.label} value={opt.value ?? ""}>{opt.label}</option>
+              ))}
+            </select>
+          </SettingRow>
+
+          {/* Post-record command */}
+          <div className="py-3">
+            <div className="flex items-center gap-3 mb-2">
+              <Terminal className={cn("w-4 h-4 shrink-0", postRecordCommand ? "text-success" : "text-text-muted/50")} />
+              <div className="min-w-0">
+                <p className="text-[13px] font-medium text-text-primary leading-snug">After recording, run</p>
+                <p className="text-[11px] text-text-muted/60 leading-snug mt-0.5">Gets the saved files as arguments and session details as DISCREC_* variables</p>
+              </div>
+            </div>
+            <div className="flex items-center gap-2">
+              <input
+                value={postRecordCommand}
+                onChange={(e) => setPostRecordCommand(e.target.value)}
+                onBlur={() => savePostRecordCommand(postRecordCommand)}
+                onKeyDown={(e) => e.key === "Enter" && savePostRecordCommand(postRecordCommand)}
+                placeholder="Nothing"
+                className="flex-1 min-w-0 px-2.5 py-1.5 rounded-lg bg-bg-primary border border-border/50 text-[12px] font-mono text-text-primary placeholder:text-text-muted/30 focus:outline-none focus:border-accent/50 transition-colors"
+              />
+              <button
+                onClick={handleBrowsePostRecord}
+                className="p-1.5 rounded-lg text-text-muted/50 hover:text-text-primary transition-colors cursor-pointer"
+                title="Choose program"
+              >
+                <FolderOpen className="w-3.5 h-3.5" />
+              </button>
+            </div>
+            {postRecordError && <p className="text-[11px] text-record/80 mt-1.5">{postRecordError}</p>}
+          </div>
         </Section>
 
+        {/* ── Audio setup (macOS) ─────────────────────────── */}
+        {deviceSetup?.required && (
+          <Section title="Audio setup">
+            <SettingRow
+              icon={Speaker}
+              iconColor={deviceSetup.ready ? "text-success" : undefined}
+              label={deviceSetup.ready ? "System audio ready" : "System audio capture"}
+              description={deviceSetup.ready ? `Output: ${deviceSetup.default_output ?? "unknown"}` : deviceSetup.next_step}
+            >
+              <div className="flex items-center gap-2">
+                {!deviceSetup.virtual_device && (
+                  <button
+                    onClick={recheckDeviceSetup}
+                    className="px-3 py-1.5 rounded-lg text-[11px] font-medium text-text-muted/60 hover:text-text-primary transition-all cursor-pointer"
+                  >
+                    Check again
+                  </button>
+                )}
+                <button
+                  onClick={handleDeviceSetup}
+                  disabled={deviceSetupBusy}
+                  className="px-3 py-1.5 rounded-lg bg-bg-primary border border-border/50 text-[11px] font-medium text-text-secondary hover:text-text-primary hover:bg-bg-elevated transition-all cursor-pointer disabled:opacity-50"
+                >
+                  {!deviceSetup.virtual_device ? "Get BlackHole" : !deviceSetup.ready ? "Set up output" : "Refresh"}
+                </button>
+              </div>
+            </SettingRow>
+            {deviceSetupError && (
+              <p className="text-[11px] text-record/80 pb-3">{deviceSetupError}</p>
+            )}
+          </Section>
+        )}
+
+        {/* ── OBS ─────────────────────────────────────────── */}
+        <Section title="OBS">
+          <div className="py-4">
+            <ObsSync />
+          </div>
+        </Section>
+
+        {/* ── Upload ──────────────────────────────────────── */}
+        <Section title="Upload">
+          <div className="py-4">
+            <AutoUpload />
+          </div>
+        </Section>
+
+        {/* ── Privacy ─────────────────────────────────────── */}
+        <Section title="Privacy">
+          <SettingRow icon={EyeOff} iconColor={redactLogs ? "text-success" : undefined} label="Redact logs" description="Hash Discord IDs and hide your home folder in logs and debug dumps, so they can be shared in bug reports">
+            <Toggle enabled={redactLogs} onChange={handleRedactLogs} />
+          </SettingRow>
+        </Section>
+
+        {/* ── Backup ──────────────────────────────────────── */}
+        <Section title="Backup">
+          {dataDirs && (
+            <SettingRow
+              icon={HardDrive}
+              iconColor={!dataDirs.config_writable ? "text-record" : dataDirs.portable ? "text-success" : undefined}
+              label={dataDirs.portable ? "Portable mode" : "App data"}
+              description={
+                !dataDirs.config_writable
+                  ? `Can't write to ${dataDirs.config}, so settings won't be saved. Check it belongs to your user`
+                  : dataDirs.portable
+                    ? `Your data stays in ${dataDirs.config}`
+                    : "Put a portable.txt next to DiscRec to keep data beside it instead"
+              }
+            >
+              <button
+                onClick={() => invoke("open_data_folder").catch(() => {})}
+                className="p-1.5 rounded-lg text-text-muted/50 hover:text-text-primary transition-colors cursor-pointer"
+                title={dataDirs.config}
+              >
+                <FolderOpen className="w-3.5 h-3.5" />
+              </button>
+            </SettingRow>
+          )}
+          <SettingRow icon={DatabaseBackup} label="Back up" description="Settings, archive index, consent log and keys in one file, for moving to a new machine">
+            <button
+              onClick={handleBackup}
+              className="px-3 py-1.5 rounded-lg bg-bg-primary border border-border/50 text-[11px] font-medium text-text-secondary hover:text-text-primary hover:bg-bg-elevated transition-all cursor-pointer"
+            >
+              Back up…
+            </button>
+          </SettingRow>
+          <SettingRow icon={ArchiveRestore} label="Restore" description="Replace this machine's configuration with a backup">
+            <button
+              onClick={handleRestore}
+              className="px-3 py-1.5 rounded-lg bg-bg-primary border border-border/50 text-[11px] font-medium text-text-secondary hover:text-text-primary hover:bg-bg-elevated transition-all cursor-pointer"
+            >
+              Restore…
+            </button>
+          </SettingRow>
+        </Section>
+
+        {/* ── Diagnostics ─────────────────────────────────── */}
+        {latency && (latency.capture_to_disk || latency.discord_receive) && (
+          <Section title="Diagnostics">
+            <SettingRow icon={Activity} label="Latency" description="From the last recording; also written to the log when it stops">
+              <div className="text-right text-[11px] font-mono text-text-muted/70 leading-snug">
+                {latency.capture_to_disk && (
+                  <p title={`${latency.capture_to_disk.count} writes`}>
+                    disk {latency.capture_to_disk.mean_ms.toFixed(1)} ms avg · {latency.capture_to_disk.p95_ms.toFixed(0)} ms p95
+                  </p>
+                )}
+                {latency.discord_receive && (
+                  <p title={`${latency.discord_receive.ticks} ticks, max deviation ${latency.discord_receive.max_deviation_ms.toFixed(1)} ms`}>
+                    jitter {latency.discord_receive.jitter_ms.toFixed(1)} ms · {latency.discord_receive.late_ticks} late
+                  </p>
+                )}
+              </div>
+            </SettingRow>
+          </Section>
+        )}
+
         {/* ── Shortcuts ───────────────────────────────────── */}
         <Section title="Shortcuts">
           <div className="flex items-center justify-between py-3">
@@ -375,13 +1244,25 @@ export function SettingsPanel({
               {capturingKey === "stop" ? "Press key…" : stopKey}
             </button>
           </div>
+          {shortcutConflict && (
+            <p className="text-[11px] text-record/80 pb-3">{shortcutConflict}</p>
+          )}
         </Section>
 
         {/* ── History ─────────────────────────────────────── */}
         <Section title="History">
+          <SettingRow icon={Trash2} iconColor={deleteEmpty ? "text-success" : undefined} label="Delete empty recordings" description="Remove files without any audio that failed recordings leave, at startup and after each stop">
+            <Toggle enabled={deleteEmpty} onChange={handleDeleteEmpty} />
+          </SettingRow>
+          <div className="py-4">
+            <Retention />
+          </div>
           <div className="py-4">
             <RecordingHistory />
           </div>
+          <div className="py-4">
+            <ArchivePanel />
+          </div>
         </Section>
       </div>
 
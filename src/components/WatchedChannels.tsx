@@ -0,0 +1,138 @@
+import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Radar, Plus, X } from "lucide-react";
+import type { AudioFormat } from "./FormatSelector";
+
+interface WatchedChannel {
+  guild_id: string;
+  channel_id: string;
+  min_members: number;
+  format: AudioFormat;
+}
+
+interface GuildInfo {
+  id: string;
+  name: string;
+}
+
+interface VoiceChannelInfo {
+  id: string;
+  name: string;
+  guild_id: string;
+}
+
+const MEMBER_COUNTS = Array.from({ length: 10 }, (_, i) => i + 1);
+
+const selectClass =
+  "text-[11px] bg-bg-primary border border-border/50 rounded-lg px-2 py-1 text-text-secondary cursor-pointer outline-none hover:border-border transition-colors";
+
+/** Voice channels the bot records by itself while people are in them. */
+export function WatchedChannels({
+  guilds,
+  selectedGuild,
+  selectedChannel,
+  format,
+}: {
+  guilds: GuildInfo[];
+  selectedGuild: string | null;
+  selectedChannel: string | null;
+  format: AudioFormat;
+}) {
+  const [watched, setWatched] = useState<WatchedChannel[]>([]);
+  const [channelNames, setChannelNames] = useState<Record<string, string>>({});
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    invoke<WatchedChannel[]>("get_watched_channels").then(setWatched).catch(() => {});
+  }, []);
+
+  // Name the channels of every guild that has a watched channel
+  useEffect(() => {
+    const guildIds = [...new Set(watched.map((w) => w.guild_id))];
+    guildIds.forEach((guildId) => {
+      invoke<VoiceChannelInfo[]>("discord_list_channels", { guildId })
+        .then((channels) =>
+          setChannelNames((prev) => ({ ...prev, ...Object.fromEntries(channels.map((c) => [c.id, c.name])) }))
+        )
+        .catch(() => {});
+    });
+  }, [watched]);
+
+  const save = async (next: WatchedChannel[]) => {
+    setWatched(next);
+    try {
+      setWatched(await invoke<WatchedChannel[]>("set_watched_channels", { channels: next }));
+      setError(null);
+    } catch (e) {
+      setError(String(e));
+    }
+  };
+
+  const update = (index: number, change: Partial<WatchedChannel>) =>
+    save(watched.map((w, i) => (i === index ? { ...w, ...change } : w)));
+
+  const canAdd =
+    selectedGuild && selectedChannel && !watched.some((w) => w.channel_id === selectedChannel);
+
+  const handleAdd = () => {
+    if (!selectedGuild || !selectedChannel) return;
+    save([...watched, { guild_id: selectedGuild, channel_id: selectedChannel, min_members: 1, format }]);
+  };
+
+  const guildName = (id: string) => guilds.find((g) => g.id === id)?.name ?? id;
+
+  return (
+    <div className="flex flex-col gap-2">
+      <div className="flex items-center justify-between mb-1">
+        <p className="text-[11px] text-text-muted/50">
+          {watched.length ? "Recording starts when enough people join and stops when the channel empties" : "Select a channel, then watch it to record automatically"}
+        </p>
+        <button
+          onClick={handleAdd}
+          disabled={!canAdd}
+          className="px-3 py-1.5 rounded-lg bg-bg-primary border border-border/50 text-[11px] font-medium text-text-secondary hover:text-text-primary hover:bg-bg-elevated transition-all cursor-pointer shrink-0 flex items-center gap-1 disabled:opacity-40 disabled:cursor-default"
+        >
+          <Plus className="w-3 h-3" /> Watch channel
+        </button>
+      </div>
+
+      {watched.map((w, i) => (
+        <div key={w.channel_id} className="flex items-center gap-2 px-3 py-2.5 rounded-xl bg-bg-primary/60 border border-border/30">
+          <Radar className="w-3.5 h-3.5 text-text-muted/40 shrink-0" />
+          <p className="flex-1 min-w-0 text-[12px] text-text-primary truncate">
+            {guildName(w.guild_id)} · #{channelNames[w.channel_id] ?? w.channel_id}
+          </p>
+          <select
+            value={w.min_members}
+            onChange={(e) => update(i, { min_members: Number(e.target.value) })}
+            className={selectClass}
+            title="People needed to start"
+          >
+            {MEMBER_COUNTS.map((n) => (
+              <option key={n} value={n}>{n}+ {n === 1 ? "person" : "people"}</option>
+            ))}
+          </select>
+          <select
+            value={w.format}
+            onChange={(e) => update(i, { format: e.target.value as AudioFormat })}
+            className={selectClass}
+            title="Format"
+          >
+            <option value="wav">WAV</option>
+            <option value="flac">FLAC</option>
+            <option value="mp3">MP3</option>
+          </select>
+          <button
+            onClick={() => save(watched.filter((_, j) => j !== i))}
+            className="p-1 rounded-lg text-text-muted/40 hover:text-text-primary transition-colors cursor-pointer"
+            title="Stop watching"
+          >
+            <X className="w-3.5 h-3.5" />
+          </button>
+        </div>
+      ))}
+
+      {error && <p className="text-[11px] text-record/80">{error}</p>}
+    </div>
+  );
+}
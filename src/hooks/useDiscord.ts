@@ -1,6 +1,7 @@
 import { useState, useEffect, useRef, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import type { AudioFormat } from "../components/FormatSelector";
+import { listenRecordingEvents } from "../lib/recordingEvents";
 
 interface GuildInfo {
   id: string;
@@ -13,12 +14,6 @@ interface VoiceChannelInfo {
   guild_id: string;
 }
 
-interface DiscordStatus {
-  connected: boolean;
-  recording: boolean;
-  peak_level: number;
-}
-
 export type DiscordState = "disconnected" | "connected" | "recording" | "done";
 
 export function useDiscord() {
@@ -34,17 +29,12 @@ export function useDiscord() {
   const [connecting, setConnecting] = useState(false);
 
   const timerRef = useRef<ReturnType<typeof setInterval> | null>(null);
-  const pollRef = useRef<ReturnType<typeof setInterval> | null>(null);
 
   const clearTimers = useCallback(() => {
     if (timerRef.current) {
       clearInterval(timerRef.current);
       timerRef.current = null;
     }
-    if (pollRef.current) {
-      clearInterval(pollRef.current);
-      pollRef.current = null;
-    }
   }, []);
 
   const connect = useCallback(async (token: string) => {
@@ -92,6 +82,15 @@ export function useDiscord() {
     }
   }, []);
 
+  const beginTimer = useCallback(() => {
+    if (timerRef.current) return;
+    setState("recording");
+    setDuration(0);
+    timerRef.current = setInterval(() => {
+      setDuration((d) => d + 1);
+    }, 1000);
+  }, []);
+
   const startRecording = useCallback(
     async (format: AudioFormat) => {
       if (!selectedGuild || !selectedChannel) {
@@ -105,26 +104,12 @@ export function useDiscord() {
           channelId: selectedChannel,
           format,
         });
-        setState("recording");
-        setDuration(0);
-
-        timerRef.current = setInterval(() => {
-          setDuration((d) => d + 1);
-        }, 1000);
-
-        pollRef.current = setInterval(async () => {
-          try {
-            const status = await invoke<DiscordStatus>("discord_get_status");
-            setPeakLevel(status.peak_level);
-          } catch {
-            // ignore
-          }
-        }, 50);
+        beginTimer();
       } catch (e) {
         setError(String(e));
       }
     },
-    [selectedGuild, selectedChannel]
+    [selectedGuild, selectedChannel, beginTimer]
   );
 
   const stopRecording = useCallback(async () => {
@@ -148,6 +133,37 @@ export function useDiscord() {
     setError(null);
   }, [clearTimers]);
 
+  const abortRecording = useCallback(async () => {
+    try {
+      await invoke<boolean>("abort_recording");
+      reset();
+    } catch (e) {
+      setError(String(e));
+    }
+  }, [reset]);
+
+  // Levels, write failures and reconnects pushed from the voice receiver;
+  // watched channels start and stop sessions without the UI asking
+  useEffect(() => {
+    return listenRecordingEvents("bot", {
+      onStarted: beginTimer,
+      onStopped: ({ reason, paths }) => {
+        if (reason === "aborted") {
+          reset();
+          return;
+        }
+        clearTimers();
+        setSavedPaths(paths);
+        setPeakLevel(0);
+        setState("done");
+      },
+      onLevel: setPeakLevel,
+      onError: setError,
+      onInterrupted: setError,
+      onResumed: () => setError(null),
+    });
+  }, [beginTimer, clearTimers, reset]);
+
   // Load saved token on mount
   useEffect(() => {
     invoke<string | null>("load_bot_token").then((token) => {
@@ -175,6 +191,7 @@ export function useDiscord() {
     setSelectedChannel,
     startRecording,
     stopRecording,
+    abortRecording,
     reset,
   };
 }
@@ -0,0 +1,25 @@
+import { useEffect } from "react";
+import { listen } from "@tauri-apps/api/event";
+
+interface ObsSyncOptions {
+  onRecord: () => void;
+  onStop: () => void;
+  isRecording: boolean;
+  canRecord: boolean;
+}
+
+/** Start and stop recording when OBS does, if "Record when OBS does" is on. */
+export function useObsSync({ onRecord, onStop, isRecording, canRecord }: ObsSyncOptions) {
+  useEffect(() => {
+    const unlisten = listen<{ active: boolean }>("obs://output", ({ payload }) => {
+      if (payload.active && !isRecording && canRecord) {
+        onRecord();
+      } else if (!payload.active && isRecording) {
+        onStop();
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [onRecord, onStop, isRecording, canRecord]);
+}
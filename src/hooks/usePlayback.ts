@@ -0,0 +1,53 @@
+import { useState, useEffect, useCallback } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+export interface PlaybackStatus {
+  path: string | null;
+  playing: boolean;
+  position_secs: number;
+  duration_secs: number | null;
+}
+
+const idle: PlaybackStatus = { path: null, playing: false, position_secs: 0, duration_secs: null };
+
+/** In-app playback of a recording, kept in step with the backend's position events. */
+export function usePlayback() {
+  const [status, setStatus] = useState<PlaybackStatus>(idle);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    invoke<PlaybackStatus>("get_playback_position").then(setStatus).catch(() => {});
+    const unlistenPosition = listen<PlaybackStatus>("playback://position", ({ payload }) => setStatus(payload));
+    const unlistenEnded = listen<string>("playback://ended", () => setStatus(idle));
+    return () => {
+      unlistenPosition.then((fn) => fn());
+      unlistenEnded.then((fn) => fn());
+      invoke("stop_playback").catch(() => {});
+    };
+  }, []);
+
+  const run = useCallback(async (command: string, args?: Record<string, unknown>) => {
+    try {
+      setStatus(await invoke<PlaybackStatus>(command, args));
+      setError(null);
+    } catch (e) {
+      setError(String(e));
+    }
+  }, []);
+
+  const toggle = useCallback(
+    (path: string) =>
+      status.path === path && status.playing ? run("pause_playback") : run("play_recording", { path }),
+    [status, run]
+  );
+
+  const seek = useCallback((positionSecs: number) => run("seek_playback", { positionSecs }), [run]);
+
+  const stop = useCallback(async () => {
+    await invoke("stop_playback").catch(() => {});
+    setStatus(idle);
+  }, []);
+
+  return { status, error, toggle, seek, stop };
+}
@@ -1,14 +1,10 @@
 import { useState, useEffect, useRef, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import type { AudioFormat } from "../components/FormatSelector";
+import { listenRecordingEvents } from "../lib/recordingEvents";
 
 export type RecordingState = "idle" | "recording" | "done";
 
-interface RecordingStatus {
-  is_recording: boolean;
-  peak_level: number;
-}
-
 export function useRecorder() {
   const [state, setState] = useState<RecordingState>("idle");
   const [filePath, setFilePath] = useState<string | null>(null);
@@ -17,18 +13,31 @@ export function useRecorder() {
   const [error, setError] = useState<string | null>(null);
   const [format, setFormat] = useState<AudioFormat>("wav");
 
+  // The chosen format is also the default for tray and shortcut starts
+  useEffect(() => {
+    invoke<AudioFormat>("get_default_format").then(setFormat).catch(() => {});
+  }, []);
+
+  const changeFormat = useCallback((next: AudioFormat) => {
+    setFormat(next);
+    invoke<AudioFormat>("set_default_format", { format: next }).catch(() => {});
+  }, []);
+
   const timerRef = useRef<ReturnType<typeof setInterval> | null>(null);
-  const pollRef = useRef<ReturnType<typeof setInterval> | null>(null);
 
   const clearTimers = useCallback(() => {
     if (timerRef.current) {
       clearInterval(timerRef.current);
       timerRef.current = null;
     }
-    if (pollRef.current) {
-      clearInterval(pollRef.current);
-      pollRef.current = null;
-    }
+  }, []);
+
+  const startTimer = useCallback(() => {
+    if (timerRef.current) return;
+    setDuration(0);
+    timerRef.current = setInterval(() => {
+      setDuration((d) => d + 1);
+    }, 1000);
   }, []);
 
   const startRecording = useCallback(async () => {
@@ -37,24 +46,11 @@ export function useRecorder() {
       const path = await invoke<string>("start_recording", { format });
       setFilePath(path);
       setState("recording");
-      setDuration(0);
-
-      timerRef.current = setInterval(() => {
-        setDuration((d) => d + 1);
-      }, 1000);
-
-      pollRef.current = setInterval(async () => {
-        try {
-          const status = await invoke<RecordingStatus>("get_status");
-          setPeakLevel(status.peak_level);
-        } catch {
-          // ignore polling errors
-        }
-      }, 50);
+      startTimer();
     } catch (e) {
       setError(String(e));
     }
-  }, [format]);
+  }, [format, startTimer]);
 
   const stopRecording = useCallback(async () => {
     try {
@@ -77,6 +73,45 @@ export function useRecorder() {
     setError(null);
   }, [clearTimers]);
 
+  const abortRecording = useCallback(async () => {
+    try {
+      await invoke<boolean>("abort_recording");
+      reset();
+    } catch (e) {
+      setError(String(e));
+    }
+  }, [reset]);
+
+  // Status pushed from the capture threads; also covers tray and shortcut starts
+  useEffect(() => {
+    return listenRecordingEvents("local", {
+      onLevel: setPeakLevel,
+      onStarted: (path, device) => {
+        if (path) setFilePath(path);
+        if (device?.fallback) {
+          setError(`${device.requested} isn't connected, recording from ${device.used ?? "an automatically picked device"}`);
+        }
+        setState("recording");
+        startTimer();
+      },
+      onStopped: ({ reason, paths }) => {
+        if (reason === "max_duration") {
+          stopRecording();
+          return;
+        }
+        if (reason === "aborted") {
+          reset();
+          return;
+        }
+        clearTimers();
+        if (paths.length > 0) setFilePath(paths[0]);
+        setPeakLevel(0);
+        setState("done");
+      },
+      onError: setError,
+    });
+  }, [startTimer, stopRecording, reset, clearTimers]);
+
   useEffect(() => {
     return () => clearTimers();
   }, [clearTimers]);
@@ -88,9 +123,10 @@ export function useRecorder() {
     peakLevel,
     error,
     format,
-    setFormat,
+    setFormat: changeFormat,
     startRecording,
     stopRecording,
+    abortRecording,
     reset,
   };
 }
@@ -0,0 +1,52 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+export interface JobStatus {
+  id: number;
+  kind: string;
+  state: "running" | "completed" | "failed" | "cancelled";
+  done: number;
+  total: number | null;
+  current: string;
+  error: string | null;
+  result: unknown;
+}
+
+/**
+ * Wait for a job started by a long-running command, reporting progress on the
+ * way. Resolves with the job's result; rejects when it fails or is cancelled.
+ */
+export async function runJob<T>(started: number | Promise<number>, onProgress?: (status: JobStatus) => void): Promise<T> {
+  const id = await started;
+  let unlisten: (() => void)[] = [];
+  try {
+    return await new Promise<T>((resolve, reject) => {
+      const settle = (status: JobStatus) => {
+        if (status.state === "completed") resolve(status.result as T);
+        else if (status.state === "failed") reject(status.error ?? "Job failed");
+        else if (status.state === "cancelled") reject("Cancelled");
+      };
+      Promise.all([
+        listen<JobStatus>("job://progress", ({ payload }) => {
+          if (payload.id === id) onProgress?.(payload);
+        }),
+        listen<JobStatus>("job://finished", ({ payload }) => {
+          if (payload.id === id) settle(payload);
+        }),
+      ])
+        .then((listeners) => {
+          unlisten = listeners;
+          // It may have finished before we were listening
+          return invoke<JobStatus>("get_job_status", { id });
+        })
+        .then(settle)
+        .catch(reject);
+    });
+  } finally {
+    unlisten.forEach((stop) => stop());
+  }
+}
+
+export function cancelJob(id: number): Promise<void> {
+  return invoke("cancel_job", { id });
+}
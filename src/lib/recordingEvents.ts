@@ -0,0 +1,80 @@
+import { listen } from "@tauri-apps/api/event";
+
+export type Recorder = "local" | "bot";
+
+interface LevelEvent {
+  source: Recorder;
+  peak: number;
+}
+
+/** The system capture device a recording used, when one was picked in settings. */
+export interface DeviceChoice {
+  requested: string;
+  /** `null` when DiscRec picked a device itself. */
+  used: string | null;
+  /** Set when the picked device wasn't connected. */
+  fallback: "fail" | "automatic" | "monitor" | null;
+}
+
+interface StartedEvent {
+  source: Recorder;
+  path: string | null;
+  device?: DeviceChoice;
+}
+
+export interface StoppedEvent {
+  source: Recorder;
+  /**
+   * `max_duration` stops still need `stop_recording` to save the files;
+   * `aborted` ones saved nothing.
+   */
+  reason: "requested" | "max_duration" | "aborted";
+  paths: string[];
+}
+
+interface ErrorEvent {
+  source: Recorder;
+  message: string;
+}
+
+interface ResumedEvent {
+  source: Recorder;
+  attempts: number;
+}
+
+export interface RecordingEventHandlers {
+  onLevel?: (peak: number) => void;
+  onStarted?: (path: string | null, device?: DeviceChoice) => void;
+  onStopped?: (event: StoppedEvent) => void;
+  onError?: (message: string) => void;
+  /** The bot lost its voice connection and is rejoining. */
+  onInterrupted?: (message: string) => void;
+  onResumed?: (attempts: number) => void;
+}
+
+/** Follow one recorder's status events. Returns a function that unsubscribes. */
+export function listenRecordingEvents(source: Recorder, handlers: RecordingEventHandlers): () => void {
+  const unlisteners = [
+    listen<LevelEvent>("recording://level", ({ payload }) => {
+      if (payload.source === source) handlers.onLevel?.(payload.peak);
+    }),
+    listen<StartedEvent>("recording://started", ({ payload }) => {
+      if (payload.source === source) handlers.onStarted?.(payload.path, payload.device);
+    }),
+    listen<StoppedEvent>("recording://stopped", ({ payload }) => {
+      if (payload.source === source) handlers.onStopped?.(payload);
+    }),
+    listen<ErrorEvent>("recording://error", ({ payload }) => {
+      if (payload.source === source) handlers.onError?.(payload.message);
+    }),
+    listen<ErrorEvent>("recording://interrupted", ({ payload }) => {
+      if (payload.source === source) handlers.onInterrupted?.(payload.message);
+    }),
+    listen<ResumedEvent>("recording://resumed", ({ payload }) => {
+      if (payload.source === source) handlers.onResumed?.(payload.attempts);
+    }),
+  ];
+  return () => {
+    unlisteners.forEach((pending) => pending.then((unlisten) => unlisten()));
+  };
+}